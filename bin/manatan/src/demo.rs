@@ -0,0 +1,286 @@
+//! First-run sample content. Everything is provisioned through the sub-servers' own HTTP
+//! APIs on loopback, so the demo goes through the same import paths as user content and
+//! is removed the same way a user would remove it.
+
+use std::{collections::HashMap, path::Path};
+
+use anyhow::anyhow;
+use axum::{Json, Router, extract::State, http::StatusCode, routing::post};
+use manatan_ocr_server::{
+    auth::{API_KEY_HEADER, ApiKey},
+    language::OcrLanguage,
+    logic::{self, CacheKeyConfig, OcrResult},
+    preprocess::Preprocess,
+    state::{CacheEntry, EntrySource},
+};
+use reqwest::{Client, RequestBuilder, multipart};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::info;
+
+const DEMO_BOOK_ID: &str = "manatan-demo-kumo-no-ito";
+const DEMO_DICTIONARY_TITLE: &str = "Manatan Demo Dictionary";
+const DEMO_OCR_CONTEXT: &str = "Manatan Demo";
+/// Manga id 0 never exists in Suwayomi, so the synthetic chapter cannot collide with a
+/// real one.
+const DEMO_CHAPTER_BASE_URL: &str = "http://127.0.0.1:4568/api/v1/manga/0/chapter/1";
+
+static DEMO_EPUB: &[u8] = include_bytes!("../resources/demo/sample-novel.epub");
+static DEMO_NOVEL_JSON: &str = include_str!("../resources/demo/sample-novel.json");
+static DEMO_DICTIONARY: &[u8] = include_bytes!("../resources/demo/sample-dictionary.zip");
+static DEMO_OCR_JSON: &str = include_str!("../resources/demo/sample-ocr.json");
+
+#[derive(Clone)]
+struct DemoState {
+    client: Client,
+    api_base: String,
+    /// The OCR server's API key, which its state-changing routes need when one is set.
+    ocr_key: Option<ApiKey>,
+}
+
+impl DemoState {
+    fn ocr_post(&self, path: &str) -> RequestBuilder {
+        let request = self.client.post(format!("{}/ocr{path}", self.api_base));
+        match &self.ocr_key {
+            Some(key) => request.header(API_KEY_HEADER, key.as_str()),
+            None => request,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DemoNovel {
+    metadata: Value,
+    content: Value,
+}
+
+#[derive(Deserialize)]
+struct DemoOcr {
+    pages: Vec<Vec<OcrResult>>,
+}
+
+pub fn router(port: u16, data_dir: &Path) -> Router {
+    let state = DemoState {
+        client: Client::new(),
+        api_base: format!("http://127.0.0.1:{port}/api"),
+        ocr_key: ApiKey::load(data_dir),
+    };
+    Router::new()
+        .route("/install", post(install_handler))
+        .route("/remove", post(remove_handler))
+        .with_state(state)
+}
+
+async fn install_handler(
+    State(state): State<DemoState>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    info!("🎁 Installing demo content...");
+    let novel = install_novel(&state).await.map_err(internal_error)?;
+    let dictionary = install_dictionary(&state).await.map_err(internal_error)?;
+    let ocr_pages = install_ocr_chapter(&state).await.map_err(internal_error)?;
+    Ok(Json(json!({
+        "status": "installed",
+        "novel": novel,
+        "dictionary": dictionary,
+        "ocr_pages": ocr_pages,
+    })))
+}
+
+async fn remove_handler(
+    State(state): State<DemoState>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    info!("🧹 Removing demo content...");
+    let api = &state.api_base;
+
+    state
+        .client
+        .delete(format!("{api}/novel/metadata/{DEMO_BOOK_ID}"))
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(internal_error)?;
+
+    let dictionary_removed = match demo_dictionary_id(&state).await.map_err(internal_error)? {
+        Some(id) => {
+            let response: Value = state
+                .client
+                .post(format!("{api}/yomitan/manage"))
+                .json(&json!({ "action": "Delete", "payload": { "id": id } }))
+                .send()
+                .await
+                .map_err(internal_error)?
+                .json()
+                .await
+                .map_err(internal_error)?;
+            ensure_ok(&response).map_err(internal_error)?;
+            true
+        }
+        None => false,
+    };
+
+    state
+        .ocr_post("/delete-chapter")
+        .json(&json!({
+            "base_url": DEMO_CHAPTER_BASE_URL,
+            "delete_data": true,
+            "language": OcrLanguage::Japanese,
+        }))
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(internal_error)?;
+
+    Ok(Json(json!({
+        "status": "removed",
+        "dictionary_removed": dictionary_removed,
+    })))
+}
+
+/// Uploads the EPUB and stores its pre-parsed metadata and content, unless the book is
+/// already in the library.
+async fn install_novel(state: &DemoState) -> anyhow::Result<&'static str> {
+    let api = &state.api_base;
+    let existing = state
+        .client
+        .get(format!("{api}/novel/metadata/{DEMO_BOOK_ID}"))
+        .send()
+        .await?;
+    if existing.status().is_success() {
+        return Ok("present");
+    }
+
+    let novel: DemoNovel = serde_json::from_str(DEMO_NOVEL_JSON)?;
+    let mut metadata = novel.metadata;
+    metadata["addedAt"] = json!(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or_default()
+    );
+
+    let form = multipart::Form::new().part(
+        "file",
+        multipart::Part::bytes(DEMO_EPUB).file_name(format!("{DEMO_BOOK_ID}.epub")),
+    );
+    state
+        .client
+        .post(format!("{api}/novel/upload/{DEMO_BOOK_ID}"))
+        .multipart(form)
+        .send()
+        .await?
+        .error_for_status()?;
+    state
+        .client
+        .post(format!("{api}/novel/content/{DEMO_BOOK_ID}"))
+        .json(&novel.content)
+        .send()
+        .await?
+        .error_for_status()?;
+    state
+        .client
+        .post(format!("{api}/novel/metadata/{DEMO_BOOK_ID}"))
+        .json(&json!({ "metadata": metadata }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok("installed")
+}
+
+async fn install_dictionary(state: &DemoState) -> anyhow::Result<&'static str> {
+    if demo_dictionary_id(state).await?.is_some() {
+        return Ok("present");
+    }
+
+    let form = multipart::Form::new().part(
+        "file",
+        multipart::Part::bytes(DEMO_DICTIONARY).file_name("sample-dictionary.zip"),
+    );
+    let response: Value = state
+        .client
+        .post(format!("{}/yomitan/import", state.api_base))
+        .multipart(form)
+        .send()
+        .await?
+        .json()
+        .await?;
+    ensure_ok(&response)?;
+    Ok("installed")
+}
+
+/// Seeds OCR cache entries for a synthetic chapter and registers them as its pages, so
+/// the chapter reports as preprocessed. Cache imports never overwrite existing rows.
+async fn install_ocr_chapter(state: &DemoState) -> anyhow::Result<usize> {
+    let demo: DemoOcr = serde_json::from_str(DEMO_OCR_JSON)?;
+    let language = OcrLanguage::Japanese;
+
+    let mut pages = Vec::with_capacity(demo.pages.len());
+    let mut entries = HashMap::new();
+    for (index, data) in demo.pages.into_iter().enumerate() {
+        let page_url = format!("{DEMO_CHAPTER_BASE_URL}/page/{index}");
+        entries.insert(
+            // Demo page URLs carry no query, so every normalization keys them alike.
+            logic::get_cache_key(&page_url, Some(language), &CacheKeyConfig::default()),
+            CacheEntry {
+                context: DEMO_OCR_CONTEXT.to_string(),
+                data,
+                backend: Default::default(),
+                orientation: None,
+                orientation_hint: Default::default(),
+                edited_at: None,
+                source: EntrySource::Ocr,
+                preprocess: Preprocess::None,
+            },
+        );
+        pages.push(page_url);
+    }
+
+    state
+        .ocr_post("/import-cache")
+        .json(&entries)
+        .send()
+        .await?
+        .error_for_status()?;
+    state
+        .ocr_post("/is-chapter-preprocessed")
+        .json(&json!({
+            "base_url": DEMO_CHAPTER_BASE_URL,
+            "context": DEMO_OCR_CONTEXT,
+            "pages": pages,
+            "language": language,
+        }))
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(pages.len())
+}
+
+async fn demo_dictionary_id(state: &DemoState) -> anyhow::Result<Option<i64>> {
+    let response: Value = state
+        .client
+        .get(format!("{}/yomitan/dictionaries", state.api_base))
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(response["dictionaries"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|dict| dict["name"] == DEMO_DICTIONARY_TITLE)
+        .and_then(|dict| dict["id"].as_i64()))
+}
+
+fn ensure_ok(response: &Value) -> anyhow::Result<()> {
+    if response["status"] == "ok" {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "{}",
+        response["message"].as_str().unwrap_or("Unknown error")
+    ))
+}
+
+fn internal_error(err: impl std::fmt::Display) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+}
@@ -0,0 +1,13 @@
+//! Lists the background work of every sub-server: each worker pool's limits and load,
+//! and the jobs queued or running in it.
+
+use axum::{Json, Router, routing::get};
+use manatan_jobs::Snapshot;
+
+pub fn router() -> Router {
+    Router::new().route("/", get(jobs_handler))
+}
+
+async fn jobs_handler() -> Json<Snapshot> {
+    Json(manatan_jobs::snapshot())
+}
@@ -1,4 +1,8 @@
+mod demo;
 mod io;
+mod jobs;
+mod notifications;
+mod search;
 
 use std::{
     env,
@@ -20,7 +24,7 @@ use axum::{
     Router,
     http::{StatusCode, Uri},
     response::IntoResponse,
-    routing::any,
+    routing::{any, get},
 };
 use clap::Parser;
 use directories::{BaseDirs, ProjectDirs};
@@ -28,6 +32,7 @@ use eframe::{
     egui::{self},
     icon_data,
 };
+use manatan_ocr_server::health::HealthStatus;
 use manatan_server_public::{
     app::build_router_without_cors, build_state, config::Config as ManatanServerConfig,
 };
@@ -183,6 +188,14 @@ struct Cli {
     /// Local novel directory (absolute or relative to data dir)
     #[arg(long, env = "MANATAN_LOCAL_LN_PATH")]
     local_novel_path: Option<PathBuf>,
+
+    /// Free disk space in MiB that uploads, imports and archives must leave untouched
+    #[arg(long, env = "MANATAN_DISK_FLOOR_MB", default_value_t = 512)]
+    disk_floor_mb: u64,
+
+    /// Free disk space in MiB below which a low disk space notification is sent
+    #[arg(long, env = "MANATAN_DISK_WARNING_MB", default_value_t = 2048)]
+    disk_warning_mb: u64,
 }
 
 fn parse_boolish(value: &str) -> Result<bool, String> {
@@ -1181,12 +1194,23 @@ async fn run_server(
 
     info!("🌍 Starting Web Interface at http://{}:{}", host, port);
 
-    let ocr_router = manatan_ocr_server::create_router(data_dir.clone());
+    manatan_storage::configure(manatan_storage::DiskLimits {
+        floor_bytes: cli.disk_floor_mb.saturating_mul(1024 * 1024),
+        warn_bytes: cli.disk_warning_mb.saturating_mul(1024 * 1024),
+    });
+    let ocr_router = manatan_ocr_server::create_router(
+        data_dir.clone(),
+        PathBuf::from(local_novel_path_str.clone()),
+    );
     let yomitan_router = manatan_yomitan_server::create_router(data_dir.clone());
     let audio_router = manatan_audio_server::create_router(data_dir.clone());
     let sync_router = manatan_sync_server::create_router(data_dir.clone());
     let novel_router = manatan_novel_server::create_router(data_dir.clone(), PathBuf::from(local_novel_path_str));
-    let system_router = Router::new().route("/version", any(current_version_handler));
+    let system_router = Router::new()
+        .route("/version", any(current_version_handler))
+        .route("/health", get(system_health_handler));
+    let demo_router = demo::router(port, &data_dir);
+    let notifier = notifications::Notifier::start(data_dir);
 
     let cors = CorsLayer::new()
         .allow_origin(AllowOrigin::mirror_request())
@@ -1215,6 +1239,10 @@ async fn run_server(
         .nest("/api/novel", novel_router)
         .nest("/api/system", system_router)
         .nest("/api/yomitan", yomitan_router)
+        .nest("/api/demo", demo_router)
+        .nest("/api/search", search::router(port))
+        .nest("/api/jobs", jobs::router())
+        .nest("/api/notifications", notifier.router())
         .merge(manatan_router)
         .fallback(serve_react_app)
         .layer(cors);
@@ -1232,7 +1260,14 @@ async fn run_server(
     info!("✅ Unified Server Running.");
 
     tokio::select! {
-        _ = suwayomi_proc.wait() => { error!("❌ Suwayomi exited unexpectedly"); }
+        status = suwayomi_proc.wait() => {
+            error!("❌ Suwayomi exited unexpectedly");
+            let code = status.ok().and_then(|status| status.code());
+            let notify = notifier.notify(manatan_events::Event::SuwayomiExited { code });
+            if tokio::time::timeout(Duration::from_secs(15), notify).await.is_err() {
+                warn!("Timed out delivering Suwayomi exit notification");
+            }
+        }
         _ = server_future => { info!("✅ Web server shutdown complete."); }
     }
 
@@ -1627,6 +1662,49 @@ async fn current_version_handler() -> impl IntoResponse {
     })
 }
 
+/// Aggregate health of the bundled services. OCR is reported as degraded, with the failing
+/// check named, when its startup self-test did not pass or its last `/health` probe of Lens
+/// failed, and disk as degraded once free space drops below the warning threshold.
+async fn system_health_handler() -> impl IntoResponse {
+    let ocr_report = manatan_ocr_server::selftest::latest();
+    let backend_health = manatan_ocr_server::health::latest(Default::default());
+    let backend_status = backend_health.as_ref().map(|health| health.status);
+    let backend_degraded = backend_status == Some(HealthStatus::Degraded);
+    let ocr_status = match &ocr_report {
+        _ if backend_degraded => "degraded",
+        None => "pending",
+        Some(report) if report.healthy => "ok",
+        Some(_) => "degraded",
+    };
+    let limits = manatan_storage::limits();
+    let directories = tokio::task::spawn_blocking(manatan_storage::usage)
+        .await
+        .unwrap_or_default();
+    let disk_low = directories
+        .iter()
+        .filter_map(|dir| dir.available_bytes)
+        .any(|available| available < limits.warn_bytes);
+    let disk_status = if disk_low { "degraded" } else { "ok" };
+    let degraded = ocr_status == "degraded" || disk_low;
+    axum::Json(serde_json::json!({
+        "status": if degraded { "degraded" } else { "ok" },
+        "components": {
+            "ocr": {
+                "status": ocr_status,
+                "failing_check": ocr_report.as_ref().and_then(|report| report.failing_check()),
+                "self_test": ocr_report,
+                "backend": backend_health,
+            },
+            "disk": {
+                "status": disk_status,
+                "floor_bytes": limits.floor_bytes,
+                "warn_bytes": limits.warn_bytes,
+                "directories": directories,
+            },
+        },
+    }))
+}
+
 fn is_flatpak() -> bool {
     std::env::var("FLATPAK_ID").is_ok()
 }
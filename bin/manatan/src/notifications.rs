@@ -0,0 +1,296 @@
+//! Webhook delivery for events published on the `manatan_events` bus.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
+use manatan_events::Event;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+const CONFIG_FILE: &str = "notifications.json";
+const MAX_ATTEMPTS: u32 = 4;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WebhookFormat {
+    #[default]
+    Json,
+    /// `{ "content": ... }` body accepted by Discord (and Slack-compatible) webhooks.
+    Discord,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EventToggles {
+    pub sync_completed: bool,
+    pub sync_failed: bool,
+    pub dictionary_imported: bool,
+    pub backup_completed: bool,
+    pub suwayomi_exited: bool,
+    pub preprocess_finished: bool,
+    pub disk_space_low: bool,
+}
+
+impl Default for EventToggles {
+    fn default() -> Self {
+        Self {
+            sync_completed: true,
+            sync_failed: true,
+            dictionary_imported: true,
+            backup_completed: true,
+            suwayomi_exited: true,
+            preprocess_finished: true,
+            disk_space_low: true,
+        }
+    }
+}
+
+impl EventToggles {
+    fn allows(&self, event: &Event) -> bool {
+        match event {
+            Event::SyncCompleted { .. } => self.sync_completed,
+            Event::SyncFailed { .. } => self.sync_failed,
+            Event::DictionaryImported { .. } => self.dictionary_imported,
+            Event::BackupCompleted { .. } => self.backup_completed,
+            Event::SuwayomiExited { .. } => self.suwayomi_exited,
+            Event::PreprocessFinished { .. } => self.preprocess_finished,
+            Event::DiskSpaceLow { .. } => self.disk_space_low,
+            Event::Test => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationConfig {
+    pub webhooks: Vec<Webhook>,
+    pub events: EventToggles,
+}
+
+#[derive(Clone)]
+pub struct Notifier {
+    client: Client,
+    config_path: PathBuf,
+    config: Arc<RwLock<NotificationConfig>>,
+}
+
+impl Notifier {
+    /// Loads the saved configuration and starts forwarding bus events to the webhooks.
+    pub fn start(data_dir: &Path) -> Self {
+        let config_path = data_dir.join(CONFIG_FILE);
+        let config = std::fs::read_to_string(&config_path)
+            .ok()
+            .and_then(|raw| serde_json::from_str(&raw).ok())
+            .unwrap_or_default();
+        let notifier = Self {
+            client: Client::builder()
+                .timeout(REQUEST_TIMEOUT)
+                .build()
+                .unwrap_or_default(),
+            config_path,
+            config: Arc::new(RwLock::new(config)),
+        };
+
+        let worker = notifier.clone();
+        let mut events = manatan_events::subscribe();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => {
+                        let worker = worker.clone();
+                        tokio::spawn(async move { worker.notify(event).await });
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("🔔 Notification bus lagged; dropped {skipped} events");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        notifier
+    }
+
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/config", get(get_config_handler).post(set_config_handler))
+            .route("/test", get(test_handler))
+            .with_state(self.clone())
+    }
+
+    fn config(&self) -> NotificationConfig {
+        self.config
+            .read()
+            .map(|config| config.clone())
+            .unwrap_or_default()
+    }
+
+    /// Delivers an event to every configured webhook, retrying transient failures.
+    pub async fn notify(&self, event: Event) {
+        let config = self.config();
+        if config.webhooks.is_empty() || !config.events.allows(&event) {
+            return;
+        }
+        let event = event.redacted();
+        let deliveries = config.webhooks.iter().map(|webhook| async {
+            let mut backoff = INITIAL_BACKOFF;
+            for attempt in 1..=MAX_ATTEMPTS {
+                match self.send(webhook, &event).await {
+                    Ok(()) => return,
+                    Err(Delivery::Fatal(err)) => {
+                        warn!(
+                            "🔔 Webhook {} rejected {}: {err}",
+                            webhook_label(&webhook.url),
+                            event.kind()
+                        );
+                        return;
+                    }
+                    Err(Delivery::Retry(err)) if attempt < MAX_ATTEMPTS => {
+                        warn!(
+                            "🔔 Webhook {} failed (attempt {attempt}/{MAX_ATTEMPTS}): {err}",
+                            webhook_label(&webhook.url)
+                        );
+                        tokio::time::sleep(backoff).await;
+                        backoff *= 2;
+                    }
+                    Err(Delivery::Retry(err)) => {
+                        warn!(
+                            "🔔 Giving up on webhook {} for {}: {err}",
+                            webhook_label(&webhook.url),
+                            event.kind()
+                        );
+                    }
+                }
+            }
+        });
+        futures::future::join_all(deliveries).await;
+    }
+
+    async fn send(&self, webhook: &Webhook, event: &Event) -> Result<(), Delivery> {
+        let response = self
+            .client
+            .post(&webhook.url)
+            .json(&payload(webhook.format, event))
+            .send()
+            .await
+            .map_err(|err| Delivery::Retry(err.without_url().to_string()))?;
+        let status = response.status();
+        if status.is_success() {
+            Ok(())
+        } else if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+            Err(Delivery::Retry(format!("HTTP {status}")))
+        } else {
+            Err(Delivery::Fatal(format!("HTTP {status}")))
+        }
+    }
+}
+
+/// Webhook URLs carry their secret in the path, so only the origin is ever logged or
+/// echoed back.
+fn webhook_label(url: &str) -> String {
+    reqwest::Url::parse(url)
+        .map(|url| url.origin().ascii_serialization())
+        .unwrap_or_else(|_| "<invalid url>".to_string())
+}
+
+enum Delivery {
+    Retry(String),
+    Fatal(String),
+}
+
+fn payload(format: WebhookFormat, event: &Event) -> Value {
+    match format {
+        WebhookFormat::Discord => json!({
+            "username": "Manatan",
+            "content": event.summary(),
+        }),
+        WebhookFormat::Json => {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs();
+            let mut body = serde_json::to_value(event).unwrap_or_else(|_| json!({}));
+            if let Value::Object(map) = &mut body {
+                map.insert("summary".to_string(), json!(event.summary()));
+                map.insert("timestamp".to_string(), json!(timestamp));
+            }
+            body
+        }
+    }
+}
+
+async fn get_config_handler(State(notifier): State<Notifier>) -> Json<NotificationConfig> {
+    Json(notifier.config())
+}
+
+async fn set_config_handler(
+    State(notifier): State<Notifier>,
+    Json(config): Json<NotificationConfig>,
+) -> Result<Json<NotificationConfig>, (StatusCode, String)> {
+    if let Some(webhook) = config
+        .webhooks
+        .iter()
+        .find(|webhook| !webhook.url.starts_with("http://") && !webhook.url.starts_with("https://"))
+    {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Invalid webhook URL: {}", webhook_label(&webhook.url)),
+        ));
+    }
+
+    let raw = serde_json::to_string_pretty(&config)
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    tokio::fs::write(&notifier.config_path, raw)
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    if let Ok(mut current) = notifier.config.write() {
+        *current = config.clone();
+    }
+    info!("🔔 Saved {} notification webhooks", config.webhooks.len());
+    Ok(Json(config))
+}
+
+/// Sends a single test event to every webhook, without retries, and reports each result.
+async fn test_handler(State(notifier): State<Notifier>) -> Json<Value> {
+    let config = notifier.config();
+    let mut results = Vec::with_capacity(config.webhooks.len());
+    for webhook in &config.webhooks {
+        let error = match notifier.send(webhook, &Event::Test).await {
+            Ok(()) => None,
+            Err(Delivery::Retry(err) | Delivery::Fatal(err)) => Some(err),
+        };
+        results.push(json!({
+            "url": webhook_label(&webhook.url),
+            "format": webhook.format,
+            "ok": error.is_none(),
+            "error": error,
+        }));
+    }
+    let all_ok = results.iter().all(|result| result["ok"] == true);
+    Json(json!({
+        "status": if config.webhooks.is_empty() {
+            "unconfigured"
+        } else if all_ok {
+            "ok"
+        } else {
+            "error"
+        },
+        "results": results,
+    }))
+}
@@ -0,0 +1,257 @@
+//! One search box over everything the user has read: library titles, cached OCR text,
+//! novel highlights and dictionary terms. Each source is queried concurrently through its
+//! own server's API on loopback, under its own timeout, so a slow source only drops its
+//! own group from the response.
+
+use std::time::Duration;
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{Map, Value, json};
+
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 100;
+const SOURCE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Clone)]
+struct SearchState {
+    client: Client,
+    api_base: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SearchType {
+    Books,
+    Text,
+    Highlights,
+    Terms,
+}
+
+impl SearchType {
+    const ALL: [SearchType; 4] = [
+        SearchType::Books,
+        SearchType::Text,
+        SearchType::Highlights,
+        SearchType::Terms,
+    ];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            SearchType::Books => "books",
+            SearchType::Text => "text",
+            SearchType::Highlights => "highlights",
+            SearchType::Terms => "terms",
+        }
+    }
+
+    fn from_str(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+}
+
+#[derive(Deserialize)]
+struct SearchParams {
+    q: String,
+    /// Comma-separated subset of `books,text,highlights,terms`. All of them when absent.
+    types: Option<String>,
+    /// Maximum hits per type.
+    limit: Option<usize>,
+}
+
+pub fn router(port: u16) -> Router {
+    let state = SearchState {
+        client: Client::new(),
+        api_base: format!("http://127.0.0.1:{port}/api"),
+    };
+    Router::new()
+        .route("/", get(search_handler))
+        .with_state(state)
+}
+
+/// Returns `{ query, results: { <type>: [...] }, errors: { <type>: message } }`. A type
+/// that failed or timed out is listed under `errors` instead of `results`.
+async fn search_handler(
+    State(state): State<SearchState>,
+    Query(params): Query<SearchParams>,
+) -> Result<Json<Value>, (StatusCode, String)> {
+    let query = params.q.trim();
+    if query.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "q must not be empty".to_string()));
+    }
+    let types = parse_types(params.types.as_deref())?;
+    let limit = params.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+
+    let outcomes = futures::future::join_all(
+        types
+            .iter()
+            .map(|kind| search_source(&state, *kind, query, limit)),
+    )
+    .await;
+
+    let mut results = Map::new();
+    let mut errors = Map::new();
+    for (kind, outcome) in types.into_iter().zip(outcomes) {
+        match outcome {
+            Ok(hits) => results.insert(kind.as_str().to_string(), hits),
+            Err(message) => errors.insert(kind.as_str().to_string(), Value::String(message)),
+        };
+    }
+    Ok(Json(json!({
+        "query": query,
+        "results": results,
+        "errors": errors,
+    })))
+}
+
+fn parse_types(types: Option<&str>) -> Result<Vec<SearchType>, (StatusCode, String)> {
+    let Some(types) = types else {
+        return Ok(SearchType::ALL.to_vec());
+    };
+    let mut selected = Vec::new();
+    for name in types.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        let kind = SearchType::from_str(name).ok_or_else(|| {
+            (
+                StatusCode::BAD_REQUEST,
+                format!("Unknown search type: {name}"),
+            )
+        })?;
+        if !selected.contains(&kind) {
+            selected.push(kind);
+        }
+    }
+    Ok(selected)
+}
+
+async fn search_source(
+    state: &SearchState,
+    kind: SearchType,
+    query: &str,
+    limit: usize,
+) -> Result<Value, String> {
+    let request = async {
+        match kind {
+            SearchType::Books => get_hits(state, "novel/search/books", query, limit).await,
+            SearchType::Text => get_hits(state, "ocr/search", query, limit).await,
+            SearchType::Highlights => {
+                get_hits(state, "novel/search/highlights", query, limit).await
+            }
+            SearchType::Terms => search_terms(state, query, limit).await,
+        }
+    };
+    match tokio::time::timeout(SOURCE_TIMEOUT, request).await {
+        Ok(result) => result.map_err(|err| err.to_string()),
+        Err(_) => Err(format!("Timed out after {}s", SOURCE_TIMEOUT.as_secs())),
+    }
+}
+
+async fn get_hits(
+    state: &SearchState,
+    path: &str,
+    query: &str,
+    limit: usize,
+) -> anyhow::Result<Value> {
+    Ok(state
+        .client
+        .get(format!("{}/{path}", state.api_base))
+        .query(&[("q", query.to_string()), ("limit", limit.to_string())])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?)
+}
+
+/// Dictionary entries for the query as a whole, not for words that merely start it.
+async fn search_terms(state: &SearchState, query: &str, limit: usize) -> anyhow::Result<Value> {
+    let response: Value = state
+        .client
+        .get(format!("{}/yomitan/lookup", state.api_base))
+        .query(&[("text", query)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(Value::Array(term_hits(&response, query, limit)))
+}
+
+/// The lookup's terms that span the whole query, with the dictionaries defining each.
+fn term_hits(response: &Value, query: &str, limit: usize) -> Vec<Value> {
+    let query_len = query.chars().count() as u64;
+    response["terms"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|term| term["spanStart"] == 0 && term["matchLen"] == query_len)
+        .take(limit)
+        .map(|term| {
+            let mut dictionaries: Vec<&str> = term["glossary"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|definition| definition["dictionaryName"].as_str())
+                .collect();
+            dictionaries.dedup();
+            json!({
+                "headword": term["headword"],
+                "reading": term["reading"],
+                "dictionaries": dictionaries,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn term(headword: &str, span_start: u64, match_len: u64, dictionaries: &[&str]) -> Value {
+        json!({
+            "headword": headword,
+            "reading": "",
+            "spanStart": span_start,
+            "matchLen": match_len,
+            "glossary": dictionaries
+                .iter()
+                .map(|name| json!({ "dictionaryName": name }))
+                .collect::<Vec<_>>(),
+        })
+    }
+
+    #[test]
+    fn parse_types_defaults_to_all_and_dedups() {
+        assert_eq!(parse_types(None).ok(), Some(SearchType::ALL.to_vec()));
+        assert_eq!(
+            parse_types(Some(" terms, books,,terms")).ok(),
+            Some(vec![SearchType::Terms, SearchType::Books])
+        );
+        let (status, message) = parse_types(Some("books,comments")).expect_err("unknown type");
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert!(message.contains("comments"));
+    }
+
+    #[test]
+    fn term_hits_keep_only_terms_spanning_the_whole_query() {
+        let response = json!({
+            "terms": [
+                term("食べ物", 0, 3, &["JMdict", "JMdict", "Daijirin"]),
+                term("食べる", 0, 2, &["JMdict"]),
+                term("物", 2, 1, &["JMdict"]),
+                term("食物", 0, 3, &[]),
+            ]
+        });
+
+        let hits = term_hits(&response, "食べ物", 10);
+        let headwords: Vec<&str> = hits.iter().filter_map(|h| h["headword"].as_str()).collect();
+        assert_eq!(headwords, ["食べ物", "食物"]);
+        assert_eq!(hits[0]["dictionaries"], json!(["JMdict", "Daijirin"]));
+        assert_eq!(term_hits(&response, "食べ物", 1).len(), 1);
+        assert!(term_hits(&json!({}), "食べ物", 10).is_empty());
+    }
+}
@@ -0,0 +1,186 @@
+//! Runs the OCR, novel and sync routers together against temporary directories, the way
+//! the desktop binary mounts them, so flows that cross servers can be tested end to end.
+//!
+//! Google Drive is replaced by [`FakeDrive`], which several [`Device`]s can share to sync
+//! with each other. Nothing here talks to Suwayomi or Lens: OCR flows have to be served
+//! from a cache seeded through `/api/ocr/import-cache`.
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use axum::Router;
+use manatan_sync_server::{
+    SyncError, SyncPayload, SyncState,
+    backend::{AuthFlow, PushResult, SyncBackend},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::{net::TcpListener, task::JoinHandle};
+
+/// In-memory stand-in for the Google Drive sync file, with the same etag checks.
+#[derive(Clone, Default)]
+pub struct FakeDrive {
+    file: Arc<Mutex<Option<(SyncPayload, String)>>>,
+}
+
+impl FakeDrive {
+    /// The payload last pushed by any device.
+    pub fn stored(&self) -> Option<SyncPayload> {
+        self.lock().as_ref().map(|(payload, _)| payload.clone())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<(SyncPayload, String)>> {
+        self.file
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+#[async_trait]
+impl SyncBackend for FakeDrive {
+    async fn pull(&self) -> Result<Option<(SyncPayload, String)>, SyncError> {
+        Ok(self.lock().clone())
+    }
+
+    async fn push(&self, data: &SyncPayload, etag: Option<&str>) -> Result<PushResult, SyncError> {
+        let mut file = self.lock();
+        let version = match file.as_ref() {
+            Some((_, current_etag)) => {
+                if let Some(expected_etag) = etag
+                    && expected_etag != current_etag
+                {
+                    return Ok(PushResult::Conflict {
+                        remote_etag: current_etag.clone(),
+                    });
+                }
+                current_etag.parse::<u64>().unwrap_or_default() + 1
+            }
+            None => 1,
+        };
+        let new_etag = version.to_string();
+        *file = Some((data.clone(), new_etag.clone()));
+        Ok(PushResult::Success { etag: new_etag })
+    }
+
+    async fn is_authenticated(&self) -> bool {
+        true
+    }
+
+    async fn get_user_info(&self) -> Result<Option<String>, SyncError> {
+        Ok(Some("fake-drive@example.com".to_string()))
+    }
+
+    fn start_auth(&self, _redirect_uri: &str) -> Result<AuthFlow, SyncError> {
+        Err(SyncError::OAuthError(
+            "FakeDrive needs no authentication".to_string(),
+        ))
+    }
+
+    async fn complete_auth(&mut self, _code: &str, _redirect_uri: &str) -> Result<(), SyncError> {
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<(), SyncError> {
+        Ok(())
+    }
+
+    async fn refresh_token(&mut self) -> Result<(), SyncError> {
+        Ok(())
+    }
+}
+
+/// One running instance of the servers, with its own data directory and sync device ID.
+pub struct Device {
+    pub base_url: String,
+    pub sync: SyncState,
+    client: reqwest::Client,
+    dir: PathBuf,
+    server: JoinHandle<()>,
+}
+
+impl Device {
+    /// Starts the routers on a free local port, syncing through `drive`.
+    pub async fn start(name: &str, drive: &FakeDrive) -> anyhow::Result<Self> {
+        let nanos = SystemTime::now().duration_since(UNIX_EPOCH)?.as_nanos();
+        let dir = std::env::temp_dir().join(format!("manatan-e2e-{name}-{nanos}"));
+        let data_dir = dir.join("data");
+        let local_novel_dir = dir.join("local-novel");
+        std::fs::create_dir_all(&local_novel_dir)?;
+
+        let sync = SyncState::new(data_dir.clone());
+        sync.set_backend(Box::new(drive.clone())).await;
+
+        let app = Router::new()
+            .nest(
+                "/api/ocr",
+                manatan_ocr_server::create_router(data_dir.clone(), local_novel_dir.clone()),
+            )
+            .nest(
+                "/api/sync",
+                manatan_sync_server::create_router_with_state(sync.clone()),
+            )
+            .nest(
+                "/api/novel",
+                manatan_novel_server::create_router(data_dir, local_novel_dir),
+            );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Ok(Self {
+            base_url,
+            sync,
+            client: reqwest::Client::new(),
+            dir,
+            server,
+        })
+    }
+
+    pub async fn get(&self, path: &str) -> anyhow::Result<reqwest::Response> {
+        Ok(self.client.get(self.url(path)).send().await?)
+    }
+
+    pub async fn post<B: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> anyhow::Result<reqwest::Response> {
+        Ok(self.client.post(self.url(path)).json(body).send().await?)
+    }
+
+    /// GETs `path`, failing on a non-success status.
+    pub async fn get_json<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        Ok(self.get(path).await?.error_for_status()?.json().await?)
+    }
+
+    /// POSTs `body` to `path`, failing on a non-success status.
+    pub async fn post_json<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> anyhow::Result<T> {
+        Ok(self
+            .post(path, body)
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base_url)
+    }
+}
+
+impl Drop for Device {
+    fn drop(&mut self) {
+        self.server.abort();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
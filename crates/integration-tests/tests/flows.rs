@@ -0,0 +1,248 @@
+use std::collections::HashMap;
+
+use manatan_integration_tests::{Device, FakeDrive};
+use manatan_ocr_server::{
+    backend::OcrBackend,
+    language::OcrLanguage,
+    logic::{self, BoundingBox, OcrResult},
+    merge::OrientationHint,
+    preprocess::Preprocess,
+    state::{CacheEntry, EntrySource},
+};
+use manatan_sync_server::{
+    LNMetadata, LNProgress, MergeResponse, SyncChange, SyncChangeKind, SyncPayload,
+};
+use serde_json::{Value, json};
+
+const BOOK_ID: &str = "book-1";
+
+fn metadata() -> Value {
+    json!({
+        "id": BOOK_ID,
+        "title": "Kino's Journey",
+        "author": "Keiichi Sigsawa",
+        "addedAt": 1_700_000_000_000_i64,
+        "stats": { "chapterLengths": [1200, 800], "totalLength": 2000 },
+        "chapterCount": 2,
+        "toc": [],
+    })
+}
+
+fn progress(chapter_index: i32, total_chars_read: i32) -> Value {
+    json!({
+        "chapterIndex": chapter_index,
+        "chapterCharOffset": 10,
+        "totalCharsRead": total_chars_read,
+        "sentenceText": "国の話。",
+        "chapterProgress": 0.5,
+        "totalProgress": 0.4,
+        "lastModified": 1_700_000_100_000_i64,
+    })
+}
+
+/// Uploads a book's metadata and progress to one device's novel server.
+async fn add_book(device: &Device) -> anyhow::Result<()> {
+    device
+        .post(
+            &format!("/api/novel/metadata/{BOOK_ID}"),
+            &json!({ "metadata": metadata() }),
+        )
+        .await?
+        .error_for_status()?;
+    device
+        .post(
+            &format!("/api/novel/progress/{BOOK_ID}"),
+            &json!({ "progress": progress(1, 1_600) }),
+        )
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Builds the payload the reader sends to `/api/sync/merge` from a device's library.
+async fn local_payload(device: &Device) -> anyhow::Result<SyncPayload> {
+    let mut payload = SyncPayload::new(device.sync.get_device_id());
+    let books: Vec<LNMetadata> = device.get_json("/api/novel/metadata").await?;
+    for book in books {
+        let progress: Option<LNProgress> = device
+            .get_json(&format!("/api/novel/progress/{}", book.id))
+            .await?;
+        if let Some(progress) = progress {
+            payload.ln_progress.insert(book.id.clone(), progress);
+        }
+        payload.ln_metadata.insert(book.id.clone(), book);
+    }
+    Ok(payload)
+}
+
+#[tokio::test]
+async fn book_listing_is_projected_to_requested_fields() -> anyhow::Result<()> {
+    let device = Device::start("listing", &FakeDrive::default()).await?;
+    add_book(&device).await?;
+
+    let listing: Value = device
+        .get_json("/api/novel/metadata?fields=chapterCount")
+        .await?;
+    assert_eq!(
+        listing,
+        json!([{ "id": BOOK_ID, "title": "Kino's Journey", "chapterCount": 2 }])
+    );
+
+    let unknown = device.get("/api/novel/metadata?fields=blurb").await?;
+    assert_eq!(unknown.status(), reqwest::StatusCode::BAD_REQUEST);
+    Ok(())
+}
+
+#[tokio::test]
+async fn cached_ocr_is_served_until_its_context_is_purged() -> anyhow::Result<()> {
+    let device = Device::start("ocr", &FakeDrive::default()).await?;
+    let page_url = "http://127.0.0.1:4568/api/v1/manga/1/chapter/1/page/0";
+    let line = OcrResult {
+        text: "こんにちは".to_string(),
+        tight_bounding_box: BoundingBox {
+            x: 0.1,
+            y: 0.1,
+            width: 0.05,
+            height: 0.3,
+            rotation: None,
+        },
+        is_merged: None,
+        forced_orientation: None,
+        confidence: None,
+        words: None,
+        font_size_hint: None,
+    };
+    let cache = HashMap::from([(
+        logic::get_cache_key(page_url, Some(OcrLanguage::default()), &Default::default()),
+        CacheEntry {
+            context: "Yotsuba Ch. 1".to_string(),
+            data: vec![line],
+            backend: OcrBackend::Lens,
+            orientation: None,
+            orientation_hint: OrientationHint::Auto,
+            edited_at: None,
+            source: EntrySource::Ocr,
+            preprocess: Preprocess::None,
+        },
+    )]);
+    let imported: Value = device.post_json("/api/ocr/import-cache", &cache).await?;
+    assert_eq!(imported["added"], 1);
+
+    let ocr_path = format!("/api/ocr/ocr?url={page_url}");
+    let lines: Vec<OcrResult> = device.get_json(&ocr_path).await?;
+    assert_eq!(lines.len(), 1);
+    assert_eq!(lines[0].text, "こんにちは");
+
+    let purged: Value = device
+        .post_json(
+            "/api/ocr/purge-cache",
+            &json!({ "context": "Yotsuba", "match_prefix": true }),
+        )
+        .await?;
+    assert_eq!(purged["context_rows"], 1);
+    let remaining: Vec<Value> = device.get_json("/api/ocr/search?q=こんにちは").await?;
+    assert!(remaining.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn reading_progress_syncs_between_two_devices() -> anyhow::Result<()> {
+    let drive = FakeDrive::default();
+    let phone = Device::start("phone", &drive).await?;
+    let desktop = Device::start("desktop", &drive).await?;
+    add_book(&phone).await?;
+
+    let pushed: MergeResponse = phone
+        .post_json(
+            "/api/sync/merge",
+            &json!({ "payload": local_payload(&phone).await? }),
+        )
+        .await?;
+    assert!(pushed.payload.ln_progress.contains_key(BOOK_ID));
+    let stored = drive.stored().expect("phone pushed to the drive");
+    assert_eq!(stored.device_id, phone.sync.get_device_id());
+
+    // The desktop has an empty library, so the merge hands back the phone's book.
+    let pulled: MergeResponse = desktop
+        .post_json(
+            "/api/sync/merge",
+            &json!({ "payload": local_payload(&desktop).await? }),
+        )
+        .await?;
+    assert!(pulled.conflicts.is_empty());
+    let kinds: Vec<_> = pulled.changes.iter().map(|change| &change.kind).collect();
+    assert!(kinds.contains(&&SyncChangeKind::BookAdded));
+    assert!(kinds.contains(&&SyncChangeKind::Progress {
+        from_percent: None,
+        to_percent: 40.0,
+    }));
+    assert!(
+        pulled
+            .changes
+            .iter()
+            .all(|change| change.title.as_deref() == Some("Kino's Journey"))
+    );
+    let history_id = pulled.history_id.clone().expect("sync recorded in history");
+    let recorded: Vec<SyncChange> = desktop
+        .get_json(&format!("/api/sync/history/{history_id}/changes"))
+        .await?;
+    assert_eq!(recorded, pulled.changes);
+    for (id, book) in &pulled.payload.ln_metadata {
+        desktop
+            .post(
+                &format!("/api/novel/metadata/{id}"),
+                &json!({ "metadata": book }),
+            )
+            .await?
+            .error_for_status()?;
+    }
+    for (id, progress) in &pulled.payload.ln_progress {
+        desktop
+            .post(
+                &format!("/api/novel/progress/{id}"),
+                &json!({ "progress": progress }),
+            )
+            .await?
+            .error_for_status()?;
+    }
+
+    let synced: Option<LNProgress> = desktop
+        .get_json(&format!("/api/novel/progress/{BOOK_ID}"))
+        .await?;
+    let synced = synced.expect("progress reached the desktop");
+    assert_eq!(synced.chapter_index, 1);
+    assert_eq!(synced.total_chars_read, 1_600);
+    assert_eq!(
+        drive.stored().map(|payload| payload.device_id),
+        Some(desktop.sync.get_device_id())
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn sync_config_is_validated_without_being_saved() -> anyhow::Result<()> {
+    let drive = FakeDrive::default();
+    let device = Device::start("validate", &drive).await?;
+    let saved: Value = device.get_json("/api/sync/config").await?;
+
+    let mut proposed = saved.clone();
+    proposed["backend"] = json!("googledrive");
+    proposed["googleDriveFolder"] = json!("Manatan's/Backups");
+    let report: Value = device
+        .post_json("/api/sync/config/validate", &proposed)
+        .await?;
+    assert_eq!(report["valid"], false);
+    assert_eq!(report["diagnostics"][0]["field"], "googleDriveFolder");
+
+    // The fake drive accepts any destination, so a well-formed folder passes the probe.
+    proposed["googleDriveFolder"] = json!("Manatan Backups");
+    let report: Value = device
+        .post_json("/api/sync/config/validate", &proposed)
+        .await?;
+    assert_eq!(report["valid"], true);
+    assert_eq!(report["diagnostics"], json!([]));
+
+    let unchanged: Value = device.get_json("/api/sync/config").await?;
+    assert_eq!(unchanged, saved);
+    Ok(())
+}
@@ -0,0 +1,336 @@
+//! Process-wide view of background work. Sub-servers run heavy jobs through a
+//! [`WorkerPool`], which bounds both how many jobs of a class run at once and how many may
+//! wait, and the launcher lists every queued or running job from [`snapshot`].
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    future::Future,
+    sync::{
+        Arc, LazyLock, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+use tokio::{sync::Semaphore, task::JoinError};
+
+static JOBS: LazyLock<Mutex<BTreeMap<u64, JobInfo>>> = LazyLock::new(Default::default);
+static POOLS: LazyLock<Mutex<BTreeMap<&'static str, PoolLimits>>> = LazyLock::new(Default::default);
+
+/// Recovers a poisoned registry: a job that panicked mid-update leaves at worst a stale
+/// label, which is no reason to stop listing, tracking or removing every other job.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct JobInfo {
+    pub id: u64,
+    /// The pool the job belongs to, e.g. `ocr-preprocess`.
+    pub class: &'static str,
+    /// The sub-server that submitted the job.
+    pub owner: String,
+    pub label: String,
+    pub state: JobState,
+    pub current: Option<usize>,
+    pub total: Option<usize>,
+    /// Unix milliseconds.
+    pub queued_at: i64,
+    pub started_at: Option<i64>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct PoolLimits {
+    workers: usize,
+    capacity: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PoolInfo {
+    pub class: &'static str,
+    pub workers: usize,
+    /// Jobs that may wait for a worker before new ones are rejected.
+    pub capacity: usize,
+    pub queued: usize,
+    pub running: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Snapshot {
+    pub pools: Vec<PoolInfo>,
+    pub jobs: Vec<JobInfo>,
+}
+
+/// Every registered pool and every queued or running job, oldest first.
+pub fn snapshot() -> Snapshot {
+    let jobs: Vec<JobInfo> = lock(&JOBS).values().cloned().collect();
+    let pools = lock(&POOLS)
+        .iter()
+        .map(|(class, limits)| {
+            let count = |state| {
+                jobs.iter()
+                    .filter(|job| job.class == *class && job.state == state)
+                    .count()
+            };
+            PoolInfo {
+                class: *class,
+                workers: limits.workers,
+                capacity: limits.capacity,
+                queued: count(JobState::Queued),
+                running: count(JobState::Running),
+            }
+        })
+        .collect();
+    Snapshot { pools, jobs }
+}
+
+/// Lists a pool in [`snapshot`]. [`WorkerPool::new`] does this itself; queues that run
+/// their own workers call it so their limits show up next to the others.
+pub fn register_pool(class: &'static str, workers: usize, capacity: usize) {
+    lock(&POOLS).insert(class, PoolLimits { workers, capacity });
+}
+
+/// Lists a queued job until every clone of the returned handle is dropped.
+pub fn track(class: &'static str, owner: &str, label: impl Into<String>) -> JobHandle {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    lock(&JOBS).insert(
+        id,
+        JobInfo {
+            id,
+            class,
+            owner: owner.to_string(),
+            label: label.into(),
+            state: JobState::Queued,
+            current: None,
+            total: None,
+            queued_at: now_millis(),
+            started_at: None,
+        },
+    );
+    JobHandle {
+        entry: Arc::new(Entry { id }),
+    }
+}
+
+/// A job's entry in the listing. Dropping the last clone removes it.
+#[derive(Clone, Debug)]
+pub struct JobHandle {
+    entry: Arc<Entry>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    id: u64,
+}
+
+impl Drop for Entry {
+    fn drop(&mut self) {
+        lock(&JOBS).remove(&self.id);
+    }
+}
+
+impl JobHandle {
+    pub fn id(&self) -> u64 {
+        self.entry.id
+    }
+
+    pub fn start(&self) {
+        self.update(|job| {
+            job.state = JobState::Running;
+            job.started_at = Some(now_millis());
+        });
+    }
+
+    pub fn set_progress(&self, current: usize, total: usize) {
+        self.update(|job| {
+            job.current = Some(current);
+            job.total = Some(total);
+        });
+    }
+
+    fn update(&self, f: impl FnOnce(&mut JobInfo)) {
+        if let Some(job) = lock(&JOBS).get_mut(&self.entry.id) {
+            f(job);
+        }
+    }
+}
+
+/// Returned when a pool already has `capacity` jobs waiting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct QueueFull {
+    pub class: &'static str,
+    pub depth: usize,
+    pub capacity: usize,
+}
+
+impl fmt::Display for QueueFull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The {} queue is full ({} of {} jobs waiting)",
+            self.class, self.depth, self.capacity
+        )
+    }
+}
+
+impl std::error::Error for QueueFull {}
+
+impl QueueFull {
+    /// The 429 body every sub-server answers a full queue with.
+    pub fn body(&self) -> serde_json::Value {
+        serde_json::json!({
+            "status": "queue_full",
+            "message": self.to_string(),
+            "class": self.class,
+            "queue_length": self.depth,
+            "max_queue_length": self.capacity,
+        })
+    }
+}
+
+/// Runs at most `workers` jobs of one class at a time and lets at most `capacity` more
+/// wait. Waiting jobs start in submission order.
+#[derive(Clone)]
+pub struct WorkerPool {
+    class: &'static str,
+    capacity: usize,
+    slots: Arc<Semaphore>,
+    waiting: Arc<AtomicUsize>,
+}
+
+/// A place in the queue, given back when the job starts or is abandoned.
+struct Admission {
+    waiting: Arc<AtomicUsize>,
+}
+
+impl Drop for Admission {
+    fn drop(&mut self) {
+        self.waiting.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl WorkerPool {
+    pub fn new(class: &'static str, workers: usize, capacity: usize) -> Self {
+        let workers = workers.max(1);
+        register_pool(class, workers, capacity);
+        Self {
+            class,
+            capacity,
+            slots: Arc::new(Semaphore::new(workers)),
+            waiting: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Jobs waiting for a worker.
+    pub fn depth(&self) -> usize {
+        self.waiting.load(Ordering::SeqCst)
+    }
+
+    fn admit(&self, owner: &str, label: String) -> Result<(JobHandle, Admission), QueueFull> {
+        self.waiting
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |waiting| {
+                (waiting < self.capacity).then_some(waiting + 1)
+            })
+            .map_err(|depth| QueueFull {
+                class: self.class,
+                depth,
+                capacity: self.capacity,
+            })?;
+        let admission = Admission {
+            waiting: self.waiting.clone(),
+        };
+        Ok((track(self.class, owner, label), admission))
+    }
+
+    /// Waits for a worker, then runs `task` in the caller's task. Dropping the returned
+    /// future gives the queue slot back.
+    pub async fn run<T>(
+        &self,
+        owner: &str,
+        label: impl Into<String>,
+        task: impl Future<Output = T>,
+    ) -> Result<T, QueueFull> {
+        let (handle, admission) = self.admit(owner, label.into())?;
+        let _permit = self
+            .slots
+            .acquire()
+            .await
+            .expect("pool semaphore is never closed");
+        drop(admission);
+        handle.start();
+        Ok(task.await)
+    }
+
+    /// Waits for a worker, then runs `task` on the blocking thread pool. Once started the
+    /// task keeps its worker until it returns, even if the caller stops waiting for it.
+    pub async fn run_blocking<T, F>(
+        &self,
+        owner: &str,
+        label: impl Into<String>,
+        task: F,
+    ) -> Result<Result<T, JoinError>, QueueFull>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (handle, admission) = self.admit(owner, label.into())?;
+        let permit = self
+            .slots
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("pool semaphore is never closed");
+        drop(admission);
+        handle.start();
+        Ok(tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            let _handle = handle;
+            task()
+        })
+        .await)
+    }
+
+    /// Queues `task` in the background and returns its job id. The task gets its handle
+    /// to report progress; the job stays listed until the task finishes.
+    pub fn spawn<F, Fut>(
+        &self,
+        owner: &str,
+        label: impl Into<String>,
+        task: F,
+    ) -> Result<u64, QueueFull>
+    where
+        F: FnOnce(JobHandle) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let (handle, admission) = self.admit(owner, label.into())?;
+        let id = handle.id();
+        let slots = self.slots.clone();
+        tokio::spawn(async move {
+            let _permit = slots
+                .acquire_owned()
+                .await
+                .expect("pool semaphore is never closed");
+            drop(admission);
+            handle.start();
+            task(handle.clone()).await;
+        });
+        Ok(id)
+    }
+}
+
+fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use manatan_jobs::{JobState, QueueFull, WorkerPool};
+use tokio::sync::oneshot;
+
+const CLASS: &str = "test-pool";
+
+fn states() -> Vec<(String, JobState)> {
+    manatan_jobs::snapshot()
+        .jobs
+        .into_iter()
+        .filter(|job| job.class == CLASS)
+        .map(|job| (job.label, job.state))
+        .collect()
+}
+
+async fn wait_until(condition: impl Fn() -> bool) {
+    for _ in 0..200 {
+        if condition() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    panic!("condition not reached");
+}
+
+#[tokio::test]
+async fn full_queue_rejects_new_jobs_with_its_depth() {
+    let pool = WorkerPool::new(CLASS, 1, 1);
+    let (release, released) = oneshot::channel::<()>();
+    pool.spawn("tests", "first", |_| async move {
+        let _ = released.await;
+    })
+    .expect("first job is accepted");
+    wait_until(|| states() == vec![("first".to_string(), JobState::Running)]).await;
+
+    pool.spawn("tests", "second", |_| async {})
+        .expect("second job waits");
+    assert_eq!(pool.depth(), 1);
+    assert_eq!(
+        pool.spawn("tests", "third", |_| async {}),
+        Err(QueueFull {
+            class: CLASS,
+            depth: 1,
+            capacity: 1,
+        })
+    );
+    assert_eq!(
+        states(),
+        vec![
+            ("first".to_string(), JobState::Running),
+            ("second".to_string(), JobState::Queued),
+        ]
+    );
+    let pool_info = manatan_jobs::snapshot()
+        .pools
+        .into_iter()
+        .find(|pool| pool.class == CLASS)
+        .expect("pool is listed");
+    assert_eq!((pool_info.queued, pool_info.running), (1, 1));
+
+    let _ = release.send(());
+    wait_until(|| states().is_empty()).await;
+    assert_eq!(pool.depth(), 0);
+    assert_eq!(pool.run("tests", "inline", async { 7 }).await, Ok(7));
+}
+
+#[tokio::test]
+async fn blocking_jobs_keep_their_worker_when_the_caller_gives_up() {
+    const BLOCKING: &str = "test-blocking-pool";
+    let pool = WorkerPool::new(BLOCKING, 1, 1);
+    let running = || {
+        manatan_jobs::snapshot()
+            .jobs
+            .into_iter()
+            .filter(|job| job.class == BLOCKING && job.state == JobState::Running)
+            .count()
+    };
+    let (release, released) = std::sync::mpsc::channel::<()>();
+    let abandoned = tokio::time::timeout(
+        Duration::from_millis(50),
+        pool.run_blocking("tests", "abandoned", move || {
+            let _ = released.recv();
+        }),
+    )
+    .await;
+    assert!(abandoned.is_err(), "the caller stops waiting");
+    assert_eq!(running(), 1);
+
+    let next = tokio::spawn({
+        let pool = pool.clone();
+        async move { pool.run_blocking("tests", "next", || 7).await }
+    });
+    wait_until(|| pool.depth() == 1).await;
+    assert_eq!(running(), 1, "the next job waits for the abandoned one");
+
+    let _ = release.send(());
+    let result = next.await.expect("join");
+    assert_eq!(result.map(|joined| joined.expect("task")), Ok(7));
+    wait_until(|| running() == 0).await;
+}
@@ -0,0 +1,292 @@
+//! Just enough EPUB reading to file a book before the reader parses it: the package
+//! document's title, author and language, the cover image, and whether the book is
+//! DRM-protected.
+
+use std::io::{Cursor, Read};
+
+use thiserror::Error;
+use zip::ZipArchive;
+
+/// Covers larger than this are left for the reader, which stores a resized copy.
+const MAX_COVER_BYTES: u64 = 2 * 1024 * 1024;
+/// Encryption algorithms that only obfuscate embedded fonts; books using them are fine.
+const FONT_OBFUSCATION: &[&str] = &[
+    "http://www.idpf.org/2008/embedding",
+    "http://ns.adobe.com/pdf/enc#RC",
+];
+
+#[derive(Debug, Error)]
+pub enum EpubError {
+    #[error("not a readable EPUB archive: {0}")]
+    Archive(#[from] zip::result::ZipError),
+    #[error("could not read {0} from the EPUB")]
+    Entry(String),
+    #[error("the EPUB has no package document")]
+    NoPackage,
+    #[error("the EPUB is DRM-protected")]
+    Drm,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EpubInfo {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub language: Option<String>,
+    /// Media type and bytes of the cover image.
+    pub cover: Option<(String, Vec<u8>)>,
+}
+
+pub fn read_info(bytes: &[u8]) -> Result<EpubInfo, EpubError> {
+    let mut zip = ZipArchive::new(Cursor::new(bytes))?;
+    if is_drm_protected(&mut zip)? {
+        return Err(EpubError::Drm);
+    }
+
+    let container = read_text(&mut zip, "META-INF/container.xml")?;
+    let opf_path = tags(&container)
+        .find(|tag| tag.name == "rootfile")
+        .and_then(|tag| tag.attr("full-path"))
+        .ok_or(EpubError::NoPackage)?;
+    let opf = read_text(&mut zip, &opf_path)?;
+    let opf_dir = opf_path.rsplit_once('/').map_or("", |(dir, _)| dir);
+
+    let mut info = EpubInfo {
+        title: element_text(&opf, "title"),
+        author: element_text(&opf, "creator"),
+        language: element_text(&opf, "language"),
+        cover: None,
+    };
+    if let Some((href, media_type)) = cover_item(&opf) {
+        let path = resolve_href(opf_dir, &href);
+        if let Ok(mut entry) = zip.by_name(&path)
+            && entry.size() <= MAX_COVER_BYTES
+        {
+            let mut cover = Vec::with_capacity(entry.size() as usize);
+            if entry.read_to_end(&mut cover).is_ok() {
+                info.cover = Some((media_type, cover));
+            }
+        }
+    }
+    Ok(info)
+}
+
+/// Adobe ADEPT and similar schemes leave a rights file or encrypt content documents;
+/// font obfuscation alone does not count.
+fn is_drm_protected(zip: &mut ZipArchive<Cursor<&[u8]>>) -> Result<bool, EpubError> {
+    if zip.by_name("META-INF/rights.xml").is_ok() {
+        return Ok(true);
+    }
+    if zip.by_name("META-INF/encryption.xml").is_err() {
+        return Ok(false);
+    }
+    let encryption = read_text(zip, "META-INF/encryption.xml")?;
+    Ok(tags(&encryption)
+        .filter(|tag| tag.name == "EncryptionMethod")
+        .filter_map(|tag| tag.attr("Algorithm"))
+        .any(|algorithm| !FONT_OBFUSCATION.contains(&algorithm.as_str())))
+}
+
+fn read_text(zip: &mut ZipArchive<Cursor<&[u8]>>, name: &str) -> Result<String, EpubError> {
+    let mut entry = zip
+        .by_name(name)
+        .map_err(|_| EpubError::Entry(name.to_string()))?;
+    let mut text = String::new();
+    entry
+        .read_to_string(&mut text)
+        .map_err(|_| EpubError::Entry(name.to_string()))?;
+    Ok(text)
+}
+
+/// The manifest href and media type of the cover: the EPUB 3 `cover-image` property,
+/// then the EPUB 2 `<meta name="cover">`, then an image whose id or href says cover.
+fn cover_item(opf: &str) -> Option<(String, String)> {
+    let items: Vec<Tag<'_>> = tags(opf).filter(|tag| tag.name == "item").collect();
+    let image = |tag: &&Tag<'_>| {
+        tag.attr("media-type")
+            .is_some_and(|media_type| media_type.starts_with("image/"))
+    };
+    let cover_id = tags(opf)
+        .find(|tag| tag.name == "meta" && tag.attr("name").as_deref() == Some("cover"))
+        .and_then(|tag| tag.attr("content"));
+
+    let item = items
+        .iter()
+        .find(|tag| {
+            tag.attr("properties")
+                .is_some_and(|properties| properties.split_whitespace().any(|p| p == "cover-image"))
+        })
+        .or_else(|| {
+            let cover_id = cover_id.as_deref()?;
+            items
+                .iter()
+                .filter(image)
+                .find(|tag| tag.attr("id").as_deref() == Some(cover_id))
+        })
+        .or_else(|| {
+            items.iter().filter(image).find(|tag| {
+                ["id", "href"].iter().any(|name| {
+                    tag.attr(name)
+                        .is_some_and(|value| value.to_lowercase().contains("cover"))
+                })
+            })
+        })?;
+    Some((item.attr("href")?, item.attr("media-type")?))
+}
+
+/// Joins a manifest href onto the package document's folder, resolving `..` and
+/// percent-escapes.
+fn resolve_href(base_dir: &str, href: &str) -> String {
+    let href = percent_decode(href.split('#').next().unwrap_or(href));
+    let mut parts: Vec<&str> = base_dir
+        .split('/')
+        .filter(|part| !part.is_empty())
+        .collect();
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        if bytes[index] == b'%'
+            && let Some(byte) = value
+                .get(index + 1..index + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            index += 3;
+            continue;
+        }
+        decoded.push(bytes[index]);
+        index += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// The text of the first element with this local name, trimmed; empty text is `None`.
+fn element_text(xml: &str, name: &str) -> Option<String> {
+    let tag = tags(xml).find(|tag| tag.name == name && !tag.closing && !tag.self_closing)?;
+    let rest = &xml[tag.end..];
+    let text = unescape(rest[..rest.find('<').unwrap_or(rest.len())].trim());
+    (!text.is_empty()).then_some(text)
+}
+
+/// One start or end tag, with the namespace prefix stripped from its name.
+struct Tag<'a> {
+    name: &'a str,
+    attrs: &'a str,
+    closing: bool,
+    self_closing: bool,
+    /// Byte offset just past the closing `>`.
+    end: usize,
+}
+
+impl Tag<'_> {
+    /// The unescaped value of an attribute, matched on its local name.
+    fn attr(&self, name: &str) -> Option<String> {
+        let mut rest = self.attrs;
+        while let Some(eq) = rest.find('=') {
+            let key = rest[..eq].trim();
+            let key = key.rsplit_once(':').map_or(key, |(_, local)| local);
+            let after = rest[eq + 1..].trim_start();
+            let quote = after.chars().next()?;
+            if quote != '"' && quote != '\'' {
+                return None;
+            }
+            let close = after[1..].find(quote)? + 1;
+            if key == name {
+                return Some(unescape(&after[1..close]));
+            }
+            rest = &after[close + 1..];
+        }
+        None
+    }
+}
+
+fn tags(xml: &str) -> impl Iterator<Item = Tag<'_>> {
+    let mut position = 0;
+    std::iter::from_fn(move || {
+        loop {
+            let start = position + xml[position..].find('<')?;
+            let rest = &xml[start..];
+            let skip_to = |terminator: &str| rest.find(terminator).map(|i| i + terminator.len());
+            let length = if rest.starts_with("<!--") {
+                skip_to("-->")
+            } else if rest.starts_with("<![CDATA[") {
+                skip_to("]]>")
+            } else {
+                skip_to(">")
+            };
+            let Some(length) = length else {
+                position = xml.len();
+                return None;
+            };
+            position = start + length;
+            let inner = &rest[1..length - 1];
+            if inner.starts_with('!') || inner.starts_with('?') {
+                continue;
+            }
+            let closing = inner.starts_with('/');
+            let self_closing = inner.ends_with('/');
+            let inner = inner.trim_start_matches('/').trim_end_matches('/');
+            let name_end = inner
+                .find(|c: char| c.is_whitespace())
+                .unwrap_or(inner.len());
+            let name = &inner[..name_end];
+            return Some(Tag {
+                name: name.rsplit_once(':').map_or(name, |(_, local)| local),
+                attrs: &inner[name_end..],
+                closing,
+                self_closing,
+                end: position,
+            });
+        }
+    })
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semicolon) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semicolon];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[semicolon + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
@@ -20,6 +20,10 @@ pub enum NovelError {
     Multipart(#[from] axum::extract::multipart::MultipartError),
     #[error("Bad request: {0}")]
     BadRequest(String),
+    #[error("Book {0} is busy")]
+    Busy(String),
+    #[error("{0}")]
+    InsufficientStorage(#[from] manatan_storage::InsufficientSpace),
 }
 
 impl IntoResponse for NovelError {
@@ -31,6 +35,14 @@ impl IntoResponse for NovelError {
             NovelError::Io(_) => (StatusCode::INTERNAL_SERVER_ERROR, "IO Error"),
             NovelError::Multipart(_) => (StatusCode::BAD_REQUEST, "Multipart Error"),
             NovelError::BadRequest(ref msg) => (StatusCode::BAD_REQUEST, msg.as_str()),
+            NovelError::Busy(_) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Another write to this book is in progress",
+            ),
+            NovelError::InsufficientStorage(_) => (
+                StatusCode::INSUFFICIENT_STORAGE,
+                "Not enough free disk space",
+            ),
         };
 
         let body = Json(json!({
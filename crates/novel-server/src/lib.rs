@@ -3,7 +3,9 @@ use std::path::{Path, PathBuf};
 use axum::{Router, extract::DefaultBodyLimit};
 use tower_http::cors::{Any, CorsLayer};
 
+pub mod epub;
 pub mod error;
+pub mod paths;
 pub mod routes;
 pub mod state;
 pub mod types;
@@ -21,14 +23,21 @@ use walkdir::WalkDir;
 use crate::types::*;
 
 pub fn create_router(data_dir: PathBuf, local_novel_path: PathBuf) -> Router {
+    manatan_storage::register("novels", &local_novel_path);
     let state = NovelState::new(data_dir, local_novel_path);
 
     let state_clone = state.clone();
-    tokio::spawn(async move {
-        if let Err(e) = scan_local_novel(&state_clone) {
-            warn!("Failed to scan local-novel: {:?}", e);
-        }
-    });
+    let scan = state
+        .scans
+        .spawn("novel", "Scan local novels", |_| async move {
+            if let Err(e) = scan_local_novel(&state_clone) {
+                warn!("Failed to scan local-novel: {:?}", e);
+            }
+        });
+    if let Err(full) = scan {
+        warn!("Skipped the local-novel scan: {full}");
+    }
+    routes::spawn_inbox_watcher(&state);
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -78,7 +87,7 @@ fn scan_local_novel(state: &NovelState) -> anyhow::Result<()> {
     {
         let path = entry.path();
         // Look for metadata.json files in subdirectories
-        if path.is_file() && path.file_name().map_or(false, |n| n == "metadata.json") {
+        if path.is_file() && paths::is_sidecar(path) {
             let Some(parent) = path.parent() else {
                 continue;
             };
@@ -109,9 +118,25 @@ fn scan_local_novel(state: &NovelState) -> anyhow::Result<()> {
                 let bytes = serde_json::to_vec(&parsed)?;
                 state.db.insert(format!("content:{}", id), bytes)?;
             }
+
+            if let Some(settings) = sidecar_data.get("readerSettings") {
+                let settings: LnReaderSettings = serde_json::from_value(settings.clone())?;
+                let bytes = serde_json::to_vec(&settings)?;
+                state.db.insert(format!("reader_settings:{}", id), bytes)?;
+            }
         }
     }
 
+    // Restore global reader settings from root
+    let reader_settings_path = local_path.join("reader-settings.json");
+    if let Ok(content) = fs::read_to_string(&reader_settings_path)
+        && let Ok(settings) = serde_json::from_str::<LnReaderSettings>(&content)
+    {
+        state
+            .db
+            .insert("reader_settings_default", serde_json::to_vec(&settings)?)?;
+    }
+
     // Scan global categories in root
     let categories_path = local_path.join("categories.json");
     if categories_path.exists() {
@@ -150,7 +175,7 @@ fn scan_local_novel(state: &NovelState) -> anyhow::Result<()> {
 }
 
 fn dir_has_legacy_novel_data(path: &Path, id: &str) -> bool {
-    path.join("metadata.json").exists()
+    paths::find_sidecar(path).is_some()
         || path.join("extracted").exists()
         || path.join(format!("{id}.epub")).exists()
 }
@@ -191,10 +216,10 @@ fn migrate_legacy_local_novel_layout(state: &NovelState) -> anyhow::Result<()> {
             );
         } else {
             // Best-effort merge when both folders exist.
-            let legacy_metadata = path.join("metadata.json");
-            let target_metadata = target_dir.join("metadata.json");
-            if legacy_metadata.exists() && !target_metadata.exists() {
-                fs::rename(&legacy_metadata, &target_metadata)?;
+            if let Some(legacy_metadata) = paths::find_sidecar(&path)
+                && paths::find_sidecar(&target_dir).is_none()
+            {
+                fs::rename(&legacy_metadata, target_dir.join(paths::SIDECAR_FILE_NAME))?;
             }
 
             let legacy_extracted = path.join("extracted");
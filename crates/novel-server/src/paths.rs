@@ -0,0 +1,162 @@
+//! Path handling shared by the scanner, extraction and uploads. Keys coming from the
+//! reader and from EPUBs use `/`, but books written on Windows can carry `\`, and the
+//! local-novel folder may sit on a case-insensitive filesystem, so nothing here builds
+//! paths by string formatting.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::error::NovelError;
+
+pub const SIDECAR_FILE_NAME: &str = "metadata.json";
+
+/// Device names Windows refuses as file or directory names, with any extension.
+const RESERVED_WINDOWS_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Turns an image blob key or manifest path into a relative path, accepting either
+/// separator and any number of leading ones. `None` for empty keys and for keys that
+/// would leave the directory they are joined onto.
+pub fn relative_path(key: &str) -> Option<PathBuf> {
+    let mut relative = PathBuf::new();
+    for part in key.split(['/', '\\']) {
+        match part {
+            "" | "." => continue,
+            ".." => return None,
+            part if part.contains(':') => return None,
+            part => relative.push(part),
+        }
+    }
+    (!relative.as_os_str().is_empty()).then_some(relative)
+}
+
+/// Whether `path` names a book sidecar, ignoring case.
+pub fn is_sidecar(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.eq_ignore_ascii_case(SIDECAR_FILE_NAME))
+}
+
+/// The sidecar in `dir` whatever its case, if there is one.
+pub fn find_sidecar(dir: &Path) -> Option<PathBuf> {
+    let exact = dir.join(SIDECAR_FILE_NAME);
+    if exact.is_file() {
+        return Some(exact);
+    }
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .find(|path| path.is_file() && is_sidecar(path))
+}
+
+/// Where the sidecar in `dir` should be read and written: an existing one whatever its
+/// case, otherwise `metadata.json`.
+pub fn sidecar_path(dir: &Path) -> PathBuf {
+    find_sidecar(dir).unwrap_or_else(|| dir.join(SIDECAR_FILE_NAME))
+}
+
+/// Whether `name` can't be used as a file or directory name on Windows.
+pub fn is_reserved_windows_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    name.ends_with(['.', ' '])
+        || RESERVED_WINDOWS_NAMES
+            .iter()
+            .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+}
+
+/// Rejects book ids that can't become a directory name on every platform the launcher
+/// runs on.
+pub fn check_book_id(id: &str) -> Result<(), NovelError> {
+    let invalid = id.is_empty()
+        || id.chars().any(|c| {
+            c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|')
+        })
+        || id == "."
+        || id == ".."
+        || is_reserved_windows_name(id);
+    if invalid {
+        return Err(NovelError::BadRequest(format!("Invalid book id: {id}")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    #[test]
+    fn relative_path_accepts_both_separators() {
+        let expected: PathBuf = ["OEBPS", "images", "cover.jpg"].iter().collect();
+        assert_eq!(
+            relative_path("/OEBPS/images/cover.jpg"),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            relative_path("OEBPS/images/cover.jpg"),
+            Some(expected.clone())
+        );
+        assert_eq!(
+            relative_path("\\OEBPS\\images\\cover.jpg"),
+            Some(expected.clone())
+        );
+        assert_eq!(relative_path("//OEBPS\\images/./cover.jpg"), Some(expected));
+    }
+
+    #[test]
+    fn relative_path_rejects_escapes() {
+        assert_eq!(relative_path(""), None);
+        assert_eq!(relative_path("/"), None);
+        assert_eq!(relative_path("images/../../secret"), None);
+        assert_eq!(relative_path("..\\secret"), None);
+        assert_eq!(relative_path("C:\\Windows\\win.ini"), None);
+    }
+
+    #[test]
+    fn sidecar_is_found_whatever_its_case() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let dir = std::env::temp_dir().join(format!("manatan-novel-server-sidecar-{nanos}"));
+        fs::create_dir_all(&dir).expect("temp dir should be created");
+
+        assert_eq!(find_sidecar(&dir), None);
+        assert_eq!(sidecar_path(&dir), dir.join("metadata.json"));
+        fs::write(dir.join("Metadata.JSON"), "{}").expect("sidecar should be written");
+        let found = find_sidecar(&dir).expect("sidecar should be found");
+        assert!(is_sidecar(&found));
+        assert_eq!(sidecar_path(&dir), found);
+        assert!(!is_sidecar(Path::new("metadata.json.bak")));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn reserved_windows_names_are_rejected() {
+        for id in [
+            "CON",
+            "con",
+            "nul.txt",
+            "Com1",
+            "lpt9.epub",
+            "novel.",
+            "novel ",
+        ] {
+            assert!(is_reserved_windows_name(id), "{id}");
+            assert!(check_book_id(id).is_err(), "{id}");
+        }
+        for id in ["novel_1700000000000_0", "console", "com10", "aux_notes"] {
+            assert!(!is_reserved_windows_name(id), "{id}");
+            assert!(check_book_id(id).is_ok(), "{id}");
+        }
+        for id in ["", "..", "a/b", "a\\b", "C:novel", "what?"] {
+            assert!(check_book_id(id).is_err(), "{id:?}");
+        }
+    }
+}
@@ -0,0 +1,474 @@
+//! Chunked saving of parsed books, for uploads too large to survive one JSON POST on a
+//! flaky connection.
+//!
+//! A client declares the book's shape with `begin`, uploads chapters and images one at a
+//! time (images may be split further with `Content-Range`), and `commit` swaps the result
+//! in once every declared piece has arrived. Pieces can be re-sent any number of times.
+//! Staged uploads live under `uploads/` in the storage dir and expire after
+//! [`UPLOAD_TTL`] without activity.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::{self, OpenOptions},
+    io::{Seek, SeekFrom, Write},
+    path::PathBuf,
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{Path as AxumPath, Query, State},
+    http::{HeaderMap, header::CONTENT_RANGE},
+};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+use walkdir::WalkDir;
+
+use super::store_content;
+use crate::{error::NovelError, paths, state::NovelState, types::LNParsedBook};
+
+/// Staged uploads untouched for this long are deleted.
+const UPLOAD_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// The shape of the book being uploaded, checked on every piece and on commit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadManifest {
+    #[serde(default)]
+    pub book_id: String,
+    pub chapter_count: usize,
+    #[serde(default)]
+    pub chapter_filenames: Vec<String>,
+    #[serde(default)]
+    pub css: Option<String>,
+    /// Image path to its size in bytes.
+    #[serde(default)]
+    pub images: BTreeMap<String, u64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BeginUploadResponse {
+    pub token: String,
+    pub expires_in_secs: u64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UploadTokenQuery {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageProgress {
+    pub received: u64,
+    pub complete: bool,
+}
+
+/// `POST /content/{id}/begin`: stages a new upload for the declared manifest.
+pub async fn begin_upload(
+    State(state): State<NovelState>,
+    AxumPath(id): AxumPath<String>,
+    Json(manifest): Json<UploadManifest>,
+) -> Result<Json<BeginUploadResponse>, NovelError> {
+    let token = begin(&state, &id, manifest)?;
+    Ok(Json(BeginUploadResponse {
+        token,
+        expires_in_secs: UPLOAD_TTL.as_secs(),
+    }))
+}
+
+/// `PUT /content/{id}/chapters/{index}?token=`: the chapter's HTML as the request body.
+pub async fn put_chapter(
+    State(state): State<NovelState>,
+    AxumPath((id, index)): AxumPath<(String, usize)>,
+    Query(query): Query<UploadTokenQuery>,
+    html: String,
+) -> Result<(), NovelError> {
+    write_chapter(&state, &id, &query.token, index, &html)
+}
+
+/// `PUT /content/{id}/images/{path}?token=`: raw image bytes, optionally one
+/// `Content-Range` slice of them.
+pub async fn put_image(
+    State(state): State<NovelState>,
+    AxumPath((id, path)): AxumPath<(String, String)>,
+    Query(query): Query<UploadTokenQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ImageProgress>, NovelError> {
+    let range = headers
+        .get(CONTENT_RANGE)
+        .map(|value| {
+            value
+                .to_str()
+                .ok()
+                .and_then(parse_content_range)
+                .ok_or_else(|| NovelError::BadRequest("invalid Content-Range header".into()))
+        })
+        .transpose()?;
+    write_image(&state, &id, &query.token, &path, range, &body).map(Json)
+}
+
+/// `POST /content/{id}/commit?token=`: validates the upload against its manifest and
+/// replaces the stored content.
+pub async fn commit_upload(
+    State(state): State<NovelState>,
+    AxumPath(id): AxumPath<String>,
+    Query(query): Query<UploadTokenQuery>,
+) -> Result<(), NovelError> {
+    let _book = state.book_locks.lock(&id).await?;
+    commit(&state, &id, &query.token)
+}
+
+/// A parsed `Content-Range: bytes start-end/total` header, `end` inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ContentRange {
+    start: u64,
+    end: u64,
+    total: u64,
+}
+
+fn parse_content_range(value: &str) -> Option<ContentRange> {
+    let (range, total) = value.trim().strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    let range = ContentRange {
+        start: start.trim().parse().ok()?,
+        end: end.trim().parse().ok()?,
+        total: total.trim().parse().ok()?,
+    };
+    (range.start <= range.end && range.end < range.total).then_some(range)
+}
+
+fn uploads_root(state: &NovelState) -> PathBuf {
+    state.storage_dir.join("uploads")
+}
+
+fn begin(state: &NovelState, id: &str, mut manifest: UploadManifest) -> Result<String, NovelError> {
+    paths::check_book_id(id)?;
+    if !manifest.chapter_filenames.is_empty()
+        && manifest.chapter_filenames.len() != manifest.chapter_count
+    {
+        return Err(NovelError::BadRequest(
+            "chapterFilenames must list every chapter".into(),
+        ));
+    }
+    for path in manifest.images.keys() {
+        image_relative_path(path)?;
+    }
+    expire_stale_uploads(state);
+    manatan_storage::ensure_space(&uploads_root(state), manifest.images.values().sum::<u64>())?;
+
+    manifest.book_id = id.to_string();
+    let token = uuid::Uuid::new_v4().to_string();
+    let dir = uploads_root(state).join(&token);
+    fs::create_dir_all(dir.join("chapters"))?;
+    fs::create_dir_all(dir.join("images"))?;
+    fs::write(dir.join(MANIFEST_FILE), serde_json::to_vec(&manifest)?)?;
+    info!(
+        "Started chunked content upload for {id}: {} chapters, {} images",
+        manifest.chapter_count,
+        manifest.images.len()
+    );
+    Ok(token)
+}
+
+/// Resolves a token to its staging directory and manifest, checking it belongs to `id`.
+fn open_upload(
+    state: &NovelState,
+    id: &str,
+    token: &str,
+) -> Result<(PathBuf, UploadManifest), NovelError> {
+    // Tokens become directory names, so only accept what `begin` hands out.
+    let token = uuid::Uuid::parse_str(token)
+        .map_err(|_| NovelError::BadRequest("invalid upload token".into()))?;
+    let dir = uploads_root(state).join(token.to_string());
+    let bytes = match fs::read(dir.join(MANIFEST_FILE)) {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err(NovelError::NotFound);
+        }
+        Err(err) => return Err(err.into()),
+    };
+    let manifest: UploadManifest = serde_json::from_slice(&bytes)?;
+    if manifest.book_id != id {
+        return Err(NovelError::BadRequest(
+            "upload token belongs to another book".into(),
+        ));
+    }
+    Ok((dir, manifest))
+}
+
+fn write_chapter(
+    state: &NovelState,
+    id: &str,
+    token: &str,
+    index: usize,
+    html: &str,
+) -> Result<(), NovelError> {
+    let (dir, manifest) = open_upload(state, id, token)?;
+    if index >= manifest.chapter_count {
+        return Err(NovelError::BadRequest(format!(
+            "chapter {index} is outside the declared {} chapters",
+            manifest.chapter_count
+        )));
+    }
+    fs::write(dir.join("chapters").join(format!("{index}.html")), html)?;
+    Ok(())
+}
+
+fn write_image(
+    state: &NovelState,
+    id: &str,
+    token: &str,
+    path: &str,
+    range: Option<ContentRange>,
+    body: &[u8],
+) -> Result<ImageProgress, NovelError> {
+    let (dir, manifest) = open_upload(state, id, token)?;
+    let (path, &size) = manifest
+        .images
+        .get_key_value(path)
+        .or_else(|| manifest.images.get_key_value(&format!("/{path}")))
+        .ok_or_else(|| NovelError::BadRequest(format!("image {path} is not in the manifest")))?;
+    let file_path = dir.join("images").join(image_relative_path(path)?);
+    if let Some(parent) = file_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let range = range.unwrap_or(ContentRange {
+        start: 0,
+        end: (body.len() as u64).saturating_sub(1),
+        total: body.len() as u64,
+    });
+    if range.total != size {
+        return Err(NovelError::BadRequest(format!(
+            "image {path} was declared as {size} bytes, not {}",
+            range.total
+        )));
+    }
+    if !body.is_empty() && range.end - range.start + 1 != body.len() as u64 {
+        return Err(NovelError::BadRequest(
+            "Content-Range does not match the body length".into(),
+        ));
+    }
+
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&file_path)?;
+    let received = file.metadata()?.len();
+    // Slices may be re-sent, but not skip ahead of what has already arrived.
+    if range.start > received {
+        return Err(NovelError::BadRequest(format!(
+            "image {path} has {received} bytes so far; resume from there"
+        )));
+    }
+    file.set_len(range.start)?;
+    file.seek(SeekFrom::Start(range.start))?;
+    file.write_all(body)?;
+    let received = range.start + body.len() as u64;
+    Ok(ImageProgress {
+        received,
+        complete: received == size,
+    })
+}
+
+fn commit(state: &NovelState, id: &str, token: &str) -> Result<(), NovelError> {
+    let (dir, manifest) = open_upload(state, id, token)?;
+
+    let mut chapters = Vec::with_capacity(manifest.chapter_count);
+    let mut missing_chapters = Vec::new();
+    for index in 0..manifest.chapter_count {
+        match fs::read_to_string(dir.join("chapters").join(format!("{index}.html"))) {
+            Ok(html) => chapters.push(html),
+            Err(_) => missing_chapters.push(index),
+        }
+    }
+
+    let mut image_blobs = HashMap::with_capacity(manifest.images.len());
+    let mut incomplete_images = Vec::new();
+    for (path, &size) in &manifest.images {
+        match fs::read(dir.join("images").join(image_relative_path(path)?)) {
+            Ok(bytes) if bytes.len() as u64 == size => {
+                image_blobs.insert(path.clone(), STANDARD.encode(bytes));
+            }
+            _ => incomplete_images.push(path.as_str()),
+        }
+    }
+
+    if !missing_chapters.is_empty() || !incomplete_images.is_empty() {
+        return Err(NovelError::BadRequest(format!(
+            "upload incomplete: missing chapters {missing_chapters:?}, incomplete images {incomplete_images:?}"
+        )));
+    }
+
+    let content = LNParsedBook {
+        chapters,
+        image_blobs,
+        chapter_filenames: manifest.chapter_filenames,
+        css: manifest.css,
+    };
+    store_content(state, id, &content)?;
+    if let Err(err) = fs::remove_dir_all(&dir) {
+        warn!("Failed to remove staged upload {}: {err}", dir.display());
+    }
+    info!("Committed chunked content upload for {id}");
+    Ok(())
+}
+
+/// Maps a manifest image path onto a relative path inside the staging directory,
+/// rejecting anything that could escape it.
+fn image_relative_path(path: &str) -> Result<PathBuf, NovelError> {
+    paths::relative_path(path)
+        .ok_or_else(|| NovelError::BadRequest(format!("invalid image path {path}")))
+}
+
+/// Deletes staged uploads with no file written for longer than [`UPLOAD_TTL`].
+fn expire_stale_uploads(state: &NovelState) {
+    let Ok(entries) = fs::read_dir(uploads_root(state)) else {
+        return;
+    };
+    let now = SystemTime::now();
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        let last_activity = WalkDir::new(&dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok()?.metadata().ok()?.modified().ok())
+            .max();
+        let stale = last_activity
+            .and_then(|modified| now.duration_since(modified).ok())
+            .is_none_or(|idle| idle > UPLOAD_TTL);
+        if stale {
+            info!("Removing abandoned content upload {}", dir.display());
+            if let Err(err) = fs::remove_dir_all(&dir) {
+                warn!("Failed to remove {}: {err}", dir.display());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_state(label: &str) -> (PathBuf, NovelState) {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let root = std::env::temp_dir().join(format!("manatan-novel-upload-{label}-{nanos}"));
+        let state = NovelState::new(root.join("data"), root.join("local-novel"));
+        (root, state)
+    }
+
+    #[test]
+    fn parses_content_range() {
+        assert_eq!(
+            parse_content_range("bytes 0-99/300"),
+            Some(ContentRange {
+                start: 0,
+                end: 99,
+                total: 300
+            })
+        );
+        assert_eq!(parse_content_range("bytes 100-99/300"), None);
+        assert_eq!(parse_content_range("bytes 0-300/300"), None);
+        assert_eq!(parse_content_range("items 0-1/2"), None);
+    }
+
+    #[test]
+    fn rejects_image_paths_outside_the_staging_dir() {
+        assert!(image_relative_path("/images/cover.jpg").is_ok());
+        assert!(image_relative_path("\\images\\cover.jpg").is_ok());
+        assert!(image_relative_path("../cover.jpg").is_err());
+        assert!(image_relative_path("..\\cover.jpg").is_err());
+        assert!(image_relative_path("").is_err());
+    }
+
+    #[test]
+    fn commits_only_once_every_piece_arrived() {
+        let (root, state) = temp_state("commit");
+        let manifest = UploadManifest {
+            book_id: String::new(),
+            chapter_count: 2,
+            chapter_filenames: vec!["a.xhtml".into(), "b.xhtml".into()],
+            css: None,
+            images: BTreeMap::from([("/img/cover.png".to_string(), 6)]),
+        };
+        let token = begin(&state, "book", manifest).expect("begin");
+
+        write_chapter(&state, "book", &token, 0, "<p>一</p>").expect("chapter 0");
+        assert!(write_chapter(&state, "book", &token, 2, "<p>x</p>").is_err());
+        assert!(write_chapter(&state, "other", &token, 1, "<p>x</p>").is_err());
+
+        let first = ContentRange {
+            start: 0,
+            end: 2,
+            total: 6,
+        };
+        let progress = write_image(&state, "book", &token, "img/cover.png", Some(first), b"abc")
+            .expect("first slice");
+        assert!(!progress.complete);
+        assert!(commit(&state, "book", &token).is_err());
+
+        let skipped = ContentRange {
+            start: 4,
+            end: 5,
+            total: 6,
+        };
+        assert!(
+            write_image(
+                &state,
+                "book",
+                &token,
+                "img/cover.png",
+                Some(skipped),
+                b"ef"
+            )
+            .is_err()
+        );
+        let second = ContentRange {
+            start: 3,
+            end: 5,
+            total: 6,
+        };
+        let progress = write_image(
+            &state,
+            "book",
+            &token,
+            "img/cover.png",
+            Some(second),
+            b"def",
+        )
+        .expect("second slice");
+        assert!(progress.complete);
+        write_chapter(&state, "book", &token, 1, "<p>二</p>").expect("chapter 1");
+
+        commit(&state, "book", &token).expect("commit");
+        let stored: LNParsedBook = serde_json::from_slice(
+            &state
+                .db
+                .get("content:book")
+                .expect("db read")
+                .expect("content stored"),
+        )
+        .expect("content json");
+        assert_eq!(stored.chapters, vec!["<p>一</p>", "<p>二</p>"]);
+        assert_eq!(
+            stored.image_blobs["/img/cover.png"],
+            STANDARD.encode(b"abcdef")
+        );
+        assert!(matches!(
+            commit(&state, "book", &token),
+            Err(NovelError::NotFound)
+        ));
+
+        drop(state);
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,129 @@
+//! `fields=` projection for book listings, so views such as the library grid only pay for
+//! the metadata they actually show.
+
+use crate::error::NovelError;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Every top-level `LNMetadata` key a listing can be projected to.
+const METADATA_FIELDS: &[&str] = &[
+    "id",
+    "title",
+    "author",
+    "cover",
+    "addedAt",
+    "isProcessing",
+    "isError",
+    "errorMsg",
+    "stats",
+    "chapterCount",
+    "toc",
+    "hasProgress",
+    "lastModified",
+    "syncVersion",
+    "language",
+    "categoryIds",
+    "languageSettings",
+    "rating",
+];
+
+/// Kept in every projection so clients can always key and label a book.
+const ALWAYS_INCLUDED: &[&str] = &["id", "title"];
+
+#[derive(Debug)]
+pub(super) struct FieldSelection(Vec<String>);
+
+impl FieldSelection {
+    /// Parses a comma-separated `fields` value. Returns `None` when no projection was
+    /// asked for, and rejects unknown names with the list of valid ones.
+    pub(super) fn parse(fields: Option<&str>) -> Result<Option<Self>, NovelError> {
+        let Some(fields) = fields else {
+            return Ok(None);
+        };
+
+        let mut selected: Vec<String> = ALWAYS_INCLUDED.iter().map(|f| f.to_string()).collect();
+        let mut unknown = Vec::new();
+        for field in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            if !METADATA_FIELDS.contains(&field) {
+                unknown.push(field);
+            } else if !selected.iter().any(|s| s == field) {
+                selected.push(field.to_string());
+            }
+        }
+        if !unknown.is_empty() {
+            return Err(NovelError::BadRequest(format!(
+                "Unknown fields: {}. Valid fields: {}",
+                unknown.join(", "),
+                METADATA_FIELDS.join(", ")
+            )));
+        }
+        Ok(Some(Self(selected)))
+    }
+
+    fn project(&self, value: Value) -> Value {
+        match value {
+            Value::Object(mut object) => {
+                let projected: Map<String, Value> = self
+                    .0
+                    .iter()
+                    .filter_map(|field| object.remove_entry(field))
+                    .collect();
+                Value::Object(projected)
+            }
+            other => other,
+        }
+    }
+}
+
+/// Serializes a listing, keeping only the selected fields of each item when a selection
+/// was given.
+pub(super) fn project_all<T: Serialize>(
+    items: &[T],
+    selection: Option<&FieldSelection>,
+) -> Result<Value, NovelError> {
+    let Some(selection) = selection else {
+        return Ok(serde_json::to_value(items)?);
+    };
+    let projected = items
+        .iter()
+        .map(|item| Ok(selection.project(serde_json::to_value(item)?)))
+        .collect::<Result<Vec<_>, NovelError>>()?;
+    Ok(Value::Array(projected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn projection_keeps_requested_fields_plus_id_and_title() {
+        let selection = FieldSelection::parse(Some("cover, rating"))
+            .expect("valid fields")
+            .expect("selection");
+        let items = [json!({
+            "id": "a",
+            "title": "A",
+            "author": "someone",
+            "cover": "cover.jpg",
+            "toc": [{ "label": "1" }],
+        })];
+
+        let projected = project_all(&items, Some(&selection)).expect("project");
+        assert_eq!(
+            projected,
+            json!([{ "id": "a", "title": "A", "cover": "cover.jpg" }])
+        );
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected_with_valid_list() {
+        let err = FieldSelection::parse(Some("title,blurb")).expect_err("unknown field");
+        let NovelError::BadRequest(message) = err else {
+            panic!("expected a bad request");
+        };
+        assert!(message.contains("blurb"));
+        assert!(message.contains("chapterCount"));
+        assert!(FieldSelection::parse(None).expect("no fields").is_none());
+    }
+}
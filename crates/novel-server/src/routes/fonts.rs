@@ -230,6 +230,10 @@ mod tests {
                 .expect("temporary db should open"),
             storage_dir,
             local_novel_path: PathBuf::new(),
+            pending_sidecars: Default::default(),
+            scans: manatan_jobs::WorkerPool::new("library-scan", 1, 1),
+            inbox_pass: Default::default(),
+            book_locks: Default::default(),
         }
     }
 
@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+use axum::{Json, extract::State};
+use manatan_sync_server::title_match::{self, CONFIDENT_MATCH};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use super::store_progress;
+use crate::{
+    error::NovelError,
+    state::NovelState,
+    types::{BookStats, LNMetadata, LNProgress},
+};
+
+/// One book from a ttu-reader bookmark export.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TtuBookmark {
+    pub title: String,
+    #[serde(default)]
+    pub explored_char_count: i32,
+    /// Fraction of the book read, `0.0..=1.0`.
+    #[serde(default)]
+    pub progress: Option<f64>,
+    #[serde(default)]
+    pub last_bookmark_modified: Option<i64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TtuImportRequest {
+    pub bookmarks: Vec<TtuBookmark>,
+    #[serde(default)]
+    pub confirm: bool,
+    /// ttu title to book id, as approved from the preview. `null` skips the title.
+    #[serde(default)]
+    pub matches: Option<HashMap<String, Option<String>>>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TtuImportEntry {
+    pub title: String,
+    pub book_id: Option<String>,
+    pub book_title: Option<String>,
+    pub score: f64,
+    pub confident: bool,
+    pub current_progress: Option<f64>,
+    pub imported_progress: f64,
+    /// Why the entry was (or would be) left alone, if it was.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skipped: Option<&'static str>,
+    pub applied: bool,
+}
+
+/// `POST /import/ttu-progress`: maps ttu-reader bookmarks onto library books by title.
+///
+/// Without `confirm` this only previews the matches. Confirming applies the approved
+/// `matches` (or, if omitted, only confident matches), and never moves a book's
+/// progress backwards.
+pub async fn import_ttu_progress(
+    State(state): State<NovelState>,
+    Json(req): Json<TtuImportRequest>,
+) -> Result<Json<Vec<TtuImportEntry>>, NovelError> {
+    let mut books = Vec::new();
+    for item in state.db.scan_prefix("metadata:") {
+        let (_, bytes) = item?;
+        books.push(serde_json::from_slice::<LNMetadata>(&bytes)?);
+    }
+
+    let mut entries = Vec::with_capacity(req.bookmarks.len());
+    for bookmark in &req.bookmarks {
+        let suggestion = title_match::best_match(
+            &bookmark.title,
+            books.iter().map(|book| (book.title.as_str(), book)),
+        );
+        let target = match &req.matches {
+            Some(matches) => matches
+                .get(&bookmark.title)
+                .cloned()
+                .flatten()
+                .and_then(|id| books.iter().find(|book| book.id == id)),
+            None => suggestion
+                .filter(|(_, score)| *score >= CONFIDENT_MATCH)
+                .map(|(book, _)| book),
+        };
+
+        let mut entry = TtuImportEntry {
+            title: bookmark.title.clone(),
+            book_id: suggestion.map(|(book, _)| book.id.clone()),
+            book_title: suggestion.map(|(book, _)| book.title.clone()),
+            score: suggestion.map_or(0.0, |(_, score)| score),
+            confident: suggestion.is_some_and(|(_, score)| score >= CONFIDENT_MATCH),
+            current_progress: None,
+            imported_progress: 0.0,
+            skipped: None,
+            applied: false,
+        };
+        let Some(book) = target else {
+            entry.skipped = Some("no approved match");
+            entries.push(entry);
+            continue;
+        };
+        entry.book_id = Some(book.id.clone());
+        entry.book_title = Some(book.title.clone());
+
+        let _book = state.book_locks.lock(&book.id).await?;
+        let current = match state.db.get(format!("progress:{}", book.id))? {
+            Some(bytes) => Some(serde_json::from_slice::<LNProgress>(&bytes)?),
+            None => None,
+        };
+        let imported = progress_from_ttu(bookmark, &book.stats);
+        entry.current_progress = current.as_ref().map(|progress| progress.total_progress);
+        entry.imported_progress = imported.total_progress;
+
+        if current
+            .as_ref()
+            .is_some_and(|current| current.total_progress >= imported.total_progress)
+        {
+            entry.skipped = Some("existing progress is further along");
+        } else if req.confirm {
+            // Keep anything the reader tracks that ttu has no notion of. The import is a
+            // change made here, so it is stamped with the server's clock; ttu's bookmark
+            // time would lose to any newer remote copy on the next sync merge.
+            let mut progress = match current {
+                Some(current) => LNProgress {
+                    highlights: current.highlights,
+                    ..imported
+                },
+                None => imported,
+            };
+            progress.last_modified = Some(chrono::Utc::now().timestamp_millis());
+            store_progress(&state, &book.id, &progress)?;
+            entry.applied = true;
+        }
+        entries.push(entry);
+    }
+
+    if req.confirm {
+        state.db.flush()?;
+        info!(
+            "Imported ttu progress for {} of {} books",
+            entries.iter().filter(|entry| entry.applied).count(),
+            entries.len()
+        );
+    }
+    Ok(Json(entries))
+}
+
+/// Converts a ttu position into reader progress. The read fraction is preferred over the
+/// raw character count because the two readers count characters slightly differently.
+fn progress_from_ttu(bookmark: &TtuBookmark, stats: &BookStats) -> LNProgress {
+    let total_length = stats.total_length.max(0);
+    let chars_read = match bookmark.progress {
+        Some(fraction) if total_length > 0 => {
+            (fraction.clamp(0.0, 1.0) * f64::from(total_length)).round() as i32
+        }
+        _ => bookmark.explored_char_count.max(0),
+    };
+
+    let mut chapter_index = 0;
+    let mut chapter_offset = chars_read;
+    for (index, length) in stats.chapter_lengths.iter().enumerate() {
+        chapter_index = index as i32;
+        if chapter_offset < *length || index + 1 == stats.chapter_lengths.len() {
+            break;
+        }
+        chapter_offset -= length;
+    }
+    let chapter_length = stats
+        .chapter_lengths
+        .get(chapter_index as usize)
+        .copied()
+        .unwrap_or(0);
+
+    LNProgress {
+        chapter_index,
+        chapter_char_offset: chapter_offset,
+        total_chars_read: chars_read,
+        chapter_progress: if chapter_length > 0 {
+            (f64::from(chapter_offset) / f64::from(chapter_length)).min(1.0)
+        } else {
+            0.0
+        },
+        total_progress: if total_length > 0 {
+            (f64::from(chars_read) / f64::from(total_length)).min(1.0)
+        } else {
+            bookmark.progress.unwrap_or(0.0).clamp(0.0, 1.0)
+        },
+        last_read: bookmark.last_bookmark_modified,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(chapter_lengths: Vec<i32>) -> BookStats {
+        BookStats {
+            total_length: chapter_lengths.iter().sum(),
+            chapter_lengths,
+            block_maps: None,
+        }
+    }
+
+    #[test]
+    fn maps_read_fraction_onto_chapters() {
+        let bookmark = TtuBookmark {
+            title: "本好きの下剋上".to_string(),
+            explored_char_count: 0,
+            progress: Some(0.5),
+            last_bookmark_modified: Some(1_700_000_000_000),
+        };
+        let progress = progress_from_ttu(&bookmark, &stats(vec![100, 200, 100]));
+        assert_eq!(progress.total_chars_read, 200);
+        assert_eq!(progress.chapter_index, 1);
+        assert_eq!(progress.chapter_char_offset, 100);
+        assert!((progress.chapter_progress - 0.5).abs() < f64::EPSILON);
+        assert!((progress.total_progress - 0.5).abs() < f64::EPSILON);
+        assert_eq!(progress.last_read, Some(1_700_000_000_000));
+    }
+
+    #[test]
+    fn falls_back_to_char_count_and_clamps_to_last_chapter() {
+        let bookmark = TtuBookmark {
+            title: "title".to_string(),
+            explored_char_count: 450,
+            progress: None,
+            last_bookmark_modified: None,
+        };
+        let progress = progress_from_ttu(&bookmark, &stats(vec![100, 200, 100]));
+        assert_eq!(progress.chapter_index, 2);
+        assert_eq!(progress.chapter_char_offset, 150);
+        assert!((progress.chapter_progress - 1.0).abs() < f64::EPSILON);
+        assert!((progress.total_progress - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn confirmed_import_is_stamped_with_the_server_time() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let root = std::env::temp_dir().join(format!("manatan-novel-ttu-import-{nanos}"));
+        let state = NovelState::new(root.join("data"), root.join("local-novel"));
+        let book: LNMetadata = serde_json::from_value(serde_json::json!({
+            "id": "honzuki",
+            "title": "本好きの下剋上",
+            "author": "",
+            "addedAt": 0,
+            "stats": { "chapterLengths": [100, 100], "totalLength": 200 },
+            "chapterCount": 2,
+            "toc": [],
+        }))
+        .expect("metadata should deserialize");
+        state
+            .db
+            .insert("metadata:honzuki", serde_json::to_vec(&book).expect("json"))
+            .expect("metadata insert should succeed");
+
+        let before = chrono::Utc::now().timestamp_millis();
+        let request = TtuImportRequest {
+            bookmarks: vec![TtuBookmark {
+                title: "本好きの下剋上 (ライトノベル)".to_string(),
+                explored_char_count: 0,
+                progress: Some(0.5),
+                last_bookmark_modified: Some(1_700_000_000_000),
+            }],
+            confirm: true,
+            matches: None,
+        };
+        let Json(entries) = import_ttu_progress(State(state.clone()), Json(request))
+            .await
+            .expect("import should succeed");
+        assert!(entries[0].applied);
+
+        let stored: LNProgress = serde_json::from_slice(
+            &state
+                .db
+                .get("progress:honzuki")
+                .expect("read")
+                .expect("stored progress"),
+        )
+        .expect("json");
+        assert_eq!(stored.last_read, Some(1_700_000_000_000));
+        let last_modified = stored.last_modified.expect("stamped");
+        assert!(last_modified >= before);
+        assert!(last_modified <= chrono::Utc::now().timestamp_millis());
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+}
@@ -0,0 +1,686 @@
+//! Automatic import of EPUBs dropped into `inbox/` inside the local novel folder.
+//!
+//! Each file is hashed and checked against the library, then filed as `{id}.epub` with a
+//! placeholder sidecar built from the EPUB's own metadata. The placeholder stays
+//! `isProcessing` until the reader parses the content, which `/discover` keeps offering
+//! it for. Duplicates and unreadable files are moved to `inbox/duplicates/` and
+//! `inbox/failed/` so they are not retried on every pass, and every outcome is kept for
+//! `GET /inbox/status`. A file that cannot be read or moved is logged and left for the
+//! next pass without holding up the others.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::PoisonError,
+    time::{Duration, SystemTime},
+};
+
+use axum::{Json, extract::State};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use image::{ImageFormat, imageops::FilterType};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::{info, warn};
+
+use super::write_metadata_sidecar;
+use crate::{
+    epub::{self, EpubInfo},
+    error::NovelError,
+    state::NovelState,
+    types::{BookStats, LNMetadata},
+};
+
+pub const INBOX_DIR_NAME: &str = "inbox";
+const DUPLICATES_DIR_NAME: &str = "duplicates";
+const FAILED_DIR_NAME: &str = "failed";
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// Files modified more recently than this may still be being copied in.
+const SETTLE_TIME: Duration = Duration::from_secs(10);
+const RESULT_PREFIX: &str = "inbox_result:";
+/// EPUB content hash to the book filed from it, and the reverse.
+const HASH_PREFIX: &str = "epub_hash:";
+const BOOK_HASH_PREFIX: &str = "epub_hash_of:";
+/// Placeholder covers are shrunk to fit this box, as library grids show them small.
+const COVER_WIDTH: u32 = 300;
+const COVER_HEIGHT: u32 = 450;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum InboxStatus {
+    Imported,
+    Duplicate,
+    Failed,
+}
+
+/// What happened to one inbox file or, in a preview, what would happen to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InboxResult {
+    pub file_name: String,
+    pub status: InboxStatus,
+    pub size: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub book_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// The library book with the same content, for duplicates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate_of: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub processed_at: i64,
+}
+
+/// `GET /inbox`: what a pass would do with the files in the inbox right now, without
+/// touching them.
+pub async fn preview_inbox(
+    State(state): State<NovelState>,
+) -> Result<Json<Vec<InboxResult>>, NovelError> {
+    let results = tokio::task::spawn_blocking(move || preview(&state))
+        .await
+        .map_err(|e| NovelError::Io(std::io::Error::other(e)))??;
+    Ok(Json(results))
+}
+
+/// `POST /inbox/ingest`: runs a pass now instead of waiting for the watcher.
+pub async fn ingest_inbox(
+    State(state): State<NovelState>,
+) -> Result<Json<Vec<InboxResult>>, NovelError> {
+    let results = tokio::task::spawn_blocking(move || ingest(&state, SETTLE_TIME))
+        .await
+        .map_err(|e| NovelError::Io(std::io::Error::other(e)))??;
+    Ok(Json(results))
+}
+
+/// `GET /inbox/status`: the latest outcome for every file the inbox has handled, newest
+/// first.
+pub async fn inbox_status(
+    State(state): State<NovelState>,
+) -> Result<Json<Vec<InboxResult>>, NovelError> {
+    let mut results = Vec::new();
+    for item in state.db.scan_prefix(RESULT_PREFIX) {
+        let (_, bytes) = item?;
+        results.push(serde_json::from_slice::<InboxResult>(&bytes)?);
+    }
+    results.sort_by(|a, b| b.processed_at.cmp(&a.processed_at));
+    Ok(Json(results))
+}
+
+/// Polls the inbox in the background while the server runs.
+pub(crate) fn spawn_watcher(state: &NovelState) {
+    if let Err(err) = fs::create_dir_all(inbox_dir(state)) {
+        warn!("Failed to create the novel inbox: {err}");
+    }
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            let pass = state.clone();
+            match tokio::task::spawn_blocking(move || ingest(&pass, SETTLE_TIME)).await {
+                Ok(Ok(results)) if !results.is_empty() => {
+                    info!("Novel inbox: handled {} files", results.len());
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => warn!("Novel inbox pass failed: {e:?}"),
+                Err(e) => warn!("Novel inbox pass panicked: {e}"),
+            }
+        }
+    });
+}
+
+fn inbox_dir(state: &NovelState) -> PathBuf {
+    state.get_local_novel_path().join(INBOX_DIR_NAME)
+}
+
+/// Files one pass handles, skipping those modified within `settle`.
+fn inbox_files(state: &NovelState, settle: Duration) -> Result<Vec<PathBuf>, NovelError> {
+    let dir = inbox_dir(state);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let now = SystemTime::now();
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_epub = path
+            .extension()
+            .and_then(|value| value.to_str())
+            .is_some_and(|value| value.eq_ignore_ascii_case("epub"));
+        if !path.is_file() || !is_epub {
+            continue;
+        }
+        let settled = fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .map(|modified| now.duration_since(modified).unwrap_or_default() >= settle)
+            .unwrap_or(false);
+        if settled {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+fn hash_bytes(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Records the content hash of library EPUBs filed before hashes were kept, so files
+/// the library already has are recognised.
+fn index_library_hashes(state: &NovelState) -> Result<(), NovelError> {
+    for item in state.db.scan_prefix("metadata:") {
+        let (key, _) = item?;
+        let id = String::from_utf8_lossy(&key["metadata:".len()..]).to_string();
+        if state.db.contains_key(format!("{BOOK_HASH_PREFIX}{id}"))? {
+            continue;
+        }
+        let Ok(bytes) = fs::read(state.get_epub_path(&id)) else {
+            continue;
+        };
+        record_hash(state, &id, &hash_bytes(&bytes))?;
+    }
+    Ok(())
+}
+
+fn record_hash(state: &NovelState, id: &str, hash: &str) -> Result<(), NovelError> {
+    state
+        .db
+        .insert(format!("{HASH_PREFIX}{hash}"), id.as_bytes())?;
+    state
+        .db
+        .insert(format!("{BOOK_HASH_PREFIX}{id}"), hash.as_bytes())?;
+    Ok(())
+}
+
+/// The library book with this content, if it is still in the library.
+fn library_book_with_hash(state: &NovelState, hash: &str) -> Result<Option<String>, NovelError> {
+    let Some(id) = state.db.get(format!("{HASH_PREFIX}{hash}"))? else {
+        return Ok(None);
+    };
+    let id = String::from_utf8_lossy(&id).to_string();
+    let exists =
+        state.db.contains_key(format!("metadata:{id}"))? || state.get_epub_path(&id).exists();
+    Ok(exists.then_some(id))
+}
+
+enum Verdict {
+    Import(EpubInfo),
+    Duplicate(String),
+    Failed(String),
+}
+
+fn examine(
+    state: &NovelState,
+    bytes: &[u8],
+    hash: &str,
+    seen: &HashMap<String, String>,
+) -> Result<Verdict, NovelError> {
+    if let Some(id) = seen.get(hash) {
+        return Ok(Verdict::Duplicate(id.clone()));
+    }
+    if let Some(id) = library_book_with_hash(state, hash)? {
+        return Ok(Verdict::Duplicate(id));
+    }
+    Ok(match epub::read_info(bytes) {
+        Ok(info) => Verdict::Import(info),
+        Err(err) => Verdict::Failed(err.to_string()),
+    })
+}
+
+fn preview(state: &NovelState) -> Result<Vec<InboxResult>, NovelError> {
+    index_library_hashes(state)?;
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut seen = HashMap::new();
+    let mut results = Vec::new();
+    for path in inbox_files(state, Duration::ZERO)? {
+        let bytes = match fs::read(&path) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                warn!("Novel inbox: skipping {}: {err}", path.display());
+                continue;
+            }
+        };
+        let hash = hash_bytes(&bytes);
+        let mut result = InboxResult {
+            file_name: file_name(&path),
+            status: InboxStatus::Imported,
+            size: bytes.len() as u64,
+            book_id: None,
+            title: None,
+            duplicate_of: None,
+            error: None,
+            processed_at: now,
+        };
+        match examine(state, &bytes, &hash, &seen)? {
+            Verdict::Import(info) => {
+                result.title = info.title;
+                seen.insert(hash, result.file_name.clone());
+            }
+            Verdict::Duplicate(id) => {
+                result.status = InboxStatus::Duplicate;
+                result.duplicate_of = Some(id);
+            }
+            Verdict::Failed(error) => {
+                result.status = InboxStatus::Failed;
+                result.error = Some(error);
+            }
+        }
+        results.push(result);
+    }
+    Ok(results)
+}
+
+/// Files every settled EPUB in the inbox and records what happened to each.
+pub(crate) fn ingest(state: &NovelState, settle: Duration) -> Result<Vec<InboxResult>, NovelError> {
+    let _pass = state
+        .inbox_pass
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    let files = inbox_files(state, settle)?;
+    if files.is_empty() {
+        return Ok(Vec::new());
+    }
+    index_library_hashes(state)?;
+
+    let mut seen = HashMap::new();
+    let mut taken_ids = HashSet::new();
+    let mut results = Vec::new();
+    for path in files {
+        let result = match ingest_file(state, &path, &mut seen, &mut taken_ids) {
+            Ok(result) => result,
+            Err(err) => {
+                warn!(
+                    "Novel inbox: skipping {} until the next pass: {err}",
+                    path.display()
+                );
+                continue;
+            }
+        };
+        state.db.insert(
+            format!("{RESULT_PREFIX}{}", result.file_name),
+            serde_json::to_vec(&result)?,
+        )?;
+        match result.status {
+            InboxStatus::Imported => info!(
+                "Novel inbox: imported {} as {}",
+                result.file_name,
+                result.book_id.as_deref().unwrap_or_default()
+            ),
+            InboxStatus::Duplicate => info!(
+                "Novel inbox: {} duplicates {}",
+                result.file_name,
+                result.duplicate_of.as_deref().unwrap_or_default()
+            ),
+            InboxStatus::Failed => warn!(
+                "Novel inbox: could not import {}: {}",
+                result.file_name,
+                result.error.as_deref().unwrap_or_default()
+            ),
+        }
+        results.push(result);
+    }
+    state.db.flush()?;
+    Ok(results)
+}
+
+fn ingest_file(
+    state: &NovelState,
+    path: &Path,
+    seen: &mut HashMap<String, String>,
+    taken_ids: &mut HashSet<String>,
+) -> Result<InboxResult, NovelError> {
+    let bytes = fs::read(path)?;
+    let hash = hash_bytes(&bytes);
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut result = InboxResult {
+        file_name: file_name(path),
+        status: InboxStatus::Imported,
+        size: bytes.len() as u64,
+        book_id: None,
+        title: None,
+        duplicate_of: None,
+        error: None,
+        processed_at: now,
+    };
+
+    let info = match examine(state, &bytes, &hash, seen)? {
+        Verdict::Import(info) => info,
+        Verdict::Duplicate(id) => {
+            set_aside(state, path, DUPLICATES_DIR_NAME)?;
+            result.status = InboxStatus::Duplicate;
+            result.duplicate_of = Some(id);
+            return Ok(result);
+        }
+        Verdict::Failed(error) => {
+            set_aside(state, path, FAILED_DIR_NAME)?;
+            result.status = InboxStatus::Failed;
+            result.error = Some(error);
+            return Ok(result);
+        }
+    };
+
+    let id = new_book_id(state, now, taken_ids)?;
+    move_file(path, &state.get_epub_path(&id))?;
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let metadata = placeholder_metadata(&id, &stem, info, now);
+    state
+        .db
+        .insert(format!("metadata:{id}"), serde_json::to_vec(&metadata)?)?;
+    write_metadata_sidecar(state, &id, &metadata)?;
+    record_hash(state, &id, &hash)?;
+    seen.insert(hash, id.clone());
+
+    result.title = Some(metadata.title);
+    result.book_id = Some(id);
+    Ok(result)
+}
+
+/// Library metadata from the EPUB itself, marked as processing until the reader has
+/// parsed the content.
+fn placeholder_metadata(id: &str, file_stem: &str, info: EpubInfo, now: i64) -> LNMetadata {
+    LNMetadata {
+        id: id.to_string(),
+        title: info.title.unwrap_or_else(|| file_stem.to_string()),
+        author: info.author.unwrap_or_default(),
+        cover: info.cover.and_then(|(_, bytes)| cover_thumbnail(&bytes)),
+        added_at: now,
+        is_processing: Some(true),
+        is_error: None,
+        error_msg: None,
+        stats: BookStats::default(),
+        chapter_count: 0,
+        toc: Vec::new(),
+        has_progress: None,
+        last_modified: Some(now),
+        sync_version: None,
+        language: info.language,
+        category_ids: Vec::new(),
+        language_settings: HashMap::new(),
+        rating: None,
+    }
+}
+
+/// A small JPEG data URL of the EPUB's cover. A cover that does not decode is left out;
+/// the reader sets one when it parses the book.
+fn cover_thumbnail(bytes: &[u8]) -> Option<String> {
+    let cover = image::load_from_memory(bytes).ok()?;
+    let thumbnail = if cover.width() > COVER_WIDTH || cover.height() > COVER_HEIGHT {
+        cover.resize(COVER_WIDTH, COVER_HEIGHT, FilterType::Triangle)
+    } else {
+        cover
+    };
+    let mut jpeg = Cursor::new(Vec::new());
+    thumbnail
+        .to_rgb8()
+        .write_to(&mut jpeg, ImageFormat::Jpeg)
+        .ok()?;
+    Some(format!(
+        "data:image/jpeg;base64,{}",
+        STANDARD.encode(jpeg.into_inner())
+    ))
+}
+
+/// An id in the reader's `novel_{millis}_{index}` form that no book uses yet.
+fn new_book_id(
+    state: &NovelState,
+    now: i64,
+    taken: &mut HashSet<String>,
+) -> Result<String, NovelError> {
+    for index in 0.. {
+        let id = format!("novel_{now}_{index}");
+        if !taken.contains(&id)
+            && !state.db.contains_key(format!("metadata:{id}"))?
+            && !state.get_epub_path(&id).exists()
+        {
+            taken.insert(id.clone());
+            return Ok(id);
+        }
+    }
+    unreachable!("ids are unbounded")
+}
+
+/// Moves a file out of the inbox into one of its subfolders, keeping any earlier file
+/// of the same name.
+fn set_aside(state: &NovelState, path: &Path, folder: &str) -> Result<(), NovelError> {
+    let dir = inbox_dir(state).join(folder);
+    fs::create_dir_all(&dir)?;
+    let name = file_name(path);
+    let mut target = dir.join(&name);
+    if target.exists() {
+        target = dir.join(format!("{}-{name}", chrono::Utc::now().timestamp_millis()));
+    }
+    move_file(path, &target)
+}
+
+/// Renames, falling back to copy and delete when the inbox is on another filesystem.
+fn move_file(from: &Path, to: &Path) -> Result<(), NovelError> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+
+    fn unique_temp_dir(label: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let dir = std::env::temp_dir().join(format!("manatan-novel-inbox-{label}-{nanos}"));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).expect("temp dir should be created");
+        dir
+    }
+
+    /// A PNG cover twice the size of the placeholder box.
+    fn cover_png() -> Vec<u8> {
+        let cover = image::RgbImage::from_pixel(
+            COVER_WIDTH * 2,
+            COVER_HEIGHT * 2,
+            image::Rgb([200, 40, 40]),
+        );
+        let mut png = Cursor::new(Vec::new());
+        cover
+            .write_to(&mut png, ImageFormat::Png)
+            .expect("encode cover");
+        png.into_inner()
+    }
+
+    fn build_epub(title: &str, extra: &[(&str, &[u8])]) -> Vec<u8> {
+        let opf = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+  <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+    <dc:title>{title}</dc:title>
+    <dc:creator id="author">香月 美夜</dc:creator>
+    <dc:language>ja</dc:language>
+    <meta name="cover" content="cover-img"/>
+  </metadata>
+  <manifest>
+    <item id="cover-img" href="images/cover%201.png" media-type="image/png"/>
+    <item id="c1" href="text/ch1.xhtml" media-type="application/xhtml+xml"/>
+  </manifest>
+</package>"#
+        );
+        let cover = cover_png();
+        let mut bytes = Vec::new();
+        {
+            let mut zip = ZipWriter::new(std::io::Cursor::new(&mut bytes));
+            let opts = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+            let mut entries: Vec<(&str, &[u8])> = vec![
+                ("mimetype", &b"application/epub+zip"[..]),
+                (
+                    "META-INF/container.xml",
+                    &br#"<container><rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles></container>"#[..],
+                ),
+                ("OEBPS/content.opf", opf.as_bytes()),
+                ("OEBPS/images/cover 1.png", &cover[..]),
+            ];
+            entries.extend_from_slice(extra);
+            for (name, contents) in entries {
+                zip.start_file(name, opts).expect("start file");
+                zip.write_all(contents).expect("write file");
+            }
+            zip.finish().expect("finish zip");
+        }
+        bytes
+    }
+
+    #[test]
+    fn ingests_new_books_and_sets_aside_duplicates_and_failures() {
+        let root = unique_temp_dir("ingest");
+        let local = root.join("local-novel");
+        let state = NovelState::new(root.join("data"), local.clone());
+        let inbox = local.join(INBOX_DIR_NAME);
+        fs::create_dir_all(&inbox).expect("inbox");
+
+        let book = build_epub("本好きの下剋上 &amp; 外伝", &[]);
+        fs::write(inbox.join("a.epub"), &book).expect("write");
+        fs::write(inbox.join("b.epub"), &book).expect("write");
+        fs::write(inbox.join("c.epub"), b"not a zip").expect("write");
+        fs::write(
+            inbox.join("d.epub"),
+            build_epub("Locked", &[("META-INF/rights.xml", &b"<rights/>"[..])]),
+        )
+        .expect("write");
+        fs::write(inbox.join("notes.txt"), b"left alone").expect("write");
+
+        let preview = preview(&state).expect("preview");
+        assert_eq!(preview.len(), 4);
+        assert!(inbox.join("a.epub").exists());
+        assert_eq!(preview[1].status, InboxStatus::Duplicate);
+
+        let results = ingest(&state, Duration::ZERO).expect("ingest");
+        let statuses: Vec<InboxStatus> = results.iter().map(|result| result.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                InboxStatus::Imported,
+                InboxStatus::Duplicate,
+                InboxStatus::Failed,
+                InboxStatus::Failed
+            ]
+        );
+        assert!(
+            results[3]
+                .error
+                .as_deref()
+                .is_some_and(|e| e.contains("DRM"))
+        );
+
+        let id = results[0].book_id.clone().expect("book id");
+        assert_eq!(results[1].duplicate_of.as_deref(), Some(id.as_str()));
+        assert!(state.get_epub_path(&id).exists());
+        let metadata: LNMetadata = serde_json::from_slice(
+            &state
+                .db
+                .get(format!("metadata:{id}"))
+                .expect("db")
+                .expect("metadata"),
+        )
+        .expect("json");
+        assert_eq!(metadata.title, "本好きの下剋上 & 外伝");
+        assert_eq!(metadata.author, "香月 美夜");
+        assert_eq!(metadata.language.as_deref(), Some("ja"));
+        let cover = metadata
+            .cover
+            .as_deref()
+            .and_then(|cover| cover.strip_prefix("data:image/jpeg;base64,"))
+            .expect("cover is a JPEG data URL");
+        let cover =
+            image::load_from_memory(&STANDARD.decode(cover).expect("base64")).expect("jpeg");
+        assert_eq!((cover.width(), cover.height()), (COVER_WIDTH, COVER_HEIGHT));
+        assert_eq!(metadata.is_processing, Some(true));
+        assert!(state.get_novel_dir(&id).join("metadata.json").exists());
+        let pending = super::super::discover_pending_epubs(&state).expect("discover");
+        assert!(
+            pending.iter().any(|epub| epub.id == id),
+            "placeholders are still offered to the reader for parsing"
+        );
+
+        let remaining: Vec<String> = fs::read_dir(&inbox)
+            .expect("inbox")
+            .flatten()
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(remaining, ["notes.txt"]);
+        assert!(inbox.join(DUPLICATES_DIR_NAME).join("b.epub").exists());
+        assert!(inbox.join(FAILED_DIR_NAME).join("c.epub").exists());
+
+        // The same book dropped in again later is still recognised.
+        fs::write(inbox.join("again.epub"), &book).expect("write");
+        let again = ingest(&state, Duration::ZERO).expect("ingest");
+        assert_eq!(again[0].status, InboxStatus::Duplicate);
+        assert_eq!(
+            state.db.scan_prefix(RESULT_PREFIX).count(),
+            5,
+            "every file's outcome is kept"
+        );
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn a_file_that_cannot_be_filed_does_not_stop_the_pass() {
+        let root = unique_temp_dir("io-error");
+        let local = root.join("local-novel");
+        let state = NovelState::new(root.join("data"), local.clone());
+        let inbox = local.join(INBOX_DIR_NAME);
+        fs::create_dir_all(&inbox).expect("inbox");
+        // A file where the failed folder should be, so setting a file aside fails.
+        fs::write(inbox.join(FAILED_DIR_NAME), b"in the way").expect("write");
+        fs::write(inbox.join("a-broken.epub"), b"not a zip").expect("write");
+        fs::write(inbox.join("b-book.epub"), build_epub("Book", &[])).expect("write");
+
+        let results = ingest(&state, Duration::ZERO).expect("ingest");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].file_name, "b-book.epub");
+        assert_eq!(results[0].status, InboxStatus::Imported);
+        assert!(
+            inbox.join("a-broken.epub").exists(),
+            "retried on the next pass"
+        );
+
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn unsettled_files_wait_for_the_next_pass() {
+        let root = unique_temp_dir("settle");
+        let local = root.join("local-novel");
+        let state = NovelState::new(root.join("data"), local.clone());
+        fs::create_dir_all(local.join(INBOX_DIR_NAME)).expect("inbox");
+        fs::write(
+            local.join(INBOX_DIR_NAME).join("copying.epub"),
+            build_epub("T", &[]),
+        )
+        .expect("write");
+
+        assert!(ingest(&state, SETTLE_TIME).expect("ingest").is_empty());
+        assert!(local.join(INBOX_DIR_NAME).join("copying.epub").exists());
+
+        let _ = fs::remove_dir_all(root);
+    }
+}
@@ -1,15 +1,28 @@
+mod content_upload;
+mod fields;
 mod fonts;
+mod import;
+mod inbox;
+mod plaintext;
+mod reader_settings;
+mod search;
 
 use crate::error::NovelError;
+use crate::paths;
 use crate::state::NovelState;
 use crate::types::*;
 use axum::{
     Json, Router,
-    extract::{Multipart, Path, State},
-    routing::{delete, get, post},
+    extract::{Multipart, Path, Query, State},
+    routing::{delete, get, post, put},
 };
-use std::collections::HashMap;
+use fields::{FieldSelection, project_all};
+use std::collections::{HashMap, HashSet};
 use std::fs;
+use std::time::Duration;
+use tracing::warn;
+
+pub(crate) use inbox::spawn_watcher as spawn_inbox_watcher;
 
 pub fn router() -> Router<NovelState> {
     Router::new()
@@ -20,18 +33,49 @@ pub fn router() -> Router<NovelState> {
         .route("/metadata/{id}", delete(delete_book))
         .route("/content/{id}", get(get_content))
         .route("/content/{id}", post(save_content))
+        .route("/content/{id}/plaintext", get(plaintext::get_plaintext))
+        .route("/content/{id}/begin", post(content_upload::begin_upload))
+        .route(
+            "/content/{id}/chapters/{index}",
+            put(content_upload::put_chapter),
+        )
+        .route(
+            "/content/{id}/images/{*path}",
+            put(content_upload::put_image),
+        )
+        .route("/content/{id}/commit", post(content_upload::commit_upload))
         .route("/progress/{id}", get(get_progress))
         .route("/progress/{id}", post(update_progress))
         .route("/categories", get(get_categories))
         .route("/categories", post(create_category))
         .route("/categories/{id}", post(update_category))
         .route("/categories/{id}", delete(delete_category))
+        .route(
+            "/categories/{id}/books",
+            get(get_category_books).post(update_category_books),
+        )
         .route("/categories/metadata", get(get_all_category_metadata))
         .route("/categories/metadata/{id}", get(get_category_metadata))
         .route("/categories/metadata/{id}", post(update_category_metadata))
+        .route("/preferences", get(get_preferences).put(update_preferences))
+        .route(
+            "/reader-settings",
+            get(reader_settings::get_default_settings)
+                .put(reader_settings::update_default_settings),
+        )
+        .route(
+            "/reader-settings/{id}",
+            get(reader_settings::get_book_settings).put(reader_settings::update_book_settings),
+        )
         .route("/fonts", get(fonts::list_fonts))
         .route("/fonts", post(fonts::save_font))
         .route("/fonts/{filename}", delete(fonts::delete_font))
+        .route("/import/ttu-progress", post(import::import_ttu_progress))
+        .route("/inbox", get(inbox::preview_inbox))
+        .route("/inbox/ingest", post(inbox::ingest_inbox))
+        .route("/inbox/status", get(inbox::inbox_status))
+        .route("/search/books", get(search::search_books))
+        .route("/search/highlights", get(search::search_highlights))
         .route("/upload/{id}", post(upload_epub))
         .route("/file/{id}", get(get_epub))
 }
@@ -66,7 +110,7 @@ fn discover_pending_epubs(state: &NovelState) -> Result<Vec<DiscoveredEpub>, Nov
         if id.trim().is_empty() {
             continue;
         }
-        if state.db.get(format!("metadata:{id}"))?.is_some() {
+        if is_parsed(state, id)? {
             continue;
         }
 
@@ -80,23 +124,151 @@ fn discover_pending_epubs(state: &NovelState) -> Result<Vec<DiscoveredEpub>, Nov
     Ok(discovered)
 }
 
+/// Whether the reader has parsed this book. Books filed from the inbox have placeholder
+/// metadata until it has, and are still offered by `/discover`.
+fn is_parsed(state: &NovelState, id: &str) -> Result<bool, NovelError> {
+    let Some(bytes) = state.db.get(format!("metadata:{id}"))? else {
+        return Ok(false);
+    };
+    let placeholder = serde_json::from_slice::<LNMetadata>(&bytes)
+        .is_ok_and(|metadata| metadata.is_processing == Some(true));
+    Ok(!placeholder || state.db.contains_key(format!("content:{id}"))?)
+}
+
 async fn discover_epubs(
     State(state): State<NovelState>,
 ) -> Result<Json<Vec<DiscoveredEpub>>, NovelError> {
     Ok(Json(discover_pending_epubs(&state)?))
 }
 
+const LIBRARY_PREFERENCES_KEY: &str = "library_preferences";
+
+fn load_library_preferences(state: &NovelState) -> Result<LnLibraryPreferences, NovelError> {
+    match state.db.get(LIBRARY_PREFERENCES_KEY)? {
+        Some(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        None => Ok(LnLibraryPreferences::default()),
+    }
+}
+
+/// Rejects sort keys `sort_library` does not know.
+fn check_sort(sort_by: &str) -> Result<(), NovelError> {
+    if matches!(sort_by, "addedAt" | "lastRead" | "rating" | "title") {
+        Ok(())
+    } else {
+        Err(NovelError::BadRequest(format!("Unknown sort: {}", sort_by)))
+    }
+}
+
+fn load_last_read_map(state: &NovelState) -> Result<HashMap<String, i64>, NovelError> {
+    let mut last_read = HashMap::new();
+    for item in state.db.scan_prefix("progress:") {
+        let (k, v) = item?;
+        let key_str = String::from_utf8_lossy(&k);
+        let id = key_str.strip_prefix("progress:").unwrap_or(&key_str);
+        let progress: LNProgress = serde_json::from_slice(&v)?;
+        if let Some(timestamp) = progress.last_read.or(progress.last_modified) {
+            last_read.insert(id.to_string(), timestamp);
+        }
+    }
+    Ok(last_read)
+}
+
+/// Sorts books for the library view. Unknown sort keys fall back to `addedAt`.
+/// Books without a rating or read timestamp always sink to the end.
+fn sort_library(
+    books: &mut [LNMetadata],
+    sort_by: &str,
+    sort_desc: bool,
+    last_read: &HashMap<String, i64>,
+) {
+    let directed = |ordering: std::cmp::Ordering| {
+        if sort_desc {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    };
+
+    match sort_by {
+        "lastRead" => books.sort_by(|a, b| match (last_read.get(&a.id), last_read.get(&b.id)) {
+            (Some(x), Some(y)) => directed(x.cmp(y)),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => b.added_at.cmp(&a.added_at),
+        }),
+        "rating" => books.sort_by(|a, b| match (a.rating, b.rating) {
+            (Some(x), Some(y)) => directed(x.total_cmp(&y)),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => b.added_at.cmp(&a.added_at),
+        }),
+        "title" => {
+            books.sort_by(|a, b| directed(a.title.to_lowercase().cmp(&b.title.to_lowercase())))
+        }
+        _ => books.sort_by(|a, b| directed(a.added_at.cmp(&b.added_at))),
+    }
+}
+
 async fn get_all_metadata(
     State(state): State<NovelState>,
-) -> Result<Json<Vec<LNMetadata>>, NovelError> {
+    Query(query): Query<MetadataListQuery>,
+) -> Result<Json<serde_json::Value>, NovelError> {
+    let selection = FieldSelection::parse(query.fields.as_deref())?;
     let mut all_metadata = Vec::new();
     for item in state.db.scan_prefix("metadata:") {
         let (_, v) = item?;
         let metadata: LNMetadata = serde_json::from_slice(&v)?;
-        all_metadata.push(metadata);
+        if query
+            .category
+            .as_ref()
+            .is_none_or(|id| metadata.category_ids.contains(id))
+        {
+            all_metadata.push(metadata);
+        }
     }
-    all_metadata.sort_by(|a, b| b.added_at.cmp(&a.added_at));
-    Ok(Json(all_metadata))
+
+    // A category's own sort beats the library-wide preference.
+    let category_metadata = match &query.category {
+        Some(id) => load_category_metadata(&state, id)?,
+        None => None,
+    };
+    let (default_sort_by, default_sort_desc) = match category_metadata {
+        Some(metadata) => (metadata.sort_by, metadata.sort_desc),
+        None => {
+            let preferences = load_library_preferences(&state)?;
+            (preferences.sort_by, preferences.sort_desc)
+        }
+    };
+    let sort_by = query.sort_by.unwrap_or(default_sort_by);
+    let sort_desc = query.sort_desc.unwrap_or(default_sort_desc);
+    let last_read = if sort_by == "lastRead" {
+        load_last_read_map(&state)?
+    } else {
+        HashMap::new()
+    };
+    sort_library(&mut all_metadata, &sort_by, sort_desc, &last_read);
+    Ok(Json(project_all(&all_metadata, selection.as_ref())?))
+}
+
+async fn get_preferences(
+    State(state): State<NovelState>,
+) -> Result<Json<LnLibraryPreferences>, NovelError> {
+    Ok(Json(load_library_preferences(&state)?))
+}
+
+async fn update_preferences(
+    State(state): State<NovelState>,
+    Json(mut preferences): Json<LnLibraryPreferences>,
+) -> Result<Json<LnLibraryPreferences>, NovelError> {
+    check_sort(&preferences.sort_by)?;
+    // Stamped here rather than trusted from the client, whose clock decides sync merges.
+    preferences.last_modified = chrono::Utc::now().timestamp_millis();
+
+    state
+        .db
+        .insert(LIBRARY_PREFERENCES_KEY, serde_json::to_vec(&preferences)?)?;
+    state.db.flush()?;
+    Ok(Json(preferences))
 }
 
 async fn get_metadata(
@@ -114,14 +286,26 @@ async fn update_metadata(
     Path(id): Path<String>,
     Json(req): Json<UpdateMetadataRequest>,
 ) -> Result<(), NovelError> {
+    paths::check_book_id(&id)?;
+    let _book = state.book_locks.lock(&id).await?;
     let key = format!("metadata:{}", id);
     let bytes = serde_json::to_vec(&req.metadata)?;
     state.db.insert(key, bytes)?;
 
-    // Sidecar save
-    let novel_dir = state.get_novel_dir(&id);
+    write_metadata_sidecar(&state, &id, &req.metadata)?;
+
+    state.db.flush()?;
+    Ok(())
+}
+
+fn write_metadata_sidecar(
+    state: &NovelState,
+    id: &str,
+    metadata: &LNMetadata,
+) -> Result<(), NovelError> {
+    let novel_dir = state.get_novel_dir(id);
     fs::create_dir_all(&novel_dir)?;
-    let sidecar_path = novel_dir.join("metadata.json");
+    let sidecar_path = paths::sidecar_path(&novel_dir);
 
     let mut sidecar_data = if sidecar_path.exists() {
         let content = fs::read_to_string(&sidecar_path)?;
@@ -130,20 +314,64 @@ async fn update_metadata(
         serde_json::json!({})
     };
 
-    sidecar_data["metadata"] = serde_json::to_value(&req.metadata)?;
+    sidecar_data["metadata"] = serde_json::to_value(metadata)?;
     fs::write(sidecar_path, serde_json::to_string_pretty(&sidecar_data)?)?;
-
-    state.db.flush()?;
     Ok(())
 }
 
+const SIDECAR_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Queues metadata sidecar writes so bulk edits rewrite each book's file once,
+/// using whatever is in the database when the debounce window closes.
+fn schedule_metadata_sidecars(state: &NovelState, ids: impl IntoIterator<Item = String>) {
+    let Ok(mut pending) = state.pending_sidecars.lock() else {
+        return;
+    };
+    let was_idle = pending.is_empty();
+    pending.extend(ids);
+    if !was_idle || pending.is_empty() {
+        return;
+    }
+    drop(pending);
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(SIDECAR_DEBOUNCE).await;
+        let ids: Vec<String> = match state.pending_sidecars.lock() {
+            Ok(mut pending) => pending.drain().collect(),
+            Err(_) => return,
+        };
+        for id in ids {
+            let _book = match state.book_locks.lock(&id).await {
+                Ok(guard) => guard,
+                Err(e) => {
+                    warn!("Skipped metadata sidecar for {}: {:?}", id, e);
+                    continue;
+                }
+            };
+            let result = match state.db.get(format!("metadata:{id}")) {
+                Ok(Some(bytes)) => serde_json::from_slice::<LNMetadata>(&bytes)
+                    .map_err(NovelError::from)
+                    .and_then(|metadata| write_metadata_sidecar(&state, &id, &metadata)),
+                Ok(None) => Ok(()),
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = result {
+                warn!("Failed to write metadata sidecar for {}: {:?}", id, e);
+            }
+        }
+    });
+}
+
 async fn delete_book(
     State(state): State<NovelState>,
     Path(id): Path<String>,
 ) -> Result<(), NovelError> {
+    let _book = state.book_locks.lock(&id).await?;
     state.db.remove(format!("metadata:{}", id))?;
     state.db.remove(format!("progress:{}", id))?;
     state.db.remove(format!("content:{}", id))?;
+    state.db.remove(reader_settings::book_settings_key(&id))?;
 
     let novel_dir = state.get_novel_dir(&id);
     if novel_dir.exists() {
@@ -183,48 +411,42 @@ async fn save_content(
     Path(id): Path<String>,
     Json(content): Json<LNParsedBook>,
 ) -> Result<(), NovelError> {
-    let key = format!("content:{}", id);
-
-    // Save to DB for sync compatibility
-    let bytes = serde_json::to_vec(&content)?;
-    state.db.insert(key, bytes)?;
+    paths::check_book_id(&id)?;
+    let _book = state.book_locks.lock(&id).await?;
+    store_content(&state, &id, &content)
+}
 
+/// Replaces a book's stored content. The extracted chapters and images are written to a
+/// scratch directory first and swapped in once complete, so a failed save leaves the
+/// previous content readable. Callers hold the book's lock.
+pub(super) fn store_content(
+    state: &NovelState,
+    id: &str,
+    content: &LNParsedBook,
+) -> Result<(), NovelError> {
     // Novel directory structure
-    let novel_dir = state.get_novel_dir(&id);
+    let novel_dir = state.get_novel_dir(id);
+    manatan_storage::ensure_space(&novel_dir, content_footprint(content))?;
     fs::create_dir_all(&novel_dir)?;
 
-    // Sidecar save for portability
-    let sidecar_path = novel_dir.join("metadata.json");
-    let mut sidecar_data = if sidecar_path.exists() {
-        let content = fs::read_to_string(&sidecar_path)?;
-        serde_json::from_str::<serde_json::Value>(&content).unwrap_or(serde_json::json!({}))
-    } else {
-        serde_json::json!({})
-    };
-
-    sidecar_data["content"] = serde_json::to_value(&content)?;
-    fs::write(sidecar_path, serde_json::to_string_pretty(&sidecar_data)?)?;
-
     // Static extraction for speed
     let extracted_dir = novel_dir.join("extracted");
-    if extracted_dir.exists() {
-        fs::remove_dir_all(&extracted_dir)?;
+    let staged_dir = novel_dir.join("extracted.partial");
+    if staged_dir.exists() {
+        fs::remove_dir_all(&staged_dir)?;
     }
-    fs::create_dir_all(&extracted_dir)?;
+    fs::create_dir_all(&staged_dir)?;
 
     // Save images as files
-    let img_dir = extracted_dir.join("images");
+    let img_dir = staged_dir.join("images");
     fs::create_dir_all(&img_dir)?;
-    for (path, base64) in content.image_blobs {
-        let data = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, &base64)
+    for (path, base64) in &content.image_blobs {
+        let data = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, base64)
             .map_err(|e| NovelError::BadRequest(format!("Invalid base64 image: {}", e)))?;
 
-        let normalized_path = if path.starts_with('/') {
-            &path[1..]
-        } else {
-            &path
-        };
-        let img_path = img_dir.join(normalized_path);
+        let relative = paths::relative_path(path)
+            .ok_or_else(|| NovelError::BadRequest(format!("Invalid image path: {path}")))?;
+        let img_path = img_dir.join(relative);
         if let Some(parent) = img_path.parent() {
             fs::create_dir_all(parent)?;
         }
@@ -232,17 +454,50 @@ async fn save_content(
     }
 
     // Save chapters as HTML files
-    let chapter_dir = extracted_dir.join("chapters");
+    let chapter_dir = staged_dir.join("chapters");
     fs::create_dir_all(&chapter_dir)?;
     for (i, html) in content.chapters.iter().enumerate() {
         let chapter_path = chapter_dir.join(format!("{}.html", i));
         fs::write(chapter_path, html)?;
     }
 
+    if extracted_dir.exists() {
+        fs::remove_dir_all(&extracted_dir)?;
+    }
+    fs::rename(&staged_dir, &extracted_dir)?;
+
+    // Save to DB for sync compatibility
+    let bytes = serde_json::to_vec(content)?;
+    state.db.insert(format!("content:{}", id), bytes)?;
+
+    // Sidecar save for portability
+    let sidecar_path = paths::sidecar_path(&novel_dir);
+    let mut sidecar_data = if sidecar_path.exists() {
+        let content = fs::read_to_string(&sidecar_path)?;
+        serde_json::from_str::<serde_json::Value>(&content).unwrap_or(serde_json::json!({}))
+    } else {
+        serde_json::json!({})
+    };
+
+    sidecar_data["content"] = serde_json::to_value(content)?;
+    fs::write(sidecar_path, serde_json::to_string_pretty(&sidecar_data)?)?;
+
     state.db.flush()?;
     Ok(())
 }
 
+/// Rough bytes [`store_content`] writes: the extracted files plus the JSON copies in the
+/// database and the sidecar, which keep the images base64 encoded.
+fn content_footprint(content: &LNParsedBook) -> u64 {
+    let images: u64 = content
+        .image_blobs
+        .values()
+        .map(|base64| base64.len() as u64)
+        .sum();
+    let chapters: u64 = content.chapters.iter().map(|html| html.len() as u64).sum();
+    images / 4 * 3 + chapters + 2 * (images + chapters)
+}
+
 async fn get_progress(
     State(state): State<NovelState>,
     Path(id): Path<String>,
@@ -262,14 +517,21 @@ async fn update_progress(
     Path(id): Path<String>,
     Json(req): Json<UpdateProgressRequest>,
 ) -> Result<(), NovelError> {
+    let _book = state.book_locks.lock(&id).await?;
+    store_progress(&state, &id, &req.progress)
+}
+
+/// Persists progress to the database and the book's sidecar. Callers hold the book's
+/// lock.
+fn store_progress(state: &NovelState, id: &str, progress: &LNProgress) -> Result<(), NovelError> {
     let key = format!("progress:{}", id);
-    let bytes = serde_json::to_vec(&req.progress)?;
+    let bytes = serde_json::to_vec(progress)?;
     state.db.insert(key, bytes)?;
 
     // Sidecar save
-    let novel_dir = state.get_novel_dir(&id);
+    let novel_dir = state.get_novel_dir(id);
     fs::create_dir_all(&novel_dir)?;
-    let sidecar_path = novel_dir.join("metadata.json");
+    let sidecar_path = paths::sidecar_path(&novel_dir);
 
     let mut sidecar_data = if sidecar_path.exists() {
         let content = fs::read_to_string(&sidecar_path)?;
@@ -278,35 +540,72 @@ async fn update_progress(
         serde_json::json!({})
     };
 
-    sidecar_data["progress"] = serde_json::to_value(&req.progress)?;
+    sidecar_data["progress"] = serde_json::to_value(progress)?;
     fs::write(sidecar_path, serde_json::to_string_pretty(&sidecar_data)?)?;
 
     state.db.flush()?;
     Ok(())
 }
 
-async fn get_categories(
-    State(state): State<NovelState>,
-) -> Result<Json<Vec<LnCategory>>, NovelError> {
-    let mut categories = Vec::new();
+/// Categories in display order with their metadata inlined. Hidden categories are left
+/// out unless `include_hidden` is set.
+fn list_categories(
+    state: &NovelState,
+    include_hidden: bool,
+) -> Result<Vec<CategoryListing>, NovelError> {
+    let mut meta_map = load_all_category_metadata(state)?;
+    let mut listings = Vec::new();
     for item in state.db.scan_prefix("category:") {
         let (_, v) = item?;
         let category: LnCategory = serde_json::from_slice(&v)?;
-        categories.push(category);
+        let metadata = meta_map.remove(&category.id);
+        if !include_hidden && metadata.as_ref().is_some_and(|m| m.hidden) {
+            continue;
+        }
+        let cover_url = match metadata.as_ref().and_then(|m| m.cover.as_ref()) {
+            Some(cover) => resolve_category_cover(state, cover)?,
+            None => None,
+        };
+        listings.push(CategoryListing {
+            category,
+            metadata,
+            cover_url,
+        });
     }
-    categories.sort_by(|a, b| a.order.cmp(&b.order));
-    Ok(Json(categories))
+    listings.sort_by(|a, b| a.category.order.cmp(&b.category.order));
+    Ok(listings)
 }
 
-async fn save_global_categories(state: &NovelState) -> Result<(), NovelError> {
-    let mut categories = Vec::new();
-    for item in state.db.scan_prefix("category:") {
-        let (_, v) = item?;
-        let category: LnCategory = serde_json::from_slice(&v)?;
-        categories.push(category);
+/// A category cover naming a book resolves to that book's cover; anything else is taken
+/// to be an image URL already.
+fn resolve_category_cover(state: &NovelState, cover: &str) -> Result<Option<String>, NovelError> {
+    match state.db.get(format!("metadata:{}", cover))? {
+        Some(bytes) => Ok(serde_json::from_slice::<LNMetadata>(&bytes)?.cover),
+        None => Ok(Some(cover.to_string())),
     }
+}
+
+async fn get_categories(
+    State(state): State<NovelState>,
+    Query(query): Query<CategoryListQuery>,
+) -> Result<Json<Vec<CategoryListing>>, NovelError> {
+    Ok(Json(list_categories(&state, query.include_hidden)?))
+}
 
-    let mut meta_map = HashMap::new();
+fn load_category_metadata(
+    state: &NovelState,
+    id: &str,
+) -> Result<Option<LnCategoryMetadata>, NovelError> {
+    match state.db.get(format!("category_metadata:{}", id))? {
+        Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
+fn load_all_category_metadata(
+    state: &NovelState,
+) -> Result<HashMap<String, LnCategoryMetadata>, NovelError> {
+    let mut map = HashMap::new();
     for item in state.db.scan_prefix("category_metadata:") {
         let (k, v) = item?;
         let key_str = String::from_utf8_lossy(&k);
@@ -315,8 +614,20 @@ async fn save_global_categories(state: &NovelState) -> Result<(), NovelError> {
             .unwrap_or(&key_str)
             .to_string();
         let meta: LnCategoryMetadata = serde_json::from_slice(&v)?;
-        meta_map.insert(id, meta);
+        map.insert(id, meta);
     }
+    Ok(map)
+}
+
+async fn save_global_categories(state: &NovelState) -> Result<(), NovelError> {
+    let mut categories = Vec::new();
+    for item in state.db.scan_prefix("category:") {
+        let (_, v) = item?;
+        let category: LnCategory = serde_json::from_slice(&v)?;
+        categories.push(category);
+    }
+
+    let meta_map = load_all_category_metadata(state)?;
 
     let local_path = state.get_local_novel_path();
     fs::create_dir_all(&local_path)?;
@@ -378,42 +689,171 @@ async fn delete_category(
     Ok(())
 }
 
+/// Applies membership changes for one category in a single pass. Changed books get a
+/// bumped sync version so the per-book sync merge keeps edits made to different books
+/// on different devices.
+fn apply_category_membership(
+    state: &NovelState,
+    category_id: &str,
+    req: &CategoryBooksRequest,
+) -> Result<Vec<CategoryBookResult>, NovelError> {
+    let remove: HashSet<&String> = req.remove.iter().collect();
+    if let Some(id) = req.add.iter().find(|id| remove.contains(id)) {
+        return Err(NovelError::BadRequest(format!(
+            "Book {} is in both add and remove",
+            id
+        )));
+    }
+
+    let now = chrono::Utc::now().timestamp_millis();
+    let mut results = Vec::with_capacity(req.add.len() + req.remove.len());
+    let changes = req
+        .add
+        .iter()
+        .map(|id| (id, true))
+        .chain(req.remove.iter().map(|id| (id, false)));
+
+    for (id, add) in changes {
+        let key = format!("metadata:{}", id);
+        let Some(bytes) = state.db.get(&key)? else {
+            results.push(CategoryBookResult {
+                id: id.clone(),
+                status: CategoryBookStatus::NotFound,
+                category_ids: Vec::new(),
+            });
+            continue;
+        };
+        let mut metadata: LNMetadata = serde_json::from_slice(&bytes)?;
+        let is_member = metadata.category_ids.iter().any(|cid| cid == category_id);
+
+        let status = match (add, is_member) {
+            (true, false) => {
+                metadata.category_ids.push(category_id.to_string());
+                CategoryBookStatus::Added
+            }
+            (false, true) => {
+                metadata.category_ids.retain(|cid| cid != category_id);
+                CategoryBookStatus::Removed
+            }
+            _ => CategoryBookStatus::Unchanged,
+        };
+
+        if status != CategoryBookStatus::Unchanged {
+            metadata.sync_version = Some(metadata.sync_version.unwrap_or(0) + 1);
+            metadata.last_modified = Some(now);
+            state.db.insert(key, serde_json::to_vec(&metadata)?)?;
+        }
+
+        results.push(CategoryBookResult {
+            id: id.clone(),
+            status,
+            category_ids: metadata.category_ids,
+        });
+    }
+
+    Ok(results)
+}
+
+/// Metadata of every book in a category, newest first.
+fn category_member_metadata(
+    state: &NovelState,
+    category_id: &str,
+) -> Result<Vec<LNMetadata>, NovelError> {
+    let mut members = Vec::new();
+    for item in state.db.scan_prefix("metadata:") {
+        let (_, v) = item?;
+        let metadata: LNMetadata = serde_json::from_slice(&v)?;
+        if metadata.category_ids.iter().any(|cid| cid == category_id) {
+            members.push(metadata);
+        }
+    }
+    members.sort_by(|a, b| b.added_at.cmp(&a.added_at));
+    Ok(members)
+}
+
+fn category_members(
+    state: &NovelState,
+    category_id: &str,
+) -> Result<Vec<CategoryBookSummary>, NovelError> {
+    Ok(category_member_metadata(state, category_id)?
+        .into_iter()
+        .map(|metadata| CategoryBookSummary {
+            id: metadata.id,
+            title: metadata.title,
+            author: metadata.author,
+            cover: metadata.cover,
+            added_at: metadata.added_at,
+        })
+        .collect())
+}
+
+/// Lists a category's books as summaries, or as metadata projected to `fields` when
+/// that is given.
+async fn get_category_books(
+    State(state): State<NovelState>,
+    Path(id): Path<String>,
+    Query(query): Query<FieldsQuery>,
+) -> Result<Json<serde_json::Value>, NovelError> {
+    match FieldSelection::parse(query.fields.as_deref())? {
+        Some(selection) => {
+            let members = category_member_metadata(&state, &id)?;
+            Ok(Json(project_all(&members, Some(&selection))?))
+        }
+        None => Ok(Json(serde_json::to_value(category_members(&state, &id)?)?)),
+    }
+}
+
+async fn update_category_books(
+    State(state): State<NovelState>,
+    Path(id): Path<String>,
+    Json(req): Json<CategoryBooksRequest>,
+) -> Result<Json<Vec<CategoryBookResult>>, NovelError> {
+    if state.db.get(format!("category:{}", id))?.is_none() {
+        return Err(NovelError::NotFound);
+    }
+
+    let _books = state
+        .book_locks
+        .lock_all(req.add.iter().chain(&req.remove))
+        .await?;
+    let results = apply_category_membership(&state, &id, &req)?;
+    schedule_metadata_sidecars(
+        &state,
+        results
+            .iter()
+            .filter(|r| {
+                matches!(
+                    r.status,
+                    CategoryBookStatus::Added | CategoryBookStatus::Removed
+                )
+            })
+            .map(|r| r.id.clone()),
+    );
+
+    state.db.flush()?;
+    Ok(Json(results))
+}
+
 async fn get_all_category_metadata(
     State(state): State<NovelState>,
 ) -> Result<Json<HashMap<String, LnCategoryMetadata>>, NovelError> {
-    let mut map = HashMap::new();
-    for item in state.db.scan_prefix("category_metadata:") {
-        let (k, v) = item?;
-        let key_str = String::from_utf8_lossy(&k);
-        let id = key_str
-            .strip_prefix("category_metadata:")
-            .unwrap_or(&key_str)
-            .to_string();
-        let meta: LnCategoryMetadata = serde_json::from_slice(&v)?;
-        map.insert(id, meta);
-    }
-    Ok(Json(map))
+    Ok(Json(load_all_category_metadata(&state)?))
 }
 
 async fn get_category_metadata(
     State(state): State<NovelState>,
     Path(id): Path<String>,
 ) -> Result<Json<Option<LnCategoryMetadata>>, NovelError> {
-    let key = format!("category_metadata:{}", id);
-    let v = state.db.get(key)?;
-    if let Some(bytes) = v {
-        let meta: LnCategoryMetadata = serde_json::from_slice(&bytes)?;
-        Ok(Json(Some(meta)))
-    } else {
-        Ok(Json(None))
-    }
+    Ok(Json(load_category_metadata(&state, &id)?))
 }
 
 async fn update_category_metadata(
     State(state): State<NovelState>,
     Path(id): Path<String>,
-    Json(meta): Json<LnCategoryMetadata>,
+    Json(mut meta): Json<LnCategoryMetadata>,
 ) -> Result<(), NovelError> {
+    check_sort(&meta.sort_by)?;
+    meta.last_modified = chrono::Utc::now().timestamp_millis();
     let key = format!("category_metadata:{}", id);
     let bytes = serde_json::to_vec(&meta)?;
     state.db.insert(key, bytes)?;
@@ -432,6 +872,7 @@ async fn upload_epub(
             if name == "file" {
                 let data = field.bytes().await?;
                 let path = state.get_epub_path(&id);
+                manatan_storage::ensure_space(&path, data.len() as u64)?;
                 if let Some(parent) = path.parent() {
                     fs::create_dir_all(parent)?;
                 }
@@ -522,6 +963,266 @@ mod tests {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_writes_to_one_book_keep_every_sidecar_section() {
+        let root = unique_temp_dir("book-lock");
+        let state = NovelState::new(root.join("data"), root.join("local-novel"));
+        let id = "shared".to_string();
+
+        let mut writes = Vec::new();
+        for round in 0..25 {
+            let (state, id) = (state.clone(), id.clone());
+            writes.push(tokio::spawn(async move {
+                let progress = LNProgress {
+                    chapter_index: round,
+                    ..Default::default()
+                };
+                update_progress(
+                    State(state),
+                    Path(id),
+                    Json(UpdateProgressRequest { progress }),
+                )
+                .await
+            }));
+            let (state, id) = (state.clone(), id.clone());
+            writes.push(tokio::spawn(async move {
+                let metadata = book(&id, i64::from(round), None);
+                update_metadata(
+                    State(state),
+                    Path(id),
+                    Json(UpdateMetadataRequest { metadata }),
+                )
+                .await
+            }));
+        }
+        for write in writes {
+            write
+                .await
+                .expect("write task should finish")
+                .expect("write should succeed");
+        }
+
+        let sidecar = fs::read_to_string(state.get_novel_dir(&id).join("metadata.json"))
+            .expect("sidecar should exist");
+        let sidecar: serde_json::Value =
+            serde_json::from_str(&sidecar).expect("sidecar should be valid JSON");
+        assert!(sidecar.get("progress").is_some(), "{sidecar}");
+        assert!(sidecar.get("metadata").is_some(), "{sidecar}");
+
+        // A held lock makes the next writer wait.
+        let held = state.book_locks.lock(&id).await.expect("lock");
+        let waiting =
+            tokio::time::timeout(Duration::from_millis(50), state.book_locks.lock(&id)).await;
+        assert!(waiting.is_err());
+        drop(held);
+        state.book_locks.lock(&id).await.expect("free again");
+    }
+
+    fn book(id: &str, added_at: i64, rating: Option<f64>) -> LNMetadata {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "title": id,
+            "author": "",
+            "addedAt": added_at,
+            "stats": {},
+            "chapterCount": 0,
+            "toc": [],
+            "rating": rating,
+        }))
+        .expect("metadata should deserialize")
+    }
+
+    #[test]
+    fn sort_library_orders_by_rating_and_last_read() {
+        let mut books = vec![
+            book("unrated", 3, None),
+            book("low", 1, Some(2.0)),
+            book("high", 2, Some(4.5)),
+        ];
+        sort_library(&mut books, "rating", true, &HashMap::new());
+        let ids: Vec<&str> = books.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(ids, vec!["high", "low", "unrated"]);
+
+        let last_read = HashMap::from([("low".to_string(), 20), ("unrated".to_string(), 10)]);
+        sort_library(&mut books, "lastRead", true, &last_read);
+        let ids: Vec<&str> = books.iter().map(|b| b.id.as_str()).collect();
+        assert_eq!(ids, vec!["low", "unrated", "high"]);
+    }
+
+    #[test]
+    fn category_membership_updates_books_in_one_pass() {
+        let root = unique_temp_dir("category-books");
+        let state = NovelState::new(root.join("data"), root.join("local-novel"));
+        for (id, added_at) in [("a", 1), ("b", 2)] {
+            let mut metadata = book(id, added_at, None);
+            if id == "b" {
+                metadata.category_ids = vec!["old".to_string()];
+            }
+            state
+                .db
+                .insert(
+                    format!("metadata:{id}"),
+                    serde_json::to_vec(&metadata).expect("json"),
+                )
+                .expect("metadata insert should succeed");
+        }
+
+        let req = CategoryBooksRequest {
+            add: vec!["a".to_string(), "b".to_string(), "missing".to_string()],
+            remove: Vec::new(),
+        };
+        let results = apply_category_membership(&state, "cat", &req).expect("apply");
+        let statuses: Vec<CategoryBookStatus> = results.into_iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                CategoryBookStatus::Added,
+                CategoryBookStatus::Added,
+                CategoryBookStatus::NotFound
+            ]
+        );
+
+        let members: Vec<String> = category_members(&state, "cat")
+            .expect("members")
+            .into_iter()
+            .map(|m| m.id)
+            .collect();
+        assert_eq!(members, vec!["b".to_string(), "a".to_string()]);
+
+        let req = CategoryBooksRequest {
+            add: vec!["a".to_string()],
+            remove: vec!["b".to_string()],
+        };
+        let results = apply_category_membership(&state, "cat", &req).expect("apply");
+        assert_eq!(results[0].status, CategoryBookStatus::Unchanged);
+        assert_eq!(results[1].status, CategoryBookStatus::Removed);
+        assert_eq!(results[1].category_ids, vec!["old".to_string()]);
+
+        let bytes = state
+            .db
+            .get("metadata:b")
+            .expect("get")
+            .expect("metadata b");
+        let b: LNMetadata = serde_json::from_slice(&bytes).expect("json");
+        assert_eq!(b.sync_version, Some(2));
+    }
+
+    #[test]
+    fn category_listing_inlines_metadata_and_skips_hidden_categories() {
+        let root = unique_temp_dir("category-listing");
+        let state = NovelState::new(root.join("data"), root.join("local-novel"));
+        for (id, order) in [("shown", 1), ("secret", 0), ("plain", 2)] {
+            let category = serde_json::json!({
+                "id": id, "name": id, "order": order, "createdAt": 0, "lastModified": 0,
+            });
+            state
+                .db
+                .insert(
+                    format!("category:{id}"),
+                    serde_json::to_vec(&category).expect("json"),
+                )
+                .expect("category insert should succeed");
+        }
+        let mut cover_book = book("cover-book", 1, None);
+        cover_book.cover = Some("/covers/cover-book.jpg".to_string());
+        state
+            .db
+            .insert(
+                "metadata:cover-book",
+                serde_json::to_vec(&cover_book).expect("json"),
+            )
+            .expect("metadata insert should succeed");
+        // Saved before the cover, description and hidden fields existed.
+        state
+            .db
+            .insert(
+                "category_metadata:shown",
+                br#"{"sortBy":"title","sortDesc":false}"#.as_slice(),
+            )
+            .expect("metadata insert should succeed");
+        let shown = load_category_metadata(&state, "shown")
+            .expect("load")
+            .expect("metadata");
+        assert!(!shown.hidden);
+        let shown = LnCategoryMetadata {
+            cover: Some("cover-book".to_string()),
+            ..shown
+        };
+        let secret = LnCategoryMetadata {
+            hidden: true,
+            ..shown.clone()
+        };
+        for (id, metadata) in [("shown", &shown), ("secret", &secret)] {
+            state
+                .db
+                .insert(
+                    format!("category_metadata:{id}"),
+                    serde_json::to_vec(metadata).expect("json"),
+                )
+                .expect("metadata insert should succeed");
+        }
+
+        let listed = list_categories(&state, false).expect("list");
+        let ids: Vec<&str> = listed.iter().map(|c| c.category.id.as_str()).collect();
+        assert_eq!(ids, vec!["shown", "plain"]);
+        assert_eq!(
+            listed[0].metadata.as_ref().map(|m| m.sort_by.as_str()),
+            Some("title")
+        );
+        assert_eq!(
+            listed[0].cover_url.as_deref(),
+            Some("/covers/cover-book.jpg")
+        );
+        assert!(listed[1].metadata.is_none());
+
+        let all = list_categories(&state, true).expect("list");
+        assert_eq!(all[0].category.id, "secret");
+    }
+
+    #[tokio::test]
+    async fn update_preferences_stamps_last_modified_on_the_server() {
+        let root = unique_temp_dir("preferences-stamp");
+        let state = NovelState::new(root.join("data"), root.join("local-novel"));
+        let before = chrono::Utc::now().timestamp_millis();
+
+        for sent in [1, i64::MAX] {
+            let preferences = LnLibraryPreferences {
+                last_modified: sent,
+                ..LnLibraryPreferences::default()
+            };
+            let Json(stored) = update_preferences(State(state.clone()), Json(preferences))
+                .await
+                .expect("preferences should be stored");
+            assert!(stored.last_modified >= before);
+            assert!(stored.last_modified <= chrono::Utc::now().timestamp_millis());
+        }
+    }
+
+    #[tokio::test]
+    async fn update_category_metadata_stamps_last_modified_on_the_server() {
+        let root = unique_temp_dir("category-metadata-stamp");
+        let state = NovelState::new(root.join("data"), root.join("local-novel"));
+        let before = chrono::Utc::now().timestamp_millis();
+
+        let meta = LnCategoryMetadata {
+            sort_by: "title".to_string(),
+            sort_desc: false,
+            cover: None,
+            description: None,
+            hidden: false,
+            last_modified: i64::MAX,
+        };
+        update_category_metadata(State(state.clone()), Path("fav".to_string()), Json(meta))
+            .await
+            .expect("metadata should be stored");
+
+        let stored = load_category_metadata(&state, "fav")
+            .expect("load")
+            .expect("stored metadata");
+        assert!(stored.last_modified >= before);
+        assert!(stored.last_modified <= chrono::Utc::now().timestamp_millis());
+    }
+
     #[test]
     fn discover_pending_epubs_returns_empty_when_local_folder_is_missing() {
         let root = unique_temp_dir("discover-missing");
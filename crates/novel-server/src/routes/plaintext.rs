@@ -0,0 +1,397 @@
+use std::convert::Infallible;
+
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::header::CONTENT_TYPE,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use crate::{
+    error::NovelError,
+    state::NovelState,
+    types::{LNMetadata, LNParsedBook},
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RubyMode {
+    /// Keep the base text only.
+    #[default]
+    Drop,
+    /// Append readings as `漢字[かんじ]`.
+    Brackets,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PlaintextOptions {
+    pub ruby: RubyMode,
+    pub image_alt: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaintextQuery {
+    /// 1-based chapter selection such as `3`, `1-5` or `1-3,7`. All chapters when absent.
+    pub chapters: Option<String>,
+    #[serde(default)]
+    pub ruby: RubyMode,
+    /// Emits image alt text as `[alt]` instead of dropping images entirely.
+    #[serde(default)]
+    pub image_alt: bool,
+}
+
+/// Streams the stored chapters as plain text, one paragraph per line, with a blank line
+/// between chapters and a `#` header naming the book.
+pub async fn get_plaintext(
+    State(state): State<NovelState>,
+    Path(id): Path<String>,
+    Query(query): Query<PlaintextQuery>,
+) -> Result<Response, NovelError> {
+    let bytes = state
+        .db
+        .get(format!("content:{id}"))?
+        .ok_or(NovelError::NotFound)?;
+    let content: LNParsedBook = serde_json::from_slice(&bytes)?;
+    let metadata = match state.db.get(format!("metadata:{id}"))? {
+        Some(bytes) => Some(serde_json::from_slice::<LNMetadata>(&bytes)?),
+        None => None,
+    };
+
+    let selected = match query.chapters.as_deref() {
+        Some(spec) => parse_chapter_ranges(spec, content.chapters.len())?,
+        None => (0..content.chapters.len()).collect(),
+    };
+
+    let mut header = String::new();
+    if let Some(metadata) = metadata {
+        header.push_str(&format!("# Title: {}\n", metadata.title));
+        header.push_str(&format!("# Author: {}\n", metadata.author));
+    }
+    header.push_str(&format!("# Chapters: {}\n\n", format_selection(&selected)));
+
+    let options = PlaintextOptions {
+        ruby: query.ruby,
+        image_alt: query.image_alt,
+    };
+    let mut chapters = content.chapters;
+    let chapter_texts = selected.into_iter().map(move |index| {
+        let mut text = html_to_text(&std::mem::take(&mut chapters[index]), options);
+        text.push('\n');
+        Ok::<_, Infallible>(text)
+    });
+    let stream = futures::stream::iter(std::iter::once(Ok(header)).chain(chapter_texts));
+
+    Ok((
+        [(CONTENT_TYPE, "text/plain; charset=utf-8")],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+/// Parses a 1-based, inclusive chapter selection into sorted, de-duplicated indices.
+fn parse_chapter_ranges(spec: &str, chapter_count: usize) -> Result<Vec<usize>, NovelError> {
+    let invalid = || NovelError::BadRequest(format!("Invalid chapter selection: {spec}"));
+    let mut selected = Vec::new();
+    for part in spec
+        .split(',')
+        .map(str::trim)
+        .filter(|part| !part.is_empty())
+    {
+        let (start, end) = match part.split_once('-') {
+            Some((start, end)) => (start.trim(), end.trim()),
+            None => (part, part),
+        };
+        let start: usize = start.parse().map_err(|_| invalid())?;
+        let end: usize = if end.is_empty() {
+            chapter_count
+        } else {
+            end.parse().map_err(|_| invalid())?
+        };
+        if start == 0 || start > end || end > chapter_count {
+            return Err(invalid());
+        }
+        selected.extend(start - 1..end);
+    }
+    selected.sort_unstable();
+    selected.dedup();
+    Ok(selected)
+}
+
+fn format_selection(indices: &[usize]) -> String {
+    let mut ranges: Vec<String> = Vec::new();
+    let mut iter = indices.iter().map(|index| index + 1).peekable();
+    while let Some(start) = iter.next() {
+        let mut end = start;
+        while iter.peek() == Some(&(end + 1)) {
+            end += 1;
+            iter.next();
+        }
+        ranges.push(if start == end {
+            start.to_string()
+        } else {
+            format!("{start}-{end}")
+        });
+    }
+    ranges.join(",")
+}
+
+const BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tr",
+    "ul",
+];
+const HIDDEN_TAGS: &[&str] = &["head", "script", "style", "title"];
+
+struct TextWriter {
+    out: String,
+    pending_space: bool,
+}
+
+impl TextWriter {
+    fn push_text(&mut self, text: &str) {
+        for ch in text.chars() {
+            if ch.is_ascii_whitespace() {
+                self.pending_space = true;
+                continue;
+            }
+            if self.pending_space && !self.out.is_empty() && !self.out.ends_with('\n') {
+                self.out.push(' ');
+            }
+            self.pending_space = false;
+            self.out.push(ch);
+        }
+    }
+
+    fn break_line(&mut self) {
+        self.pending_space = false;
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+    }
+}
+
+/// Converts chapter XHTML to plain text. Block elements become line breaks, ruby readings
+/// follow `options.ruby`, and images are dropped unless `options.image_alt` is set.
+pub fn html_to_text(html: &str, options: PlaintextOptions) -> String {
+    let mut writer = TextWriter {
+        out: String::with_capacity(html.len() / 2),
+        pending_space: false,
+    };
+    let mut hidden_depth = 0usize;
+    let mut in_rt = false;
+    let mut in_rp = false;
+    let mut reading = String::new();
+    let mut rest = html;
+
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+        if rest.starts_with('<') {
+            let Some(end) = rest.find('>') else {
+                break;
+            };
+            let tag = &rest[1..end];
+            rest = &rest[end + 1..];
+
+            let closing = tag.starts_with('/');
+            let name = tag
+                .trim_start_matches('/')
+                .split(|c: char| c.is_whitespace() || c == '/')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            let name = name.rsplit(':').next().unwrap_or_default();
+
+            if HIDDEN_TAGS.contains(&name) {
+                if closing {
+                    hidden_depth = hidden_depth.saturating_sub(1);
+                } else if !tag.ends_with('/') {
+                    hidden_depth += 1;
+                }
+                continue;
+            }
+            if hidden_depth > 0 {
+                continue;
+            }
+
+            match (name, closing) {
+                ("rt", false) => {
+                    in_rt = true;
+                    reading.clear();
+                }
+                ("rt", true) => {
+                    in_rt = false;
+                    if options.ruby == RubyMode::Brackets && !reading.trim().is_empty() {
+                        writer.push_text(&format!("[{}]", reading.trim()));
+                    }
+                }
+                ("rp", _) => in_rp = !closing,
+                ("img", false) if options.image_alt => {
+                    if let Some(alt) = attribute(tag, "alt").filter(|alt| !alt.trim().is_empty()) {
+                        writer.push_text(&format!("[{}]", decode_entities(alt.trim())));
+                    }
+                }
+                _ if BLOCK_TAGS.contains(&name) => writer.break_line(),
+                _ => {}
+            }
+            continue;
+        }
+
+        let end = rest.find('<').unwrap_or(rest.len());
+        let text = &rest[..end];
+        rest = &rest[end..];
+        if hidden_depth > 0 || in_rp {
+            continue;
+        }
+        let text = decode_entities(text);
+        if in_rt {
+            reading.push_str(&text);
+        } else {
+            writer.push_text(&text);
+        }
+    }
+
+    writer.out.trim_end().to_string()
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let lower = tag.to_ascii_lowercase();
+    let mut search_from = 0;
+    while let Some(found) = lower[search_from..].find(name) {
+        let start = search_from + found;
+        search_from = start + name.len();
+        let preceded_by_space = lower[..start]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_whitespace);
+        let after = lower[search_from..].trim_start();
+        if !preceded_by_space || !after.starts_with('=') {
+            continue;
+        }
+        let value_start = tag.len() - after.len() + 1;
+        let value = tag[value_start..].trim_start();
+        let quote = value.chars().next()?;
+        return if quote == '"' || quote == '\'' {
+            value[1..].split(quote).next()
+        } else {
+            value.split(|c: char| c.is_whitespace() || c == '/').next()
+        };
+    }
+    None
+}
+
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').filter(|end| *end <= 10).and_then(|end| {
+            let entity = &rest[1..end];
+            let ch = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                _ => entity
+                    .strip_prefix("#x")
+                    .or_else(|| entity.strip_prefix("#X"))
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                    .and_then(char::from_u32),
+            }?;
+            Some((ch, end))
+        });
+        match decoded {
+            Some((ch, end)) => {
+                out.push(ch);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHAPTER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<html xmlns="http://www.w3.org/1999/xhtml"><head><title>第一章</title><style>p { margin: 0 }</style></head>
+<body>
+  <h1>第一章</h1>
+  <p><ruby>蜘蛛<rp>(</rp><rt>くも</rt><rp>)</rp></ruby>の糸&amp;その他</p>
+  <p>二行目<br/>三行目</p>
+  <div><img src="a.png" alt="挿絵"/></div>
+</body></html>"#;
+
+    #[test]
+    fn drops_ruby_readings_and_images_by_default() {
+        let text = html_to_text(CHAPTER, PlaintextOptions::default());
+        assert_eq!(text, "第一章\n蜘蛛の糸&その他\n二行目\n三行目");
+    }
+
+    #[test]
+    fn brackets_readings_and_keeps_alt_text_when_asked() {
+        let options = PlaintextOptions {
+            ruby: RubyMode::Brackets,
+            image_alt: true,
+        };
+        let text = html_to_text(CHAPTER, options);
+        assert_eq!(
+            text,
+            "第一章\n蜘蛛[くも]の糸&その他\n二行目\n三行目\n[挿絵]"
+        );
+    }
+
+    #[test]
+    fn parses_chapter_ranges() {
+        assert_eq!(
+            parse_chapter_ranges("1-3, 5, 2", 6).expect("valid"),
+            vec![0, 1, 2, 4]
+        );
+        assert_eq!(parse_chapter_ranges("5-", 6).expect("valid"), vec![4, 5]);
+        assert!(parse_chapter_ranges("0-2", 6).is_err());
+        assert!(parse_chapter_ranges("4-9", 6).is_err());
+        assert_eq!(format_selection(&[0, 1, 2, 4]), "1-3,5");
+    }
+}
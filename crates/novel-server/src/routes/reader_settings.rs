@@ -0,0 +1,200 @@
+use std::fs;
+
+use axum::{
+    Json,
+    extract::{Path, State},
+};
+
+use crate::{error::NovelError, paths, state::NovelState, types::LnReaderSettings};
+
+const DEFAULT_READER_SETTINGS_KEY: &str = "reader_settings_default";
+const DEFAULT_READER_SETTINGS_FILE: &str = "reader-settings.json";
+const MAX_CUSTOM_CSS_BYTES: usize = 64 * 1024;
+
+pub(super) fn book_settings_key(id: &str) -> String {
+    format!("reader_settings:{id}")
+}
+
+fn load_settings(state: &NovelState, key: &str) -> Result<Option<LnReaderSettings>, NovelError> {
+    match state.db.get(key)? {
+        Some(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+        None => Ok(None),
+    }
+}
+
+pub async fn get_default_settings(
+    State(state): State<NovelState>,
+) -> Result<Json<LnReaderSettings>, NovelError> {
+    Ok(Json(
+        load_settings(&state, DEFAULT_READER_SETTINGS_KEY)?.unwrap_or_default(),
+    ))
+}
+
+pub async fn update_default_settings(
+    State(state): State<NovelState>,
+    Json(settings): Json<LnReaderSettings>,
+) -> Result<Json<LnReaderSettings>, NovelError> {
+    let settings = prepare_settings(settings)?;
+    state
+        .db
+        .insert(DEFAULT_READER_SETTINGS_KEY, serde_json::to_vec(&settings)?)?;
+
+    let local_path = state.get_local_novel_path();
+    fs::create_dir_all(&local_path)?;
+    fs::write(
+        local_path.join(DEFAULT_READER_SETTINGS_FILE),
+        serde_json::to_string_pretty(&settings)?,
+    )?;
+
+    state.db.flush()?;
+    Ok(Json(settings))
+}
+
+/// Returns the book's settings layered over the global default.
+pub async fn get_book_settings(
+    State(state): State<NovelState>,
+    Path(id): Path<String>,
+) -> Result<Json<LnReaderSettings>, NovelError> {
+    Ok(Json(effective_settings(&state, &id)?))
+}
+
+pub async fn update_book_settings(
+    State(state): State<NovelState>,
+    Path(id): Path<String>,
+    Json(settings): Json<LnReaderSettings>,
+) -> Result<Json<LnReaderSettings>, NovelError> {
+    let settings = prepare_settings(settings)?;
+    let _book = state.book_locks.lock(&id).await?;
+    state
+        .db
+        .insert(book_settings_key(&id), serde_json::to_vec(&settings)?)?;
+
+    // Sidecar save
+    let novel_dir = state.get_novel_dir(&id);
+    fs::create_dir_all(&novel_dir)?;
+    let sidecar_path = paths::sidecar_path(&novel_dir);
+
+    let mut sidecar_data = if sidecar_path.exists() {
+        let content = fs::read_to_string(&sidecar_path)?;
+        serde_json::from_str::<serde_json::Value>(&content).unwrap_or(serde_json::json!({}))
+    } else {
+        serde_json::json!({})
+    };
+
+    sidecar_data["readerSettings"] = serde_json::to_value(&settings)?;
+    fs::write(sidecar_path, serde_json::to_string_pretty(&sidecar_data)?)?;
+
+    state.db.flush()?;
+    Ok(Json(effective_settings(&state, &id)?))
+}
+
+fn effective_settings(state: &NovelState, id: &str) -> Result<LnReaderSettings, NovelError> {
+    let global = load_settings(state, DEFAULT_READER_SETTINGS_KEY)?.unwrap_or_default();
+    Ok(match load_settings(state, &book_settings_key(id))? {
+        Some(book) => book.overlay(&global),
+        None => global,
+    })
+}
+
+/// Validates and sanitizes settings before they are stored, stamping them for sync.
+fn prepare_settings(mut settings: LnReaderSettings) -> Result<LnReaderSettings, NovelError> {
+    if let Some(css) = settings.custom_css.take() {
+        if css.len() > MAX_CUSTOM_CSS_BYTES {
+            return Err(NovelError::BadRequest(format!(
+                "customCss exceeds {MAX_CUSTOM_CSS_BYTES} bytes"
+            )));
+        }
+        let css = strip_remote_imports(&css);
+        settings.custom_css = (!css.trim().is_empty()).then_some(css);
+    }
+    settings.last_modified = chrono::Utc::now().timestamp_millis();
+    Ok(settings)
+}
+
+/// Drops `@import` rules that pull stylesheets from the network; local imports are kept.
+fn strip_remote_imports(css: &str) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css;
+    while let Some(start) = find_ascii_case_insensitive(rest, "@import") {
+        output.push_str(&rest[..start]);
+        let rule = &rest[start..];
+        let end = rule.find(';').map_or(rule.len(), |idx| idx + 1);
+        let target = rule[..end].to_ascii_lowercase();
+        let is_remote = ["http:", "https:", "//", "ftp:"]
+            .iter()
+            .any(|scheme| target.contains(scheme));
+        if !is_remote {
+            output.push_str(&rule[..end]);
+        }
+        rest = &rule[end..];
+    }
+    output.push_str(rest);
+    output
+}
+
+fn find_ascii_case_insensitive(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strip_remote_imports_keeps_local_rules() {
+        let css = "@import url(\"https://evil.example/x.css\");\n@IMPORT '//cdn.example/y.css';\n@import \"local.css\";\np { line-height: 2; }";
+        assert_eq!(
+            strip_remote_imports(css),
+            "\n\n@import \"local.css\";\np { line-height: 2; }"
+        );
+    }
+
+    #[test]
+    fn prepare_settings_rejects_oversized_css() {
+        let settings = LnReaderSettings {
+            custom_css: Some("a".repeat(MAX_CUSTOM_CSS_BYTES + 1)),
+            ..Default::default()
+        };
+        let err = prepare_settings(settings).expect_err("oversized css should be rejected");
+        assert!(matches!(err, NovelError::BadRequest(_)));
+    }
+
+    #[test]
+    fn prepare_settings_ignores_the_client_timestamp() {
+        let before = chrono::Utc::now().timestamp_millis();
+        for sent in [1, i64::MAX] {
+            let settings = LnReaderSettings {
+                last_modified: sent,
+                ..Default::default()
+            };
+            let prepared = prepare_settings(settings).expect("settings should be accepted");
+            assert!(prepared.last_modified >= before);
+            assert!(prepared.last_modified <= chrono::Utc::now().timestamp_millis());
+        }
+    }
+
+    #[test]
+    fn book_settings_override_global_defaults() {
+        let global: LnReaderSettings = serde_json::from_value(serde_json::json!({
+            "fontFamily": "Noto Serif JP",
+            "lineHeight": 1.8,
+            "customCss": "p { margin: 0; }",
+            "lastModified": 10,
+        }))
+        .expect("global settings");
+        let book: LnReaderSettings = serde_json::from_value(serde_json::json!({
+            "lineHeight": 2.2,
+            "lastModified": 20,
+        }))
+        .expect("book settings");
+
+        let merged = book.overlay(&global);
+        assert_eq!(merged.settings["fontFamily"], "Noto Serif JP");
+        assert_eq!(merged.settings["lineHeight"], 2.2);
+        assert_eq!(merged.custom_css.as_deref(), Some("p { margin: 0; }"));
+        assert_eq!(merged.last_modified, 20);
+    }
+}
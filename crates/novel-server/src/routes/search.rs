@@ -0,0 +1,111 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::NovelError,
+    state::NovelState,
+    types::{LNMetadata, LNProgress},
+};
+
+const DEFAULT_LIMIT: usize = 20;
+
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub q: String,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BookHit {
+    pub id: String,
+    pub title: String,
+    pub author: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HighlightHit {
+    pub book_id: String,
+    pub highlight_id: String,
+    pub chapter_index: i32,
+    pub block_id: String,
+    pub text: String,
+}
+
+impl SearchQuery {
+    /// The trimmed query, or a bad request when nothing is left to search for.
+    fn needle(&self) -> Result<String, NovelError> {
+        let needle = self.q.trim().to_lowercase();
+        if needle.is_empty() {
+            return Err(NovelError::BadRequest("q must not be empty".to_string()));
+        }
+        Ok(needle)
+    }
+
+    fn limit(&self) -> usize {
+        self.limit.unwrap_or(DEFAULT_LIMIT)
+    }
+}
+
+/// Books whose title or author contains `q`, ignoring case.
+pub async fn search_books(
+    State(state): State<NovelState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Vec<BookHit>>, NovelError> {
+    let needle = query.needle()?;
+    let mut hits = Vec::new();
+    for item in state.db.scan_prefix("metadata:") {
+        if hits.len() >= query.limit() {
+            break;
+        }
+        let (_, v) = item?;
+        let metadata: LNMetadata = serde_json::from_slice(&v)?;
+        if metadata.title.to_lowercase().contains(&needle)
+            || metadata.author.to_lowercase().contains(&needle)
+        {
+            hits.push(BookHit {
+                id: metadata.id,
+                title: metadata.title,
+                author: metadata.author,
+                cover: metadata.cover,
+            });
+        }
+    }
+    Ok(Json(hits))
+}
+
+/// Highlights whose text contains `q`, ignoring case, with the chapter and block needed
+/// to open the reader at them.
+pub async fn search_highlights(
+    State(state): State<NovelState>,
+    Query(query): Query<SearchQuery>,
+) -> Result<Json<Vec<HighlightHit>>, NovelError> {
+    let needle = query.needle()?;
+    let mut hits = Vec::new();
+    'books: for item in state.db.scan_prefix("progress:") {
+        let (key, v) = item?;
+        let progress: LNProgress = serde_json::from_slice(&v)?;
+        let book_id = String::from_utf8_lossy(&key["progress:".len()..]).to_string();
+        for highlight in progress.highlights {
+            if hits.len() >= query.limit() {
+                break 'books;
+            }
+            if highlight.text.to_lowercase().contains(&needle) {
+                hits.push(HighlightHit {
+                    book_id: book_id.clone(),
+                    highlight_id: highlight.id,
+                    chapter_index: highlight.chapter_index,
+                    block_id: highlight.block_id,
+                    text: highlight.text,
+                });
+            }
+        }
+    }
+    Ok(Json(hits))
+}
@@ -1,13 +1,65 @@
+use manatan_jobs::WorkerPool;
 use sled::Db;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::OwnedMutexGuard;
+
+use crate::error::NovelError;
 
 pub const NOVEL_METADATA_DIR_NAME: &str = ".manatan-metadata";
 
+/// How long a write waits behind another write to the same book before giving up.
+pub const BOOK_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Clone)]
 pub struct NovelState {
     pub db: Db,
     pub storage_dir: PathBuf,
     pub local_novel_path: PathBuf,
+    /// Book ids whose metadata sidecar is waiting for a debounced write.
+    pub pending_sidecars: Arc<Mutex<HashSet<String>>>,
+    /// Library scans; one runs at a time and one more may wait behind it.
+    pub scans: WorkerPool,
+    /// Held while an inbox pass runs, so the watcher and `/inbox/ingest` never file the
+    /// same book twice.
+    pub inbox_pass: Arc<Mutex<()>>,
+    /// Held by every write to a book's database keys or sidecar.
+    pub book_locks: BookLocks,
+}
+
+/// One async lock per book id. Writes read, modify and rewrite the book's
+/// `metadata.json`, so two of them interleaving would drop one writer's section.
+#[derive(Clone, Default)]
+pub struct BookLocks(Arc<Mutex<HashMap<String, Arc<tokio::sync::Mutex<()>>>>>);
+
+impl BookLocks {
+    /// Waits for the book's lock, up to [`BOOK_LOCK_TIMEOUT`].
+    pub async fn lock(&self, id: &str) -> Result<OwnedMutexGuard<()>, NovelError> {
+        let lock = {
+            let mut locks = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+            // Locks nobody holds or waits on are dropped rather than kept for every book.
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            locks.entry(id.to_string()).or_default().clone()
+        };
+        tokio::time::timeout(BOOK_LOCK_TIMEOUT, lock.lock_owned())
+            .await
+            .map_err(|_| NovelError::Busy(id.to_string()))
+    }
+
+    /// Locks several books, in a fixed order so two callers can't deadlock.
+    pub async fn lock_all<'a>(
+        &self,
+        ids: impl IntoIterator<Item = &'a String>,
+    ) -> Result<Vec<OwnedMutexGuard<()>>, NovelError> {
+        let ids: std::collections::BTreeSet<&String> = ids.into_iter().collect();
+        let mut guards = Vec::with_capacity(ids.len());
+        for id in ids {
+            guards.push(self.lock(id).await?);
+        }
+        Ok(guards)
+    }
 }
 
 impl NovelState {
@@ -22,6 +74,10 @@ impl NovelState {
             db,
             storage_dir: novel_dir,
             local_novel_path,
+            pending_sidecars: Arc::default(),
+            scans: WorkerPool::new("library-scan", 1, 1),
+            inbox_pass: Arc::default(),
+            book_locks: BookLocks::default(),
         }
     }
 
@@ -1,6 +1,6 @@
 pub use manatan_sync_server::types::{
     BlockIndexMap, BookStats, LNHighlight, LNMetadata, LNParsedBook, LNProgress, LnCategory,
-    LnCategoryMetadata, TocItem,
+    LnCategoryMetadata, LnLibraryPreferences, LnReaderSettings, TocItem,
 };
 use serde::{Deserialize, Serialize};
 
@@ -28,3 +28,77 @@ pub struct DiscoveredEpub {
     pub id: String,
     pub file_name: String,
 }
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataListQuery {
+    pub sort_by: Option<String>,
+    pub sort_desc: Option<bool>,
+    /// Comma-separated `LNMetadata` fields to return; `id` and `title` are always kept.
+    pub fields: Option<String>,
+    /// Only books in this category, sorted by its default sort unless one is given.
+    pub category: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryListQuery {
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+
+/// A category with its display settings inlined. `coverUrl` is the category cover with
+/// book ids resolved to that book's cover.
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryListing {
+    #[serde(flatten)]
+    pub category: LnCategory,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<LnCategoryMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_url: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct FieldsQuery {
+    pub fields: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryBooksRequest {
+    #[serde(default)]
+    pub add: Vec<String>,
+    #[serde(default)]
+    pub remove: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum CategoryBookStatus {
+    Added,
+    Removed,
+    Unchanged,
+    NotFound,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryBookResult {
+    pub id: String,
+    pub status: CategoryBookStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub category_ids: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CategoryBookSummary {
+    pub id: String,
+    pub title: String,
+    pub author: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover: Option<String>,
+    pub added_at: i64,
+}
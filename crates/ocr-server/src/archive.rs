@@ -0,0 +1,317 @@
+//! Moves the OCR cache of finished series out of the live database into one compressed
+//! file per series, and back again on request.
+//!
+//! A series is identified by a prefix of its entries' `context`. The keys of archived
+//! pages stay listed in `ocr_archived_keys`, so a read of an archived page can be told
+//! apart from a page that was never OCRed.
+
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use rusqlite::{OptionalExtension, TransactionBehavior, params};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::{
+    spread,
+    state::{
+        AppState, CacheEntry, backend_from_row, hint_from_row, now_unix, orientation_from_row,
+        preprocess_from_row, source_from_row,
+    },
+};
+
+const ARCHIVE_DIR_NAME: &str = "ocr-archive";
+
+/// Matches rows whose context starts with `?1`, without LIKE's wildcard escaping.
+const CONTEXT_MATCHES: &str = "substr(context, 1, length(?1)) = ?1";
+
+/// Contents of one archive file.
+#[derive(Serialize, Deserialize, Default)]
+struct SeriesArchive {
+    context_prefix: String,
+    entries: HashMap<String, CacheEntry>,
+    /// `(chapter_key, cache_key)` pairs, so restored chapters count as preprocessed again.
+    chapter_links: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveSummary {
+    pub context_prefix: String,
+    pub file_name: String,
+    pub entry_count: usize,
+    pub size_bytes: u64,
+    pub archived_at: i64,
+}
+
+pub(crate) fn archive_dir(state: &AppState) -> PathBuf {
+    state.cache_dir.join(ARCHIVE_DIR_NAME)
+}
+
+/// Moves every cache row whose context starts with `context_prefix` into the series'
+/// archive file, adding to it if the series was archived before. Returns `None` when no
+/// row matches.
+pub fn archive(state: &AppState, context_prefix: &str) -> anyhow::Result<Option<ArchiveSummary>> {
+    let mut conn = state.conn()?;
+    // Immediate, so no page of the series can be cached between reading and deleting.
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+
+    let entries = {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT cache_key, context, data, backend, orientation, edited_at, source, preprocess,
+                    orientation_hint
+             FROM ocr_cache WHERE {CONTEXT_MATCHES}"
+        ))?;
+        let rows = stmt.query_map(params![context_prefix], |row| {
+            let data_blob: Vec<u8> = row.get(2)?;
+            Ok((
+                row.get::<_, String>(0)?,
+                CacheEntry {
+                    context: row.get(1)?,
+                    data: serde_json::from_slice(&data_blob).unwrap_or_default(),
+                    backend: backend_from_row(row.get(3)?),
+                    orientation: orientation_from_row(row.get(4)?),
+                    orientation_hint: hint_from_row(row.get(8)?),
+                    edited_at: row.get(5)?,
+                    source: source_from_row(row.get(6)?),
+                    preprocess: preprocess_from_row(row.get(7)?),
+                },
+            ))
+        })?;
+        rows.collect::<Result<HashMap<_, _>, _>>()?
+    };
+    if entries.is_empty() {
+        return Ok(None);
+    }
+
+    let chapter_links = {
+        let mut stmt = tx.prepare(&format!(
+            "SELECT chapter_key, cache_key FROM chapter_cache
+             WHERE cache_key IN (SELECT cache_key FROM ocr_cache WHERE {CONTEXT_MATCHES})"
+        ))?;
+        let rows = stmt.query_map(params![context_prefix], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    let existing: Option<String> = tx
+        .query_row(
+            "SELECT file_name FROM ocr_archive WHERE context_prefix = ?",
+            params![context_prefix],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let dir = archive_dir(state);
+    let (file_name, mut archive) = match existing {
+        Some(file_name) => {
+            let archive = read_archive(&dir.join(&file_name))?;
+            (file_name, archive)
+        }
+        None => (
+            format!("{}.json.gz", now_unix_nanos()),
+            SeriesArchive {
+                context_prefix: context_prefix.to_string(),
+                ..SeriesArchive::default()
+            },
+        ),
+    };
+
+    let archived_keys: Vec<String> = entries.keys().cloned().collect();
+    archive.entries.extend(entries);
+    archive.chapter_links.extend(chapter_links);
+    archive.chapter_links.sort();
+    archive.chapter_links.dedup();
+
+    let path = dir.join(&file_name);
+    let size_bytes = write_archive(&path, &archive)?;
+
+    tx.execute(
+        &format!(
+            "DELETE FROM chapter_cache
+             WHERE cache_key IN (SELECT cache_key FROM ocr_cache WHERE {CONTEXT_MATCHES})"
+        ),
+        params![context_prefix],
+    )?;
+    tx.execute(
+        &format!("DELETE FROM ocr_cache WHERE {CONTEXT_MATCHES}"),
+        params![context_prefix],
+    )?;
+    tx.execute(spread::DELETE_ORPHAN_SPLITS, [])?;
+    for key in &archived_keys {
+        tx.execute(
+            "INSERT OR REPLACE INTO ocr_archived_keys (cache_key, context_prefix) VALUES (?, ?)",
+            params![key, context_prefix],
+        )?;
+    }
+    let archived_at = now_unix();
+    tx.execute(
+        "INSERT INTO ocr_archive
+            (context_prefix, file_name, entry_count, size_bytes, archived_at)
+         VALUES (?, ?, ?, ?, ?)
+         ON CONFLICT(context_prefix) DO UPDATE SET
+            entry_count = excluded.entry_count,
+            size_bytes = excluded.size_bytes,
+            archived_at = excluded.archived_at",
+        params![
+            context_prefix,
+            file_name,
+            archive.entries.len() as i64,
+            size_bytes as i64,
+            archived_at
+        ],
+    )?;
+    tx.commit()?;
+
+    info!(
+        "[ARCHIVE] Archived {} page(s) for '{}' into {}",
+        archived_keys.len(),
+        context_prefix,
+        file_name
+    );
+    Ok(Some(ArchiveSummary {
+        context_prefix: context_prefix.to_string(),
+        file_name,
+        entry_count: archive.entries.len(),
+        size_bytes,
+        archived_at,
+    }))
+}
+
+/// Restores an archived series into the cache and deletes its archive file. Pages cached
+/// again since archiving keep their newer results. Returns the number of restored pages,
+/// or `None` when the series is not archived.
+pub fn unarchive(state: &AppState, context_prefix: &str) -> anyhow::Result<Option<usize>> {
+    let mut conn = state.conn()?;
+    let file_name: Option<String> = conn
+        .query_row(
+            "SELECT file_name FROM ocr_archive WHERE context_prefix = ?",
+            params![context_prefix],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(file_name) = file_name else {
+        return Ok(None);
+    };
+    let path = archive_dir(state).join(&file_name);
+    let archive = read_archive(&path)?;
+
+    let now = now_unix();
+    let tx = conn.transaction()?;
+    let mut restored = 0;
+    for (key, entry) in &archive.entries {
+        let data_blob = serde_json::to_vec(&entry.data)?;
+        restored += tx.execute(
+            "INSERT OR IGNORE INTO ocr_cache
+                (cache_key, context, data, backend, orientation, edited_at, source, preprocess, orientation_hint, created_at, last_processed_at, last_accessed_at, access_count)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            params![
+                key,
+                entry.context,
+                data_blob,
+                entry.backend.as_str(),
+                entry.orientation.map(|o| o.as_str()),
+                entry.edited_at,
+                entry.source.as_str(),
+                entry.preprocess.as_str(),
+                entry.orientation_hint.as_str(),
+                now,
+                now,
+                now,
+                1i64
+            ],
+        )?;
+    }
+    for (chapter_key, cache_key) in &archive.chapter_links {
+        tx.execute(
+            "INSERT OR IGNORE INTO chapter_cache (chapter_key, cache_key, created_at) VALUES (?, ?, ?)",
+            params![chapter_key, cache_key, now],
+        )?;
+    }
+    tx.execute(
+        "DELETE FROM ocr_archived_keys WHERE context_prefix = ?",
+        params![context_prefix],
+    )?;
+    tx.execute(
+        "DELETE FROM ocr_archive WHERE context_prefix = ?",
+        params![context_prefix],
+    )?;
+    tx.commit()?;
+
+    if let Err(err) = fs::remove_file(&path) {
+        warn!("[ARCHIVE] Failed to remove {}: {err}", path.display());
+    }
+    info!(
+        "[ARCHIVE] Restored {} page(s) for '{}'",
+        restored, context_prefix
+    );
+    Ok(Some(restored))
+}
+
+/// Every archived series, largest first.
+pub fn list(state: &AppState) -> anyhow::Result<Vec<ArchiveSummary>> {
+    let conn = state.conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT context_prefix, file_name, entry_count, size_bytes, archived_at
+         FROM ocr_archive ORDER BY size_bytes DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(ArchiveSummary {
+            context_prefix: row.get(0)?,
+            file_name: row.get(1)?,
+            entry_count: row.get::<_, i64>(2)? as usize,
+            size_bytes: row.get::<_, i64>(3)? as u64,
+            archived_at: row.get(4)?,
+        })
+    })?;
+    Ok(rows.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// The context prefix of the archive holding `cache_key`, if the page was archived.
+pub fn archived_series(state: &AppState, cache_key: &str) -> Option<String> {
+    let Ok(conn) = state.conn() else {
+        warn!("Failed to get DB connection for archived_series");
+        return None;
+    };
+    conn.query_row(
+        "SELECT context_prefix FROM ocr_archived_keys WHERE cache_key = ?",
+        params![cache_key],
+        |row| row.get(0),
+    )
+    .optional()
+    .unwrap_or(None)
+}
+
+fn read_archive(path: &Path) -> anyhow::Result<SeriesArchive> {
+    let mut json = Vec::new();
+    GzDecoder::new(fs::File::open(path)?).read_to_end(&mut json)?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Writes the archive next to its final path first, so a crash never leaves a truncated
+/// file in place of a good one. Returns the compressed size.
+fn write_archive(path: &Path, archive: &SeriesArchive) -> anyhow::Result<u64> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&serde_json::to_vec(archive)?)?;
+    let bytes = encoder.finish()?;
+    manatan_storage::ensure_space(path, bytes.len() as u64)?;
+
+    let tmp_path = path.with_extension("tmp");
+    fs::write(&tmp_path, &bytes)?;
+    fs::rename(&tmp_path, path)?;
+    Ok(bytes.len() as u64)
+}
+
+fn now_unix_nanos() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+}
@@ -0,0 +1,123 @@
+//! Optional API key for the endpoints that change server state, for instances reachable
+//! from outside the local network. Read paths stay open so readers keep working without
+//! the key; `GET /ocr` and `POST /ocr/spread` need it only when asked to overwrite a
+//! cached result.
+
+use std::{path::Path, sync::Arc};
+
+use axum::{
+    Json,
+    extract::{Query, Request, State},
+    http::{Method, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::{info, warn};
+
+use crate::handlers::{OcrRequest, SpreadQuery};
+
+pub const API_KEY_ENV: &str = "MANATAN_OCR_API_KEY";
+/// Read from the cache directory when the environment variable is unset.
+pub const API_KEY_FILE: &str = "api-key";
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// POST routes that only look things up, so they stay open like GET routes.
+const READ_ONLY_POSTS: &[&str] = &[
+    "/ocr-novel-image",
+    "/is-chapter-preprocessed",
+    "/is-chapters-preprocessed",
+];
+
+#[derive(Clone)]
+pub struct ApiKey(Arc<str>);
+
+impl ApiKey {
+    pub fn new(key: &str) -> Option<Self> {
+        let key = key.trim();
+        (!key.is_empty()).then(|| Self(key.into()))
+    }
+
+    /// The configured key: `MANATAN_OCR_API_KEY`, else the `api-key` file in `cache_dir`.
+    /// `None` leaves every endpoint open.
+    pub fn load(cache_dir: &Path) -> Option<Self> {
+        if let Some(key) = std::env::var(API_KEY_ENV)
+            .ok()
+            .and_then(|key| Self::new(&key))
+        {
+            info!("API key auth enabled from {API_KEY_ENV}");
+            return Some(key);
+        }
+        let path = cache_dir.join(API_KEY_FILE);
+        let contents = match std::fs::read_to_string(&path) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                warn!("Could not read API key from {}: {err}", path.display());
+                return None;
+            }
+        };
+        let key = Self::new(&contents);
+        if key.is_some() {
+            info!("API key auth enabled from {}", path.display());
+        }
+        key
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Compares in constant time so the key can't be guessed byte by byte.
+    pub fn matches(&self, candidate: &str) -> bool {
+        let (expected, candidate) = (self.0.as_bytes(), candidate.as_bytes());
+        expected.len() == candidate.len()
+            && expected
+                .iter()
+                .zip(candidate)
+                .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+/// Whether a request needs the key: anything but GET, HEAD and OPTIONS, except POST
+/// routes that only read. A `GET /ocr` that forces a re-OCR, or sets an orientation hint
+/// or preprocessing, overwrites the cached page and needs it too, as does a forced
+/// `POST /ocr/spread`.
+pub fn requires_key(method: &Method, uri: &Uri) -> bool {
+    match *method {
+        Method::GET if uri.path() == "/ocr" => Query::<OcrRequest>::try_from_uri(uri)
+            .is_ok_and(|Query(params)| params.rewrites_cache()),
+        Method::POST if uri.path() == "/ocr/spread" => {
+            Query::<SpreadQuery>::try_from_uri(uri).is_ok_and(|Query(query)| query.force)
+        }
+        Method::GET | Method::HEAD | Method::OPTIONS => false,
+        Method::POST => !READ_ONLY_POSTS.contains(&uri.path()),
+        _ => true,
+    }
+}
+
+/// Middleware rejecting state-changing requests without a matching `X-Api-Key`.
+pub async fn require_api_key(State(key): State<ApiKey>, request: Request, next: Next) -> Response {
+    if !requires_key(request.method(), request.uri()) {
+        return next.run(request).await;
+    }
+    let provided = request
+        .headers()
+        .get(API_KEY_HEADER)
+        .and_then(|value| value.to_str().ok());
+    let error = match provided {
+        Some(provided) if key.matches(provided) => return next.run(request).await,
+        Some(_) => "Invalid API key",
+        None => "Missing X-Api-Key header",
+    };
+    warn!(
+        "Rejected {} {}: {error}",
+        request.method(),
+        request.uri().path()
+    );
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(serde_json::json!({ "error": error })),
+    )
+        .into_response()
+}
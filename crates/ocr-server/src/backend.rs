@@ -0,0 +1,212 @@
+use std::{collections::BTreeMap, io::Cursor, process::Stdio};
+
+use anyhow::anyhow;
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use crate::{
+    language::OcrLanguage,
+    logic::{self, BoundingBox, OcrResult, WordBox},
+};
+
+/// OCR engine used for a page. Lens is the default; Tesseract runs locally through the
+/// `tesseract` CLI, which must be installed along with the traineddata for the language.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OcrBackend {
+    #[default]
+    Lens,
+    Tesseract,
+}
+
+impl OcrBackend {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OcrBackend::Lens => "lens",
+            OcrBackend::Tesseract => "tesseract",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "lens" => Some(OcrBackend::Lens),
+            "tesseract" => Some(OcrBackend::Tesseract),
+            _ => None,
+        }
+    }
+
+    /// Keeps results from different engines apart in the cache. Lens keeps the
+    /// historical unprefixed keys so existing caches stay valid.
+    pub fn cache_key(&self, cache_key: &str) -> String {
+        match self {
+            OcrBackend::Lens => cache_key.to_string(),
+            OcrBackend::Tesseract => format!("tesseract/{cache_key}"),
+        }
+    }
+}
+
+/// OCRs an image with the Tesseract CLI, returning line boxes normalized to the page.
+pub async fn run_tesseract(
+    image_bytes: &[u8],
+    language: OcrLanguage,
+) -> anyhow::Result<Vec<OcrResult>> {
+    // Tesseract cannot read every format we accept (AVIF in particular), so hand it a PNG.
+    let decoded_image = logic::decode_image(image_bytes)?;
+    let mut png_buffer = Cursor::new(Vec::new());
+    decoded_image
+        .write_to(&mut png_buffer, ImageFormat::Png)
+        .map_err(|err| anyhow!("Failed write_to: {err:?}"))?;
+    let png_bytes = png_buffer.into_inner();
+
+    let mut child = Command::new("tesseract")
+        .args(["stdin", "stdout", "-l", language.tesseract_code(), "tsv"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|err| anyhow!("Failed to start tesseract (is it installed?): {err}"))?;
+
+    let mut stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| anyhow!("Failed to open tesseract stdin"))?;
+    stdin.write_all(&png_bytes).await?;
+    drop(stdin);
+
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "tesseract exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    Ok(parse_tsv(
+        &String::from_utf8_lossy(&output.stdout),
+        decoded_image.width(),
+        decoded_image.height(),
+        language,
+    ))
+}
+
+#[derive(Default)]
+struct LineAccumulator {
+    /// Word texts with their boxes in pixels.
+    words: Vec<(String, [f64; 4])>,
+    /// Word confidences, 0 to 100.
+    confidences: Vec<f64>,
+    min_x: f64,
+    min_y: f64,
+    max_x: f64,
+    max_y: f64,
+}
+
+/// Groups Tesseract's word rows into lines. TSV columns are: level, page, block, par,
+/// line, word, left, top, width, height, conf, text.
+pub fn parse_tsv(tsv: &str, width: u32, height: u32, language: OcrLanguage) -> Vec<OcrResult> {
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+
+    let mut lines: BTreeMap<(u32, u32, u32, u32), LineAccumulator> = BTreeMap::new();
+    for row in tsv.lines().skip(1) {
+        let cols: Vec<&str> = row.splitn(12, '\t').collect();
+        if cols.len() < 12 || cols[0] != "5" {
+            continue;
+        }
+        let text = cols[11].trim();
+        if text.is_empty() {
+            continue;
+        }
+        let nums: Vec<f64> = cols[6..10]
+            .iter()
+            .filter_map(|value| value.parse().ok())
+            .collect();
+        let [left, top, w, h] = nums[..] else {
+            continue;
+        };
+        let key: Vec<u32> = cols[1..5]
+            .iter()
+            .filter_map(|value| value.parse().ok())
+            .collect();
+        let [page, block, par, line] = key[..] else {
+            continue;
+        };
+
+        let acc = lines
+            .entry((page, block, par, line))
+            .or_insert_with(|| LineAccumulator {
+                min_x: f64::INFINITY,
+                min_y: f64::INFINITY,
+                max_x: f64::NEG_INFINITY,
+                max_y: f64::NEG_INFINITY,
+                ..LineAccumulator::default()
+            });
+        acc.words.push((text.to_string(), [left, top, w, h]));
+        // Tesseract reports -1 for rows it has no score for.
+        if let Ok(conf) = cols[10].parse::<f64>()
+            && conf >= 0.0
+        {
+            acc.confidences.push(conf);
+        }
+        acc.min_x = acc.min_x.min(left);
+        acc.min_y = acc.min_y.min(top);
+        acc.max_x = acc.max_x.max(left + w);
+        acc.max_y = acc.max_y.max(top + h);
+    }
+
+    let separator = if language.prefers_no_space() { "" } else { " " };
+    lines
+        .into_values()
+        .map(|acc| {
+            let box_width = acc.max_x - acc.min_x;
+            let box_height = acc.max_y - acc.min_y;
+            let is_vertical = language.prefers_vertical() && box_height > box_width;
+            let normalized = |[x, y, w, h]: [f64; 4]| BoundingBox {
+                x: x / width as f64,
+                y: y / height as f64,
+                width: w / width as f64,
+                height: h / height as f64,
+                rotation: None,
+            };
+            let text = acc
+                .words
+                .iter()
+                .map(|(text, _)| text.as_str())
+                .collect::<Vec<_>>()
+                .join(separator);
+            let words = acc
+                .words
+                .into_iter()
+                .map(|(text, bounds)| WordBox {
+                    text,
+                    tight_bounding_box: normalized(bounds),
+                })
+                .collect();
+            OcrResult {
+                text,
+                tight_bounding_box: BoundingBox {
+                    x: acc.min_x / width as f64,
+                    y: acc.min_y / height as f64,
+                    width: box_width / width as f64,
+                    height: box_height / height as f64,
+                    rotation: None,
+                },
+                is_merged: Some(false),
+                forced_orientation: Some(if is_vertical {
+                    "vertical".into()
+                } else {
+                    "horizontal".into()
+                }),
+                confidence: (!acc.confidences.is_empty()).then(|| {
+                    acc.confidences.iter().sum::<f64>() / acc.confidences.len() as f64 / 100.0
+                }),
+                words: Some(words),
+                font_size_hint: None,
+            }
+        })
+        .collect()
+}
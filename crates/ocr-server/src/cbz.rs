@@ -0,0 +1,159 @@
+//! Pages of local CBZ/ZIP chapters, so an archive can be preprocessed without the client
+//! listing page URLs. Pages are the archive's images in natural name order and are cached
+//! under `/local-archive/{hash}/{index}`, where the hash is taken over the archive bytes so
+//! moving or renaming the file keeps its cache.
+
+use std::{
+    cmp::Ordering,
+    fs::File,
+    io::{Cursor, Read, Seek},
+    path::{Path, PathBuf},
+};
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+use zip::ZipArchive;
+
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp", "avif", "gif", "bmp"];
+/// Hex digits of the content hash kept in cache keys.
+const HASH_LEN: usize = 16;
+
+#[derive(Debug, Clone)]
+enum Storage {
+    File(PathBuf),
+    Memory(Bytes),
+}
+
+/// An opened archive with its page list. Entries are read on demand, so a chapter on
+/// disk is never loaded whole.
+#[derive(Debug, Clone)]
+pub struct PageArchive {
+    storage: Storage,
+    hash: String,
+    pages: Vec<String>,
+}
+
+impl PageArchive {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut File::open(path)?, &mut hasher)?;
+        let pages = page_names(&mut ZipArchive::new(File::open(path)?)?);
+        Self::new(Storage::File(path.to_path_buf()), hasher, pages)
+    }
+
+    pub fn from_bytes(bytes: Bytes) -> anyhow::Result<Self> {
+        let hasher = Sha256::new_with_prefix(&bytes);
+        let pages = page_names(&mut ZipArchive::new(Cursor::new(bytes.clone()))?);
+        Self::new(Storage::Memory(bytes), hasher, pages)
+    }
+
+    fn new(storage: Storage, hasher: Sha256, pages: Vec<String>) -> anyhow::Result<Self> {
+        if pages.is_empty() {
+            return Err(anyhow!("archive contains no images"));
+        }
+        let hash = format!("{:x}", hasher.finalize())[..HASH_LEN].to_string();
+        Ok(Self {
+            storage,
+            hash,
+            pages,
+        })
+    }
+
+    /// The path standing in for a chapter URL; page `i` is at `{base_url}/{i}`.
+    pub fn base_url(&self) -> String {
+        format!("/local-archive/{}", self.hash)
+    }
+
+    pub fn page_urls(&self) -> Vec<String> {
+        let base_url = self.base_url();
+        (0..self.pages.len())
+            .map(|index| format!("{base_url}/{index}"))
+            .collect()
+    }
+
+    pub fn page_names(&self) -> &[String] {
+        &self.pages
+    }
+
+    /// The bytes of the 0-based page `index`.
+    pub fn page_bytes(&self, index: usize) -> anyhow::Result<Vec<u8>> {
+        let name = self
+            .pages
+            .get(index)
+            .ok_or_else(|| anyhow!("archive has no page {index}"))?;
+        match &self.storage {
+            Storage::File(path) => read_entry(&mut ZipArchive::new(File::open(path)?)?, name),
+            Storage::Memory(bytes) => {
+                read_entry(&mut ZipArchive::new(Cursor::new(bytes.clone()))?, name)
+            }
+        }
+    }
+}
+
+fn read_entry<R: Read + Seek>(zip: &mut ZipArchive<R>, name: &str) -> anyhow::Result<Vec<u8>> {
+    let mut entry = zip.by_name(name)?;
+    let mut bytes = Vec::with_capacity(entry.size() as usize);
+    entry.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Image entries in reading order, skipping folders and macOS metadata.
+fn page_names<R: Read + Seek>(zip: &mut ZipArchive<R>) -> Vec<String> {
+    let mut names: Vec<String> = zip
+        .file_names()
+        .filter(|name| {
+            let file_name = name.rsplit('/').next().unwrap_or(name);
+            !name.starts_with("__MACOSX/")
+                && !file_name.starts_with('.')
+                && file_name
+                    .rsplit_once('.')
+                    .is_some_and(|(_, ext)| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        })
+        .map(str::to_string)
+        .collect();
+    names.sort_by(|a, b| natural_cmp(a, b));
+    names
+}
+
+/// Compares names with digit runs taken as numbers, so `2.jpg` sorts before `10.jpg`.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let x = take_number(&mut a);
+                let y = take_number(&mut b);
+                // Compare by magnitude, then by length so `01` and `1` stay distinct.
+                let ordering = x
+                    .trim_start_matches('0')
+                    .len()
+                    .cmp(&y.trim_start_matches('0').len())
+                    .then_with(|| x.trim_start_matches('0').cmp(y.trim_start_matches('0')))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_number(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits
+}
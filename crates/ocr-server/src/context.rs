@@ -0,0 +1,201 @@
+//! Canonical cache contexts built from Suwayomi metadata, so pages are labelled
+//! "Series Title / Chapter Name" whichever client requested them.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::anyhow;
+use serde::Deserialize;
+use serde_json::{Value, json};
+use tracing::warn;
+
+use crate::locks;
+
+/// Same loopback address page fetches are forced to.
+const SUWAYOMI_GRAPHQL_URL: &str = "http://127.0.0.1:4568/api/graphql";
+
+/// The Suwayomi ids a client can send instead of a free-form context. The most specific
+/// one wins: a chapter gives "Series Title / Chapter Name", a manga its title and a
+/// source, on its own, the source's name.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+pub struct ContextIds {
+    #[serde(default, alias = "sourceId", deserialize_with = "lenient_id")]
+    pub source_id: Option<i64>,
+    #[serde(default, alias = "mangaId", deserialize_with = "lenient_id")]
+    pub manga_id: Option<i64>,
+    #[serde(default, alias = "chapterId", deserialize_with = "lenient_id")]
+    pub chapter_id: Option<i64>,
+}
+
+/// Accepts ids as numbers or strings: Suwayomi hands source ids out as strings, and query
+/// strings carry nothing else.
+pub fn lenient_id<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        Number(i64),
+        Text(String),
+    }
+    match Option::<Id>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Id::Number(id)) => Ok(Some(id)),
+        Some(Id::Text(text)) if text.trim().is_empty() => Ok(None),
+        Some(Id::Text(text)) => text
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| serde::de::Error::custom(format!("Invalid Suwayomi id: {text}"))),
+    }
+}
+
+impl ContextIds {
+    pub fn is_empty(&self) -> bool {
+        self.source_id.is_none() && self.manga_id.is_none() && self.chapter_id.is_none()
+    }
+}
+
+/// Resolves and remembers contexts. Only successful resolutions are cached, so a
+/// Suwayomi outage is retried on the next page.
+pub struct ContextResolver {
+    http: reqwest::Client,
+    graphql_url: String,
+    resolved: RwLock<HashMap<ContextIds, Arc<str>>>,
+}
+
+impl Default for ContextResolver {
+    fn default() -> Self {
+        Self::new(SUWAYOMI_GRAPHQL_URL)
+    }
+}
+
+#[derive(Deserialize)]
+struct MangaTitle {
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct ChapterWithManga {
+    name: String,
+    manga: MangaTitle,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SourceName {
+    display_name: String,
+}
+
+impl ContextResolver {
+    /// A resolver that queries `graphql_url` instead of the loopback Suwayomi server.
+    pub fn new(graphql_url: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            graphql_url: graphql_url.into(),
+            resolved: RwLock::default(),
+        }
+    }
+
+    /// The canonical context for `ids`, or `fallback` when there are no ids or Suwayomi
+    /// cannot resolve them.
+    pub async fn resolve_or(
+        &self,
+        ids: ContextIds,
+        user: Option<&str>,
+        pass: Option<&str>,
+        fallback: &str,
+    ) -> String {
+        if ids.is_empty() {
+            return fallback.to_string();
+        }
+        if let Some(context) = locks::read(&self.resolved).get(&ids) {
+            return context.to_string();
+        }
+        match self.fetch(ids, user, pass).await {
+            Ok(context) => {
+                locks::write(&self.resolved).insert(ids, Arc::from(context.as_str()));
+                context
+            }
+            Err(err) => {
+                warn!("Failed to resolve OCR context for {ids:?}: {err}");
+                fallback.to_string()
+            }
+        }
+    }
+
+    async fn fetch(
+        &self,
+        ids: ContextIds,
+        user: Option<&str>,
+        pass: Option<&str>,
+    ) -> anyhow::Result<String> {
+        if let Some(chapter_id) = ids.chapter_id {
+            let data = self
+                .request(
+                    user,
+                    pass,
+                    "query ($id: Int!) { chapter(id: $id) { name manga { title } } }",
+                    json!({ "id": chapter_id }),
+                )
+                .await?;
+            let chapter: ChapterWithManga = serde_json::from_value(data["chapter"].clone())?;
+            return Ok(format!("{} / {}", chapter.manga.title, chapter.name));
+        }
+        if let Some(manga_id) = ids.manga_id {
+            let data = self
+                .request(
+                    user,
+                    pass,
+                    "query ($id: Int!) { manga(id: $id) { title } }",
+                    json!({ "id": manga_id }),
+                )
+                .await?;
+            let manga: MangaTitle = serde_json::from_value(data["manga"].clone())?;
+            return Ok(manga.title);
+        }
+        let source_id = ids.source_id.ok_or_else(|| anyhow!("No Suwayomi ids"))?;
+        // Source ids are 64-bit, which GraphQL only carries as strings.
+        let data = self
+            .request(
+                user,
+                pass,
+                "query ($id: LongString!) { source(id: $id) { displayName } }",
+                json!({ "id": source_id.to_string() }),
+            )
+            .await?;
+        let source: SourceName = serde_json::from_value(data["source"].clone())?;
+        Ok(source.display_name)
+    }
+
+    async fn request(
+        &self,
+        user: Option<&str>,
+        pass: Option<&str>,
+        query: &str,
+        variables: Value,
+    ) -> anyhow::Result<Value> {
+        let mut request = self
+            .http
+            .post(&self.graphql_url)
+            .json(&json!({ "query": query, "variables": variables }));
+        if let Some(user) = user {
+            request = request.basic_auth(user, pass);
+        }
+        let response: Value = request
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| anyhow!("Suwayomi GraphQL request failed: {err}"))?
+            .json()
+            .await
+            .map_err(|err| anyhow!("Invalid Suwayomi GraphQL response: {err}"))?;
+        if let Some(errors) = response.get("errors") {
+            return Err(anyhow!("Suwayomi GraphQL error: {errors}"));
+        }
+        Ok(response["data"].clone())
+    }
+}
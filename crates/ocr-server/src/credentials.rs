@@ -0,0 +1,143 @@
+//! Credentials for page sources, stored once so clients can stop sending `user`/`pass` on
+//! every request, where they end up in access logs. Entries are keyed by a URL prefix
+//! (a host, `host:port` or `host/path`) and the longest prefix matching a page URL wins.
+//! Credentials sent inline with a request still take precedence.
+
+use std::sync::{Arc, RwLock};
+
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    locks,
+    state::{AppState, now_unix},
+};
+
+/// No `Debug`, so the password cannot slip into a log line.
+#[derive(Clone, Deserialize)]
+pub struct SourceCredential {
+    pub prefix: String,
+    pub user: String,
+    pub pass: Option<String>,
+}
+
+/// What `GET /credentials` shows of an entry: never the password.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct MaskedCredential {
+    pub prefix: String,
+    pub user: String,
+    pub has_password: bool,
+    pub updated_at: i64,
+}
+
+/// The stored entries, kept on [`AppState::credentials`] so page fetches need no database
+/// read. Clones share the entries.
+#[derive(Clone, Default)]
+pub struct StoredCredentials(Arc<RwLock<Vec<SourceCredential>>>);
+
+impl StoredCredentials {
+    /// The stored user and password for `url`, from the longest prefix it falls under.
+    /// Page fetches pass the loopback URL they actually request.
+    pub fn lookup(&self, url: &str) -> Option<(String, Option<String>)> {
+        let rest = strip_scheme(url).to_ascii_lowercase();
+        locks::read(&self.0)
+            .iter()
+            .filter(|credential| matches_prefix(&rest, &credential.prefix))
+            .max_by_key(|credential| credential.prefix.len())
+            .map(|credential| (credential.user.clone(), credential.pass.clone()))
+    }
+
+    /// `user` and `pass` as sent, or the stored ones for `url` when none were.
+    pub fn or_stored(
+        &self,
+        url: &str,
+        user: Option<String>,
+        pass: Option<String>,
+    ) -> (Option<String>, Option<String>) {
+        match user {
+            Some(user) => (Some(user), pass),
+            None => self
+                .lookup(url)
+                .map_or((None, None), |(user, pass)| (Some(user), pass)),
+        }
+    }
+}
+
+/// `prefix` without its scheme or trailing slash, or `None` when nothing is left.
+pub fn normalize_prefix(prefix: &str) -> Option<String> {
+    let prefix = strip_scheme(prefix.trim()).trim_end_matches('/');
+    (!prefix.is_empty()).then(|| prefix.to_ascii_lowercase())
+}
+
+fn strip_scheme(url: &str) -> &str {
+    url.split_once("://").map_or(url, |(_, rest)| rest)
+}
+
+/// Stores or replaces the entry for `credential.prefix`.
+pub fn save(state: &AppState, credential: &SourceCredential) -> anyhow::Result<()> {
+    let prefix = normalize_prefix(&credential.prefix)
+        .ok_or_else(|| anyhow::anyhow!("prefix must not be empty"))?;
+    state.conn()?.execute(
+        "INSERT OR REPLACE INTO source_credentials (prefix, user, pass, updated_at)
+         VALUES (?, ?, ?, ?)",
+        params![prefix, credential.user, credential.pass, now_unix()],
+    )?;
+    load(state)
+}
+
+/// Removes the entry for `prefix`; `false` when there was none.
+pub fn remove(state: &AppState, prefix: &str) -> anyhow::Result<bool> {
+    let Some(prefix) = normalize_prefix(prefix) else {
+        return Ok(false);
+    };
+    let removed = state.conn()?.execute(
+        "DELETE FROM source_credentials WHERE prefix = ?",
+        params![prefix],
+    )?;
+    load(state)?;
+    Ok(removed > 0)
+}
+
+/// Every entry, passwords left out.
+pub fn list(state: &AppState) -> anyhow::Result<Vec<MaskedCredential>> {
+    let conn = state.conn()?;
+    let mut stmt = conn.prepare(
+        "SELECT prefix, user, pass IS NOT NULL AND pass != '', updated_at
+         FROM source_credentials ORDER BY prefix",
+    )?;
+    let entries = stmt
+        .query_map([], |row| {
+            Ok(MaskedCredential {
+                prefix: row.get(0)?,
+                user: row.get(1)?,
+                has_password: row.get(2)?,
+                updated_at: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+/// Makes the entries stored in `state`'s database the ones [`AppState::credentials`] sees.
+pub fn load(state: &AppState) -> anyhow::Result<()> {
+    let conn = state.conn()?;
+    let mut stmt = conn.prepare("SELECT prefix, user, pass FROM source_credentials")?;
+    let entries = stmt
+        .query_map([], |row| {
+            Ok(SourceCredential {
+                prefix: row.get(0)?,
+                user: row.get(1)?,
+                pass: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    *locks::write(&state.credentials.0) = entries;
+    Ok(())
+}
+
+/// Whether `rest` (a URL without its scheme) starts with `prefix` at a boundary, so
+/// `example.com` matches neither `example.com.evil` nor `example.community`.
+fn matches_prefix(rest: &str, prefix: &str) -> bool {
+    rest.strip_prefix(prefix)
+        .is_some_and(|after| after.is_empty() || after.starts_with(['/', ':', '?', '#']))
+}
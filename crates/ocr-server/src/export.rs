@@ -0,0 +1,189 @@
+//! Cache export as gzip-compressed newline-delimited JSON, one `{"cache_key", ...entry}`
+//! object per line after a leading `{"manifest": ...}` line. The export is produced and the
+//! import consumed in batches, so neither needs the whole cache in memory. An export can be
+//! limited to one context or a context prefix, e.g. to share a single series. The raw Lens
+//! lines kept for re-merging are left out, so imported pages can only be re-merged once
+//! they have been OCRed again.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+};
+
+use bytes::Bytes;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use futures::Stream;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::state::{AppState, CacheEntry, ImportReport, now_unix};
+
+const BATCH_SIZE: usize = 500;
+
+/// Suggested file name for downloads.
+pub const EXPORT_FILE_NAME: &str = "manatan-ocr-cache.ndjson.gz";
+
+#[derive(Serialize, Deserialize)]
+struct ExportLine {
+    cache_key: String,
+    #[serde(flatten)]
+    entry: CacheEntry,
+}
+
+/// Which rows an export includes; both unset exports the whole cache.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportFilter {
+    /// Only pages cached under exactly this context.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+    /// Only pages whose context starts with this, e.g. a series title.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_prefix: Option<String>,
+}
+
+impl ExportFilter {
+    /// This filter with blank fields dropped.
+    pub fn normalized(self) -> Self {
+        let non_blank = |value: Option<String>| value.filter(|value| !value.trim().is_empty());
+        Self {
+            context: non_blank(self.context),
+            context_prefix: non_blank(self.context_prefix),
+        }
+    }
+}
+
+/// First line of an export, for checking an import got what was sent.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportManifest {
+    pub exported_at: i64,
+    /// Entry lines following the manifest.
+    pub entries: usize,
+    /// Every context the entries were cached under.
+    pub contexts: Vec<String>,
+    #[serde(default)]
+    pub filter: ExportFilter,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ManifestLine {
+    manifest: ExportManifest,
+}
+
+/// Whether `body` starts with the gzip magic bytes.
+pub fn is_gzip(body: &[u8]) -> bool {
+    body.starts_with(&[0x1f, 0x8b])
+}
+
+/// Streams the rows `filter` selects as gzip NDJSON. Rows are read on a blocking thread one
+/// batch at a time, and each batch is sent as soon as it is compressed.
+pub fn export_stream(
+    state: AppState,
+    filter: ExportFilter,
+) -> impl Stream<Item = io::Result<Bytes>> {
+    let (sender, receiver) = mpsc::channel::<io::Result<Bytes>>(2);
+    tokio::task::spawn_blocking(move || {
+        if let Err(err) = write_export(&state, &filter, &sender) {
+            warn!("[EXPORT] Cache export failed: {err}");
+            let _ = sender.blocking_send(Err(io::Error::other(err.to_string())));
+        }
+    });
+    futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    })
+}
+
+fn write_export(
+    state: &AppState,
+    filter: &ExportFilter,
+    sender: &mpsc::Sender<io::Result<Bytes>>,
+) -> anyhow::Result<()> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    let contexts = state.export_contexts(filter)?;
+    let manifest = ExportManifest {
+        exported_at: now_unix(),
+        entries: contexts.iter().map(|(_, count)| count).sum(),
+        contexts: contexts.into_iter().map(|(context, _)| context).collect(),
+        filter: filter.clone(),
+    };
+    serde_json::to_writer(&mut encoder, &ManifestLine { manifest })?;
+    encoder.write_all(b"\n")?;
+
+    let mut after_key: Option<String> = None;
+    loop {
+        let batch = state.export_cache_batch(after_key.as_deref(), BATCH_SIZE, filter)?;
+        let Some((last_key, _)) = batch.last() else {
+            break;
+        };
+        after_key = Some(last_key.clone());
+        let full_batch = batch.len() == BATCH_SIZE;
+
+        for (cache_key, entry) in batch {
+            serde_json::to_writer(&mut encoder, &ExportLine { cache_key, entry })?;
+            encoder.write_all(b"\n")?;
+        }
+        encoder.flush()?;
+        let chunk = std::mem::take(encoder.get_mut());
+        if !chunk.is_empty() && sender.blocking_send(Ok(Bytes::from(chunk))).is_err() {
+            // The client went away.
+            return Ok(());
+        }
+        if !full_batch {
+            break;
+        }
+    }
+    let tail = encoder.finish()?;
+    let _ = sender.blocking_send(Ok(Bytes::from(tail)));
+    Ok(())
+}
+
+/// Imports a gzip NDJSON export, whole or filtered, replacing existing rows only when
+/// `overwrite` is set. Lines that are not valid entries are counted as invalid rather than
+/// failing the import. The manifest, when there is one, is passed on in the report.
+pub fn import_ndjson_gz(
+    state: &AppState,
+    body: &[u8],
+    overwrite: bool,
+) -> anyhow::Result<ImportReport> {
+    let reader = BufReader::new(GzDecoder::new(body));
+    let mut report = ImportReport::default();
+    let mut batch = HashMap::with_capacity(BATCH_SIZE);
+    for (index, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value: Value = match serde_json::from_str(&line) {
+            Ok(value) => value,
+            Err(_) => {
+                report.note_invalid(format!("line {}", index + 1));
+                continue;
+            }
+        };
+        if value.get("manifest").is_some() && value.get("cache_key").is_none() {
+            match serde_json::from_value::<ManifestLine>(value) {
+                Ok(ManifestLine { manifest }) => report.manifest = Some(manifest),
+                Err(_) => report.note_invalid(format!("line {}", index + 1)),
+            }
+            continue;
+        }
+        let key = value["cache_key"]
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("line {}", index + 1));
+        match serde_json::from_value::<ExportLine>(value) {
+            Ok(ExportLine { cache_key, entry }) => {
+                batch.insert(cache_key, entry);
+            }
+            Err(_) => report.note_invalid(key),
+        }
+        if batch.len() >= BATCH_SIZE {
+            report.absorb(state.import_cache(std::mem::take(&mut batch), overwrite));
+        }
+    }
+    if !batch.is_empty() {
+        report.absorb(state.import_cache(batch, overwrite));
+    }
+    Ok(report)
+}
@@ -1,131 +1,1478 @@
 use std::{
     collections::HashMap,
+    convert::Infallible,
     sync::{Arc, Mutex, atomic::Ordering},
+    time::Instant,
 };
 
 use axum::{
     Json,
-    extract::{Query, State},
-    http::StatusCode,
+    body::{Body, Bytes},
+    extract::{FromRequest, Multipart, Query, Request, State, ws::WebSocketUpgrade},
+    http::{
+        StatusCode,
+        header::{CONTENT_DISPOSITION, CONTENT_TYPE},
+    },
+    response::{
+        IntoResponse, Response,
+        sse::{Event as SseEvent, KeepAlive, Sse},
+    },
 };
-use futures::StreamExt;
+use futures::{Stream, StreamExt};
 use serde::Deserialize;
+use tokio::sync::watch;
 use tracing::{info, warn};
 
 use crate::{
-    jobs,
+    archive::{self, ArchiveSummary},
+    backend::OcrBackend,
+    cbz,
+    context::{self, ContextIds},
+    credentials::{self, MaskedCredential, SourceCredential},
+    export::{self, ExportFilter},
+    headers::{self, PageHeaders},
+    health::{self, HealthReport, HealthStatus},
+    imaging::{self, OutputFormat},
+    job_history::{self, JobRecord},
+    jobs::{self, JobSettings},
     language::OcrLanguage,
-    logic,
-    state::{AppState, CacheEntry},
+    locks,
+    logic::{self, CacheKeyConfig, Granularity},
+    manual::{self, ManualBlock},
+    merge::{MergeConfig, OrientationHint},
+    metrics::{self, METRICS},
+    normalize::TextNormalization,
+    page_events::{self, PageFilter, PageStreamParams},
+    pdf,
+    preprocess::Preprocess,
+    proxy::ProxyConfig,
+    prune::{self, PruneOptions, PruneReport},
+    remerge::{self, RemergeRequest},
+    retry::TimeoutSettings,
+    selftest::{self, SelfTestReport},
+    spread,
+    state::{
+        AppState, CacheEntry, CacheKeyMigration, DbBusy, EntrySource, ImportReport, OcrConfig,
+        PreprocessProgress, PreprocessStatus, TextHit,
+    },
+    text_export,
+    throttle::LENS_PACER,
 };
 
+/// Set on a `GET /ocr` cache hit for a page a chapter job cached empty without OCR, so
+/// clients can tell a skipped page from one without text.
+pub const SKIPPED_REASON_HEADER: &str = "x-skipped-reason";
+
 #[derive(Deserialize)]
 pub struct OcrRequest {
     pub url: String,
     pub user: Option<String>,
     pub pass: Option<String>,
-    #[serde(default, rename = "base_url", alias = "baseUrl")]
-    pub base_url: Option<String>,
+    #[serde(default, rename = "base_url", alias = "baseUrl")]
+    pub base_url: Option<String>,
+    #[serde(default = "default_context")]
+    pub context: String,
+    /// Suwayomi ids the cache context is built from instead of `context`, which is then
+    /// only used when they cannot be resolved. Spelled out rather than a flattened
+    /// [`ContextIds`], which query strings cannot deserialize.
+    #[serde(default, alias = "sourceId", deserialize_with = "context::lenient_id")]
+    pub source_id: Option<i64>,
+    #[serde(default, alias = "mangaId", deserialize_with = "context::lenient_id")]
+    pub manga_id: Option<i64>,
+    #[serde(default, alias = "chapterId", deserialize_with = "context::lenient_id")]
+    pub chapter_id: Option<i64>,
+    pub add_space_on_merge: Option<bool>,
+    #[serde(alias = "lang")]
+    pub language: Option<OcrLanguage>,
+    #[serde(default)]
+    pub backend: Option<OcrBackend>,
+    /// Skips the cache lookup and overwrites the stored result, for pages Lens got wrong.
+    #[serde(default)]
+    pub force: bool,
+    /// Merge thresholds for this request only, as a JSON object (URL-encoded in query
+    /// strings). Results produced with an override are never cached.
+    #[serde(default, deserialize_with = "inline_merge_config")]
+    pub merge: Option<MergeConfig>,
+    /// `word` adds per-word boxes to each line of pages whose engine reports them.
+    #[serde(default)]
+    pub granularity: Granularity,
+    /// Chunk preprocessing for this request instead of the saved setting. Results are
+    /// cached with the pipeline that produced them.
+    #[serde(default)]
+    pub preprocess: Option<Preprocess>,
+    /// 1-based page to OCR when the URL serves a PDF. Each page is cached on its own.
+    #[serde(default)]
+    pub page: Option<u32>,
+    /// Extra headers for the image fetch, e.g. a `Referer` the source checks. A JSON
+    /// object, URL-encoded in query strings.
+    #[serde(default, deserialize_with = "headers::inline_headers")]
+    pub headers: HashMap<String, String>,
+    /// `Cookie` header value for the image fetch.
+    #[serde(default)]
+    pub cookies: Option<String>,
+    /// Bearer token for the image fetch, for servers behind a token-based auth proxy.
+    /// Cannot be combined with `user`/`pass`.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Corrects which way boxes are read: `vertical` or `horizontal` for every box, or
+    /// `force-vertical-if-square` for single glyphs and near-square boxes. Anything but
+    /// `auto` re-OCRs the page when the stored result was merged under another hint;
+    /// hand-edited and manual pages are served as they are.
+    #[serde(default)]
+    pub orientation: OrientationHint,
+}
+
+impl OcrRequest {
+    /// Whether the request asks for a cached page to be OCRed again and overwritten, as
+    /// opposed to reading it or filling a miss.
+    pub fn rewrites_cache(&self) -> bool {
+        self.force || self.orientation != OrientationHint::Auto || self.preprocess.is_some()
+    }
+
+    /// The in-flight slot for this request's OCR run. Each experiment (an orientation
+    /// hint, a preprocess pipeline, a merge override) gets its own suffix so it never
+    /// shares a run with, or hands its results to, a request for the same page made with
+    /// other settings.
+    pub fn run_key(&self, cache_key: &str) -> String {
+        let mut run_key = cache_key.to_string();
+        if self.orientation != OrientationHint::Auto {
+            run_key = format!("{run_key}#orientation={}", self.orientation.as_str());
+        }
+        if let Some(preprocess) = self.preprocess {
+            run_key = format!("{run_key}#preprocess={}", preprocess.as_str());
+        }
+        if let Some(merge) = &self.merge {
+            run_key = format!(
+                "{run_key}#merge={}",
+                serde_json::to_string(merge).unwrap_or_default()
+            );
+        }
+        run_key
+    }
+}
+
+/// Bundles a request's fetch headers and token, rejecting names or values that are not
+/// valid HTTP and a token sent alongside basic auth credentials.
+fn page_headers(
+    headers: HashMap<String, String>,
+    cookies: Option<String>,
+    token: Option<String>,
+    user: Option<&str>,
+) -> Result<PageHeaders, String> {
+    let token = token.filter(|token| !token.trim().is_empty());
+    if token.is_some() && user.is_some() {
+        return Err("token and user/pass are mutually exclusive".to_string());
+    }
+    let page_headers = PageHeaders {
+        headers,
+        cookies,
+        token,
+    };
+    page_headers.to_header_map()?;
+    Ok(page_headers)
+}
+
+fn default_context() -> String {
+    "No Context".to_string()
+}
+
+/// Accepts a merge override either as an object or as a JSON string, since query strings
+/// cannot carry nested values.
+fn inline_merge_config<'de, D>(deserializer: D) -> Result<Option<MergeConfig>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Inline {
+        Json(String),
+        Config(MergeConfig),
+    }
+
+    match Option::<Inline>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Inline::Config(config)) => Ok(Some(config)),
+        Some(Inline::Json(raw)) if raw.trim().is_empty() => Ok(None),
+        Some(Inline::Json(raw)) => serde_json::from_str(&raw)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+// --- Handlers ---
+
+/// Counters, histograms and gauges in the Prometheus text format.
+pub async fn metrics_handler(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        metrics::render(&METRICS, &state),
+    )
+}
+
+/// A lookup that found every cache connection in use for the whole pool wait answers 503,
+/// so the client retries instead of reading an empty cache as a miss.
+impl From<DbBusy> for (StatusCode, String) {
+    fn from(busy: DbBusy) -> Self {
+        (StatusCode::SERVICE_UNAVAILABLE, busy.to_string())
+    }
+}
+
+pub async fn status_handler(
+    State(state): State<AppState>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let cache_size = state.cache_len()?;
+    Ok(Json(serde_json::json!({
+        "status": "running",
+        "backend": "Rust (manatan-ocr-server)",
+        "requests_processed": state.requests_processed.load(Ordering::Relaxed),
+        "items_in_cache": cache_size,
+        "image_hash_reuses": state.image_hash_reuses(),
+        "active_jobs": state.active_jobs.load(Ordering::Relaxed),
+        "lens_pacing": LENS_PACER.snapshot(),
+        "lens_concurrency": state.lens_limiter.snapshot(),
+        "self_test": selftest::latest(),
+    })))
+}
+
+/// Re-runs the capability checks and returns their results.
+pub async fn self_test_handler(State(state): State<AppState>) -> Json<SelfTestReport> {
+    Json(selftest::run(&state).await)
+}
+
+#[derive(Deserialize)]
+pub struct HealthParams {
+    pub backend: Option<OcrBackend>,
+    /// Probe again even when the cached report is still fresh.
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// Confirms the backend can OCR an image end to end, answering 503 while it can't.
+pub async fn health_handler(
+    State(state): State<AppState>,
+    Query(params): Query<HealthParams>,
+) -> (StatusCode, Json<HealthReport>) {
+    let backend = params.backend.unwrap_or_default();
+    let report = health::check(&state, backend, params.refresh).await;
+    let status = match report.status {
+        HealthStatus::Ok => StatusCode::OK,
+        HealthStatus::Degraded => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(report))
+}
+
+pub async fn get_config_handler(State(state): State<AppState>) -> Json<OcrConfig> {
+    Json(state.ocr_config())
+}
+
+pub async fn set_config_handler(
+    State(state): State<AppState>,
+    Json(config): Json<OcrConfig>,
+) -> Result<Json<OcrConfig>, (StatusCode, String)> {
+    if config.deadline_secs == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "deadline_secs must be greater than zero".to_string(),
+        ));
+    }
+    if config.max_concurrent_lens_calls == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "max_concurrent_lens_calls must be greater than zero".to_string(),
+        ));
+    }
+    if config.retry_attempts == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "retry_attempts must be at least one".to_string(),
+        ));
+    }
+    if config.chunk_width_limit == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "chunk_width_limit must be greater than zero".to_string(),
+        ));
+    }
+    if let Some(auto_prune) = &config.auto_prune {
+        auto_prune
+            .validate()
+            .map_err(|message| (StatusCode::BAD_REQUEST, format!("auto_prune: {message}")))?;
+    }
+    config
+        .merge
+        .validate()
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    config
+        .proxy
+        .validate()
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    config
+        .jobs
+        .validate()
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    config
+        .timeouts
+        .validate()
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    state
+        .set_ocr_config(&config)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(config))
+}
+
+pub async fn get_merge_config_handler(State(state): State<AppState>) -> Json<MergeConfig> {
+    Json(state.ocr_config().merge)
+}
+
+pub async fn get_preprocess_config_handler(State(state): State<AppState>) -> Json<Preprocess> {
+    Json(state.ocr_config().preprocess)
+}
+
+/// Replaces the saved chunk preprocessing. Pages cached with another pipeline are OCRed
+/// again on their next read.
+pub async fn set_preprocess_config_handler(
+    State(state): State<AppState>,
+    Json(preprocess): Json<Preprocess>,
+) -> Result<Json<Preprocess>, (StatusCode, String)> {
+    let config = OcrConfig {
+        preprocess,
+        ..state.ocr_config()
+    };
+    state
+        .set_ocr_config(&config)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(config.preprocess))
+}
+
+/// The outbound proxy, with its password masked.
+pub async fn get_proxy_config_handler(State(state): State<AppState>) -> Json<ProxyConfig> {
+    Json(state.ocr_config().proxy.masked())
+}
+
+/// Replaces the outbound proxy. It applies from the next page fetch or Lens call on. A
+/// masked password, as `GET /proxy-config` returns it, keeps the saved one.
+pub async fn set_proxy_config_handler(
+    State(state): State<AppState>,
+    Json(proxy): Json<ProxyConfig>,
+) -> Result<Json<ProxyConfig>, (StatusCode, String)> {
+    proxy
+        .validate()
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    let current = state.ocr_config();
+    let config = OcrConfig {
+        proxy: proxy.unmask(&current.proxy),
+        ..current
+    };
+    state
+        .set_ocr_config(&config)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(config.proxy.masked()))
+}
+
+pub async fn get_job_settings_handler(State(state): State<AppState>) -> Json<JobSettings> {
+    Json(state.ocr_config().jobs)
+}
+
+/// Replaces chapter job pacing. Running jobs pick it up from their next page.
+pub async fn set_job_settings_handler(
+    State(state): State<AppState>,
+    Json(jobs): Json<JobSettings>,
+) -> Result<Json<JobSettings>, (StatusCode, String)> {
+    jobs.validate()
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    let config = OcrConfig {
+        jobs,
+        ..state.ocr_config()
+    };
+    state
+        .set_ocr_config(&config)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(config.jobs))
+}
+
+pub async fn get_text_normalization_handler(
+    State(state): State<AppState>,
+) -> Json<TextNormalization> {
+    Json(state.ocr_config().normalization)
+}
+
+/// Replaces the text cleanup rules. Cached pages keep their text until OCR'd again or
+/// re-merged.
+pub async fn set_text_normalization_handler(
+    State(state): State<AppState>,
+    Json(normalization): Json<TextNormalization>,
+) -> Result<Json<TextNormalization>, (StatusCode, String)> {
+    let config = OcrConfig {
+        normalization,
+        ..state.ocr_config()
+    };
+    state
+        .set_ocr_config(&config)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(config.normalization))
+}
+
+pub async fn get_timeout_settings_handler(State(state): State<AppState>) -> Json<TimeoutSettings> {
+    Json(state.ocr_config().timeouts)
+}
+
+/// Replaces the download and OCR call limits. Pages already in flight keep the old ones.
+pub async fn set_timeout_settings_handler(
+    State(state): State<AppState>,
+    Json(timeouts): Json<TimeoutSettings>,
+) -> Result<Json<TimeoutSettings>, (StatusCode, String)> {
+    timeouts
+        .validate()
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    let config = OcrConfig {
+        timeouts,
+        ..state.ocr_config()
+    };
+    state
+        .set_ocr_config(&config)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(config.timeouts))
+}
+
+pub async fn get_cache_key_config_handler(State(state): State<AppState>) -> Json<CacheKeyConfig> {
+    Json(state.ocr_config().cache_key)
+}
+
+/// Replaces the cache key normalization. Pages already cached keep their old keys until
+/// `/cache-key-config/migrate` rewrites them.
+pub async fn set_cache_key_config_handler(
+    State(state): State<AppState>,
+    Json(cache_key): Json<CacheKeyConfig>,
+) -> Result<Json<CacheKeyConfig>, (StatusCode, String)> {
+    let config = OcrConfig {
+        cache_key,
+        ..state.ocr_config()
+    };
+    state
+        .set_ocr_config(&config)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(config.cache_key))
+}
+
+/// Rewrites every cached key under the saved normalization, merging pages that end up
+/// with the same key.
+pub async fn migrate_cache_keys_handler(
+    State(state): State<AppState>,
+) -> Result<Json<CacheKeyMigration>, (StatusCode, String)> {
+    let config = state.ocr_config().cache_key;
+    tokio::task::spawn_blocking(move || state.migrate_cache_keys(&config))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Re-merges the cached pages a request covers from their stored raw lines with the
+/// current merge settings. Runs in the background; `GET /remerge` reports progress and the
+/// outcome.
+pub async fn remerge_handler(
+    State(state): State<AppState>,
+    Json(req): Json<RemergeRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let Some(scope) = req.scope(&state.cache_key_config()) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Give a url or context, or set all to re-merge the whole cache".to_string(),
+        ));
+    };
+    let lookup = state.clone();
+    let (keys, skipped) = tokio::task::spawn_blocking(move || remerge::candidates(&lookup, &scope))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match remerge::spawn(&state, keys, skipped) {
+        Some(total) => Ok(Json(serde_json::json!({
+            "status": "started",
+            "job_id": remerge::JOB_KEY,
+            "total": total,
+            "skipped": skipped,
+        }))),
+        None => Err((
+            StatusCode::CONFLICT,
+            "A re-merge is already running".to_string(),
+        )),
+    }
+}
+
+pub async fn remerge_status_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
+    let progress = locks::read(&state.active_chapter_jobs)
+        .get(remerge::JOB_KEY)
+        .map(|progress| (progress.current, progress.total));
+    let mut body = serde_json::json!({
+        "status": if progress.is_some() { "running" } else { "idle" },
+        "last_report": remerge::last_report(&state),
+    });
+    if let Some((current, total)) = progress {
+        body["progress"] = current.into();
+        body["total"] = total.into();
+    }
+    Json(body)
+}
+
+#[derive(Deserialize)]
+pub struct JobHistoryParams {
+    pub limit: Option<usize>,
+}
+
+/// Finished chapter jobs, newest first, with the pages each one failed on.
+pub async fn job_history_handler(
+    State(state): State<AppState>,
+    Query(params): Query<JobHistoryParams>,
+) -> Result<Json<Vec<JobRecord>>, (StatusCode, String)> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 500);
+    tokio::task::spawn_blocking(move || job_history::recent(&state, limit))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Replaces the line merging thresholds. Invalid values are rejected and the previous
+/// thresholds stay in effect.
+pub async fn set_merge_config_handler(
+    State(state): State<AppState>,
+    Json(merge): Json<MergeConfig>,
+) -> Result<Json<MergeConfig>, (StatusCode, String)> {
+    merge
+        .validate()
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    let config = OcrConfig {
+        merge,
+        ..state.ocr_config()
+    };
+    state
+        .set_ocr_config(&config)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    Ok(Json(config.merge))
+}
+
+/// Returns the OCR lines for a page. When the configured deadline cuts processing short,
+/// the response is `{ "partial": true, "results": [...] }` instead of a bare array and
+/// nothing is cached, so a retry can complete the page. Forced requests always answer
+/// with an object carrying `"regenerated": true`. Requests with a `merge` override bypass
+/// the cache in both directions.
+pub async fn ocr_handler(
+    State(state): State<AppState>,
+    Query(params): Query<OcrRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    let language = params.language.unwrap_or_default();
+    let backend = params.backend.unwrap_or_default();
+    if params.page == Some(0) {
+        return Err((StatusCode::BAD_REQUEST, "page is 1-based".to_string()));
+    }
+    let fetch_headers = page_headers(
+        params.headers.clone(),
+        params.cookies.clone(),
+        params.token.clone(),
+        params.user.as_deref(),
+    )
+    .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    let key_config = state.cache_key_config();
+    let cache_key = pdf::page_cache_key(
+        &backend.cache_key(&logic::get_cache_key(
+            &params.url,
+            Some(language),
+            &key_config,
+        )),
+        params.page,
+    );
+    let chapter_key = params
+        .base_url
+        .as_ref()
+        .map(|base| logic::get_cache_key(base, Some(language), &key_config));
+    info!("OCR Handler: Incoming request for cache_key={}", cache_key);
+
+    let mut config = state.ocr_config();
+    let run_key = params.run_key(&cache_key);
+    if params.orientation != OrientationHint::Auto {
+        config.merge.orientation_hint = params.orientation;
+    }
+    if let Some(preprocess) = params.preprocess {
+        config.preprocess = preprocess;
+    }
+    if let Some(merge) = &params.merge {
+        merge
+            .validate()
+            .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+        config.merge = merge.clone();
+        info!(
+            "OCR Handler: Merge override for cache_key={}. Skipping cache.",
+            cache_key
+        );
+    } else if params.force && !state.is_manual_entry(&cache_key)? {
+        // Manual pages are never re-OCRed; a forced request falls through to the cache.
+        info!(
+            "OCR Handler: Forced re-OCR for cache_key={}. Skipping cache.",
+            cache_key
+        );
+    } else if let Some(reason) = stale_cache_reason(&state, &cache_key, backend, &config)? {
+        info!("OCR Handler: cache_key={cache_key} {reason}. Re-running OCR.");
+        METRICS.cache_misses(metrics::Path::Request, 1);
+    } else {
+        info!("OCR Handler: Checking cache...");
+        if let Some(data) = cached_ocr(&state, &cache_key, chapter_key.as_deref())? {
+            METRICS.cache_hits(metrics::Path::Request, 1);
+            let results = Json(params.granularity.apply(data));
+            return Ok(match state.skipped_reason(&cache_key)? {
+                Some(reason) => ([(SKIPPED_REASON_HEADER, reason)], results).into_response(),
+                None => results.into_response(),
+            });
+        }
+        if let Some(context_prefix) = archive::archived_series(&state, &cache_key) {
+            info!("OCR Handler: cache_key={} is archived", cache_key);
+            return Ok(archived_response(&context_prefix).into_response());
+        }
+        info!(
+            "OCR Handler: Cache MISS for cache_key={}. Starting processing.",
+            cache_key
+        );
+        METRICS.cache_misses(metrics::Path::Request, 1);
+    }
+
+    // Reusing another page's results only fits runs with the saved settings.
+    let reuse_images =
+        params.merge.is_none() && !params.force && params.orientation == OrientationHint::Auto;
+
+    // Identical uncached requests (e.g. two open tabs) share a single upstream run; only
+    // the caller that actually ran it writes the cache entry.
+    let result = state
+        .in_flight_ocr
+        .run(&run_key, || async {
+            let _permit = state.lens_limiter.acquire(backend).await;
+            let started = Instant::now();
+            let outcome = logic::fetch_and_process(
+                &params.url,
+                params.user.clone(),
+                params.pass.clone(),
+                &state.credentials,
+                params.add_space_on_merge,
+                language,
+                backend,
+                params.page,
+                &fetch_headers,
+                &config,
+                reuse_images.then_some(&state),
+            )
+            .await;
+            METRICS.page_done(metrics::Path::Request, &outcome, started.elapsed());
+            let outcome = outcome.map_err(|e| e.to_string())?;
+
+            if !outcome.partial && params.merge.is_none() {
+                let ids = ContextIds {
+                    source_id: params.source_id,
+                    manga_id: params.manga_id,
+                    chapter_id: params.chapter_id,
+                };
+                let context = state
+                    .contexts
+                    .resolve_or(
+                        ids,
+                        params.user.as_deref(),
+                        params.pass.as_deref(),
+                        &params.context,
+                    )
+                    .await;
+                info!("OCR Handler: Writing cache entry to DB...");
+                state.cache_outcome(&cache_key, context, backend, &outcome);
+                info!("OCR Handler: Cache write complete.");
+            }
+            Ok::<_, String>(outcome)
+        })
+        .await;
+
+    match result {
+        Ok(outcome) if outcome.partial => {
+            state.requests_processed.fetch_add(1, Ordering::Relaxed);
+            warn!(
+                "OCR Handler: Deadline hit for cache_key={}; returning uncached partial results",
+                cache_key
+            );
+            let mut body = serde_json::json!({
+                "partial": true,
+                "results": params.granularity.apply(outcome.results),
+            });
+            if params.force {
+                body["regenerated"] = true.into();
+            }
+            Ok(Json(body).into_response())
+        }
+        Ok(outcome) => {
+            let data = params.granularity.apply(outcome.results);
+            state.requests_processed.fetch_add(1, Ordering::Relaxed);
+            info!(
+                "OCR Handler: Processing successful for cache_key={}",
+                cache_key
+            );
+
+            if let Some(chapter_key) = chapter_key.as_deref()
+                && params.merge.is_none()
+            {
+                state.insert_chapter_cache(chapter_key, &cache_key);
+            }
+
+            if params.force {
+                return Ok(Json(serde_json::json!({
+                    "regenerated": true,
+                    "results": data,
+                }))
+                .into_response());
+            }
+            Ok(Json(data).into_response())
+        }
+        Err(e) => {
+            warn!(
+                "OCR Handler: Processing FAILED for cache_key={}: {}",
+                cache_key, e
+            );
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SpreadRequest {
+    /// The page shown on the left, whatever the reading direction.
+    pub left: String,
+    pub right: String,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    #[serde(default = "default_context")]
+    pub context: String,
+    /// Suwayomi ids the cache context is built from; `context` is then only used when they
+    /// cannot be resolved.
+    #[serde(flatten)]
+    pub ids: ContextIds,
+    pub add_space_on_merge: Option<bool>,
+    #[serde(alias = "lang")]
+    pub language: Option<OcrLanguage>,
+    #[serde(default)]
+    pub backend: Option<OcrBackend>,
+    #[serde(default)]
+    pub granularity: Granularity,
+    /// Extra headers for both image fetches.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub cookies: Option<String>,
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+/// Query string of `POST /ocr/spread`. `force` lives here rather than in the body so the
+/// API key check can see that the cache is about to be overwritten.
+#[derive(Deserialize, Default)]
+pub struct SpreadQuery {
+    /// Skips the cache lookup and overwrites the stored result.
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// OCRs a double-page spread as one image, so bubbles crossing the gutter come back whole.
+/// Boxes are normalized against the whole spread and tagged with the page they sit on;
+/// `split` is where the gutter is.
+pub async fn ocr_spread_handler(
+    State(state): State<AppState>,
+    Query(query): Query<SpreadQuery>,
+    Json(params): Json<SpreadRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let language = params.language.unwrap_or_default();
+    let backend = params.backend.unwrap_or_default();
+    let fetch_headers = page_headers(
+        params.headers.clone(),
+        params.cookies.clone(),
+        params.token.clone(),
+        params.user.as_deref(),
+    )
+    .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    let key_config = state.cache_key_config();
+    let cache_key = backend.cache_key(&spread::cache_key(
+        &logic::get_cache_key(&params.left, Some(language), &key_config),
+        &logic::get_cache_key(&params.right, Some(language), &key_config),
+    ));
+    let config = state.ocr_config();
+
+    if !query.force
+        && stale_cache_reason(&state, &cache_key, backend, &config)?.is_none()
+        && let Some(entry) = state.get_cache_entry(&cache_key)?
+        && let Some(split) = spread::split(&state, &cache_key)
+    {
+        info!("OCR Spread: Cache HIT for cache_key={cache_key}");
+        METRICS.cache_hits(metrics::Path::Request, 1);
+        state.requests_processed.fetch_add(1, Ordering::Relaxed);
+        let results = spread::assign_pages(params.granularity.apply(entry.data), split);
+        return Ok(Json(serde_json::json!({
+            "split": split,
+            "results": results,
+        })));
+    }
+    METRICS.cache_misses(metrics::Path::Request, 1);
+
+    let (left, right) = tokio::try_join!(
+        logic::download_page(
+            &params.left,
+            &params.user,
+            &params.pass,
+            &state.credentials,
+            &fetch_headers,
+            &config
+        ),
+        logic::download_page(
+            &params.right,
+            &params.user,
+            &params.pass,
+            &state.credentials,
+            &fetch_headers,
+            &config
+        ),
+    )
+    .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+    let (image_bytes, split) = tokio::task::spawn_blocking(move || {
+        let left = logic::decode_image(&left)?;
+        let right = logic::decode_image(&right)?;
+        spread::stitch(&left, &right)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let permit = state.lens_limiter.acquire(backend).await;
+    let outcome = logic::process_uploaded_image(
+        &image_bytes,
+        params.user.clone(),
+        params.pass.clone(),
+        &state.credentials,
+        params.add_space_on_merge,
+        language,
+        backend,
+        &config,
+    )
+    .await
+    .map_err(|e| {
+        warn!("OCR Spread: Processing FAILED for cache_key={cache_key}: {e}");
+        (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    })?;
+    drop(permit);
+    state.requests_processed.fetch_add(1, Ordering::Relaxed);
+
+    if !outcome.partial {
+        let context = state
+            .contexts
+            .resolve_or(
+                params.ids,
+                params.user.as_deref(),
+                params.pass.as_deref(),
+                &params.context,
+            )
+            .await;
+        state.cache_outcome(&cache_key, context, backend, &outcome);
+        if let Err(err) = spread::record_split(&state, &cache_key, split) {
+            warn!("OCR Spread: Failed to record the gutter for cache_key={cache_key}: {err}");
+        }
+    }
+    let results = spread::assign_pages(params.granularity.apply(outcome.results), split);
+    let mut body = serde_json::json!({
+        "split": split,
+        "results": results,
+    });
+    if outcome.partial {
+        body["partial"] = true.into();
+    }
+    Ok(Json(body))
+}
+
+/// Answer for a page whose cache was archived: the client can offer to restore the
+/// series instead of OCRing the page again.
+fn archived_response(context_prefix: &str) -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::CONFLICT,
+        Json(serde_json::json!({
+            "status": "archived",
+            "error": "OCR results for this page are archived",
+            "context_prefix": context_prefix,
+        })),
+    )
+}
+
+/// Corrected results for the cached page at `url`, stored in place of its OCR lines.
+#[derive(Deserialize)]
+pub struct EditOcrRequest {
+    pub url: String,
+    #[serde(alias = "lang")]
+    pub language: Option<OcrLanguage>,
+    #[serde(default)]
+    pub backend: Option<OcrBackend>,
+    pub results: Vec<logic::OcrResult>,
+}
+
+/// Stores hand-corrected results for an already cached page. Later reads of the page
+/// return the corrected text, and exports carry the `edited_at` stamp.
+pub async fn edit_ocr_handler(
+    State(state): State<AppState>,
+    Json(req): Json<EditOcrRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let language = req.language.unwrap_or_default();
+    let backend = req.backend.unwrap_or_default();
+    let cache_key = backend.cache_key(&logic::get_cache_key(
+        &req.url,
+        Some(language),
+        &state.cache_key_config(),
+    ));
+
+    let edited_at = state
+        .edit_cache_entry(&cache_key, &req.results)
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .ok_or_else(|| {
+            (
+                StatusCode::NOT_FOUND,
+                format!("No cached OCR for {cache_key}"),
+            )
+        })?;
+    info!("OCR Handler: Stored corrected results for cache_key={cache_key}");
+    Ok(Json(serde_json::json!({
+        "status": "ok",
+        "cache_key": cache_key,
+        "edited_at": edited_at,
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct ManualOcrRequest {
+    #[serde(alias = "pageUrl")]
+    pub page_url: String,
+    #[serde(default = "default_context")]
+    pub context: String,
+    /// Suwayomi ids the cache context is built from; `context` is then only used when they
+    /// cannot be resolved.
+    #[serde(flatten)]
+    pub ids: ContextIds,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    #[serde(alias = "lang")]
+    pub language: Option<OcrLanguage>,
+    #[serde(default)]
+    pub backend: Option<OcrBackend>,
+    pub blocks: Vec<ManualBlock>,
+}
+
+/// Stores a human transcription for a page, replacing any OCR result. Manual pages are
+/// kept by purges and prunes, are served even to forced re-OCR requests, and keep their
+/// `source` marker in exports.
+pub async fn manual_ocr_handler(
+    State(state): State<AppState>,
+    Json(req): Json<ManualOcrRequest>,
+) -> Json<serde_json::Value> {
+    let language = req.language.unwrap_or_default();
+    let backend = req.backend.unwrap_or_default();
+    let cache_key = backend.cache_key(&logic::get_cache_key(
+        &req.page_url,
+        Some(language),
+        &state.cache_key_config(),
+    ));
+
+    let data = manual::to_results(req.blocks);
+    let lines = data.len();
+    let context = state
+        .contexts
+        .resolve_or(
+            req.ids,
+            req.user.as_deref(),
+            req.pass.as_deref(),
+            &req.context,
+        )
+        .await;
+    state.insert_cache_entry(
+        &cache_key,
+        &CacheEntry {
+            context,
+            data,
+            backend,
+            orientation: None,
+            orientation_hint: OrientationHint::Auto,
+            edited_at: None,
+            source: EntrySource::Manual,
+            preprocess: Preprocess::None,
+        },
+    );
+    info!("OCR Handler: Stored manual text for cache_key={cache_key} ({lines} lines)");
+    Json(serde_json::json!({
+        "status": "ok",
+        "cache_key": cache_key,
+        "lines": lines,
+    }))
+}
+
+/// Why a cached Lens page cannot answer this request and has to be OCRed again: it was
+/// produced with a different preprocessing pipeline, or merged under a different explicit
+/// orientation hint. Manual and hand-edited pages are never stale.
+fn stale_cache_reason(
+    state: &AppState,
+    cache_key: &str,
+    backend: OcrBackend,
+    config: &OcrConfig,
+) -> Result<Option<&'static str>, DbBusy> {
+    if backend != OcrBackend::Lens {
+        return Ok(None);
+    }
+    let Some(entry) = state.get_cache_entry(cache_key)? else {
+        return Ok(None);
+    };
+    let hint = config.merge.orientation_hint;
+    Ok(
+        if entry.source == EntrySource::Manual || entry.edited_at.is_some() {
+            None
+        } else if entry.preprocess != config.preprocess {
+            Some("was produced with a different preprocessing pipeline")
+        } else if !hint.is_auto() && entry.orientation_hint != hint {
+            Some("was merged under a different orientation hint")
+        } else {
+            None
+        },
+    )
+}
+
+/// Looks up a page in the cache, promoting entries stored under a legacy key, and links it
+/// to its chapter on a hit.
+fn cached_ocr(
+    state: &AppState,
+    cache_key: &str,
+    chapter_key: Option<&str>,
+) -> Result<Option<Vec<logic::OcrResult>>, DbBusy> {
+    let entry = if let Some(entry) = state.get_cache_entry(cache_key)? {
+        info!("OCR Handler: Cache HIT for cache_key={}", cache_key);
+        entry
+    } else {
+        // Back-compat: older versions included sourceId in the cache key.
+        // Try to find a matching entry and promote it to the normalized key.
+        let Some((_legacy_key, legacy_entry)) =
+            state.get_cache_entry_sourceid_variant(cache_key)?
+        else {
+            return Ok(None);
+        };
+        info!(
+            "OCR Handler: Cache HIT via sourceId variant for cache_key={}",
+            cache_key
+        );
+        state.insert_cache_entry(cache_key, &legacy_entry);
+        legacy_entry
+    };
+    if let Some(chapter_key) = chapter_key {
+        state.insert_chapter_cache(chapter_key, cache_key);
+    }
+    state.requests_processed.fetch_add(1, Ordering::Relaxed);
+    Ok(Some(entry.data))
+}
+
+/// Pages of a batch processed at the same time; the rest wait their turn.
+const BATCH_CONCURRENCY: usize = 3;
+
+#[derive(Deserialize)]
+pub struct BatchOcrRequest {
+    pub urls: Vec<String>,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    #[serde(default, rename = "base_url", alias = "baseUrl")]
+    pub base_url: Option<String>,
+    #[serde(default = "default_context")]
+    pub context: String,
+    /// Suwayomi ids the cache context is built from; `context` is then only used when they
+    /// cannot be resolved.
+    #[serde(flatten)]
+    pub ids: ContextIds,
+    pub add_space_on_merge: Option<bool>,
+    #[serde(alias = "lang")]
+    pub language: Option<OcrLanguage>,
+    #[serde(default)]
+    pub backend: Option<OcrBackend>,
+    #[serde(default)]
+    pub granularity: Granularity,
+}
+
+/// The cache key of a batch URL; pages after the first of a PDF are named `url#page=N`.
+fn batch_cache_key(
+    url: &str,
+    language: OcrLanguage,
+    backend: OcrBackend,
+    key_config: &CacheKeyConfig,
+) -> String {
+    let (document, page) = pdf::split_page(url);
+    pdf::page_cache_key(
+        &backend.cache_key(&logic::get_cache_key(document, Some(language), key_config)),
+        page,
+    )
+}
+
+/// OCRs several pages in one round trip, e.g. to prefetch upcoming pages. A URL serving a
+/// PDF stands for its first page and `url#page=N` for a later one. Returns a map of
+/// url to `{ "status": "ok", "results": [...] }`, `{ "status": "partial", "results": [...] }`
+/// or `{ "status": "error", "error": "..." }`; one failing page never fails the batch.
+pub async fn ocr_batch_handler(
+    State(state): State<AppState>,
+    Json(req): Json<BatchOcrRequest>,
+) -> Json<serde_json::Value> {
+    let language = req.language.unwrap_or_default();
+    let backend = req.backend.unwrap_or_default();
+    let key_config = state.cache_key_config();
+    let chapter_key = req
+        .base_url
+        .as_ref()
+        .map(|base| logic::get_cache_key(base, Some(language), &key_config));
+    let add_space_on_merge = req.add_space_on_merge;
+    let granularity = req.granularity;
+    let config = state.ocr_config();
+
+    let mut responses = serde_json::Map::new();
+    let mut misses = Vec::new();
+    for url in req.urls {
+        if responses.contains_key(&url) || misses.contains(&url) {
+            continue;
+        }
+        let cache_key = batch_cache_key(&url, language, backend, &key_config);
+        let busy_response =
+            |busy: DbBusy| serde_json::json!({ "status": "error", "error": busy.to_string() });
+        match stale_cache_reason(&state, &cache_key, backend, &config) {
+            Ok(None) => {}
+            Ok(Some(reason)) => {
+                info!("OCR Batch: cache_key={cache_key} {reason}");
+                METRICS.cache_misses(metrics::Path::Request, 1);
+                misses.push(url);
+                continue;
+            }
+            Err(busy) => {
+                responses.insert(url, busy_response(busy));
+                continue;
+            }
+        }
+        match cached_ocr(&state, &cache_key, chapter_key.as_deref()) {
+            Err(busy) => {
+                responses.insert(url, busy_response(busy));
+            }
+            Ok(Some(data)) => {
+                METRICS.cache_hits(metrics::Path::Request, 1);
+                let results = granularity.apply(data);
+                responses.insert(
+                    url,
+                    serde_json::json!({ "status": "ok", "results": results }),
+                );
+            }
+            Ok(None) => match archive::archived_series(&state, &cache_key) {
+                Some(context_prefix) => {
+                    let response = serde_json::json!({
+                        "status": "archived",
+                        "context_prefix": context_prefix,
+                    });
+                    responses.insert(url, response);
+                }
+                None => {
+                    METRICS.cache_misses(metrics::Path::Request, 1);
+                    misses.push(url);
+                }
+            },
+        }
+    }
+    info!(
+        "OCR Batch: {} cached, {} to process",
+        responses.len(),
+        misses.len()
+    );
+
+    let context = state
+        .contexts
+        .resolve_or(
+            req.ids,
+            req.user.as_deref(),
+            req.pass.as_deref(),
+            &req.context,
+        )
+        .await;
+    let processed: Vec<(String, serde_json::Value)> = futures::stream::iter(misses)
+        .map(|url| {
+            let state = state.clone();
+            let user = req.user.clone();
+            let pass = req.pass.clone();
+            let context = context.clone();
+            let chapter_key = chapter_key.clone();
+            let config = config.clone();
+            let key_config = key_config.clone();
+            async move {
+                let cache_key = batch_cache_key(&url, language, backend, &key_config);
+                let (document, page) = pdf::split_page(&url);
+                let permit = state.lens_limiter.acquire(backend).await;
+                let started = Instant::now();
+                let result = logic::fetch_and_process(
+                    document,
+                    user,
+                    pass,
+                    &state.credentials,
+                    add_space_on_merge,
+                    language,
+                    backend,
+                    page,
+                    &PageHeaders::default(),
+                    &config,
+                    Some(&state),
+                )
+                .await;
+                drop(permit);
+                METRICS.page_done(metrics::Path::Request, &result, started.elapsed());
+                state.requests_processed.fetch_add(1, Ordering::Relaxed);
+                let response = match result {
+                    Ok(outcome) if outcome.partial => {
+                        warn!("OCR Batch: Deadline hit for cache_key={}", cache_key);
+                        let results = granularity.apply(outcome.results);
+                        serde_json::json!({ "status": "partial", "results": results })
+                    }
+                    Ok(outcome) => {
+                        state.cache_outcome(&cache_key, context, backend, &outcome);
+                        if let Some(chapter_key) = chapter_key.as_deref() {
+                            state.insert_chapter_cache(chapter_key, &cache_key);
+                        }
+                        let results = granularity.apply(outcome.results);
+                        serde_json::json!({ "status": "ok", "results": results })
+                    }
+                    Err(e) => {
+                        warn!(
+                            "OCR Batch: Processing FAILED for cache_key={}: {}",
+                            cache_key, e
+                        );
+                        serde_json::json!({ "status": "error", "error": e.to_string() })
+                    }
+                };
+                (url, response)
+            }
+        })
+        .buffer_unordered(BATCH_CONCURRENCY)
+        .collect()
+        .await;
+    responses.extend(processed);
+
+    Json(serde_json::json!({ "results": responses }))
+}
+
+#[derive(Deserialize)]
+pub struct OcrUploadParams {
+    pub cache_key: Option<String>,
+    pub context: Option<String>,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    pub add_space_on_merge: Option<bool>,
+    #[serde(alias = "lang")]
+    pub language: Option<OcrLanguage>,
+    #[serde(default)]
+    pub backend: Option<OcrBackend>,
+    #[serde(default)]
+    pub granularity: Granularity,
+}
+
+/// OCRs image bytes sent by the client, for images the server cannot fetch (local files,
+/// blob URLs). The body is either the raw image or a multipart form with a `file` field;
+/// multipart `cache_key` and `context` fields override the query parameters. Results are
+/// cached only when a cache key is supplied.
+pub async fn ocr_upload_handler(
+    State(state): State<AppState>,
+    Query(mut params): Query<OcrUploadParams>,
+    request: Request,
+) -> Result<Response, (StatusCode, String)> {
+    let is_multipart = request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("multipart/form-data"));
+
+    let image_bytes = if is_multipart {
+        let mut multipart = Multipart::from_request(request, &state)
+            .await
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.body_text()))?;
+        let mut file = None;
+        while let Some(field) = multipart
+            .next_field()
+            .await
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.body_text()))?
+        {
+            let name = field.name().unwrap_or_default().to_string();
+            match name.as_str() {
+                "cache_key" | "context" => {
+                    let value = field
+                        .text()
+                        .await
+                        .map_err(|e| (StatusCode::BAD_REQUEST, e.body_text()))?;
+                    if name == "cache_key" {
+                        params.cache_key = Some(value);
+                    } else {
+                        params.context = Some(value);
+                    }
+                }
+                "file" => {
+                    file = Some(
+                        field
+                            .bytes()
+                            .await
+                            .map_err(|e| (StatusCode::BAD_REQUEST, e.body_text()))?,
+                    );
+                }
+                _ => {}
+            }
+        }
+        file.unwrap_or_default()
+    } else {
+        Bytes::from_request(request, &state)
+            .await
+            .map_err(|e| (StatusCode::BAD_REQUEST, e.body_text()))?
+    };
+    if image_bytes.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "No image data provided".to_string(),
+        ));
+    }
+
+    let language = params.language.unwrap_or_default();
+    let backend = params.backend.unwrap_or_default();
+    let cache_key = params
+        .cache_key
+        .filter(|key| !key.trim().is_empty())
+        .map(|key| backend.cache_key(&key));
+
+    if let Some(entry) = cache_key
+        .as_deref()
+        .map(|key| state.get_cache_entry(key))
+        .transpose()?
+        .flatten()
+    {
+        info!("OCR Upload: Cache HIT for cache_key={:?}", cache_key);
+        state.requests_processed.fetch_add(1, Ordering::Relaxed);
+        return Ok(Json(params.granularity.apply(entry.data)).into_response());
+    }
+
+    info!(
+        "OCR Upload: Processing {} bytes for cache_key={:?}",
+        image_bytes.len(),
+        cache_key
+    );
+    let permit = state.lens_limiter.acquire(backend).await;
+    let outcome = logic::process_uploaded_image(
+        &image_bytes,
+        params.user,
+        params.pass,
+        &state.credentials,
+        params.add_space_on_merge,
+        language,
+        backend,
+        &state.ocr_config(),
+    )
+    .await
+    .map_err(|e| {
+        warn!(
+            "OCR Upload: Processing FAILED for cache_key={:?}: {}",
+            cache_key, e
+        );
+        (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    })?;
+    drop(permit);
+    state.requests_processed.fetch_add(1, Ordering::Relaxed);
+
+    if outcome.partial {
+        warn!(
+            "OCR Upload: Deadline hit for cache_key={:?}; returning uncached partial results",
+            cache_key
+        );
+        return Ok(Json(serde_json::json!({
+            "partial": true,
+            "results": params.granularity.apply(outcome.results),
+        }))
+        .into_response());
+    }
+
+    if let Some(cache_key) = cache_key.as_deref() {
+        let context = params.context.unwrap_or_else(default_context);
+        state.cache_outcome(cache_key, context, backend, &outcome);
+    }
+    Ok(Json(params.granularity.apply(outcome.results)).into_response())
+}
+
+#[derive(Deserialize)]
+pub struct NovelImageOcrRequest {
+    pub book_id: String,
+    pub image_path: String,
+    pub user: Option<String>,
+    pub pass: Option<String>,
     #[serde(default = "default_context")]
     pub context: String,
     pub add_space_on_merge: Option<bool>,
+    #[serde(alias = "lang")]
     pub language: Option<OcrLanguage>,
 }
 
-fn default_context() -> String {
-    "No Context".to_string()
-}
-
-// --- Handlers ---
-
-pub async fn status_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
-    let cache_size = state.cache_len();
-    Json(serde_json::json!({
-        "status": "running",
-        "backend": "Rust (manatan-ocr-server)",
-        "requests_processed": state.requests_processed.load(Ordering::Relaxed),
-        "items_in_cache": cache_size,
-        "active_jobs": state.active_jobs.load(Ordering::Relaxed),
-    }))
-}
-
-pub async fn ocr_handler(
+/// OCRs an illustration that was extracted from a locally stored EPUB.
+pub async fn ocr_novel_image_handler(
     State(state): State<AppState>,
-    Query(params): Query<OcrRequest>,
+    Json(req): Json<NovelImageOcrRequest>,
 ) -> Result<Json<Vec<crate::logic::OcrResult>>, (StatusCode, String)> {
-    let language = params.language.unwrap_or_default();
-    let cache_key = logic::get_cache_key(&params.url, Some(language));
-    let chapter_key = params
-        .base_url
-        .as_ref()
-        .map(|base| logic::get_cache_key(base, Some(language)));
-    info!("OCR Handler: Incoming request for cache_key={}", cache_key);
+    let language = req.language.unwrap_or_default();
+    let image_path = req.image_path.trim_start_matches('/');
+    let Some(file_path) = state.novel_image_path(&req.book_id, image_path) else {
+        return Err((StatusCode::BAD_REQUEST, "Invalid image path".to_string()));
+    };
+    // Keyed by language like manga pages, so switching languages OCRs the image again.
+    let cache_key = format!(
+        "lang/{}/novel:{}:{image_path}",
+        language.as_str(),
+        req.book_id
+    );
 
-    info!("OCR Handler: Checking cache...");
-    if let Some(entry) = state.get_cache_entry(&cache_key) {
-        info!("OCR Handler: Cache HIT for cache_key={}", cache_key);
-        if let Some(chapter_key) = chapter_key.as_deref() {
-            state.insert_chapter_cache(chapter_key, &cache_key);
-        }
+    if let Some(entry) = state.get_cache_entry(&cache_key)? {
+        info!("Novel OCR: Cache HIT for cache_key={}", cache_key);
         state.requests_processed.fetch_add(1, Ordering::Relaxed);
-        return Ok(Json(entry.data));
+        return Ok(Json(Granularity::Line.apply(entry.data)));
     }
 
-    // Back-compat: older versions included sourceId in the cache key.
-    // Try to find a matching entry and promote it to the normalized key.
-    if let Some((_legacy_key, legacy_entry)) = state.get_cache_entry_sourceid_variant(&cache_key) {
-        info!(
-            "OCR Handler: Cache HIT via sourceId variant for cache_key={}",
-            cache_key
-        );
-        if let Some(chapter_key) = chapter_key.as_deref() {
-            state.insert_chapter_cache(chapter_key, &cache_key);
+    let image_bytes = match tokio::fs::read(&file_path).await {
+        Ok(bytes) => bytes,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            return Err((StatusCode::NOT_FOUND, "Image not found".to_string()));
         }
-        state.insert_cache_entry(&cache_key, &legacy_entry);
-        state.requests_processed.fetch_add(1, Ordering::Relaxed);
-        return Ok(Json(legacy_entry.data));
-    }
+        Err(err) => return Err((StatusCode::INTERNAL_SERVER_ERROR, err.to_string())),
+    };
+
     info!(
-        "OCR Handler: Cache MISS for cache_key={}. Starting processing.",
+        "Novel OCR: Cache MISS for cache_key={}. Starting processing.",
         cache_key
     );
-
-    let result = logic::fetch_and_process(
-        &params.url,
-        params.user.clone(),
-        params.pass.clone(),
-        params.add_space_on_merge,
+    match logic::process_image_bytes(
+        &image_bytes,
+        req.user,
+        req.pass,
+        &state.credentials,
+        req.add_space_on_merge,
         language,
+        &state.ocr_config().merge,
     )
-    .await;
-
-    match result {
+    .await
+    {
         Ok(data) => {
             state.requests_processed.fetch_add(1, Ordering::Relaxed);
-            info!(
-                "OCR Handler: Processing successful for cache_key={}",
-                cache_key
-            );
-
-            info!("OCR Handler: Writing cache entry to DB...");
             state.insert_cache_entry(
                 &cache_key,
                 &CacheEntry {
-                    context: params.context,
+                    context: req.context,
                     data: data.clone(),
+                    backend: OcrBackend::Lens,
+                    orientation: None,
+                    orientation_hint: OrientationHint::Auto,
+                    edited_at: None,
+                    source: EntrySource::Ocr,
+                    preprocess: Preprocess::None,
                 },
             );
-            info!("OCR Handler: Cache write complete.");
-
-            if let Some(chapter_key) = chapter_key.as_deref() {
-                state.insert_chapter_cache(chapter_key, &cache_key);
-            }
-
-            Ok(Json(data))
+            Ok(Json(Granularity::Line.apply(data)))
         }
         Err(e) => {
             warn!(
-                "OCR Handler: Processing FAILED for cache_key={}: {}",
+                "Novel OCR: Processing FAILED for cache_key={}: {}",
                 cache_key, e
             );
             Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
@@ -133,15 +1480,92 @@ pub async fn ocr_handler(
     }
 }
 
+#[derive(Deserialize)]
+pub struct CropRequest {
+    pub url: String,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    /// The crop rectangle in page-relative coordinates; the whole page by default.
+    #[serde(default)]
+    pub x: f64,
+    #[serde(default)]
+    pub y: f64,
+    #[serde(default = "full_extent")]
+    pub width: f64,
+    #[serde(default = "full_extent")]
+    pub height: f64,
+    #[serde(default)]
+    pub format: OutputFormat,
+    /// 1-100 for lossy formats; rejected for lossless ones.
+    pub quality: Option<u8>,
+}
+
+fn full_extent() -> f64 {
+    1.0
+}
+
+/// Crops a page and re-encodes it in the requested format, for Anki card images.
+pub async fn crop_handler(
+    State(state): State<AppState>,
+    Query(params): Query<CropRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    let config = state.ocr_config();
+    let bytes = logic::fetch_page_image(
+        &params.url,
+        &params.user,
+        &params.pass,
+        &state.credentials,
+        &PageHeaders::default(),
+        &config,
+    )
+    .await
+    .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+
+    let format = params.format;
+    let encoded = tokio::task::spawn_blocking(move || {
+        let page =
+            logic::decode_image(&bytes).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        let cropped = imaging::crop(&page, params.x, params.y, params.width, params.height);
+        imaging::encode(&cropped, format, params.quality).map_err(|e| {
+            let status = if e.is_bad_request() {
+                StatusCode::BAD_REQUEST
+            } else {
+                StatusCode::INTERNAL_SERVER_ERROR
+            };
+            (status, e.to_string())
+        })
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))??;
+
+    Ok(([(CONTENT_TYPE, format.content_type())], encoded).into_response())
+}
+
 #[derive(Deserialize)]
 pub struct JobRequest {
     pub base_url: String,
     pub user: Option<String>,
     pub pass: Option<String>,
     pub context: String,
+    /// Suwayomi ids the chapter's context is built from; `context` is then only used when
+    /// they cannot be resolved.
+    #[serde(flatten)]
+    pub ids: ContextIds,
     pub pages: Option<Vec<String>>,
     pub add_space_on_merge: Option<bool>,
+    #[serde(alias = "lang")]
     pub language: Option<OcrLanguage>,
+    /// Extra headers sent with every page fetch of the chapter.
+    #[serde(default, deserialize_with = "headers::inline_headers")]
+    pub headers: HashMap<String, String>,
+    #[serde(default)]
+    pub cookies: Option<String>,
+    /// Bearer token used instead of `user`/`pass`.
+    #[serde(default)]
+    pub token: Option<String>,
+    /// OCR pages that look blank too, and re-OCR those an earlier job skipped as blank.
+    #[serde(default)]
+    pub force: bool,
 }
 
 #[derive(Deserialize)]
@@ -149,6 +1573,7 @@ pub struct ChapterStatusQuery {
     pub base_url: String,
     pub user: Option<String>,
     pub pass: Option<String>,
+    #[serde(alias = "lang")]
     pub language: Option<OcrLanguage>,
 }
 
@@ -156,6 +1581,7 @@ pub struct ChapterStatusQuery {
 pub struct ChapterStatusBatchItem {
     pub base_url: String,
     pub pages: Option<Vec<String>>,
+    #[serde(alias = "lang")]
     pub language: Option<OcrLanguage>,
 }
 
@@ -164,63 +1590,76 @@ pub struct ChapterStatusBatchRequest {
     pub chapters: Vec<ChapterStatusBatchItem>,
     pub user: Option<String>,
     pub pass: Option<String>,
+    #[serde(alias = "lang")]
     pub language: Option<OcrLanguage>,
 }
 
-async fn chapter_status(state: &AppState, req: JobRequest) -> Json<serde_json::Value> {
+async fn chapter_status(
+    state: &AppState,
+    req: JobRequest,
+) -> Result<Json<serde_json::Value>, DbBusy> {
     let language = req.language.unwrap_or_default();
-    let job_key = logic::get_cache_key(&req.base_url, Some(language));
+    let key_config = state.cache_key_config();
+    let job_key = logic::get_cache_key(&req.base_url, Some(language), &key_config);
     let progress = {
-        state
-            .active_chapter_jobs
-            .read()
-            .expect("lock poisoned")
+        locks::read(&state.active_chapter_jobs)
             .get(&job_key)
             .cloned()
     };
 
     if let Some(p) = progress {
-        return Json(serde_json::json!({
+        return Ok(Json(serde_json::json!({
             "status": "processing",
             "progress": p.current,
-            "total": p.total
-        }));
+            "total": p.total,
+            "adaptive_delay_ms": p.adaptive_delay_ms,
+            "throttled_since": p.throttled_since
+        })));
+    }
+
+    // Queued chapters report as processing so clients keep their spinner up.
+    if let Some(position) = state.job_queue.position(&job_key) {
+        return Ok(Json(serde_json::json!({
+            "status": "processing",
+            "queued": true,
+            "queue_position": position,
+            "queue_length": state.job_queue.len(),
+            "progress": 0,
+            "total": req.pages.as_ref().map_or(0, Vec::len)
+        })));
     }
 
     let mut cached_count = 0usize;
     let mut total_expected = 0usize;
     if let Some(page_list) = req.pages.as_ref() {
         if page_list.is_empty() {
-            return Json(serde_json::json!({
+            return Ok(Json(serde_json::json!({
                 "status": "idle",
                 "cached_count": 0,
                 "total_expected": 0
-            }));
-        }
-        let mut cached_keys = Vec::new();
-        for page in page_list {
-            let cache_key = logic::get_cache_key(page, Some(language));
-            if state.has_cache_entry(&cache_key)
-                || state.has_cache_entry_prefix(&format!("{cache_key}?sourceId="))
-                || state.has_cache_entry_prefix(&format!("{cache_key}&sourceId="))
-            {
-                cached_count += 1;
-                cached_keys.push(cache_key);
-            }
+            })));
         }
+        let page_keys: Vec<String> = page_list
+            .iter()
+            .map(|page| logic::get_cache_key(page, Some(language), &key_config))
+            .collect();
+        let cached = state.cached_page_keys(&page_keys)?;
+        let cached_keys: Vec<String> = page_keys
+            .into_iter()
+            .filter(|cache_key| cached.contains(cache_key))
+            .collect();
+        cached_count = cached_keys.len();
         if cached_count > 0 {
             total_expected = page_list.len();
             state.set_chapter_pages(&job_key, total_expected);
-            for cache_key in cached_keys {
-                state.insert_chapter_cache(&job_key, &cache_key);
-            }
+            state.insert_chapter_cache_keys(&job_key, &cached_keys);
         }
     } else {
-        cached_count = state.count_chapter_cache(&job_key);
+        cached_count = state.count_chapter_cache(&job_key)?;
         if cached_count > 0 {
-            if let Some((page_count, _)) = state.get_chapter_progress(&job_key) {
+            if let Some((page_count, _)) = state.get_chapter_progress(&job_key)? {
                 total_expected = page_count;
-            } else if let Some(page_count) = state.get_chapter_pages(&job_key) {
+            } else if let Some(page_count) = state.get_chapter_pages(&job_key)? {
                 total_expected = page_count;
             }
         }
@@ -235,6 +1674,7 @@ async fn chapter_status(state: &AppState, req: JobRequest) -> Json<serde_json::V
             &req.base_url,
             req.user.clone(),
             req.pass.clone(),
+            &state.credentials,
         )
         .await
         {
@@ -253,45 +1693,180 @@ async fn chapter_status(state: &AppState, req: JobRequest) -> Json<serde_json::V
         }
     }
 
+    // Cached pages a job skipped as blank, by reason, so clients can tell them from pages
+    // that simply have no text.
+    let skipped = if cached_count > 0 {
+        state.chapter_skipped_reasons(&job_key)?
+    } else {
+        HashMap::new()
+    };
+
     if total_expected > 0 && cached_count >= total_expected {
-        return Json(serde_json::json!({
+        return Ok(Json(serde_json::json!({
             "status": "processed",
             "cached_count": cached_count,
-            "total_expected": total_expected
-        }));
+            "total_expected": total_expected,
+            "skipped": skipped,
+        })));
     }
 
-    Json(serde_json::json!({
+    // The chapter's last job failed some pages, so clients can offer to retry just those.
+    let last_failed_job = job_history::last_failed(state, &job_key);
+    Ok(Json(serde_json::json!({
         "status": "idle",
         "cached_count": cached_count,
-        "total_expected": total_expected
-    }))
+        "total_expected": total_expected,
+        "skipped": skipped,
+        "last_failed_job": last_failed_job,
+        "failed_pages": last_failed_job.as_ref().map(JobRecord::failed_pages),
+    })))
 }
 
 pub async fn is_chapter_preprocessed_handler(
     State(state): State<AppState>,
     Json(req): Json<JobRequest>,
-) -> Json<serde_json::Value> {
-    chapter_status(&state, req).await
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    Ok(chapter_status(&state, req).await?)
 }
 
 pub async fn is_chapter_preprocessed_get_handler(
     State(state): State<AppState>,
     Query(req): Query<ChapterStatusQuery>,
-) -> Json<serde_json::Value> {
-    chapter_status(
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let status = chapter_status(
         &state,
         JobRequest {
             base_url: req.base_url,
             user: req.user,
             pass: req.pass,
             context: "Check Status".to_string(),
+            ids: ContextIds::default(),
             pages: None,
             add_space_on_merge: None,
             language: req.language,
+            headers: HashMap::new(),
+            cookies: None,
+            token: None,
+            force: false,
         },
     )
-    .await
+    .await?;
+    Ok(status)
+}
+
+/// `GET /ws`: pushes a JSON message per page as its OCR is cached or fails. With
+/// `base_url` only that chapter's pages are sent, each with its page URL.
+pub async fn page_updates_handler(
+    State(state): State<AppState>,
+    Query(params): Query<PageStreamParams>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let filter = params
+        .base_url
+        .as_deref()
+        .filter(|url| !url.is_empty())
+        .map(|url| PageFilter::new(url, params.language.unwrap_or_default()));
+    // Subscribe before upgrading so nothing cached during the handshake is missed.
+    let updates = state.page_updates.subscribe();
+    ws.on_upgrade(move |socket| page_events::stream(socket, updates, filter))
+}
+
+/// `GET /preprocess-progress`: streams a chapter job's progress as server-sent events.
+/// A queued chapter streams once a worker picks it up. The stream ends with a `done` or
+/// `failed` event; if the chapter is neither queued nor running it sends the last
+/// recorded outcome (or `idle` if there is none) and ends.
+pub async fn preprocess_progress_handler(
+    State(state): State<AppState>,
+    Query(req): Query<ChapterStatusQuery>,
+) -> Sse<impl Stream<Item = Result<SseEvent, Infallible>>> {
+    let language = req.language.unwrap_or_default();
+    let job_key = logic::get_cache_key(&req.base_url, Some(language), &state.cache_key_config());
+
+    let stream = futures::stream::once(async move {
+        match wait_for_job(&state, &job_key).await {
+            Some(updates) => progress_stream(updates).left_stream(),
+            None => {
+                let event = finished_event(&state, &job_key);
+                futures::stream::once(async move { Ok(event) }).right_stream()
+            }
+        }
+    })
+    .flatten();
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+/// Progress feed of a running job, waiting out its time in the queue first.
+async fn wait_for_job(
+    state: &AppState,
+    job_key: &str,
+) -> Option<watch::Receiver<PreprocessProgress>> {
+    loop {
+        let updates = {
+            locks::read(&state.active_chapter_jobs)
+                .get(job_key)
+                .map(|job| job.updates.clone())
+        };
+        if updates.is_some() {
+            return updates;
+        }
+        state.job_queue.position(job_key)?;
+        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    }
+}
+
+fn finished_event(state: &AppState, job_key: &str) -> SseEvent {
+    match state.get_chapter_progress(job_key) {
+        Ok(Some((total, processed))) => progress_event(&PreprocessProgress {
+            status: if processed >= total {
+                PreprocessStatus::Done
+            } else {
+                PreprocessStatus::Failed
+            },
+            current: total,
+            total,
+            last_page: None,
+            skipped: 0,
+            processed,
+            failed: total.saturating_sub(processed),
+            ..PreprocessProgress::default()
+        }),
+        Ok(None) => SseEvent::default().event("idle").data("{}"),
+        Err(busy) => SseEvent::default()
+            .event("error")
+            .json_data(serde_json::json!({ "error": busy.to_string() }))
+            .unwrap_or_else(|_| SseEvent::default().event("error")),
+    }
+}
+
+fn progress_stream(
+    updates: watch::Receiver<PreprocessProgress>,
+) -> impl Stream<Item = Result<SseEvent, Infallible>> {
+    futures::stream::unfold(Some((updates, true)), |cursor| async move {
+        let (mut updates, first) = cursor?;
+        if !first && updates.changed().await.is_err() {
+            // The job went away without reporting an outcome.
+            let progress = PreprocessProgress {
+                status: PreprocessStatus::Failed,
+                ..updates.borrow().clone()
+            };
+            return Some((Ok(progress_event(&progress)), None));
+        }
+        let progress = updates.borrow_and_update().clone();
+        let next = (progress.status == PreprocessStatus::Running).then_some((updates, false));
+        Some((Ok(progress_event(&progress)), next))
+    })
+}
+
+fn progress_event(progress: &PreprocessProgress) -> SseEvent {
+    let name = match progress.status {
+        PreprocessStatus::Running => "progress",
+        PreprocessStatus::Done => "done",
+        PreprocessStatus::Failed => "failed",
+    };
+    SseEvent::default()
+        .event(name)
+        .json_data(progress)
+        .unwrap_or_else(|_| SseEvent::default().event(name))
 }
 
 pub async fn is_chapters_preprocessed_handler(
@@ -312,73 +1887,224 @@ pub async fn is_chapters_preprocessed_handler(
             let pass = pass.clone();
             async move {
                 let language = item.language.or(default_language);
-                let Json(value) = chapter_status(
+                let status = chapter_status(
                     &state,
                     JobRequest {
                         base_url: item.base_url.clone(),
                         user: user.clone(),
                         pass: pass.clone(),
                         context: "Batch Status".to_string(),
+                        ids: ContextIds::default(),
                         pages: item.pages,
                         add_space_on_merge: None,
                         language,
+                        headers: HashMap::new(),
+                        cookies: None,
+                        token: None,
+                        force: false,
                     },
                 )
                 .await;
-                let mut locked = results.lock().expect("lock poisoned");
+                let value = match status {
+                    Ok(Json(value)) => value,
+                    Err(busy) => {
+                        serde_json::json!({ "status": "error", "error": busy.to_string() })
+                    }
+                };
+                let mut locked = locks::lock(&results);
                 locked.insert(item.base_url, value);
             }
         })
         .await;
 
-    let out = results.lock().expect("lock poisoned").clone();
+    let out = locks::lock(&results).clone();
     Json(out)
 }
 
 pub async fn preprocess_handler(
     State(state): State<AppState>,
     Json(req): Json<JobRequest>,
-) -> Json<serde_json::Value> {
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
     let language = req.language.unwrap_or_default();
     let pages = match req.pages {
         Some(p) => p,
-        None => return Json(serde_json::json!({ "error": "No pages provided" })),
+        None => return Ok(Json(serde_json::json!({ "error": "No pages provided" }))),
+    };
+    let headers = page_headers(req.headers, req.cookies, req.token, req.user.as_deref()).map_err(
+        |message| {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(serde_json::json!({ "error": message })),
+            )
+        },
+    )?;
+    let context = state
+        .contexts
+        .resolve_or(
+            req.ids,
+            req.user.as_deref(),
+            req.pass.as_deref(),
+            &req.context,
+        )
+        .await;
+
+    let job = jobs::ChapterJob {
+        base_url: req.base_url,
+        pages,
+        user: req.user,
+        pass: req.pass,
+        context,
+        add_space_on_merge: req.add_space_on_merge,
+        language,
+        headers,
+        archive: None,
+        force: req.force,
     };
+    enqueue_response(&state, job, serde_json::Map::new())
+}
 
-    let is_processing = {
-        state
-            .active_chapter_jobs
-            .read()
-            .expect("lock poisoned")
-            .contains_key(&logic::get_cache_key(&req.base_url, Some(language)))
+/// Queues a chapter job and reports where it landed; `extra` is merged into the answer.
+fn enqueue_response(
+    state: &AppState,
+    job: jobs::ChapterJob,
+    extra: serde_json::Map<String, serde_json::Value>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let mut response = match jobs::enqueue(state, job) {
+        jobs::Enqueued::Queued(position) => {
+            Json(serde_json::json!({ "status": "queued", "queue_position": position }))
+        }
+        jobs::Enqueued::AlreadyQueued(position) => {
+            Json(serde_json::json!({ "status": "already_queued", "queue_position": position }))
+        }
+        jobs::Enqueued::AlreadyRunning => {
+            Json(serde_json::json!({ "status": "already_processing" }))
+        }
+        jobs::Enqueued::Full(full) => {
+            return Err((StatusCode::TOO_MANY_REQUESTS, Json(full.body())));
+        }
     };
+    if let Some(object) = response.0.as_object_mut() {
+        object.extend(extra);
+    }
+    Ok(response)
+}
 
-    if is_processing {
-        return Json(serde_json::json!({ "status": "already_processing" }));
-    }
-
-    let state_clone = state.clone();
-    tokio::spawn(async move {
-        jobs::run_chapter_job(
-            state_clone,
-            req.base_url,
-            pages,
-            req.user,
-            req.pass,
-            req.context,
-            req.add_space_on_merge,
-            language,
+#[derive(Deserialize)]
+pub struct PreprocessArchiveParams {
+    /// CBZ/ZIP file on the server's disk; otherwise the archive is the request body.
+    pub path: Option<String>,
+    #[serde(default = "default_context")]
+    pub context: String,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    pub add_space_on_merge: Option<bool>,
+    #[serde(alias = "lang")]
+    pub language: Option<OcrLanguage>,
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Preprocesses a local CBZ/ZIP chapter: either a `path` on the server or the archive
+/// itself as the body (raw or a multipart `file` field). The answer carries the chapter's
+/// `base_url`, which `/preprocess-progress` and the chapter status endpoints accept like
+/// any other chapter URL.
+pub async fn preprocess_archive_handler(
+    State(state): State<AppState>,
+    Query(params): Query<PreprocessArchiveParams>,
+    request: Request,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
+    let bad_request = |message: String| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({ "error": message })),
         )
-        .await;
-    });
+    };
+
+    let archive = match params
+        .path
+        .as_deref()
+        .filter(|path| !path.trim().is_empty())
+    {
+        Some(path) => {
+            let path = std::path::PathBuf::from(path);
+            tokio::task::spawn_blocking(move || cbz::PageArchive::open(&path))
+                .await
+                .map_err(|e| bad_request(e.to_string()))?
+        }
+        None => {
+            let is_multipart = request
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with("multipart/form-data"));
+            let bytes = if is_multipart {
+                let mut multipart = Multipart::from_request(request, &state)
+                    .await
+                    .map_err(|e| bad_request(e.body_text()))?;
+                let mut file = None;
+                while let Some(field) = multipart
+                    .next_field()
+                    .await
+                    .map_err(|e| bad_request(e.body_text()))?
+                {
+                    if field.name() == Some("file") {
+                        file = Some(
+                            field
+                                .bytes()
+                                .await
+                                .map_err(|e| bad_request(e.body_text()))?,
+                        );
+                    }
+                }
+                file.unwrap_or_default()
+            } else {
+                Bytes::from_request(request, &state)
+                    .await
+                    .map_err(|e| bad_request(e.body_text()))?
+            };
+            if bytes.is_empty() {
+                return Err(bad_request(
+                    "Provide a path or the archive as the body".to_string(),
+                ));
+            }
+            tokio::task::spawn_blocking(move || cbz::PageArchive::from_bytes(bytes))
+                .await
+                .map_err(|e| bad_request(e.to_string()))?
+        }
+    }
+    .map_err(|e| bad_request(format!("Could not read archive: {e}")))?;
+
+    let language = params.language.unwrap_or_default();
+    let base_url = archive.base_url();
+    info!(
+        "Preprocess Archive: {} pages as {}",
+        archive.page_names().len(),
+        base_url
+    );
+    let mut extra = serde_json::Map::new();
+    extra.insert("base_url".to_string(), base_url.clone().into());
+    extra.insert("pages".to_string(), archive.page_names().into());
 
-    Json(serde_json::json!({ "status": "started" }))
+    let job = jobs::ChapterJob {
+        base_url,
+        pages: archive.page_urls(),
+        user: params.user,
+        pass: params.pass,
+        context: params.context,
+        add_space_on_merge: params.add_space_on_merge,
+        language,
+        headers: PageHeaders::default(),
+        archive: Some(Arc::new(archive)),
+        force: params.force,
+    };
+    enqueue_response(&state, job, extra)
 }
 
 #[derive(Deserialize)]
 pub struct DeleteChapterRequest {
     pub base_url: String,
     pub delete_data: Option<bool>,
+    #[serde(alias = "lang")]
     pub language: Option<OcrLanguage>,
 }
 
@@ -387,13 +2113,14 @@ pub async fn delete_chapter_handler(
     Json(req): Json<DeleteChapterRequest>,
 ) -> Json<serde_json::Value> {
     let language = req.language.unwrap_or_default();
-    let chapter_key = logic::get_cache_key(&req.base_url, Some(language));
+    let chapter_key =
+        logic::get_cache_key(&req.base_url, Some(language), &state.cache_key_config());
     let delete_data = req.delete_data.unwrap_or(true);
 
     // If a job is currently tracked, drop the progress entry.
     // This doesn't cancel the underlying task, but keeps status checks consistent.
     {
-        let mut locked = state.active_chapter_jobs.write().expect("lock poisoned");
+        let mut locked = locks::write(&state.active_chapter_jobs);
         locked.remove(&chapter_key);
     }
 
@@ -409,21 +2136,426 @@ pub async fn delete_chapter_handler(
     }))
 }
 
-pub async fn purge_cache_handler(State(state): State<AppState>) -> Json<serde_json::Value> {
-    state.clear_cache();
-    Json(serde_json::json!({ "status": "cleared" }))
+/// Filters for `/purge-cache`. Rows matching either filter are removed; a body without
+/// filters must set `all` to wipe the whole cache.
+#[derive(Deserialize)]
+pub struct PurgeCacheRequest {
+    #[serde(default)]
+    pub all: bool,
+    pub context: Option<String>,
+    /// Match every context starting with `context` instead of only an exact match.
+    #[serde(default)]
+    pub match_prefix: bool,
+    pub base_url: Option<String>,
+    #[serde(alias = "lang")]
+    pub language: Option<OcrLanguage>,
+}
+
+/// Purges the OCR cache. Without a body the whole cache is cleared, as before; with one,
+/// only rows for the given `context` and/or chapter `base_url` are removed, and the
+/// removed row counts are reported per filter.
+pub async fn purge_cache_handler(
+    State(state): State<AppState>,
+    body: Option<Json<PurgeCacheRequest>>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let Some(Json(req)) = body else {
+        state.clear_cache();
+        return Ok(Json(serde_json::json!({ "status": "cleared" })));
+    };
+
+    let context = req.context.filter(|context| !context.is_empty());
+    let base_url = req.base_url.filter(|base_url| !base_url.is_empty());
+    if context.is_none() && base_url.is_none() {
+        if !req.all {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Give a context or base_url filter, or set all to purge the whole cache"
+                    .to_string(),
+            ));
+        }
+        state.clear_cache();
+        return Ok(Json(serde_json::json!({ "status": "cleared" })));
+    }
+
+    let mut body = serde_json::json!({ "status": "purged" });
+    if let Some(base_url) = base_url {
+        let chapter_key = logic::get_cache_key(
+            &base_url,
+            Some(req.language.unwrap_or_default()),
+            &state.cache_key_config(),
+        );
+        locks::write(&state.active_chapter_jobs).remove(&chapter_key);
+        let (_, _, ocr_cache_rows) = state.delete_chapter_ocr(&chapter_key, true);
+        body["base_url_rows"] = ocr_cache_rows.into();
+    }
+    if let Some(context) = context {
+        body["context_rows"] = state
+            .delete_cache_by_context(&context, req.match_prefix)
+            .into();
+    }
+    Ok(Json(body))
+}
+
+/// Deletes the oldest cache rows until the given age and size limits hold.
+pub async fn prune_cache_handler(
+    State(state): State<AppState>,
+    Json(options): Json<PruneOptions>,
+) -> Result<Json<PruneReport>, (StatusCode, String)> {
+    options
+        .validate()
+        .map_err(|message| (StatusCode::BAD_REQUEST, message))?;
+    tokio::task::spawn_blocking(move || prune::prune(&state, &options))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[derive(Deserialize)]
+pub struct SearchTextRequest {
+    pub q: String,
+    /// Only pages whose context starts with this, e.g. a series title.
+    #[serde(default)]
+    pub context: Option<String>,
+    #[serde(default = "default_search_limit")]
+    pub limit: usize,
+}
+
+fn default_search_limit() -> usize {
+    20
+}
+
+/// Finds cached OCR lines containing `q`, so text seen on a manga page can be found again,
+/// optionally within one series via `context`.
+pub async fn search_text_handler(
+    State(state): State<AppState>,
+    Query(req): Query<SearchTextRequest>,
+) -> Result<Json<Vec<TextHit>>, (StatusCode, String)> {
+    let q = req.q.trim().to_string();
+    if q.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, "q must not be empty".to_string()));
+    }
+    let context = req
+        .context
+        .map(|context| context.trim().to_string())
+        .filter(|context| !context.is_empty());
+    let hits =
+        tokio::task::spawn_blocking(move || state.search_text(&q, context.as_deref(), req.limit))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))??;
+    Ok(Json(hits))
+}
+
+#[derive(Deserialize)]
+pub struct ExportTextRequest {
+    #[serde(default)]
+    pub context: Option<String>,
+    /// Every context as a zip of text files, instead of one context.
+    #[serde(default)]
+    pub all: bool,
+}
+
+/// `attachment` with `name` as the download name; non-ASCII names go in `filename*`.
+fn attachment(name: &str) -> String {
+    let fallback: String = name
+        .chars()
+        .map(|c| {
+            if (c.is_ascii_graphic() && c != '"') || c == ' ' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let encoded: String = name
+        .bytes()
+        .map(|b| {
+            if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                (b as char).to_string()
+            } else {
+                format!("%{b:02X}")
+            }
+        })
+        .collect();
+    format!("attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}")
+}
+
+/// Downloads one context's OCR text as a plain text file, or with `all` every context as a
+/// zip (see [`text_export`]).
+pub async fn export_text_handler(
+    State(state): State<AppState>,
+    Query(req): Query<ExportTextRequest>,
+) -> Result<Response, (StatusCode, String)> {
+    if req.all {
+        return Ok((
+            [
+                (CONTENT_TYPE, "application/zip".to_string()),
+                (
+                    CONTENT_DISPOSITION,
+                    attachment(text_export::ALL_TEXT_FILE_NAME),
+                ),
+            ],
+            Body::from_stream(text_export::all_zip_stream(state)),
+        )
+            .into_response());
+    }
+
+    let Some(context) = req.context.filter(|context| !context.trim().is_empty()) else {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "Give a context, or all=true".to_string(),
+        ));
+    };
+    let file_name = text_export::file_name(&context);
+    let text = tokio::task::spawn_blocking(move || text_export::context_text(&state, &context))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            "No cached pages for that context".to_string(),
+        ))?;
+    Ok((
+        [
+            (CONTENT_TYPE, "text/plain; charset=utf-8".to_string()),
+            (CONTENT_DISPOSITION, attachment(&file_name)),
+        ],
+        text,
+    )
+        .into_response())
 }
 
+/// Streams the cache as a gzip NDJSON download (see [`export`]), limited to one context or
+/// context prefix when `context` or `context_prefix` is given.
 pub async fn export_cache_handler(
     State(state): State<AppState>,
-) -> Json<std::collections::HashMap<String, CacheEntry>> {
-    Json(state.export_cache())
+    Query(filter): Query<ExportFilter>,
+) -> Response {
+    (
+        [
+            (CONTENT_TYPE, "application/gzip".to_string()),
+            (
+                CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", export::EXPORT_FILE_NAME),
+            ),
+        ],
+        Body::from_stream(export::export_stream(state, filter.normalized())),
+    )
+        .into_response()
+}
+
+#[derive(Deserialize)]
+pub struct ImportCacheQuery {
+    #[serde(default)]
+    pub overwrite: bool,
 }
 
+/// How much larger than the upload a gzip cache export is assumed to get once its rows
+/// are in the database. OCR JSON compresses well.
+const GZIP_IMPORT_EXPANSION: u64 = 5;
+
+/// Imports a gzip NDJSON export, a JSON object of cache entries keyed by cache key, or
+/// `{"overwrite": bool, "entries": {...}}`. Existing rows are only replaced in overwrite
+/// mode, set by the `overwrite` query parameter or field. Entries that fail to parse are
+/// reported instead of failing the whole import.
 pub async fn import_cache_handler(
     State(state): State<AppState>,
-    Json(data): Json<std::collections::HashMap<String, CacheEntry>>,
-) -> Json<serde_json::Value> {
-    let added = state.import_cache(data);
-    Json(serde_json::json!({ "message": "Import successful", "added": added }))
+    Query(query): Query<ImportCacheQuery>,
+    body: Bytes,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let incoming = if export::is_gzip(&body) {
+        body.len() as u64 * GZIP_IMPORT_EXPANSION
+    } else {
+        body.len() as u64
+    };
+    manatan_storage::ensure_space(&state.cache_dir, incoming)
+        .map_err(|e| (StatusCode::INSUFFICIENT_STORAGE, e.to_string()))?;
+    let report = if export::is_gzip(&body) {
+        let overwrite = query.overwrite;
+        tokio::task::spawn_blocking(move || export::import_ndjson_gz(&state, &body, overwrite))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .map_err(|e| {
+                (
+                    StatusCode::BAD_REQUEST,
+                    format!("Invalid cache export: {e}"),
+                )
+            })?
+    } else {
+        let mut body: serde_json::Value = serde_json::from_slice(&body)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid cache JSON: {e}")))?;
+        let mut overwrite = query.overwrite;
+        if body
+            .get("entries")
+            .is_some_and(serde_json::Value::is_object)
+        {
+            overwrite |= body["overwrite"].as_bool().unwrap_or(false);
+            body = body["entries"].take();
+        }
+        let serde_json::Value::Object(raw_entries) = body else {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Expected an object of cache entries".to_string(),
+            ));
+        };
+
+        let mut invalid = ImportReport::default();
+        let mut entries = HashMap::with_capacity(raw_entries.len());
+        for (key, value) in raw_entries {
+            match serde_json::from_value::<CacheEntry>(value) {
+                Ok(entry) => {
+                    entries.insert(key, entry);
+                }
+                Err(_) => invalid.note_invalid(key),
+            }
+        }
+        let mut report =
+            tokio::task::spawn_blocking(move || state.import_cache(entries, overwrite))
+                .await
+                .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        report.absorb(invalid);
+        report
+    };
+
+    let mut body = serde_json::to_value(&report)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    body["message"] = "Import successful".into();
+    if let Some(manifest) = &report.manifest
+        && manifest.entries != report.seen()
+    {
+        body["warning"] = format!(
+            "The export lists {} entries but {} were read; it may be truncated",
+            manifest.entries,
+            report.seen()
+        )
+        .into();
+    }
+    Ok(Json(body))
+}
+
+#[derive(Deserialize)]
+pub struct ArchiveCacheRequest {
+    pub context_prefix: String,
+}
+
+/// Moves the cache of every page whose context starts with `context_prefix` into a
+/// compressed archive file and deletes the rows.
+pub async fn archive_cache_handler(
+    State(state): State<AppState>,
+    Json(req): Json<ArchiveCacheRequest>,
+) -> Result<Json<ArchiveSummary>, (StatusCode, String)> {
+    // An empty prefix would archive the whole cache.
+    if req.context_prefix.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "context_prefix must not be empty".to_string(),
+        ));
+    }
+    let result = tokio::task::spawn_blocking(move || archive::archive(&state, &req.context_prefix))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match result {
+        Ok(Some(summary)) => Ok(Json(summary)),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            "No cached pages match context_prefix".to_string(),
+        )),
+        Err(e) if e.is::<manatan_storage::InsufficientSpace>() => {
+            Err((StatusCode::INSUFFICIENT_STORAGE, e.to_string()))
+        }
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+pub async fn unarchive_cache_handler(
+    State(state): State<AppState>,
+    Json(req): Json<ArchiveCacheRequest>,
+) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    let context_prefix = req.context_prefix.clone();
+    let result =
+        tokio::task::spawn_blocking(move || archive::unarchive(&state, &req.context_prefix))
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    match result {
+        Ok(Some(restored)) => Ok(Json(serde_json::json!({
+            "status": "restored",
+            "context_prefix": context_prefix,
+            "restored": restored,
+        }))),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            "No archive for context_prefix".to_string(),
+        )),
+        Err(e) => Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string())),
+    }
+}
+
+pub async fn list_archived_cache_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<ArchiveSummary>>, (StatusCode, String)> {
+    archive::list(&state)
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Stored source credentials, without their passwords.
+pub async fn list_credentials_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Vec<MaskedCredential>>, (StatusCode, String)> {
+    tokio::task::spawn_blocking(move || credentials::list(&state))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map(Json)
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+/// Stores credentials for a URL prefix, replacing any for the same prefix. The password
+/// is never sent back.
+pub async fn save_credentials_handler(
+    State(state): State<AppState>,
+    Json(credential): Json<SourceCredential>,
+) -> Result<Json<Vec<MaskedCredential>>, (StatusCode, String)> {
+    if credentials::normalize_prefix(&credential.prefix).is_none() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "prefix must not be empty".to_string(),
+        ));
+    }
+    if credential.user.trim().is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "user must not be empty".to_string(),
+        ));
+    }
+    tokio::task::spawn_blocking(move || {
+        credentials::save(&state, &credential)?;
+        credentials::list(&state)
+    })
+    .await
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+    .map(Json)
+    .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+}
+
+#[derive(Deserialize)]
+pub struct CredentialPrefix {
+    pub prefix: String,
+}
+
+pub async fn delete_credentials_handler(
+    State(state): State<AppState>,
+    Query(params): Query<CredentialPrefix>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    let removed = tokio::task::spawn_blocking(move || credentials::remove(&state, &params.prefix))
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    if removed {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err((
+            StatusCode::NOT_FOUND,
+            "No credentials stored for prefix".to_string(),
+        ))
+    }
 }
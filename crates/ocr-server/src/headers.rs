@@ -0,0 +1,91 @@
+//! Extra headers and cookies for page image fetches. Some sources check the Referer or
+//! need a session cookie, so the reader forwards what it used to display the image.
+
+use std::collections::HashMap;
+
+use reqwest::header::{AUTHORIZATION, COOKIE, HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Deserializer};
+
+/// Headers that describe the connection rather than the request, or that the fetch sets
+/// itself. They are dropped instead of forwarded.
+const DROPPED_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+    "authorization",
+];
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PageHeaders {
+    /// Header names and values, e.g. `Referer`.
+    pub headers: HashMap<String, String>,
+    /// A `Cookie` header value such as `session=abc; cf_clearance=xyz`.
+    pub cookies: Option<String>,
+    /// Sent as `Authorization: Bearer ...` in place of basic auth.
+    pub token: Option<String>,
+}
+
+impl PageHeaders {
+    /// The headers to send, without the dropped ones. `cookies` is appended to any
+    /// `Cookie` header and `token` becomes the `Authorization` header. Names or values
+    /// that are not valid HTTP are an error.
+    pub fn to_header_map(&self) -> Result<HeaderMap, String> {
+        let mut map = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| format!("invalid header name {name:?}"))?;
+            if DROPPED_HEADERS.contains(&name.as_str()) {
+                continue;
+            }
+            let value = HeaderValue::from_str(value.trim())
+                .map_err(|_| format!("invalid value for header {name}"))?;
+            map.insert(name, value);
+        }
+        if let Some(cookies) = self.cookies.as_deref().map(str::trim)
+            && !cookies.is_empty()
+        {
+            let cookie = match map.get(COOKIE).and_then(|value| value.to_str().ok()) {
+                Some(existing) => format!("{existing}; {cookies}"),
+                None => cookies.to_string(),
+            };
+            let value =
+                HeaderValue::from_str(&cookie).map_err(|_| "invalid cookies".to_string())?;
+            map.insert(COOKIE, value);
+        }
+        if let Some(token) = &self.token {
+            let value = HeaderValue::from_str(&format!("Bearer {}", token.trim()))
+                .map_err(|_| "invalid token".to_string())?;
+            map.insert(AUTHORIZATION, value);
+        }
+        Ok(map)
+    }
+}
+
+/// Accepts request headers either as an object or as a JSON string, since query strings
+/// cannot carry nested values.
+pub fn inline_headers<'de, D>(deserializer: D) -> Result<HashMap<String, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Inline {
+        Json(String),
+        Map(HashMap<String, String>),
+    }
+
+    match Option::<Inline>::deserialize(deserializer)? {
+        None => Ok(HashMap::new()),
+        Some(Inline::Map(map)) => Ok(map),
+        Some(Inline::Json(raw)) if raw.trim().is_empty() => Ok(HashMap::new()),
+        Some(Inline::Json(raw)) => serde_json::from_str(&raw).map_err(serde::de::Error::custom),
+    }
+}
@@ -0,0 +1,149 @@
+//! `GET /health`: OCRs the self-test image through a backend to confirm it is actually
+//! reachable, unlike `/`, which only says the server is up. Results are cached for
+//! [`HEALTH_TTL`] so polling the endpoint doesn't hammer Lens.
+
+use std::{sync::RwLock, time::Duration};
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::{
+    backend::OcrBackend,
+    language::OcrLanguage,
+    locks, logic,
+    selftest::TEST_IMAGE,
+    state::{AppState, OcrConfig, now_unix},
+};
+
+/// How long a probe result is served before the next request probes again.
+pub const HEALTH_TTL: Duration = Duration::from_secs(5 * 60);
+/// Budget for one probe, well under the page deadline so a blocked network shows up fast.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(15);
+
+lazy_static! {
+    /// Latest report per backend.
+    static ref REPORTS: RwLock<Vec<HealthReport>> = RwLock::new(Vec::new());
+    /// Held while probing, so concurrent requests for a stale report share one probe.
+    static ref PROBING: tokio::sync::Mutex<()> = tokio::sync::Mutex::new(());
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Ok,
+    Degraded,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    pub backend: OcrBackend,
+    /// False when the probe was skipped because `self_test_lens` is off.
+    pub probed: bool,
+    pub checked_at: i64,
+    pub latency_ms: u64,
+    /// The most recent probe failure, kept after the backend recovers.
+    pub last_error: Option<String>,
+    pub last_error_at: Option<i64>,
+}
+
+/// The cached report for `backend`, if it has been checked yet.
+pub fn latest(backend: OcrBackend) -> Option<HealthReport> {
+    locks::read(&REPORTS)
+        .iter()
+        .find(|report| report.backend == backend)
+        .cloned()
+}
+
+/// The report for `backend`, probing it first when the cached one is older than
+/// [`HEALTH_TTL`] or `refresh` is set.
+pub async fn check(state: &AppState, backend: OcrBackend, refresh: bool) -> HealthReport {
+    let _probing = PROBING.lock().await;
+    if !refresh && let Some(report) = latest(backend).filter(is_fresh) {
+        return report;
+    }
+
+    let config = state.ocr_config();
+    if backend == OcrBackend::Lens && !config.self_test_lens {
+        return record(backend, None, 0, Ok(()));
+    }
+    let probe_config = OcrConfig {
+        deadline_secs: PROBE_TIMEOUT.as_secs(),
+        retry_attempts: 1,
+        ..config
+    };
+    let started = std::time::Instant::now();
+    let result = tokio::time::timeout(PROBE_TIMEOUT, async {
+        let _permit = state.lens_limiter.acquire(backend).await;
+        logic::process_uploaded_image(
+            TEST_IMAGE,
+            None,
+            None,
+            &state.credentials,
+            None,
+            OcrLanguage::default(),
+            backend,
+            &probe_config,
+        )
+        .await
+        .map(|_| ())
+    })
+    .await
+    .unwrap_or_else(|_| {
+        Err(anyhow::anyhow!(
+            "No answer within {}s",
+            PROBE_TIMEOUT.as_secs()
+        ))
+    });
+    let latency_ms = started.elapsed().as_millis() as u64;
+    if let Err(err) = &result {
+        warn!("OCR health check for {} failed: {err}", backend.as_str());
+    }
+    record(backend, Some(now_unix()), latency_ms, result)
+}
+
+/// Stores the outcome of a probe of `backend` and returns the updated report. `probed_at`
+/// is unset when the probe was skipped.
+pub fn record(
+    backend: OcrBackend,
+    probed_at: Option<i64>,
+    latency_ms: u64,
+    result: anyhow::Result<()>,
+) -> HealthReport {
+    let mut reports = locks::write(&REPORTS);
+    let previous = reports.iter().position(|report| report.backend == backend);
+    let (mut last_error, mut last_error_at) = previous
+        .map(|index| {
+            let report = &reports[index];
+            (report.last_error.clone(), report.last_error_at)
+        })
+        .unwrap_or_default();
+    let checked_at = probed_at.unwrap_or_else(now_unix);
+    let status = match result {
+        Ok(()) => HealthStatus::Ok,
+        Err(err) => {
+            last_error = Some(err.to_string());
+            last_error_at = Some(checked_at);
+            HealthStatus::Degraded
+        }
+    };
+    let report = HealthReport {
+        status,
+        backend,
+        probed: probed_at.is_some(),
+        checked_at,
+        latency_ms,
+        last_error,
+        last_error_at,
+    };
+    match previous {
+        Some(index) => reports[index] = report.clone(),
+        None => reports.push(report.clone()),
+    }
+    report
+}
+
+fn is_fresh(report: &HealthReport) -> bool {
+    now_unix().saturating_sub(report.checked_at) < HEALTH_TTL.as_secs() as i64
+}
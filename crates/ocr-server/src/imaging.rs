@@ -0,0 +1,234 @@
+//! Encoding of derived images (page crops for Anki cards and the like) in a caller-chosen
+//! format and quality, with server-side caps on both.
+
+use std::{fmt, io::Cursor, time::Instant};
+
+use image::{
+    DynamicImage, ImageEncoder, Rgba, RgbaImage,
+    codecs::{
+        avif::AvifEncoder,
+        jpeg::JpegEncoder,
+        png::{CompressionType, FilterType, PngEncoder},
+        webp::WebPEncoder,
+    },
+    imageops::{self, FilterType as ResizeFilter},
+};
+use serde::Deserialize;
+use tracing::debug;
+
+/// Quality used for lossy formats when the caller does not pick one.
+pub const DEFAULT_QUALITY: u8 = 80;
+/// Higher qualities mostly add bytes; requests above this are clamped.
+pub const MAX_QUALITY: u8 = 95;
+/// Longest side of a derived image; larger crops are scaled down.
+pub const MAX_DIMENSION: u32 = 2048;
+/// AVIF encoder speed, 1 (slowest) to 10; crops are encoded on request so favour speed.
+const AVIF_SPEED: u8 = 8;
+/// Longest side a page is scaled to before [`edge_density`] looks at it.
+const EDGE_SCAN_DIMENSION: u32 = 512;
+/// Brightness step between neighbouring pixels that counts as an edge.
+const EDGE_STEP: u8 = 48;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    Webp,
+    #[default]
+    #[serde(alias = "jpg")]
+    Jpeg,
+    Png,
+    Avif,
+}
+
+impl OutputFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OutputFormat::Webp => "webp",
+            OutputFormat::Jpeg => "jpeg",
+            OutputFormat::Png => "png",
+            OutputFormat::Avif => "avif",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            OutputFormat::Webp => "image/webp",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Png => "image/png",
+            OutputFormat::Avif => "image/avif",
+        }
+    }
+
+    /// Whether `quality` means anything for this format. PNG is lossless, and WebP is only
+    /// encoded losslessly.
+    pub fn is_lossy(&self) -> bool {
+        matches!(self, OutputFormat::Jpeg | OutputFormat::Avif)
+    }
+}
+
+#[derive(Debug)]
+pub enum EncodeError {
+    /// A quality was given for a lossless format.
+    QualityUnsupported(OutputFormat),
+    /// Quality outside 1..=100.
+    InvalidQuality(u8),
+    Encode(String),
+}
+
+impl EncodeError {
+    /// Whether the caller asked for something impossible, as opposed to the encoder failing.
+    pub fn is_bad_request(&self) -> bool {
+        !matches!(self, EncodeError::Encode(_))
+    }
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::QualityUnsupported(format) => write!(
+                f,
+                "quality is not supported for {}, which is encoded losslessly",
+                format.as_str()
+            ),
+            EncodeError::InvalidQuality(quality) => {
+                write!(f, "quality must be between 1 and 100, got {quality}")
+            }
+            EncodeError::Encode(message) => write!(f, "failed to encode image: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+/// Encodes `image` as `format`, first scaling it down to [`MAX_DIMENSION`]. Quality is
+/// clamped to [`MAX_QUALITY`] and rejected for lossless formats.
+pub fn encode(
+    image: &DynamicImage,
+    format: OutputFormat,
+    quality: Option<u8>,
+) -> Result<Vec<u8>, EncodeError> {
+    let quality = match quality {
+        Some(_) if !format.is_lossy() => return Err(EncodeError::QualityUnsupported(format)),
+        Some(quality) if !(1..=100).contains(&quality) => {
+            return Err(EncodeError::InvalidQuality(quality));
+        }
+        Some(quality) => quality.min(MAX_QUALITY),
+        None => DEFAULT_QUALITY,
+    };
+
+    let started = Instant::now();
+    let scaled;
+    let image = if image.width() > MAX_DIMENSION || image.height() > MAX_DIMENSION {
+        scaled = image.resize(MAX_DIMENSION, MAX_DIMENSION, ResizeFilter::Triangle);
+        &scaled
+    } else {
+        image
+    };
+    // Every encoder below accepts 8-bit RGBA, except JPEG which has no alpha.
+    let mut bytes = Cursor::new(Vec::new());
+    let result = match format {
+        OutputFormat::Jpeg => {
+            let rgb = image.to_rgb8();
+            JpegEncoder::new_with_quality(&mut bytes, quality).write_image(
+                &rgb,
+                rgb.width(),
+                rgb.height(),
+                image::ExtendedColorType::Rgb8,
+            )
+        }
+        OutputFormat::Png => {
+            let rgba = image.to_rgba8();
+            PngEncoder::new_with_quality(&mut bytes, CompressionType::Best, FilterType::Adaptive)
+                .write_image(
+                    &rgba,
+                    rgba.width(),
+                    rgba.height(),
+                    image::ExtendedColorType::Rgba8,
+                )
+        }
+        OutputFormat::Webp => {
+            let rgba = image.to_rgba8();
+            WebPEncoder::new_lossless(&mut bytes).write_image(
+                &rgba,
+                rgba.width(),
+                rgba.height(),
+                image::ExtendedColorType::Rgba8,
+            )
+        }
+        OutputFormat::Avif => {
+            let rgba = image.to_rgba8();
+            AvifEncoder::new_with_speed_quality(&mut bytes, AVIF_SPEED, quality).write_image(
+                &rgba,
+                rgba.width(),
+                rgba.height(),
+                image::ExtendedColorType::Rgba8,
+            )
+        }
+    };
+    result.map_err(|err| EncodeError::Encode(err.to_string()))?;
+
+    let bytes = bytes.into_inner();
+    debug!(
+        "[IMAGE] Encoded {}x{} {} (quality {quality}) to {} bytes in {}ms",
+        image.width(),
+        image.height(),
+        format.as_str(),
+        bytes.len(),
+        started.elapsed().as_millis()
+    );
+    Ok(bytes)
+}
+
+/// Cuts the normalized rectangle `(x, y, width, height)` out of `image`. The rectangle is
+/// clamped to the image and is at least one pixel in each direction.
+pub fn crop(image: &DynamicImage, x: f64, y: f64, width: f64, height: f64) -> DynamicImage {
+    let (full_w, full_h) = (image.width() as f64, image.height() as f64);
+    let left = (x.clamp(0.0, 1.0) * full_w).floor() as u32;
+    let top = (y.clamp(0.0, 1.0) * full_h).floor() as u32;
+    let right = ((x + width).clamp(0.0, 1.0) * full_w).ceil() as u32;
+    let bottom = ((y + height).clamp(0.0, 1.0) * full_h).ceil() as u32;
+    let left = left.min(image.width().saturating_sub(1));
+    let top = top.min(image.height().saturating_sub(1));
+    image.crop_imm(
+        left,
+        top,
+        right.saturating_sub(left).max(1),
+        bottom.saturating_sub(top).max(1),
+    )
+}
+
+/// Share of pixels, from 0 to 1, that differ sharply from their right or lower neighbour
+/// once `image` is scaled down and turned grey. Blank pages and smooth art score close to
+/// zero, while every stroke of text adds to the count.
+pub fn edge_density(image: &DynamicImage) -> f64 {
+    let gray = image
+        .thumbnail(EDGE_SCAN_DIMENSION, EDGE_SCAN_DIMENSION)
+        .to_luma8();
+    let (width, height) = gray.dimensions();
+    if width < 2 || height < 2 {
+        return 0.0;
+    }
+    let mut edges = 0usize;
+    for y in 0..height - 1 {
+        for x in 0..width - 1 {
+            let here = gray.get_pixel(x, y)[0];
+            let right = gray.get_pixel(x + 1, y)[0];
+            let below = gray.get_pixel(x, y + 1)[0];
+            if here.abs_diff(right) >= EDGE_STEP || here.abs_diff(below) >= EDGE_STEP {
+                edges += 1;
+            }
+        }
+    }
+    edges as f64 / ((width - 1) as f64 * (height - 1) as f64)
+}
+
+/// Places `left` and `right` side by side on a white canvas as tall as the taller of the
+/// two, both aligned to the top, so a double-page spread can be read as one image.
+pub fn stitch_horizontally(left: &DynamicImage, right: &DynamicImage) -> DynamicImage {
+    let width = left.width() + right.width();
+    let height = left.height().max(right.height());
+    let mut canvas = RgbaImage::from_pixel(width, height, Rgba([255, 255, 255, 255]));
+    imageops::overlay(&mut canvas, &left.to_rgba8(), 0, 0);
+    imageops::overlay(&mut canvas, &right.to_rgba8(), left.width() as i64, 0);
+    DynamicImage::ImageRgba8(canvas)
+}
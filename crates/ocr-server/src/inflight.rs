@@ -0,0 +1,77 @@
+use std::{collections::HashMap, future::Future, sync::Mutex};
+
+use tokio::sync::watch;
+
+use crate::locks;
+
+/// Deduplicates concurrent work by key: the first caller computes, later callers with the
+/// same key wait for that result instead of starting their own.
+pub struct InFlight<T> {
+    pending: Mutex<HashMap<String, watch::Receiver<Option<T>>>>,
+}
+
+impl<T> Default for InFlight<T> {
+    fn default() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+enum Role<T> {
+    Leader(watch::Sender<Option<T>>),
+    Follower(watch::Receiver<Option<T>>),
+}
+
+impl<T: Clone> InFlight<T> {
+    /// Runs `compute` unless an identical `key` is already in flight, in which case the
+    /// running computation's result is shared. If the computing caller is dropped before
+    /// it finishes, a waiting caller takes over.
+    pub async fn run<F, Fut>(&self, key: &str, compute: F) -> T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        loop {
+            match self.join(key) {
+                Role::Leader(sender) => {
+                    let _guard = PendingGuard { owner: self, key };
+                    let value = compute().await;
+                    sender.send_replace(Some(value.clone()));
+                    return value;
+                }
+                Role::Follower(mut receiver) => {
+                    if let Ok(value) = receiver.wait_for(Option::is_some).await
+                        && let Some(value) = value.clone()
+                    {
+                        return value;
+                    }
+                    // The leader was cancelled; retry and possibly lead.
+                }
+            }
+        }
+    }
+
+    fn join(&self, key: &str) -> Role<T> {
+        let mut pending = locks::lock(&self.pending);
+        if let Some(receiver) = pending.get(key)
+            && receiver.has_changed().is_ok()
+        {
+            return Role::Follower(receiver.clone());
+        }
+        let (sender, receiver) = watch::channel(None);
+        pending.insert(key.to_string(), receiver);
+        Role::Leader(sender)
+    }
+}
+
+struct PendingGuard<'a, T> {
+    owner: &'a InFlight<T>,
+    key: &'a str,
+}
+
+impl<T> Drop for PendingGuard<'_, T> {
+    fn drop(&mut self) {
+        locks::lock(&self.owner.pending).remove(self.key);
+    }
+}
@@ -0,0 +1,118 @@
+//! Finished chapter jobs, kept in the cache DB with the pages that failed and why, so a
+//! failed chapter can be inspected and its failed pages retried later.
+
+use rusqlite::{OptionalExtension, Row, params};
+use serde::{Deserialize, Serialize};
+
+use crate::state::AppState;
+
+/// Jobs kept; recording one drops the oldest beyond this.
+pub const MAX_ENTRIES: i64 = 1000;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PageFailure {
+    pub url: String,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct JobRecord {
+    /// Assigned when the record is stored.
+    pub id: i64,
+    pub chapter_key: String,
+    pub context: String,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub total_pages: usize,
+    /// Pages already cached when the job started.
+    pub skipped_pages: usize,
+    /// Pages OCR'd by this job.
+    pub processed_pages: usize,
+    pub failures: Vec<PageFailure>,
+}
+
+impl JobRecord {
+    pub fn failed(&self) -> bool {
+        !self.failures.is_empty()
+    }
+
+    /// URLs of the pages that failed, for retrying just those.
+    pub fn failed_pages(&self) -> Vec<String> {
+        self.failures
+            .iter()
+            .map(|failure| failure.url.clone())
+            .collect()
+    }
+
+    fn from_row(row: &Row<'_>) -> rusqlite::Result<Self> {
+        let failures: String = row.get(8)?;
+        Ok(Self {
+            id: row.get(0)?,
+            chapter_key: row.get(1)?,
+            context: row.get(2)?,
+            started_at: row.get(3)?,
+            finished_at: row.get(4)?,
+            total_pages: row.get::<_, i64>(5)? as usize,
+            skipped_pages: row.get::<_, i64>(6)? as usize,
+            processed_pages: row.get::<_, i64>(7)? as usize,
+            failures: serde_json::from_str(&failures).unwrap_or_default(),
+        })
+    }
+}
+
+const COLUMNS: &str = "id, chapter_key, context, started_at, finished_at, total_pages,
+     skipped_pages, processed_pages, failures";
+
+/// Stores a finished job and returns its id.
+pub fn record(state: &AppState, job: &JobRecord) -> anyhow::Result<i64> {
+    let conn = state.conn()?;
+    conn.execute(
+        "INSERT INTO job_history (chapter_key, context, started_at, finished_at, total_pages,
+            skipped_pages, processed_pages, failures)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            job.chapter_key,
+            job.context,
+            job.started_at,
+            job.finished_at,
+            job.total_pages as i64,
+            job.skipped_pages as i64,
+            job.processed_pages as i64,
+            serde_json::to_string(&job.failures)?,
+        ],
+    )?;
+    let id = conn.last_insert_rowid();
+    conn.execute(
+        "DELETE FROM job_history WHERE id <= ?",
+        params![id - MAX_ENTRIES],
+    )?;
+    Ok(id)
+}
+
+/// The most recent jobs, newest first.
+pub fn recent(state: &AppState, limit: usize) -> anyhow::Result<Vec<JobRecord>> {
+    let conn = state.conn()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {COLUMNS} FROM job_history ORDER BY id DESC LIMIT ?"
+    ))?;
+    let jobs = stmt
+        .query_map(params![limit as i64], JobRecord::from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(jobs)
+}
+
+/// The chapter's last job, if it left pages failed.
+pub fn last_failed(state: &AppState, chapter_key: &str) -> Option<JobRecord> {
+    let conn = state.conn().ok()?;
+    conn.query_row(
+        &format!(
+            "SELECT {COLUMNS} FROM job_history WHERE chapter_key = ? ORDER BY id DESC LIMIT 1"
+        ),
+        params![chapter_key],
+        JobRecord::from_row,
+    )
+    .optional()
+    .ok()
+    .flatten()
+    .filter(JobRecord::failed)
+}
@@ -1,93 +1,749 @@
-use std::sync::{
-    Arc,
-    atomic::{AtomicUsize, Ordering},
+use std::{
+    collections::VecDeque,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, Instant},
 };
 
 use futures::StreamExt;
+use manatan_jobs::{JobHandle, QueueFull};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, mpsc, watch};
 
 use crate::{
+    backend::OcrBackend,
+    cbz::PageArchive,
+    headers::PageHeaders,
+    job_history::{self, JobRecord, PageFailure},
     language::OcrLanguage,
-    state::{AppState, JobProgress},
+    locks,
+    logic::{CacheKeyConfig, OcrOutcome},
+    metrics::{self, METRICS},
+    page_events::PageStatus,
+    pdf,
+    state::{AppState, JobProgress, OcrConfig, PreprocessProgress, PreprocessStatus, now_unix},
+    throttle::LENS_PACER,
 };
 
-#[allow(clippy::too_many_arguments)]
-pub async fn run_chapter_job(
-    state: AppState,
-    base_url: String,
-    pages: Vec<String>,
-    user: Option<String>,
-    pass: Option<String>,
-    context: String,
+/// Chapters processed at once. Each job already fans out over its pages, so a couple
+/// of workers keeps Lens busy without letting a burst of chapters flood it.
+const CHAPTER_WORKERS: usize = if cfg!(target_os = "android") { 1 } else { 2 };
+/// Chapters that may wait for a worker; further requests are turned away.
+pub const MAX_QUEUED_CHAPTERS: usize = 100;
+/// How preprocess jobs are listed in the launcher's `/api/jobs`.
+const POOL_CLASS: &str = "ocr-preprocess";
+/// Page downloads a chapter job runs at once, ahead of its OCR stage.
+const DOWNLOAD_WORKERS: usize = if cfg!(target_os = "android") { 2 } else { 4 };
+/// Downloaded pages a chapter job may hold before they are OCRed, counting those being
+/// OCRed, so a long chapter never runs more than a few pages ahead.
+const PREFETCH_PAGES: usize = if cfg!(target_os = "android") { 4 } else { 12 };
+/// The same limit in bytes, for sources that serve very large pages.
+const PREFETCH_BYTES: usize = if cfg!(target_os = "android") {
+    32 * 1024 * 1024
+} else {
+    128 * 1024 * 1024
+};
+
+/// A chapter waiting for, or being handled by, a preprocess worker.
+#[derive(Debug, Clone)]
+pub struct ChapterJob {
+    pub base_url: String,
+    pub pages: Vec<String>,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    pub context: String,
+    pub add_space_on_merge: Option<bool>,
+    pub language: OcrLanguage,
+    /// Extra headers and cookies for every page fetch.
+    pub headers: PageHeaders,
+    /// Local archive the pages are read from instead of being fetched; `pages` then hold
+    /// the archive's placeholder page URLs, in order.
+    pub archive: Option<Arc<PageArchive>>,
+    /// OCR every page, including ones that look blank and ones an earlier job skipped.
+    pub force: bool,
+}
+
+impl ChapterJob {
+    pub fn key(&self, key_config: &CacheKeyConfig) -> String {
+        crate::logic::get_cache_key(&self.base_url, Some(self.language), key_config)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Enqueued {
+    /// Newly queued at this 1-based position.
+    Queued(usize),
+    /// Already waiting at this 1-based position.
+    AlreadyQueued(usize),
+    AlreadyRunning,
+    /// Rejected because the queue already holds its maximum of chapters.
+    Full(QueueFull),
+}
+
+/// Bounded FIFO of chapters waiting for a preprocess worker.
+#[derive(Clone)]
+pub struct JobQueue {
+    sender: mpsc::Sender<(ChapterJob, JobHandle)>,
+    receiver: Arc<tokio::sync::Mutex<mpsc::Receiver<(ChapterJob, JobHandle)>>>,
+    /// Keys of queued jobs in order; a key leaves only once its job is registered in
+    /// `active_chapter_jobs`, so a chapter is always visible as queued or running.
+    pending: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel(MAX_QUEUED_CHAPTERS);
+        manatan_jobs::register_pool(POOL_CLASS, CHAPTER_WORKERS, MAX_QUEUED_CHAPTERS);
+        Self {
+            sender,
+            receiver: Arc::new(tokio::sync::Mutex::new(receiver)),
+            pending: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+}
+
+impl JobQueue {
+    /// 1-based position of a queued chapter.
+    pub fn position(&self, key: &str) -> Option<usize> {
+        locks::lock(&self.pending)
+            .iter()
+            .position(|pending| pending == key)
+            .map(|index| index + 1)
+    }
+
+    pub fn len(&self) -> usize {
+        locks::lock(&self.pending).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn mark_started(&self, key: &str) {
+        locks::lock(&self.pending).retain(|pending| pending != key);
+    }
+}
+
+/// Queues a chapter unless it is already queued or running, or the queue is full.
+pub fn enqueue(state: &AppState, job: ChapterJob) -> Enqueued {
+    let key = job.key(&state.cache_key_config());
+    let mut pending = locks::lock(&state.job_queue.pending);
+    if locks::read(&state.active_chapter_jobs).contains_key(&key) {
+        return Enqueued::AlreadyRunning;
+    }
+    if let Some(index) = pending.iter().position(|queued| *queued == key) {
+        return Enqueued::AlreadyQueued(index + 1);
+    }
+    if pending.len() >= MAX_QUEUED_CHAPTERS {
+        return full(pending.len());
+    }
+    let handle = manatan_jobs::track(POOL_CLASS, "ocr", job.context.clone());
+    // The queue owns its receiver, so the channel never closes.
+    if state.job_queue.sender.try_send((job, handle)).is_err() {
+        return full(pending.len());
+    }
+    pending.push_back(key);
+    Enqueued::Queued(pending.len())
+}
+
+fn full(depth: usize) -> Enqueued {
+    Enqueued::Full(QueueFull {
+        class: POOL_CLASS,
+        depth,
+        capacity: MAX_QUEUED_CHAPTERS,
+    })
+}
+
+/// Starts the workers that drain the preprocess queue.
+pub fn spawn_workers(state: &AppState) {
+    for worker in 0..CHAPTER_WORKERS {
+        let state = state.clone();
+        tokio::spawn(async move {
+            loop {
+                let job = state.job_queue.receiver.lock().await.recv().await;
+                let Some((job, handle)) = job else {
+                    break;
+                };
+                tracing::info!("[Worker {worker}] Picked up {}", job.context);
+                handle.start();
+                run_chapter_job(state.clone(), job, handle).await;
+            }
+        });
+    }
+}
+
+/// Pacing for chapter jobs, on top of the adaptive Lens pacing. Persisted in
+/// [`OcrConfig`](crate::state::OcrConfig) and exposed at `/job-settings`; running jobs
+/// pick up changes from their next page.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default)]
+pub struct JobSettings {
+    /// Pause between bursts of pages sent to OCR.
+    pub page_delay_ms: u64,
+    /// Pages sent back to back before the delay applies; 1 pauses before every page.
+    pub burst_size: usize,
+    /// Consecutive page failures that pause the job; 0 never pauses.
+    pub error_cooldown_after: usize,
+    /// How long that pause lasts.
+    pub error_cooldown_secs: u64,
+    /// Pages whose [`edge_density`](crate::imaging::edge_density) falls below this are
+    /// cached as empty without calling Lens; 0 sends every page.
+    pub blank_page_threshold: f64,
+}
+
+impl Default for JobSettings {
+    fn default() -> Self {
+        Self {
+            page_delay_ms: 0,
+            burst_size: 1,
+            error_cooldown_after: 0,
+            error_cooldown_secs: 30,
+            blank_page_threshold: 0.002,
+        }
+    }
+}
+
+impl JobSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.burst_size == 0 {
+            return Err("burst_size must be at least 1".to_string());
+        }
+        if self.page_delay_ms > 60_000 {
+            return Err(format!(
+                "page_delay_ms must be at most 60000, got {}",
+                self.page_delay_ms
+            ));
+        }
+        if self.error_cooldown_secs > 3600 {
+            return Err(format!(
+                "error_cooldown_secs must be at most 3600, got {}",
+                self.error_cooldown_secs
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.blank_page_threshold) {
+            return Err(format!(
+                "blank_page_threshold must be between 0 and 1, got {}",
+                self.blank_page_threshold
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Recorded as the `skipped_reason` of pages cached empty because they looked blank.
+pub const SKIPPED_BLANK: &str = "blank";
+
+/// Whether `bytes` decode to a page with too few edges to hold text under `settings`.
+/// Pages that fail to decode are left for the OCR backend to report on.
+pub fn looks_blank(bytes: &[u8], settings: &JobSettings) -> bool {
+    settings.blank_page_threshold > 0.0
+        && image::load_from_memory(bytes)
+            .is_ok_and(|image| crate::imaging::edge_density(&image) < settings.blank_page_threshold)
+}
+
+/// Applies [`JobSettings`] across the pages one chapter job OCRs concurrently.
+#[derive(Default)]
+pub struct JobPacer {
+    /// Pages sent since the last pause. Held across the pause so other pages wait on it.
+    sent_in_burst: tokio::sync::Mutex<usize>,
+    consecutive_errors: AtomicUsize,
+}
+
+impl JobPacer {
+    /// Waits out the cool-down or inter-burst delay that is due before the next page.
+    /// Pages queue behind the pause rather than each sleeping on its own.
+    pub async fn before_page(&self, settings: &JobSettings) {
+        let mut sent_in_burst = self.sent_in_burst.lock().await;
+        let errors = self.consecutive_errors.load(Ordering::Relaxed);
+        if settings.error_cooldown_after > 0 && errors >= settings.error_cooldown_after {
+            tracing::warn!(
+                "[Job] {errors} pages failed in a row, cooling down for {}s",
+                settings.error_cooldown_secs
+            );
+            tokio::time::sleep(Duration::from_secs(settings.error_cooldown_secs)).await;
+            self.consecutive_errors.store(0, Ordering::Relaxed);
+        }
+        if *sent_in_burst >= settings.burst_size.max(1) {
+            if settings.page_delay_ms > 0 {
+                tokio::time::sleep(Duration::from_millis(settings.page_delay_ms)).await;
+            }
+            *sent_in_burst = 0;
+        }
+        *sent_in_burst += 1;
+    }
+
+    pub fn page_finished(&self, ok: bool) {
+        if ok {
+            self.consecutive_errors.store(0, Ordering::Relaxed);
+        } else {
+            self.consecutive_errors.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Caps what a chapter job holds in downloaded pages that are not OCRed yet, by count and
+/// by size.
+pub struct PrefetchBudget {
+    pages: Arc<Semaphore>,
+    kib: Arc<Semaphore>,
+    max_kib: u32,
+}
+
+/// Room for one page in a [`PrefetchBudget`], given back when dropped.
+pub struct PrefetchPermit {
+    _page: OwnedSemaphorePermit,
+    _kib: OwnedSemaphorePermit,
+}
+
+impl PrefetchBudget {
+    pub fn new(pages: usize, bytes: usize) -> Self {
+        let max_kib = u32::try_from(bytes.div_ceil(1024))
+            .unwrap_or(u32::MAX)
+            .max(1);
+        Self {
+            pages: Arc::new(Semaphore::new(pages.max(1))),
+            kib: Arc::new(Semaphore::new(max_kib as usize)),
+            max_kib,
+        }
+    }
+
+    /// Waits until a page of `len` bytes fits. A page larger than the whole budget waits
+    /// until nothing else is held.
+    pub async fn reserve(&self, len: usize) -> PrefetchPermit {
+        let kib = u32::try_from(len.div_ceil(1024))
+            .unwrap_or(u32::MAX)
+            .clamp(1, self.max_kib);
+        // The semaphores are never closed.
+        let page = self
+            .pages
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("prefetch budget is never closed");
+        let kib = self
+            .kib
+            .clone()
+            .acquire_many_owned(kib)
+            .await
+            .expect("prefetch budget is never closed");
+        PrefetchPermit {
+            _page: page,
+            _kib: kib,
+        }
+    }
+
+    /// Pages that still fit.
+    pub fn available_pages(&self) -> usize {
+        self.pages.available_permits()
+    }
+}
+
+/// A page handed from the download stage to the OCR stage.
+struct PrefetchedPage {
+    url: String,
+    cache_key: String,
+    bytes: anyhow::Result<Vec<u8>>,
+    /// How long the download or archive read took.
+    fetch: Duration,
+    /// Held until the page is OCRed; failed downloads hold nothing.
+    permit: Option<PrefetchPermit>,
+}
+
+/// What a chapter job needs to OCR the pages of a PDF it fetched.
+struct PdfPages<'a> {
+    state: &'a AppState,
+    job_id: &'a str,
+    cache_key: &'a str,
+    context: &'a str,
+    user: &'a Option<String>,
+    pass: &'a Option<String>,
     add_space_on_merge: Option<bool>,
     language: OcrLanguage,
-) {
+    config: &'a OcrConfig,
+    force: bool,
+    pacer: &'a JobPacer,
+}
+
+impl PdfPages<'_> {
+    /// OCRs and caches pages 2 and up of `document`, each under its own key, and returns
+    /// page 1 rendered for the caller to handle like any other page. Page 1 is cached
+    /// last, so a re-run that finds it cached knows the whole document is; pages an
+    /// interrupted run already cached are skipped unless the job is forced.
+    async fn ocr_later_pages(&self, document: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        let document = Arc::new(document);
+        let pages = pdf::page_count(document.clone()).await?;
+        for page in 2..=pages {
+            let page_key = pdf::page_cache_key(self.cache_key, Some(page));
+            if !self.force && self.state.has_cache_entry(&page_key)? {
+                continue;
+            }
+            let image = pdf::render_page(document.clone(), page, self.config.pdf_dpi).await?;
+            self.pacer.before_page(&self.state.ocr_config().jobs).await;
+            let outcome = {
+                let _lens = self.state.lens_limiter.acquire(OcrBackend::Lens).await;
+                crate::logic::process_uploaded_image(
+                    &image,
+                    self.user.clone(),
+                    self.pass.clone(),
+                    &self.state.credentials,
+                    self.add_space_on_merge,
+                    self.language,
+                    OcrBackend::Lens,
+                    self.config,
+                )
+                .await
+            };
+            self.pacer
+                .page_finished(matches!(&outcome, Ok(outcome) if !outcome.partial));
+            let outcome = outcome?;
+            if outcome.partial {
+                return Err(anyhow::anyhow!(
+                    "Deadline exceeded with partial results on PDF page {page}"
+                ));
+            }
+            self.state.cache_outcome(
+                &page_key,
+                self.context.to_string(),
+                OcrBackend::Lens,
+                &outcome,
+            );
+            tracing::info!("[Job {}] Cached PDF page {page} of {pages}", self.job_id);
+        }
+        pdf::render_page(document, 1, self.config.pdf_dpi).await
+    }
+}
+
+pub async fn run_chapter_job(state: AppState, job: ChapterJob, handle: JobHandle) {
+    let key_config = state.cache_key_config();
+    let job_id = job.key(&key_config);
+    let ChapterJob {
+        base_url,
+        pages,
+        user,
+        pass,
+        context,
+        add_space_on_merge,
+        language,
+        headers,
+        archive,
+        force,
+    } = job;
     let total = pages.len();
-    let job_id = crate::logic::get_cache_key(&base_url, Some(language));
+    let started_at = now_unix();
+    let config = state.ocr_config().for_page(&base_url).into_owned();
+    let (updates, receiver) = watch::channel(PreprocessProgress {
+        total,
+        ..Default::default()
+    });
+    let updates = &updates;
 
     {
-        state
-            .active_chapter_jobs
-            .write()
-            .expect("lock poisoned")
-            .insert(job_id.clone(), JobProgress { current: 0, total });
+        locks::write(&state.active_chapter_jobs).insert(
+            job_id.clone(),
+            JobProgress {
+                current: 0,
+                total,
+                adaptive_delay_ms: 0,
+                throttled_since: None,
+                updates: receiver,
+            },
+        );
+    }
+    state.job_queue.mark_started(&job_id);
+
+    // Look the whole chapter up at once, so a re-run after a crash or a failed job goes
+    // straight to the pages that are still missing.
+    let pages: Vec<(usize, String, String)> = pages
+        .into_iter()
+        .enumerate()
+        .map(|(index, url)| {
+            let cache_key = crate::logic::get_cache_key(&url, Some(language), &key_config);
+            (index, url, cache_key)
+        })
+        .collect();
+    let cache_keys: Vec<String> = pages.iter().map(|(_, _, key)| key.clone()).collect();
+    let cached = state.cached_keys(&cache_keys).and_then(|mut cached| {
+        if force {
+            // Pages an earlier job skipped as blank get their Lens call this time.
+            let skipped = state.skipped_keys(&cache_keys)?;
+            cached.retain(|key| !skipped.contains(key));
+        }
+        Ok(cached)
+    });
+    let cached = match cached {
+        Ok(cached) => cached,
+        Err(err) => {
+            // Treating every page as missing would send the whole chapter to Lens
+            // again, so give up and let the reader retry.
+            tracing::warn!("[Job {job_id}] Could not look up cached pages: {err}");
+            updates.send_modify(|progress| progress.status = PreprocessStatus::Failed);
+            locks::write(&state.active_chapter_jobs).remove(&job_id);
+            return;
+        }
+    };
+    for cache_key in &cached {
+        state.insert_chapter_cache(&job_id, cache_key);
+    }
+    let missing: Vec<(usize, String, String)> = pages
+        .into_iter()
+        .filter(|(_, _, key)| !cached.contains(key))
+        .collect();
+    let skipped = total - missing.len();
+    METRICS.cache_hits(metrics::Path::Job, skipped);
+    METRICS.cache_misses(metrics::Path::Job, missing.len());
+    state.set_chapter_progress(&job_id, total, skipped);
+    if let Some(prog) = locks::write(&state.active_chapter_jobs).get_mut(&job_id) {
+        prog.current = skipped;
     }
+    updates.send_modify(|progress| {
+        progress.current = skipped;
+        progress.skipped = skipped;
+    });
+    handle.set_progress(skipped, total);
 
     state.active_jobs.fetch_add(1, Ordering::Relaxed);
-    tracing::info!("[Job] Started for {} ({} pages)", context, total);
+    tracing::info!(
+        "[Job] Started for {} ({} pages, {} already cached)",
+        context,
+        total,
+        skipped
+    );
+
+    let pacer = JobPacer::default();
+    let completed_counter = Arc::new(AtomicUsize::new(skipped));
+    let processed_counter = Arc::new(AtomicUsize::new(skipped));
+    let error_counter = Arc::new(AtomicUsize::new(0));
+    let failures = Mutex::new(Vec::<PageFailure>::new());
 
-    let completed_counter = Arc::new(AtomicUsize::new(0));
-    let processed_counter = Arc::new(AtomicUsize::new(0));
-    let stream = futures::stream::iter(pages.into_iter());
+    // Pages are downloaded ahead of the OCR stage so fetch and Lens latency overlap; the
+    // budget bounds how much the download side may hold at once.
+    let budget = PrefetchBudget::new(PREFETCH_PAGES, PREFETCH_BYTES);
+    let (prefetched, mut ready) = mpsc::channel::<PrefetchedPage>(PREFETCH_PAGES);
+    let download = async {
+        // Owned here, so the channel closes once every page has been handed over.
+        let prefetched = prefetched;
+        futures::stream::iter(missing)
+            .for_each_concurrent(DOWNLOAD_WORKERS, |(index, url, cache_key)| {
+                let prefetched = prefetched.clone();
+                let archive = archive.clone();
+                let (budget, user, pass, headers, config) =
+                    (&budget, &user, &pass, &headers, &config);
+                let credentials = &state.credentials;
+                async move {
+                    updates.send_modify(|progress| progress.fetching += 1);
+                    let fetch_started = Instant::now();
+                    let bytes = match archive {
+                        Some(archive) => {
+                            tokio::task::spawn_blocking(move || archive.page_bytes(index))
+                                .await
+                                .map_err(anyhow::Error::from)
+                                .and_then(|bytes| bytes)
+                        }
+                        None => {
+                            crate::logic::download_document(
+                                &url,
+                                user,
+                                pass,
+                                credentials,
+                                headers,
+                                config,
+                            )
+                            .await
+                        }
+                    };
+                    let fetch = fetch_started.elapsed();
+                    updates.send_modify(|progress| {
+                        progress.fetching -= 1;
+                        progress.buffered += 1;
+                    });
+                    let permit = match &bytes {
+                        Ok(bytes) => Some(budget.reserve(bytes.len()).await),
+                        Err(_) => None,
+                    };
+                    // The receiver lives until every page is sent.
+                    let _ = prefetched
+                        .send(PrefetchedPage {
+                            url,
+                            cache_key,
+                            bytes,
+                            fetch,
+                            permit,
+                        })
+                        .await;
+                }
+            })
+            .await;
+    };
 
     // Change from 6 to 2 or 3 for Android stability
     let concurrency_limit = if cfg!(target_os = "android") { 2 } else { 6 };
 
-    stream
-        .for_each_concurrent(concurrency_limit, |url| {
+    let recognize = futures::stream::poll_fn(|cx| ready.poll_recv(cx)).for_each_concurrent(
+        concurrency_limit,
+        |page| {
             let state = state.clone();
             let job_id = job_id.clone();
             let user = user.clone();
             let pass = pass.clone();
             let context = context.clone();
+            let config = config.clone();
             let completed_counter = completed_counter.clone();
             let processed_counter = processed_counter.clone();
+            let error_counter = error_counter.clone();
+            let handle = handle.clone();
+            let pacer = &pacer;
+            let failures = &failures;
 
+            let PrefetchedPage {
+                url,
+                cache_key,
+                bytes,
+                fetch,
+                permit,
+            } = page;
             let page_id = url.split('/').next_back().unwrap_or("unknown").to_string();
 
             async move {
-                let cache_key = crate::logic::get_cache_key(&url, Some(language));
-                let exists = state.has_cache_entry(&cache_key);
-                if exists {
-                    state.insert_chapter_cache(&job_id, &cache_key);
-                    processed_counter.fetch_add(1, Ordering::Relaxed);
-                    tracing::info!("[Page {page_id}] Skip (Cached)");
+                // A PDF chapter page holds a whole document: its later pages are OCRed
+                // here and page 1 goes on like any other image.
+                let bytes = match bytes {
+                    Ok(document) if pdf::is_pdf(&document) => {
+                        let pdf_job = PdfPages {
+                            state: &state,
+                            job_id: &job_id,
+                            cache_key: &cache_key,
+                            context: &context,
+                            user: &user,
+                            pass: &pass,
+                            add_space_on_merge,
+                            language,
+                            config: &config,
+                            force,
+                            pacer,
+                        };
+                        pdf_job.ocr_later_pages(document).await
+                    }
+                    bytes => bytes,
+                };
+                let blank = match &bytes {
+                    Ok(bytes) if !force && config.jobs.blank_page_threshold > 0.0 => {
+                        let (bytes, settings) = (bytes.clone(), config.jobs.clone());
+                        tokio::task::spawn_blocking(move || looks_blank(&bytes, &settings))
+                            .await
+                            .unwrap_or(false)
+                    }
+                    _ => false,
+                };
+
+                // Blank pages never reach Lens, so they wait on neither its pacing nor a slot.
+                let _lens = if blank {
+                    None
                 } else {
-                    tracing::info!("[Page {page_id}] Starting fetch_and_process (Async)...");
-
-                    // None defaults to Smart Detection for space merging
-                    match crate::logic::fetch_and_process(
-                        &url,
-                        user,
-                        pass,
-                        add_space_on_merge,
-                        language,
-                    )
-                    .await
-                    {
-                        Ok(res) => {
-                            state.insert_cache_entry(
-                                &cache_key,
-                                &crate::state::CacheEntry {
-                                    context: context.clone(),
-                                    data: res,
-                                },
-                            );
-                            state.insert_chapter_cache(&job_id, &cache_key);
-                            processed_counter.fetch_add(1, Ordering::Relaxed);
+                    pacer.before_page(&state.ocr_config().jobs).await;
+                    let delay = LENS_PACER.current_delay();
+                    if !delay.is_zero() {
+                        tracing::info!(
+                            "[Page {page_id}] Waiting {}ms (adaptive Lens pacing)",
+                            delay.as_millis()
+                        );
+                        tokio::time::sleep(delay).await;
+                    }
+                    Some(state.lens_limiter.acquire(OcrBackend::Lens).await)
+                };
+                updates.send_modify(|progress| {
+                    progress.buffered -= 1;
+                    progress.recognizing += 1;
+                });
+
+                // None defaults to Smart Detection for space merging
+                let started = Instant::now();
+                let mut result = match bytes {
+                    Ok(_) if blank => {
+                        tracing::info!("[Page {page_id}] Looks blank, caching it without OCR");
+                        Ok(OcrOutcome {
+                            results: Vec::new(),
+                            partial: false,
+                            orientation: None,
+                            orientation_hint: config.merge.orientation_hint,
+                            preprocess: config.preprocess,
+                            timings: Default::default(),
+                            image_hash: None,
+                            raw: None,
+                        })
+                    }
+                    Ok(bytes) => {
+                        tracing::info!("[Page {page_id}] Starting OCR (Async)...");
+                        let image_hash =
+                            crate::logic::image_hash(&bytes, language, OcrBackend::Lens);
+                        match state.reuse_by_image_hash(&image_hash, config.preprocess) {
+                            Some(outcome) => {
+                                tracing::info!("[Page {page_id}] Identical image already cached");
+                                Ok(outcome)
+                            }
+                            None => crate::logic::process_uploaded_image(
+                                &bytes,
+                                user,
+                                pass,
+                                &state.credentials,
+                                add_space_on_merge,
+                                language,
+                                OcrBackend::Lens,
+                                &config,
+                            )
+                            .await
+                            .map(|outcome| OcrOutcome {
+                                image_hash: Some(image_hash),
+                                ..outcome
+                            }),
                         }
-                        Err(err) => {
-                            tracing::warn!("[Page {page_id}] Failed: {err:?}");
+                    }
+                    Err(err) => Err(err),
+                };
+                drop(permit);
+                if let Ok(outcome) = &mut result {
+                    outcome.timings.fetch = fetch;
+                }
+                METRICS.page_done(metrics::Path::Job, &result, fetch + started.elapsed());
+                if !blank {
+                    pacer.page_finished(matches!(&result, Ok(outcome) if !outcome.partial));
+                }
+                match result {
+                    // Partial pages stay uncached so re-running the job picks them up again.
+                    Ok(outcome) if outcome.partial => {
+                        tracing::warn!(
+                            "[Page {page_id}] Failed: deadline exceeded with partial results"
+                        );
+                        error_counter.fetch_add(1, Ordering::Relaxed);
+                        state.notify_page(&cache_key, &context, PageStatus::Failed);
+                        locks::lock(failures).push(PageFailure {
+                            url,
+                            error: "Deadline exceeded with partial results".to_string(),
+                        });
+                    }
+                    Ok(outcome) => {
+                        state.cache_outcome(
+                            &cache_key,
+                            context.clone(),
+                            OcrBackend::Lens,
+                            &outcome,
+                        );
+                        if blank {
+                            state.mark_skipped(&cache_key, SKIPPED_BLANK);
+                            updates.send_modify(|progress| progress.blank += 1);
                         }
+                        state.insert_chapter_cache(&job_id, &cache_key);
+                        processed_counter.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Err(err) => {
+                        tracing::warn!("[Page {page_id}] Failed: {err:?}");
+                        error_counter.fetch_add(1, Ordering::Relaxed);
+                        state.notify_page(&cache_key, &context, PageStatus::Failed);
+                        locks::lock(failures).push(PageFailure {
+                            url,
+                            error: format!("{err:#}"),
+                        });
                     }
                 }
 
@@ -96,18 +752,26 @@ pub async fn run_chapter_job(
                 state.set_chapter_progress(&job_id, total, processed_count);
 
                 {
-                    if let Some(prog) = state
-                        .active_chapter_jobs
-                        .write()
-                        .expect("lock")
-                        .get_mut(&job_id)
-                    {
+                    if let Some(prog) = locks::write(&state.active_chapter_jobs).get_mut(&job_id) {
+                        let pacing = LENS_PACER.snapshot();
                         prog.current = current;
+                        prog.adaptive_delay_ms = pacing.adaptive_delay_ms;
+                        prog.throttled_since = pacing.throttled_since;
                     }
                 }
+                handle.set_progress(current, total);
+                updates.send_modify(|progress| {
+                    progress.recognizing -= 1;
+                    progress.current = progress.current.max(current);
+                    progress.last_page = Some(page_id);
+                    progress.processed = processed_counter.load(Ordering::Relaxed) - skipped;
+                    progress.failed = error_counter.load(Ordering::Relaxed);
+                });
             }
-        })
-        .await;
+        },
+    );
+
+    tokio::join!(download, recognize);
 
     tracing::info!("[Job {job_id}] Finalize...");
     let processed_count = processed_counter.load(Ordering::Relaxed);
@@ -115,13 +779,47 @@ pub async fn run_chapter_job(
 
     state.active_jobs.fetch_sub(1, Ordering::Relaxed);
 
+    let failed = error_counter.load(Ordering::Relaxed);
+    updates.send_modify(|progress| {
+        progress.processed = processed_count - skipped;
+        progress.failed = failed;
+        progress.status = if failed == 0 {
+            PreprocessStatus::Done
+        } else {
+            PreprocessStatus::Failed
+        };
+    });
+
     {
-        state
-            .active_chapter_jobs
-            .write()
-            .expect("lock poisoned")
-            .remove(&job_id);
+        locks::write(&state.active_chapter_jobs).remove(&job_id);
     }
 
-    tracing::info!("[Job {job_id}] Finished for {}", context);
+    tracing::info!(
+        "[Job {job_id}] Finished for {}: {} skipped, {} processed, {} failed",
+        context,
+        skipped,
+        processed_count - skipped,
+        failed
+    );
+    let record = JobRecord {
+        id: 0,
+        chapter_key: job_id.clone(),
+        context: context.clone(),
+        started_at,
+        finished_at: now_unix(),
+        total_pages: total,
+        skipped_pages: skipped,
+        processed_pages: processed_count - skipped,
+        failures: failures
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner),
+    };
+    if let Err(err) = job_history::record(&state, &record) {
+        tracing::warn!("[Job {job_id}] Failed to record job history: {err}");
+    }
+    manatan_events::publish(manatan_events::Event::PreprocessFinished {
+        context,
+        total_pages: total,
+        processed_pages: processed_count,
+    });
 }
@@ -5,47 +5,89 @@ use serde::{Deserialize, Serialize};
 #[derive(Default)]
 pub enum OcrLanguage {
     #[default]
+    #[serde(alias = "ja")]
     Japanese,
+    #[serde(alias = "en")]
     English,
+    #[serde(alias = "zh")]
     Chinese,
+    #[serde(alias = "ko")]
     Korean,
+    #[serde(alias = "ar")]
     Arabic,
+    #[serde(alias = "es")]
     Spanish,
+    #[serde(alias = "fr")]
     French,
+    #[serde(alias = "de")]
     German,
+    #[serde(alias = "pt")]
     Portuguese,
+    #[serde(alias = "bg")]
     Bulgarian,
+    #[serde(alias = "cs")]
     Czech,
+    #[serde(alias = "da")]
     Danish,
+    #[serde(alias = "el")]
     Greek,
+    #[serde(alias = "et")]
     Estonian,
+    #[serde(alias = "fa")]
     Persian,
+    #[serde(alias = "fi")]
     Finnish,
+    #[serde(alias = "he")]
     Hebrew,
+    #[serde(alias = "hi")]
     Hindi,
+    #[serde(alias = "hu")]
     Hungarian,
+    #[serde(alias = "id")]
     Indonesian,
+    #[serde(alias = "it")]
     Italian,
+    #[serde(alias = "la")]
     Latin,
+    #[serde(alias = "lo")]
     Lao,
+    #[serde(alias = "lv")]
     Latvian,
+    #[serde(alias = "ka")]
     Georgian,
+    #[serde(alias = "kn")]
     Kannada,
+    #[serde(alias = "km")]
     Khmer,
+    #[serde(alias = "mn")]
     Mongolian,
+    #[serde(alias = "mt")]
     Maltese,
+    #[serde(alias = "nl")]
     Dutch,
+    #[serde(alias = "no")]
     Norwegian,
+    #[serde(alias = "pl")]
     Polish,
+    #[serde(alias = "ro")]
     Romanian,
+    #[serde(alias = "ru")]
     Russian,
+    #[serde(alias = "sv")]
     Swedish,
+    #[serde(alias = "th")]
     Thai,
+    #[serde(alias = "tl")]
     Tagalog,
+    #[serde(alias = "tr")]
     Turkish,
+    #[serde(alias = "uk")]
     Ukrainian,
+    #[serde(alias = "vi")]
     Vietnamese,
+    #[serde(alias = "cy")]
     Welsh,
+    #[serde(alias = "yue")]
     Cantonese,
 }
 
@@ -97,6 +139,103 @@ impl OcrLanguage {
         }
     }
 
+    /// ISO 639 code sent to Google Lens as the recognition language hint. Requests may
+    /// also name the language by this code.
+    pub fn lens_code(&self) -> &'static str {
+        match self {
+            OcrLanguage::Japanese => "ja",
+            OcrLanguage::English => "en",
+            OcrLanguage::Chinese => "zh",
+            OcrLanguage::Korean => "ko",
+            OcrLanguage::Arabic => "ar",
+            OcrLanguage::Spanish => "es",
+            OcrLanguage::French => "fr",
+            OcrLanguage::German => "de",
+            OcrLanguage::Portuguese => "pt",
+            OcrLanguage::Bulgarian => "bg",
+            OcrLanguage::Czech => "cs",
+            OcrLanguage::Danish => "da",
+            OcrLanguage::Greek => "el",
+            OcrLanguage::Estonian => "et",
+            OcrLanguage::Persian => "fa",
+            OcrLanguage::Finnish => "fi",
+            OcrLanguage::Hebrew => "he",
+            OcrLanguage::Hindi => "hi",
+            OcrLanguage::Hungarian => "hu",
+            OcrLanguage::Indonesian => "id",
+            OcrLanguage::Italian => "it",
+            OcrLanguage::Latin => "la",
+            OcrLanguage::Lao => "lo",
+            OcrLanguage::Latvian => "lv",
+            OcrLanguage::Georgian => "ka",
+            OcrLanguage::Kannada => "kn",
+            OcrLanguage::Khmer => "km",
+            OcrLanguage::Mongolian => "mn",
+            OcrLanguage::Maltese => "mt",
+            OcrLanguage::Dutch => "nl",
+            OcrLanguage::Norwegian => "no",
+            OcrLanguage::Polish => "pl",
+            OcrLanguage::Romanian => "ro",
+            OcrLanguage::Russian => "ru",
+            OcrLanguage::Swedish => "sv",
+            OcrLanguage::Thai => "th",
+            OcrLanguage::Tagalog => "tl",
+            OcrLanguage::Turkish => "tr",
+            OcrLanguage::Ukrainian => "uk",
+            OcrLanguage::Vietnamese => "vi",
+            OcrLanguage::Welsh => "cy",
+            OcrLanguage::Cantonese => "yue",
+        }
+    }
+
+    /// Tesseract traineddata name for this language.
+    pub fn tesseract_code(&self) -> &'static str {
+        match self {
+            OcrLanguage::Japanese => "jpn",
+            OcrLanguage::English => "eng",
+            OcrLanguage::Chinese => "chi_sim",
+            OcrLanguage::Korean => "kor",
+            OcrLanguage::Arabic => "ara",
+            OcrLanguage::Spanish => "spa",
+            OcrLanguage::French => "fra",
+            OcrLanguage::German => "deu",
+            OcrLanguage::Portuguese => "por",
+            OcrLanguage::Bulgarian => "bul",
+            OcrLanguage::Czech => "ces",
+            OcrLanguage::Danish => "dan",
+            OcrLanguage::Greek => "ell",
+            OcrLanguage::Estonian => "est",
+            OcrLanguage::Persian => "fas",
+            OcrLanguage::Finnish => "fin",
+            OcrLanguage::Hebrew => "heb",
+            OcrLanguage::Hindi => "hin",
+            OcrLanguage::Hungarian => "hun",
+            OcrLanguage::Indonesian => "ind",
+            OcrLanguage::Italian => "ita",
+            OcrLanguage::Latin => "lat",
+            OcrLanguage::Lao => "lao",
+            OcrLanguage::Latvian => "lav",
+            OcrLanguage::Georgian => "kat",
+            OcrLanguage::Kannada => "kan",
+            OcrLanguage::Khmer => "khm",
+            OcrLanguage::Mongolian => "mon",
+            OcrLanguage::Maltese => "mlt",
+            OcrLanguage::Dutch => "nld",
+            OcrLanguage::Norwegian => "nor",
+            OcrLanguage::Polish => "pol",
+            OcrLanguage::Romanian => "ron",
+            OcrLanguage::Russian => "rus",
+            OcrLanguage::Swedish => "swe",
+            OcrLanguage::Thai => "tha",
+            OcrLanguage::Tagalog => "tgl",
+            OcrLanguage::Turkish => "tur",
+            OcrLanguage::Ukrainian => "ukr",
+            OcrLanguage::Vietnamese => "vie",
+            OcrLanguage::Welsh => "cym",
+            OcrLanguage::Cantonese => "chi_tra",
+        }
+    }
+
     pub fn prefers_vertical(&self) -> bool {
         matches!(
             self,
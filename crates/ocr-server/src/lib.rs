@@ -1,29 +1,133 @@
+pub mod archive;
+pub mod auth;
+pub mod backend;
+pub mod cbz;
+pub mod context;
+pub mod credentials;
+pub mod export;
 pub mod handlers;
+pub mod headers;
+pub mod health;
+pub mod imaging;
+pub mod inflight;
+pub mod job_history;
 pub mod jobs;
 pub mod language;
+pub mod locks;
 pub mod logic;
+pub mod manual;
 pub mod merge;
+pub mod metrics;
+pub mod normalize;
+pub mod page_events;
+pub mod pdf;
+pub mod preprocess;
+pub mod proxy;
+pub mod prune;
+pub mod remerge;
+pub mod retry;
+pub mod selftest;
+pub mod spread;
 pub mod state;
+pub mod text_export;
+pub mod throttle;
 
 use std::path::PathBuf;
 
 use axum::{
     Router,
     extract::DefaultBodyLimit,
-    routing::{get, post},
+    middleware,
+    routing::{get, post, put},
 };
-use state::AppState;
+use state::{AppState, CACHE_DB_FILE_NAME};
+
+/// Uploaded chapter archives may be far larger than any other request body.
+const MAX_ARCHIVE_UPLOAD_BYTES: usize = 512 * 1024 * 1024;
+
+/// Cache exports of a large library outgrow the router-wide limit as well.
+const MAX_CACHE_IMPORT_BYTES: usize = 512 * 1024 * 1024;
 
 /// Creates the OCR Router.
-pub fn create_router(cache_dir: PathBuf) -> Router {
-    let state = AppState::new(cache_dir);
+pub fn create_router(cache_dir: PathBuf, local_novel_path: PathBuf) -> Router {
+    let state = AppState::new(cache_dir, local_novel_path);
+    manatan_storage::register("ocr-cache", state.cache_dir.join(CACHE_DB_FILE_NAME));
+    manatan_storage::register("ocr-archive", archive::archive_dir(&state));
+
+    let self_test_state = state.clone();
+    tokio::spawn(async move {
+        selftest::run(&self_test_state).await;
+    });
 
-    // Spawn the job worker if you want strict concurrency,
-    // or we just spawn tasks per request (handled in handlers).
+    jobs::spawn_workers(&state);
+    prune::spawn_scheduler(&state);
 
-    Router::new()
+    let router = Router::new()
         .route("/", get(handlers::status_handler))
-        .route("/ocr", get(handlers::ocr_handler))
+        .route("/self-test", get(handlers::self_test_handler))
+        .route("/health", get(handlers::health_handler))
+        .route("/metrics", get(handlers::metrics_handler))
+        .route(
+            "/ocr",
+            get(handlers::ocr_handler)
+                .post(handlers::ocr_upload_handler)
+                .put(handlers::edit_ocr_handler),
+        )
+        .route("/ocr/manual", put(handlers::manual_ocr_handler))
+        .route("/ocr/batch", post(handlers::ocr_batch_handler))
+        .route("/ocr/spread", post(handlers::ocr_spread_handler))
+        .route(
+            "/credentials",
+            get(handlers::list_credentials_handler)
+                .post(handlers::save_credentials_handler)
+                .delete(handlers::delete_credentials_handler),
+        )
+        .route(
+            "/config",
+            get(handlers::get_config_handler).post(handlers::set_config_handler),
+        )
+        .route(
+            "/merge-config",
+            get(handlers::get_merge_config_handler).put(handlers::set_merge_config_handler),
+        )
+        .route("/crop", get(handlers::crop_handler))
+        .route(
+            "/proxy-config",
+            get(handlers::get_proxy_config_handler).put(handlers::set_proxy_config_handler),
+        )
+        .route(
+            "/job-settings",
+            get(handlers::get_job_settings_handler).put(handlers::set_job_settings_handler),
+        )
+        .route(
+            "/text-normalization",
+            get(handlers::get_text_normalization_handler)
+                .put(handlers::set_text_normalization_handler),
+        )
+        .route(
+            "/timeout-settings",
+            get(handlers::get_timeout_settings_handler).put(handlers::set_timeout_settings_handler),
+        )
+        .route(
+            "/cache-key-config",
+            get(handlers::get_cache_key_config_handler).put(handlers::set_cache_key_config_handler),
+        )
+        .route(
+            "/cache-key-config/migrate",
+            post(handlers::migrate_cache_keys_handler),
+        )
+        .route(
+            "/remerge",
+            get(handlers::remerge_status_handler).post(handlers::remerge_handler),
+        )
+        .route("/job-history", get(handlers::job_history_handler))
+        .route("/ws", get(handlers::page_updates_handler))
+        .route(
+            "/preprocess-config",
+            get(handlers::get_preprocess_config_handler)
+                .put(handlers::set_preprocess_config_handler),
+        )
+        .route("/ocr-novel-image", post(handlers::ocr_novel_image_handler))
         .route(
             "/is-chapter-preprocessed",
             get(handlers::is_chapter_preprocessed_get_handler)
@@ -34,10 +138,37 @@ pub fn create_router(cache_dir: PathBuf) -> Router {
             post(handlers::is_chapters_preprocessed_handler),
         )
         .route("/preprocess-chapter", post(handlers::preprocess_handler))
+        .route(
+            "/preprocess-archive",
+            post(handlers::preprocess_archive_handler)
+                .layer(DefaultBodyLimit::max(MAX_ARCHIVE_UPLOAD_BYTES)),
+        )
+        .route(
+            "/preprocess-progress",
+            get(handlers::preprocess_progress_handler),
+        )
         .route("/delete-chapter", post(handlers::delete_chapter_handler))
         .route("/purge-cache", post(handlers::purge_cache_handler))
+        .route("/prune-cache", post(handlers::prune_cache_handler))
+        .route("/search", get(handlers::search_text_handler))
         .route("/export-cache", get(handlers::export_cache_handler))
-        .route("/import-cache", post(handlers::import_cache_handler))
-        .layer(DefaultBodyLimit::max(50 * 1024 * 1024)) // 50MB limit for imports
-        .with_state(state)
+        .route("/export-text", get(handlers::export_text_handler))
+        .route(
+            "/import-cache",
+            post(handlers::import_cache_handler)
+                .layer(DefaultBodyLimit::max(MAX_CACHE_IMPORT_BYTES)),
+        )
+        .route("/archive-cache", post(handlers::archive_cache_handler))
+        .route("/unarchive-cache", post(handlers::unarchive_cache_handler))
+        .route(
+            "/archived-cache",
+            get(handlers::list_archived_cache_handler),
+        )
+        .layer(DefaultBodyLimit::max(50 * 1024 * 1024)); // 50MB limit for other request bodies
+
+    let router = match auth::ApiKey::load(&state.cache_dir) {
+        Some(key) => router.layer(middleware::from_fn_with_state(key, auth::require_api_key)),
+        None => router,
+    };
+    router.with_state(state)
 }
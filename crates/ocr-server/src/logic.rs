@@ -1,14 +1,28 @@
-use std::{io::Cursor, time::Duration};
+use std::{
+    io::Cursor,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::anyhow;
 use chrome_lens_ocr::LensClient;
 use image::{DynamicImage, GenericImageView, ImageBuffer, ImageFormat, ImageReader};
 use reqwest::header::ACCEPT;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 use crate::{
+    backend::{OcrBackend, run_tesseract},
+    credentials::StoredCredentials,
+    headers::PageHeaders,
     language::OcrLanguage,
-    merge::{self, MergeConfig},
+    merge::{self, MergeConfig, OrientationHint, TextOrientation},
+    pdf,
+    preprocess::Preprocess,
+    proxy,
+    retry::{CallTimeout, LensCallError, RetryPolicy, is_retryable},
+    state::{AppState, OcrConfig},
+    throttle::LENS_PACER,
 };
 
 // --- REST Structs ---
@@ -44,13 +58,40 @@ struct ProxySettings {
     socks_proxy_password: Option<String>,
 }
 
+/// Suwayomi's SOCKS proxy as a URL, when it is enabled there.
+async fn suwayomi_socks_proxy(
+    user: Option<String>,
+    pass: Option<String>,
+    credentials: &StoredCredentials,
+) -> Option<String> {
+    let proxy = get_proxy_settings(user, pass, credentials)
+        .await
+        .ok()
+        .flatten()?;
+    if !proxy.socks_proxy_enabled || proxy.socks_proxy_host.is_empty() {
+        return None;
+    }
+    let credentials = match (&proxy.socks_proxy_username, &proxy.socks_proxy_password) {
+        (Some(username), Some(password)) if !username.is_empty() && !password.is_empty() => {
+            format!("{username}:{password}@")
+        }
+        _ => String::new(),
+    };
+    Some(format!(
+        "socks{}://{credentials}{}:{}",
+        proxy.socks_proxy_version, proxy.socks_proxy_host, proxy.socks_proxy_port
+    ))
+}
+
 async fn get_proxy_settings(
     user: Option<String>,
     pass: Option<String>,
+    credentials: &StoredCredentials,
 ) -> anyhow::Result<Option<ProxySettings>> {
     let client = reqwest::Client::new();
     let settings_url = "http://127.0.0.1:4568/api/v1/settings";
     let mut request = client.get(settings_url).header(ACCEPT, "application/json");
+    let (user, pass) = credentials.or_stored(settings_url, user, pass);
     if let Some(username) = user {
         request = request.basic_auth(username, pass);
     }
@@ -87,8 +128,9 @@ pub async fn resolve_total_pages_from_graphql(
     chapter_base_url: &str,
     user: Option<String>,
     pass: Option<String>,
+    credentials: &StoredCredentials,
 ) -> anyhow::Result<usize> {
-    resolve_total_pages_from_rest(chapter_base_url, user, pass).await
+    resolve_total_pages_from_rest(chapter_base_url, user, pass, credentials).await
 }
 
 #[derive(Deserialize)]
@@ -114,8 +156,10 @@ pub async fn resolve_total_pages_from_rest(
     chapter_base_url: &str,
     user: Option<String>,
     pass: Option<String>,
+    credentials: &StoredCredentials,
 ) -> anyhow::Result<usize> {
-    let path = get_cache_key(chapter_base_url, None);
+    // Only the path is read, so the query normalization makes no difference.
+    let path = get_cache_key(chapter_base_url, None, &CacheKeyConfig::default());
     let parts: Vec<&str> = path.split('/').collect();
     let manga_id_str = parts
         .iter()
@@ -132,6 +176,7 @@ pub async fn resolve_total_pages_from_rest(
     let url = format!("{api_base}/api/v1/manga/{manga_id_str}/chapter/{chapter_index_str}/pages");
 
     let client = reqwest::Client::new();
+    let (user, pass) = credentials.or_stored(&url, user, pass);
     let mut request = client.get(url).header(ACCEPT, "application/json");
     if let Some(username) = user {
         request = request.basic_auth(username, pass);
@@ -166,6 +211,55 @@ pub struct OcrResult {
 
     #[serde(rename = "forcedOrientation", skip_serializing_if = "Option::is_none")]
     pub forced_orientation: Option<String>,
+
+    /// Recognition confidence from 0 to 1, when the engine reports one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub confidence: Option<f64>,
+
+    /// Per-word boxes in reading order, when the engine reports word geometry; only
+    /// Tesseract does so far. Always cached, but only returned to clients that ask for
+    /// [`Granularity::Word`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub words: Option<Vec<WordBox>>,
+
+    /// Median thickness of the block's lines (width when vertical, height when
+    /// horizontal) as a fraction of the image height, for sizing overlay text. Set by
+    /// merging; pixels until [`merge_raw_chunks`] normalizes it.
+    #[serde(
+        rename = "fontSizeHint",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub font_size_hint: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct WordBox {
+    pub text: String,
+
+    #[serde(rename = "tightBoundingBox")]
+    pub tight_bounding_box: BoundingBox,
+}
+
+/// How much geometry a response carries.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    #[default]
+    Line,
+    Word,
+}
+
+impl Granularity {
+    /// Drops word boxes from `results` unless they were asked for.
+    pub fn apply(self, mut results: Vec<OcrResult>) -> Vec<OcrResult> {
+        if self == Granularity::Line {
+            for result in &mut results {
+                result.words = None;
+            }
+        }
+        results
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default)]
@@ -178,29 +272,67 @@ pub struct BoundingBox {
     pub rotation: Option<f64>,
 }
 
-/// Helper to strip the scheme/host/query from the URL for caching purposes.
-pub fn get_cache_key(url: &str, language: Option<OcrLanguage>) -> String {
-    let raw = if let Ok(parsed) = reqwest::Url::parse(url) {
-        let mut path = parsed.path().to_string();
-        if let Some(query) = parsed.query()
-            && !query.is_empty()
-        {
-            // "sourceId" does not affect the actual image bytes for Suwayomi page URLs,
-            // but it does vary between requests. Strip it to keep cache hits stable.
-            let kept_parts: Vec<&str> = query
-                .split('&')
-                .filter(|part| {
-                    let key = part.split('=').next().unwrap_or("");
-                    key != "sourceId"
-                })
-                .collect();
+/// Which query parameters of a page URL make it into its cache key. Suwayomi varies
+/// some of them between requests for the same image, which would otherwise make every
+/// previously OCRed page a miss.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CacheKeyConfig {
+    /// Parameters dropped from the key.
+    pub strip_params: Vec<String>,
+    /// When set, only these parameters are kept and `strip_params` is ignored.
+    pub keep_params: Option<Vec<String>>,
+}
+
+impl Default for CacheKeyConfig {
+    fn default() -> Self {
+        // "sourceId" does not affect the actual image bytes for Suwayomi page URLs,
+        // but it does vary between requests.
+        Self {
+            strip_params: vec!["sourceId".to_string()],
+            keep_params: None,
+        }
+    }
+}
 
-            if !kept_parts.is_empty() {
-                path.push('?');
-                path.push_str(&kept_parts.join("&"));
+impl CacheKeyConfig {
+    fn keeps(&self, param: &str) -> bool {
+        match &self.keep_params {
+            Some(keep) => keep.iter().any(|kept| kept == param),
+            None => !self.strip_params.iter().any(|stripped| stripped == param),
+        }
+    }
+
+    /// `key` with its query string filtered. Applies to keys already in the cache as
+    /// well as to fresh ones, which is what the cache key migration relies on.
+    pub fn normalize(&self, key: &str) -> String {
+        let Some((path, query)) = key.split_once('?') else {
+            return key.to_string();
+        };
+        let kept_parts: Vec<&str> = query
+            .split('&')
+            .filter(|part| !part.is_empty())
+            .filter(|part| self.keeps(part.split('=').next().unwrap_or("")))
+            .collect();
+        if kept_parts.is_empty() {
+            path.to_string()
+        } else {
+            format!("{path}?{}", kept_parts.join("&"))
+        }
+    }
+}
+
+/// Helper to strip the scheme/host/query from the URL for caching purposes. `config`
+/// picks the query parameters that stay; handlers pass
+/// [`AppState::cache_key_config`](crate::state::AppState::cache_key_config).
+pub fn get_cache_key(url: &str, language: Option<OcrLanguage>, config: &CacheKeyConfig) -> String {
+    let raw = if let Ok(parsed) = reqwest::Url::parse(url) {
+        match parsed.query() {
+            Some(query) if !query.is_empty() => {
+                config.normalize(&format!("{}?{query}", parsed.path()))
             }
+            _ => parsed.path().to_string(),
         }
-        path
     } else {
         url.to_string()
     };
@@ -283,151 +415,435 @@ fn decode_avif_custom(bytes: &[u8]) -> anyhow::Result<DynamicImage> {
     }
 }
 
+/// Result of OCRing a page. `partial` is set when the deadline was hit after some
+/// chunks finished; partial results must not be cached. `orientation` is the direction
+/// the merge treated the page as set in; Tesseract pages leave it unset.
+#[derive(Clone, Debug)]
+pub struct OcrOutcome {
+    pub results: Vec<OcrResult>,
+    pub partial: bool,
+    pub orientation: Option<TextOrientation>,
+    /// The orientation hint the run was merged under.
+    pub orientation_hint: OrientationHint,
+    /// The chunk preprocessing the results were produced with.
+    pub preprocess: Preprocess,
+    pub timings: PhaseTimings,
+    /// [`image_hash`] of the fetched page, for recording it once the results are cached.
+    pub image_hash: Option<String>,
+    /// The Lens lines the results were merged from; unset for Tesseract pages.
+    pub raw: Option<RawPage>,
+}
+
+/// Where the time spent on one page went, for `/metrics`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PhaseTimings {
+    /// Downloading the page and rasterizing it if it is a PDF; zero for uploaded images.
+    pub fetch: Duration,
+    /// Decoding the image and cutting and encoding its chunks.
+    pub decode: Duration,
+    /// Waiting on Lens, or running Tesseract, retries included.
+    pub recognize: Duration,
+    pub merge: Duration,
+}
+
+/// Decodes page bytes, including AVIF which the `image` crate cannot read on its own.
+pub fn decode_image(image_bytes: &[u8]) -> anyhow::Result<DynamicImage> {
+    let reader = ImageReader::new(Cursor::new(image_bytes))
+        .with_guessed_format()
+        .map_err(|err| anyhow!("Failed with_guessed_format: {err:?}"))?;
+
+    if reader.format() == Some(ImageFormat::Avif) {
+        decode_avif_custom(image_bytes)
+    } else {
+        reader
+            .decode()
+            .map_err(|err| anyhow!("Failed decode: {err:?}"))
+    }
+}
+
+/// Identifies a page image by content, so the same credit or recap page served under
+/// many URLs is OCRed once. Language and backend are part of it since they change the
+/// results.
+pub fn image_hash(image_bytes: &[u8], language: OcrLanguage, backend: OcrBackend) -> String {
+    format!(
+        "{:x}:{}:{}",
+        Sha256::digest(image_bytes),
+        language.as_str(),
+        backend.as_str()
+    )
+}
+
+/// The Suwayomi manga id in a chapter or page URL.
+pub fn manga_id(url: &str) -> Option<&str> {
+    let mut parts = url.split(['/', '?']);
+    parts.find(|part| *part == "manga")?;
+    parts.next().filter(|id| !id.is_empty())
+}
+
+/// Fetches a page and OCRs it. When the URL serves a PDF, `pdf_page` (1-based, default 1)
+/// picks the page that is rasterized; any other page of a non-PDF is an error. With
+/// `known_images`, a page whose image is already cached under another URL reuses those
+/// results instead of being OCRed again.
+#[allow(clippy::too_many_arguments)]
 pub async fn fetch_and_process(
     url: &str,
     user: Option<String>,
     pass: Option<String>,
+    credentials: &StoredCredentials,
     add_space_on_merge: Option<bool>,
     language: OcrLanguage,
-) -> anyhow::Result<Vec<OcrResult>> {
-    let mut last_error = anyhow!("Unknown error");
+    backend: OcrBackend,
+    pdf_page: Option<u32>,
+    headers: &PageHeaders,
+    config: &OcrConfig,
+    known_images: Option<&AppState>,
+) -> anyhow::Result<OcrOutcome> {
+    let deadline_at = tokio::time::Instant::now() + config.deadline();
+    let retry = RetryPolicy::from_config(config);
+
+    let config = config.for_page(url);
+    let config = &*config;
 
-    for attempt_number in 1..=3 {
-        match fetch_and_process_internal(
+    let mut attempt_number = 1;
+    loop {
+        let error = match fetch_and_process_internal(
             url,
             user.clone(),
             pass.clone(),
+            credentials,
             add_space_on_merge,
             language,
+            backend,
+            pdf_page,
+            headers,
+            deadline_at,
+            config,
+            known_images,
         )
         .await
         {
-            Ok(result) => return Ok(result),
-            Err(error) => {
-                last_error = error;
-                tracing::warn!(
-                    "Attempt {} failed for {}: {:?}",
-                    attempt_number,
-                    url,
-                    last_error
-                );
-                tokio::time::sleep(Duration::from_secs(attempt_number)).await;
-            }
+            Ok(outcome) => return Ok(outcome),
+            Err(error) => error,
+        };
+
+        // Lens calls are retried per chunk, so a Lens error reaching this point is final.
+        let retryable = is_retryable(&error) && error.downcast_ref::<LensCallError>().is_none();
+        let retry_at = tokio::time::Instant::now() + retry.backoff(attempt_number);
+        if !retryable || attempt_number >= retry.attempts || retry_at >= deadline_at {
+            tracing::warn!("Giving up on {url} after {attempt_number} attempt(s): {error:?}");
+            return Err(error);
         }
+        tracing::warn!("Attempt {attempt_number} failed for {url}, retrying: {error:?}");
+        tokio::time::sleep_until(retry_at).await;
+        attempt_number += 1;
     }
-    Err(last_error)
+}
+
+/// Downloads a page's image, retrying transient failures, and rasterizes the first page
+/// when the URL serves a PDF.
+pub async fn download_page(
+    url: &str,
+    user: &Option<String>,
+    pass: &Option<String>,
+    credentials: &StoredCredentials,
+    headers: &PageHeaders,
+    config: &OcrConfig,
+) -> anyhow::Result<Vec<u8>> {
+    let bytes = download_document(url, user, pass, credentials, headers, config).await?;
+    if pdf::is_pdf(&bytes) {
+        return pdf::render_page(Arc::new(bytes), 1, config.pdf_dpi).await;
+    }
+    Ok(bytes)
+}
+
+/// Downloads a page as served, retrying transient failures. PDFs are returned whole;
+/// chapter jobs use this to fetch pages ahead of the OCR stage and OCR every PDF page.
+pub async fn download_document(
+    url: &str,
+    user: &Option<String>,
+    pass: &Option<String>,
+    credentials: &StoredCredentials,
+    headers: &PageHeaders,
+    config: &OcrConfig,
+) -> anyhow::Result<Vec<u8>> {
+    let deadline_at = tokio::time::Instant::now() + config.deadline();
+    let retry = RetryPolicy::from_config(config);
+
+    let mut attempt_number = 1;
+    let bytes = loop {
+        let error = match tokio::time::timeout_at(
+            deadline_at,
+            fetch_page_image(url, user, pass, credentials, headers, config),
+        )
+        .await
+        {
+            Ok(Ok(bytes)) => break bytes,
+            Ok(Err(error)) => error,
+            Err(_) => return Err(anyhow!("Deadline exceeded while fetching {url}")),
+        };
+
+        let retry_at = tokio::time::Instant::now() + retry.backoff(attempt_number);
+        if !is_retryable(&error) || attempt_number >= retry.attempts || retry_at >= deadline_at {
+            tracing::warn!("Giving up on {url} after {attempt_number} attempt(s): {error:?}");
+            return Err(error);
+        }
+        tracing::warn!("Attempt {attempt_number} failed for {url}, retrying: {error:?}");
+        tokio::time::sleep_until(retry_at).await;
+        attempt_number += 1;
+    };
+    Ok(bytes)
 }
 
 // --- Data Structure for Test Caching ---
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct RawChunk {
     pub lines: Vec<OcrResult>,
     pub width: u32,
     pub height: u32,
+    #[serde(default)]
+    pub global_x: u32,
     pub global_y: u32,
     pub full_width: u32,
     pub full_height: u32,
 }
 
+/// A page's Lens lines before merging, stored next to its cached results so the page can
+/// be merged again under another [`MergeConfig`] without calling Lens.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RawPage {
+    pub language: OcrLanguage,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub add_space_on_merge: Option<bool>,
+    pub chunks: Vec<RawChunk>,
+}
+
+impl RawPage {
+    /// The orientation `config` merges the page in: its own if set, otherwise the one
+    /// inferred from the lines.
+    pub fn orientation(&self, config: &MergeConfig) -> TextOrientation {
+        config
+            .page_orientation()
+            .unwrap_or_else(|| page_orientation(&self.chunks, self.language))
+    }
+
+    /// Merges the stored lines as [`merge_raw_chunks`] would have on the first run.
+    pub fn merge(&self, config: &MergeConfig) -> Vec<OcrResult> {
+        merge_raw_chunks(
+            self.chunks.clone(),
+            self.add_space_on_merge,
+            self.language,
+            config,
+        )
+    }
+}
+
 // --- Public Helper for Testing ---
 pub async fn get_raw_ocr_data(
     image_bytes: &[u8],
     user: Option<String>,
     pass: Option<String>,
+    credentials: &StoredCredentials,
     language: OcrLanguage,
 ) -> anyhow::Result<Vec<RawChunk>> {
-    let reader = ImageReader::new(Cursor::new(image_bytes))
-        .with_guessed_format()
-        .map_err(|err| anyhow!("Failed with_guessed_format: {err:?}"))?;
+    let config = OcrConfig::default();
+    let (raw_chunks, _) = collect_raw_chunks(
+        image_bytes,
+        user,
+        pass,
+        credentials,
+        language,
+        &config,
+        None,
+        &mut PhaseTimings::default(),
+    )
+    .await?;
+    Ok(raw_chunks)
+}
+
+const CHUNK_HEIGHT_LIMIT: u32 = 3000;
+/// Pixels shared by neighbouring chunks, so a line cut at one chunk's edge is read whole
+/// by the next. The copies read twice are dropped in [`merge_raw_chunks`].
+const CHUNK_OVERLAP: u32 = 200;
+/// Results from neighbouring chunks are the same text read twice when their boxes overlap
+/// this much (intersection over union)...
+const SEAM_IOU_THRESHOLD: f64 = 0.5;
+/// ...or when this much of the smaller box lies inside the larger, as when one chunk only
+/// saw the part of a bubble above its edge...
+const SEAM_CONTAINMENT_THRESHOLD: f64 = 0.8;
+/// ...and this much of the shorter text is found, in order, in the longer.
+const SEAM_TEXT_SIMILARITY: f64 = 0.6;
 
-    let decoded_image = if reader.format() == Some(ImageFormat::Avif) {
-        decode_avif_custom(image_bytes)?
+/// Tiles the image into `(x, y, width, height)` rectangles, row by row, so tall pages are
+/// cut into strips and wide spreads into columns. Neighbouring rectangles share `overlap`
+/// pixels.
+fn chunk_rects(
+    full_width: u32,
+    full_height: u32,
+    width_limit: u32,
+    height_limit: u32,
+    overlap: u32,
+) -> Vec<(u32, u32, u32, u32)> {
+    let mut rects = Vec::new();
+    for (y, height) in chunk_spans(full_height, height_limit, overlap) {
+        for &(x, width) in &chunk_spans(full_width, width_limit, overlap) {
+            rects.push((x, y, width, height));
+        }
+    }
+    rects
+}
+
+/// `(start, length)` spans of at most `limit` pixels covering `full`, each starting
+/// `overlap` pixels before the previous one ends.
+fn chunk_spans(full: u32, limit: u32, overlap: u32) -> Vec<(u32, u32)> {
+    let limit = limit.max(1);
+    // An overlap as large as the chunk would never advance.
+    let step = if overlap < limit {
+        limit - overlap
     } else {
-        reader
-            .decode()
-            .map_err(|err| anyhow!("Failed decode: {err:?}"))?
+        limit
     };
+    let mut spans = Vec::new();
+    let mut start = 0;
+    while start < full {
+        let length = limit.min(full - start);
+        spans.push((start, length));
+        if start + length >= full {
+            break;
+        }
+        start += step;
+    }
+    spans
+}
+
+/// Splits the image into chunks and OCRs each one. When `deadline` passes after at least
+/// one chunk finished, the chunks collected so far are returned with the partial flag set.
+/// Decode and Lens time are added to `timings`.
+#[allow(clippy::too_many_arguments)]
+async fn collect_raw_chunks(
+    image_bytes: &[u8],
+    user: Option<String>,
+    pass: Option<String>,
+    credentials: &StoredCredentials,
+    language: OcrLanguage,
+    config: &OcrConfig,
+    deadline: Option<tokio::time::Instant>,
+    timings: &mut PhaseTimings,
+) -> anyhow::Result<(Vec<RawChunk>, bool)> {
+    let decode_started = Instant::now();
+    let decoded_image = decode_image(image_bytes)?;
+    timings.decode += decode_started.elapsed();
 
     let full_image_width = decoded_image.width();
     let full_image_height = decoded_image.height();
-    let chunk_height_limit = 3000;
+    let rects = chunk_rects(
+        full_image_width,
+        full_image_height,
+        config.chunk_width_limit,
+        CHUNK_HEIGHT_LIMIT,
+        CHUNK_OVERLAP,
+    );
+    let retry = RetryPolicy::from_config(config);
 
     let mut raw_chunks = Vec::new();
 
-    // Fetch proxy settings
-    let proxy_settings = get_proxy_settings(user.clone(), pass.clone())
-        .await
-        .ok()
-        .flatten();
-
-    // Create LensClient with optional proxy
-    let lens_client = if let Some(ref proxy) = proxy_settings {
-        if proxy.socks_proxy_enabled && !proxy.socks_proxy_host.is_empty() {
-            // Build proxy URL with authentication if provided
-            let proxy_url = if let (Some(username), Some(password)) =
-                (&proxy.socks_proxy_username, &proxy.socks_proxy_password)
-            {
-                if !username.is_empty() && !password.is_empty() {
-                    format!(
-                        "socks{}://{username}:{password}@{}:{}",
-                        proxy.socks_proxy_version, proxy.socks_proxy_host, proxy.socks_proxy_port
-                    )
-                } else {
-                    format!(
-                        "socks{}://{}:{}",
-                        proxy.socks_proxy_version, proxy.socks_proxy_host, proxy.socks_proxy_port
-                    )
-                }
-            } else {
-                format!(
-                    "socks{}://{}:{}",
-                    proxy.socks_proxy_version, proxy.socks_proxy_host, proxy.socks_proxy_port
+    // The configured proxy wins over Suwayomi's SOCKS setting.
+    let lens_proxy = match config.proxy.effective_url() {
+        Some(url) => Some(url),
+        None => suwayomi_socks_proxy(user.clone(), pass.clone(), credentials).await,
+    };
+    let lens_client = match &lens_proxy {
+        Some(url) => {
+            tracing::info!("Using proxy for Google Lens: {}", proxy::redact(url));
+            LensClient::new_with_proxy(None, Some(url)).map_err(|e| {
+                anyhow!(
+                    "Failed to create LensClient with proxy {}: {e}",
+                    proxy::redact(url)
                 )
-            };
-
-            tracing::info!(
-                "Using SOCKS{} proxy for Google Lens: {}:{}",
-                proxy.socks_proxy_version,
-                proxy.socks_proxy_host,
-                proxy.socks_proxy_port
-            );
-
-            LensClient::new_with_proxy(None, Some(&proxy_url))
-                .map_err(|e| anyhow!("Failed to create LensClient with proxy: {e}"))?
-        } else {
-            LensClient::new(None)
+            })?
         }
-    } else {
-        LensClient::new(None)
+        None => LensClient::new(None),
     };
+    let via_proxy = lens_proxy
+        .as_deref()
+        .map(|url| format!(" (via proxy {})", proxy::redact(url)))
+        .unwrap_or_default();
 
-    let mut current_y_position = 0;
-    while current_y_position < full_image_height {
-        let current_chunk_height =
-            std::cmp::min(chunk_height_limit, full_image_height - current_y_position);
-        if current_chunk_height == 0 {
-            break;
-        }
-
-        let chunk_image = decoded_image
-            .view(
-                0,
-                current_y_position,
-                full_image_width,
-                current_chunk_height,
-            )
-            .to_image();
+    for &(chunk_x, chunk_y, chunk_width, chunk_height) in &rects {
+        let encode_started = Instant::now();
+        let chunk_image = config.preprocess.apply(DynamicImage::ImageRgba8(
+            decoded_image
+                .view(chunk_x, chunk_y, chunk_width, chunk_height)
+                .to_image(),
+        ));
         let mut image_buffer = Cursor::new(Vec::new());
         chunk_image
             .write_to(&mut image_buffer, ImageFormat::Png)
             .map_err(|err| anyhow!("Failed write_to: {err:?}"))?;
         let chunk_png_bytes = image_buffer.into_inner();
+        timings.decode += encode_started.elapsed();
 
-        let lens_response = lens_client
-            .process_image_bytes(&chunk_png_bytes, Some("jp"))
-            .await
-            .map_err(|err| anyhow!("Failed process_image_bytes: {err:?}"))?;
+        let mut attempt_number = 1;
+        let lens_response = loop {
+            let lens_started = Instant::now();
+            let lens_call = tokio::time::timeout(
+                config.timeouts.ocr_call(),
+                lens_client.process_image_bytes(&chunk_png_bytes, Some(language.lens_code())),
+            );
+            let lens_result = match deadline {
+                Some(deadline) if tokio::time::Instant::now() < deadline => {
+                    tokio::time::timeout_at(deadline, lens_call).await.ok()
+                }
+                Some(_) => None,
+                None => Some(lens_call.await),
+            };
+            timings.recognize += lens_started.elapsed();
+            let Some(lens_result) = lens_result else {
+                if raw_chunks.is_empty() {
+                    return Err(anyhow!(
+                        "OCR deadline exceeded before any chunk finished{via_proxy}"
+                    ));
+                }
+                tracing::warn!(
+                    "OCR deadline exceeded after {} of {} chunks; returning partial results",
+                    raw_chunks.len(),
+                    rects.len()
+                );
+                return Ok((raw_chunks, true));
+            };
+            let message = match lens_result {
+                Ok(Ok(response)) => {
+                    LENS_PACER.record_success(lens_started.elapsed());
+                    break response;
+                }
+                Ok(Err(err)) => format!("{err:?}"),
+                Err(_) => CallTimeout {
+                    call: "Lens call",
+                    after: config.timeouts.ocr_call(),
+                }
+                .to_string(),
+            };
+
+            LENS_PACER.record_error(&message);
+            let error = anyhow::Error::new(LensCallError(message));
+            let retry_at = tokio::time::Instant::now() + retry.backoff(attempt_number);
+            if !is_retryable(&error)
+                || attempt_number >= retry.attempts
+                || deadline.is_some_and(|deadline| retry_at >= deadline)
+            {
+                if via_proxy.is_empty() {
+                    return Err(error);
+                }
+                return Err(error.context(format!("Lens call failed{via_proxy}")));
+            }
+            tracing::warn!(
+                "Lens call failed (attempt {attempt_number} of {}), retrying: {error}",
+                retry.attempts
+            );
+            tokio::time::sleep_until(retry_at).await;
+            attempt_number += 1;
+        };
 
         let mut flat_ocr_lines = Vec::new();
         for paragraph in lens_response.paragraphs {
@@ -439,60 +855,41 @@ pub async fn get_raw_ocr_data(
                     }
 
                     let rotation = geometry.rotation_z as f64;
-                    let cx = (geometry.center_x * full_image_width as f32) as f64;
-                    let cy = (geometry.center_y * current_chunk_height as f32) as f64;
-                    let w = (geometry.width * full_image_width as f32) as f64;
-                    let h = (geometry.height * current_chunk_height as f32) as f64;
-
-                    let hw = w / 2.0;
-                    let hh = h / 2.0;
-                    let cos_a = rotation.cos();
-                    let sin_a = rotation.sin();
-
-                    let corners = [(-hw, -hh), (hw, -hh), (hw, hh), (-hw, hh)];
-
-                    let mut min_x = f64::INFINITY;
-                    let mut max_x = f64::NEG_INFINITY;
-                    let mut min_y = f64::INFINITY;
-                    let mut max_y = f64::NEG_INFINITY;
-
-                    for (lx, ly) in corners {
-                        let rx = lx * cos_a - ly * sin_a + cx;
-                        let ry = lx * sin_a + ly * cos_a + cy;
-                        min_x = min_x.min(rx);
-                        max_x = max_x.max(rx);
-                        min_y = min_y.min(ry);
-                        max_y = max_y.max(ry);
-                    }
+                    let tight_bounding_box = rotated_aabb(
+                        geometry.center_x,
+                        geometry.center_y,
+                        geometry.width,
+                        geometry.height,
+                        geometry.rotation_z,
+                        chunk_width,
+                        chunk_height,
+                    );
 
-                    let aabb_w = max_x - min_x;
-                    let aabb_h = max_y - min_y;
-
-                    let is_vertical = if language.prefers_vertical() {
-                        if rotation.abs() > 0.1 {
-                            (rotation.abs() - std::f32::consts::FRAC_PI_2 as f64).abs() < 0.5
-                        } else {
-                            aabb_w <= aabb_h
-                        }
+                    // A rotated box's angle shows its direction; an upright one's shape
+                    // only does when it is clearly stretched, and the merge settles the
+                    // rest from their neighbours.
+                    let orientation = if !language.prefers_vertical() {
+                        Some(TextOrientation::Horizontal)
+                    } else if rotation.abs() > 0.1 {
+                        Some(
+                            if (rotation.abs() - std::f32::consts::FRAC_PI_2 as f64).abs() < 0.5 {
+                                TextOrientation::Vertical
+                            } else {
+                                TextOrientation::Horizontal
+                            },
+                        )
                     } else {
-                        false
+                        merge::shape_orientation(&clean_text, &tight_bounding_box)
                     };
 
                     flat_ocr_lines.push(OcrResult {
                         text: clean_text,
                         is_merged: Some(false),
-                        forced_orientation: Some(if is_vertical {
-                            "vertical".into()
-                        } else {
-                            "horizontal".into()
-                        }),
-                        tight_bounding_box: BoundingBox {
-                            x: min_x,
-                            y: min_y,
-                            width: aabb_w,
-                            height: aabb_h,
-                            rotation: None,
-                        },
+                        forced_orientation: orientation.map(|o| o.as_str().into()),
+                        tight_bounding_box,
+                        confidence: None,
+                        words: None,
+                        font_size_hint: None,
                     });
                 }
             }
@@ -500,28 +897,63 @@ pub async fn get_raw_ocr_data(
 
         raw_chunks.push(RawChunk {
             lines: flat_ocr_lines,
-            width: full_image_width,
-            height: current_chunk_height,
-            global_y: current_y_position,
+            width: chunk_width,
+            height: chunk_height,
+            global_x: chunk_x,
+            global_y: chunk_y,
             full_width: full_image_width,
             full_height: full_image_height,
         });
+    }
+
+    Ok((raw_chunks, false))
+}
 
-        current_y_position += chunk_height_limit;
+/// The axis-aligned box, in chunk pixels, around a Lens box given as a rotated rectangle
+/// in chunk-relative coordinates.
+fn rotated_aabb(
+    center_x: f32,
+    center_y: f32,
+    width: f32,
+    height: f32,
+    rotation: f32,
+    chunk_width: u32,
+    chunk_height: u32,
+) -> BoundingBox {
+    let rotation = rotation as f64;
+    let cx = (center_x * chunk_width as f32) as f64;
+    let cy = (center_y * chunk_height as f32) as f64;
+    let hw = (width * chunk_width as f32) as f64 / 2.0;
+    let hh = (height * chunk_height as f32) as f64 / 2.0;
+    let cos_a = rotation.cos();
+    let sin_a = rotation.sin();
+
+    let mut min_x = f64::INFINITY;
+    let mut max_x = f64::NEG_INFINITY;
+    let mut min_y = f64::INFINITY;
+    let mut max_y = f64::NEG_INFINITY;
+    for (lx, ly) in [(-hw, -hh), (hw, -hh), (hw, hh), (-hw, hh)] {
+        let rx = lx * cos_a - ly * sin_a + cx;
+        let ry = lx * sin_a + ly * cos_a + cy;
+        min_x = min_x.min(rx);
+        max_x = max_x.max(rx);
+        min_y = min_y.min(ry);
+        max_y = max_y.max(ry);
     }
 
-    Ok(raw_chunks)
+    BoundingBox {
+        x: min_x,
+        y: min_y,
+        width: max_x - min_x,
+        height: max_y - min_y,
+        rotation: None,
+    }
 }
 
-async fn fetch_and_process_internal(
-    url: &str,
-    user: Option<String>,
-    pass: Option<String>,
-    add_space_on_merge: Option<bool>,
-    language: OcrLanguage,
-) -> anyhow::Result<Vec<OcrResult>> {
-    // 0. Force URL to Localhost
-    let target_url = match reqwest::Url::parse(url) {
+/// `url` on the local Suwayomi instance: page fetches always go to loopback, whatever
+/// host the client named.
+pub fn loopback_url(url: &str) -> String {
+    match reqwest::Url::parse(url) {
         Ok(mut parsed) => {
             let _ = parsed.set_scheme("http");
             let _ = parsed.set_host(Some("127.0.0.1"));
@@ -529,52 +961,409 @@ async fn fetch_and_process_internal(
             parsed.to_string()
         }
         Err(_) => url.to_string(),
-    };
-
-    // 1. Fetch
-    let client = reqwest::Client::new();
-    let mut request = client.get(&target_url);
-    if let Some(username) = &user {
-        request = request.basic_auth(username, pass.as_ref());
     }
+}
+
+/// Downloads a page image from the local Suwayomi instance, giving up after the configured
+/// fetch timeout. Without credentials in the request, stored ones for the URL fetched
+/// are used. The host is always forced to loopback, whatever the URL says.
+pub async fn fetch_page_image(
+    url: &str,
+    user: &Option<String>,
+    pass: &Option<String>,
+    credentials: &StoredCredentials,
+    headers: &PageHeaders,
+    config: &OcrConfig,
+) -> anyhow::Result<Vec<u8>> {
+    let proxy = &config.proxy;
+    let target_url = loopback_url(url);
+
+    let client = proxy.http_client(config.timeouts.fetch())?;
+    let mut request = client
+        .get(&target_url)
+        .headers(headers.to_header_map().map_err(|err| anyhow!(err))?);
+    // A bearer token, already in the headers, replaces basic auth; credentials sent with
+    // the request replace stored ones.
+    let auth_mode = match (&headers.token, user) {
+        (Some(_), _) => "bearer token auth".to_string(),
+        (None, Some(username)) => {
+            request = request.basic_auth(username, pass.as_ref());
+            format!("basic auth as {username}")
+        }
+        (None, None) => match credentials.lookup(&target_url) {
+            Some((username, stored_pass)) => {
+                request = request.basic_auth(&username, stored_pass);
+                format!("stored basic auth as {username}")
+            }
+            None => "no credentials".to_string(),
+        },
+    };
+    // Keep the reqwest error as the source so retries can tell a 404 from a 502.
     let response = request
         .send()
-        .await?
+        .await
+        .map_err(|err| proxy.describe_error(err))?
         .error_for_status()
-        .map_err(|err| anyhow!("Failed error_for_status (URL: {target_url}): {err:?}"))?;
-    let image_bytes = response.bytes().await?.to_vec();
+        .map_err(|err| {
+            let message = if err.status() == Some(reqwest::StatusCode::UNAUTHORIZED) {
+                format!("Unauthorized with {auth_mode} (URL: {target_url})")
+            } else {
+                format!("Failed error_for_status (URL: {target_url})")
+            };
+            anyhow::Error::new(err).context(message)
+        })?;
+    Ok(response
+        .bytes()
+        .await
+        .map_err(|err| proxy.describe_error(err))?
+        .to_vec())
+}
+
+#[allow(clippy::too_many_arguments)]
+async fn fetch_and_process_internal(
+    url: &str,
+    user: Option<String>,
+    pass: Option<String>,
+    credentials: &StoredCredentials,
+    add_space_on_merge: Option<bool>,
+    language: OcrLanguage,
+    backend: OcrBackend,
+    pdf_page: Option<u32>,
+    headers: &PageHeaders,
+    deadline: tokio::time::Instant,
+    config: &OcrConfig,
+    known_images: Option<&AppState>,
+) -> anyhow::Result<OcrOutcome> {
+    let fetch_started = Instant::now();
+    let mut image_bytes = tokio::time::timeout_at(
+        deadline,
+        fetch_page_image(url, &user, &pass, credentials, headers, config),
+    )
+    .await
+    .map_err(|_| anyhow!("OCR deadline exceeded while fetching {url}"))??;
+
+    let page = pdf_page.unwrap_or(1);
+    if pdf::is_pdf(&image_bytes) {
+        let document = Arc::new(std::mem::take(&mut image_bytes));
+        image_bytes =
+            tokio::time::timeout_at(deadline, pdf::render_page(document, page, config.pdf_dpi))
+                .await
+                .map_err(|_| anyhow!("OCR deadline exceeded while rendering {url}"))??;
+    } else if page > 1 {
+        return Err(anyhow!("page {page} was requested but {url} is not a PDF"));
+    }
+    let fetch = fetch_started.elapsed();
+
+    let hash = image_hash(&image_bytes, language, backend);
+    if let Some(state) = known_images
+        && let Some(outcome) = state.reuse_by_image_hash(&hash, config.preprocess)
+    {
+        tracing::info!("Reusing cached OCR results for identical image at {url}");
+        return Ok(OcrOutcome {
+            timings: PhaseTimings {
+                fetch,
+                ..PhaseTimings::default()
+            },
+            ..outcome
+        });
+    }
+
+    let mut outcome = run_ocr_pipeline(
+        &image_bytes,
+        user,
+        pass,
+        credentials,
+        add_space_on_merge,
+        language,
+        backend,
+        deadline,
+        config,
+    )
+    .await?;
+    outcome.timings.fetch = fetch;
+    outcome.image_hash = Some(hash);
+    Ok(outcome)
+}
+
+/// Runs the same pipeline as [`fetch_and_process`] on image bytes supplied by the caller,
+/// for images the server cannot fetch itself. Only the Lens calls are retried since nothing
+/// is fetched.
+#[allow(clippy::too_many_arguments)]
+pub async fn process_uploaded_image(
+    image_bytes: &[u8],
+    user: Option<String>,
+    pass: Option<String>,
+    credentials: &StoredCredentials,
+    add_space_on_merge: Option<bool>,
+    language: OcrLanguage,
+    backend: OcrBackend,
+    config: &OcrConfig,
+) -> anyhow::Result<OcrOutcome> {
+    run_ocr_pipeline(
+        image_bytes,
+        user,
+        pass,
+        credentials,
+        add_space_on_merge,
+        language,
+        backend,
+        tokio::time::Instant::now() + config.deadline(),
+        config,
+    )
+    .await
+}
 
+#[allow(clippy::too_many_arguments)]
+async fn run_ocr_pipeline(
+    image_bytes: &[u8],
+    user: Option<String>,
+    pass: Option<String>,
+    credentials: &StoredCredentials,
+    add_space_on_merge: Option<bool>,
+    language: OcrLanguage,
+    backend: OcrBackend,
+    deadline: tokio::time::Instant,
+    config: &OcrConfig,
+) -> anyhow::Result<OcrOutcome> {
+    let mut timings = PhaseTimings::default();
+    if backend == OcrBackend::Tesseract {
+        let started = Instant::now();
+        let call_timeout = config.timeouts.ocr_call();
+        let tesseract = tokio::time::timeout(call_timeout, run_tesseract(image_bytes, language));
+        let mut results = tokio::time::timeout_at(deadline, tesseract)
+            .await
+            .map_err(|_| anyhow!("OCR deadline exceeded while running Tesseract"))?
+            .map_err(|_| {
+                anyhow::Error::new(CallTimeout {
+                    call: "Tesseract",
+                    after: call_timeout,
+                })
+            })??;
+        timings.recognize = started.elapsed();
+        config.normalization.apply(&mut results);
+        return Ok(OcrOutcome {
+            results,
+            partial: false,
+            orientation: None,
+            orientation_hint: config.merge.orientation_hint,
+            preprocess: Preprocess::None,
+            timings,
+            image_hash: None,
+            raw: None,
+        });
+    }
+
+    // 2. Decode & OCR (Wrapped) - now passes user/pass for proxy settings
+    let (raw_chunks, partial) = collect_raw_chunks(
+        image_bytes,
+        user,
+        pass,
+        credentials,
+        language,
+        config,
+        Some(deadline),
+        &mut timings,
+    )
+    .await?;
+
+    let orientation = config
+        .merge
+        .page_orientation()
+        .unwrap_or_else(|| page_orientation(&raw_chunks, language));
+    let merge_config = MergeConfig {
+        orientation: Some(orientation),
+        ..config.merge.clone()
+    };
+
+    let raw = RawPage {
+        language,
+        add_space_on_merge,
+        chunks: raw_chunks,
+    };
+    let merge_started = Instant::now();
+    let mut results = raw.merge(&merge_config);
+    config.normalization.apply(&mut results);
+    timings.merge = merge_started.elapsed();
+
+    Ok(OcrOutcome {
+        results,
+        partial,
+        orientation: Some(orientation),
+        orientation_hint: config.merge.orientation_hint,
+        preprocess: config.preprocess,
+        timings,
+        image_hash: None,
+        raw: Some(raw),
+    })
+}
+
+/// Runs OCR, merging and normalization on an already loaded image.
+pub async fn process_image_bytes(
+    image_bytes: &[u8],
+    user: Option<String>,
+    pass: Option<String>,
+    credentials: &StoredCredentials,
+    add_space_on_merge: Option<bool>,
+    language: OcrLanguage,
+    merge_config: &MergeConfig,
+) -> anyhow::Result<Vec<OcrResult>> {
     // 2. Decode & OCR (Wrapped) - now passes user/pass for proxy settings
-    let raw_chunks = get_raw_ocr_data(&image_bytes, user, pass, language).await?;
+    let raw_chunks = get_raw_ocr_data(image_bytes, user, pass, credentials, language).await?;
 
+    Ok(merge_raw_chunks(
+        raw_chunks,
+        add_space_on_merge,
+        language,
+        merge_config,
+    ))
+}
+
+/// Infers the orientation of a whole page from all of its chunks, so the columns of a
+/// wide spread are never merged in different directions.
+pub fn page_orientation(raw_chunks: &[RawChunk], language: OcrLanguage) -> TextOrientation {
+    merge::infer_orientation(raw_chunks.iter().flat_map(|chunk| &chunk.lines), language)
+}
+
+/// Merges each chunk's lines and maps their boxes from chunk pixels to coordinates
+/// normalized against the full image. Text read twice where chunks overlap is kept once.
+pub fn merge_raw_chunks(
+    raw_chunks: Vec<RawChunk>,
+    add_space_on_merge: Option<bool>,
+    language: OcrLanguage,
+    config: &MergeConfig,
+) -> Vec<OcrResult> {
     // 3. Merge & Normalize
     let mut final_results = Vec::new();
     let merge_config = MergeConfig {
         add_space_on_merge,
         language,
-        ..MergeConfig::default()
+        orientation: Some(
+            config
+                .page_orientation()
+                .unwrap_or_else(|| page_orientation(&raw_chunks, language)),
+        ),
+        ..config.clone()
     };
 
-    for chunk in raw_chunks {
-        let merged_lines = merge::auto_merge(chunk.lines, chunk.width, chunk.height, &merge_config);
+    // Each final result's chunk and box in global pixels, for finding text read twice
+    // where chunks overlap.
+    let mut placed: Vec<(usize, BoundingBox)> = Vec::new();
+    for (index, mut chunk) in raw_chunks.into_iter().enumerate() {
+        let lines = std::mem::take(&mut chunk.lines);
+        let merged_lines = merge::auto_merge(lines, chunk.width, chunk.height, &merge_config);
 
         for mut result in merged_lines {
-            // Adjust Coordinates: Chunk Pixels -> Global Pixels -> Global Normalized
-            let chunk_pixel_x = result.tight_bounding_box.x;
-            let chunk_pixel_y = result.tight_bounding_box.y;
-            let chunk_pixel_width = result.tight_bounding_box.width;
-            let chunk_pixel_height = result.tight_bounding_box.height;
+            let global = BoundingBox {
+                x: result.tight_bounding_box.x + chunk.global_x as f64,
+                y: result.tight_bounding_box.y + chunk.global_y as f64,
+                ..result.tight_bounding_box.clone()
+            };
+            let duplicate =
+                placed
+                    .iter()
+                    .zip(&final_results)
+                    .position(|((other_index, other_box), other)| {
+                        *other_index != index
+                            && seam_duplicate(&global, &result.text, other_box, &other.text)
+                    });
 
-            let global_pixel_y = chunk_pixel_y + (chunk.global_y as f64);
+            normalize_to_page(&mut result.tight_bounding_box, &chunk);
+            for word in result.words.iter_mut().flatten() {
+                normalize_to_page(&mut word.tight_bounding_box, &chunk);
+            }
+            if let Some(hint) = result.font_size_hint.as_mut() {
+                *hint /= chunk.full_height as f64;
+            }
 
-            result.tight_bounding_box.x = chunk_pixel_x / chunk.full_width as f64;
-            result.tight_bounding_box.width = chunk_pixel_width / chunk.full_width as f64;
-            result.tight_bounding_box.y = global_pixel_y / chunk.full_height as f64;
-            result.tight_bounding_box.height = chunk_pixel_height / chunk.full_height as f64;
+            match duplicate {
+                // Keep whichever copy read more of the text; the other was cut by its
+                // chunk's edge.
+                Some(kept) => {
+                    if reads_more(
+                        &result.text,
+                        &global,
+                        &final_results[kept].text,
+                        &placed[kept].1,
+                    ) {
+                        final_results[kept] = result;
+                        placed[kept] = (index, global);
+                    }
+                }
+                None => {
+                    final_results.push(result);
+                    placed.push((index, global));
+                }
+            }
+        }
+    }
+
+    final_results
+}
+
+/// Whether `text` at `bbox` was already read as the result placed at `other`, both boxes in
+/// global pixels.
+fn seam_duplicate(bbox: &BoundingBox, text: &str, other: &BoundingBox, other_text: &str) -> bool {
+    let intersection = intersection_area(bbox, other);
+    if intersection <= 0.0 {
+        return false;
+    }
+    let (area, other_area) = (bbox.width * bbox.height, other.width * other.height);
+    let iou = intersection / (area + other_area - intersection);
+    let containment = intersection / area.min(other_area);
+    (iou >= SEAM_IOU_THRESHOLD || containment >= SEAM_CONTAINMENT_THRESHOLD)
+        && text_similarity(text, other_text) >= SEAM_TEXT_SIMILARITY
+}
+
+fn intersection_area(a: &BoundingBox, b: &BoundingBox) -> f64 {
+    let width = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+    let height = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+    width.max(0.0) * height.max(0.0)
+}
 
-            final_results.push(result);
+/// Share of the shorter text's characters found, in order, in the longer one (longest
+/// common subsequence), ignoring whitespace.
+fn text_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().filter(|c| !c.is_whitespace()).collect();
+    let b: Vec<char> = b.chars().filter(|c| !c.is_whitespace()).collect();
+    let shorter = a.len().min(b.len());
+    if shorter == 0 {
+        return 0.0;
+    }
+    let mut row = vec![0usize; b.len() + 1];
+    for &ca in &a {
+        let mut diagonal = 0;
+        for (j, &cb) in b.iter().enumerate() {
+            let above = row[j + 1];
+            row[j + 1] = if ca == cb {
+                diagonal + 1
+            } else {
+                above.max(row[j])
+            };
+            diagonal = above;
         }
     }
+    row[b.len()] as f64 / shorter as f64
+}
+
+/// Whether a copy of seam text should replace the one kept so far: the copy with more
+/// characters was not cut by its chunk's edge, and on a tie the larger box is.
+fn reads_more(text: &str, bbox: &BoundingBox, kept_text: &str, kept_box: &BoundingBox) -> bool {
+    let glyphs = |text: &str| text.chars().filter(|c| !c.is_whitespace()).count();
+    match glyphs(text).cmp(&glyphs(kept_text)) {
+        std::cmp::Ordering::Greater => true,
+        std::cmp::Ordering::Less => false,
+        std::cmp::Ordering::Equal => bbox.width * bbox.height > kept_box.width * kept_box.height,
+    }
+}
+
+/// Maps a box from chunk pixels to global pixels to coordinates normalized against the
+/// full image.
+fn normalize_to_page(bbox: &mut BoundingBox, chunk: &RawChunk) {
+    let global_pixel_x = bbox.x + (chunk.global_x as f64);
+    let global_pixel_y = bbox.y + (chunk.global_y as f64);
 
-    Ok(final_results)
+    bbox.x = global_pixel_x / chunk.full_width as f64;
+    bbox.width /= chunk.full_width as f64;
+    bbox.y = global_pixel_y / chunk.full_height as f64;
+    bbox.height /= chunk.full_height as f64;
 }
@@ -0,0 +1,61 @@
+//! Human transcriptions stored in place of OCR, e.g. text from an official digital release.
+//! Blocks may come without positions; those are laid out as a stack of boxes down the
+//! right edge of the page so the overlay still has something to render.
+
+use serde::Deserialize;
+
+use crate::logic::{BoundingBox, OcrResult};
+
+/// Width of a synthetic box, as a fraction of the page width.
+const SYNTHETIC_BOX_WIDTH: f64 = 0.25;
+/// Tallest synthetic box, so a page with a handful of blocks does not get page-sized boxes.
+const MAX_SYNTHETIC_BOX_HEIGHT: f64 = 0.1;
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ManualBlock {
+    pub text: String,
+    #[serde(default)]
+    pub bbox: Option<BoundingBox>,
+}
+
+/// Turns blocks into cacheable lines. Blank blocks are dropped; blocks without a box get
+/// the next free slot of the right-edge stack, top to bottom in block order.
+pub fn to_results(blocks: Vec<ManualBlock>) -> Vec<OcrResult> {
+    let blocks: Vec<ManualBlock> = blocks
+        .into_iter()
+        .filter(|block| !block.text.trim().is_empty())
+        .collect();
+    let unplaced = blocks.iter().filter(|block| block.bbox.is_none()).count();
+    let slot_height = if unplaced == 0 {
+        0.0
+    } else {
+        (1.0 / unplaced as f64).min(MAX_SYNTHETIC_BOX_HEIGHT)
+    };
+
+    let mut next_slot = 0;
+    blocks
+        .into_iter()
+        .map(|block| {
+            let tight_bounding_box = block.bbox.unwrap_or_else(|| {
+                let y = next_slot as f64 * slot_height;
+                next_slot += 1;
+                BoundingBox {
+                    x: 1.0 - SYNTHETIC_BOX_WIDTH,
+                    y,
+                    width: SYNTHETIC_BOX_WIDTH,
+                    height: slot_height,
+                    rotation: None,
+                }
+            });
+            OcrResult {
+                text: block.text,
+                tight_bounding_box,
+                is_merged: Some(true),
+                forced_orientation: None,
+                confidence: None,
+                words: None,
+                font_size_hint: None,
+            }
+        })
+        .collect()
+}
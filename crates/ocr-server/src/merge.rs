@@ -2,10 +2,11 @@ use std::cmp::Ordering;
 
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     language::OcrLanguage,
-    logic::{BoundingBox, OcrResult},
+    logic::{BoundingBox, OcrResult, WordBox},
 };
 
 lazy_static! {
@@ -17,12 +18,46 @@ lazy_static! {
     static ref KATAKANA_REGEX: Regex = Regex::new(r"[\p{Katakana}]").expect("valid Katakana regex");
 }
 
-#[derive(Clone)]
+/// Line merging thresholds. Gaps are measured in multiples of the smaller line's font size.
+/// The spacing and language fields come from each request and are never persisted.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct MergeConfig {
     pub enabled: bool,
+    /// Lines whose font sizes differ by more than this factor are never merged.
     pub font_size_ratio: f64,
+    /// Lines closer than this across the reading direction always merge.
+    pub touching_gap: f64,
+    /// Scales the gap allowed between neighbouring lines. Dense layouts such as 4-koma
+    /// want less than 1.0, airy ones more.
+    pub gap_scale: f64,
+    /// Lines that do not overlap along the reading direction stay apart beyond this gap.
+    pub main_axis_gap: f64,
+    /// Multiplies `gap_scale` on horizontally set pages, whose line spacing is wider
+    /// relative to the glyphs than in vertical columns.
+    pub horizontal_gap_scale: f64,
+    /// Hard limit on the gap across the reading direction, as a fraction of the page's
+    /// width for vertical lines and of its height for horizontal ones. Neighbouring
+    /// bubbles on dense pages sit closer than the font-relative gaps allow; 1.0 leaves
+    /// those gaps in charge.
+    pub max_gap_fraction: f64,
+    /// Lines must overlap along the reading direction by at least this share of the
+    /// longer line to merge, from 0.0 (any) to 1.0 (fully). Bubbles side by side are
+    /// usually offset, while the lines of one bubble line up.
+    pub min_overlap_ratio: f64,
+    /// Most lines one merged block may hold; the closest lines are merged first. Unset
+    /// leaves blocks unbounded.
+    pub max_lines_per_block: Option<usize>,
+    #[serde(skip)]
     pub add_space_on_merge: Option<bool>,
+    #[serde(skip)]
     pub language: OcrLanguage,
+    /// Skips detection and treats the page as set in this direction.
+    #[serde(skip)]
+    pub orientation: Option<TextOrientation>,
+    /// How each box's direction is decided, from the request.
+    #[serde(skip)]
+    pub orientation_hint: OrientationHint,
 }
 
 impl Default for MergeConfig {
@@ -30,12 +65,263 @@ impl Default for MergeConfig {
         Self {
             enabled: true,
             font_size_ratio: 3.0,
+            touching_gap: 0.2,
+            gap_scale: 1.0,
+            main_axis_gap: 0.6,
+            horizontal_gap_scale: 1.2,
+            max_gap_fraction: 1.0,
+            min_overlap_ratio: 0.0,
+            max_lines_per_block: None,
             add_space_on_merge: None,
             language: OcrLanguage::default(),
+            orientation: None,
+            orientation_hint: OrientationHint::Auto,
         }
     }
 }
 
+impl MergeConfig {
+    /// Rejects thresholds that would merge everything or nothing.
+    pub fn validate(&self) -> Result<(), String> {
+        let fields = [
+            ("font_size_ratio", self.font_size_ratio),
+            ("touching_gap", self.touching_gap),
+            ("gap_scale", self.gap_scale),
+            ("main_axis_gap", self.main_axis_gap),
+            ("horizontal_gap_scale", self.horizontal_gap_scale),
+        ];
+        for (name, value) in fields {
+            if !value.is_finite() || value <= 0.0 {
+                return Err(format!("{name} must be a positive number"));
+            }
+        }
+        if self.font_size_ratio < 1.0 {
+            return Err("font_size_ratio must be at least 1.0".to_string());
+        }
+        if !(self.max_gap_fraction > 0.0 && self.max_gap_fraction <= 1.0) {
+            return Err("max_gap_fraction must be above 0.0 and at most 1.0".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.min_overlap_ratio) {
+            return Err("min_overlap_ratio must be between 0.0 and 1.0".to_string());
+        }
+        if self.max_lines_per_block == Some(0) {
+            return Err("max_lines_per_block must be at least 1".to_string());
+        }
+        Ok(())
+    }
+
+    /// The page orientation the request or series settles, leaving detection out.
+    pub fn page_orientation(&self) -> Option<TextOrientation> {
+        self.orientation_hint.forced().or(self.orientation)
+    }
+
+    /// The gap scale to use for a page set in `orientation`.
+    fn gap_scale_for(&self, orientation: TextOrientation) -> f64 {
+        match orientation {
+            TextOrientation::Vertical => self.gap_scale,
+            TextOrientation::Horizontal => self.gap_scale * self.horizontal_gap_scale,
+        }
+    }
+}
+
+/// The direction most text on a page is set in. Vertical pages read in right-to-left
+/// columns, horizontal ones (webtoons, western comics, Korean and horizontal manga) in
+/// left-to-right rows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextOrientation {
+    Vertical,
+    Horizontal,
+}
+
+impl TextOrientation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TextOrientation::Vertical => "vertical",
+            TextOrientation::Horizontal => "horizontal",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "vertical" => Some(TextOrientation::Vertical),
+            "horizontal" => Some(TextOrientation::Horizontal),
+            _ => None,
+        }
+    }
+
+    pub fn default_for(language: OcrLanguage) -> Self {
+        if language.prefers_vertical() {
+            TextOrientation::Vertical
+        } else {
+            TextOrientation::Horizontal
+        }
+    }
+}
+
+/// A request's say in which way each box's text runs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OrientationHint {
+    /// Boxes whose shape shows their direction keep it; the rest follow their nearest
+    /// such neighbour, else the page.
+    #[default]
+    Auto,
+    /// Every box, and the page, is vertical.
+    Vertical,
+    /// Every box, and the page, is horizontal.
+    Horizontal,
+    /// As `Auto`, but single glyphs and near-square boxes are vertical instead of
+    /// following their neighbours.
+    ForceVerticalIfSquare,
+}
+
+impl OrientationHint {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OrientationHint::Auto => "auto",
+            OrientationHint::Vertical => "vertical",
+            OrientationHint::Horizontal => "horizontal",
+            OrientationHint::ForceVerticalIfSquare => "force-vertical-if-square",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "auto" => Some(OrientationHint::Auto),
+            "vertical" => Some(OrientationHint::Vertical),
+            "horizontal" => Some(OrientationHint::Horizontal),
+            "force-vertical-if-square" => Some(OrientationHint::ForceVerticalIfSquare),
+            _ => None,
+        }
+    }
+
+    pub fn is_auto(&self) -> bool {
+        *self == OrientationHint::Auto
+    }
+
+    /// The direction this hint forces on every box, if it forces one.
+    pub fn forced(&self) -> Option<TextOrientation> {
+        match self {
+            OrientationHint::Vertical => Some(TextOrientation::Vertical),
+            OrientationHint::Horizontal => Some(TextOrientation::Horizontal),
+            OrientationHint::Auto | OrientationHint::ForceVerticalIfSquare => None,
+        }
+    }
+}
+
+/// Long side over short side at which a box's shape shows which way its text runs.
+const CLEAR_ASPECT: f64 = 1.25;
+/// How far, in multiples of its longer side, a box whose shape says nothing looks for a
+/// neighbour to take its direction from.
+const NEIGHBOUR_REACH: f64 = 4.0;
+
+/// The direction a box's shape shows: two or more glyphs stretched clearly along one
+/// axis. Single glyphs and near-square boxes say nothing.
+pub fn shape_orientation(text: &str, b: &BoundingBox) -> Option<TextOrientation> {
+    let chars = text.chars().filter(|c| !c.is_whitespace()).count();
+    if chars < 2 {
+        None
+    } else if b.height >= b.width * CLEAR_ASPECT {
+        Some(TextOrientation::Vertical)
+    } else if b.width >= b.height * CLEAR_ASPECT {
+        Some(TextOrientation::Horizontal)
+    } else {
+        None
+    }
+}
+
+/// Decides which way each line runs. Lines whose shape shows it, or whose rotated Lens
+/// box did, keep that direction; the rest follow the nearest such line within reach and
+/// otherwise the page. Languages never set vertically are horizontal throughout unless
+/// the request says otherwise.
+fn line_orientations(
+    lines: &[OcrResult],
+    page: TextOrientation,
+    config: &MergeConfig,
+) -> Vec<TextOrientation> {
+    let hint = config.orientation_hint;
+    if let Some(forced) = hint.forced() {
+        return vec![forced; lines.len()];
+    }
+    if !config.language.prefers_vertical() {
+        return vec![TextOrientation::Horizontal; lines.len()];
+    }
+    let own: Vec<Option<TextOrientation>> = lines
+        .iter()
+        .map(|l| {
+            shape_orientation(&l.text, &l.tight_bounding_box).or_else(|| {
+                let glyphs = l.text.chars().filter(|c| !c.is_whitespace()).count();
+                l.forced_orientation
+                    .as_deref()
+                    .and_then(TextOrientation::parse)
+                    .filter(|_| glyphs >= 2)
+            })
+        })
+        .collect();
+
+    lines
+        .iter()
+        .zip(&own)
+        .map(|(line, known)| {
+            if let Some(orientation) = known {
+                return *orientation;
+            }
+            if hint == OrientationHint::ForceVerticalIfSquare {
+                return TextOrientation::Vertical;
+            }
+            let b = &line.tight_bounding_box;
+            let reach = b.width.max(b.height) * NEIGHBOUR_REACH;
+            lines
+                .iter()
+                .zip(&own)
+                .filter_map(|(other, orientation)| {
+                    Some((box_gap(b, &other.tight_bounding_box), (*orientation)?))
+                })
+                .filter(|(gap, _)| *gap <= reach)
+                .min_by(|x, y| x.0.partial_cmp(&y.0).unwrap_or(Ordering::Equal))
+                .map_or(page, |(_, orientation)| orientation)
+        })
+        .collect()
+}
+
+/// The distance between the nearest edges of two boxes; zero when they overlap.
+fn box_gap(a: &BoundingBox, b: &BoundingBox) -> f64 {
+    let dx = (b.x - (a.x + a.width)).max(a.x - (b.x + b.width)).max(0.0);
+    let dy = (b.y - (a.y + a.height))
+        .max(a.y - (b.y + b.height))
+        .max(0.0);
+    dx.hypot(dy)
+}
+
+/// Guesses the page's orientation from the shape of its raw lines. Every line of two or
+/// more characters votes with its length for the axis its box is stretched along; single
+/// glyphs and near-square boxes abstain. A tie falls back to the language's usual
+/// direction. Boxes must be in pixels, not normalized coordinates.
+pub fn infer_orientation<'a>(
+    lines: impl IntoIterator<Item = &'a OcrResult>,
+    language: OcrLanguage,
+) -> TextOrientation {
+    let (mut vertical, mut horizontal) = (0usize, 0usize);
+    for line in lines {
+        let chars = line.text.chars().filter(|c| !c.is_whitespace()).count();
+        if chars < 2 {
+            continue;
+        }
+        let b = &line.tight_bounding_box;
+        if b.height > b.width * 1.5 {
+            vertical += chars;
+        } else if b.width > b.height * 1.5 {
+            horizontal += chars;
+        }
+    }
+    match vertical.cmp(&horizontal) {
+        Ordering::Greater => TextOrientation::Vertical,
+        Ordering::Less => TextOrientation::Horizontal,
+        Ordering::Equal => TextOrientation::default_for(language),
+    }
+}
+
 // --- Geometry Helpers ---
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -274,13 +560,20 @@ struct ProcessedLine {
 
 struct UnionFind {
     parent: Vec<usize>,
+    size: Vec<usize>,
 }
 impl UnionFind {
     fn new(n: usize) -> Self {
         Self {
             parent: (0..n).collect(),
+            size: vec![1; n],
         }
     }
+    /// Lines in the group holding `i`.
+    fn group_size(&mut self, i: usize) -> usize {
+        let root = self.find(i);
+        self.size[root]
+    }
     fn find(&mut self, i: usize) -> usize {
         if self.parent[i] != i {
             self.parent[i] = self.find(self.parent[i]);
@@ -292,11 +585,29 @@ impl UnionFind {
         let root_j = self.find(j);
         if root_i != root_j {
             self.parent[root_i] = root_j;
+            self.size[root_j] += self.size[root_i];
         }
     }
 }
 
-fn are_lines_mergeable(a: &ProcessedLine, b: &ProcessedLine, config: &MergeConfig) -> bool {
+/// The gap between two lines' boxes, for merging the closest pairs first.
+fn line_distance(a: &ProcessedLine, b: &ProcessedLine) -> f64 {
+    let gap_main = (b.min_main - a.max_main)
+        .max(a.min_main - b.max_main)
+        .max(0.0);
+    let gap_cross = (b.min_cross - a.max_cross)
+        .max(a.min_cross - b.max_cross)
+        .max(0.0);
+    gap_main.hypot(gap_cross)
+}
+
+fn are_lines_mergeable(
+    a: &ProcessedLine,
+    b: &ProcessedLine,
+    config: &MergeConfig,
+    gap_scale: f64,
+    (page_w, page_h): (f64, f64),
+) -> bool {
     if a.is_vertical != b.is_vertical {
         return false;
     }
@@ -324,10 +635,19 @@ fn are_lines_mergeable(a: &ProcessedLine, b: &ProcessedLine, config: &MergeConfi
     let base_metric = min_font;
     let global_overlap = overlap_main / a.length_main.max(b.length_main);
 
+    // Hard limits from the config, ahead of the tiers below.
+    let cross_extent = if a.is_vertical { page_w } else { page_h };
+    if gap_cross > cross_extent * config.max_gap_fraction {
+        return false;
+    }
+    if global_overlap < config.min_overlap_ratio {
+        return false;
+    }
+
     // --- REFINED TIERED STRATEGY (INVERTED LOGIC) ---
 
     // 1. TOUCHING: Merge anything that touches horizontally.
-    if gap_cross < base_metric * 0.2 {
+    if gap_cross < base_metric * config.touching_gap {
         return true;
     }
 
@@ -367,7 +687,7 @@ fn are_lines_mergeable(a: &ProcessedLine, b: &ProcessedLine, config: &MergeConfi
         return false;
     }
 
-    if gap_cross > base_metric * allowed_gap {
+    if gap_cross > base_metric * allowed_gap * gap_scale {
         return false;
     }
 
@@ -376,7 +696,7 @@ fn are_lines_mergeable(a: &ProcessedLine, b: &ProcessedLine, config: &MergeConfi
         let gap_main = 0.0f64
             .max(b.min_main - a.max_main)
             .max(a.min_main - b.max_main);
-        if gap_main > base_metric * 0.6 {
+        if gap_main > base_metric * config.main_axis_gap {
             return false;
         }
     }
@@ -384,31 +704,37 @@ fn are_lines_mergeable(a: &ProcessedLine, b: &ProcessedLine, config: &MergeConfi
     true
 }
 
-pub fn auto_merge(lines: Vec<OcrResult>, w: u32, h: u32, config: &MergeConfig) -> Vec<OcrResult> {
-    if !config.enabled || lines.is_empty() {
+pub fn auto_merge(
+    mut lines: Vec<OcrResult>,
+    w: u32,
+    h: u32,
+    config: &MergeConfig,
+) -> Vec<OcrResult> {
+    if lines.is_empty() {
         return lines;
     }
 
+    let orientation = config
+        .page_orientation()
+        .unwrap_or_else(|| infer_orientation(&lines, config.language));
+    if !config.enabled {
+        // Unmerged lines still say which way they run.
+        let directions = line_orientations(&lines, orientation, config);
+        for (line, direction) in lines.iter_mut().zip(directions) {
+            line.forced_orientation = Some(direction.as_str().into());
+        }
+        return lines;
+    }
+    let gap_scale = config.gap_scale_for(orientation);
     let clean_lines = filter_bad_boxes(lines, w, h, config);
+    let directions = line_orientations(&clean_lines, orientation, config);
 
     let processed: Vec<ProcessedLine> = clean_lines
         .iter()
-        .map(|l| {
+        .zip(directions)
+        .map(|(l, direction)| {
             let b = &l.tight_bounding_box;
-            let prefers_vertical = config.language.prefers_vertical();
-            let lens_is_vertical = l.forced_orientation.as_deref() == Some("vertical");
-            let char_count = l.text.chars().count();
-
-            let is_v = if prefers_vertical {
-                if char_count == 1 {
-                    b.height > b.width * 0.8
-                } else {
-                    let is_physically_vertical = b.height > b.width;
-                    lens_is_vertical || is_physically_vertical
-                }
-            } else {
-                lens_is_vertical && b.height > b.width * 1.1
-            };
+            let is_v = direction == TextOrientation::Vertical;
 
             let (min_main, max_main, min_cross, max_cross) = if is_v {
                 (b.y, b.y + b.height, b.x, b.x + b.width)
@@ -428,14 +754,28 @@ pub fn auto_merge(lines: Vec<OcrResult>, w: u32, h: u32, config: &MergeConfig) -
         })
         .collect();
 
-    let mut uf = UnionFind::new(processed.len());
+    let page = (w as f64, h as f64);
+    let mut pairs = Vec::new();
     for i in 0..processed.len() {
         for j in (i + 1)..processed.len() {
-            if are_lines_mergeable(&processed[i], &processed[j], config) {
-                uf.union(i, j);
+            if are_lines_mergeable(&processed[i], &processed[j], config, gap_scale, page) {
+                pairs.push((line_distance(&processed[i], &processed[j]), i, j));
             }
         }
     }
+    // Closest first, so a capped block takes its nearest lines.
+    pairs.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+    let mut uf = UnionFind::new(processed.len());
+    for (_, i, j) in pairs {
+        if let Some(cap) = config.max_lines_per_block
+            && uf.find(i) != uf.find(j)
+            && uf.group_size(i) + uf.group_size(j) > cap
+        {
+            continue;
+        }
+        uf.union(i, j);
+    }
 
     let mut groups: std::collections::HashMap<usize, Vec<usize>> = std::collections::HashMap::new();
     for i in 0..processed.len() {
@@ -451,6 +791,7 @@ pub fn auto_merge(lines: Vec<OcrResult>, w: u32, h: u32, config: &MergeConfig) -
         if indices.len() == 1 {
             let mut line = clean_lines[indices[0]].clone();
             let is_v = processed[indices[0]].is_vertical;
+            line.font_size_hint = Some(processed[indices[0]].font_size);
             line.forced_orientation = Some(if is_v {
                 "vertical".into()
             } else {
@@ -537,7 +878,84 @@ pub fn auto_merge(lines: Vec<OcrResult>, w: u32, h: u32, config: &MergeConfig) -
             } else {
                 "horizontal".into()
             }),
+            confidence: merged_confidence(&group_lines),
+            words: merged_words(&group_lines),
+            font_size_hint: median_font_size(&indices, &processed),
         });
     }
+    sort_reading_order(&mut results, orientation);
     results
 }
+
+/// Average confidence of the merged lines, weighted by their length. Lines without a
+/// score are left out; `None` when none has one.
+fn merged_confidence(lines: &[&OcrResult]) -> Option<f64> {
+    let (weighted, chars) = lines
+        .iter()
+        .filter_map(|line| {
+            let chars = line.text.chars().count().max(1) as f64;
+            line.confidence
+                .map(|confidence| (confidence * chars, chars))
+        })
+        .fold((0.0, 0.0), |(sum, total), (value, chars)| {
+            (sum + value, total + chars)
+        });
+    (chars > 0.0).then(|| weighted / chars)
+}
+
+/// Median line thickness of a block, in pixels.
+fn median_font_size(indices: &[usize], processed: &[ProcessedLine]) -> Option<f64> {
+    let mut sizes: Vec<f64> = indices.iter().map(|&i| processed[i].font_size).collect();
+    sizes.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+    let mid = sizes.len() / 2;
+    match sizes.len() {
+        0 => None,
+        len if len % 2 == 0 => Some((sizes[mid - 1] + sizes[mid]) / 2.0),
+        _ => Some(sizes[mid]),
+    }
+}
+
+/// The merged lines' word boxes in reading order; `None` when no line has any.
+fn merged_words(lines: &[&OcrResult]) -> Option<Vec<WordBox>> {
+    lines.iter().any(|line| line.words.is_some()).then(|| {
+        lines
+            .iter()
+            .flat_map(|line| line.words.iter().flatten().cloned())
+            .collect()
+    })
+}
+
+/// Orders merged blocks the way the page is read. Blocks are banded into rows by their
+/// tops; a block joins the current row while its top is above the middle of the row's
+/// first block. Rows run top to bottom, and within a row vertical pages read right to
+/// left and horizontal pages left to right.
+fn sort_reading_order(results: &mut Vec<OcrResult>, orientation: TextOrientation) {
+    results.sort_by(|a, b| a.tight_bounding_box.y.total_cmp(&b.tight_bounding_box.y));
+
+    let mut rows: Vec<Vec<OcrResult>> = Vec::new();
+    let mut row_limit = f64::NEG_INFINITY;
+    for result in results.drain(..) {
+        let b = &result.tight_bounding_box;
+        match rows.last_mut() {
+            Some(row) if b.y < row_limit => row.push(result),
+            _ => {
+                row_limit = b.y + b.height / 2.0;
+                rows.push(vec![result]);
+            }
+        }
+    }
+
+    for mut row in rows {
+        match orientation {
+            TextOrientation::Vertical => row.sort_by(|a, b| {
+                let ra = a.tight_bounding_box.x + a.tight_bounding_box.width;
+                let rb = b.tight_bounding_box.x + b.tight_bounding_box.width;
+                rb.total_cmp(&ra)
+            }),
+            TextOrientation::Horizontal => {
+                row.sort_by(|a, b| a.tight_bounding_box.x.total_cmp(&b.tight_bounding_box.x))
+            }
+        }
+        results.extend(row);
+    }
+}
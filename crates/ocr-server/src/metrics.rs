@@ -0,0 +1,330 @@
+//! Process-wide counters behind `/metrics`, rendered in the Prometheus text format.
+//! Single-page requests and chapter jobs are recorded separately under the `path` label.
+
+use std::{
+    fmt::Write,
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use lazy_static::lazy_static;
+
+use crate::{locks, logic::OcrOutcome, state::AppState};
+
+lazy_static! {
+    pub static ref METRICS: Metrics = Metrics::default();
+}
+
+/// Upper bounds of the duration histogram buckets, in seconds.
+pub const BUCKETS: [f64; 10] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+/// Which code path OCRed a page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Path {
+    /// `/ocr` and `/ocr/batch`.
+    Request,
+    /// Chapter preprocess jobs.
+    Job,
+}
+
+impl Path {
+    const ALL: [Path; 2] = [Path::Request, Path::Job];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Path::Request => "request",
+            Path::Job => "job",
+        }
+    }
+}
+
+/// A step of `fetch_and_process`, plus the whole run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Fetch,
+    Decode,
+    /// Lens, or Tesseract.
+    Recognize,
+    Merge,
+    Total,
+}
+
+impl Phase {
+    const ALL: [Phase; 5] = [
+        Phase::Fetch,
+        Phase::Decode,
+        Phase::Recognize,
+        Phase::Merge,
+        Phase::Total,
+    ];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Phase::Fetch => "fetch",
+            Phase::Decode => "decode",
+            Phase::Recognize => "recognize",
+            Phase::Merge => "merge",
+            Phase::Total => "total",
+        }
+    }
+}
+
+/// How an OCR run for one page ended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PageStatus {
+    Ok,
+    /// The deadline hit; the results were returned but not cached.
+    Partial,
+    Failed,
+}
+
+impl PageStatus {
+    const ALL: [PageStatus; 3] = [PageStatus::Ok, PageStatus::Partial, PageStatus::Failed];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            PageStatus::Ok => "ok",
+            PageStatus::Partial => "partial",
+            PageStatus::Failed => "failed",
+        }
+    }
+}
+
+/// A cumulative-bucket histogram of durations.
+#[derive(Default)]
+pub struct Histogram {
+    buckets: [AtomicU64; BUCKETS.len()],
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+}
+
+impl Histogram {
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            if seconds <= bound {
+                bucket.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum_micros
+            .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        for (bucket, bound) in self.buckets.iter().zip(BUCKETS) {
+            let _ = writeln!(
+                out,
+                "{name}_bucket{{{labels},le=\"{bound}\"}} {}",
+                bucket.load(Ordering::Relaxed)
+            );
+        }
+        let count = self.count.load(Ordering::Relaxed);
+        let sum = self.sum_micros.load(Ordering::Relaxed) as f64 / 1e6;
+        let _ = writeln!(out, "{name}_bucket{{{labels},le=\"+Inf\"}} {count}");
+        let _ = writeln!(out, "{name}_sum{{{labels}}} {sum}");
+        let _ = writeln!(out, "{name}_count{{{labels}}} {count}");
+    }
+}
+
+#[derive(Default)]
+struct PathMetrics {
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    pages: [AtomicU64; PageStatus::ALL.len()],
+    phases: [Histogram; Phase::ALL.len()],
+}
+
+#[derive(Default)]
+pub struct Metrics {
+    paths: [PathMetrics; Path::ALL.len()],
+}
+
+impl Metrics {
+    fn path(&self, path: Path) -> &PathMetrics {
+        &self.paths[path as usize]
+    }
+
+    pub fn cache_hits(&self, path: Path, count: usize) {
+        self.path(path)
+            .cache_hits
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn cache_misses(&self, path: Path, count: usize) {
+        self.path(path)
+            .cache_misses
+            .fetch_add(count as u64, Ordering::Relaxed);
+    }
+
+    pub fn observe_phase(&self, path: Path, phase: Phase, duration: Duration) {
+        self.path(path).phases[phase as usize].observe(duration);
+    }
+
+    /// Records a finished OCR run: how it ended, its phase timings and the overall time.
+    pub fn page_done(&self, path: Path, outcome: &anyhow::Result<OcrOutcome>, total: Duration) {
+        let status = match outcome {
+            Ok(outcome) if outcome.partial => PageStatus::Partial,
+            Ok(_) => PageStatus::Ok,
+            Err(_) => PageStatus::Failed,
+        };
+        self.path(path).pages[status as usize].fetch_add(1, Ordering::Relaxed);
+        if let Ok(OcrOutcome { timings, .. }) = outcome {
+            self.observe_phase(path, Phase::Fetch, timings.fetch);
+            self.observe_phase(path, Phase::Decode, timings.decode);
+            self.observe_phase(path, Phase::Recognize, timings.recognize);
+            self.observe_phase(path, Phase::Merge, timings.merge);
+        }
+        self.observe_phase(path, Phase::Total, total);
+    }
+
+    fn render_counters(&self, out: &mut String) {
+        let per_path =
+            |out: &mut String, name: &str, help: &str, value: fn(&PathMetrics) -> u64| {
+                let _ = writeln!(out, "# HELP {name} {help}\n# TYPE {name} counter");
+                for path in Path::ALL {
+                    let value = value(self.path(path));
+                    let _ = writeln!(out, "{name}{{path=\"{}\"}} {value}", path.as_str());
+                }
+            };
+        per_path(
+            out,
+            "manatan_ocr_cache_hits_total",
+            "Pages answered from the OCR cache.",
+            |p| p.cache_hits.load(Ordering::Relaxed),
+        );
+        per_path(
+            out,
+            "manatan_ocr_cache_misses_total",
+            "Pages that had to be OCRed because they were not cached or were stale.",
+            |p| p.cache_misses.load(Ordering::Relaxed),
+        );
+
+        let name = "manatan_ocr_pages_total";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Pages OCRed, by how the run ended.\n# TYPE {name} counter"
+        );
+        for path in Path::ALL {
+            for status in PageStatus::ALL {
+                let value = self.path(path).pages[status as usize].load(Ordering::Relaxed);
+                let _ = writeln!(
+                    out,
+                    "{name}{{path=\"{}\",status=\"{}\"}} {value}",
+                    path.as_str(),
+                    status.as_str()
+                );
+            }
+        }
+    }
+
+    fn render_histograms(&self, out: &mut String) {
+        let name = "manatan_ocr_phase_duration_seconds";
+        let _ = writeln!(
+            out,
+            "# HELP {name} Time spent per page in each OCR phase.\n# TYPE {name} histogram"
+        );
+        for path in Path::ALL {
+            for phase in Phase::ALL {
+                let labels = format!("path=\"{}\",phase=\"{}\"", path.as_str(), phase.as_str());
+                self.path(path).phases[phase as usize].render(out, name, &labels);
+            }
+        }
+    }
+}
+
+fn single(out: &mut String, kind: &str, name: &str, help: &str, value: usize) {
+    let _ = writeln!(
+        out,
+        "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}"
+    );
+}
+
+/// Renders `metrics` and the server's live gauges in the Prometheus text format.
+pub fn render(metrics: &Metrics, state: &AppState) -> String {
+    let mut out = String::new();
+    metrics.render_counters(&mut out);
+    metrics.render_histograms(&mut out);
+
+    let chapter_jobs = locks::read(&state.active_chapter_jobs).len();
+    let lens = state.lens_limiter.snapshot();
+    single(
+        &mut out,
+        "gauge",
+        "manatan_ocr_active_jobs",
+        "Chapter jobs currently running.",
+        state.active_jobs.load(Ordering::Relaxed),
+    );
+    single(
+        &mut out,
+        "gauge",
+        "manatan_ocr_job_queue_depth",
+        "Chapter jobs waiting for a worker.",
+        state.job_queue.len(),
+    );
+    single(
+        &mut out,
+        "gauge",
+        "manatan_ocr_chapter_jobs",
+        "Chapter jobs with progress tracked, running or queued.",
+        chapter_jobs,
+    );
+    single(
+        &mut out,
+        "gauge",
+        "manatan_ocr_lens_in_use",
+        "Pages currently holding a Lens slot.",
+        lens.in_use,
+    );
+    single(
+        &mut out,
+        "gauge",
+        "manatan_ocr_lens_limit",
+        "Pages allowed to hold a Lens slot at once.",
+        lens.limit,
+    );
+    // A busy cache leaves the gauge out rather than reporting an empty cache.
+    if let Ok(cached_pages) = state.cache_len() {
+        single(
+            &mut out,
+            "gauge",
+            "manatan_ocr_cached_pages",
+            "Pages in the OCR cache.",
+            cached_pages,
+        );
+    }
+    single(
+        &mut out,
+        "counter",
+        "manatan_ocr_requests_processed_total",
+        "Pages OCRed since the server started.",
+        state.requests_processed.load(Ordering::Relaxed),
+    );
+    render_disk_usage(&mut out, &manatan_storage::usage());
+    out
+}
+
+fn render_disk_usage(out: &mut String, usage: &[manatan_storage::DirectoryUsage]) {
+    let name = "manatan_data_dir_bytes";
+    let _ = writeln!(
+        out,
+        "# HELP {name} Bytes used by each data directory.\n# TYPE {name} gauge"
+    );
+    for dir in usage {
+        let _ = writeln!(
+            out,
+            "{name}{{directory=\"{}\"}} {}",
+            dir.name, dir.size_bytes
+        );
+    }
+    let name = "manatan_data_dir_available_bytes";
+    let _ = writeln!(
+        out,
+        "# HELP {name} Free space on the filesystem holding each data directory.\n# TYPE {name} gauge"
+    );
+    for dir in usage {
+        if let Some(available) = dir.available_bytes {
+            let _ = writeln!(out, "{name}{{directory=\"{}\"}} {available}", dir.name);
+        }
+    }
+}
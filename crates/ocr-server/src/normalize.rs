@@ -0,0 +1,172 @@
+//! Cleanup of merged OCR text before it is returned and cached. Lens leaves stray spaces
+//! inside Japanese text, reads furigana as a repeat of the line it sits on and mixes full
+//! and half-width forms, which all end up in concatenated text and character counts.
+
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::logic::{OcrResult, WordBox};
+
+/// Which cleanups run. Persisted in [`OcrConfig`](crate::state::OcrConfig) and exposed at
+/// `/text-normalization`; changes apply to pages OCR'd or re-merged afterwards.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct TextNormalization {
+    /// Drop spaces that sit between two CJK characters. Hangul keeps its spaces.
+    pub strip_cjk_spaces: bool,
+    /// Apply Unicode NFKC, folding full-width Latin and digits and half-width katakana.
+    /// Off by default since it also rewrites full-width punctuation readers may expect.
+    pub nfkc: bool,
+    /// Drop a line that repeats the line before it, as furigana read again often does.
+    /// Off by default since dialogue can repeat a line on purpose.
+    pub collapse_duplicate_lines: bool,
+}
+
+impl Default for TextNormalization {
+    fn default() -> Self {
+        Self {
+            strip_cjk_spaces: true,
+            nfkc: false,
+            collapse_duplicate_lines: false,
+        }
+    }
+}
+
+impl TextNormalization {
+    /// Cleans every result's text and its words' text. Words of a dropped duplicate line
+    /// are dropped with it.
+    pub fn apply(&self, results: &mut [OcrResult]) {
+        for result in results {
+            let lines = self.lines(&result.text);
+            if let Some(words) = result.words.as_mut() {
+                for word in words.iter_mut() {
+                    word.text = self.normalize_line(&word.text);
+                }
+                if lines.iter().any(|(_, kept)| !kept)
+                    && let Some(kept) = words_of_kept_lines(&lines, words)
+                {
+                    *words = kept;
+                }
+            }
+            result.text = join_kept(lines);
+        }
+    }
+
+    /// Cleans one block of text, line by line.
+    pub fn normalize(&self, text: &str) -> String {
+        join_kept(self.lines(text))
+    }
+
+    /// Each cleaned line of `text`, and whether it is kept or dropped as a repeat.
+    fn lines(&self, text: &str) -> Vec<(String, bool)> {
+        let mut lines: Vec<(String, bool)> = Vec::new();
+        let mut last_kept: Option<String> = None;
+        for line in text.split('\n') {
+            let line = self.normalize_line(line);
+            let repeat = self.collapse_duplicate_lines
+                && !line.trim().is_empty()
+                && last_kept.as_deref() == Some(line.trim());
+            if !repeat {
+                last_kept = Some(line.trim().to_string());
+            }
+            lines.push((line, !repeat));
+        }
+        lines
+    }
+
+    fn normalize_line(&self, line: &str) -> String {
+        let line = if self.nfkc {
+            line.nfkc().collect()
+        } else {
+            line.to_string()
+        };
+        if self.strip_cjk_spaces {
+            strip_cjk_spaces(&line)
+        } else {
+            line
+        }
+    }
+}
+
+fn join_kept(lines: Vec<(String, bool)>) -> String {
+    lines
+        .into_iter()
+        .filter_map(|(line, kept)| kept.then_some(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The words of the kept lines. Words are matched to lines in order by their text, spaces
+/// aside; `None` when they do not line up, and the caller keeps every word.
+fn words_of_kept_lines(lines: &[(String, bool)], words: &[WordBox]) -> Option<Vec<WordBox>> {
+    let squeeze = |text: &str| {
+        text.chars()
+            .filter(|c| !c.is_whitespace())
+            .collect::<String>()
+    };
+    let mut words = words.iter();
+    let mut kept_words = Vec::new();
+    for (line, kept) in lines {
+        let target = squeeze(line);
+        let mut taken = String::new();
+        let mut line_words = Vec::new();
+        while taken.len() < target.len() {
+            let word = words.next()?;
+            taken.push_str(&squeeze(&word.text));
+            line_words.push(word.clone());
+        }
+        if taken != target {
+            return None;
+        }
+        if *kept {
+            kept_words.extend(line_words);
+        }
+    }
+    words.next().is_none().then_some(kept_words)
+}
+
+/// Removes runs of spaces with a CJK character on both sides.
+pub fn strip_cjk_spaces(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = String::with_capacity(text.len());
+    let mut index = 0;
+    while index < chars.len() {
+        let ch = chars[index];
+        if !is_space(ch) {
+            out.push(ch);
+            index += 1;
+            continue;
+        }
+        let run_end = chars[index..]
+            .iter()
+            .position(|&c| !is_space(c))
+            .map_or(chars.len(), |offset| index + offset);
+        let between_cjk = out.chars().next_back().is_some_and(is_cjk)
+            && chars.get(run_end).copied().is_some_and(is_cjk);
+        if !between_cjk {
+            out.extend(&chars[index..run_end]);
+        }
+        index = run_end;
+    }
+    out
+}
+
+fn is_space(ch: char) -> bool {
+    ch == ' ' || ch == '\u{3000}' || ch == '\t'
+}
+
+/// Han, kana, CJK punctuation and full-width forms. Hangul is left out on purpose: Korean
+/// separates words with spaces.
+fn is_cjk(ch: char) -> bool {
+    matches!(ch,
+        '\u{3000}'..='\u{303F}' // CJK symbols and punctuation
+        | '\u{3040}'..='\u{30FF}' // Hiragana, Katakana
+        | '\u{31F0}'..='\u{31FF}' // Katakana phonetic extensions
+        | '\u{3400}'..='\u{4DBF}' // CJK extension A
+        | '\u{4E00}'..='\u{9FFF}' // CJK unified ideographs
+        | '\u{F900}'..='\u{FAFF}' // CJK compatibility ideographs
+        | '\u{FF01}'..='\u{FF60}' // Full-width forms
+        | '\u{FF61}'..='\u{FF9F}' // Half-width katakana and punctuation
+        | '\u{20000}'..='\u{2FA1F}' // CJK extensions B and up
+    )
+}
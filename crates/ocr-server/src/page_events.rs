@@ -0,0 +1,129 @@
+//! Live page notifications for `GET /ws`. Every page written to the cache is announced on
+//! [`AppState::page_updates`](crate::state::AppState::page_updates), so a reader that
+//! started a preprocess job can show each page's text as soon as it lands instead of
+//! polling for it.
+
+use axum::extract::ws::{Message, WebSocket};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::debug;
+
+use crate::{
+    backend::OcrBackend,
+    language::OcrLanguage,
+    logic::{self, CacheKeyConfig},
+};
+
+/// Updates buffered per subscriber; a client further behind than this skips ahead.
+pub const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PageStatus {
+    /// OCR results were cached.
+    Ready,
+    /// A preprocess job gave up on the page.
+    Failed,
+}
+
+#[derive(Clone, Debug)]
+pub struct PageUpdate {
+    pub cache_key: String,
+    pub context: String,
+    pub status: PageStatus,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct PageStreamParams {
+    /// Only stream pages of this chapter.
+    pub base_url: Option<String>,
+    #[serde(alias = "lang")]
+    pub language: Option<OcrLanguage>,
+}
+
+/// Matches cache keys against a chapter's base URL.
+#[derive(Clone, Debug)]
+pub struct PageFilter {
+    base_url: String,
+    prefixes: Vec<String>,
+}
+
+impl PageFilter {
+    pub fn new(base_url: &str, language: OcrLanguage) -> Self {
+        let base_url = base_url
+            .split('?')
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches('/')
+            .to_string();
+        // The query is gone already, so no normalization can change the key.
+        let chapter_key =
+            logic::get_cache_key(&base_url, Some(language), &CacheKeyConfig::default());
+        let prefixes = [OcrBackend::Lens, OcrBackend::Tesseract]
+            .iter()
+            .map(|backend| backend.cache_key(&chapter_key))
+            .collect();
+        Self { base_url, prefixes }
+    }
+
+    /// The page's URL under the base URL, or `None` when the page belongs to another
+    /// chapter.
+    pub fn page_url(&self, cache_key: &str) -> Option<String> {
+        self.prefixes.iter().find_map(|prefix| {
+            let rest = cache_key.strip_prefix(prefix.as_str())?;
+            (rest.is_empty() || rest.starts_with('/')).then(|| format!("{}{rest}", self.base_url))
+        })
+    }
+}
+
+#[derive(Serialize)]
+struct PageMessage<'a> {
+    /// Set when the client subscribed with a `base_url`.
+    page_url: Option<String>,
+    cache_key: &'a str,
+    context: &'a str,
+    status: PageStatus,
+}
+
+/// Forwards updates to the socket until the client goes away.
+pub async fn stream(
+    mut socket: WebSocket,
+    mut updates: broadcast::Receiver<PageUpdate>,
+    filter: Option<PageFilter>,
+) {
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(update) => {
+                    let page_url = match &filter {
+                        Some(filter) => match filter.page_url(&update.cache_key) {
+                            Some(url) => Some(url),
+                            None => continue,
+                        },
+                        None => None,
+                    };
+                    let message = PageMessage {
+                        page_url,
+                        cache_key: &update.cache_key,
+                        context: &update.context,
+                        status: update.status,
+                    };
+                    let Ok(text) = serde_json::to_string(&message) else {
+                        continue;
+                    };
+                    if socket.send(Message::Text(text.into())).await.is_err() {
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!("Page update subscriber fell behind, skipped {skipped} updates");
+                }
+                Err(RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! Rasterizing PDF pages so they can go through the normal image pipeline. Rendering uses
+//! PDFium, loaded at runtime from [`PDFIUM_PATH_ENV`], next to the executable or from the
+//! system library path, so the server still starts without it.
+
+use std::{
+    io::Cursor,
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+};
+
+use anyhow::anyhow;
+use image::{ImageFormat, RgbaImage};
+use pdfium_render::prelude::{PdfRenderConfig, Pdfium};
+
+/// Directory holding the PDFium library, checked before the executable's directory.
+pub const PDFIUM_PATH_ENV: &str = "MANATAN_PDFIUM_PATH";
+/// Lowest and highest rendering resolution accepted from the config.
+pub const MIN_DPI: u32 = 72;
+pub const MAX_DPI: u32 = 600;
+/// PDF user space units per inch.
+const POINTS_PER_INCH: f32 = 72.0;
+/// Readers accept the header anywhere in the first kilobyte, after junk bytes.
+const HEADER_WINDOW: usize = 1024;
+/// Separates a page number from the document URL or cache key it belongs to.
+const PAGE_SUFFIX: &str = "#page=";
+
+static PDFIUM: OnceLock<Result<Pdfium, String>> = OnceLock::new();
+
+/// Whether `bytes` look like a PDF file.
+pub fn is_pdf(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(HEADER_WINDOW)]
+        .windows(5)
+        .any(|window| window == b"%PDF-")
+}
+
+/// The cache key for one page of a document. Page 1 keeps the plain key, so a URL that
+/// turns out to be an image and the first page of a PDF share their entry.
+pub fn page_cache_key(cache_key: &str, page: Option<u32>) -> String {
+    match page {
+        Some(page) if page > 1 => format!("{cache_key}{PAGE_SUFFIX}{page}"),
+        _ => cache_key.to_string(),
+    }
+}
+
+/// A `LIKE` pattern matching the keys of every page after the first of a document.
+pub fn later_pages_pattern(cache_key: &str) -> String {
+    format!("{cache_key}{PAGE_SUFFIX}%")
+}
+
+/// Splits a `url#page=N` reference, as batch requests name later pages of a PDF, into the
+/// URL and its 1-based page. Anything else is a plain URL.
+pub fn split_page(url: &str) -> (&str, Option<u32>) {
+    url.rsplit_once(PAGE_SUFFIX)
+        .and_then(|(document, page)| Some((document, page.parse().ok()?)))
+        .unwrap_or((url, None))
+}
+
+/// Whether PDFium could be loaded. The first call loads it.
+pub fn available() -> bool {
+    pdfium().is_ok()
+}
+
+fn pdfium() -> anyhow::Result<&'static Pdfium> {
+    PDFIUM
+        .get_or_init(|| {
+            let mut dirs: Vec<PathBuf> = std::env::var_os(PDFIUM_PATH_ENV)
+                .map(PathBuf::from)
+                .into_iter()
+                .collect();
+            if let Some(dir) = std::env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(PathBuf::from))
+            {
+                dirs.push(dir);
+            }
+            dirs.iter()
+                .find_map(|dir| {
+                    Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(dir)).ok()
+                })
+                .map_or_else(Pdfium::bind_to_system_library, Ok)
+                .map(Pdfium::new)
+                .map_err(|err| format!("PDFium is not available: {err}"))
+        })
+        .as_ref()
+        .map_err(|err| anyhow!("{err}"))
+}
+
+/// How many pages `pdf_bytes` has.
+pub async fn page_count(pdf_bytes: Arc<Vec<u8>>) -> anyhow::Result<u32> {
+    tokio::task::spawn_blocking(move || {
+        let document = pdfium()?
+            .load_pdf_from_byte_slice(&pdf_bytes, None)
+            .map_err(|err| anyhow!("Failed to open PDF: {err}"))?;
+        Ok(u32::from(document.pages().len()))
+    })
+    .await?
+}
+
+/// Renders the 1-based `page` of `pdf_bytes` at `dpi` and returns it as PNG bytes.
+pub async fn render_page(pdf_bytes: Arc<Vec<u8>>, page: u32, dpi: u32) -> anyhow::Result<Vec<u8>> {
+    let dpi = dpi.clamp(MIN_DPI, MAX_DPI);
+    tokio::task::spawn_blocking(move || {
+        let document = pdfium()?
+            .load_pdf_from_byte_slice(&pdf_bytes, None)
+            .map_err(|err| anyhow!("Failed to open PDF: {err}"))?;
+        let pages = document.pages();
+        let index = page
+            .checked_sub(1)
+            .and_then(|index| u16::try_from(index).ok())
+            .filter(|index| *index < pages.len())
+            .ok_or_else(|| anyhow!("PDF has no page {page} (it has {})", pages.len()))?;
+        let bitmap = pages
+            .get(index)
+            .and_then(|pdf_page| {
+                pdf_page.render_with_config(
+                    &PdfRenderConfig::new().scale_page_by_factor(dpi as f32 / POINTS_PER_INCH),
+                )
+            })
+            .map_err(|err| anyhow!("Failed to render PDF page {page}: {err}"))?;
+        let (width, height) = (
+            u32::try_from(bitmap.width())?,
+            u32::try_from(bitmap.height())?,
+        );
+        let image = RgbaImage::from_raw(width, height, bitmap.as_rgba_bytes())
+            .ok_or_else(|| anyhow!("PDFium returned a truncated bitmap for page {page}"))?;
+        let mut png = Vec::new();
+        image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png)?;
+        Ok(png)
+    })
+    .await?
+}
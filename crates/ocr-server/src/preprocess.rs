@@ -0,0 +1,134 @@
+//! Optional cleanup of page chunks before they are sent to Lens. Low-contrast raws and
+//! heavy screentones OCR badly; flattening them to gray, stretching the contrast and
+//! thresholding the result often recovers the text.
+//!
+//! Each pipeline includes the steps of the ones before it, and cache entries record which
+//! one produced them so changing the setting re-OCRs pages on their next read.
+
+use image::{DynamicImage, GrayImage, Luma};
+use serde::{Deserialize, Serialize};
+
+/// Fraction of the darkest and of the brightest pixels clipped by the contrast stretch,
+/// so a few specks of pure black or white do not pin the range.
+const STRETCH_CLIP: f64 = 0.01;
+/// Radius in pixels of the neighbourhood each pixel is thresholded against. Comfortably
+/// larger than a screentone dot, smaller than a speech bubble.
+const THRESHOLD_RADIUS: u32 = 15;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Preprocess {
+    /// Chunks are sent as they are.
+    #[default]
+    None,
+    Grayscale,
+    /// Grayscale, then a contrast stretch.
+    Contrast,
+    /// Grayscale, a contrast stretch, then an adaptive threshold to pure black and white.
+    Binarize,
+}
+
+impl Preprocess {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Preprocess::None => "none",
+            Preprocess::Grayscale => "grayscale",
+            Preprocess::Contrast => "contrast",
+            Preprocess::Binarize => "binarize",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "none" => Some(Preprocess::None),
+            "grayscale" => Some(Preprocess::Grayscale),
+            "contrast" => Some(Preprocess::Contrast),
+            "binarize" => Some(Preprocess::Binarize),
+            _ => None,
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == Preprocess::None
+    }
+
+    /// Runs the pipeline over one chunk.
+    pub fn apply(self, image: DynamicImage) -> DynamicImage {
+        if self == Preprocess::None {
+            return image;
+        }
+        let mut gray = image.into_luma8();
+        if matches!(self, Preprocess::Contrast | Preprocess::Binarize) {
+            stretch_contrast(&mut gray);
+        }
+        if self == Preprocess::Binarize {
+            gray = adaptive_threshold(&gray, THRESHOLD_RADIUS);
+        }
+        DynamicImage::ImageLuma8(gray)
+    }
+}
+
+/// Maps the clipped luminance range of `image` onto the full 0-255 range.
+fn stretch_contrast(image: &mut GrayImage) {
+    let mut histogram = [0u64; 256];
+    for pixel in image.pixels() {
+        histogram[pixel.0[0] as usize] += 1;
+    }
+    let clip = (image.width() as f64 * image.height() as f64 * STRETCH_CLIP) as u64;
+    let low = percentile_from(histogram.iter().enumerate(), clip).unwrap_or(0);
+    let high = percentile_from(histogram.iter().enumerate().rev(), clip).unwrap_or(255);
+    if high <= low {
+        return;
+    }
+
+    let range = (high - low) as f64;
+    for pixel in image.pixels_mut() {
+        let value = pixel.0[0].clamp(low, high) - low;
+        pixel.0[0] = (value as f64 * 255.0 / range).round() as u8;
+    }
+}
+
+/// The first luminance, walking the histogram in the given order, past `clip` pixels.
+fn percentile_from<'a>(
+    mut buckets: impl Iterator<Item = (usize, &'a u64)>,
+    clip: u64,
+) -> Option<u8> {
+    let mut seen = 0;
+    buckets.find_map(|(value, count)| {
+        seen += count;
+        (seen > clip).then_some(value as u8)
+    })
+}
+
+/// Sets each pixel to white when it is at least as bright as the mean of the square of
+/// `radius` around it, and to black otherwise. The means come from a summed-area table so
+/// the cost does not grow with the radius.
+fn adaptive_threshold(image: &GrayImage, radius: u32) -> GrayImage {
+    let (width, height) = image.dimensions();
+    let stride = width as usize + 1;
+    let mut sums = vec![0u64; stride * (height as usize + 1)];
+    for y in 0..height as usize {
+        let mut row = 0u64;
+        for x in 0..width as usize {
+            row += image.get_pixel(x as u32, y as u32).0[0] as u64;
+            sums[(y + 1) * stride + x + 1] = sums[y * stride + x + 1] + row;
+        }
+    }
+
+    GrayImage::from_fn(width, height, |x, y| {
+        let left = x.saturating_sub(radius) as usize;
+        let top = y.saturating_sub(radius) as usize;
+        let right = (x + radius + 1).min(width) as usize;
+        let bottom = (y + radius + 1).min(height) as usize;
+        let total = sums[bottom * stride + right] + sums[top * stride + left]
+            - sums[top * stride + right]
+            - sums[bottom * stride + left];
+        let count = ((right - left) * (bottom - top)) as u64;
+        let value = image.get_pixel(x, y).0[0] as u64;
+        if value * count >= total {
+            Luma([255])
+        } else {
+            Luma([0])
+        }
+    })
+}
@@ -0,0 +1,154 @@
+//! Proxy for the server's outbound requests: page image downloads and Lens calls.
+//!
+//! The saved setting wins, then `MANATAN_OCR_PROXY`. With neither, Lens calls keep using
+//! Suwayomi's SOCKS proxy setting and page fetches go out directly.
+
+use std::time::Duration;
+
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+
+pub const PROXY_ENV: &str = "MANATAN_OCR_PROXY";
+pub const NO_PROXY_ENV: &str = "MANATAN_OCR_NO_PROXY";
+/// Pages are fetched from the local Suwayomi server, which a corporate proxy cannot reach.
+pub const DEFAULT_NO_PROXY: &str = "localhost,127.0.0.1,::1";
+const SCHEMES: &[&str] = &["http", "https", "socks5", "socks5h"];
+/// Stands in for a proxy password in logs, errors and `GET /proxy-config`.
+const MASK: &str = "***";
+/// How long connecting to the proxy may take, so an unreachable proxy fails fast with its
+/// own error instead of running into the page deadline.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(default)]
+pub struct ProxyConfig {
+    /// `http`, `https`, `socks5` or `socks5h` URL, credentials included if needed.
+    pub url: Option<String>,
+    /// Comma-separated hosts reached directly. Unset uses `MANATAN_OCR_NO_PROXY`, then
+    /// [`DEFAULT_NO_PROXY`]; an empty string sends everything through the proxy.
+    pub no_proxy: Option<String>,
+}
+
+impl ProxyConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        let Some(url) = self.url.as_deref().filter(|url| !url.trim().is_empty()) else {
+            return Ok(());
+        };
+        let parsed =
+            reqwest::Url::parse(url).map_err(|e| format!("invalid proxy url {url:?}: {e}"))?;
+        if !SCHEMES.contains(&parsed.scheme()) {
+            return Err(format!(
+                "proxy url must use one of {}, got {}",
+                SCHEMES.join(", "),
+                parsed.scheme()
+            ));
+        }
+        if parsed.host_str().is_none_or(str::is_empty) {
+            return Err("proxy url has no host".to_string());
+        }
+        Ok(())
+    }
+
+    /// This config as shown to clients, with the proxy password masked.
+    pub fn masked(&self) -> Self {
+        Self {
+            url: self.url.as_deref().map(redact),
+            ..self.clone()
+        }
+    }
+
+    /// `self` as sent back by a client that was shown [`masked`](Self::masked): a masked
+    /// password stands for the one in `saved`, as long as the rest of the URL is unchanged.
+    pub fn unmask(mut self, saved: &Self) -> Self {
+        if let (Some(url), Some(saved_url)) = (&self.url, &saved.url)
+            && let Ok(parsed) = reqwest::Url::parse(url)
+            && parsed.password() == Some(MASK)
+            && redact(saved_url) == parsed.as_str()
+        {
+            self.url = Some(saved_url.clone());
+        }
+        self
+    }
+
+    /// The proxy in effect: the saved URL, else `MANATAN_OCR_PROXY`.
+    pub fn effective_url(&self) -> Option<String> {
+        non_empty(self.url.clone()).or_else(|| non_empty(std::env::var(PROXY_ENV).ok()))
+    }
+
+    fn effective_no_proxy(&self) -> String {
+        self.no_proxy
+            .clone()
+            .or_else(|| std::env::var(NO_PROXY_ENV).ok())
+            .unwrap_or_else(|| DEFAULT_NO_PROXY.to_string())
+    }
+
+    /// Whether requests to `host` skip the proxy. Entries match the host itself and its
+    /// subdomains; `*` matches everything.
+    pub fn bypasses(&self, host: &str) -> bool {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        self.effective_no_proxy()
+            .split(',')
+            .map(|entry| entry.trim().trim_start_matches('.'))
+            .filter(|entry| !entry.is_empty())
+            .any(|entry| {
+                entry == "*"
+                    || host.eq_ignore_ascii_case(entry)
+                    || host
+                        .to_ascii_lowercase()
+                        .ends_with(&format!(".{}", entry.to_ascii_lowercase()))
+            })
+    }
+
+    /// A client for page downloads that goes through the proxy, if one is set. Each
+    /// request, body included, is cut off after `timeout`.
+    pub fn http_client(&self, timeout: Duration) -> anyhow::Result<reqwest::Client> {
+        let builder = reqwest::Client::builder().timeout(timeout);
+        let Some(url) = self.effective_url() else {
+            return builder
+                .build()
+                .map_err(|e| anyhow!("Failed to build client: {e}"));
+        };
+        let proxy = reqwest::Proxy::all(&url)
+            .map_err(|e| anyhow!("Invalid proxy {}: {e}", redact(&url)))?
+            .no_proxy(reqwest::NoProxy::from_string(&self.effective_no_proxy()));
+        builder
+            .proxy(proxy)
+            .connect_timeout(CONNECT_TIMEOUT)
+            .build()
+            .map_err(|e| anyhow!("Failed to build client for proxy {}: {e}", redact(&url)))
+    }
+
+    /// Wraps a request error so a proxy that cannot be reached is named as the cause. The
+    /// reqwest error stays the source, so retries still classify it.
+    pub fn describe_error(&self, err: reqwest::Error) -> anyhow::Error {
+        let through_proxy = err
+            .url()
+            .and_then(reqwest::Url::host_str)
+            .is_some_and(|host| !self.bypasses(host));
+        let proxy = self
+            .effective_url()
+            .filter(|_| through_proxy && (err.is_connect() || err.is_timeout()));
+        match proxy {
+            Some(url) => {
+                let message = format!("Could not connect through proxy {}", redact(&url));
+                anyhow::Error::new(err).context(message)
+            }
+            None => err.into(),
+        }
+    }
+}
+
+/// `url` with any password replaced, for logs and error messages.
+pub fn redact(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(mut parsed) if parsed.password().is_some() => {
+            let _ = parsed.set_password(Some(MASK));
+            parsed.to_string()
+        }
+        _ => url.to_string(),
+    }
+}
+
+fn non_empty(value: Option<String>) -> Option<String> {
+    value.filter(|value| !value.trim().is_empty())
+}
@@ -0,0 +1,177 @@
+//! Keeps the OCR cache from growing without bound by deleting its oldest rows.
+//!
+//! Rows are removed oldest `created_at` first until the age and size limits both hold.
+//! Contexts read recently are never touched: one page of a context (usually a chapter)
+//! read within `protect_recent_days` keeps every page stored under that context.
+
+use std::time::Duration;
+
+use rusqlite::{TransactionBehavior, params};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::state::{AppState, now_unix};
+
+const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+/// Longest day count accepted for any prune limit; about a century.
+pub const MAX_DAYS: u64 = 36_500;
+
+/// How often the scheduler re-reads the config to see whether pruning is due.
+const SCHEDULER_TICK: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PruneOptions {
+    /// Rows created more than this many days ago are removed.
+    pub older_than_days: Option<u64>,
+    /// Oldest rows are removed until the cached OCR data fits in this many bytes.
+    pub max_total_bytes: Option<u64>,
+    /// Rows read within this many days are kept whatever their age.
+    pub protect_recent_days: u64,
+    /// Rebuilds the database file afterwards so the freed space goes back to the disk.
+    pub vacuum: bool,
+}
+
+impl Default for PruneOptions {
+    fn default() -> Self {
+        Self {
+            older_than_days: None,
+            max_total_bytes: None,
+            protect_recent_days: 14,
+            vacuum: false,
+        }
+    }
+}
+
+impl PruneOptions {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.older_than_days.is_none() && self.max_total_bytes.is_none() {
+            return Err("Give older_than_days and/or max_total_bytes".to_string());
+        }
+        for (name, days) in [
+            ("older_than_days", self.older_than_days),
+            ("protect_recent_days", Some(self.protect_recent_days)),
+        ] {
+            if let Some(days) = days
+                && days > MAX_DAYS
+            {
+                return Err(format!("{name} must be at most {MAX_DAYS}, got {days}"));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PruneReport {
+    pub deleted_rows: usize,
+    pub freed_bytes: u64,
+    pub remaining_bytes: u64,
+}
+
+/// Deletes the oldest unprotected cache rows until `options` are met.
+pub fn prune(state: &AppState, options: &PruneOptions) -> anyhow::Result<PruneReport> {
+    let now = now_unix();
+    let protected_since = days_before(now, options.protect_recent_days);
+    let created_before = options.older_than_days.map(|days| days_before(now, days));
+
+    let mut conn = state.conn()?;
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let mut total_bytes: u64 = tx.query_row(
+        "SELECT COALESCE(SUM(length(data) + COALESCE(length(raw), 0)), 0) FROM ocr_cache",
+        [],
+        |row| row.get::<_, i64>(0),
+    )? as u64;
+
+    let candidates = {
+        let mut stmt = tx.prepare(
+            "SELECT cache_key, created_at, length(data) + COALESCE(length(raw), 0) FROM ocr_cache
+             WHERE source != 'manual'
+               AND context NOT IN (SELECT context FROM ocr_cache WHERE last_accessed_at >= ?)
+             ORDER BY created_at ASC",
+        )?;
+        let rows = stmt.query_map(params![protected_since], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, i64>(2)? as u64,
+            ))
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut report = PruneReport::default();
+    for (cache_key, created_at, size) in candidates {
+        let too_old = created_before.is_some_and(|cutoff| created_at < cutoff);
+        let over_budget = options.max_total_bytes.is_some_and(|max| total_bytes > max);
+        // Candidates come oldest first, so once neither limit applies none of the
+        // younger rows will either.
+        if !too_old && !over_budget {
+            break;
+        }
+        tx.execute(
+            "DELETE FROM chapter_cache WHERE cache_key = ?",
+            params![cache_key],
+        )?;
+        tx.execute(
+            "DELETE FROM ocr_cache WHERE cache_key = ?",
+            params![cache_key],
+        )?;
+        tx.execute(
+            "DELETE FROM ocr_spreads WHERE cache_key = ?",
+            params![cache_key],
+        )?;
+        report.deleted_rows += 1;
+        report.freed_bytes += size;
+        total_bytes = total_bytes.saturating_sub(size);
+    }
+    tx.commit()?;
+    report.remaining_bytes = total_bytes;
+
+    if options.vacuum && report.deleted_rows > 0 {
+        conn.execute_batch("VACUUM")?;
+    }
+
+    info!(
+        "[PRUNE] Removed {} cache row(s), freed {} bytes, {} bytes remain",
+        report.deleted_rows, report.freed_bytes, report.remaining_bytes
+    );
+    Ok(report)
+}
+
+/// `now` minus `days`, saturating instead of overflowing on absurd day counts.
+fn days_before(now: i64, days: u64) -> i64 {
+    now.saturating_sub(
+        i64::try_from(days)
+            .unwrap_or(i64::MAX)
+            .saturating_mul(SECONDS_PER_DAY),
+    )
+}
+
+/// Runs [`prune`] with the configured `auto_prune` limits every `prune_interval_hours`.
+/// The config is re-read on every tick, so turning pruning on or off needs no restart.
+pub fn spawn_scheduler(state: &AppState) {
+    let state = state.clone();
+    tokio::spawn(async move {
+        let mut last_run = now_unix();
+        loop {
+            tokio::time::sleep(SCHEDULER_TICK).await;
+            let config = state.ocr_config();
+            let Some(options) = config.auto_prune else {
+                continue;
+            };
+            let interval = config.prune_interval_hours.max(1) as i64 * 60 * 60;
+            if now_unix() - last_run < interval {
+                continue;
+            }
+            last_run = now_unix();
+
+            let prune_state = state.clone();
+            match tokio::task::spawn_blocking(move || prune(&prune_state, &options)).await {
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => warn!("[PRUNE] Scheduled prune failed: {err}"),
+                Err(err) => warn!("[PRUNE] Scheduled prune panicked: {err}"),
+            }
+        }
+    });
+}
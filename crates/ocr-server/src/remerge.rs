@@ -0,0 +1,279 @@
+//! Merges cached pages again from the raw Lens lines stored with them, so a new
+//! `MergeConfig` reaches pages that were already read without calling Lens.
+//!
+//! Manual pages and pages corrected by hand are left alone, as are pages cached before raw
+//! lines were kept. Only `data` and `orientation` are rewritten; `created_at` stays.
+
+use rusqlite::{OptionalExtension, params};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tracing::{info, warn};
+
+use crate::{
+    language::OcrLanguage,
+    locks,
+    logic::{self, CacheKeyConfig, RawPage},
+    merge::MergeConfig,
+    state::{AppState, JobProgress, OcrConfig, PreprocessProgress, PreprocessStatus, now_unix},
+};
+
+/// Key of the running re-merge in `active_chapter_jobs`; one runs at a time.
+pub const JOB_KEY: &str = "remerge";
+const JOB_CLASS: &str = "ocr-remerge";
+const LAST_REPORT_KEY: &str = "last_remerge";
+
+/// Body of `POST /remerge`. `url` wins over `context`, which wins over `all`.
+#[derive(Debug, Default, Deserialize)]
+pub struct RemergeRequest {
+    #[serde(default)]
+    pub all: bool,
+    pub context: Option<String>,
+    /// Match every context starting with `context` instead of only an exact match.
+    #[serde(default)]
+    pub match_prefix: bool,
+    /// A page URL, or a chapter's base URL to re-merge every page cached for it.
+    pub url: Option<String>,
+    #[serde(alias = "lang")]
+    pub language: Option<OcrLanguage>,
+}
+
+impl RemergeRequest {
+    /// The pages the request covers, keyed under `key_config`; `None` when it names none.
+    pub fn scope(&self, key_config: &CacheKeyConfig) -> Option<RemergeScope> {
+        if let Some(url) = self.url.as_deref().filter(|url| !url.is_empty()) {
+            let language = Some(self.language.unwrap_or_default());
+            let key = logic::get_cache_key(url, language, key_config);
+            return Some(RemergeScope::Url(key));
+        }
+        if let Some(context) = self
+            .context
+            .as_deref()
+            .filter(|context| !context.is_empty())
+        {
+            return Some(RemergeScope::Context {
+                context: context.to_string(),
+                prefix: self.match_prefix,
+            });
+        }
+        self.all.then_some(RemergeScope::All)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RemergeScope {
+    All,
+    Context {
+        context: String,
+        prefix: bool,
+    },
+    /// A page's cache key, or a chapter key whose pages are linked in `chapter_cache`.
+    Url(String),
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RemergeReport {
+    /// Pages in scope, including the skipped ones.
+    pub scanned: usize,
+    pub changed: usize,
+    pub unchanged: usize,
+    /// Manual or hand-corrected pages, and pages without raw lines.
+    pub skipped: usize,
+    pub failed: usize,
+    pub finished_at: i64,
+}
+
+/// The keys of the pages in `scope` that can be re-merged, and how many others it
+/// matched.
+pub fn candidates(state: &AppState, scope: &RemergeScope) -> anyhow::Result<(Vec<String>, usize)> {
+    let (filter, value) = match scope {
+        RemergeScope::All => ("?1 IS NULL", None),
+        RemergeScope::Context {
+            context,
+            prefix: false,
+        } => ("context = ?1", Some(context.as_str())),
+        RemergeScope::Context {
+            context,
+            prefix: true,
+        } => (
+            "substr(context, 1, length(?1)) = ?1",
+            Some(context.as_str()),
+        ),
+        RemergeScope::Url(key) => (
+            "(cache_key = ?1
+              OR cache_key IN (SELECT cache_key FROM chapter_cache WHERE chapter_key = ?1))",
+            Some(key.as_str()),
+        ),
+    };
+    let conn = state.conn()?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT cache_key, raw IS NOT NULL AND source != 'manual' AND edited_at IS NULL
+         FROM ocr_cache WHERE {filter} ORDER BY cache_key"
+    ))?;
+    let rows = stmt
+        .query_map(params![value], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    let (eligible, skipped): (Vec<_>, Vec<_>) = rows.into_iter().partition(|(_, ok)| *ok);
+    Ok((
+        eligible.into_iter().map(|(key, _)| key).collect(),
+        skipped.len(),
+    ))
+}
+
+/// Re-merges one page under `config`'s merge thresholds and text normalization.
+/// `Some(true)` when its results changed, `None` when it can't be re-merged.
+pub fn remerge_page(
+    state: &AppState,
+    cache_key: &str,
+    config: &OcrConfig,
+) -> anyhow::Result<Option<bool>> {
+    const ELIGIBLE: &str = "raw IS NOT NULL AND source != 'manual' AND edited_at IS NULL";
+    let conn = state.conn()?;
+    let row = conn
+        .query_row(
+            &format!("SELECT raw, data FROM ocr_cache WHERE cache_key = ? AND {ELIGIBLE}"),
+            params![cache_key],
+            |row| Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, Vec<u8>>(1)?)),
+        )
+        .optional()?;
+    let Some((raw, data)) = row else {
+        return Ok(None);
+    };
+    let raw: RawPage = serde_json::from_slice(&raw)?;
+    let orientation = raw.orientation(&config.merge);
+    let mut results = raw.merge(&MergeConfig {
+        orientation: Some(orientation),
+        ..config.merge.clone()
+    });
+    config.normalization.apply(&mut results);
+    let merged = serde_json::to_vec(&results)?;
+    if merged == data {
+        return Ok(Some(false));
+    }
+    conn.execute(
+        &format!(
+            "UPDATE ocr_cache SET data = ?, orientation = ?, last_processed_at = ?
+             WHERE cache_key = ? AND {ELIGIBLE}"
+        ),
+        params![merged, orientation.as_str(), now_unix(), cache_key],
+    )?;
+    Ok(Some(true))
+}
+
+/// Re-merges `keys` with the current config, series orientation overrides included.
+/// `on_page` gets the pages done and changed so far after each one.
+pub fn remerge(
+    state: &AppState,
+    keys: &[String],
+    skipped: usize,
+    mut on_page: impl FnMut(usize, usize),
+) -> RemergeReport {
+    let config = state.ocr_config();
+    let mut report = RemergeReport {
+        scanned: keys.len() + skipped,
+        skipped,
+        ..RemergeReport::default()
+    };
+    for (index, key) in keys.iter().enumerate() {
+        match remerge_page(state, key, &config.for_page(key)) {
+            Ok(Some(true)) => report.changed += 1,
+            Ok(Some(false)) => report.unchanged += 1,
+            Ok(None) => report.skipped += 1,
+            Err(err) => {
+                warn!("Failed to re-merge {key}: {err}");
+                report.failed += 1;
+            }
+        }
+        on_page(index + 1, report.changed);
+    }
+    report.finished_at = now_unix();
+    report
+}
+
+/// Starts re-merging `keys` in the background and returns how many there are, or `None`
+/// when a re-merge is already running. Progress is listed under [`JOB_KEY`] in
+/// `active_chapter_jobs` and the report is kept for [`last_report`].
+pub fn spawn(state: &AppState, keys: Vec<String>, skipped: usize) -> Option<usize> {
+    let total = keys.len();
+    let (updates, receiver) = watch::channel(PreprocessProgress {
+        total,
+        ..Default::default()
+    });
+    {
+        let mut jobs = locks::write(&state.active_chapter_jobs);
+        if jobs.contains_key(JOB_KEY) {
+            return None;
+        }
+        jobs.insert(
+            JOB_KEY.to_string(),
+            JobProgress {
+                current: 0,
+                total,
+                adaptive_delay_ms: 0,
+                throttled_since: None,
+                updates: receiver,
+            },
+        );
+    }
+
+    let handle = manatan_jobs::track(JOB_CLASS, "ocr", "Re-merge cached pages");
+    let state = state.clone();
+    tokio::task::spawn_blocking(move || {
+        handle.start();
+        let report = remerge(&state, &keys, skipped, |current, changed| {
+            if let Some(progress) = locks::write(&state.active_chapter_jobs).get_mut(JOB_KEY) {
+                progress.current = current;
+            }
+            handle.set_progress(current, total);
+            updates.send_modify(|progress| {
+                progress.current = current;
+                progress.processed = changed;
+            });
+        });
+        updates.send_modify(|progress| {
+            progress.failed = report.failed;
+            progress.status = if report.failed == 0 {
+                PreprocessStatus::Done
+            } else {
+                PreprocessStatus::Failed
+            };
+        });
+        info!(
+            "Re-merged {} pages: {} changed, {} unchanged, {} skipped, {} failed",
+            report.scanned, report.changed, report.unchanged, report.skipped, report.failed
+        );
+        save_report(&state, &report);
+        locks::write(&state.active_chapter_jobs).remove(JOB_KEY);
+    });
+    Some(total)
+}
+
+fn save_report(state: &AppState, report: &RemergeReport) {
+    let Ok(conn) = state.conn() else {
+        warn!("Failed to get DB connection for the re-merge report");
+        return;
+    };
+    let Ok(json) = serde_json::to_string(report) else {
+        return;
+    };
+    let _ = conn.execute(
+        "INSERT OR REPLACE INTO metadata (key, value) VALUES (?, ?)",
+        params![LAST_REPORT_KEY, json],
+    );
+}
+
+/// The report of the last finished re-merge, if any.
+pub fn last_report(state: &AppState) -> Option<RemergeReport> {
+    let conn = state.conn().ok()?;
+    let json: String = conn
+        .query_row(
+            "SELECT value FROM metadata WHERE key = ?",
+            params![LAST_REPORT_KEY],
+            |row| row.get(0),
+        )
+        .optional()
+        .ok()
+        .flatten()?;
+    serde_json::from_str(&json).ok()
+}
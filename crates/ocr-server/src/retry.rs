@@ -0,0 +1,169 @@
+//! Retry policy for page fetches and Lens calls.
+//!
+//! Only failures that are likely to go away on their own (timeouts, dropped connections,
+//! 429 and 5xx responses) are retried. Missing pages and undecodable images fail at once.
+
+use std::{
+    collections::hash_map::RandomState,
+    fmt,
+    hash::{BuildHasher, Hasher},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::state::OcrConfig;
+
+/// Upper bound for a single backoff sleep, however many attempts are configured.
+const MAX_BACKOFF: Duration = Duration::from_secs(15);
+
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// Total tries, including the first one.
+    pub attempts: u32,
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &OcrConfig) -> Self {
+        Self {
+            attempts: config.retry_attempts.max(1),
+            base_delay: Duration::from_millis(config.retry_base_delay_ms),
+        }
+    }
+
+    /// Sleep before retry number `retry` (1 for the first retry): the base delay doubled
+    /// per retry and capped, then scaled into `50%..100%` so parallel pages spread out.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exponent = retry.saturating_sub(1).min(16);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(MAX_BACKOFF);
+        delay.mul_f64(0.5 + jitter() / 2.0)
+    }
+}
+
+/// Uniform-ish value in `0.0..1.0`; good enough to desynchronize retries.
+fn jitter() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+    );
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Per-call time limits, within the page deadline. Persisted in
+/// [`OcrConfig`](crate::state::OcrConfig) and exposed at `/timeout-settings`.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct TimeoutSettings {
+    /// Limit for downloading one page image, so a dead source fails instead of hanging
+    /// the request or job.
+    pub fetch_secs: u64,
+    /// Limit for one Lens chunk call or Tesseract run.
+    pub ocr_call_secs: u64,
+}
+
+impl Default for TimeoutSettings {
+    fn default() -> Self {
+        Self {
+            fetch_secs: 30,
+            ocr_call_secs: 60,
+        }
+    }
+}
+
+impl TimeoutSettings {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, secs) in [
+            ("fetch_secs", self.fetch_secs),
+            ("ocr_call_secs", self.ocr_call_secs),
+        ] {
+            if !(1..=600).contains(&secs) {
+                return Err(format!("{name} must be between 1 and 600, got {secs}"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn fetch(&self) -> Duration {
+        Duration::from_secs(self.fetch_secs.max(1))
+    }
+
+    pub fn ocr_call(&self) -> Duration {
+        Duration::from_secs(self.ocr_call_secs.max(1))
+    }
+}
+
+/// A call that ran past its [`TimeoutSettings`] limit. Always retried; a Lens call that
+/// times out is reported as a [`LensCallError`] carrying this message instead, since
+/// Lens calls are retried per chunk.
+#[derive(Debug)]
+pub struct CallTimeout {
+    pub call: &'static str,
+    pub after: Duration,
+}
+
+impl fmt::Display for CallTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} timed out after {}s", self.call, self.after.as_secs())
+    }
+}
+
+impl std::error::Error for CallTimeout {}
+
+/// A failed Lens call. The client only reports errors as text, so this keeps the message
+/// around for classification.
+#[derive(Debug)]
+pub struct LensCallError(pub String);
+
+impl fmt::Display for LensCallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Failed process_image_bytes: {}", self.0)
+    }
+}
+
+impl std::error::Error for LensCallError {}
+
+/// Whether trying again has a reasonable chance of succeeding.
+pub fn is_retryable(err: &anyhow::Error) -> bool {
+    if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+        return match err.status() {
+            Some(status) => status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+            None => err.is_timeout() || err.is_connect() || err.is_request() || err.is_body(),
+        };
+    }
+    if let Some(LensCallError(message)) = err.downcast_ref::<LensCallError>() {
+        return is_transient_message(message);
+    }
+    if err.downcast_ref::<CallTimeout>().is_some() {
+        return true;
+    }
+    false
+}
+
+fn is_transient_message(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    [
+        "429",
+        "500",
+        "502",
+        "503",
+        "504",
+        "too many requests",
+        "resource_exhausted",
+        "unavailable",
+        "timed out",
+        "timeout",
+        "connection reset",
+        "connection refused",
+        "connection closed",
+    ]
+    .iter()
+    .any(|signal| lower.contains(signal))
+}
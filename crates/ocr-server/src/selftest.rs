@@ -0,0 +1,160 @@
+use std::{
+    sync::RwLock,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+use tracing::{info, warn};
+
+use crate::{
+    backend::OcrBackend,
+    language::OcrLanguage,
+    logic,
+    state::{AppState, OcrConfig},
+};
+
+/// A tiny black-on-white PNG, small enough for a single Lens chunk.
+pub(crate) const TEST_IMAGE: &[u8] = include_bytes!("../assets/self-test.png");
+/// The Lens probe gets its own short deadline so a blocked network fails fast instead
+/// of waiting out the page deadline.
+const LENS_PROBE_DEADLINE_SECS: u64 = 20;
+
+lazy_static! {
+    /// Most recent report, shared with the launcher's aggregate health endpoint.
+    static ref LAST_REPORT: RwLock<Option<SelfTestReport>> = RwLock::new(None);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SelfTestReport {
+    pub ran_at: i64,
+    pub healthy: bool,
+    pub checks: Vec<CheckResult>,
+}
+
+impl SelfTestReport {
+    /// Name of the first failing check, if any.
+    pub fn failing_check(&self) -> Option<&'static str> {
+        self.checks
+            .iter()
+            .find(|check| check.status == CheckStatus::Failed)
+            .map(|check| check.name)
+    }
+}
+
+/// Returns the report from the last completed run, if the self-test has run yet.
+pub fn latest() -> Option<SelfTestReport> {
+    LAST_REPORT.read().ok().and_then(|report| report.clone())
+}
+
+/// Checks that the OCR server can actually do its job: decode images, reach Lens (unless
+/// disabled for offline installs) and write to its cache. The result is stored for
+/// [`latest`] and returned.
+pub async fn run(state: &AppState) -> SelfTestReport {
+    let config = state.ocr_config();
+    let mut checks = Vec::with_capacity(3);
+
+    checks.push(
+        timed("decode", async {
+            logic::decode_image(TEST_IMAGE).map(|_| ())
+        })
+        .await,
+    );
+
+    if config.self_test_lens {
+        let probe_config = OcrConfig {
+            deadline_secs: LENS_PROBE_DEADLINE_SECS,
+            // Report Lens as it is right now rather than after a round of backoff.
+            retry_attempts: 1,
+            ..config
+        };
+        checks.push(
+            timed("lens", async {
+                let _permit = state.lens_limiter.acquire(OcrBackend::Lens).await;
+                logic::process_uploaded_image(
+                    TEST_IMAGE,
+                    None,
+                    None,
+                    &state.credentials,
+                    None,
+                    OcrLanguage::default(),
+                    OcrBackend::Lens,
+                    &probe_config,
+                )
+                .await
+                .map(|_| ())
+            })
+            .await,
+        );
+    } else {
+        checks.push(CheckResult {
+            name: "lens",
+            status: CheckStatus::Skipped,
+            latency_ms: 0,
+            error: None,
+        });
+    }
+
+    let cache_state = state.clone();
+    checks.push(
+        timed("cache", async move {
+            tokio::task::spawn_blocking(move || cache_state.probe_cache_write()).await?
+        })
+        .await,
+    );
+
+    let report = SelfTestReport {
+        ran_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs() as i64,
+        healthy: checks
+            .iter()
+            .all(|check| check.status != CheckStatus::Failed),
+        checks,
+    };
+    match report.failing_check() {
+        None => info!("OCR self-test passed"),
+        Some(name) => warn!("OCR self-test failed at check '{name}'"),
+    }
+    if let Ok(mut last) = LAST_REPORT.write() {
+        *last = Some(report.clone());
+    }
+    report
+}
+
+async fn timed(name: &'static str, check: impl Future<Output = anyhow::Result<()>>) -> CheckResult {
+    let started = Instant::now();
+    let result = check.await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+    match result {
+        Ok(()) => CheckResult {
+            name,
+            status: CheckStatus::Passed,
+            latency_ms,
+            error: None,
+        },
+        Err(err) => CheckResult {
+            name,
+            status: CheckStatus::Failed,
+            latency_ms,
+            error: Some(err.to_string()),
+        },
+    }
+}
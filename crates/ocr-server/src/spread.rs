@@ -0,0 +1,92 @@
+//! Double-page spreads OCR'd as one image, so a bubble crossing the gutter is read whole
+//! instead of as two halves. Results are cached under a key combining both pages, next to
+//! the position of the gutter so each box can be attributed to its page again on a hit.
+
+use std::io::Cursor;
+
+use image::{DynamicImage, ImageFormat};
+use rusqlite::{OptionalExtension, params};
+use serde::Serialize;
+
+use crate::{imaging, logic::OcrResult, state::AppState};
+
+/// The source page a box sits on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SpreadPage {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SpreadResult {
+    #[serde(flatten)]
+    pub result: OcrResult,
+    /// The page most of the box lies on.
+    pub page: SpreadPage,
+}
+
+/// Key for the spread of two page keys. Swapping the pages is another spread. The page
+/// keys' `?` are escaped, so [`CacheKeyConfig::normalize`](crate::logic::CacheKeyConfig::normalize),
+/// which filters whatever follows the first `?`, leaves spread keys alone.
+pub fn cache_key(left_key: &str, right_key: &str) -> String {
+    let escape = |key: &str| key.replace('?', "%3F");
+    format!("spread:{}|{}", escape(left_key), escape(right_key))
+}
+
+/// Drops the gutters of spreads no longer in the cache, after `ocr_cache` rows are deleted.
+pub(crate) const DELETE_ORPHAN_SPLITS: &str =
+    "DELETE FROM ocr_spreads WHERE cache_key NOT IN (SELECT cache_key FROM ocr_cache)";
+
+/// Composites the two pages left to right and encodes them as a PNG for the OCR pipeline.
+/// Also returns the gutter's position as a fraction of the spread's width.
+pub fn stitch(left: &DynamicImage, right: &DynamicImage) -> anyhow::Result<(Vec<u8>, f64)> {
+    let spread = imaging::stitch_horizontally(left, right);
+    let split = left.width() as f64 / spread.width().max(1) as f64;
+    let mut bytes = Cursor::new(Vec::new());
+    spread.write_to(&mut bytes, ImageFormat::Png)?;
+    Ok((bytes.into_inner(), split))
+}
+
+/// Tags each result with the page holding most of its box, `split` being the gutter's
+/// position in spread-normalized coordinates.
+pub fn assign_pages(results: Vec<OcrResult>, split: f64) -> Vec<SpreadResult> {
+    results
+        .into_iter()
+        .map(|result| {
+            let bbox = &result.tight_bounding_box;
+            let left = (split.min(bbox.x + bbox.width) - bbox.x).max(0.0);
+            let right = (bbox.x + bbox.width - split.max(bbox.x)).max(0.0);
+            let page = if right > left {
+                SpreadPage::Right
+            } else {
+                SpreadPage::Left
+            };
+            SpreadResult { result, page }
+        })
+        .collect()
+}
+
+/// Remembers where the gutter of a cached spread is.
+pub fn record_split(state: &AppState, cache_key: &str, split: f64) -> anyhow::Result<()> {
+    state.conn()?.execute(
+        "INSERT OR REPLACE INTO ocr_spreads (cache_key, split) VALUES (?, ?)",
+        params![cache_key, split],
+    )?;
+    Ok(())
+}
+
+/// The gutter of a cached spread, `None` when the spread was never cached.
+pub fn split(state: &AppState, cache_key: &str) -> Option<f64> {
+    state
+        .conn()
+        .ok()?
+        .query_row(
+            "SELECT split FROM ocr_spreads WHERE cache_key = ?",
+            params![cache_key],
+            |row| row.get(0),
+        )
+        .optional()
+        .ok()
+        .flatten()
+}
@@ -1,37 +1,371 @@
 use std::{
-    collections::HashMap,
-    path::{Path, PathBuf},
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    path::{Component, Path, PathBuf},
     sync::{Arc, RwLock, atomic::AtomicUsize},
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use r2d2::Pool;
+use r2d2::{Pool, PooledConnection};
 use r2d2_sqlite::SqliteConnectionManager;
 use rusqlite::{OptionalExtension, params};
 use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, watch};
 use tracing::{info, warn};
 
-use crate::logic::OcrResult;
+use crate::{
+    backend::OcrBackend,
+    context::ContextResolver,
+    credentials::{self, StoredCredentials},
+    export::{ExportFilter, ExportManifest},
+    inflight::InFlight,
+    jobs::{JobQueue, JobSettings},
+    logic::{self, CacheKeyConfig, OcrOutcome, OcrResult, RawPage},
+    merge::{MergeConfig, OrientationHint, TextOrientation},
+    normalize::TextNormalization,
+    page_events::{self, PageStatus, PageUpdate},
+    pdf,
+    preprocess::Preprocess,
+    proxy::ProxyConfig,
+    prune::PruneOptions,
+    retry::TimeoutSettings,
+    spread,
+    throttle::LensLimiter,
+};
+
+pub const CACHE_DB_FILE_NAME: &str = "ocr-cache.db";
+
+/// Hidden folder inside the local novel directory where the novel server keeps
+/// per-book metadata and extracted EPUB assets.
+const NOVEL_METADATA_DIR_NAME: &str = ".manatan-metadata";
 
-#[derive(Clone, Copy, Serialize, Debug)]
+#[derive(Clone, Serialize, Debug)]
 pub struct JobProgress {
     pub current: usize,
     pub total: usize,
+    pub adaptive_delay_ms: u64,
+    pub throttled_since: Option<i64>,
+    /// Live feed of the job's progress for `/preprocess-progress` subscribers.
+    #[serde(skip)]
+    pub updates: watch::Receiver<PreprocessProgress>,
+}
+
+#[derive(Clone, Copy, Serialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PreprocessStatus {
+    #[default]
+    Running,
+    Done,
+    Failed,
+}
+
+/// One step of a chapter preprocess job, as streamed to the reader.
+#[derive(Clone, Serialize, Debug, Default)]
+pub struct PreprocessProgress {
+    #[serde(skip)]
+    pub status: PreprocessStatus,
+    pub current: usize,
+    pub total: usize,
+    pub last_page: Option<String>,
+    /// Pages already cached when the job started, which it did not fetch again.
+    pub skipped: usize,
+    /// Pages OCRed and cached by this job.
+    pub processed: usize,
+    /// Of those, pages cached empty without OCR because they looked blank.
+    pub blank: usize,
+    pub failed: usize,
+    /// Pages being downloaded.
+    pub fetching: usize,
+    /// Downloaded pages waiting for the OCR stage.
+    pub buffered: usize,
+    /// Pages being OCRed.
+    pub recognizing: usize,
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub pool: DbPool,
     pub cache_dir: PathBuf,
+    pub local_novel_path: PathBuf,
     pub active_jobs: Arc<AtomicUsize>,
     pub requests_processed: Arc<AtomicUsize>,
     pub active_chapter_jobs: Arc<RwLock<HashMap<String, JobProgress>>>,
+    /// Uncached pages currently being OCRed, keyed by cache key, so identical concurrent
+    /// requests share one upstream run.
+    pub in_flight_ocr: Arc<InFlight<Result<OcrOutcome, String>>>,
+    pub lens_limiter: Arc<LensLimiter>,
+    /// Chapters waiting for a preprocess worker.
+    pub job_queue: JobQueue,
+    pub contexts: Arc<ContextResolver>,
+    /// Whether the `ocr_text` full-text index is available; without FTS5 in the linked
+    /// SQLite, searches scan the cached JSON instead.
+    pub text_index: bool,
+    /// How long [`conn`](Self::conn) waits for a free cache connection.
+    pub pool_wait: Duration,
+    /// Announces pages as they are cached or fail, for `GET /ws`.
+    pub page_updates: broadcast::Sender<PageUpdate>,
+    /// The saved [`OcrConfig::cache_key`], so building a key needs no database read.
+    cache_key_config: Arc<RwLock<CacheKeyConfig>>,
+    /// Source credentials saved at `/credentials`, loaded once and after every change.
+    pub credentials: StoredCredentials,
+}
+
+/// Every cache connection stayed in use for the whole pool wait. Lookups return it rather
+/// than an empty answer, so a busy cache is never mistaken for a miss.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbBusy;
+
+impl std::fmt::Display for DbBusy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("The OCR cache database is busy; try again shortly")
+    }
+}
+
+impl std::error::Error for DbBusy {}
+
+/// User-tunable OCR settings, persisted in the `metadata` table.
+#[derive(Clone, Serialize, Deserialize, Debug)]
+#[serde(default)]
+pub struct OcrConfig {
+    /// Overall time budget for a single page, covering the image fetch, retries and every
+    /// Lens chunk call.
+    pub deadline_secs: u64,
+    /// Images wider than this many pixels are split into columns before being sent to
+    /// Lens, which mangles boxes on ultra-wide spreads.
+    pub chunk_width_limit: u32,
+    /// Whether the startup self-test sends a probe image to Lens. Offline installs turn
+    /// this off so the check is skipped rather than reported as failing.
+    pub self_test_lens: bool,
+    /// Pages that may be sent to Lens at the same time, across all requests and jobs.
+    /// `MANATAN_LENS_CONCURRENCY` overrides the saved value at startup.
+    pub max_concurrent_lens_calls: usize,
+    /// Tries per page fetch and per Lens chunk call, including the first. Only timeouts,
+    /// 429 and 5xx responses are retried.
+    pub retry_attempts: u32,
+    /// Backoff before the first retry; it doubles on every further retry.
+    pub retry_base_delay_ms: u64,
+    /// Line merging thresholds, also exposed on their own at `/merge-config`.
+    pub merge: MergeConfig,
+    /// Cleanup applied to chunks before Lens sees them, also exposed at
+    /// `/preprocess-config`.
+    pub preprocess: Preprocess,
+    /// Resolution PDF pages are rasterized at before OCR, clamped to 72-600.
+    pub pdf_dpi: u32,
+    /// Proxy for page downloads and Lens calls, also exposed at `/proxy-config`.
+    pub proxy: ProxyConfig,
+    /// Per-series orientation, keyed by Suwayomi manga id, for series whose pages the
+    /// detection gets wrong.
+    pub orientation_overrides: HashMap<String, TextOrientation>,
+    /// Limits for the background cache pruning; unset leaves the cache alone.
+    pub auto_prune: Option<PruneOptions>,
+    pub prune_interval_hours: u64,
+    /// Chapter job pacing, also exposed at `/job-settings`.
+    pub jobs: JobSettings,
+    /// Image download and OCR call limits, also exposed at `/timeout-settings`.
+    pub timeouts: TimeoutSettings,
+    /// Cleanup of merged text, also exposed at `/text-normalization`.
+    pub normalization: TextNormalization,
+    /// Query parameters kept in cache keys, also exposed at `/cache-key-config`. Changing
+    /// it only affects new keys until `/cache-key-config/migrate` is run.
+    pub cache_key: CacheKeyConfig,
+}
+
+impl Default for OcrConfig {
+    fn default() -> Self {
+        Self {
+            deadline_secs: 90,
+            chunk_width_limit: 3000,
+            self_test_lens: true,
+            max_concurrent_lens_calls: 3,
+            retry_attempts: 3,
+            retry_base_delay_ms: 1000,
+            merge: MergeConfig::default(),
+            preprocess: Preprocess::None,
+            pdf_dpi: 200,
+            proxy: ProxyConfig::default(),
+            orientation_overrides: HashMap::new(),
+            auto_prune: None,
+            prune_interval_hours: 24,
+            jobs: JobSettings::default(),
+            timeouts: TimeoutSettings::default(),
+            normalization: TextNormalization::default(),
+            cache_key: CacheKeyConfig::default(),
+        }
+    }
+}
+
+impl OcrConfig {
+    /// The orientation forced for the series a page URL belongs to, if any.
+    pub fn orientation_for(&self, url: &str) -> Option<TextOrientation> {
+        let manga_id = crate::logic::manga_id(url)?;
+        self.orientation_overrides.get(manga_id).copied()
+    }
+
+    /// This config with the series orientation for `url` applied, unless the request
+    /// already settled the orientation itself.
+    pub fn for_page(&self, url: &str) -> Cow<'_, OcrConfig> {
+        let series_orientation = self
+            .orientation_for(url)
+            .filter(|_| self.merge.page_orientation().is_none());
+        match series_orientation {
+            Some(orientation) => Cow::Owned(OcrConfig {
+                merge: MergeConfig {
+                    orientation: Some(orientation),
+                    ..self.merge.clone()
+                },
+                ..self.clone()
+            }),
+            None => Cow::Borrowed(self),
+        }
+    }
+
+    pub fn deadline(&self) -> Duration {
+        Duration::from_secs(self.deadline_secs.max(1))
+    }
 }
 
+const OCR_CONFIG_KEY: &str = "ocr_config";
+/// `metadata` counter of pages answered from another page's results by image hash.
+const IMAGE_HASH_REUSES_KEY: &str = "image_hash_reuses";
+const LENS_CONCURRENCY_ENV: &str = "MANATAN_LENS_CONCURRENCY";
+/// How long a caller waits for a cache connection before the pool reports itself busy.
+const POOL_WAIT: Duration = Duration::from_secs(5);
+
 #[derive(Serialize, Deserialize, Clone)]
 pub struct CacheEntry {
     pub context: String,
     pub data: Vec<OcrResult>,
+    #[serde(default)]
+    pub backend: OcrBackend,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<TextOrientation>,
+    /// The orientation hint the results were merged under.
+    #[serde(default, skip_serializing_if = "OrientationHint::is_auto")]
+    pub orientation_hint: OrientationHint,
+    /// When the results were last corrected by hand, if ever.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub edited_at: Option<i64>,
+    /// Where the text came from. Manual pages survive purges and are never re-OCRed.
+    #[serde(default, skip_serializing_if = "EntrySource::is_ocr")]
+    pub source: EntrySource,
+    /// The image preprocessing the results were produced with.
+    #[serde(default, skip_serializing_if = "Preprocess::is_none")]
+    pub preprocess: Preprocess,
+}
+
+impl CacheEntry {
+    /// The entry for a finished OCR run. Preprocess jobs and interactive requests both
+    /// write through this, so a page cached by either is a hit for the other.
+    pub fn from_outcome(context: String, backend: OcrBackend, outcome: &OcrOutcome) -> Self {
+        Self {
+            context,
+            data: outcome.results.clone(),
+            backend,
+            orientation: outcome.orientation,
+            orientation_hint: outcome.orientation_hint,
+            edited_at: None,
+            source: EntrySource::Ocr,
+            preprocess: outcome.preprocess,
+        }
+    }
+}
+
+/// Origin of a cached page's text.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EntrySource {
+    #[default]
+    Ocr,
+    /// Supplied by a person, e.g. from an official digital release.
+    Manual,
+}
+
+impl EntrySource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntrySource::Ocr => "ocr",
+            EntrySource::Manual => "manual",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "ocr" => Some(EntrySource::Ocr),
+            "manual" => Some(EntrySource::Manual),
+            _ => None,
+        }
+    }
+
+    pub fn is_ocr(&self) -> bool {
+        *self == EntrySource::Ocr
+    }
+}
+
+/// One cached line matching a text search.
+#[derive(Clone, Debug, Serialize)]
+pub struct TextHit {
+    pub cache_key: String,
+    /// A chapter the page was preprocessed as part of, if any.
+    pub chapter_key: Option<String>,
+    pub context: String,
+    pub text: String,
+    /// The line, HTML-escaped and trimmed around the first match, which is wrapped in
+    /// `<mark>` tags.
+    pub snippet: String,
+}
+
+/// Outcome of rewriting cached keys under a new [`CacheKeyConfig`].
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CacheKeyMigration {
+    pub scanned: usize,
+    /// Entries kept under a new key.
+    pub rewritten: usize,
+    /// Entries dropped because another entry ended up with the same key.
+    pub merged: usize,
+}
+
+/// Outcome of a cache import.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ImportReport {
+    pub added: usize,
+    pub overwritten: usize,
+    pub skipped_existing: usize,
+    /// Entries that could not be parsed or stored.
+    pub invalid: usize,
+    /// The first few invalid entries' cache keys.
+    pub invalid_keys: Vec<String>,
+    /// What the export said it holds, for exports that carry a manifest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest: Option<ExportManifest>,
+}
+
+impl ImportReport {
+    const MAX_INVALID_KEYS: usize = 10;
+
+    pub fn note_invalid(&mut self, key: String) {
+        self.invalid += 1;
+        if self.invalid_keys.len() < Self::MAX_INVALID_KEYS {
+            self.invalid_keys.push(key);
+        }
+    }
+
+    /// Entries the import went through, whatever became of them.
+    pub fn seen(&self) -> usize {
+        self.added + self.overwritten + self.skipped_existing + self.invalid
+    }
+
+    /// Adds the counts of a report for another batch of the same import.
+    pub fn absorb(&mut self, other: ImportReport) {
+        self.added += other.added;
+        self.overwritten += other.overwritten;
+        self.skipped_existing += other.skipped_existing;
+        self.invalid += other.invalid;
+        for key in other.invalid_keys {
+            if self.invalid_keys.len() < Self::MAX_INVALID_KEYS {
+                self.invalid_keys.push(key);
+            }
+        }
+    }
 }
 
 pub type DbPool = Pool<SqliteConnectionManager>;
@@ -44,14 +378,25 @@ struct PersistentState {
 }
 
 impl AppState {
-    pub fn new(cache_dir: PathBuf) -> Self {
+    /// A cache connection, waiting at most `pool_wait` for one to free up.
+    pub fn conn(&self) -> Result<PooledConnection<SqliteConnectionManager>, DbBusy> {
+        self.pool.get_timeout(self.pool_wait).map_err(|err| {
+            warn!("No OCR cache connection within {:?}: {err}", self.pool_wait);
+            DbBusy
+        })
+    }
+
+    pub fn new(cache_dir: PathBuf, local_novel_path: PathBuf) -> Self {
         if !cache_dir.exists() {
             let _ = std::fs::create_dir_all(&cache_dir);
         }
 
-        let db_path = cache_dir.join("ocr-cache.db");
+        let db_path = cache_dir.join(CACHE_DB_FILE_NAME);
         let manager = SqliteConnectionManager::file(&db_path);
-        let pool = Pool::new(manager).expect("Failed to create OCR DB pool");
+        let pool = Pool::builder()
+            .connection_timeout(POOL_WAIT)
+            .build(manager)
+            .expect("Failed to create OCR DB pool");
         let mut conn = pool.get().expect("Failed to get OCR DB connection");
 
         conn.execute_batch(
@@ -95,7 +440,56 @@ impl AppState {
              );
 
              CREATE INDEX IF NOT EXISTS idx_chapter_pages_accessed
-                ON chapter_pages(last_accessed_at);",
+                ON chapter_pages(last_accessed_at);
+
+             CREATE TABLE IF NOT EXISTS ocr_archive (
+                context_prefix TEXT PRIMARY KEY,
+                file_name TEXT NOT NULL,
+                entry_count INTEGER NOT NULL,
+                size_bytes INTEGER NOT NULL,
+                archived_at INTEGER NOT NULL
+             );
+
+             CREATE TABLE IF NOT EXISTS ocr_archived_keys (
+                cache_key TEXT PRIMARY KEY,
+                context_prefix TEXT NOT NULL
+             );
+
+             CREATE INDEX IF NOT EXISTS idx_ocr_archived_keys_prefix
+                ON ocr_archived_keys(context_prefix);
+
+             CREATE TABLE IF NOT EXISTS ocr_image_hash (
+                image_hash TEXT PRIMARY KEY,
+                cache_key TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+             );
+
+             CREATE TABLE IF NOT EXISTS job_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                chapter_key TEXT NOT NULL,
+                context TEXT NOT NULL,
+                started_at INTEGER NOT NULL,
+                finished_at INTEGER NOT NULL,
+                total_pages INTEGER NOT NULL,
+                skipped_pages INTEGER NOT NULL,
+                processed_pages INTEGER NOT NULL,
+                failures TEXT NOT NULL
+             );
+
+             CREATE INDEX IF NOT EXISTS idx_job_history_chapter
+                ON job_history(chapter_key);
+
+             CREATE TABLE IF NOT EXISTS ocr_spreads (
+                cache_key TEXT PRIMARY KEY,
+                split REAL NOT NULL
+             );
+
+             CREATE TABLE IF NOT EXISTS source_credentials (
+                prefix TEXT PRIMARY KEY,
+                user TEXT NOT NULL,
+                pass TEXT,
+                updated_at INTEGER NOT NULL
+             );",
         )
         .expect("Failed to initialize OCR cache database");
 
@@ -103,65 +497,274 @@ impl AppState {
             "ALTER TABLE chapter_pages ADD COLUMN processed_count INTEGER NOT NULL DEFAULT 0",
             [],
         );
+        let _ = conn.execute(
+            "ALTER TABLE ocr_cache ADD COLUMN backend TEXT NOT NULL DEFAULT 'lens'",
+            [],
+        );
+        let _ = conn.execute("ALTER TABLE ocr_cache ADD COLUMN orientation TEXT", []);
+        let _ = conn.execute("ALTER TABLE ocr_cache ADD COLUMN edited_at INTEGER", []);
+        let _ = conn.execute(
+            "ALTER TABLE ocr_cache ADD COLUMN source TEXT NOT NULL DEFAULT 'ocr'",
+            [],
+        );
+        let _ = conn.execute(
+            "ALTER TABLE ocr_cache ADD COLUMN preprocess TEXT NOT NULL DEFAULT 'none'",
+            [],
+        );
+        let _ = conn.execute("ALTER TABLE ocr_cache ADD COLUMN raw BLOB", []);
+        let _ = conn.execute("ALTER TABLE ocr_cache ADD COLUMN skipped_reason TEXT", []);
+        let _ = conn.execute(
+            "ALTER TABLE ocr_cache ADD COLUMN orientation_hint TEXT NOT NULL DEFAULT 'auto'",
+            [],
+        );
 
+        let text_index = init_text_index(&conn);
         migrate_legacy_cache(&mut conn, &cache_dir);
 
-        Self {
+        let state = Self {
             pool,
             cache_dir,
+            local_novel_path,
             active_jobs: Arc::new(AtomicUsize::new(0)),
             requests_processed: Arc::new(AtomicUsize::new(0)),
             active_chapter_jobs: Arc::new(RwLock::new(HashMap::new())),
+            in_flight_ocr: Arc::new(InFlight::default()),
+            lens_limiter: Arc::new(LensLimiter::new(1)),
+            job_queue: JobQueue::default(),
+            contexts: Arc::new(ContextResolver::default()),
+            text_index,
+            pool_wait: POOL_WAIT,
+            page_updates: broadcast::channel(page_events::CHANNEL_CAPACITY).0,
+            cache_key_config: Arc::default(),
+            credentials: StoredCredentials::default(),
+        };
+        let mut config = state.ocr_config();
+        if let Some(limit) = std::env::var(LENS_CONCURRENCY_ENV)
+            .ok()
+            .and_then(|value| value.parse::<usize>().ok())
+            .filter(|limit| *limit > 0)
+        {
+            info!("Using {LENS_CONCURRENCY_ENV}={limit} for concurrent Lens calls");
+            config.max_concurrent_lens_calls = limit;
         }
+        state
+            .lens_limiter
+            .set_limit(config.max_concurrent_lens_calls);
+        state.set_cache_key_config(&config.cache_key);
+        if let Err(err) = credentials::load(&state) {
+            warn!("Failed to load stored source credentials: {err}");
+        }
+        state
     }
 }
 
 impl AppState {
-    pub fn cache_len(&self) -> usize {
-        let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for cache_len");
-            return 0;
+    /// Resolves an extracted novel image on disk. Returns `None` when the path
+    /// tries to escape the book's image directory.
+    pub fn novel_image_path(&self, book_id: &str, image_path: &str) -> Option<PathBuf> {
+        let book_id = Path::new(book_id);
+        let image_path = Path::new(image_path.trim_start_matches('/'));
+        let is_safe = |path: &Path| {
+            path.components().count() > 0
+                && path
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)))
         };
-        conn.query_row("SELECT COUNT(*) FROM ocr_cache", [], |row| {
-            row.get::<_, i64>(0)
-        })
-        .map(|count| count as usize)
-        .unwrap_or(0)
+        if !is_safe(book_id) || book_id.components().count() != 1 || !is_safe(image_path) {
+            return None;
+        }
+
+        Some(
+            self.local_novel_path
+                .join(NOVEL_METADATA_DIR_NAME)
+                .join(book_id)
+                .join("extracted")
+                .join("images")
+                .join(image_path),
+        )
     }
 
-    pub fn has_cache_entry(&self, cache_key: &str) -> bool {
-        let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for has_cache_entry");
-            return false;
+    pub fn ocr_config(&self) -> OcrConfig {
+        let Ok(conn) = self.conn() else {
+            warn!("Failed to get DB connection for ocr_config");
+            return OcrConfig::default();
         };
         conn.query_row(
-            "SELECT 1 FROM ocr_cache WHERE cache_key = ? LIMIT 1",
-            params![cache_key],
-            |_| Ok(()),
+            "SELECT value FROM metadata WHERE key = ?",
+            params![OCR_CONFIG_KEY],
+            |row| row.get::<_, String>(0),
         )
         .optional()
-        .map(|v| v.is_some())
-        .unwrap_or(false)
+        .ok()
+        .flatten()
+        .and_then(|raw| serde_json::from_str(&raw).ok())
+        .unwrap_or_default()
+    }
+
+    pub fn set_ocr_config(&self, config: &OcrConfig) -> anyhow::Result<()> {
+        let conn = self.conn()?;
+        conn.execute(
+            "INSERT OR REPLACE INTO metadata (key, value) VALUES (?, ?)",
+            params![OCR_CONFIG_KEY, serde_json::to_string(config)?],
+        )?;
+        self.lens_limiter
+            .set_limit(config.max_concurrent_lens_calls);
+        self.set_cache_key_config(&config.cache_key);
+        Ok(())
+    }
+
+    /// The normalization cache keys are built with; see [`logic::get_cache_key`].
+    pub fn cache_key_config(&self) -> CacheKeyConfig {
+        self.cache_key_config
+            .read()
+            .map(|config| config.clone())
+            .unwrap_or_default()
+    }
+
+    fn set_cache_key_config(&self, config: &CacheKeyConfig) {
+        if let Ok(mut current) = self.cache_key_config.write() {
+            *current = config.clone();
+        }
+    }
+
+    /// Writes, reads back and deletes a throwaway cache row.
+    pub fn probe_cache_write(&self) -> anyhow::Result<()> {
+        const PROBE_KEY: &str = "__self_test__";
+        let conn = self.conn()?;
+        let now = now_unix();
+        conn.execute(
+            "INSERT OR REPLACE INTO ocr_cache
+                (cache_key, context, data, created_at, last_processed_at, last_accessed_at, access_count)
+             VALUES (?, 'self-test', ?, ?, ?, ?, 0)",
+            params![PROBE_KEY, b"[]".as_slice(), now, now, now],
+        )?;
+        let stored: Vec<u8> = conn.query_row(
+            "SELECT data FROM ocr_cache WHERE cache_key = ?",
+            params![PROBE_KEY],
+            |row| row.get(0),
+        )?;
+        conn.execute(
+            "DELETE FROM ocr_cache WHERE cache_key = ?",
+            params![PROBE_KEY],
+        )?;
+        if stored != b"[]" {
+            return Err(anyhow::anyhow!("cache probe read back different data"));
+        }
+        Ok(())
+    }
+
+    pub fn cache_len(&self) -> Result<usize, DbBusy> {
+        let conn = self.conn()?;
+        Ok(conn
+            .query_row("SELECT COUNT(*) FROM ocr_cache", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .map(|count| count as usize)
+            .unwrap_or(0))
+    }
+
+    pub fn has_cache_entry(&self, cache_key: &str) -> Result<bool, DbBusy> {
+        let conn = self.conn()?;
+        Ok(conn
+            .query_row(
+                "SELECT 1 FROM ocr_cache WHERE cache_key = ? LIMIT 1",
+                params![cache_key],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|v| v.is_some())
+            .unwrap_or(false))
+    }
+
+    /// Which of `cache_keys` are cached, looked up in a single statement.
+    pub fn cached_keys(&self, cache_keys: &[String]) -> Result<HashSet<String>, DbBusy> {
+        let conn = self.conn()?;
+        let keys_json = serde_json::to_string(cache_keys).unwrap_or_default();
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT cache_key FROM ocr_cache WHERE cache_key IN (SELECT value FROM json_each(?))",
+        ) else {
+            warn!("Failed to prepare cached_keys");
+            return Ok(HashSet::new());
+        };
+        Ok(stmt
+            .query_map(params![keys_json], |row| row.get::<_, String>(0))
+            .map(|rows| rows.flatten().collect())
+            .unwrap_or_default())
     }
 
-    pub fn has_cache_entry_prefix(&self, prefix: &str) -> bool {
-        let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for has_cache_entry_prefix");
-            return false;
+    /// Which of `cache_keys` were cached empty by a chapter job that skipped the page
+    /// rather than OCR it.
+    pub fn skipped_keys(&self, cache_keys: &[String]) -> Result<HashSet<String>, DbBusy> {
+        let conn = self.conn()?;
+        let keys_json = serde_json::to_string(cache_keys).unwrap_or_default();
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT cache_key FROM ocr_cache
+             WHERE cache_key IN (SELECT value FROM json_each(?)) AND skipped_reason IS NOT NULL",
+        ) else {
+            warn!("Failed to prepare skipped_keys");
+            return Ok(HashSet::new());
         };
+        Ok(stmt
+            .query_map(params![keys_json], |row| row.get::<_, String>(0))
+            .map(|rows| rows.flatten().collect())
+            .unwrap_or_default())
+    }
+
+    /// Why the page cached under `cache_key` was skipped rather than OCRed, if it was.
+    pub fn skipped_reason(&self, cache_key: &str) -> Result<Option<String>, DbBusy> {
+        let conn = self.conn()?;
+        Ok(conn
+            .query_row(
+                "SELECT skipped_reason FROM ocr_cache WHERE cache_key = ?",
+                params![cache_key],
+                |row| row.get(0),
+            )
+            .ok()
+            .flatten())
+    }
+
+    /// Which of `cache_keys` are cached under the key itself or a legacy key still carrying
+    /// `sourceId`, in a single statement. Chapter status polls this for every page.
+    pub fn cached_page_keys(&self, cache_keys: &[String]) -> Result<HashSet<String>, DbBusy> {
+        let conn = self.conn()?;
+        let keys_json = serde_json::to_string(cache_keys).unwrap_or_default();
+        // Ranges rather than LIKE so every probe stays on the primary key index: keys
+        // starting with `X?sourceId=` sort between it and `X?sourceId>`.
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT DISTINCT k.value FROM json_each(?) k
+             WHERE EXISTS (SELECT 1 FROM ocr_cache WHERE cache_key = k.value)
+                OR EXISTS (SELECT 1 FROM ocr_cache
+                           WHERE cache_key >= k.value || '?sourceId='
+                             AND cache_key < k.value || '?sourceId>')
+                OR EXISTS (SELECT 1 FROM ocr_cache
+                           WHERE cache_key >= k.value || '&sourceId='
+                             AND cache_key < k.value || '&sourceId>')",
+        ) else {
+            warn!("Failed to prepare cached_page_keys");
+            return Ok(HashSet::new());
+        };
+        Ok(stmt
+            .query_map(params![keys_json], |row| row.get::<_, String>(0))
+            .map(|rows| rows.flatten().collect())
+            .unwrap_or_default())
+    }
+
+    pub fn has_cache_entry_prefix(&self, prefix: &str) -> Result<bool, DbBusy> {
+        let conn = self.conn()?;
         let like_pattern = format!("{prefix}%");
-        conn.query_row(
-            "SELECT 1 FROM ocr_cache WHERE cache_key LIKE ? LIMIT 1",
-            params![like_pattern],
-            |_| Ok(()),
-        )
-        .optional()
-        .map(|v| v.is_some())
-        .unwrap_or(false)
+        Ok(conn
+            .query_row(
+                "SELECT 1 FROM ocr_cache WHERE cache_key LIKE ? LIMIT 1",
+                params![like_pattern],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|v| v.is_some())
+            .unwrap_or(false))
     }
 
     pub fn insert_chapter_cache(&self, chapter_key: &str, cache_key: &str) {
-        let Ok(conn) = self.pool.get() else {
+        let Ok(conn) = self.conn() else {
             warn!("Failed to get DB connection for insert_chapter_cache");
             return;
         };
@@ -172,35 +775,78 @@ impl AppState {
         );
     }
 
-    pub fn count_chapter_cache(&self, chapter_key: &str) -> usize {
-        let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for count_chapter_cache");
-            return 0;
+    /// [`insert_chapter_cache`](Self::insert_chapter_cache) for many pages in one statement.
+    pub fn insert_chapter_cache_keys(&self, chapter_key: &str, cache_keys: &[String]) {
+        let Ok(conn) = self.conn() else {
+            warn!("Failed to get DB connection for insert_chapter_cache_keys");
+            return;
         };
-        conn.query_row(
-            "SELECT COUNT(*) FROM chapter_cache WHERE chapter_key = ?",
-            params![chapter_key],
-            |row| row.get::<_, i64>(0),
-        )
-        .map(|count| count as usize)
-        .unwrap_or(0)
+        let keys_json = serde_json::to_string(cache_keys).unwrap_or_default();
+        let _ = conn.execute(
+            "INSERT OR IGNORE INTO chapter_cache (chapter_key, cache_key, created_at)
+             SELECT ?, value, ? FROM json_each(?)",
+            params![chapter_key, now_unix(), keys_json],
+        );
     }
 
-    pub fn get_cache_entry(&self, cache_key: &str) -> Option<CacheEntry> {
-        let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for get_cache_entry");
-            return None;
+    pub fn count_chapter_cache(&self, chapter_key: &str) -> Result<usize, DbBusy> {
+        let conn = self.conn()?;
+        Ok(conn
+            .query_row(
+                "SELECT COUNT(*) FROM chapter_cache WHERE chapter_key = ?",
+                params![chapter_key],
+                |row| row.get::<_, i64>(0),
+            )
+            .map(|count| count as usize)
+            .unwrap_or(0))
+    }
+
+    /// How many of the chapter's cached pages were skipped rather than OCRed, by reason.
+    pub fn chapter_skipped_reasons(
+        &self,
+        chapter_key: &str,
+    ) -> Result<HashMap<String, usize>, DbBusy> {
+        let conn = self.conn()?;
+        let Ok(mut stmt) = conn.prepare(
+            "SELECT c.skipped_reason, COUNT(*) FROM chapter_cache cc
+             JOIN ocr_cache c ON c.cache_key = cc.cache_key
+             WHERE cc.chapter_key = ? AND c.skipped_reason IS NOT NULL
+             GROUP BY c.skipped_reason",
+        ) else {
+            warn!("Failed to prepare chapter_skipped_reasons");
+            return Ok(HashMap::new());
         };
+        Ok(stmt
+            .query_map(params![chapter_key], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+            })
+            .map(|rows| rows.flatten().collect())
+            .unwrap_or_default())
+    }
+
+    pub fn get_cache_entry(&self, cache_key: &str) -> Result<Option<CacheEntry>, DbBusy> {
+        let conn = self.conn()?;
 
         let entry = conn
             .query_row(
-                "SELECT context, data FROM ocr_cache WHERE cache_key = ?",
+                "SELECT context, data, backend, orientation, edited_at, source, preprocess, orientation_hint FROM ocr_cache WHERE cache_key = ?",
                 params![cache_key],
                 |row| {
                     let context: String = row.get(0)?;
                     let data_blob: Vec<u8> = row.get(1)?;
                     let data = serde_json::from_slice(&data_blob).unwrap_or_default();
-                    Ok(CacheEntry { context, data })
+                    let backend = backend_from_row(row.get(2)?);
+                    let orientation = orientation_from_row(row.get(3)?);
+                    Ok(CacheEntry {
+                        context,
+                        data,
+                        backend,
+                        orientation,
+                        orientation_hint: hint_from_row(row.get(7)?),
+                        edited_at: row.get(4)?,
+                        source: source_from_row(row.get(5)?),
+                        preprocess: preprocess_from_row(row.get(6)?),
+                    })
                 },
             )
             .optional()
@@ -216,31 +862,130 @@ impl AppState {
             );
         }
 
-        entry
+        Ok(entry)
     }
 
-    pub fn get_cache_entry_sourceid_variant(
+    /// Remembers that the image with `image_hash` was OCRed into `cache_key`. The first
+    /// page recorded stays the source for later copies.
+    pub fn record_image_hash(&self, image_hash: &str, cache_key: &str) {
+        let Ok(conn) = self.conn() else {
+            warn!("Failed to get DB connection for record_image_hash");
+            return;
+        };
+        let _ = conn.execute(
+            "INSERT OR IGNORE INTO ocr_image_hash (image_hash, cache_key, created_at)
+             VALUES (?, ?, ?)",
+            params![image_hash, cache_key, now_unix()],
+        );
+    }
+
+    /// The cached results of a page with the same image, as an outcome to store under the
+    /// new page's key. Entries produced with other preprocessing don't count, and a mapping
+    /// whose page was since removed is dropped.
+    pub fn reuse_by_image_hash(
         &self,
-        cache_key: &str,
-    ) -> Option<(String, CacheEntry)> {
-        let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for get_cache_entry_sourceid_variant");
+        image_hash: &str,
+        preprocess: Preprocess,
+    ) -> Option<OcrOutcome> {
+        let conn = self.conn().ok()?;
+        let cache_key: String = conn
+            .query_row(
+                "SELECT cache_key FROM ocr_image_hash WHERE image_hash = ?",
+                params![image_hash],
+                |row| row.get(0),
+            )
+            .optional()
+            .ok()
+            .flatten()?;
+        let Some(entry) = self.get_cache_entry(&cache_key).ok()? else {
+            let _ = conn.execute(
+                "DELETE FROM ocr_image_hash WHERE image_hash = ?",
+                params![image_hash],
+            );
             return None;
         };
+        if entry.preprocess != preprocess {
+            return None;
+        }
+        let _ = conn.execute(
+            "INSERT INTO metadata (key, value) VALUES (?, '1')
+             ON CONFLICT(key) DO UPDATE SET value = CAST(value AS INTEGER) + 1",
+            params![IMAGE_HASH_REUSES_KEY],
+        );
+        Some(OcrOutcome {
+            results: entry.data,
+            partial: false,
+            orientation: entry.orientation,
+            orientation_hint: entry.orientation_hint,
+            preprocess: entry.preprocess,
+            timings: Default::default(),
+            image_hash: Some(image_hash.to_string()),
+            raw: self.raw_page(&cache_key),
+        })
+    }
+
+    /// The Lens lines a cached page was merged from, if they were kept.
+    pub fn raw_page(&self, cache_key: &str) -> Option<RawPage> {
+        let conn = self.conn().ok()?;
+        let raw: Vec<u8> = conn
+            .query_row(
+                "SELECT raw FROM ocr_cache WHERE cache_key = ? AND raw IS NOT NULL",
+                params![cache_key],
+                |row| row.get(0),
+            )
+            .optional()
+            .ok()
+            .flatten()?;
+        serde_json::from_slice(&raw).ok()
+    }
+
+    /// Pages answered by [`reuse_by_image_hash`](Self::reuse_by_image_hash) so far.
+    pub fn image_hash_reuses(&self) -> u64 {
+        let Ok(conn) = self.conn() else {
+            return 0;
+        };
+        conn.query_row(
+            "SELECT CAST(value AS INTEGER) FROM metadata WHERE key = ?",
+            params![IMAGE_HASH_REUSES_KEY],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|count| count as u64)
+        .unwrap_or(0)
+    }
+
+    pub fn get_cache_entry_sourceid_variant(
+        &self,
+        cache_key: &str,
+    ) -> Result<Option<(String, CacheEntry)>, DbBusy> {
+        let conn = self.conn()?;
 
         let like_q = format!("{cache_key}?sourceId=%");
         let like_amp = format!("{cache_key}&sourceId=%");
 
         let row = conn
             .query_row(
-                "SELECT cache_key, context, data FROM ocr_cache WHERE cache_key LIKE ? OR cache_key LIKE ? LIMIT 1",
+                "SELECT cache_key, context, data, backend, orientation, edited_at, source, preprocess, orientation_hint FROM ocr_cache WHERE cache_key LIKE ? OR cache_key LIKE ? LIMIT 1",
                 params![like_q, like_amp],
                 |row| {
                     let key: String = row.get(0)?;
                     let context: String = row.get(1)?;
                     let data_blob: Vec<u8> = row.get(2)?;
                     let data = serde_json::from_slice(&data_blob).unwrap_or_default();
-                    Ok((key, CacheEntry { context, data }))
+                    let backend = backend_from_row(row.get(3)?);
+                    let orientation = orientation_from_row(row.get(4)?);
+                    Ok((
+                        key,
+                        CacheEntry {
+                            context,
+                            data,
+                            backend,
+                            orientation,
+                            orientation_hint: hint_from_row(row.get(8)?),
+                            edited_at: row.get(5)?,
+                            source: source_from_row(row.get(6)?),
+                            preprocess: preprocess_from_row(row.get(7)?),
+                        },
+                    ))
                 },
             )
             .optional()
@@ -256,46 +1001,283 @@ impl AppState {
             );
         }
 
-        row
+        Ok(row)
+    }
+
+    /// Caches a finished OCR run under `cache_key`, along with its image hash and the raw
+    /// lines it was merged from.
+    pub fn cache_outcome(
+        &self,
+        cache_key: &str,
+        context: String,
+        backend: OcrBackend,
+        outcome: &OcrOutcome,
+    ) {
+        self.write_cache_entry(
+            cache_key,
+            &CacheEntry::from_outcome(context, backend, outcome),
+            outcome.raw.as_ref(),
+        );
+        if let Some(image_hash) = &outcome.image_hash {
+            self.record_image_hash(image_hash, cache_key);
+        }
+    }
+
+    /// Marks the page cached under `cache_key` as skipped for `reason`, so a forced job
+    /// knows to OCR it after all. Cleared whenever the entry is written again.
+    pub fn mark_skipped(&self, cache_key: &str, reason: &str) {
+        let Ok(conn) = self.conn() else {
+            warn!("Failed to get DB connection for mark_skipped");
+            return;
+        };
+        let _ = conn.execute(
+            "UPDATE ocr_cache SET skipped_reason = ? WHERE cache_key = ? AND source != 'manual'",
+            params![reason, cache_key],
+        );
     }
 
+    /// Stores an entry. Machine results never replace a manual page. Raw lines kept for
+    /// the entry it replaces are dropped.
     pub fn insert_cache_entry(&self, cache_key: &str, entry: &CacheEntry) {
-        let Ok(conn) = self.pool.get() else {
+        self.write_cache_entry(cache_key, entry, None);
+    }
+
+    /// [`insert_cache_entry`](Self::insert_cache_entry), keeping `raw` as the lines the
+    /// entry was merged from in the same write.
+    fn write_cache_entry(&self, cache_key: &str, entry: &CacheEntry, raw: Option<&RawPage>) {
+        let Ok(conn) = self.conn() else {
             warn!("Failed to get DB connection for insert_cache_entry");
             return;
         };
         let now = now_unix();
         let data_blob = serde_json::to_vec(&entry.data).unwrap_or_default();
-        let _ = conn.execute(
+        let raw_blob = raw.and_then(|raw| serde_json::to_vec(raw).ok());
+        let written = conn.execute(
             "INSERT INTO ocr_cache
-                (cache_key, context, data, created_at, last_processed_at, last_accessed_at, access_count)
-             VALUES (?, ?, ?, ?, ?, ?, ?)
+                (cache_key, context, data, backend, orientation, edited_at, source, preprocess, orientation_hint, raw, created_at, last_processed_at, last_accessed_at, access_count)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
              ON CONFLICT(cache_key) DO UPDATE SET
                 context = excluded.context,
                 data = excluded.data,
+                backend = excluded.backend,
+                orientation = excluded.orientation,
+                edited_at = excluded.edited_at,
+                source = excluded.source,
+                preprocess = excluded.preprocess,
+                orientation_hint = excluded.orientation_hint,
+                raw = excluded.raw,
+                skipped_reason = NULL,
                 last_processed_at = excluded.last_processed_at,
                 last_accessed_at = excluded.last_accessed_at,
-                access_count = ocr_cache.access_count + 1",
+                access_count = ocr_cache.access_count + 1
+             WHERE ocr_cache.source != 'manual' OR excluded.source = 'manual'",
             params![
                 cache_key,
                 entry.context.as_str(),
                 data_blob,
+                entry.backend.as_str(),
+                entry.orientation.map(|o| o.as_str()),
+                entry.edited_at,
+                entry.source.as_str(),
+                entry.preprocess.as_str(),
+                entry.orientation_hint.as_str(),
+                raw_blob,
                 now,
                 now,
                 now,
                 1i64
             ],
         );
+        if written.is_ok_and(|rows| rows > 0) {
+            self.notify_page(cache_key, &entry.context, PageStatus::Ready);
+        }
+    }
+
+    /// Tells `GET /ws` subscribers about a page.
+    pub fn notify_page(&self, cache_key: &str, context: &str, status: PageStatus) {
+        if self.page_updates.receiver_count() == 0 {
+            return;
+        }
+        let _ = self.page_updates.send(PageUpdate {
+            cache_key: cache_key.to_string(),
+            context: context.to_string(),
+            status,
+        });
+    }
+
+    /// Whether the page's cached text was supplied by hand.
+    pub fn is_manual_entry(&self, cache_key: &str) -> Result<bool, DbBusy> {
+        let conn = self.conn()?;
+        Ok(conn
+            .query_row(
+                "SELECT 1 FROM ocr_cache WHERE cache_key = ? AND source = 'manual'",
+                params![cache_key],
+                |_| Ok(()),
+            )
+            .optional()
+            .map(|v| v.is_some())
+            .unwrap_or(false))
     }
 
+    /// Replaces a cached page's results with hand-corrected ones and stamps `edited_at`.
+    /// Returns the timestamp, or `None` when the page is not cached.
+    pub fn edit_cache_entry(
+        &self,
+        cache_key: &str,
+        results: &[OcrResult],
+    ) -> anyhow::Result<Option<i64>> {
+        let conn = self.conn()?;
+        let now = now_unix();
+        let changes = conn.execute(
+            "UPDATE ocr_cache
+             SET data = ?, edited_at = ?, last_accessed_at = ?, skipped_reason = NULL
+             WHERE cache_key = ?",
+            params![serde_json::to_vec(results)?, now, now, cache_key],
+        )?;
+        Ok((changes > 0).then_some(now))
+    }
+
+    /// Deletes every machine-generated page; manual pages are kept.
     pub fn clear_cache(&self) {
-        let Ok(conn) = self.pool.get() else {
+        let Ok(conn) = self.conn() else {
             warn!("Failed to get DB connection for clear_cache");
             return;
         };
-        let _ = conn.execute("DELETE FROM ocr_cache", []);
-        let _ = conn.execute("DELETE FROM chapter_cache", []);
+        let _ = conn.execute(
+            "DELETE FROM chapter_cache
+             WHERE cache_key NOT IN (SELECT cache_key FROM ocr_cache WHERE source = 'manual')",
+            [],
+        );
+        let _ = conn.execute("DELETE FROM ocr_cache WHERE source != 'manual'", []);
+        let _ = conn.execute(spread::DELETE_ORPHAN_SPLITS, []);
         let _ = conn.execute("DELETE FROM chapter_pages", []);
+        let _ = conn.execute(
+            "DELETE FROM ocr_image_hash
+             WHERE cache_key NOT IN (SELECT cache_key FROM ocr_cache)",
+            [],
+        );
+    }
+
+    /// Deletes cache rows whose context equals `context`, or starts with it when `prefix`
+    /// is set, along with their chapter links. Manual pages are kept. Returns the number of
+    /// cache rows removed.
+    pub fn delete_cache_by_context(&self, context: &str, prefix: bool) -> usize {
+        let Ok(mut conn) = self.conn() else {
+            warn!("Failed to get DB connection for delete_cache_by_context");
+            return 0;
+        };
+        let matches = if prefix {
+            "substr(context, 1, length(?1)) = ?1 AND source != 'manual'"
+        } else {
+            "context = ?1 AND source != 'manual'"
+        };
+
+        let tx = match conn.transaction() {
+            Ok(tx) => tx,
+            Err(err) => {
+                warn!("Failed to start delete transaction: {err}");
+                return 0;
+            }
+        };
+        let _ = tx.execute(
+            &format!(
+                "DELETE FROM chapter_cache
+                 WHERE cache_key IN (SELECT cache_key FROM ocr_cache WHERE {matches})"
+            ),
+            params![context],
+        );
+        let deleted = tx
+            .execute(
+                &format!("DELETE FROM ocr_cache WHERE {matches}"),
+                params![context],
+            )
+            .unwrap_or(0);
+        let _ = tx.execute(spread::DELETE_ORPHAN_SPLITS, []);
+        if let Err(err) = tx.commit() {
+            warn!("Failed to commit delete transaction: {err}");
+            return 0;
+        }
+        deleted
+    }
+
+    /// Cached lines containing `query`, ignoring case, most recently read pages first.
+    /// `context` keeps pages whose context starts with it, such as a series title. Stops
+    /// after `limit` hits.
+    pub fn search_text(
+        &self,
+        query: &str,
+        context: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<TextHit>, DbBusy> {
+        let conn = self.conn()?;
+        let needle = query.to_lowercase();
+        let (candidates, pattern) = if self.text_index && needle.chars().count() >= 3 {
+            // The trigram index matches phrases anywhere in the text.
+            (
+                "FROM ocr_text t JOIN ocr_cache o ON o.rowid = t.rowid
+                 WHERE t.ocr_text MATCH ?1",
+                format!("\"{}\"", needle.replace('"', "\"\"")),
+            )
+        } else if self.text_index {
+            // Too short for a trigram, so scan the indexed text instead.
+            let escaped = needle
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            (
+                "FROM ocr_text t JOIN ocr_cache o ON o.rowid = t.rowid
+                 WHERE t.text LIKE '%' || ?1 || '%' ESCAPE '\\'",
+                escaped,
+            )
+        } else {
+            // Match against the stored JSON, so the query needs the same escaping as the text.
+            let escaped = serde_json::to_string(&needle).unwrap_or_default();
+            (
+                "FROM ocr_cache o WHERE instr(lower(CAST(o.data AS TEXT)), ?1) > 0",
+                escaped.trim_matches('"').to_string(),
+            )
+        };
+        let Ok(mut stmt) = conn.prepare(&format!(
+            "SELECT o.cache_key, o.context, o.data,
+                (SELECT c.chapter_key FROM chapter_cache c WHERE c.cache_key = o.cache_key LIMIT 1)
+             {candidates}
+               AND (?2 IS NULL OR substr(o.context, 1, length(?2)) = ?2)
+             ORDER BY o.last_accessed_at DESC"
+        )) else {
+            warn!("Failed to prepare search_text");
+            return Ok(Vec::new());
+        };
+        let Ok(rows) = stmt.query_map(params![pattern, context], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Vec<u8>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+            ))
+        }) else {
+            return Ok(Vec::new());
+        };
+
+        let mut hits = Vec::new();
+        for (cache_key, context, data, chapter_key) in rows.flatten() {
+            let lines: Vec<OcrResult> = serde_json::from_slice(&data).unwrap_or_default();
+            // The JSON match can also come from a field name, so check the lines themselves.
+            for line in lines {
+                if hits.len() >= limit {
+                    return Ok(hits);
+                }
+                if let Some(snippet) = highlight(&line.text, &needle) {
+                    hits.push(TextHit {
+                        cache_key: cache_key.clone(),
+                        chapter_key: chapter_key.clone(),
+                        context: context.clone(),
+                        text: line.text,
+                        snippet,
+                    });
+                }
+            }
+        }
+        Ok(hits)
     }
 
     pub fn delete_chapter_ocr(
@@ -303,7 +1285,7 @@ impl AppState {
         chapter_key: &str,
         delete_data: bool,
     ) -> (usize, usize, usize) {
-        let Ok(mut conn) = self.pool.get() else {
+        let Ok(mut conn) = self.conn() else {
             warn!("Failed to get DB connection for delete_chapter_ocr");
             return (0, 0, 0);
         };
@@ -352,18 +1334,23 @@ impl AppState {
         if delete_data {
             for cache_key in cache_keys {
                 // Delete exact cache_key plus common variants that include sourceId query params.
-                // This mirrors the prefix matching used in chapter_status().
+                // This mirrors the prefix matching used in chapter_status(). Later pages of a
+                // PDF are cached under the page's key plus a page suffix.
                 let like_q = format!("{cache_key}?sourceId=%");
                 let like_amp = format!("{cache_key}&sourceId=%");
+                let like_pdf = pdf::later_pages_pattern(&cache_key);
 
                 let deleted = tx
                     .execute(
-                        "DELETE FROM ocr_cache WHERE cache_key = ? OR cache_key LIKE ? OR cache_key LIKE ?",
-                        params![cache_key, like_q, like_amp],
+                        "DELETE FROM ocr_cache
+                         WHERE (cache_key = ? OR cache_key LIKE ? OR cache_key LIKE ? OR cache_key LIKE ?)
+                           AND source != 'manual'",
+                        params![cache_key, like_q, like_amp, like_pdf],
                     )
                     .unwrap_or(0);
                 ocr_cache_rows += deleted as usize;
             }
+            let _ = tx.execute(spread::DELETE_ORPHAN_SPLITS, []);
         }
 
         if let Err(err) = tx.commit() {
@@ -374,39 +1361,92 @@ impl AppState {
         (chapter_cache_rows, chapter_pages_rows, ocr_cache_rows)
     }
 
-    pub fn export_cache(&self) -> HashMap<String, CacheEntry> {
-        let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for export_cache");
-            return HashMap::new();
-        };
-        let mut out = HashMap::new();
-        let mut stmt = match conn.prepare("SELECT cache_key, context, data FROM ocr_cache") {
-            Ok(stmt) => stmt,
-            Err(err) => {
-                warn!("Failed to prepare export_cache: {err}");
-                return out;
-            }
-        };
-
-        if let Ok(rows) = stmt.query_map([], |row| {
-            let key: String = row.get(0)?;
-            let context: String = row.get(1)?;
+    /// Up to `limit` cache rows ordered by key, starting after `after_key`. Paging by key
+    /// keeps exports of large caches from holding every row in memory at once.
+    pub fn export_cache_batch(
+        &self,
+        after_key: Option<&str>,
+        limit: usize,
+        filter: &ExportFilter,
+    ) -> anyhow::Result<Vec<(String, CacheEntry)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT cache_key, context, data, backend, orientation, edited_at, source, preprocess,
+                    orientation_hint
+             FROM ocr_cache WHERE (?1 IS NULL OR cache_key > ?1) AND {}
+             ORDER BY cache_key LIMIT ?2",
+            export_filter_sql(3)
+        ))?;
+        let bindings = params![
+            after_key,
+            limit as i64,
+            filter.context,
+            filter.context_prefix
+        ];
+        let rows = stmt.query_map(bindings, |row| {
             let data_blob: Vec<u8> = row.get(2)?;
-            let data = serde_json::from_slice(&data_blob).unwrap_or_default();
-            Ok((key, CacheEntry { context, data }))
-        }) {
-            for row in rows.flatten() {
-                out.insert(row.0, row.1);
-            }
-        }
+            Ok((
+                row.get::<_, String>(0)?,
+                CacheEntry {
+                    context: row.get(1)?,
+                    data: serde_json::from_slice(&data_blob).unwrap_or_default(),
+                    backend: backend_from_row(row.get(3)?),
+                    orientation: orientation_from_row(row.get(4)?),
+                    orientation_hint: hint_from_row(row.get(8)?),
+                    edited_at: row.get(5)?,
+                    source: source_from_row(row.get(6)?),
+                    preprocess: preprocess_from_row(row.get(7)?),
+                },
+            ))
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
 
-        out
+    /// Each context `filter` selects with its number of cached pages, in context order.
+    pub fn export_contexts(&self, filter: &ExportFilter) -> anyhow::Result<Vec<(String, usize)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT context, COUNT(*) FROM ocr_cache WHERE {}
+             GROUP BY context ORDER BY context",
+            export_filter_sql(1)
+        ))?;
+        let rows = stmt.query_map(params![filter.context, filter.context_prefix], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
     }
 
-    pub fn import_cache(&self, data: HashMap<String, CacheEntry>) -> usize {
-        let Ok(mut conn) = self.pool.get() else {
+    /// The cache keys and lines of every page cached under exactly `context`.
+    pub fn context_pages(&self, context: &str) -> anyhow::Result<Vec<(String, Vec<OcrResult>)>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT cache_key, data FROM ocr_cache WHERE context = ?")?;
+        let rows = stmt.query_map(params![context], |row| {
+            let data_blob: Vec<u8> = row.get(1)?;
+            Ok((
+                row.get::<_, String>(0)?,
+                serde_json::from_slice(&data_blob).unwrap_or_default(),
+            ))
+        })?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Every distinct context in the cache, sorted.
+    pub fn cache_contexts(&self) -> anyhow::Result<Vec<String>> {
+        let conn = self.conn()?;
+        let mut stmt = conn.prepare("SELECT DISTINCT context FROM ocr_cache ORDER BY context")?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0))?;
+        Ok(rows.collect::<Result<Vec<_>, _>>()?)
+    }
+
+    /// Stores imported entries. Existing rows are kept unless `overwrite` is set, in which
+    /// case their results are replaced but their access history is kept. Only a manual
+    /// entry can overwrite a manual page. Exports carry no raw lines, so a replaced row
+    /// drops its own rather than re-merging back to its old text.
+    pub fn import_cache(&self, data: HashMap<String, CacheEntry>, overwrite: bool) -> ImportReport {
+        let mut report = ImportReport::default();
+        let Ok(mut conn) = self.conn() else {
             warn!("Failed to get DB connection for import_cache");
-            return 0;
+            return report;
         };
 
         let now = now_unix();
@@ -414,31 +1454,167 @@ impl AppState {
             Ok(tx) => tx,
             Err(err) => {
                 warn!("Failed to start import transaction: {err}");
-                return 0;
+                return report;
             }
         };
-        let mut added = 0;
         for (key, entry) in data {
             let data_blob = serde_json::to_vec(&entry.data).unwrap_or_default();
-            if let Ok(changes) = tx.execute(
+            let inserted = tx.execute(
                 "INSERT OR IGNORE INTO ocr_cache
-                    (cache_key, context, data, created_at, last_processed_at, last_accessed_at, access_count)
-                 VALUES (?, ?, ?, ?, ?, ?, ?)",
-                params![key, entry.context, data_blob, now, now, now, 1i64],
-            )
-                && changes > 0 {
-                    added += 1;
+                    (cache_key, context, data, backend, orientation, edited_at, source, preprocess, orientation_hint, created_at, last_processed_at, last_accessed_at, access_count)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                params![
+                    key,
+                    entry.context,
+                    data_blob,
+                    entry.backend.as_str(),
+                    entry.orientation.map(|o| o.as_str()),
+                    entry.edited_at,
+                    entry.source.as_str(),
+                    entry.preprocess.as_str(),
+                    entry.orientation_hint.as_str(),
+                    now,
+                    now,
+                    now,
+                    1i64
+                ],
+            );
+            match inserted {
+                Ok(changes) if changes > 0 => report.added += 1,
+                Ok(_) if overwrite => match tx.execute(
+                    "UPDATE ocr_cache
+                     SET context = ?, data = ?, backend = ?, orientation = ?, edited_at = ?,
+                         source = ?, preprocess = ?, orientation_hint = ?, raw = NULL,
+                         last_processed_at = ?
+                     WHERE cache_key = ? AND (source != 'manual' OR ? = 'manual')",
+                    params![
+                        entry.context,
+                        data_blob,
+                        entry.backend.as_str(),
+                        entry.orientation.map(|o| o.as_str()),
+                        entry.edited_at,
+                        entry.source.as_str(),
+                        entry.preprocess.as_str(),
+                        entry.orientation_hint.as_str(),
+                        now,
+                        key,
+                        entry.source.as_str()
+                    ],
+                ) {
+                    Ok(changes) if changes > 0 => report.overwritten += 1,
+                    Ok(_) => report.skipped_existing += 1,
+                    Err(err) => {
+                        warn!("Failed to overwrite cache entry {key}: {err}");
+                        report.note_invalid(key);
+                    }
+                },
+                Ok(_) => report.skipped_existing += 1,
+                Err(err) => {
+                    warn!("Failed to import cache entry {key}: {err}");
+                    report.note_invalid(key);
                 }
+            }
         }
-        let _ = tx.commit();
-        added
+        if let Err(err) = tx.commit() {
+            warn!("Failed to commit import transaction: {err}");
+            return ImportReport::default();
+        }
+        report
     }
 
-    pub fn get_chapter_pages(&self, chapter_key: &str) -> Option<usize> {
-        let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for get_chapter_pages");
-            return None;
+    /// Rewrites every cached key under `config`. When several entries end up with the
+    /// same key, a manual page wins over OCR results and otherwise the most recently
+    /// processed entry is kept, with the others' access counts added to it. Chapter and
+    /// image hash links follow the surviving entry.
+    pub fn migrate_cache_keys(&self, config: &CacheKeyConfig) -> anyhow::Result<CacheKeyMigration> {
+        let mut conn = self.conn()?;
+        let tx = conn.transaction()?;
+        let rows = {
+            let mut stmt = tx.prepare(
+                "SELECT cache_key, source = 'manual', last_processed_at, access_count
+                 FROM ocr_cache",
+            )?;
+            stmt.query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, bool>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?
         };
+
+        let mut report = CacheKeyMigration {
+            scanned: rows.len(),
+            ..CacheKeyMigration::default()
+        };
+        let mut groups: HashMap<String, Vec<(String, bool, i64, i64)>> = HashMap::new();
+        for row in rows {
+            groups
+                .entry(config.normalize(&row.0))
+                .or_default()
+                .push(row);
+        }
+        for (new_key, mut entries) in groups {
+            if entries.len() == 1 && entries[0].0 == new_key {
+                continue;
+            }
+            entries
+                .sort_by_key(|(_, manual, processed, _)| std::cmp::Reverse((*manual, *processed)));
+            let access_count: i64 = entries.iter().map(|(.., count)| count).sum();
+            let (winner, losers) = entries.split_first().expect("groups are never empty");
+            for (old_key, ..) in losers {
+                tx.execute(
+                    "DELETE FROM ocr_cache WHERE cache_key = ?",
+                    params![old_key],
+                )?;
+                tx.execute(
+                    "DELETE FROM ocr_spreads WHERE cache_key = ?",
+                    params![old_key],
+                )?;
+                report.merged += 1;
+            }
+            if winner.0 != new_key {
+                report.rewritten += 1;
+                tx.execute(
+                    "UPDATE ocr_cache SET cache_key = ? WHERE cache_key = ?",
+                    params![new_key, winner.0],
+                )?;
+                tx.execute(
+                    "UPDATE OR REPLACE ocr_spreads SET cache_key = ? WHERE cache_key = ?",
+                    params![new_key, winner.0],
+                )?;
+            }
+            tx.execute(
+                "UPDATE ocr_cache SET access_count = ? WHERE cache_key = ?",
+                params![access_count, new_key],
+            )?;
+            for (old_key, ..) in entries.iter().filter(|(old_key, ..)| *old_key != new_key) {
+                tx.execute(
+                    "UPDATE OR IGNORE chapter_cache SET cache_key = ? WHERE cache_key = ?",
+                    params![new_key, old_key],
+                )?;
+                tx.execute(
+                    "DELETE FROM chapter_cache WHERE cache_key = ?",
+                    params![old_key],
+                )?;
+                tx.execute(
+                    "UPDATE ocr_image_hash SET cache_key = ? WHERE cache_key = ?",
+                    params![new_key, old_key],
+                )?;
+            }
+        }
+        tx.commit()?;
+        info!(
+            "Migrated cache keys: {} scanned, {} rewritten, {} merged",
+            report.scanned, report.rewritten, report.merged
+        );
+        Ok(report)
+    }
+
+    pub fn get_chapter_pages(&self, chapter_key: &str) -> Result<Option<usize>, DbBusy> {
+        let conn = self.conn()?;
         let count = conn
             .query_row(
                 "SELECT page_count FROM chapter_pages WHERE chapter_key = ?",
@@ -456,14 +1632,14 @@ impl AppState {
             );
         }
 
-        count.map(|val| val as usize)
+        Ok(count.map(|val| val as usize))
     }
 
-    pub fn get_chapter_progress(&self, chapter_key: &str) -> Option<(usize, usize)> {
-        let Ok(conn) = self.pool.get() else {
-            warn!("Failed to get DB connection for get_chapter_progress");
-            return None;
-        };
+    pub fn get_chapter_progress(
+        &self,
+        chapter_key: &str,
+    ) -> Result<Option<(usize, usize)>, DbBusy> {
+        let conn = self.conn()?;
         let progress = conn
             .query_row(
                 "SELECT page_count, processed_count FROM chapter_pages WHERE chapter_key = ?",
@@ -485,12 +1661,12 @@ impl AppState {
             );
         }
 
-        progress
-            .map(|(page_count, processed_count)| (page_count as usize, processed_count as usize))
+        Ok(progress
+            .map(|(page_count, processed_count)| (page_count as usize, processed_count as usize)))
     }
 
     pub fn set_chapter_pages(&self, chapter_key: &str, page_count: usize) {
-        let Ok(conn) = self.pool.get() else {
+        let Ok(conn) = self.conn() else {
             warn!("Failed to get DB connection for set_chapter_pages");
             return;
         };
@@ -511,7 +1687,7 @@ impl AppState {
         page_count: usize,
         processed_count: usize,
     ) {
-        let Ok(conn) = self.pool.get() else {
+        let Ok(conn) = self.conn() else {
             warn!("Failed to get DB connection for set_chapter_progress");
             return;
         };
@@ -528,13 +1704,140 @@ impl AppState {
     }
 }
 
-fn now_unix() -> i64 {
+pub(crate) fn backend_from_row(value: String) -> OcrBackend {
+    OcrBackend::parse(&value).unwrap_or_default()
+}
+
+pub(crate) fn source_from_row(value: String) -> EntrySource {
+    EntrySource::parse(&value).unwrap_or_default()
+}
+
+pub(crate) fn preprocess_from_row(value: String) -> Preprocess {
+    Preprocess::parse(&value).unwrap_or_default()
+}
+
+pub(crate) fn orientation_from_row(value: Option<String>) -> Option<TextOrientation> {
+    value.as_deref().and_then(TextOrientation::parse)
+}
+
+pub(crate) fn hint_from_row(value: String) -> OrientationHint {
+    OrientationHint::parse(&value).unwrap_or_default()
+}
+
+/// SQL matching the rows an [`ExportFilter`] selects, its context bound to `?first` and
+/// its context prefix to the parameter after. The prefix is compared verbatim rather than
+/// through LIKE, so titles with `%` or `_` match literally.
+fn export_filter_sql(first: usize) -> String {
+    let prefix = first + 1;
+    format!(
+        "(?{first} IS NULL OR context = ?{first})
+         AND (?{prefix} IS NULL OR substr(context, 1, length(?{prefix})) = ?{prefix})"
+    )
+}
+
+pub(crate) fn now_unix() -> i64 {
     SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap_or_default()
         .as_secs() as i64
 }
 
+/// Characters of context kept on each side of a search match.
+const SNIPPET_CONTEXT_CHARS: usize = 20;
+
+/// The cached lines of a row's `data` blob joined by newlines, for the text index.
+fn indexed_text_sql(data: &str) -> String {
+    format!(
+        "(SELECT group_concat(json_extract(value, '$.text'), char(10))
+          FROM json_each(CASE WHEN json_valid(CAST({data} AS TEXT))
+                              THEN CAST({data} AS TEXT) ELSE '[]' END))"
+    )
+}
+
+/// Creates the `ocr_text` index and the triggers that keep it in step with `ocr_cache`,
+/// filling it from existing rows the first time. Returns false when the linked SQLite
+/// lacks FTS5 or its trigram tokenizer.
+fn init_text_index(conn: &rusqlite::Connection) -> bool {
+    let new_text = indexed_text_sql("NEW.data");
+    let created = conn.execute_batch(&format!(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS ocr_text USING fts5(text, tokenize = 'trigram');
+
+         CREATE TRIGGER IF NOT EXISTS ocr_text_insert AFTER INSERT ON ocr_cache BEGIN
+            DELETE FROM ocr_text WHERE rowid = NEW.rowid;
+            INSERT INTO ocr_text (rowid, text) VALUES (NEW.rowid, {new_text});
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS ocr_text_update AFTER UPDATE OF data ON ocr_cache BEGIN
+            DELETE FROM ocr_text WHERE rowid = NEW.rowid;
+            INSERT INTO ocr_text (rowid, text) VALUES (NEW.rowid, {new_text});
+         END;
+
+         CREATE TRIGGER IF NOT EXISTS ocr_text_delete AFTER DELETE ON ocr_cache BEGIN
+            DELETE FROM ocr_text WHERE rowid = OLD.rowid;
+         END;"
+    ));
+    if let Err(err) = created {
+        warn!("Full-text search unavailable, falling back to scanning the cache: {err}");
+        return false;
+    }
+
+    let empty = conn
+        .query_row("SELECT NOT EXISTS (SELECT 1 FROM ocr_text)", [], |row| {
+            row.get::<_, bool>(0)
+        })
+        .unwrap_or(false);
+    if empty {
+        match conn.execute(
+            &format!(
+                "INSERT INTO ocr_text (rowid, text) SELECT rowid, {} FROM ocr_cache",
+                indexed_text_sql("data")
+            ),
+            [],
+        ) {
+            Ok(0) => {}
+            Ok(indexed) => info!("Indexed {indexed} cached OCR pages for search"),
+            Err(err) => warn!("Failed to fill the OCR text index: {err}"),
+        }
+    }
+    true
+}
+
+/// `text` around the first case-insensitive occurrence of `needle`, HTML-escaped with the
+/// match in `<mark>` tags. `None` when `text` lacks it.
+fn highlight(text: &str, needle: &str) -> Option<String> {
+    // Fold per character, so match positions line up with the original text.
+    let fold = |c: char| c.to_lowercase().next().unwrap_or(c);
+    let chars: Vec<char> = text.chars().collect();
+    let folded: Vec<char> = chars.iter().copied().map(fold).collect();
+    let needle: Vec<char> = needle.chars().map(fold).collect();
+    let start = (0..=folded.len().checked_sub(needle.len())?)
+        .find(|&i| folded[i..i + needle.len()] == needle[..])?;
+    let end = start + needle.len();
+
+    let from = start.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let to = (end + SNIPPET_CONTEXT_CHARS).min(chars.len());
+    let escape = |part: &[char]| {
+        part.iter().fold(String::new(), |mut out, c| {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' => out.push_str("&quot;"),
+                c => out.push(*c),
+            }
+            out
+        })
+    };
+    Some(format!(
+        "{}{}<mark>{}</mark>{}{}",
+        if from > 0 { "…" } else { "" },
+        escape(&chars[from..start]),
+        escape(&chars[start..end]),
+        escape(&chars[end..to]),
+        if to < chars.len() { "…" } else { "" },
+    ))
+}
+
 fn migrate_legacy_cache(conn: &mut rusqlite::Connection, cache_dir: &Path) {
     let migrated: Option<String> = conn
         .query_row(
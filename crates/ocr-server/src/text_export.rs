@@ -0,0 +1,165 @@
+//! Plain-text export of cached OCR results, for frequency tools and sentence mining. A
+//! context (usually one chapter) becomes one text file with its pages in reading order;
+//! the whole cache becomes a zip with one such file per context.
+
+use std::{
+    collections::HashSet,
+    io::{self, Write},
+};
+
+use bytes::Bytes;
+use futures::Stream;
+use tokio::sync::mpsc;
+use tracing::warn;
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+use crate::{logic::OcrResult, state::AppState};
+
+/// Suggested file name for the zip of every context.
+pub const ALL_TEXT_FILE_NAME: &str = "manatan-ocr-text.zip";
+
+/// Buffered zip output is sent to the client in chunks of about this size.
+const STREAM_CHUNK_BYTES: usize = 64 * 1024;
+
+/// The page number in a page URL or cache key: the segment after `page`, else the last
+/// numeric path segment.
+pub fn page_index(cache_key: &str) -> Option<u64> {
+    let path = cache_key.split(['?', '#']).next().unwrap_or(cache_key);
+    let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+    segments
+        .windows(2)
+        .rev()
+        .find(|pair| pair[0].eq_ignore_ascii_case("page"))
+        .and_then(|pair| pair[1].parse().ok())
+        .or_else(|| segments.iter().rev().find_map(|s| s.parse().ok()))
+}
+
+/// Every cached page of `context` as text, ordered by page number, with a separator
+/// before each page. `None` when nothing is cached under that context.
+pub fn context_text(state: &AppState, context: &str) -> anyhow::Result<Option<String>> {
+    let mut pages = state.context_pages(context)?;
+    if pages.is_empty() {
+        return Ok(None);
+    }
+    pages.sort_by(|(a, _), (b, _)| {
+        page_index(a)
+            .unwrap_or(u64::MAX)
+            .cmp(&page_index(b).unwrap_or(u64::MAX))
+            .then_with(|| a.cmp(b))
+    });
+
+    // Other backends cache the same page under a prefixed key; keep the first.
+    let mut seen = HashSet::new();
+    let mut text = String::new();
+    let mut number = 0;
+    for (cache_key, lines) in pages {
+        let page_key = cache_key.strip_prefix("tesseract/").unwrap_or(&cache_key);
+        if !seen.insert(page_key.to_string()) {
+            continue;
+        }
+        number += 1;
+        if number > 1 {
+            text.push('\n');
+        }
+        text.push_str(&format!("--- Page {number} ---\n"));
+        text.push_str(&page_text(&lines));
+        text.push('\n');
+    }
+    Ok(Some(text))
+}
+
+fn page_text(lines: &[OcrResult]) -> String {
+    lines
+        .iter()
+        .map(|line| line.text.as_str())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A file name for `context`'s text, without characters file systems reject.
+pub fn file_name(context: &str) -> String {
+    let cleaned: String = context
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let cleaned = cleaned.trim().trim_matches('.');
+    if cleaned.is_empty() {
+        "ocr-text.txt".to_string()
+    } else {
+        format!("{cleaned}.txt")
+    }
+}
+
+/// Writes one text file per cached context into a zip. Contexts that clean up to the same
+/// file name are numbered.
+pub fn write_all_zip<W: Write>(state: &AppState, writer: W) -> anyhow::Result<()> {
+    let mut zip = ZipWriter::new_stream(writer);
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut used = HashSet::new();
+    for context in state.cache_contexts()? {
+        let Some(text) = context_text(state, &context)? else {
+            continue;
+        };
+        let base = file_name(&context);
+        let mut name = base.clone();
+        let mut copy = 1;
+        while !used.insert(name.clone()) {
+            copy += 1;
+            name = format!("{} ({copy}).txt", base.trim_end_matches(".txt"));
+        }
+        zip.start_file(name, options)?;
+        zip.write_all(text.as_bytes())?;
+    }
+    zip.finish()?;
+    Ok(())
+}
+
+/// Streams [`write_all_zip`] from a blocking thread.
+pub fn all_zip_stream(state: AppState) -> impl Stream<Item = io::Result<Bytes>> {
+    let (sender, receiver) = mpsc::channel::<io::Result<Bytes>>(2);
+    tokio::task::spawn_blocking(move || {
+        let mut writer = ChannelWriter {
+            sender: sender.clone(),
+            buffer: Vec::new(),
+        };
+        let result = write_all_zip(&state, &mut writer)
+            .and_then(|()| writer.flush().map_err(anyhow::Error::from));
+        if let Err(err) = result {
+            warn!("[EXPORT] Text export failed: {err}");
+            let _ = sender.blocking_send(Err(io::Error::other(err.to_string())));
+        }
+    });
+    futures::stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|chunk| (chunk, receiver))
+    })
+}
+
+/// Hands written bytes to the response stream in chunks.
+struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<Bytes>>,
+    buffer: Vec<u8>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(bytes);
+        if self.buffer.len() >= STREAM_CHUNK_BYTES {
+            self.flush()?;
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = Bytes::from(std::mem::take(&mut self.buffer));
+        self.sender
+            .blocking_send(Ok(chunk))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the client went away"))
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use lazy_static::lazy_static;
+use serde::Serialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::backend::OcrBackend;
+
+lazy_static! {
+    /// Google Lens quotas are per-client rather than per-job, so pacing is shared
+    /// by every request in the process.
+    pub static ref LENS_PACER: LensPacer = LensPacer::default();
+}
+
+/// Samples needed before latency spikes are trusted as a throttling signal.
+const WARMUP_SAMPLES: u32 = 5;
+/// A response this many times slower than the baseline counts as throttled.
+const LATENCY_SPIKE_FACTOR: f64 = 3.0;
+const BASELINE_SMOOTHING: f64 = 0.2;
+const MIN_THROTTLED_DELAY: Duration = Duration::from_millis(500);
+const MAX_DELAY: Duration = Duration::from_secs(30);
+/// Below this the delay snaps back to zero and the throttle is considered over.
+const RECOVERED_DELAY: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, Serialize, Debug, Default)]
+pub struct PacingSnapshot {
+    pub adaptive_delay_ms: u64,
+    pub throttled_since: Option<i64>,
+    pub baseline_latency_ms: u64,
+    pub throttle_events: u64,
+}
+
+#[derive(Default)]
+struct PacerInner {
+    baseline_ms: f64,
+    samples: u32,
+    delay: Duration,
+    throttled_since: Option<i64>,
+    throttle_events: u64,
+}
+
+#[derive(Default)]
+pub struct LensPacer {
+    inner: Mutex<PacerInner>,
+}
+
+impl LensPacer {
+    /// Delay to wait before issuing the next Lens call.
+    pub fn current_delay(&self) -> Duration {
+        self.inner
+            .lock()
+            .map(|inner| inner.delay)
+            .unwrap_or_default()
+    }
+
+    pub fn snapshot(&self) -> PacingSnapshot {
+        let Ok(inner) = self.inner.lock() else {
+            return PacingSnapshot::default();
+        };
+        PacingSnapshot {
+            adaptive_delay_ms: inner.delay.as_millis() as u64,
+            throttled_since: inner.throttled_since,
+            baseline_latency_ms: inner.baseline_ms as u64,
+            throttle_events: inner.throttle_events,
+        }
+    }
+
+    /// Records a successful Lens call and its latency.
+    pub fn record_success(&self, latency: Duration) {
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        let latency_ms = latency.as_secs_f64() * 1000.0;
+
+        if inner.samples >= WARMUP_SAMPLES && latency_ms > inner.baseline_ms * LATENCY_SPIKE_FACTOR
+        {
+            tracing::warn!(
+                target: "lens_throttle",
+                latency_ms = latency_ms as u64,
+                baseline_ms = inner.baseline_ms as u64,
+                "Lens response latency spiked above baseline"
+            );
+            inner.escalate();
+            return;
+        }
+
+        inner.baseline_ms = if inner.samples == 0 {
+            latency_ms
+        } else {
+            inner.baseline_ms * (1.0 - BASELINE_SMOOTHING) + latency_ms * BASELINE_SMOOTHING
+        };
+        inner.samples = inner.samples.saturating_add(1);
+        inner.relax();
+    }
+
+    /// Records a failed Lens call. Only errors that look like quota signals affect pacing.
+    pub fn record_error(&self, error: &str) {
+        if !is_throttle_error(error) {
+            return;
+        }
+        let Ok(mut inner) = self.inner.lock() else {
+            return;
+        };
+        tracing::warn!(
+            target: "lens_throttle",
+            error,
+            "Lens returned a throttling status"
+        );
+        inner.escalate();
+    }
+}
+
+impl PacerInner {
+    fn escalate(&mut self) {
+        self.delay = (self.delay * 2).clamp(MIN_THROTTLED_DELAY, MAX_DELAY);
+        self.throttle_events += 1;
+        if self.throttled_since.is_none() {
+            self.throttled_since = Some(now_unix());
+        }
+        tracing::info!(
+            target: "lens_throttle",
+            delay_ms = self.delay.as_millis() as u64,
+            "Increased adaptive Lens delay"
+        );
+    }
+
+    fn relax(&mut self) {
+        if self.delay.is_zero() {
+            return;
+        }
+        self.delay = self.delay * 3 / 4;
+        if self.delay < RECOVERED_DELAY {
+            self.delay = Duration::ZERO;
+            self.throttled_since = None;
+            tracing::info!(target: "lens_throttle", "Lens responses normalized");
+        }
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Debug)]
+pub struct LimiterSnapshot {
+    pub limit: usize,
+    pub in_use: usize,
+}
+
+/// Caps how many pages are sent to Lens at once across every handler and job.
+pub struct LensLimiter {
+    semaphore: Arc<Semaphore>,
+    limit: AtomicUsize,
+    in_use: Arc<AtomicUsize>,
+}
+
+/// Held for the duration of one page's Lens calls.
+pub struct LensPermit {
+    _permit: OwnedSemaphorePermit,
+    in_use: Arc<AtomicUsize>,
+}
+
+impl Drop for LensPermit {
+    fn drop(&mut self) {
+        self.in_use.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl LensLimiter {
+    pub fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            limit: AtomicUsize::new(limit),
+            in_use: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    /// Waits for a permit when `backend` calls Lens; local backends are not limited.
+    pub async fn acquire(&self, backend: OcrBackend) -> Option<LensPermit> {
+        if backend != OcrBackend::Lens {
+            return None;
+        }
+        let permit = self.semaphore.clone().acquire_owned().await.ok()?;
+        self.in_use.fetch_add(1, Ordering::Relaxed);
+        Some(LensPermit {
+            _permit: permit,
+            in_use: self.in_use.clone(),
+        })
+    }
+
+    /// Changes the limit. Shrinking waits for busy permits to be returned in the
+    /// background rather than cancelling in-flight pages.
+    pub fn set_limit(&self, limit: usize) {
+        let limit = limit.max(1);
+        let previous = self.limit.swap(limit, Ordering::Relaxed);
+        if limit > previous {
+            self.semaphore.add_permits(limit - previous);
+        } else if limit < previous {
+            let excess = (previous - limit) as u32;
+            let semaphore = self.semaphore.clone();
+            tokio::spawn(async move {
+                if let Ok(permits) = semaphore.acquire_many_owned(excess).await {
+                    permits.forget();
+                }
+            });
+        }
+    }
+
+    pub fn snapshot(&self) -> LimiterSnapshot {
+        LimiterSnapshot {
+            limit: self.limit.load(Ordering::Relaxed),
+            in_use: self.in_use.load(Ordering::Relaxed),
+        }
+    }
+}
+
+fn is_throttle_error(error: &str) -> bool {
+    let lower = error.to_lowercase();
+    [
+        "429",
+        "503",
+        "too many requests",
+        "resource_exhausted",
+        "quota",
+    ]
+    .iter()
+    .any(|signal| lower.contains(signal))
+}
+
+fn now_unix() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+}
@@ -0,0 +1,82 @@
+use manatan_ocr_server::archive;
+
+mod common;
+
+#[test]
+fn archive_round_trip_restores_pages_and_chapter_links() {
+    let (state, dir) = common::temp_state("archive");
+
+    state.insert_cache_entry(
+        "/manga/1/chapter/1/page/0",
+        &common::entry("Yotsuba Ch. 1", Vec::new()),
+    );
+    state.insert_cache_entry(
+        "/manga/1/chapter/2/page/0",
+        &common::entry("Yotsuba Ch. 2", Vec::new()),
+    );
+    state.insert_cache_entry(
+        "/manga/2/chapter/1/page/0",
+        &common::entry("Frieren Ch. 1", Vec::new()),
+    );
+    state.insert_chapter_cache("/manga/1/chapter/1", "/manga/1/chapter/1/page/0");
+
+    let summary = archive::archive(&state, "Yotsuba")
+        .expect("archive")
+        .expect("matching rows");
+    assert_eq!(summary.entry_count, 2);
+    assert!(summary.size_bytes > 0);
+    assert!(
+        state
+            .get_cache_entry("/manga/1/chapter/1/page/0")
+            .expect("lookup")
+            .is_none()
+    );
+    assert!(
+        state
+            .get_cache_entry("/manga/2/chapter/1/page/0")
+            .expect("lookup")
+            .is_some()
+    );
+    assert_eq!(
+        state
+            .count_chapter_cache("/manga/1/chapter/1")
+            .expect("lookup"),
+        0
+    );
+    assert_eq!(
+        archive::archived_series(&state, "/manga/1/chapter/2/page/0").as_deref(),
+        Some("Yotsuba")
+    );
+    assert_eq!(archive::list(&state).expect("list").len(), 1);
+    assert!(
+        archive::archive(&state, "Yotsuba")
+            .expect("archive")
+            .is_none()
+    );
+
+    assert_eq!(
+        archive::unarchive(&state, "Yotsuba").expect("unarchive"),
+        Some(2)
+    );
+    assert!(
+        state
+            .get_cache_entry("/manga/1/chapter/1/page/0")
+            .expect("lookup")
+            .is_some()
+    );
+    assert_eq!(
+        state
+            .count_chapter_cache("/manga/1/chapter/1")
+            .expect("lookup"),
+        1
+    );
+    assert!(archive::archived_series(&state, "/manga/1/chapter/2/page/0").is_none());
+    assert!(archive::list(&state).expect("list").is_empty());
+    assert_eq!(
+        archive::unarchive(&state, "Yotsuba").expect("unarchive"),
+        None
+    );
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
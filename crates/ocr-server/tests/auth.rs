@@ -0,0 +1,127 @@
+use axum::{
+    Router,
+    http::{Method, Uri},
+    middleware,
+    routing::{get, post},
+};
+use manatan_ocr_server::auth::{self, API_KEY_FILE, API_KEY_HEADER, ApiKey};
+
+mod common;
+
+async fn spawn_server(key: ApiKey) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind server");
+    let addr = listener.local_addr().expect("server address");
+    let app = Router::new()
+        .route("/ocr", get(|| async { "ocr" }).put(|| async { "edited" }))
+        .route("/ocr/batch", post(|| async { "batch" }))
+        .route("/purge-cache", post(|| async { "purged" }))
+        .layer(middleware::from_fn_with_state(key, auth::require_api_key));
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+    format!("http://{addr}")
+}
+
+fn requires_key(method: Method, uri: &str) -> bool {
+    auth::requires_key(&method, &uri.parse::<Uri>().expect("uri"))
+}
+
+#[test]
+fn only_state_changing_requests_need_the_key() {
+    assert!(!requires_key(Method::GET, "/purge-cache"));
+    assert!(!requires_key(Method::POST, "/is-chapters-preprocessed"));
+    assert!(!requires_key(Method::POST, "/ocr/spread"));
+    assert!(!requires_key(Method::POST, "/ocr/spread?force=false"));
+    assert!(requires_key(Method::POST, "/ocr/spread?force=true"));
+    assert!(requires_key(Method::POST, "/ocr/batch"));
+    assert!(requires_key(Method::POST, "/purge-cache"));
+    assert!(requires_key(Method::POST, "/import-cache"));
+    assert!(requires_key(Method::POST, "/preprocess-chapter"));
+    assert!(requires_key(Method::PUT, "/ocr"));
+}
+
+#[test]
+fn reads_that_overwrite_the_cache_need_the_key() {
+    let page = "/ocr?url=http%3A%2F%2Fhost%2Fpage%2F0";
+    assert!(!requires_key(Method::GET, page));
+    assert!(!requires_key(
+        Method::GET,
+        &format!("{page}&orientation=auto")
+    ));
+    assert!(!requires_key(Method::GET, &format!("{page}&force=false")));
+    assert!(requires_key(Method::GET, &format!("{page}&force=true")));
+    assert!(requires_key(
+        Method::GET,
+        &format!("{page}&orientation=vertical")
+    ));
+    assert!(requires_key(
+        Method::GET,
+        &format!("{page}&preprocess=binarize")
+    ));
+    assert!(requires_key(Method::GET, &format!("{page}&for%63e=true")));
+}
+
+#[test]
+fn key_is_read_from_the_cache_dir_and_trimmed() {
+    let dir = common::temp_dir("auth");
+    std::fs::create_dir_all(&dir).expect("create dir");
+    if std::env::var(auth::API_KEY_ENV).is_err() {
+        assert!(ApiKey::load(&dir).is_none());
+        std::fs::write(dir.join(API_KEY_FILE), "  \n").expect("write key");
+        assert!(ApiKey::load(&dir).is_none());
+        std::fs::write(dir.join(API_KEY_FILE), "s3cret\n").expect("write key");
+        let key = ApiKey::load(&dir).expect("key from file");
+        assert!(key.matches("s3cret"));
+        assert!(!key.matches("s3cre"));
+        assert!(!key.matches("s3cret\n"));
+    }
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn mutating_routes_reject_missing_or_wrong_keys() {
+    let base = spawn_server(ApiKey::new("s3cret").expect("key")).await;
+    let client = reqwest::Client::builder()
+        .no_proxy()
+        .build()
+        .expect("client");
+
+    let open = client.get(format!("{base}/ocr")).send().await.expect("get");
+    assert_eq!(open.status(), 200);
+    let batch = client
+        .post(format!("{base}/ocr/batch"))
+        .send()
+        .await
+        .expect("batch");
+    assert_eq!(batch.status(), 401);
+
+    let missing = client
+        .post(format!("{base}/purge-cache"))
+        .send()
+        .await
+        .expect("purge");
+    assert_eq!(missing.status(), 401);
+    let body: serde_json::Value = missing.json().await.expect("json error");
+    assert_eq!(body["error"], "Missing X-Api-Key header");
+
+    let wrong = client
+        .put(format!("{base}/ocr"))
+        .header(API_KEY_HEADER, "guess")
+        .send()
+        .await
+        .expect("edit");
+    assert_eq!(wrong.status(), 401);
+    let body: serde_json::Value = wrong.json().await.expect("json error");
+    assert_eq!(body["error"], "Invalid API key");
+
+    let allowed = client
+        .post(format!("{base}/purge-cache"))
+        .header(API_KEY_HEADER, "s3cret")
+        .send()
+        .await
+        .expect("purge");
+    assert_eq!(allowed.status(), 200);
+    assert_eq!(allowed.text().await.expect("body"), "purged");
+}
@@ -0,0 +1,70 @@
+use manatan_ocr_server::{backend, language::OcrLanguage};
+
+const HEADER: &str =
+    "level\tpage_num\tblock_num\tpar_num\tline_num\tword_num\tleft\ttop\twidth\theight\tconf\ttext";
+
+fn tsv(rows: &[&str]) -> String {
+    std::iter::once(HEADER)
+        .chain(rows.iter().copied())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn tesseract_words_keep_their_own_boxes() {
+    let tsv = tsv(&[
+        "4\t1\t1\t1\t1\t0\t100\t50\t300\t40\t-1\t",
+        "5\t1\t1\t1\t1\t1\t100\t50\t120\t40\t90\tHello",
+        "5\t1\t1\t1\t1\t2\t250\t55\t150\t35\t70\tworld",
+    ]);
+    let results = backend::parse_tsv(&tsv, 1000, 500, OcrLanguage::English);
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].text, "Hello world");
+    let words = results[0].words.as_deref().expect("word boxes");
+    let texts: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+    assert_eq!(texts, ["Hello", "world"]);
+    let second = &words[1].tight_bounding_box;
+    assert!((second.x - 0.25).abs() < 1e-9);
+    assert!((second.y - 0.11).abs() < 1e-9);
+    assert!((second.width - 0.15).abs() < 1e-9);
+    assert!((second.height - 0.07).abs() < 1e-9);
+}
+
+#[test]
+fn tesseract_rows_are_grouped_into_lines() {
+    let tsv = tsv(&[
+        "5\t1\t1\t1\t2\t1\t10\t200\t40\t20\t50\tsecond",
+        "5\t1\t1\t1\t1\t1\t10\t100\t40\t20\t80\tfirst",
+        "5\t1\t1\t1\t1\t2\t60\t100\t40\t20\t-1\tline",
+        "5\t1\t1\t1\t1\t3\t110\t100\t40\t20\t60\t   ",
+        "5\t1\t1\t1\t3\t1\tbad\t300\t40\t20\t90\tskipped",
+        "5\t1\t1\t1\t4",
+    ]);
+    let results = backend::parse_tsv(&tsv, 200, 400, OcrLanguage::English);
+
+    let texts: Vec<&str> = results.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(texts, ["first line", "second"]);
+    assert_eq!(
+        results[0].confidence,
+        Some(0.8),
+        "rows without a score are left out"
+    );
+    let first = &results[0].tight_bounding_box;
+    assert!((first.x - 0.05).abs() < 1e-9);
+    assert!((first.width - 0.45).abs() < 1e-9);
+    assert_eq!(results[0].forced_orientation.as_deref(), Some("horizontal"));
+}
+
+#[test]
+fn japanese_lines_join_without_spaces_and_tall_lines_are_vertical() {
+    let tsv = tsv(&[
+        "5\t1\t1\t1\t1\t1\t100\t100\t30\t60\t90\t日本",
+        "5\t1\t1\t1\t1\t2\t100\t160\t30\t60\t90\t語",
+    ]);
+    let results = backend::parse_tsv(&tsv, 400, 400, OcrLanguage::Japanese);
+
+    assert_eq!(results[0].text, "日本語");
+    assert_eq!(results[0].forced_orientation.as_deref(), Some("vertical"));
+    assert!(backend::parse_tsv(&tsv, 0, 400, OcrLanguage::Japanese).is_empty());
+}
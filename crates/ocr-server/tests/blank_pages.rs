@@ -0,0 +1,149 @@
+use std::io::Cursor;
+
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+use manatan_ocr_server::{
+    handlers::{self, JobRequest, SKIPPED_REASON_HEADER},
+    imaging,
+    jobs::{self, JobSettings},
+    language::OcrLanguage,
+    logic,
+};
+
+mod common;
+
+fn blank_page() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_pixel(800, 1200, Rgb([250, 250, 250])))
+}
+
+/// A white page with rows of short black strokes, like lines of text in a bubble.
+fn text_page() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(800, 1200, |x, y| {
+        let in_line = (y / 24) % 2 == 1 && (200..600).contains(&y);
+        let in_stroke = (x / 12) % 2 == 0 && (200..600).contains(&x);
+        if in_line && in_stroke {
+            Rgb([0, 0, 0])
+        } else {
+            Rgb([255, 255, 255])
+        }
+    }))
+}
+
+fn png(image: &DynamicImage) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .expect("encode png");
+    bytes
+}
+
+#[test]
+fn blank_pages_score_below_pages_with_text() {
+    let settings = JobSettings::default();
+    assert_eq!(imaging::edge_density(&blank_page()), 0.0);
+    assert!(imaging::edge_density(&text_page()) > settings.blank_page_threshold);
+
+    assert!(jobs::looks_blank(&png(&blank_page()), &settings));
+    assert!(!jobs::looks_blank(&png(&text_page()), &settings));
+    assert!(
+        !jobs::looks_blank(b"not an image", &settings),
+        "undecodable pages go to the backend"
+    );
+
+    let disabled = JobSettings {
+        blank_page_threshold: 0.0,
+        ..JobSettings::default()
+    };
+    assert!(!jobs::looks_blank(&png(&blank_page()), &disabled));
+}
+
+#[test]
+fn blank_page_threshold_is_validated() {
+    for threshold in [-0.1, 1.5, f64::NAN] {
+        let settings = JobSettings {
+            blank_page_threshold: threshold,
+            ..JobSettings::default()
+        };
+        assert!(settings.validate().is_err(), "{threshold} accepted");
+    }
+    assert!(JobSettings::default().validate().is_ok());
+}
+
+#[test]
+fn skipped_marker_is_cleared_when_the_page_is_written_again() {
+    let (state, dir) = common::temp_state("blank-pages");
+    let entry = common::entry("Chapter 1", Vec::new());
+    let keys = vec!["page/0".to_string(), "page/1".to_string()];
+    for key in &keys {
+        state.insert_cache_entry(key, &entry);
+    }
+
+    state.mark_skipped(&keys[0], jobs::SKIPPED_BLANK);
+    assert_eq!(
+        state.skipped_reason(&keys[0]).expect("lookup").as_deref(),
+        Some(jobs::SKIPPED_BLANK)
+    );
+    assert_eq!(state.skipped_reason(&keys[1]).expect("lookup"), None);
+    assert_eq!(
+        state
+            .skipped_keys(&keys)
+            .expect("lookup")
+            .into_iter()
+            .collect::<Vec<_>>(),
+        vec![keys[0].clone()]
+    );
+
+    state.insert_cache_entry(&keys[0], &entry);
+    assert_eq!(state.skipped_reason(&keys[0]).expect("lookup"), None);
+    assert!(state.skipped_keys(&keys).expect("lookup").is_empty());
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn skipped_pages_are_reported_to_readers() {
+    let (state, dir) = common::temp_state("blank-pages-reported");
+    let chapter = "http://127.0.0.1:1/api/v1/manga/2/chapter/5";
+    let pages: Vec<String> = (0..2)
+        .map(|index| format!("{chapter}/page/{index}"))
+        .collect();
+    for page in &pages {
+        let key = logic::get_cache_key(page, Some(OcrLanguage::default()), &Default::default());
+        state.insert_cache_entry(&key, &common::entry("Chapter 5", Vec::new()));
+    }
+    state.mark_skipped(
+        &logic::get_cache_key(&pages[0], Some(OcrLanguage::default()), &Default::default()),
+        jobs::SKIPPED_BLANK,
+    );
+
+    for (page, reason) in [(&pages[0], Some(jobs::SKIPPED_BLANK)), (&pages[1], None)] {
+        let params = serde_json::from_value(serde_json::json!({ "url": page })).expect("params");
+        let response = handlers::ocr_handler(State(state.clone()), Query(params))
+            .await
+            .expect("served from cache");
+        let header = response
+            .headers()
+            .get(SKIPPED_REASON_HEADER)
+            .map(|value| value.to_str().expect("ascii header"));
+        assert_eq!(header, reason, "{page}");
+    }
+
+    let Json(status) = handlers::is_chapter_preprocessed_handler(
+        State(state.clone()),
+        Json(JobRequest {
+            pages: Some(pages),
+            ..common::job_request(chapter, "Check Status")
+        }),
+    )
+    .await
+    .expect("status");
+    assert_eq!(status["status"], "processed");
+    assert_eq!(status["skipped"], serde_json::json!({ "blank": 1 }));
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
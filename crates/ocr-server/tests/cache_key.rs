@@ -0,0 +1,136 @@
+use manatan_ocr_server::{
+    language::OcrLanguage,
+    logic::{CacheKeyConfig, get_cache_key},
+    state::{CacheEntry, EntrySource, OcrConfig},
+};
+
+mod common;
+
+const PAGE: &str =
+    "http://host/api/v1/manga/7/chapter/2/page/3?sourceId=99&updatedAt=1700&width=800";
+
+fn strip(params: &[&str]) -> CacheKeyConfig {
+    CacheKeyConfig {
+        strip_params: params.iter().map(|param| param.to_string()).collect(),
+        keep_params: None,
+    }
+}
+
+#[test]
+fn default_config_only_strips_source_id() {
+    let key = get_cache_key(PAGE, None, &CacheKeyConfig::default());
+    assert_eq!(
+        key,
+        "/api/v1/manga/7/chapter/2/page/3?updatedAt=1700&width=800"
+    );
+    let key = get_cache_key(PAGE, Some(OcrLanguage::Korean), &CacheKeyConfig::default());
+    assert_eq!(
+        key,
+        "lang/ko/api/v1/manga/7/chapter/2/page/3?updatedAt=1700&width=800"
+    );
+}
+
+#[test]
+fn listed_params_are_stripped_and_allowlist_wins() {
+    let key = get_cache_key(PAGE, None, &strip(&["sourceId", "updatedAt"]));
+    assert_eq!(key, "/api/v1/manga/7/chapter/2/page/3?width=800");
+    let key = get_cache_key(PAGE, None, &strip(&["sourceId", "updatedAt", "width"]));
+    assert_eq!(key, "/api/v1/manga/7/chapter/2/page/3");
+
+    let keep_width = CacheKeyConfig {
+        strip_params: vec!["width".to_string()],
+        keep_params: Some(vec!["width".to_string()]),
+    };
+    let key = get_cache_key(PAGE, None, &keep_width);
+    assert_eq!(key, "/api/v1/manga/7/chapter/2/page/3?width=800");
+}
+
+#[test]
+fn normalize_rewrites_stored_keys_the_same_way() {
+    let config = strip(&["sourceId", "updatedAt"]);
+    assert_eq!(
+        config.normalize("lang/ja/page/3?sourceId=1&updatedAt=2&w=5"),
+        "lang/ja/page/3?w=5"
+    );
+    assert_eq!(config.normalize("page/3?updatedAt=2"), "page/3");
+    assert_eq!(config.normalize("page/3"), "page/3");
+    assert_eq!(
+        config.normalize(&get_cache_key(PAGE, None, &CacheKeyConfig::default())),
+        get_cache_key(PAGE, None, &config)
+    );
+}
+
+fn entry(text: &str, source: EntrySource) -> CacheEntry {
+    CacheEntry {
+        source,
+        ..common::entry("Series", vec![common::line(text)])
+    }
+}
+
+#[test]
+fn migration_rewrites_keys_and_merges_duplicates() {
+    let (state, dir) = common::temp_state("cache-key");
+
+    state.insert_cache_entry("/page/1?updatedAt=1", &entry("ocr", EntrySource::Ocr));
+    state.insert_cache_entry("/page/1?updatedAt=2", &entry("typed", EntrySource::Manual));
+    state.insert_cache_entry("/page/2?updatedAt=1", &entry("two", EntrySource::Ocr));
+    state.insert_cache_entry("/page/3", &entry("three", EntrySource::Ocr));
+    state.insert_chapter_cache("chapter", "/page/2?updatedAt=1");
+
+    let report = state
+        .migrate_cache_keys(&strip(&["updatedAt"]))
+        .expect("migration");
+    assert_eq!(report.scanned, 4);
+    assert_eq!(report.rewritten, 2);
+    assert_eq!(report.merged, 1);
+
+    assert_eq!(state.cache_len().expect("lookup"), 3);
+    let page_one = state
+        .get_cache_entry("/page/1")
+        .expect("lookup")
+        .expect("merged page");
+    assert_eq!(page_one.data[0].text, "typed");
+    assert_eq!(page_one.source, EntrySource::Manual);
+    assert!(state.get_cache_entry("/page/2").expect("lookup").is_some());
+    assert!(
+        state
+            .get_cache_entry("/page/2?updatedAt=1")
+            .expect("lookup")
+            .is_none()
+    );
+    assert!(state.get_cache_entry("/page/3").expect("lookup").is_some());
+    assert_eq!(state.count_chapter_cache("chapter").expect("lookup"), 1);
+
+    let again = state
+        .migrate_cache_keys(&strip(&["updatedAt"]))
+        .expect("second migration");
+    assert_eq!((again.rewritten, again.merged), (0, 0));
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn each_state_keys_pages_under_its_own_saved_config() {
+    let (tuned, tuned_dir) = common::temp_state("cache-key-tuned");
+    let (plain, plain_dir) = common::temp_state("cache-key-plain");
+    tuned
+        .set_ocr_config(&OcrConfig {
+            cache_key: strip(&["sourceId", "updatedAt", "width"]),
+            ..OcrConfig::default()
+        })
+        .expect("config");
+
+    assert_eq!(
+        get_cache_key(PAGE, None, &tuned.cache_key_config()),
+        "/api/v1/manga/7/chapter/2/page/3"
+    );
+    assert_eq!(
+        get_cache_key(PAGE, None, &plain.cache_key_config()),
+        "/api/v1/manga/7/chapter/2/page/3?updatedAt=1700&width=800"
+    );
+
+    drop((tuned, plain));
+    let _ = std::fs::remove_dir_all(tuned_dir);
+    let _ = std::fs::remove_dir_all(plain_dir);
+}
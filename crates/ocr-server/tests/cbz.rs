@@ -0,0 +1,61 @@
+use std::{cmp::Ordering, io::Write};
+
+use bytes::Bytes;
+use manatan_ocr_server::cbz::{PageArchive, natural_cmp};
+use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+fn build_zip(entries: &[(&str, &[u8])]) -> Bytes {
+    let mut bytes = Vec::new();
+    {
+        let mut zip = ZipWriter::new(std::io::Cursor::new(&mut bytes));
+        let opts = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+        for (name, contents) in entries {
+            zip.start_file(*name, opts).expect("start file");
+            zip.write_all(contents).expect("write file");
+        }
+        zip.finish().expect("finish zip");
+    }
+    Bytes::from(bytes)
+}
+
+#[test]
+fn digit_runs_sort_by_value() {
+    assert_eq!(natural_cmp("2.jpg", "10.jpg"), Ordering::Less);
+    assert_eq!(natural_cmp("page10.png", "Page9.png"), Ordering::Greater);
+    assert_eq!(natural_cmp("01.jpg", "1.jpg"), Ordering::Greater);
+    assert_eq!(natural_cmp("a/3.jpg", "a/3.jpg"), Ordering::Equal);
+}
+
+#[test]
+fn pages_are_images_in_reading_order() {
+    let bytes = build_zip(&[
+        ("ch1/10.jpg", b"ten"),
+        ("ch1/2.jpg", b"two"),
+        ("ch1/ComicInfo.xml", b"<xml/>"),
+        ("ch1/.thumb.png", b"hidden"),
+        ("__MACOSX/ch1/._2.jpg", b"junk"),
+        ("ch1/1.PNG", b"one"),
+    ]);
+    let archive = PageArchive::from_bytes(bytes.clone()).expect("archive");
+
+    assert_eq!(
+        archive.page_names(),
+        ["ch1/1.PNG", "ch1/2.jpg", "ch1/10.jpg"]
+    );
+    assert_eq!(archive.page_bytes(2).expect("page"), b"ten");
+    assert!(archive.page_bytes(3).is_err());
+
+    let base_url = archive.base_url();
+    assert!(base_url.starts_with("/local-archive/"));
+    assert_eq!(archive.page_urls()[1], format!("{base_url}/1"));
+    // The same bytes map to the same chapter, so re-running hits the cache.
+    let again = PageArchive::from_bytes(bytes).expect("archive");
+    assert_eq!(again.base_url(), base_url);
+}
+
+#[test]
+fn archives_without_images_are_rejected() {
+    let bytes = build_zip(&[("notes.txt", b"nothing here")]);
+    assert!(PageArchive::from_bytes(bytes).is_err());
+    assert!(PageArchive::from_bytes(Bytes::from_static(b"not a zip")).is_err());
+}
@@ -0,0 +1,79 @@
+use axum::{Json, extract::State};
+use manatan_ocr_server::{
+    handlers::{self, JobRequest},
+    language::OcrLanguage,
+    logic,
+    state::CacheEntry,
+};
+
+mod common;
+
+const CHAPTER: &str = "http://127.0.0.1:4568/api/v1/manga/9/chapter/4";
+
+fn page(index: usize) -> String {
+    format!("{CHAPTER}/page/{index}")
+}
+
+fn entry() -> CacheEntry {
+    common::entry("Chapter 4", Vec::new())
+}
+
+fn status_request(pages: Vec<String>) -> JobRequest {
+    JobRequest {
+        pages: Some(pages),
+        ..common::job_request(CHAPTER, "Check Status")
+    }
+}
+
+#[tokio::test]
+async fn page_list_status_counts_cached_pages_in_one_pass() {
+    let (state, dir) = common::temp_state("chapter-status");
+    let key = |index: usize| {
+        logic::get_cache_key(
+            &page(index),
+            Some(OcrLanguage::default()),
+            &Default::default(),
+        )
+    };
+
+    // Pages 0 and 2 are cached as is, page 3 under a legacy key with its sourceId, and
+    // page 30 only shares page 3's prefix.
+    state.insert_cache_entry(&key(0), &entry());
+    state.insert_cache_entry(&key(2), &entry());
+    state.insert_cache_entry(&format!("{}?sourceId=7", key(3)), &entry());
+    state.insert_cache_entry(&key(30), &entry());
+    let pages: Vec<String> = (0..5).map(page).collect();
+
+    let cached = state
+        .cached_page_keys(&(0..5).map(key).collect::<Vec<_>>())
+        .expect("lookup");
+    assert_eq!(cached.len(), 3);
+    assert!(!cached.contains(&key(1)) && !cached.contains(&key(4)));
+
+    let Json(status) = handlers::is_chapter_preprocessed_handler(
+        State(state.clone()),
+        Json(status_request(pages)),
+    )
+    .await
+    .expect("status");
+    assert_eq!(status["status"], "idle");
+    assert_eq!(status["cached_count"], 3);
+    assert_eq!(status["total_expected"], 5);
+    let job_key = logic::get_cache_key(CHAPTER, Some(OcrLanguage::default()), &Default::default());
+    assert_eq!(state.count_chapter_cache(&job_key).expect("lookup"), 3);
+
+    state.insert_cache_entry(&key(1), &entry());
+    state.insert_cache_entry(&key(4), &entry());
+    let Json(status) = handlers::is_chapter_preprocessed_handler(
+        State(state.clone()),
+        Json(status_request((0..5).map(page).collect())),
+    )
+    .await
+    .expect("status");
+    assert_eq!(status["status"], "processed");
+    assert_eq!(status["cached_count"], 5);
+    assert_eq!(state.count_chapter_cache(&job_key).expect("lookup"), 5);
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,101 @@
+//! Fixtures shared by the integration tests. Each test binary uses only some of them.
+#![allow(dead_code)]
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use manatan_ocr_server::{
+    backend::OcrBackend,
+    context::ContextIds,
+    handlers::JobRequest,
+    headers::PageHeaders,
+    jobs::ChapterJob,
+    language::OcrLanguage,
+    logic::{BoundingBox, OcrResult},
+    merge::OrientationHint,
+    preprocess::Preprocess,
+    state::{AppState, CacheEntry, EntrySource},
+};
+
+/// A fresh path under the system temp dir; `label` keeps concurrent tests apart.
+pub fn temp_dir(label: &str) -> PathBuf {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time")
+        .as_nanos();
+    std::env::temp_dir().join(format!("manatan-ocr-{label}-{nanos}"))
+}
+
+/// State backed by a fresh temp dir, returned with the dir so the test can remove it.
+pub fn temp_state(label: &str) -> (AppState, PathBuf) {
+    let dir = temp_dir(label);
+    (AppState::new(dir.clone(), dir.clone()), dir)
+}
+
+/// A plain line of text with an empty box.
+pub fn line(text: &str) -> OcrResult {
+    line_at(text, BoundingBox::default())
+}
+
+pub fn line_at(text: &str, tight_bounding_box: BoundingBox) -> OcrResult {
+    OcrResult {
+        text: text.to_string(),
+        tight_bounding_box,
+        is_merged: None,
+        forced_orientation: None,
+        confidence: None,
+        words: None,
+        font_size_hint: None,
+    }
+}
+
+/// A Lens page cached by OCR, without preprocessing or edits.
+pub fn entry(context: &str, data: Vec<OcrResult>) -> CacheEntry {
+    CacheEntry {
+        context: context.to_string(),
+        data,
+        backend: OcrBackend::Lens,
+        orientation: None,
+        orientation_hint: OrientationHint::Auto,
+        edited_at: None,
+        source: EntrySource::Ocr,
+        preprocess: Preprocess::None,
+    }
+}
+
+/// A chapter request with every optional field left unset.
+pub fn job_request(base_url: &str, context: &str) -> JobRequest {
+    JobRequest {
+        base_url: base_url.to_string(),
+        user: None,
+        pass: None,
+        context: context.to_string(),
+        ids: ContextIds::default(),
+        pages: None,
+        add_space_on_merge: None,
+        language: None,
+        headers: HashMap::new(),
+        cookies: None,
+        token: None,
+        force: false,
+    }
+}
+
+/// A queued chapter in the default language, fetched without credentials or headers.
+pub fn chapter_job(base_url: &str, pages: Vec<String>, context: &str) -> ChapterJob {
+    ChapterJob {
+        base_url: base_url.to_string(),
+        pages,
+        user: None,
+        pass: None,
+        context: context.to_string(),
+        add_space_on_merge: None,
+        language: OcrLanguage::default(),
+        headers: PageHeaders::default(),
+        archive: None,
+        force: false,
+    }
+}
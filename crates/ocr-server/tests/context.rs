@@ -0,0 +1,135 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicUsize, Ordering},
+};
+
+use axum::{Json, Router, extract::State, routing::post};
+use manatan_ocr_server::{
+    context::{ContextIds, ContextResolver},
+    handlers::JobRequest,
+};
+use serde_json::{Value, json};
+
+/// A Suwayomi GraphQL stand-in that knows chapter 7 of manga 3 and source 900, and
+/// counts the queries it answers.
+async fn spawn_graphql() -> (String, Arc<AtomicUsize>) {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind mock server");
+    let addr = listener.local_addr().expect("mock server address");
+    let calls = Arc::new(AtomicUsize::new(0));
+    let app = Router::new()
+        .route(
+            "/api/graphql",
+            post(
+                |State(calls): State<Arc<AtomicUsize>>, Json(body): Json<Value>| async move {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    let query = body["query"].as_str().unwrap_or_default();
+                    let id = &body["variables"]["id"];
+                    Json(if query.contains("chapter(") && *id == json!(7) {
+                        json!({ "data": { "chapter": {
+                            "name": "Chapter 7",
+                            "manga": { "title": "Series" },
+                        } } })
+                    } else if query.contains("manga(") && *id == json!(3) {
+                        json!({ "data": { "manga": { "title": "Series" } } })
+                    } else if query.contains("source(") && *id == json!("900") {
+                        json!({ "data": { "source": { "displayName": "Source (JA)" } } })
+                    } else {
+                        json!({ "errors": [{ "message": "not found" }] })
+                    })
+                },
+            ),
+        )
+        .with_state(calls.clone());
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+    (format!("http://{addr}/api/graphql"), calls)
+}
+
+fn ids(source_id: Option<i64>, manga_id: Option<i64>, chapter_id: Option<i64>) -> ContextIds {
+    ContextIds {
+        source_id,
+        manga_id,
+        chapter_id,
+    }
+}
+
+#[tokio::test]
+async fn the_most_specific_id_names_the_context() {
+    let (url, _) = spawn_graphql().await;
+    let resolver = ContextResolver::new(url);
+
+    let chapter = resolver
+        .resolve_or(ids(Some(900), Some(3), Some(7)), None, None, "fallback")
+        .await;
+    assert_eq!(chapter, "Series / Chapter 7");
+    let manga = resolver
+        .resolve_or(ids(Some(900), Some(3), None), None, None, "fallback")
+        .await;
+    assert_eq!(manga, "Series");
+    let source = resolver
+        .resolve_or(ids(Some(900), None, None), None, None, "fallback")
+        .await;
+    assert_eq!(source, "Source (JA)");
+}
+
+#[tokio::test]
+async fn resolutions_are_cached_but_failures_are_retried() {
+    let (url, calls) = spawn_graphql().await;
+    let resolver = ContextResolver::new(url);
+
+    for _ in 0..3 {
+        let context = resolver
+            .resolve_or(ids(None, None, Some(7)), None, None, "fallback")
+            .await;
+        assert_eq!(context, "Series / Chapter 7");
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+    for _ in 0..2 {
+        let context = resolver
+            .resolve_or(ids(None, None, Some(8)), None, None, "Client Context")
+            .await;
+        assert_eq!(context, "Client Context");
+    }
+    assert_eq!(calls.load(Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn falls_back_without_ids_or_a_reachable_server() {
+    let (url, calls) = spawn_graphql().await;
+    let resolver = ContextResolver::new(url);
+    let context = resolver
+        .resolve_or(ContextIds::default(), None, None, "Client Context")
+        .await;
+    assert_eq!(context, "Client Context");
+    assert_eq!(calls.load(Ordering::SeqCst), 0);
+
+    let unreachable = ContextResolver::new("http://127.0.0.1:1/api/graphql");
+    let context = unreachable
+        .resolve_or(ids(None, Some(3), None), None, None, "Client Context")
+        .await;
+    assert_eq!(context, "Client Context");
+}
+
+#[test]
+fn ids_deserialize_from_numbers_and_strings() {
+    let parsed: ContextIds = serde_json::from_value(json!({
+        "sourceId": "900",
+        "mangaId": 3,
+        "chapter_id": "",
+    }))
+    .expect("ids");
+    assert_eq!(parsed, ids(Some(900), Some(3), None));
+    assert!(serde_json::from_value::<ContextIds>(json!({ "mangaId": "three" })).is_err());
+
+    let job: JobRequest = serde_json::from_value(json!({
+        "base_url": "http://127.0.0.1:4568/api/v1/manga/3/chapter/7",
+        "context": "Client Context",
+        "chapterId": 7,
+    }))
+    .expect("job request");
+    assert_eq!(job.ids, ids(None, None, Some(7)));
+}
@@ -0,0 +1,133 @@
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use manatan_ocr_server::{
+    credentials::SourceCredential,
+    handlers::{self, CredentialPrefix},
+    logic,
+    state::AppState,
+};
+
+mod common;
+
+fn credential(prefix: &str, user: &str, pass: Option<&str>) -> SourceCredential {
+    SourceCredential {
+        prefix: prefix.to_string(),
+        user: user.to_string(),
+        pass: pass.map(str::to_string),
+    }
+}
+
+#[tokio::test]
+async fn stored_credentials_are_matched_by_prefix_and_never_shown() {
+    let (state, dir) = common::temp_state("credentials");
+
+    let (status, _) = handlers::save_credentials_handler(
+        State(state.clone()),
+        Json(credential(" ", "alice", None)),
+    )
+    .await
+    .expect_err("empty prefix");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    handlers::save_credentials_handler(
+        State(state.clone()),
+        Json(credential(
+            "http://127.0.0.1:4568/",
+            "alice",
+            Some("hunter2"),
+        )),
+    )
+    .await
+    .expect("saved");
+    let Json(listed) = handlers::save_credentials_handler(
+        State(state.clone()),
+        Json(credential("127.0.0.1:4568/api/v1/manga/9", "bob", None)),
+    )
+    .await
+    .expect("saved");
+    assert_eq!(listed.len(), 2);
+    assert_eq!(listed[0].prefix, "127.0.0.1:4568");
+    assert!(listed[0].has_password);
+    assert!(!listed[1].has_password);
+    let Json(shown) = handlers::list_credentials_handler(State(state.clone()))
+        .await
+        .expect("list");
+    let shown = serde_json::to_string(&shown).expect("json");
+    assert!(!shown.contains("hunter2"), "{shown}");
+
+    // The longest matching prefix wins; hosts only match at a boundary.
+    assert_eq!(
+        state
+            .credentials
+            .lookup("http://127.0.0.1:4568/api/v1/manga/3/chapter/1/page/0"),
+        Some(("alice".to_string(), Some("hunter2".to_string())))
+    );
+    assert_eq!(
+        state
+            .credentials
+            .lookup("http://127.0.0.1:4568/api/v1/manga/9/chapter/1/page/0"),
+        Some(("bob".to_string(), None))
+    );
+    assert_eq!(
+        state
+            .credentials
+            .lookup("http://127.0.0.1:4568/api/v1/manga/90/chapter/1"),
+        Some(("alice".to_string(), Some("hunter2".to_string())))
+    );
+    assert_eq!(
+        state.credentials.lookup("http://127.0.0.1:45680/page/0"),
+        None
+    );
+
+    // Pages are fetched from loopback whatever host the client named, and matched as such.
+    let page = "http://192.168.1.20:4567/api/v1/manga/9/chapter/1/page/0";
+    assert_eq!(state.credentials.lookup(page), None);
+    assert_eq!(
+        state.credentials.lookup(&logic::loopback_url(page)),
+        Some(("bob".to_string(), None))
+    );
+
+    // Each state keeps its own entries.
+    let (other, other_dir) = common::temp_state("credentials-other");
+    assert_eq!(other.credentials.lookup("http://127.0.0.1:4568/"), None);
+    drop(other);
+    let _ = std::fs::remove_dir_all(other_dir);
+
+    // Inline credentials take precedence.
+    assert_eq!(
+        state.credentials.or_stored(
+            "http://127.0.0.1:4568/api/v1/settings",
+            Some("carol".to_string()),
+            None
+        ),
+        (Some("carol".to_string()), None)
+    );
+    assert_eq!(
+        state
+            .credentials
+            .or_stored("http://127.0.0.1:4568/api/v1/settings", None, None),
+        (Some("alice".to_string()), Some("hunter2".to_string()))
+    );
+
+    // Entries survive a restart.
+    drop(state);
+    let state = AppState::new(dir.clone(), dir.clone());
+    assert!(state.credentials.lookup("http://127.0.0.1:4568/").is_some());
+
+    let status = handlers::delete_credentials_handler(
+        State(state.clone()),
+        Query(CredentialPrefix {
+            prefix: "127.0.0.1:4568".to_string(),
+        }),
+    )
+    .await
+    .expect("deleted");
+    assert_eq!(status, StatusCode::NO_CONTENT);
+    assert_eq!(state.credentials.lookup("http://127.0.0.1:4568/"), None);
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,78 @@
+use std::time::{Duration, Instant};
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use manatan_ocr_server::{handlers, language::OcrLanguage, logic, state::OcrConfig};
+
+mod common;
+
+#[test]
+fn deadline_is_at_least_one_second() {
+    let config = OcrConfig {
+        deadline_secs: 0,
+        ..OcrConfig::default()
+    };
+    assert_eq!(config.deadline(), Duration::from_secs(1));
+    assert_eq!(OcrConfig::default().deadline(), Duration::from_secs(90));
+}
+
+#[tokio::test]
+async fn zero_deadline_is_rejected() {
+    let (state, dir) = common::temp_state("deadline-config");
+
+    let invalid = OcrConfig {
+        deadline_secs: 0,
+        ..OcrConfig::default()
+    };
+    let (status, _) = handlers::set_config_handler(State(state.clone()), Json(invalid))
+        .await
+        .expect_err("zero deadline");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let tuned = OcrConfig {
+        deadline_secs: 15,
+        ..OcrConfig::default()
+    };
+    handlers::set_config_handler(State(state.clone()), Json(tuned))
+        .await
+        .expect("valid deadline");
+    assert_eq!(state.ocr_config().deadline_secs, 15);
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn retries_stop_at_the_deadline_and_nothing_is_cached() {
+    let (state, dir) = common::temp_state("deadline-retries");
+    // Without the deadline, three attempts would back off for several seconds.
+    state
+        .set_ocr_config(&OcrConfig {
+            deadline_secs: 1,
+            retry_attempts: 3,
+            ..OcrConfig::default()
+        })
+        .expect("config");
+
+    let url = "http://127.0.0.1:4568/api/v1/manga/1/chapter/1/page/0";
+    let params = serde_json::from_value(serde_json::json!({ "url": url })).expect("params");
+    let started = Instant::now();
+    let result = handlers::ocr_handler(State(state.clone()), Query(params)).await;
+    assert!(result.is_err(), "nothing serves the page");
+    assert!(started.elapsed() < Duration::from_secs(3));
+    assert!(
+        !state
+            .has_cache_entry(&logic::get_cache_key(
+                url,
+                Some(OcrLanguage::default()),
+                &Default::default()
+            ))
+            .expect("lookup")
+    );
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
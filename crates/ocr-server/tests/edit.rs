@@ -0,0 +1,182 @@
+use axum::extract::{Query, State};
+use manatan_ocr_server::{
+    export::ExportFilter,
+    handlers,
+    language::OcrLanguage,
+    logic,
+    merge::OrientationHint,
+    preprocess::Preprocess,
+    state::{CacheEntry, OcrConfig},
+};
+
+mod common;
+
+#[test]
+fn edited_results_replace_the_cached_page() {
+    let (state, dir) = common::temp_state("edit");
+    let key = "lang/japanese/manga/1/chapter/1/page/0";
+
+    state.insert_cache_entry(
+        key,
+        &common::entry("Ch. 1", vec![common::line("こんにちわ")]),
+    );
+    let edited_at = state
+        .edit_cache_entry(key, &[common::line("こんにちは")])
+        .expect("edit")
+        .expect("page is cached");
+
+    let entry = state.get_cache_entry(key).expect("lookup").expect("entry");
+    assert_eq!(entry.data[0].text, "こんにちは");
+    assert_eq!(entry.context, "Ch. 1");
+    assert_eq!(entry.edited_at, Some(edited_at));
+
+    let exported = state
+        .export_cache_batch(None, 10, &ExportFilter::default())
+        .expect("export");
+    assert_eq!(exported[0].1.edited_at, Some(edited_at));
+
+    assert_eq!(
+        state
+            .edit_cache_entry("lang/japanese/manga/1/chapter/1/page/1", &[])
+            .expect("edit"),
+        None
+    );
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn edited_pages_survive_a_preprocess_change() {
+    let (state, dir) = common::temp_state("edit-stale");
+    // A stale page would be fetched again; keep that short so a regression fails fast.
+    state
+        .set_ocr_config(&OcrConfig {
+            preprocess: Preprocess::Binarize,
+            deadline_secs: 1,
+            retry_attempts: 1,
+            ..OcrConfig::default()
+        })
+        .expect("config");
+    let url = "http://127.0.0.1:1/api/v1/manga/1/chapter/1/page/0";
+    let key = logic::get_cache_key(url, Some(OcrLanguage::default()), &Default::default());
+    state.insert_cache_entry(
+        &key,
+        &common::entry("Ch. 1", vec![common::line("こんにちわ")]),
+    );
+    state
+        .edit_cache_entry(&key, &[common::line("こんにちは")])
+        .expect("edit")
+        .expect("page is cached");
+
+    let params = serde_json::from_value(serde_json::json!({ "url": url })).expect("params");
+    let response = handlers::ocr_handler(State(state.clone()), Query(params))
+        .await
+        .expect("served from cache");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body");
+    let lines: serde_json::Value = serde_json::from_slice(&body).expect("json");
+    assert_eq!(lines[0]["text"], "こんにちは");
+    assert_eq!(
+        state
+            .get_cache_entry(&key)
+            .expect("lookup")
+            .expect("entry")
+            .data[0]
+            .text,
+        "こんにちは"
+    );
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn experiments_keep_every_setting_in_their_run_key() {
+    let request = |extra: serde_json::Value| -> handlers::OcrRequest {
+        let mut params = serde_json::json!({ "url": "http://127.0.0.1:1/page/0" });
+        if let (Some(params), Some(extra)) = (params.as_object_mut(), extra.as_object()) {
+            params.extend(extra.clone());
+        }
+        serde_json::from_value(params).expect("params")
+    };
+    let key = "lang/japanese/page/0";
+
+    assert_eq!(request(serde_json::json!({})).run_key(key), key);
+    let both = request(serde_json::json!({
+        "orientation": "vertical",
+        "preprocess": "binarize",
+    }))
+    .run_key(key);
+    assert_eq!(
+        both,
+        format!("{key}#orientation=vertical#preprocess=binarize")
+    );
+    // A request with only one of the two must not share the run.
+    assert_ne!(
+        both,
+        request(serde_json::json!({ "preprocess": "binarize" })).run_key(key)
+    );
+    assert_ne!(
+        both,
+        request(serde_json::json!({ "orientation": "vertical" })).run_key(key)
+    );
+}
+
+#[tokio::test]
+async fn orientation_hints_reuse_matching_pages_and_keep_edits() {
+    let (state, dir) = common::temp_state("edit-hint");
+    // A page that misses would be fetched; keep that short so a regression fails fast.
+    state
+        .set_ocr_config(&OcrConfig {
+            deadline_secs: 1,
+            retry_attempts: 1,
+            ..OcrConfig::default()
+        })
+        .expect("config");
+    let vertical = "http://127.0.0.1:1/api/v1/manga/1/chapter/1/page/0";
+    let edited = "http://127.0.0.1:1/api/v1/manga/1/chapter/1/page/1";
+    let language = Some(OcrLanguage::default());
+    state.insert_cache_entry(
+        &logic::get_cache_key(vertical, language, &Default::default()),
+        &CacheEntry {
+            orientation_hint: OrientationHint::Vertical,
+            ..common::entry("Ch. 1", vec![common::line("縦書き")])
+        },
+    );
+    let edited_key = logic::get_cache_key(edited, language, &Default::default());
+    state.insert_cache_entry(
+        &edited_key,
+        &common::entry("Ch. 1", vec![common::line("こんにちわ")]),
+    );
+    state
+        .edit_cache_entry(&edited_key, &[common::line("こんにちは")])
+        .expect("edit")
+        .expect("page is cached");
+
+    for (url, text) in [(vertical, "縦書き"), (edited, "こんにちは")] {
+        let params = serde_json::from_value(serde_json::json!({
+            "url": url,
+            "orientation": "vertical",
+        }))
+        .expect("params");
+        let response = handlers::ocr_handler(State(state.clone()), Query(params))
+            .await
+            .expect("served from cache");
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body");
+        let lines: serde_json::Value = serde_json::from_slice(&body).expect("json");
+        assert_eq!(lines[0]["text"], text);
+    }
+    let entry = state
+        .get_cache_entry(&edited_key)
+        .expect("lookup")
+        .expect("entry");
+    assert_eq!(entry.data[0].text, "こんにちは");
+    assert!(entry.edited_at.is_some());
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
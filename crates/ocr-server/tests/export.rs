@@ -0,0 +1,121 @@
+use futures::StreamExt;
+use manatan_ocr_server::{
+    export::{self, ExportFilter},
+    state::AppState,
+};
+
+mod common;
+
+#[tokio::test]
+async fn streamed_export_imports_into_an_empty_cache() {
+    let (source, source_dir) = common::temp_state("export-source");
+    let (target, target_dir) = common::temp_state("export-target");
+
+    // More rows than one export batch, so the stream spans several chunks.
+    for index in 0..1_200 {
+        source.insert_cache_entry(
+            &format!("lang/japanese/manga/1/chapter/1/page/{index}"),
+            &common::entry(&format!("Page {index}"), Vec::new()),
+        );
+    }
+
+    let mut body = Vec::new();
+    let mut stream = Box::pin(export::export_stream(
+        source.clone(),
+        ExportFilter::default(),
+    ));
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.expect("chunk"));
+    }
+    assert!(export::is_gzip(&body));
+
+    let report = export::import_ndjson_gz(&target, &body, false).expect("import");
+    assert_eq!(report.added, 1_200);
+    let restored = target
+        .get_cache_entry("lang/japanese/manga/1/chapter/1/page/1199")
+        .expect("lookup")
+        .expect("restored row");
+    assert_eq!(restored.context, "Page 1199");
+
+    let report = export::import_ndjson_gz(&target, &body, false).expect("re-import");
+    assert_eq!((report.added, report.skipped_existing), (0, 1_200));
+    let report = export::import_ndjson_gz(&target, &body, true).expect("overwrite");
+    assert_eq!((report.overwritten, report.skipped_existing), (1_200, 0));
+
+    drop(source);
+    drop(target);
+    let _ = std::fs::remove_dir_all(source_dir);
+    let _ = std::fs::remove_dir_all(target_dir);
+}
+
+async fn collect_export(state: &AppState, filter: ExportFilter) -> Vec<u8> {
+    let mut body = Vec::new();
+    let mut stream = Box::pin(export::export_stream(state.clone(), filter));
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.expect("chunk"));
+    }
+    body
+}
+
+#[tokio::test]
+async fn filtered_export_carries_one_series_and_its_manifest() {
+    let (source, source_dir) = common::temp_state("export-filtered-source");
+    let (target, target_dir) = common::temp_state("export-filtered-target");
+    for (index, context) in [
+        "Series A / Ch. 1",
+        "Series A / Ch. 2",
+        "Series A / Ch. 2",
+        "Series B / Ch. 1",
+        // `%` is matched literally, not as a wildcard.
+        "Series % / Ch. 1",
+    ]
+    .iter()
+    .enumerate()
+    {
+        source.insert_cache_entry(
+            &format!("lang/japanese/manga/{index}/chapter/1/page/0"),
+            &common::entry(context, Vec::new()),
+        );
+    }
+
+    let series_a = ExportFilter {
+        context: None,
+        context_prefix: Some("Series A /".to_string()),
+    };
+    let body = collect_export(&source, series_a.clone()).await;
+    let report = export::import_ndjson_gz(&target, &body, false).expect("import");
+    assert_eq!((report.added, report.invalid), (3, 0));
+    let manifest = report.manifest.expect("manifest");
+    assert_eq!(manifest.entries, 3);
+    assert_eq!(manifest.contexts, ["Series A / Ch. 1", "Series A / Ch. 2"]);
+    assert_eq!(manifest.filter, series_a);
+    assert!(manifest.exported_at > 0);
+    assert!(
+        target
+            .get_cache_entry("lang/japanese/manga/3/chapter/1/page/0")
+            .expect("lookup")
+            .is_none()
+    );
+
+    let one_chapter = ExportFilter {
+        context: Some("Series A / Ch. 1".to_string()),
+        context_prefix: None,
+    };
+    let body = collect_export(&source, one_chapter).await;
+    let report = export::import_ndjson_gz(&target, &body, false).expect("re-import");
+    assert_eq!((report.added, report.skipped_existing), (0, 1));
+
+    let literal = ExportFilter {
+        context: None,
+        context_prefix: Some("Series %".to_string()),
+    };
+    let body = collect_export(&source, literal).await;
+    let report = export::import_ndjson_gz(&target, &body, false).expect("import");
+    assert_eq!(report.added, 1);
+    assert_eq!(report.manifest.expect("manifest").entries, 1);
+
+    drop(source);
+    drop(target);
+    let _ = std::fs::remove_dir_all(source_dir);
+    let _ = std::fs::remove_dir_all(target_dir);
+}
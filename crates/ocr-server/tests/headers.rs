@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use manatan_ocr_server::{handlers::OcrRequest, headers::PageHeaders};
+
+fn page_headers(headers: &[(&str, &str)], cookies: Option<&str>) -> PageHeaders {
+    PageHeaders {
+        headers: headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        cookies: cookies.map(str::to_string),
+        token: None,
+    }
+}
+
+#[test]
+fn hop_by_hop_headers_are_dropped() {
+    let map = page_headers(
+        &[
+            ("Referer", "https://source.example/chapter/1"),
+            ("Connection", "keep-alive"),
+            ("Host", "evil.example"),
+            ("Transfer-Encoding", "chunked"),
+            ("Authorization", "Bearer token"),
+        ],
+        None,
+    )
+    .to_header_map()
+    .expect("valid headers");
+
+    assert_eq!(map.len(), 1);
+    assert_eq!(map["referer"], "https://source.example/chapter/1");
+}
+
+#[test]
+fn cookies_are_appended_to_a_cookie_header() {
+    let map = page_headers(&[("Cookie", "a=1")], Some(" session=abc "))
+        .to_header_map()
+        .expect("valid headers");
+    assert_eq!(map["cookie"], "a=1; session=abc");
+
+    let map = page_headers(&[], Some("session=abc"))
+        .to_header_map()
+        .expect("valid headers");
+    assert_eq!(map["cookie"], "session=abc");
+}
+
+#[test]
+fn a_token_is_sent_as_bearer_authorization() {
+    let headers = PageHeaders {
+        token: Some(" abc.def ".to_string()),
+        ..page_headers(&[("Authorization", "Basic Zm9vOmJhcg==")], None)
+    };
+    let map = headers.to_header_map().expect("valid headers");
+    assert_eq!(map["authorization"], "Bearer abc.def");
+    assert_eq!(map.len(), 1);
+}
+
+#[test]
+fn invalid_headers_are_rejected() {
+    assert!(
+        page_headers(&[("Bad Name", "x")], None)
+            .to_header_map()
+            .is_err()
+    );
+    assert!(
+        page_headers(&[("Referer", "line\nbreak")], None)
+            .to_header_map()
+            .is_err()
+    );
+}
+
+#[test]
+fn headers_are_accepted_inline_in_query_strings() {
+    let request: OcrRequest = serde_json::from_value(serde_json::json!({
+        "url": "http://127.0.0.1:4568/page/0",
+        "headers": "{\"Referer\":\"https://source.example/\"}",
+        "cookies": "session=abc",
+    }))
+    .expect("request");
+    assert_eq!(
+        request.headers,
+        HashMap::from([("Referer".to_string(), "https://source.example/".to_string())])
+    );
+    assert_eq!(request.cookies.as_deref(), Some("session=abc"));
+}
@@ -0,0 +1,48 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::anyhow;
+use manatan_ocr_server::{
+    backend::OcrBackend,
+    health::{self, HealthStatus},
+};
+
+mod common;
+
+#[tokio::test]
+async fn failures_are_remembered_and_fresh_reports_are_served_from_cache() {
+    let (state, dir) = common::temp_state("health");
+
+    let failed = health::record(
+        OcrBackend::Tesseract,
+        Some(1_000),
+        12,
+        Err(anyhow!("connection refused")),
+    );
+    assert_eq!(failed.status, HealthStatus::Degraded);
+    assert_eq!(failed.last_error.as_deref(), Some("connection refused"));
+    assert_eq!(failed.last_error_at, Some(1_000));
+
+    // Recovering clears the status but keeps the error for diagnosis.
+    let recovered = health::record(OcrBackend::Tesseract, Some(2_000), 8, Ok(()));
+    assert_eq!(recovered.status, HealthStatus::Ok);
+    assert!(recovered.probed);
+    assert_eq!(recovered.last_error.as_deref(), Some("connection refused"));
+    assert_eq!(recovered.last_error_at, Some(1_000));
+
+    // A report checked just now is returned without probing again.
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time")
+        .as_secs() as i64;
+    health::record(OcrBackend::Tesseract, Some(now), 5, Ok(()));
+    let cached = health::check(&state, OcrBackend::Tesseract, false).await;
+    assert_eq!(cached.checked_at, now);
+    assert_eq!(cached.latency_ms, 5);
+    assert_eq!(
+        health::latest(OcrBackend::Tesseract).map(|report| report.checked_at),
+        Some(now)
+    );
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
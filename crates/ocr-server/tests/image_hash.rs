@@ -0,0 +1,63 @@
+use manatan_ocr_server::{
+    backend::OcrBackend, language::OcrLanguage, logic, preprocess::Preprocess, state::CacheEntry,
+};
+
+mod common;
+
+fn credits_page() -> CacheEntry {
+    common::entry("Chapter 1", vec![common::line("翻訳・編集")])
+}
+
+#[test]
+fn image_hash_covers_bytes_language_and_backend() {
+    let page = b"the same credits page".as_slice();
+    let hash = logic::image_hash(page, OcrLanguage::Japanese, OcrBackend::Lens);
+    assert_eq!(
+        hash,
+        logic::image_hash(page, OcrLanguage::Japanese, OcrBackend::Lens)
+    );
+    assert_ne!(
+        hash,
+        logic::image_hash(b"another page", OcrLanguage::Japanese, OcrBackend::Lens)
+    );
+    assert_ne!(
+        hash,
+        logic::image_hash(page, OcrLanguage::Korean, OcrBackend::Lens)
+    );
+    assert_ne!(
+        hash,
+        logic::image_hash(page, OcrLanguage::Japanese, OcrBackend::Tesseract)
+    );
+}
+
+#[test]
+fn identical_images_reuse_the_first_pages_results() {
+    let (state, dir) = common::temp_state("image-hash");
+    let hash = logic::image_hash(b"credits", OcrLanguage::Japanese, OcrBackend::Lens);
+
+    assert!(state.reuse_by_image_hash(&hash, Preprocess::None).is_none());
+    state.insert_cache_entry("/manga/1/chapter/1/page/20", &credits_page());
+    state.record_image_hash(&hash, "/manga/1/chapter/1/page/20");
+    state.record_image_hash(&hash, "/manga/1/chapter/2/page/20");
+
+    let outcome = state
+        .reuse_by_image_hash(&hash, Preprocess::None)
+        .expect("first page's results");
+    assert_eq!(outcome.results[0].text, "翻訳・編集");
+    assert!(!outcome.partial);
+    assert_eq!(outcome.image_hash.as_deref(), Some(hash.as_str()));
+    assert!(
+        state
+            .reuse_by_image_hash(&hash, Preprocess::Binarize)
+            .is_none()
+    );
+    assert_eq!(state.image_hash_reuses(), 1);
+
+    // Once the source page is gone, nothing is reused.
+    state.clear_cache();
+    assert!(state.reuse_by_image_hash(&hash, Preprocess::None).is_none());
+    assert_eq!(state.image_hash_reuses(), 1);
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,55 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use manatan_ocr_server::imaging::{self, EncodeError, MAX_DIMENSION, OutputFormat};
+
+fn page(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+        Rgba([(x % 256) as u8, (y % 256) as u8, 128, 255])
+    }))
+}
+
+#[test]
+fn every_format_writes_its_own_container() {
+    let image = page(64, 48);
+
+    let png = imaging::encode(&image, OutputFormat::Png, None).expect("png");
+    assert!(png.starts_with(b"\x89PNG"));
+
+    let jpeg = imaging::encode(&image, OutputFormat::Jpeg, Some(70)).expect("jpeg");
+    assert!(jpeg.starts_with(&[0xFF, 0xD8, 0xFF]));
+
+    let webp = imaging::encode(&image, OutputFormat::Webp, None).expect("webp");
+    assert!(webp.starts_with(b"RIFF"));
+    assert_eq!(&webp[8..12], b"WEBP");
+
+    let avif = imaging::encode(&image, OutputFormat::Avif, Some(60)).expect("avif");
+    assert_eq!(&avif[4..12], b"ftypavif");
+
+    let decoded = image::load_from_memory(&png).expect("decode png");
+    assert_eq!((decoded.width(), decoded.height()), (64, 48));
+}
+
+#[test]
+fn quality_is_rejected_for_lossless_formats_and_out_of_range() {
+    let image = page(8, 8);
+    for format in [OutputFormat::Png, OutputFormat::Webp] {
+        let err = imaging::encode(&image, format, Some(80)).expect_err("lossless with quality");
+        assert!(matches!(err, EncodeError::QualityUnsupported(_)));
+        assert!(err.is_bad_request());
+    }
+    let err = imaging::encode(&image, OutputFormat::Jpeg, Some(0)).expect_err("quality 0");
+    assert!(matches!(err, EncodeError::InvalidQuality(0)));
+}
+
+#[test]
+fn large_crops_are_scaled_down_and_crops_stay_inside_the_page() {
+    let image = page(MAX_DIMENSION * 2, 100);
+    let png = imaging::encode(&image, OutputFormat::Png, None).expect("png");
+    let decoded = image::load_from_memory(&png).expect("decode png");
+    assert_eq!(decoded.width(), MAX_DIMENSION);
+
+    let image = page(200, 100);
+    let cropped = imaging::crop(&image, 0.5, 0.5, 0.25, 0.5);
+    assert_eq!((cropped.width(), cropped.height()), (50, 50));
+    let clamped = imaging::crop(&image, 0.9, -0.5, 0.5, 2.0);
+    assert_eq!((clamped.width(), clamped.height()), (20, 100));
+}
@@ -0,0 +1,85 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use axum::{Router, extract::State, routing::get};
+use manatan_ocr_server::inflight::InFlight;
+
+async fn slow_page(State(hits): State<Arc<AtomicUsize>>) -> &'static str {
+    hits.fetch_add(1, Ordering::SeqCst);
+    tokio::time::sleep(Duration::from_millis(300)).await;
+    "page-bytes"
+}
+
+async fn spawn_slow_server(hits: Arc<AtomicUsize>) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind mock server");
+    let addr = listener.local_addr().expect("mock server address");
+    let app = Router::new()
+        .route("/page.png", get(slow_page))
+        .with_state(hits);
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+    format!("http://{addr}/page.png")
+}
+
+async fn fetch(url: &str) -> Result<String, String> {
+    reqwest::Client::builder()
+        .no_proxy()
+        .build()
+        .map_err(|err| err.to_string())?
+        .get(url)
+        .send()
+        .await
+        .map_err(|err| err.to_string())?
+        .text()
+        .await
+        .map_err(|err| err.to_string())
+}
+
+#[tokio::test]
+async fn concurrent_identical_requests_share_one_upstream_fetch() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let url = spawn_slow_server(hits.clone()).await;
+    let in_flight: InFlight<Result<String, String>> = InFlight::default();
+
+    let (first, second) = tokio::join!(
+        in_flight.run(&url, || fetch(&url)),
+        in_flight.run(&url, || fetch(&url)),
+    );
+
+    assert_eq!(first, Ok("page-bytes".to_string()));
+    assert_eq!(first, second);
+    assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+    // Once the first run has finished, the key is free again.
+    let third = in_flight.run(&url, || fetch(&url)).await;
+    assert_eq!(third, first);
+    assert_eq!(hits.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn cancelled_leader_hands_over_to_waiting_caller() {
+    let hits = Arc::new(AtomicUsize::new(0));
+    let url = spawn_slow_server(hits.clone()).await;
+    let in_flight: InFlight<Result<String, String>> = InFlight::default();
+
+    let leader = tokio::time::timeout(
+        Duration::from_millis(50),
+        in_flight.run(&url, || fetch(&url)),
+    );
+    let follower = async {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        in_flight.run(&url, || fetch(&url)).await
+    };
+    let (leader, follower) = tokio::join!(leader, follower);
+
+    assert!(leader.is_err(), "leader should time out");
+    assert_eq!(follower, Ok("page-bytes".to_string()));
+}
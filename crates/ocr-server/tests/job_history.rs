@@ -0,0 +1,78 @@
+use axum::{Json, extract::State};
+use manatan_ocr_server::{
+    handlers,
+    job_history::{self, JobRecord, PageFailure},
+    language::OcrLanguage,
+    logic,
+};
+
+mod common;
+
+const CHAPTER: &str = "http://127.0.0.1:4568/api/v1/manga/9/chapter/5";
+
+fn job(started_at: i64, failed_pages: &[usize]) -> JobRecord {
+    JobRecord {
+        id: 0,
+        chapter_key: logic::get_cache_key(
+            CHAPTER,
+            Some(OcrLanguage::default()),
+            &Default::default(),
+        ),
+        context: "Series: Chapter 5".to_string(),
+        started_at,
+        finished_at: started_at + 30,
+        total_pages: 4,
+        skipped_pages: 0,
+        processed_pages: 4 - failed_pages.len(),
+        failures: failed_pages
+            .iter()
+            .map(|index| PageFailure {
+                url: format!("{CHAPTER}/page/{index}"),
+                error: "Lens returned 503".to_string(),
+            })
+            .collect(),
+    }
+}
+
+#[tokio::test]
+async fn failed_jobs_are_kept_and_surface_in_the_chapter_status() {
+    let (state, dir) = common::temp_state("job-history");
+    let chapter_key =
+        logic::get_cache_key(CHAPTER, Some(OcrLanguage::default()), &Default::default());
+
+    assert!(job_history::last_failed(&state, &chapter_key).is_none());
+    let first = job_history::record(&state, &job(100, &[1, 3])).expect("record");
+    let second = job_history::record(&state, &job(200, &[3])).expect("record");
+    assert!(second > first);
+
+    let recent = job_history::recent(&state, 10).expect("history");
+    assert_eq!(recent.len(), 2);
+    assert_eq!(recent[0].id, second);
+    assert_eq!(recent[0].started_at, 200);
+    assert_eq!(recent[1].failures.len(), 2);
+    assert_eq!(job_history::recent(&state, 1).expect("history").len(), 1);
+
+    let Json(status) = handlers::is_chapter_preprocessed_handler(
+        State(state.clone()),
+        Json(common::job_request(CHAPTER, "Check Status")),
+    )
+    .await
+    .expect("status");
+    assert_eq!(status["status"], "idle");
+    assert_eq!(status["last_failed_job"]["id"], second);
+    assert_eq!(
+        status["failed_pages"],
+        serde_json::json!([format!("{CHAPTER}/page/3")])
+    );
+    assert_eq!(
+        status["last_failed_job"]["failures"][0]["error"],
+        "Lens returned 503"
+    );
+
+    // A later clean run means there is nothing left to retry.
+    job_history::record(&state, &job(300, &[])).expect("record");
+    assert!(job_history::last_failed(&state, &chapter_key).is_none());
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,262 @@
+use std::time::Duration;
+
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+};
+use manatan_jobs::QueueFull;
+use manatan_ocr_server::{
+    backend::OcrBackend,
+    handlers,
+    jobs::{self, ChapterJob, Enqueued, JobPacer, JobSettings, PrefetchBudget},
+    language::OcrLanguage,
+    logic::{self, OcrOutcome},
+    merge::OrientationHint,
+    state::{CacheEntry, OcrConfig},
+};
+
+mod common;
+
+fn chapter(index: usize) -> ChapterJob {
+    common::chapter_job(
+        &format!("http://127.0.0.1:4568/api/v1/manga/1/chapter/{index}"),
+        vec![format!("http://127.0.0.1:4568/page/{index}/0")],
+        &format!("Chapter {index}"),
+    )
+}
+
+#[test]
+fn burst_of_chapters_queues_in_order_without_duplicates() {
+    // No workers are started, so every chapter stays queued.
+    let (state, dir) = common::temp_state("job-queue");
+
+    for index in 0..20 {
+        assert_eq!(
+            jobs::enqueue(&state, chapter(index)),
+            Enqueued::Queued(index + 1)
+        );
+    }
+    assert_eq!(
+        jobs::enqueue(&state, chapter(4)),
+        Enqueued::AlreadyQueued(5)
+    );
+    assert_eq!(state.job_queue.len(), 20);
+    assert_eq!(
+        state
+            .job_queue
+            .position(&chapter(19).key(&Default::default())),
+        Some(20)
+    );
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn full_queue_rejects_further_chapters() {
+    let (state, dir) = common::temp_state("job-queue-full");
+
+    for index in 0..jobs::MAX_QUEUED_CHAPTERS {
+        assert_eq!(
+            jobs::enqueue(&state, chapter(index)),
+            Enqueued::Queued(index + 1)
+        );
+    }
+    assert_eq!(
+        jobs::enqueue(&state, chapter(jobs::MAX_QUEUED_CHAPTERS)),
+        Enqueued::Full(QueueFull {
+            class: "ocr-preprocess",
+            depth: jobs::MAX_QUEUED_CHAPTERS,
+            capacity: jobs::MAX_QUEUED_CHAPTERS,
+        })
+    );
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn pages_cached_by_a_job_are_served_to_interactive_requests() {
+    let (state, dir) = common::temp_state("job-queue-shared");
+    // A cache miss would try to fetch the page; keep that short so a regression fails fast.
+    state
+        .set_ocr_config(&OcrConfig {
+            deadline_secs: 1,
+            retry_attempts: 1,
+            ..OcrConfig::default()
+        })
+        .expect("config");
+
+    // Written exactly as a preprocess job writes a finished page.
+    let url = chapter(1).pages[0].clone();
+    let outcome = OcrOutcome {
+        results: vec![common::line("前処理済み")],
+        partial: false,
+        orientation: None,
+        orientation_hint: OrientationHint::Auto,
+        preprocess: state.ocr_config().preprocess,
+        timings: Default::default(),
+        image_hash: None,
+        raw: None,
+    };
+    state.insert_cache_entry(
+        &logic::get_cache_key(&url, Some(OcrLanguage::default()), &Default::default()),
+        &CacheEntry::from_outcome("Chapter 1".to_string(), OcrBackend::Lens, &outcome),
+    );
+
+    let params = serde_json::from_value(serde_json::json!({ "url": url })).expect("params");
+    let response = handlers::ocr_handler(State(state.clone()), Query(params))
+        .await
+        .expect("served from cache");
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .expect("body");
+    let lines: serde_json::Value = serde_json::from_slice(&body).expect("json");
+    assert_eq!(lines[0]["text"], "前処理済み");
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn jobs_follow_the_given_page_list_exactly() {
+    let (state, dir) = common::temp_state("job-queue-list");
+
+    // Gaps and non-numeric names that probing `base_url/{index}` would never find.
+    let job = ChapterJob {
+        pages: ["cover", "p-003", "p-010", "credits"]
+            .iter()
+            .map(|name| format!("http://127.0.0.1:4568/api/v1/manga/1/chapter/7/page/{name}"))
+            .collect(),
+        ..chapter(7)
+    };
+    let outcome = OcrOutcome {
+        results: Vec::new(),
+        partial: false,
+        orientation: None,
+        orientation_hint: OrientationHint::Auto,
+        preprocess: state.ocr_config().preprocess,
+        timings: Default::default(),
+        image_hash: None,
+        raw: None,
+    };
+    for url in &job.pages {
+        state.insert_cache_entry(
+            &logic::get_cache_key(url, Some(OcrLanguage::default()), &Default::default()),
+            &CacheEntry::from_outcome(job.context.clone(), OcrBackend::Lens, &outcome),
+        );
+    }
+
+    let key = job.key(&Default::default());
+    let handle = manatan_jobs::track("ocr-preprocess-test", "ocr", job.context.clone());
+    jobs::run_chapter_job(state.clone(), job, handle).await;
+
+    assert_eq!(
+        state.get_chapter_progress(&key).expect("lookup"),
+        Some((4, 4))
+    );
+    assert_eq!(state.count_chapter_cache(&key).expect("lookup"), 4);
+    assert!(state.active_chapter_jobs.read().expect("lock").is_empty());
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn prefetch_budget_bounds_pages_and_bytes() {
+    let budget = PrefetchBudget::new(3, 1024 * 1024);
+    let first = budget.reserve(400 * 1024).await;
+    let second = budget.reserve(400 * 1024).await;
+    assert_eq!(budget.available_pages(), 1);
+
+    // A page slot is free, but the bytes are not.
+    let waiting = tokio::time::timeout(Duration::from_millis(50), budget.reserve(300 * 1024));
+    assert!(waiting.await.is_err());
+
+    drop(first);
+    let third = budget.reserve(300 * 1024).await;
+
+    // A page larger than the whole budget waits until nothing else is held.
+    let oversized = tokio::time::timeout(Duration::from_millis(50), budget.reserve(8 << 20));
+    assert!(oversized.await.is_err());
+    drop((second, third));
+    let _oversized = tokio::time::timeout(Duration::from_millis(50), budget.reserve(8 << 20))
+        .await
+        .expect("fits once the budget is empty");
+}
+
+#[tokio::test]
+async fn job_pacer_delays_between_bursts_and_cools_down_after_errors() {
+    let settings = JobSettings {
+        page_delay_ms: 500,
+        burst_size: 2,
+        error_cooldown_after: 2,
+        error_cooldown_secs: 5,
+        blank_page_threshold: 0.0,
+    };
+    let quick = Duration::from_millis(50);
+
+    let pacer = JobPacer::default();
+    for _ in 0..2 {
+        tokio::time::timeout(quick, pacer.before_page(&settings))
+            .await
+            .expect("pages within a burst go straight through");
+    }
+    assert!(
+        tokio::time::timeout(quick, pacer.before_page(&settings))
+            .await
+            .is_err()
+    );
+
+    let pacer = JobPacer::default();
+    let no_delay = JobSettings {
+        page_delay_ms: 0,
+        ..settings
+    };
+    pacer.before_page(&no_delay).await;
+    pacer.page_finished(false);
+    pacer.page_finished(true);
+    pacer.page_finished(false);
+    tokio::time::timeout(quick, pacer.before_page(&no_delay))
+        .await
+        .expect("a success resets the error streak");
+    pacer.page_finished(false);
+    assert!(
+        tokio::time::timeout(quick, pacer.before_page(&no_delay))
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn job_settings_are_validated_and_persisted() {
+    let (state, dir) = common::temp_state("job-settings");
+
+    let Json(defaults) = handlers::get_job_settings_handler(State(state.clone())).await;
+    assert_eq!(defaults, JobSettings::default());
+
+    let invalid = JobSettings {
+        burst_size: 0,
+        ..JobSettings::default()
+    };
+    let (status, _) = handlers::set_job_settings_handler(State(state.clone()), Json(invalid))
+        .await
+        .expect_err("zero burst size");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let tuned = JobSettings {
+        page_delay_ms: 250,
+        burst_size: 3,
+        error_cooldown_after: 5,
+        error_cooldown_secs: 60,
+        blank_page_threshold: 0.01,
+    };
+    handlers::set_job_settings_handler(State(state.clone()), Json(tuned.clone()))
+        .await
+        .expect("valid settings");
+    assert_eq!(state.ocr_config().jobs, tuned);
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
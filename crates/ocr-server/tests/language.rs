@@ -0,0 +1,26 @@
+use manatan_ocr_server::{handlers::OcrRequest, language::OcrLanguage, logic::get_cache_key};
+
+#[test]
+fn lang_accepts_iso_codes_and_keeps_caches_apart() {
+    let request: OcrRequest =
+        serde_json::from_str(r#"{ "url": "http://host/page.png", "lang": "ko" }"#)
+            .expect("request with lang");
+    assert_eq!(request.language, Some(OcrLanguage::Korean));
+    assert_eq!(OcrLanguage::Korean.lens_code(), "ko");
+
+    let korean = get_cache_key(&request.url, request.language, &Default::default());
+    let japanese = get_cache_key(
+        &request.url,
+        Some(OcrLanguage::Japanese),
+        &Default::default(),
+    );
+    assert_ne!(korean, japanese);
+}
+
+#[test]
+fn language_defaults_to_japanese_when_absent() {
+    let request: OcrRequest =
+        serde_json::from_str(r#"{ "url": "http://host/page.png" }"#).expect("request without lang");
+    assert_eq!(request.language, None);
+    assert_eq!(request.language.unwrap_or_default().lens_code(), "ja");
+}
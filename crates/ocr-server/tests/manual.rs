@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+
+use manatan_ocr_server::{
+    export::ExportFilter,
+    logic::BoundingBox,
+    manual::{self, ManualBlock},
+    state::{CacheEntry, EntrySource},
+};
+
+mod common;
+
+fn entry(text: &str, source: EntrySource) -> CacheEntry {
+    CacheEntry {
+        source,
+        ..common::entry("Series / Ch. 1", vec![common::line(text)])
+    }
+}
+
+fn block(text: &str, bbox: Option<BoundingBox>) -> ManualBlock {
+    ManualBlock {
+        text: text.to_string(),
+        bbox,
+    }
+}
+
+#[test]
+fn manual_pages_survive_purges_and_machine_writes() {
+    let (state, dir) = common::temp_state("manual");
+    let manual_key = "lang/japanese/manga/1/chapter/1/page/0";
+    let ocr_key = "lang/japanese/manga/1/chapter/1/page/1";
+
+    state.insert_cache_entry(manual_key, &entry("公式", EntrySource::Manual));
+    state.insert_cache_entry(ocr_key, &entry("機械", EntrySource::Ocr));
+    assert!(state.is_manual_entry(manual_key).expect("lookup"));
+    assert!(!state.is_manual_entry(ocr_key).expect("lookup"));
+
+    // A re-OCR of the page must not replace the human text.
+    state.insert_cache_entry(manual_key, &entry("誤認識", EntrySource::Ocr));
+    let stored = state
+        .get_cache_entry(manual_key)
+        .expect("lookup")
+        .expect("manual entry");
+    assert_eq!(stored.data[0].text, "公式");
+    assert_eq!(stored.source, EntrySource::Manual);
+
+    let report = state.import_cache(
+        HashMap::from([(manual_key.to_string(), entry("誤認識", EntrySource::Ocr))]),
+        true,
+    );
+    assert_eq!((report.overwritten, report.skipped_existing), (0, 1));
+
+    let exported = state
+        .export_cache_batch(None, 10, &ExportFilter::default())
+        .expect("export");
+    let line = serde_json::to_value(&exported[0].1).expect("serialize");
+    assert_eq!(line["source"], "manual");
+    let line = serde_json::to_value(&exported[1].1).expect("serialize");
+    assert!(line.get("source").is_none());
+
+    assert_eq!(state.delete_cache_by_context("Series", true), 1);
+    state.insert_cache_entry(ocr_key, &entry("機械", EntrySource::Ocr));
+    state.clear_cache();
+    assert!(state.has_cache_entry(manual_key).expect("lookup"));
+    assert!(!state.has_cache_entry(ocr_key).expect("lookup"));
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn blocks_without_boxes_are_stacked_down_the_right_edge() {
+    let given = BoundingBox {
+        x: 0.1,
+        y: 0.2,
+        width: 0.3,
+        height: 0.4,
+        rotation: None,
+    };
+    let results = manual::to_results(vec![
+        block("一", None),
+        block("  ", None),
+        block("二", Some(given)),
+        block("三", None),
+    ]);
+
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[1].tight_bounding_box.x, 0.1);
+    let (first, third) = (
+        &results[0].tight_bounding_box,
+        &results[2].tight_bounding_box,
+    );
+    assert_eq!(first.x + first.width, 1.0);
+    assert_eq!(first.y, 0.0);
+    assert_eq!(third.y, first.height);
+    assert!(first.height > 0.0 && first.height <= 0.1);
+}
@@ -7,31 +7,203 @@
 use std::{fs, path::PathBuf};
 
 use manatan_ocr_server::{
+    handlers,
     language::OcrLanguage,
-    logic::{self, RawChunk},
-    merge::{self, MergeConfig},
+    logic::{self, BoundingBox, Granularity, OcrResult, RawChunk, WordBox},
+    merge::{self, MergeConfig, OrientationHint, TextOrientation},
+    state::OcrConfig,
 };
 use pretty_assertions::StrComparison;
+use serde::Serialize;
 use serde_json::Value;
 use walkdir::WalkDir;
 
-fn sanitize_results(v: &mut Value) {
+mod common;
+
+/// Left out of expected files: they shift with every Lens or threshold tweak.
+const UNSTORED_FIELDS: &[&str] = &["confidence", "words", "fontSizeHint"];
+/// Also left out of comparisons. Boxes are stored to order blocks and to pair them up in
+/// the summary, but never compared.
+const UNCOMPARED_FIELDS: &[&str] = &["tightBoundingBox", "confidence", "words", "fontSizeHint"];
+
+fn strip_fields(v: &mut Value, fields: &[&str]) {
     match v {
         Value::Array(arr) => {
             for item in arr {
-                sanitize_results(item);
+                strip_fields(item, fields);
             }
         }
         Value::Object(map) => {
-            map.remove("tightBoundingBox");
+            for field in fields {
+                map.remove(*field);
+            }
             for (_, value) in map.iter_mut() {
-                sanitize_results(value);
+                strip_fields(value, fields);
             }
         }
         _ => {}
     }
 }
 
+fn sanitize_results(v: &mut Value) {
+    strip_fields(v, UNCOMPARED_FIELDS);
+}
+
+/// Blocks top to bottom, then left to right, so a regenerated expected file only moves
+/// where the merge output changed rather than wherever reading order shifted.
+fn canonical_order(results: &mut [OcrResult]) {
+    results.sort_by(|a, b| {
+        position_order(
+            (&a.text, &a.tight_bounding_box),
+            (&b.text, &b.tight_bounding_box),
+        )
+    });
+}
+
+fn position_order(
+    (a_text, a_box): (&str, &BoundingBox),
+    (b_text, b_box): (&str, &BoundingBox),
+) -> std::cmp::Ordering {
+    a_box
+        .y
+        .total_cmp(&b_box.y)
+        .then(a_box.x.total_cmp(&b_box.x))
+        .then_with(|| a_text.cmp(b_text))
+}
+
+/// Whether every block carries its box. Files written before [`canonical_order`] have
+/// none and list blocks in the order the merge emitted them.
+fn has_boxes(v: &Value) -> bool {
+    v.as_array().is_none_or(|blocks| {
+        blocks
+            .iter()
+            .all(|block| block_text_and_box(block).1.is_some())
+    })
+}
+
+/// `expected` and `emitted`, the merge output in the order it came out, sanitized for
+/// comparison. Blocks are put in [`canonical_order`] when the expected file has boxes to
+/// sort by, so a block that moves past another does not compare equal; box-less files are
+/// compared in emission order.
+fn comparable(expected: &Value, emitted: &Value) -> (Value, Value) {
+    let order = |v: &Value| {
+        if has_boxes(expected) {
+            by_position(v)
+        } else {
+            v.clone()
+        }
+    };
+    let (mut expected, mut emitted) = (order(expected), order(emitted));
+    sanitize_results(&mut expected);
+    sanitize_results(&mut emitted);
+    (expected, emitted)
+}
+
+/// Blocks in [`canonical_order`], sorted by the boxes that sanitizing drops.
+fn by_position(v: &Value) -> Value {
+    let mut v = v.clone();
+    if let Value::Array(blocks) = &mut v {
+        let mut placed: Vec<_> = blocks
+            .drain(..)
+            .map(|block| {
+                let (text, bbox) = block_text_and_box(&block);
+                (text, bbox.unwrap_or_default(), block)
+            })
+            .collect();
+        placed.sort_by(|(a_text, a_box, _), (b_text, b_box, _)| {
+            position_order((a_text, a_box), (b_text, b_box))
+        });
+        blocks.extend(placed.into_iter().map(|(_, _, block)| block));
+    }
+    v
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum TextChange {
+    Added { text: String },
+    Removed { text: String },
+    Retextualized { from: String, to: String },
+}
+
+#[derive(Serialize, Debug, Default)]
+struct CaseSummary {
+    case: String,
+    added: usize,
+    removed: usize,
+    retextualized: usize,
+    changes: Vec<TextChange>,
+}
+
+fn block_text_and_box(block: &Value) -> (String, Option<BoundingBox>) {
+    let text = block["text"].as_str().unwrap_or_default().to_string();
+    let bbox = block
+        .get("tightBoundingBox")
+        .and_then(|bbox| serde_json::from_value(bbox.clone()).ok());
+    (text, bbox)
+}
+
+fn overlap_ratio(a: &BoundingBox, b: &BoundingBox) -> f64 {
+    let width = (a.x + a.width).min(b.x + b.width) - a.x.max(b.x);
+    let height = (a.y + a.height).min(b.y + b.height) - a.y.max(b.y);
+    if width <= 0.0 || height <= 0.0 {
+        return 0.0;
+    }
+    let intersection = width * height;
+    intersection / (a.width * a.height + b.width * b.height - intersection)
+}
+
+/// What changed in a case's text between the expected file and this run. Blocks with the
+/// same text match up; a removed and an added block covering the same spot count as one
+/// block whose text changed. Orientation and merge flags are not summarized.
+fn summarize(case: &str, expected: &Value, actual: &Value) -> CaseSummary {
+    let blocks = |v: &Value| {
+        v.as_array()
+            .map(|blocks| blocks.iter().map(block_text_and_box).collect::<Vec<_>>())
+            .unwrap_or_default()
+    };
+    let mut removed = blocks(expected);
+    let mut added = Vec::new();
+    for block in blocks(actual) {
+        match removed.iter().position(|(text, _)| *text == block.0) {
+            Some(index) => {
+                removed.remove(index);
+            }
+            None => added.push(block),
+        }
+    }
+
+    let mut summary = CaseSummary {
+        case: case.to_string(),
+        ..CaseSummary::default()
+    };
+    for (from, from_box) in removed {
+        let paired = from_box.and_then(|from_box| {
+            added.iter().position(|(_, to_box)| {
+                to_box
+                    .as_ref()
+                    .is_some_and(|to_box| overlap_ratio(&from_box, to_box) >= 0.5)
+            })
+        });
+        match paired {
+            Some(index) => {
+                let (to, _) = added.remove(index);
+                summary.retextualized += 1;
+                summary.changes.push(TextChange::Retextualized { from, to });
+            }
+            None => {
+                summary.removed += 1;
+                summary.changes.push(TextChange::Removed { text: from });
+            }
+        }
+    }
+    for (text, _) in added {
+        summary.added += 1;
+        summary.changes.push(TextChange::Added { text });
+    }
+    summary
+}
+
 #[tokio::test]
 async fn run_merge_regression_tests() {
     // 1. Path Resolution
@@ -70,10 +242,28 @@ async fn run_merge_regression_tests() {
             .unwrap_or(test_data_path.clone())
     );
 
+    // Thresholds under test: `merge-config.json` in the data root, as saved through
+    // `/merge-config`, or the defaults.
+    let merge_config: MergeConfig = fs::read_to_string(test_data_path.join("merge-config.json"))
+        .ok()
+        .map(|content| serde_json::from_str(&content).expect("Parse merge-config.json"))
+        .unwrap_or_default();
+
     // Env flags
     let force_regen_raw = std::env::var("REGENERATE_RAW").is_ok();
     let only_generate_missing = std::env::var("ONLY_GENERATE_MISSING").is_ok();
     let update_expected = std::env::var("UPDATE_EXPECTED").is_ok();
+    // SUMMARY prints and saves only the text changes per case, e.g. alongside
+    // UPDATE_EXPECTED to review a regeneration. Its value is where the JSON goes,
+    // `merge-summary.json` in the data root when empty.
+    let summary_path = std::env::var("SUMMARY").ok().map(|path| {
+        if path.is_empty() {
+            test_data_path.join("merge-summary.json")
+        } else {
+            PathBuf::from(path)
+        }
+    });
+    let mut summaries = Vec::new();
 
     let mut passed = 0;
     let mut generated = 0;
@@ -118,10 +308,15 @@ async fn run_merge_regression_tests() {
                 } else {
                     println!("  [OCR] Running Lens OCR for {}...", test_name);
                     let image_bytes = fs::read(path).expect("Read image");
-                    let chunks =
-                        logic::get_raw_ocr_data(&image_bytes, None, None, OcrLanguage::default())
-                            .await
-                            .expect("Lens OCR failed");
+                    let chunks = logic::get_raw_ocr_data(
+                        &image_bytes,
+                        None,
+                        None,
+                        &Default::default(),
+                        OcrLanguage::default(),
+                    )
+                    .await
+                    .expect("Lens OCR failed");
 
                     let json = serde_json::to_string_pretty(&chunks).unwrap();
                     fs::write(&raw_cache_path, json).expect("Write raw cache");
@@ -129,31 +324,32 @@ async fn run_merge_regression_tests() {
                 };
 
                 // 2. Run Merge Logic
-                let config = MergeConfig::default();
-                let mut final_results = Vec::new();
-
-                for chunk in raw_chunks {
-                    let merged_lines =
-                        merge::auto_merge(chunk.lines, chunk.width, chunk.height, &config);
-
-                    for mut result in merged_lines {
-                        let global_pixel_y = result.tight_bounding_box.y + (chunk.global_y as f64);
-                        result.tight_bounding_box.x =
-                            result.tight_bounding_box.x / chunk.full_width as f64;
-                        result.tight_bounding_box.width =
-                            result.tight_bounding_box.width / chunk.full_width as f64;
-                        result.tight_bounding_box.y = global_pixel_y / chunk.full_height as f64;
-                        result.tight_bounding_box.height =
-                            result.tight_bounding_box.height / chunk.full_height as f64;
-                        final_results.push(result);
-                    }
-                }
+                let mut final_results = logic::merge_raw_chunks(
+                    raw_chunks,
+                    None,
+                    OcrLanguage::default(),
+                    &merge_config,
+                );
+                let mut emitted = serde_json::to_value(&final_results).expect("Serialize");
+                strip_fields(&mut emitted, UNSTORED_FIELDS);
+                canonical_order(&mut final_results);
 
                 // Sanitize
                 let mut actual_value = serde_json::to_value(&final_results).expect("Serialize");
-                sanitize_results(&mut actual_value);
+                strip_fields(&mut actual_value, UNSTORED_FIELDS);
                 let actual_json_str = serde_json::to_string_pretty(&actual_value).unwrap();
 
+                if summary_path.is_some() && !force_regen_raw {
+                    let expected = fs::read_to_string(&expected_path)
+                        .ok()
+                        .and_then(|content| serde_json::from_str(&content).ok())
+                        .unwrap_or(Value::Array(Vec::new()));
+                    let summary = summarize(&test_name, &expected, &actual_value);
+                    if !summary.changes.is_empty() {
+                        summaries.push(summary);
+                    }
+                }
+
                 // 3. Validation Logic
                 if expected_path.exists() {
                     if update_expected {
@@ -166,12 +362,12 @@ async fn run_merge_regression_tests() {
                         // STANDARD TEST mode
                         let expected_content =
                             fs::read_to_string(&expected_path).expect("Read expected");
-                        let mut expected: Value =
+                        let expected: Value =
                             serde_json::from_str(&expected_content).expect("Invalid JSON");
-                        sanitize_results(&mut expected);
 
+                        let (expected, emitted) = comparable(&expected, &emitted);
                         let p_exp = serde_json::to_string_pretty(&expected).unwrap();
-                        let p_act = serde_json::to_string_pretty(&actual_value).unwrap();
+                        let p_act = serde_json::to_string_pretty(&emitted).unwrap();
 
                         if p_act != p_exp {
                             println!(
@@ -197,6 +393,31 @@ async fn run_merge_regression_tests() {
         }
     }
 
+    if let Some(summary_path) = &summary_path {
+        for summary in &summaries {
+            println!(
+                "  [SUMMARY] {}: {} added, {} removed, {} retextualized",
+                summary.case, summary.added, summary.removed, summary.retextualized
+            );
+            for change in &summary.changes {
+                match change {
+                    TextChange::Added { text } => println!("      + {:?}", text),
+                    TextChange::Removed { text } => println!("      - {:?}", text),
+                    TextChange::Retextualized { from, to } => {
+                        println!("      ~ {:?} -> {:?}", from, to)
+                    }
+                }
+            }
+        }
+        let json = serde_json::to_string_pretty(&summaries).unwrap();
+        fs::write(summary_path, json).expect("Write summary");
+        println!(
+            "📝 {} changed cases summarized in {:?}",
+            summaries.len(),
+            summary_path
+        );
+    }
+
     if force_regen_raw {
         println!("✅ Raw Data Regeneration Complete.");
     } else if only_generate_missing {
@@ -221,3 +442,649 @@ async fn run_merge_regression_tests() {
         }
     }
 }
+
+/// Expected files are written and compared in position order, whatever order the blocks
+/// are listed in; the summary lists only text changes, pairing rewritten blocks by their boxes.
+#[test]
+fn expected_files_are_ordered_and_summarized_by_position() {
+    let mut results = vec![
+        horizontal_line("下", 100.0, 900.0, 400.0),
+        horizontal_line("右上", 900.0, 100.0, 400.0),
+        horizontal_line("左上", 100.0, 100.0, 400.0),
+    ];
+    canonical_order(&mut results);
+    let texts: Vec<&str> = results.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(texts, ["左上", "右上", "下"]);
+
+    let expected = serde_json::to_value(&results).expect("serialize");
+    results.reverse();
+    let (left, right) = comparable(
+        &expected,
+        &serde_json::to_value(&results).expect("serialize"),
+    );
+    assert_eq!(left, right);
+    let mut moved = results.clone();
+    moved[0].tight_bounding_box.y = 0.0;
+    let (left, right) = comparable(&expected, &serde_json::to_value(&moved).expect("serialize"));
+    assert_ne!(left, right);
+
+    results[0].text = "下の台詞".to_string();
+    results.push(horizontal_line("新しい", 100.0, 1500.0, 400.0));
+    results.retain(|r| r.text != "右上");
+    let actual = serde_json::to_value(&results).expect("serialize");
+    let summary = summarize("case", &expected, &actual);
+    assert_eq!(
+        (summary.added, summary.removed, summary.retextualized),
+        (1, 1, 1)
+    );
+    assert_eq!(
+        summary.changes,
+        [
+            TextChange::Removed {
+                text: "右上".to_string()
+            },
+            TextChange::Retextualized {
+                from: "下".to_string(),
+                to: "下の台詞".to_string()
+            },
+            TextChange::Added {
+                text: "新しい".to_string()
+            },
+        ]
+    );
+}
+
+/// Files without boxes cannot be put in position order, so they are compared with the
+/// merge output as it was emitted, right-to-left columns and all.
+#[test]
+fn boxless_expected_files_compare_in_emission_order() {
+    let emitted = vec![
+        vertical_line("右", 900.0, 100.0),
+        vertical_line("左", 100.0, 100.0),
+    ];
+    let mut expected = serde_json::to_value(&emitted).expect("serialize");
+    strip_fields(&mut expected, &["tightBoundingBox"]);
+    assert!(!has_boxes(&expected));
+
+    let (left, right) = comparable(
+        &expected,
+        &serde_json::to_value(&emitted).expect("serialize"),
+    );
+    assert_eq!(left, right);
+
+    let mut reordered = emitted.clone();
+    canonical_order(&mut reordered);
+    let (left, right) = comparable(
+        &expected,
+        &serde_json::to_value(&reordered).expect("serialize"),
+    );
+    assert_ne!(left, right);
+}
+
+fn vertical_line(text: &str, x: f64, y: f64) -> OcrResult {
+    OcrResult {
+        is_merged: Some(false),
+        forced_orientation: Some("vertical".into()),
+        ..common::line_at(
+            text,
+            BoundingBox {
+                x,
+                y,
+                width: 60.0,
+                height: 800.0,
+                rotation: None,
+            },
+        )
+    }
+}
+
+fn horizontal_line(text: &str, x: f64, y: f64, width: f64) -> OcrResult {
+    OcrResult {
+        is_merged: Some(false),
+        forced_orientation: Some("horizontal".into()),
+        ..common::line_at(
+            text,
+            BoundingBox {
+                x,
+                y,
+                width,
+                height: 50.0,
+                rotation: None,
+            },
+        )
+    }
+}
+
+/// A horizontally set Japanese page: detection must override the language's vertical
+/// default, merge the two-line bubble, and order blocks left to right, top to bottom.
+#[test]
+fn horizontal_page_reads_left_to_right() {
+    let lines = vec![
+        horizontal_line("夜まで待ってて", 100.0, 900.0, 550.0),
+        horizontal_line("もう帰るよ。疲れた", 1200.0, 220.0, 500.0),
+        horizontal_line("どこへ行くの？", 100.0, 200.0, 600.0),
+        horizontal_line("もう遅いよ", 100.0, 260.0, 500.0),
+    ];
+    assert_eq!(
+        merge::infer_orientation(&lines, OcrLanguage::Japanese),
+        TextOrientation::Horizontal
+    );
+
+    let raw_chunks = vec![RawChunk {
+        lines,
+        width: 1500,
+        height: 2000,
+        global_x: 0,
+        global_y: 0,
+        full_width: 1500,
+        full_height: 2000,
+    }];
+    let results = logic::merge_raw_chunks(
+        raw_chunks,
+        None,
+        OcrLanguage::Japanese,
+        &MergeConfig::default(),
+    );
+
+    let texts: Vec<&str> = results.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        [
+            "どこへ行くの？\nもう遅いよ",
+            "もう帰るよ。疲れた",
+            "夜まで待ってて"
+        ]
+    );
+    assert!(
+        results
+            .iter()
+            .all(|r| r.forced_orientation.as_deref() == Some("horizontal"))
+    );
+}
+
+/// Merged blocks keep a confidence averaged over their lines by length.
+#[test]
+fn merged_confidence_is_weighted_by_line_length() {
+    let mut question = horizontal_line("どこへ行くの？", 100.0, 200.0, 600.0);
+    question.confidence = Some(0.9);
+    let mut answer = horizontal_line("もう遅いよ", 100.0, 260.0, 500.0);
+    answer.confidence = Some(0.6);
+    let lone = horizontal_line("夜まで待ってて", 100.0, 900.0, 550.0);
+
+    let raw_chunks = vec![RawChunk {
+        lines: vec![question, answer, lone],
+        width: 1500,
+        height: 2000,
+        global_x: 0,
+        global_y: 0,
+        full_width: 1500,
+        full_height: 2000,
+    }];
+    let results = logic::merge_raw_chunks(
+        raw_chunks,
+        None,
+        OcrLanguage::Japanese,
+        &MergeConfig::default(),
+    );
+
+    let merged = results
+        .iter()
+        .find(|r| r.text == "どこへ行くの？\nもう遅いよ")
+        .expect("bubble is merged");
+    let confidence = merged.confidence.expect("merged confidence");
+    assert!((confidence - (0.9 * 7.0 + 0.6 * 5.0) / 12.0).abs() < 1e-9);
+    let lone = results
+        .iter()
+        .find(|r| r.text == "夜まで待ってて")
+        .expect("lone line");
+    assert_eq!(lone.confidence, None);
+}
+
+/// Blocks carry their median line thickness relative to the full image height.
+#[test]
+fn merged_blocks_carry_a_font_size_hint() {
+    let first = horizontal_line("一行目", 100.0, 200.0, 600.0);
+    let mut second = horizontal_line("二行目", 100.0, 255.0, 500.0);
+    second.tight_bounding_box.height = 56.0;
+    let mut third = horizontal_line("三行目", 100.0, 316.0, 550.0);
+    third.tight_bounding_box.height = 60.0;
+    let lone = horizontal_line("離れた行", 100.0, 900.0, 550.0);
+
+    let raw_chunks = vec![RawChunk {
+        lines: vec![first, second, third, lone],
+        width: 1500,
+        height: 2000,
+        global_x: 0,
+        global_y: 1000,
+        full_width: 1500,
+        full_height: 4000,
+    }];
+    let results = logic::merge_raw_chunks(
+        raw_chunks,
+        None,
+        OcrLanguage::Japanese,
+        &MergeConfig::default(),
+    );
+
+    let merged = results
+        .iter()
+        .find(|r| r.text == "一行目\n二行目\n三行目")
+        .expect("lines are merged");
+    let hint = merged.font_size_hint.expect("merged hint");
+    assert!((hint - 56.0 / 4000.0).abs() < 1e-9);
+    let lone = results
+        .iter()
+        .find(|r| r.text == "離れた行")
+        .expect("lone line");
+    let hint = lone.font_size_hint.expect("lone hint");
+    assert!((hint - 50.0 / 4000.0).abs() < 1e-9);
+}
+
+#[test]
+fn series_override_forces_orientation() {
+    let mut config = OcrConfig::default();
+    config
+        .orientation_overrides
+        .insert("42".to_string(), TextOrientation::Horizontal);
+
+    assert_eq!(
+        config.orientation_for("http://127.0.0.1:4568/api/v1/manga/42/chapter/3/page/0"),
+        Some(TextOrientation::Horizontal)
+    );
+    assert_eq!(
+        config.orientation_for("http://127.0.0.1:4568/api/v1/manga/7/chapter/3/page/0"),
+        None
+    );
+}
+
+/// A 6000x2200 double-page spread is OCR'd as two 3000px columns; boxes from the right
+/// column must land on the right half of the page once normalized.
+#[test]
+fn wide_spread_columns_normalize_against_full_width() {
+    let full_width = 6000;
+    let full_height = 2200;
+    let raw_chunks = vec![
+        RawChunk {
+            lines: vec![vertical_line("左のページの台詞です", 2500.0, 300.0)],
+            width: 3000,
+            height: full_height,
+            global_x: 0,
+            global_y: 0,
+            full_width,
+            full_height,
+        },
+        RawChunk {
+            lines: vec![vertical_line("右のページの台詞です", 500.0, 300.0)],
+            width: 3000,
+            height: full_height,
+            global_x: 3000,
+            global_y: 0,
+            full_width,
+            full_height,
+        },
+    ];
+
+    let results = logic::merge_raw_chunks(
+        raw_chunks,
+        None,
+        OcrLanguage::default(),
+        &MergeConfig::default(),
+    );
+    assert_eq!(results.len(), 2);
+
+    let left = &results[0].tight_bounding_box;
+    assert!((left.x - 2500.0 / 6000.0).abs() < 1e-9);
+    assert!((left.width - 60.0 / 6000.0).abs() < 1e-9);
+    assert!((left.y - 300.0 / 2200.0).abs() < 1e-9);
+
+    let right = &results[1].tight_bounding_box;
+    assert!((right.x - 3500.0 / 6000.0).abs() < 1e-9);
+    assert!((right.width - 60.0 / 6000.0).abs() < 1e-9);
+    assert!((right.height - 800.0 / 2200.0).abs() < 1e-9);
+}
+
+/// A bubble sitting on the 3000px seam of a tall page: the first strip only reads the top
+/// of its first line, the second strip (starting 200px earlier) reads it whole. The bubble
+/// must come out once, whole, where it sits on the page.
+#[test]
+fn bubble_on_chunk_seam_is_read_once() {
+    let full_width = 1500;
+    let full_height = 5800;
+    let mut cut_line = horizontal_line("どこへ行", 100.0, 2960.0, 350.0);
+    cut_line.tight_bounding_box.height = 40.0;
+    let raw_chunks = vec![
+        RawChunk {
+            lines: vec![
+                horizontal_line("夜まで待ってて", 100.0, 900.0, 550.0),
+                cut_line,
+            ],
+            width: full_width,
+            height: 3000,
+            global_x: 0,
+            global_y: 0,
+            full_width,
+            full_height,
+        },
+        RawChunk {
+            lines: vec![
+                horizontal_line("どこへ行くの？", 100.0, 160.0, 600.0),
+                horizontal_line("もう遅いよ", 100.0, 220.0, 500.0),
+                horizontal_line("もう帰るよ。疲れた", 100.0, 2000.0, 500.0),
+            ],
+            width: full_width,
+            height: 3000,
+            global_x: 0,
+            global_y: 2800,
+            full_width,
+            full_height,
+        },
+    ];
+
+    let results = logic::merge_raw_chunks(
+        raw_chunks,
+        None,
+        OcrLanguage::Japanese,
+        &MergeConfig::default(),
+    );
+
+    let texts: Vec<&str> = results.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        [
+            "夜まで待ってて",
+            "どこへ行くの？\nもう遅いよ",
+            "もう帰るよ。疲れた"
+        ]
+    );
+    let bubble = &results[1].tight_bounding_box;
+    assert!((bubble.y - 2960.0 / 5800.0).abs() < 1e-9);
+    assert!((bubble.height - 110.0 / 5800.0).abs() < 1e-9);
+}
+
+/// A line that fits inside the overlap band is read whole by both strips and kept once.
+#[test]
+fn line_inside_chunk_overlap_is_kept_once() {
+    let chunk = |global_y: u32, y: f64| RawChunk {
+        lines: vec![horizontal_line("もう遅いよ", 100.0, y, 500.0)],
+        width: 1500,
+        height: 3000,
+        global_x: 0,
+        global_y,
+        full_width: 1500,
+        full_height: 5800,
+    };
+    let results = logic::merge_raw_chunks(
+        vec![chunk(0, 2900.0), chunk(2800, 100.0)],
+        None,
+        OcrLanguage::Japanese,
+        &MergeConfig::default(),
+    );
+
+    assert_eq!(results.len(), 1);
+    assert!((results[0].tight_bounding_box.y - 2900.0 / 5800.0).abs() < 1e-9);
+}
+
+#[test]
+fn merge_config_rejects_invalid_thresholds() {
+    assert!(MergeConfig::default().validate().is_ok());
+
+    let negative_gap: MergeConfig =
+        serde_json::from_str(r#"{ "main_axis_gap": -0.5 }"#).expect("parse config");
+    assert!(negative_gap.validate().is_err());
+
+    let zero_ratio = MergeConfig {
+        font_size_ratio: 0.0,
+        ..MergeConfig::default()
+    };
+    assert!(zero_ratio.validate().is_err());
+
+    for invalid in [
+        r#"{ "max_gap_fraction": 0.0 }"#,
+        r#"{ "max_gap_fraction": 1.5 }"#,
+        r#"{ "min_overlap_ratio": -0.1 }"#,
+        r#"{ "max_lines_per_block": 0 }"#,
+    ] {
+        let config: MergeConfig = serde_json::from_str(invalid).expect("parse config");
+        assert!(config.validate().is_err(), "{invalid} should be rejected");
+    }
+
+    let stored = serde_json::to_value(MergeConfig::default()).expect("serialize config");
+    assert!(stored.get("language").is_none());
+}
+
+#[test]
+fn merge_override_parses_from_query_string_json() {
+    let uri: axum::http::Uri =
+        "/ocr?url=http%3A%2F%2Fhost%2Fpage.png&merge=%7B%22gap_scale%22%3A0.5%7D"
+            .parse()
+            .expect("uri");
+    let axum::extract::Query(request) =
+        axum::extract::Query::<handlers::OcrRequest>::try_from_uri(&uri).expect("query with merge");
+    let merge = request.merge.expect("merge override");
+    assert!((merge.gap_scale - 0.5).abs() < f64::EPSILON);
+    assert!((merge.font_size_ratio - 3.0).abs() < f64::EPSILON);
+
+    let request: handlers::OcrRequest =
+        serde_json::from_str(r#"{ "url": "u", "merge": { "enabled": false } }"#)
+            .expect("request with inline merge object");
+    assert!(request.merge.is_some_and(|merge| !merge.enabled));
+}
+
+/// Word boxes follow their lines through merging and are normalized with them.
+#[test]
+fn merged_words_keep_reading_order_and_page_coordinates() {
+    let word = |text: &str, x: f64, y: f64| WordBox {
+        text: text.to_string(),
+        tight_bounding_box: BoundingBox {
+            x,
+            y,
+            width: 100.0,
+            height: 50.0,
+            rotation: None,
+        },
+    };
+    let mut question = horizontal_line("どこへ行くの？", 100.0, 200.0, 600.0);
+    question.words = Some(vec![
+        word("どこへ", 100.0, 200.0),
+        word("行くの？", 400.0, 200.0),
+    ]);
+    let mut answer = horizontal_line("もう遅いよ", 100.0, 260.0, 500.0);
+    answer.words = Some(vec![
+        word("もう", 100.0, 260.0),
+        word("遅いよ", 300.0, 260.0),
+    ]);
+
+    let raw_chunks = vec![RawChunk {
+        lines: vec![answer, question],
+        width: 1500,
+        height: 2000,
+        global_x: 0,
+        global_y: 1000,
+        full_width: 1500,
+        full_height: 3000,
+    }];
+    let results = logic::merge_raw_chunks(
+        raw_chunks,
+        None,
+        OcrLanguage::Japanese,
+        &MergeConfig::default(),
+    );
+
+    assert_eq!(results.len(), 1);
+    let words = results[0].words.as_deref().expect("merged words");
+    let texts: Vec<&str> = words.iter().map(|w| w.text.as_str()).collect();
+    assert_eq!(texts, ["どこへ", "行くの？", "もう", "遅いよ"]);
+    let first = &words[0].tight_bounding_box;
+    assert!((first.x - 100.0 / 1500.0).abs() < 1e-9);
+    assert!((first.y - 1200.0 / 3000.0).abs() < 1e-9);
+    assert!((first.width - 100.0 / 1500.0).abs() < 1e-9);
+
+    let lines = Granularity::Line.apply(results.clone());
+    assert!(lines[0].words.is_none());
+    assert!(Granularity::Word.apply(results)[0].words.is_some());
+}
+
+/// A box whose shape says nothing about its direction, as Lens leaves upright ones.
+fn unlabelled_box(text: &str, x: f64, y: f64, width: f64, height: f64) -> OcrResult {
+    OcrResult {
+        is_merged: Some(false),
+        ..common::line_at(
+            text,
+            BoundingBox {
+                x,
+                y,
+                width,
+                height,
+                rotation: None,
+            },
+        )
+    }
+}
+
+/// A vertical page with a one-glyph reply under a column and a short caption over a
+/// horizontal credit line. Both boxes are slightly off-square, which a bare
+/// width-against-height comparison read the wrong way round.
+fn mixed_page(hint: OrientationHint) -> Vec<OcrResult> {
+    let raw_chunks = vec![RawChunk {
+        lines: vec![
+            vertical_line("今日は雨が降っている", 1000.0, 100.0),
+            vertical_line("傘を持ってきた", 920.0, 100.0),
+            unlabelled_box("え", 1010.0, 920.0, 44.0, 34.0),
+            unlabelled_box("次回", 100.0, 1730.0, 58.0, 62.0),
+            horizontal_line("作者のひとこと", 100.0, 1800.0, 400.0),
+        ],
+        width: 1500,
+        height: 2000,
+        global_x: 0,
+        global_y: 0,
+        full_width: 1500,
+        full_height: 2000,
+    }];
+    let config = MergeConfig {
+        orientation_hint: hint,
+        ..MergeConfig::default()
+    };
+    logic::merge_raw_chunks(raw_chunks, None, OcrLanguage::Japanese, &config)
+}
+
+fn orientation_of<'a>(results: &'a [OcrResult], text: &str) -> Option<&'a str> {
+    results
+        .iter()
+        .find(|r| r.text.contains(text))
+        .and_then(|r| r.forced_orientation.as_deref())
+}
+
+#[test]
+fn ambiguous_boxes_follow_their_neighbours() {
+    let results = mixed_page(OrientationHint::Auto);
+    assert_eq!(orientation_of(&results, "え"), Some("vertical"));
+    assert_eq!(orientation_of(&results, "次回"), Some("horizontal"));
+    assert_eq!(orientation_of(&results, "傘を持ってきた"), Some("vertical"));
+}
+
+#[test]
+fn orientation_hints_override_detection() {
+    let results = mixed_page(OrientationHint::Horizontal);
+    assert!(
+        results
+            .iter()
+            .all(|r| r.forced_orientation.as_deref() == Some("horizontal"))
+    );
+
+    let results = mixed_page(OrientationHint::ForceVerticalIfSquare);
+    assert_eq!(orientation_of(&results, "次回"), Some("vertical"));
+    assert_eq!(
+        orientation_of(&results, "作者のひとこと"),
+        Some("horizontal")
+    );
+
+    let hint: OrientationHint =
+        serde_json::from_str(r#""force-vertical-if-square""#).expect("parse hint");
+    assert_eq!(hint, OrientationHint::ForceVerticalIfSquare);
+}
+
+fn merged_texts(lines: Vec<OcrResult>, config: &MergeConfig) -> Vec<String> {
+    let raw_chunks = vec![RawChunk {
+        lines,
+        width: 1500,
+        height: 2000,
+        global_x: 0,
+        global_y: 0,
+        full_width: 1500,
+        full_height: 2000,
+    }];
+    logic::merge_raw_chunks(raw_chunks, None, OcrLanguage::Japanese, config)
+        .into_iter()
+        .map(|r| r.text)
+        .collect()
+}
+
+/// Two bubbles of two columns each, 70px apart: close enough for the font-relative
+/// gaps to fuse them, which a page-relative limit prevents.
+#[test]
+fn max_gap_fraction_splits_neighbouring_bubbles() {
+    let page = || {
+        vec![
+            vertical_line("右の吹き出しです", 1000.0, 300.0),
+            vertical_line("二行目もあります", 930.0, 300.0),
+            vertical_line("左の吹き出しです", 800.0, 300.0),
+            vertical_line("こちらも二行です", 730.0, 300.0),
+        ]
+    };
+    assert_eq!(merged_texts(page(), &MergeConfig::default()).len(), 1);
+
+    let config = MergeConfig {
+        max_gap_fraction: 0.02,
+        ..MergeConfig::default()
+    };
+    assert_eq!(
+        merged_texts(page(), &config),
+        [
+            "右の吹き出しです\n二行目もあります",
+            "左の吹き出しです\nこちらも二行です"
+        ]
+    );
+}
+
+#[test]
+fn min_overlap_ratio_keeps_staggered_columns_apart() {
+    let page = || {
+        vec![
+            vertical_line("上のほうの台詞です", 1000.0, 300.0),
+            vertical_line("下のほうの台詞です", 930.0, 700.0),
+        ]
+    };
+    assert_eq!(merged_texts(page(), &MergeConfig::default()).len(), 1);
+
+    let config = MergeConfig {
+        min_overlap_ratio: 0.6,
+        ..MergeConfig::default()
+    };
+    assert_eq!(merged_texts(page(), &config).len(), 2);
+}
+
+/// Evenly spaced columns chain into one block; a cap splits them, closest pairs first.
+#[test]
+fn max_lines_per_block_caps_merged_blocks() {
+    let page = || {
+        vec![
+            vertical_line("一列目の台詞です", 1000.0, 300.0),
+            vertical_line("二列目の台詞です", 930.0, 300.0),
+            vertical_line("三列目の台詞です", 860.0, 300.0),
+            vertical_line("四列目の台詞です", 790.0, 300.0),
+        ]
+    };
+    assert_eq!(merged_texts(page(), &MergeConfig::default()).len(), 1);
+
+    let config = MergeConfig {
+        max_lines_per_block: Some(2),
+        ..MergeConfig::default()
+    };
+    assert_eq!(
+        merged_texts(page(), &config),
+        [
+            "一列目の台詞です\n二列目の台詞です",
+            "三列目の台詞です\n四列目の台詞です"
+        ]
+    );
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use manatan_ocr_server::{
+    logic::{OcrOutcome, PhaseTimings},
+    merge::OrientationHint,
+    metrics::{self, Metrics, Path},
+    preprocess::Preprocess,
+};
+
+mod common;
+
+fn line<'a>(text: &'a str, series: &str) -> &'a str {
+    text.lines()
+        .find(|line| line.split(' ').next() == Some(series))
+        .unwrap_or_else(|| panic!("no {series} in\n{text}"))
+}
+
+#[test]
+fn render_reports_counters_histograms_and_gauges() {
+    let (state, dir) = common::temp_state("metrics");
+    let metrics = Metrics::default();
+
+    metrics.cache_hits(Path::Request, 3);
+    metrics.cache_misses(Path::Request, 1);
+    metrics.cache_misses(Path::Job, 5);
+    let outcome = OcrOutcome {
+        results: Vec::new(),
+        partial: false,
+        orientation: None,
+        orientation_hint: OrientationHint::Auto,
+        preprocess: Preprocess::None,
+        timings: PhaseTimings {
+            fetch: Duration::from_millis(300),
+            decode: Duration::from_millis(40),
+            recognize: Duration::from_millis(1500),
+            merge: Duration::from_millis(2),
+        },
+        image_hash: None,
+        raw: None,
+    };
+    metrics.page_done(Path::Job, &Ok(outcome), Duration::from_millis(1900));
+    metrics.page_done(
+        Path::Job,
+        &Err(anyhow!("lens failed")),
+        Duration::from_secs(90),
+    );
+
+    let text = metrics::render(&metrics, &state);
+    assert!(text.contains("# TYPE manatan_ocr_cache_hits_total counter"));
+    assert!(text.contains("# TYPE manatan_ocr_phase_duration_seconds histogram"));
+    assert_eq!(
+        line(&text, "manatan_ocr_cache_hits_total{path=\"request\"}"),
+        "manatan_ocr_cache_hits_total{path=\"request\"} 3"
+    );
+    assert!(text.contains("manatan_ocr_cache_misses_total{path=\"job\"} 5\n"));
+    assert!(text.contains("manatan_ocr_pages_total{path=\"job\",status=\"ok\"} 1\n"));
+    assert!(text.contains("manatan_ocr_pages_total{path=\"job\",status=\"failed\"} 1\n"));
+
+    // Buckets are cumulative; the failed page only counts towards the total.
+    let recognize = "manatan_ocr_phase_duration_seconds_bucket{path=\"job\",phase=\"recognize\"";
+    assert!(text.contains(&format!("{recognize},le=\"1\"}} 0\n")));
+    assert!(text.contains(&format!("{recognize},le=\"2.5\"}} 1\n")));
+    let total = "manatan_ocr_phase_duration_seconds_bucket{path=\"job\",phase=\"total\"";
+    assert!(text.contains(&format!("{total},le=\"2.5\"}} 1\n")));
+    assert!(text.contains(&format!("{total},le=\"60\"}} 1\n")));
+    assert!(text.contains(&format!("{total},le=\"+Inf\"}} 2\n")));
+    assert!(
+        text.contains(
+            "manatan_ocr_phase_duration_seconds_sum{path=\"job\",phase=\"merge\"} 0.002\n"
+        )
+    );
+
+    assert!(text.contains("manatan_ocr_active_jobs 0\n"));
+    assert!(text.contains("manatan_ocr_job_queue_depth 0\n"));
+    assert!(text.contains("# TYPE manatan_ocr_requests_processed_total counter"));
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,115 @@
+use manatan_ocr_server::{
+    logic::{BoundingBox, OcrResult, WordBox},
+    normalize::{self, TextNormalization},
+};
+
+mod common;
+
+const NONE: TextNormalization = TextNormalization {
+    strip_cjk_spaces: false,
+    nfkc: false,
+    collapse_duplicate_lines: false,
+};
+
+#[test]
+fn spaces_between_cjk_characters_are_dropped() {
+    let rules = TextNormalization {
+        strip_cjk_spaces: true,
+        ..NONE
+    };
+    assert_eq!(rules.normalize("どこへ 行く の？"), "どこへ行くの？");
+    assert_eq!(
+        rules.normalize("本当に　そう\n思う 。"),
+        "本当にそう\n思う。"
+    );
+    // Latin next to CJK, and Korean, keep their spaces.
+    assert_eq!(rules.normalize("OK です"), "OK です");
+    assert_eq!(rules.normalize("안녕 하세요"), "안녕 하세요");
+    assert_eq!(NONE.normalize("どこへ 行くの"), "どこへ 行くの");
+    assert_eq!(normalize::strip_cjk_spaces(" 行く  の "), " 行くの ");
+}
+
+#[test]
+fn nfkc_folds_width_variants() {
+    let rules = TextNormalization { nfkc: true, ..NONE };
+    assert_eq!(rules.normalize("ＡＢＣ１２３"), "ABC123");
+    assert_eq!(rules.normalize("ｶﾀｶﾅ"), "カタカナ");
+    assert_eq!(NONE.normalize("ＡＢＣ"), "ＡＢＣ");
+}
+
+#[test]
+fn repeated_lines_are_collapsed() {
+    let rules = TextNormalization {
+        collapse_duplicate_lines: true,
+        ..NONE
+    };
+    assert_eq!(
+        rules.normalize("きょう\nきょう\n今日は晴れ"),
+        "きょう\n今日は晴れ"
+    );
+    // Only neighbours count as repeats.
+    assert_eq!(rules.normalize("ああ\nいい\nああ"), "ああ\nいい\nああ");
+    assert_eq!(NONE.normalize("きょう\nきょう"), "きょう\nきょう");
+}
+
+#[test]
+fn results_and_their_words_are_cleaned() {
+    let bbox = BoundingBox {
+        x: 0.1,
+        y: 0.1,
+        width: 0.2,
+        height: 0.05,
+        rotation: None,
+    };
+    let word = |text: &str| WordBox {
+        text: text.to_string(),
+        tight_bounding_box: bbox.clone(),
+    };
+    let results = vec![OcrResult {
+        is_merged: Some(false),
+        words: Some(vec![
+            word("もう"),
+            word("遅 い"),
+            word("もう"),
+            word("遅い"),
+        ]),
+        ..common::line_at("もう 遅い\nもう 遅い", bbox.clone())
+    }];
+    let texts = |results: &[OcrResult]| -> Vec<String> {
+        results[0]
+            .words
+            .iter()
+            .flatten()
+            .map(|word| word.text.clone())
+            .collect()
+    };
+
+    // Repeated lines are kept unless asked for.
+    let mut kept = results.clone();
+    TextNormalization::default().apply(&mut kept);
+    assert_eq!(kept[0].text, "もう遅い\nもう遅い");
+    assert_eq!(texts(&kept), ["もう", "遅い", "もう", "遅い"]);
+
+    // A dropped repeat takes its words along.
+    let mut collapsed = results.clone();
+    TextNormalization {
+        collapse_duplicate_lines: true,
+        ..TextNormalization::default()
+    }
+    .apply(&mut collapsed);
+    assert_eq!(collapsed[0].text, "もう遅い");
+    assert_eq!(texts(&collapsed), ["もう", "遅い"]);
+
+    // Words that do not line up with the text are all kept.
+    let mut unaligned = vec![OcrResult {
+        words: Some(vec![word("まだ")]),
+        ..results[0].clone()
+    }];
+    TextNormalization {
+        collapse_duplicate_lines: true,
+        ..TextNormalization::default()
+    }
+    .apply(&mut unaligned);
+    assert_eq!(unaligned[0].text, "もう遅い");
+    assert_eq!(texts(&unaligned), ["まだ"]);
+}
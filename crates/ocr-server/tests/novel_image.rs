@@ -0,0 +1,60 @@
+use axum::{Json, extract::State, http::StatusCode};
+use manatan_ocr_server::handlers;
+
+mod common;
+
+fn request(image_path: &str, language: &str) -> handlers::NovelImageOcrRequest {
+    serde_json::from_value(serde_json::json!({
+        "book_id": "book",
+        "image_path": image_path,
+        "language": language,
+    }))
+    .expect("request")
+}
+
+#[tokio::test]
+async fn novel_images_are_cached_per_language() {
+    let (state, dir) = common::temp_state("novel-lang");
+    state.insert_cache_entry(
+        "lang/english/novel:book:images/1.png",
+        &common::entry("Novel", vec![common::line("Chapter One")]),
+    );
+
+    let Json(results) = handlers::ocr_novel_image_handler(
+        State(state.clone()),
+        Json(request("images/1.png", "en")),
+    )
+    .await
+    .expect("cached in English");
+    assert_eq!(results[0].text, "Chapter One");
+
+    // The English result is not served to a Japanese reader; the image is read again.
+    let (status, _) = handlers::ocr_novel_image_handler(
+        State(state.clone()),
+        Json(request("images/1.png", "ja")),
+    )
+    .await
+    .expect_err("no Japanese result and no image on disk");
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn unsafe_paths_are_rejected_before_the_cache_is_read() {
+    let (state, dir) = common::temp_state("novel-path");
+    state.insert_cache_entry(
+        "lang/japanese/novel:book:../secret.png",
+        &common::entry("Novel", vec![common::line("秘密")]),
+    );
+
+    let (status, _) = handlers::ocr_novel_image_handler(
+        State(state.clone()),
+        Json(request("../secret.png", "ja")),
+    )
+    .await
+    .expect_err("traversal");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,82 @@
+use std::time::Duration;
+
+use axum::{Router, routing::get};
+use futures::StreamExt;
+use manatan_ocr_server::{
+    backend::OcrBackend, handlers, language::OcrLanguage, logic, page_events::PageFilter,
+};
+use serde_json::Value;
+
+mod common;
+
+const CHAPTER: &str = "http://127.0.0.1:4568/api/v1/manga/3/chapter/7";
+
+fn page_key(url: &str) -> String {
+    logic::get_cache_key(url, Some(OcrLanguage::default()), &Default::default())
+}
+
+#[test]
+fn filter_maps_cache_keys_back_to_the_clients_urls() {
+    let filter = PageFilter::new(&format!("{CHAPTER}/?sourceId=1"), OcrLanguage::default());
+    let key = page_key(&format!("{CHAPTER}/page/2"));
+    assert_eq!(filter.page_url(&key), Some(format!("{CHAPTER}/page/2")));
+    assert_eq!(
+        filter.page_url(&OcrBackend::Tesseract.cache_key(&key)),
+        Some(format!("{CHAPTER}/page/2"))
+    );
+    assert_eq!(
+        filter.page_url(&page_key(&format!("{CHAPTER}0/page/2"))),
+        None
+    );
+    assert_eq!(
+        filter.page_url(&page_key(
+            "http://127.0.0.1:4568/api/v1/manga/3/chapter/8/page/2"
+        )),
+        None
+    );
+}
+
+#[tokio::test]
+async fn cached_pages_are_pushed_to_subscribed_sockets() {
+    let (state, dir) = common::temp_state("page-events");
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind server");
+    let addr = listener.local_addr().expect("server address");
+    let app = Router::new()
+        .route("/ws", get(handlers::page_updates_handler))
+        .with_state(state.clone());
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+
+    let (mut socket, _) =
+        tokio_tungstenite::connect_async(format!("ws://{addr}/ws?base_url={CHAPTER}"))
+            .await
+            .expect("connect");
+
+    // Another chapter's page is filtered out; this chapter's page comes through.
+    state.insert_cache_entry(
+        &page_key("http://127.0.0.1:4568/api/v1/manga/3/chapter/8/page/1"),
+        &common::entry("Chapter 8", Vec::new()),
+    );
+    state.insert_cache_entry(
+        &page_key(&format!("{CHAPTER}/page/1")),
+        &common::entry("Chapter 7", Vec::new()),
+    );
+
+    let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+        .await
+        .expect("page update")
+        .expect("open socket")
+        .expect("message");
+    let message: Value =
+        serde_json::from_str(message.to_text().expect("text")).expect("json message");
+    assert_eq!(message["page_url"], format!("{CHAPTER}/page/1"));
+    assert_eq!(message["context"], "Chapter 7");
+    assert_eq!(message["status"], "ready");
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
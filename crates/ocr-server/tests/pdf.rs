@@ -0,0 +1,146 @@
+use std::sync::Arc;
+
+use image::GenericImageView;
+use manatan_ocr_server::pdf;
+
+#[test]
+fn pdfs_are_recognized_by_their_header() {
+    assert!(pdf::is_pdf(b"%PDF-1.7\n%\xE2\xE3\xCF\xD3\n1 0 obj"));
+    // Some generators put junk before the header; readers look through the first kilobyte.
+    let mut padded = vec![b' '; 512];
+    padded.extend_from_slice(b"%PDF-1.4\n");
+    assert!(pdf::is_pdf(&padded));
+
+    assert!(!pdf::is_pdf(b"\x89PNG\r\n\x1a\n"));
+    assert!(!pdf::is_pdf(b"%PD"));
+    let mut too_late = vec![b' '; 2048];
+    too_late.extend_from_slice(b"%PDF-1.4\n");
+    assert!(!pdf::is_pdf(&too_late));
+}
+
+#[test]
+fn each_page_after_the_first_gets_its_own_cache_key() {
+    let key = "lang/japanese/local/book.pdf";
+    assert_eq!(pdf::page_cache_key(key, None), key);
+    assert_eq!(pdf::page_cache_key(key, Some(1)), key);
+    assert_eq!(
+        pdf::page_cache_key(key, Some(3)),
+        "lang/japanese/local/book.pdf#page=3"
+    );
+}
+
+#[test]
+fn later_pages_are_named_with_a_page_suffix() {
+    let url = "http://127.0.0.1:4568/local/book.pdf";
+    assert_eq!(pdf::split_page(url), (url, None));
+    assert_eq!(
+        pdf::split_page("http://127.0.0.1:4568/local/book.pdf#page=12"),
+        (url, Some(12))
+    );
+    assert_eq!(
+        pdf::split_page("http://127.0.0.1:4568/local/book.pdf#page=last"),
+        ("http://127.0.0.1:4568/local/book.pdf#page=last", None)
+    );
+    assert_eq!(
+        pdf::later_pages_pattern("lang/japanese/local/book.pdf"),
+        "lang/japanese/local/book.pdf#page=%"
+    );
+}
+
+/// A PDF with one page per `(width, height)` in points, each with a black 20pt square
+/// whose lower left corner sits at (10, 10).
+fn document(pages: &[(u32, u32)]) -> Vec<u8> {
+    let square = "0 0 0 rg 10 10 20 20 re f";
+    let kids: Vec<String> = (0..pages.len())
+        .map(|index| format!("{} 0 R", 3 + index * 2))
+        .collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            pages.len()
+        ),
+    ];
+    for (index, (width, height)) in pages.iter().enumerate() {
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {width} {height}] /Contents {} 0 R >>",
+            4 + index * 2
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{square}\nendstream",
+            square.len()
+        ));
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n{object}\nendobj\n", index + 1).as_bytes());
+    }
+    let xref = pdf.len();
+    pdf.extend_from_slice(
+        format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes(),
+    );
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+    pdf
+}
+
+/// Rendering needs the PDFium library, which CI images may not ship.
+fn pdfium_missing() -> bool {
+    if pdf::available() {
+        return false;
+    }
+    eprintln!("PDFium is not installed; skipping the rendering test");
+    true
+}
+
+#[tokio::test]
+async fn renders_the_requested_page_at_the_given_resolution() {
+    if pdfium_missing() {
+        return;
+    }
+    let bytes = Arc::new(document(&[(144, 72), (72, 72)]));
+    assert!(pdf::is_pdf(&bytes));
+    assert_eq!(pdf::page_count(bytes.clone()).await.expect("count"), 2);
+
+    let first = pdf::render_page(bytes.clone(), 1, 144)
+        .await
+        .expect("page 1");
+    let first = image::load_from_memory(&first).expect("png");
+    assert_eq!(first.dimensions(), (288, 144));
+
+    let second = pdf::render_page(bytes.clone(), 2, 72)
+        .await
+        .expect("page 2");
+    let second = image::load_from_memory(&second).expect("png").to_luma8();
+    assert_eq!(second.dimensions(), (72, 72));
+    // PDF space grows upwards, so the square sits near the bottom of the bitmap.
+    assert!(second.get_pixel(20, 52)[0] < 64, "inside the square");
+    assert!(second.get_pixel(60, 10)[0] > 192, "outside the square");
+
+    // The resolution is clamped rather than trusted.
+    let tiny = pdf::render_page(bytes.clone(), 2, 1)
+        .await
+        .expect("clamped");
+    let tiny = image::load_from_memory(&tiny).expect("png");
+    assert_eq!(tiny.dimensions(), (72, 72));
+
+    assert!(pdf::render_page(bytes.clone(), 3, 72).await.is_err());
+    assert!(pdf::render_page(bytes, 0, 72).await.is_err());
+    assert!(
+        pdf::render_page(Arc::new(b"%PDF-1.4\ngarbage".to_vec()), 1, 72)
+            .await
+            .is_err()
+    );
+}
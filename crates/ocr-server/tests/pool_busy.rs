@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use axum::{Router, http::StatusCode, routing::get};
+use manatan_ocr_server::{
+    handlers, locks, metrics,
+    metrics::METRICS,
+    state::{AppState, DbBusy},
+};
+
+mod common;
+
+fn temp_state(label: &str) -> (AppState, std::path::PathBuf) {
+    let (mut state, dir) = common::temp_state(&format!("pool-{label}"));
+    state.pool_wait = Duration::from_millis(50);
+    (state, dir)
+}
+
+async fn spawn_server(state: AppState) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind server");
+    let addr = listener.local_addr().expect("server address");
+    let app = Router::new()
+        .route("/", get(handlers::status_handler))
+        .with_state(state);
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+    format!("http://{addr}")
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn exhausted_pool_answers_service_unavailable() {
+    let (state, dir) = temp_state("exhausted");
+    let held: Vec<_> = (0..state.pool.max_size())
+        .map(|_| state.conn().expect("connection"))
+        .collect();
+    assert_eq!(state.conn().err(), Some(DbBusy));
+    // Lookups report the busy pool instead of answering as if the cache were empty.
+    assert_eq!(state.has_cache_entry("missing"), Err(DbBusy));
+    assert_eq!(state.get_chapter_progress("missing"), Err(DbBusy));
+
+    let base = spawn_server(state.clone()).await;
+    let client = reqwest::Client::new();
+    let response = client.get(&base).send().await.expect("request");
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+
+    drop(held);
+    let response = client.get(&base).send().await.expect("request");
+    assert_eq!(response.status(), StatusCode::OK);
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn poisoned_job_map_is_still_readable() {
+    let (state, dir) = temp_state("poisoned");
+    let jobs = state.active_chapter_jobs.clone();
+    let _ = std::thread::spawn(move || {
+        let _guard = jobs.write().expect("lock");
+        panic!("poison the job map");
+    })
+    .join();
+    assert!(state.active_chapter_jobs.is_poisoned());
+
+    assert!(locks::read(&state.active_chapter_jobs).is_empty());
+    assert!(metrics::render(&METRICS, &state).contains("manatan_ocr_"));
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,60 @@
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
+use manatan_ocr_server::preprocess::Preprocess;
+
+/// A dim, low-contrast page: luminance only spans 100..=140.
+fn washed_out(width: u32, height: u32) -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+        let value = 100 + ((x + y) % 41) as u8;
+        Rgba([value, value, value, 255])
+    }))
+}
+
+fn luma_range(image: &GrayImage) -> (u8, u8) {
+    image
+        .pixels()
+        .fold((u8::MAX, u8::MIN), |(low, high), Luma([value])| {
+            (low.min(*value), high.max(*value))
+        })
+}
+
+#[test]
+fn none_leaves_the_chunk_untouched() {
+    let image = washed_out(32, 32);
+    let result = Preprocess::None.apply(image.clone());
+    assert_eq!(result, image);
+}
+
+#[test]
+fn each_pipeline_builds_on_the_last() {
+    let gray = Preprocess::Grayscale.apply(washed_out(64, 64));
+    let gray = gray.as_luma8().expect("grayscale output");
+    assert_eq!(luma_range(gray), (100, 140));
+
+    let stretched = Preprocess::Contrast.apply(washed_out(64, 64));
+    let stretched = stretched.as_luma8().expect("grayscale output");
+    let (low, high) = luma_range(stretched);
+    assert!(low <= 5 && high >= 250, "range {low}..={high}");
+
+    let binary = Preprocess::Binarize.apply(washed_out(64, 64));
+    let binary = binary.as_luma8().expect("grayscale output");
+    assert!(
+        binary
+            .pixels()
+            .all(|Luma([value])| *value == 0 || *value == 255)
+    );
+}
+
+#[test]
+fn names_round_trip() {
+    for preprocess in [
+        Preprocess::None,
+        Preprocess::Grayscale,
+        Preprocess::Contrast,
+        Preprocess::Binarize,
+    ] {
+        assert_eq!(Preprocess::parse(preprocess.as_str()), Some(preprocess));
+        let json = serde_json::to_value(preprocess).expect("serialize");
+        assert_eq!(json, preprocess.as_str());
+    }
+    assert_eq!(Preprocess::parse("sharpen"), None);
+}
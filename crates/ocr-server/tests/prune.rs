@@ -0,0 +1,143 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use manatan_ocr_server::{
+    prune::{self, PruneOptions},
+    state::AppState,
+};
+use rusqlite::params;
+
+mod common;
+
+const DAY: i64 = 24 * 60 * 60;
+
+fn insert(state: &AppState, key: &str, created_days_ago: i64, accessed_days_ago: i64) {
+    insert_in(state, key, key, created_days_ago, accessed_days_ago);
+}
+
+fn insert_in(
+    state: &AppState,
+    key: &str,
+    context: &str,
+    created_days_ago: i64,
+    accessed_days_ago: i64,
+) {
+    state.insert_cache_entry(key, &common::entry(context, Vec::new()));
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time")
+        .as_secs() as i64;
+    state
+        .pool
+        .get()
+        .expect("conn")
+        .execute(
+            "UPDATE ocr_cache SET created_at = ?, last_accessed_at = ? WHERE cache_key = ?",
+            params![
+                now - created_days_ago * DAY,
+                now - accessed_days_ago * DAY,
+                key
+            ],
+        )
+        .expect("backdate");
+}
+
+#[test]
+fn prune_removes_old_rows_but_keeps_recently_read_ones() {
+    let (state, dir) = common::temp_state("prune");
+
+    insert(&state, "old-unread", 400, 300);
+    insert(&state, "old-but-reading", 400, 1);
+    insert(&state, "recent", 10, 10);
+
+    let report = prune::prune(
+        &state,
+        &PruneOptions {
+            older_than_days: Some(365),
+            ..PruneOptions::default()
+        },
+    )
+    .expect("prune");
+    assert_eq!(report.deleted_rows, 1);
+    assert!(!state.has_cache_entry("old-unread").expect("lookup"));
+    assert!(state.has_cache_entry("old-but-reading").expect("lookup"));
+    assert!(state.has_cache_entry("recent").expect("lookup"));
+
+    let report = prune::prune(
+        &state,
+        &PruneOptions {
+            max_total_bytes: Some(0),
+            ..PruneOptions::default()
+        },
+    )
+    .expect("prune");
+    assert_eq!(report.deleted_rows, 0);
+    assert!(state.has_cache_entry("old-but-reading").expect("lookup"));
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn reading_one_page_protects_its_whole_context() {
+    let (state, dir) = common::temp_state("prune-context");
+
+    insert_in(&state, "ch1/page/0", "Series / Ch. 1", 400, 300);
+    insert_in(&state, "ch1/page/1", "Series / Ch. 1", 400, 1);
+    insert_in(&state, "ch2/page/0", "Series / Ch. 2", 400, 300);
+
+    let report = prune::prune(
+        &state,
+        &PruneOptions {
+            older_than_days: Some(365),
+            ..PruneOptions::default()
+        },
+    )
+    .expect("prune");
+    assert_eq!(report.deleted_rows, 1);
+    assert!(state.has_cache_entry("ch1/page/0").expect("lookup"));
+    assert!(state.has_cache_entry("ch1/page/1").expect("lookup"));
+    assert!(!state.has_cache_entry("ch2/page/0").expect("lookup"));
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn day_counts_are_bounded() {
+    let huge = PruneOptions {
+        older_than_days: Some(365),
+        protect_recent_days: u64::MAX,
+        ..PruneOptions::default()
+    };
+    assert!(huge.validate().is_err());
+    assert!(
+        PruneOptions {
+            older_than_days: Some(prune::MAX_DAYS + 1),
+            ..PruneOptions::default()
+        }
+        .validate()
+        .is_err()
+    );
+    assert!(
+        PruneOptions::default().validate().is_err(),
+        "no limit given"
+    );
+    assert!(
+        PruneOptions {
+            older_than_days: Some(prune::MAX_DAYS),
+            protect_recent_days: prune::MAX_DAYS,
+            ..PruneOptions::default()
+        }
+        .validate()
+        .is_ok()
+    );
+
+    // Unvalidated options still must not overflow; everything is then protected.
+    let (state, dir) = common::temp_state("prune-huge");
+    insert(&state, "old", 400, 300);
+    let report = prune::prune(&state, &huge).expect("prune");
+    assert_eq!(report.deleted_rows, 0);
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
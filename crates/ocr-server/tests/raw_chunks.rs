@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+
+use manatan_ocr_server::{
+    backend::OcrBackend,
+    export::ExportFilter,
+    language::OcrLanguage,
+    logic::{BoundingBox, OcrOutcome, OcrResult, RawChunk, RawPage},
+    merge::{MergeConfig, OrientationHint},
+    preprocess::Preprocess,
+    state::{CacheEntry, EntrySource},
+};
+
+mod common;
+
+fn line(text: &str, x: f64, y: f64) -> OcrResult {
+    common::line_at(
+        text,
+        BoundingBox {
+            x,
+            y,
+            width: 40.0,
+            height: 200.0,
+            rotation: None,
+        },
+    )
+}
+
+fn raw_page() -> RawPage {
+    RawPage {
+        language: OcrLanguage::Japanese,
+        add_space_on_merge: None,
+        chunks: vec![RawChunk {
+            lines: vec![line("吹き出し", 100.0, 50.0)],
+            width: 800,
+            height: 1200,
+            global_x: 0,
+            global_y: 0,
+            full_width: 800,
+            full_height: 1200,
+        }],
+    }
+}
+
+fn outcome(raw: Option<RawPage>) -> OcrOutcome {
+    let raw_page = raw_page();
+    OcrOutcome {
+        results: raw_page.merge(&MergeConfig::default()),
+        partial: false,
+        orientation: None,
+        orientation_hint: OrientationHint::Auto,
+        preprocess: Preprocess::None,
+        timings: Default::default(),
+        image_hash: None,
+        raw,
+    }
+}
+
+#[test]
+fn raw_lines_are_kept_with_the_cached_page() {
+    let (state, dir) = common::temp_state("raw-chunks");
+    let key = "/manga/1/chapter/1/page/1";
+
+    assert!(state.raw_page(key).is_none());
+    state.cache_outcome(
+        key,
+        "Chapter 1".to_string(),
+        OcrBackend::Lens,
+        &outcome(Some(raw_page())),
+    );
+    let stored = state.raw_page(key).expect("raw lines");
+    assert_eq!(stored.language, OcrLanguage::Japanese);
+    assert_eq!(stored.chunks.len(), 1);
+    assert_eq!(stored.chunks[0].lines[0].text, "吹き出し");
+
+    // Merging the stored lines again gives the cached results back.
+    let cached = state
+        .get_cache_entry(key)
+        .expect("lookup")
+        .expect("cached page");
+    let remerged = stored.merge(&MergeConfig::default());
+    assert_eq!(remerged.len(), cached.data.len());
+    assert_eq!(remerged[0].text, cached.data[0].text);
+    assert_eq!(
+        remerged[0].tight_bounding_box.x,
+        cached.data[0].tight_bounding_box.x
+    );
+
+    // A later run without raw lines, such as Tesseract, drops the stale ones.
+    state.cache_outcome(
+        key,
+        "Chapter 1".to_string(),
+        OcrBackend::Tesseract,
+        &outcome(None),
+    );
+    assert!(state.raw_page(key).is_none());
+
+    // Manual pages never carry raw lines.
+    let manual_key = "/manga/1/chapter/1/page/2";
+    state.insert_cache_entry(
+        manual_key,
+        &CacheEntry {
+            source: EntrySource::Manual,
+            ..CacheEntry::from_outcome("Chapter 1".to_string(), OcrBackend::Lens, &outcome(None))
+        },
+    );
+    state.cache_outcome(
+        manual_key,
+        "Chapter 1".to_string(),
+        OcrBackend::Lens,
+        &outcome(Some(raw_page())),
+    );
+    assert!(state.raw_page(manual_key).is_none());
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn overwriting_imports_drop_raw_lines() {
+    let (state, dir) = common::temp_state("raw-import");
+    let key = "/manga/1/chapter/1/page/1";
+    state.cache_outcome(
+        key,
+        "Chapter 1".to_string(),
+        OcrBackend::Lens,
+        &outcome(Some(raw_page())),
+    );
+    assert!(state.raw_page(key).is_some());
+
+    let exported = state
+        .export_cache_batch(None, 10, &ExportFilter::default())
+        .expect("export");
+    state.import_cache(exported.into_iter().collect(), false);
+    assert!(state.raw_page(key).is_some());
+
+    let imported = common::entry("Chapter 1", vec![common::line("翻訳")]);
+    let report = state.import_cache(HashMap::from([(key.to_string(), imported)]), true);
+    assert_eq!(report.overwritten, 1);
+    assert!(state.raw_page(key).is_none());
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
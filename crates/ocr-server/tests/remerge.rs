@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+use manatan_ocr_server::{
+    backend::OcrBackend,
+    language::OcrLanguage,
+    logic::{BoundingBox, OcrOutcome, OcrResult, RawChunk, RawPage},
+    merge::{MergeConfig, OrientationHint},
+    preprocess::Preprocess,
+    remerge::{self, RemergeRequest, RemergeScope},
+    state::{AppState, CacheEntry, EntrySource},
+};
+
+mod common;
+
+fn line(text: &str) -> OcrResult {
+    common::line_at(
+        text,
+        BoundingBox {
+            x: 100.0,
+            y: 50.0,
+            width: 40.0,
+            height: 200.0,
+            rotation: None,
+        },
+    )
+}
+
+/// A page whose cached results are stale: they no longer match its raw lines.
+fn stale_outcome(raw: bool) -> OcrOutcome {
+    OcrOutcome {
+        results: vec![line("古い結果")],
+        partial: false,
+        orientation: None,
+        orientation_hint: OrientationHint::Auto,
+        preprocess: Preprocess::None,
+        timings: Default::default(),
+        image_hash: None,
+        raw: raw.then(|| RawPage {
+            language: OcrLanguage::Japanese,
+            add_space_on_merge: None,
+            chunks: vec![RawChunk {
+                lines: vec![line("吹き出し")],
+                width: 800,
+                height: 1200,
+                global_x: 0,
+                global_y: 0,
+                full_width: 800,
+                full_height: 1200,
+            }],
+        }),
+    }
+}
+
+fn cache(state: &AppState, key: &str, context: &str, raw: bool) {
+    state.cache_outcome(
+        key,
+        context.to_string(),
+        OcrBackend::Lens,
+        &stale_outcome(raw),
+    );
+}
+
+#[test]
+fn requests_resolve_to_the_narrowest_scope() {
+    let request = RemergeRequest {
+        all: true,
+        context: Some("Series".to_string()),
+        match_prefix: true,
+        ..RemergeRequest::default()
+    };
+    assert_eq!(
+        request.scope(&Default::default()),
+        Some(RemergeScope::Context {
+            context: "Series".to_string(),
+            prefix: true,
+        })
+    );
+    let request = RemergeRequest {
+        url: Some("http://host/api/v1/manga/1/chapter/2/page/3".to_string()),
+        ..request
+    };
+    assert_eq!(
+        request.scope(&Default::default()),
+        Some(RemergeScope::Url(
+            "lang/ja/api/v1/manga/1/chapter/2/page/3".to_string()
+        ))
+    );
+    assert_eq!(RemergeRequest::default().scope(&Default::default()), None);
+}
+
+#[test]
+fn stale_pages_are_rewritten_and_hand_edits_are_kept() {
+    let (state, dir) = common::temp_state("remerge-pages");
+    cache(
+        &state,
+        "/manga/1/chapter/1/page/1",
+        "Series: Chapter 1",
+        true,
+    );
+    cache(
+        &state,
+        "/manga/1/chapter/1/page/2",
+        "Series: Chapter 1",
+        false,
+    );
+    cache(
+        &state,
+        "/manga/1/chapter/2/page/1",
+        "Series: Chapter 2",
+        true,
+    );
+    cache(
+        &state,
+        "/manga/2/chapter/1/page/1",
+        "Other: Chapter 1",
+        true,
+    );
+    state
+        .edit_cache_entry("/manga/1/chapter/2/page/1", &[line("手直し")])
+        .expect("edit");
+    state.insert_cache_entry(
+        "/manga/1/chapter/3/page/1",
+        &CacheEntry {
+            source: EntrySource::Manual,
+            ..CacheEntry::from_outcome(
+                "Series: Chapter 3".to_string(),
+                OcrBackend::Lens,
+                &stale_outcome(false),
+            )
+        },
+    );
+
+    let scope = RemergeScope::Context {
+        context: "Series".to_string(),
+        prefix: true,
+    };
+    let (keys, skipped) = remerge::candidates(&state, &scope).expect("candidates");
+    assert_eq!(keys, vec!["/manga/1/chapter/1/page/1".to_string()]);
+    assert_eq!(skipped, 3);
+
+    let mut progress = Vec::new();
+    let report = remerge::remerge(&state, &keys, skipped, |current, changed| {
+        progress.push((current, changed));
+    });
+    assert_eq!(progress, vec![(1, 1)]);
+    assert_eq!((report.scanned, report.changed, report.skipped), (4, 1, 3));
+    let page = state
+        .get_cache_entry("/manga/1/chapter/1/page/1")
+        .expect("lookup")
+        .expect("page");
+    let expected = state
+        .raw_page("/manga/1/chapter/1/page/1")
+        .expect("raw")
+        .merge(&MergeConfig::default());
+    assert_eq!(page.data.len(), expected.len());
+    assert_eq!(page.data[0].text, "吹き出し");
+    assert_eq!(
+        page.data[0].tight_bounding_box.x,
+        expected[0].tight_bounding_box.x
+    );
+    assert!(page.orientation.is_some());
+
+    let edited = state
+        .get_cache_entry("/manga/1/chapter/2/page/1")
+        .expect("lookup")
+        .expect("edited page");
+    assert_eq!(edited.data[0].text, "手直し");
+
+    // A second run finds nothing left to change.
+    let report = remerge::remerge(&state, &keys, skipped, |_, _| {});
+    assert_eq!((report.changed, report.unchanged), (0, 1));
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn chapter_urls_cover_the_chapters_pages() {
+    let (state, dir) = common::temp_state("remerge-chapter");
+    cache(
+        &state,
+        "lang/ja/manga/1/chapter/1/page/1",
+        "Chapter 1",
+        true,
+    );
+    cache(
+        &state,
+        "lang/ja/manga/1/chapter/1/page/2",
+        "Chapter 1",
+        true,
+    );
+    cache(
+        &state,
+        "lang/ja/manga/1/chapter/2/page/1",
+        "Chapter 2",
+        true,
+    );
+    state.insert_chapter_cache(
+        "lang/ja/manga/1/chapter/1",
+        "lang/ja/manga/1/chapter/1/page/1",
+    );
+    state.insert_chapter_cache(
+        "lang/ja/manga/1/chapter/1",
+        "lang/ja/manga/1/chapter/1/page/2",
+    );
+
+    let request = RemergeRequest {
+        url: Some("http://host/manga/1/chapter/1".to_string()),
+        ..RemergeRequest::default()
+    };
+    let scope = request.scope(&Default::default()).expect("scope");
+    let (keys, skipped) = remerge::candidates(&state, &scope).expect("candidates");
+    assert_eq!(keys.len(), 2);
+    assert_eq!(skipped, 0);
+
+    let (keys, _) = remerge::candidates(&state, &RemergeScope::All).expect("candidates");
+    assert_eq!(keys.len(), 3);
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[tokio::test]
+async fn background_remerge_reports_when_done() {
+    let (state, dir) = common::temp_state("remerge-job");
+    cache(&state, "/manga/1/chapter/1/page/1", "Chapter 1", true);
+    let (keys, skipped) = remerge::candidates(&state, &RemergeScope::All).expect("candidates");
+
+    assert_eq!(remerge::spawn(&state, keys, skipped), Some(1));
+    let mut finished = false;
+    for _ in 0..200 {
+        if !state
+            .active_chapter_jobs
+            .read()
+            .expect("lock")
+            .contains_key(remerge::JOB_KEY)
+        {
+            finished = true;
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert!(finished, "re-merge should finish");
+    let report = remerge::last_report(&state).expect("report");
+    assert_eq!((report.scanned, report.changed), (1, 1));
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,151 @@
+use std::time::Duration;
+
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
+use manatan_ocr_server::{
+    handlers,
+    proxy::ProxyConfig,
+    retry::{CallTimeout, LensCallError, RetryPolicy, TimeoutSettings, is_retryable},
+};
+
+mod common;
+
+async fn spawn_status_server() -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind mock server");
+    let addr = listener.local_addr().expect("mock server address");
+    let app = Router::new()
+        .route("/missing.png", get(|| async { StatusCode::NOT_FOUND }))
+        .route("/flaky.png", get(|| async { StatusCode::BAD_GATEWAY }))
+        .route("/busy.png", get(|| async { StatusCode::TOO_MANY_REQUESTS }));
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+    format!("http://{addr}")
+}
+
+async fn status_error(url: &str) -> anyhow::Error {
+    let response = reqwest::Client::builder()
+        .no_proxy()
+        .build()
+        .expect("client")
+        .get(url)
+        .send()
+        .await
+        .expect("mock server reachable");
+    let err = response.error_for_status().expect_err("non-success status");
+    anyhow::Error::new(err).context("Failed error_for_status")
+}
+
+#[tokio::test]
+async fn classifies_http_statuses() {
+    let base = spawn_status_server().await;
+    assert!(!is_retryable(
+        &status_error(&format!("{base}/missing.png")).await
+    ));
+    assert!(is_retryable(
+        &status_error(&format!("{base}/flaky.png")).await
+    ));
+    assert!(is_retryable(
+        &status_error(&format!("{base}/busy.png")).await
+    ));
+}
+
+#[test]
+fn classifies_lens_and_decode_failures() {
+    let throttled = anyhow::Error::new(LensCallError("status: 429 Too Many Requests".into()));
+    assert!(is_retryable(&throttled));
+    let rejected = anyhow::Error::new(LensCallError("invalid image payload".into()));
+    assert!(!is_retryable(&rejected));
+    assert!(!is_retryable(&anyhow::anyhow!(
+        "Failed decode: unsupported format"
+    )));
+}
+
+/// A source that accepts the connection but never answers is cut off by the fetch timeout,
+/// and the timeout is retried like any other transient failure.
+#[tokio::test]
+async fn hung_downloads_time_out_as_retryable() {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("bind mock server");
+    let addr = listener.local_addr().expect("mock server address");
+    tokio::spawn(async move {
+        let mut held = Vec::new();
+        while let Ok((socket, _)) = listener.accept().await {
+            held.push(socket);
+        }
+    });
+
+    let client = ProxyConfig::default()
+        .http_client(Duration::from_millis(200))
+        .expect("client");
+    let err = tokio::time::timeout(
+        Duration::from_secs(5),
+        client.get(format!("http://{addr}/page.png")).send(),
+    )
+    .await
+    .expect("client gave up on its own")
+    .expect_err("server never answers");
+    assert!(err.is_timeout());
+    assert!(is_retryable(&anyhow::Error::new(err)));
+
+    assert!(is_retryable(&anyhow::Error::new(CallTimeout {
+        call: "Tesseract",
+        after: Duration::from_secs(60),
+    })));
+    let lens_timeout = CallTimeout {
+        call: "Lens call",
+        after: Duration::from_secs(60),
+    };
+    assert!(is_retryable(&anyhow::Error::new(LensCallError(
+        lens_timeout.to_string()
+    ))));
+}
+
+#[tokio::test]
+async fn timeout_settings_are_validated_and_persisted() {
+    let (state, dir) = common::temp_state("timeouts");
+
+    let Json(defaults) = handlers::get_timeout_settings_handler(State(state.clone())).await;
+    assert_eq!(defaults, TimeoutSettings::default());
+
+    let invalid = TimeoutSettings {
+        fetch_secs: 0,
+        ..TimeoutSettings::default()
+    };
+    let (status, _) = handlers::set_timeout_settings_handler(State(state.clone()), Json(invalid))
+        .await
+        .expect_err("zero timeout");
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let tuned = TimeoutSettings {
+        fetch_secs: 10,
+        ocr_call_secs: 45,
+    };
+    handlers::set_timeout_settings_handler(State(state.clone()), Json(tuned.clone()))
+        .await
+        .expect("valid settings");
+    assert_eq!(state.ocr_config().timeouts, tuned);
+    assert_eq!(state.ocr_config().timeouts.fetch(), Duration::from_secs(10));
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn backoff_doubles_with_jitter_and_caps() {
+    let policy = RetryPolicy {
+        attempts: 5,
+        base_delay: Duration::from_millis(200),
+    };
+    for retry in 1..=3 {
+        let full = Duration::from_millis(200 << (retry - 1));
+        let delay = policy.backoff(retry);
+        assert!(
+            delay >= full / 2 && delay <= full,
+            "retry {retry}: {delay:?}"
+        );
+    }
+    assert!(policy.backoff(30) <= Duration::from_secs(15));
+}
@@ -0,0 +1,138 @@
+use manatan_ocr_server::state::{AppState, CacheEntry};
+
+mod common;
+
+fn page(context: &str, lines: &[&str]) -> CacheEntry {
+    common::entry(
+        context,
+        lines.iter().map(|text| common::line(text)).collect(),
+    )
+}
+
+#[test]
+fn search_finds_cached_lines_with_their_page_and_chapter() {
+    let (state, dir) = common::temp_state("search-lines");
+
+    state.insert_cache_entry(
+        "lang/japanese/manga/1/chapter/1/page/0",
+        &page("Heike Ch. 1", &["祇園精舎の鐘の声", "諸行無常の響きあり"]),
+    );
+    state.insert_cache_entry(
+        "lang/japanese/manga/1/chapter/2/page/0",
+        &page("Heike Ch. 2", &["無常観について"]),
+    );
+    state.insert_cache_entry(
+        "lang/english/manga/2/chapter/1/page/0",
+        &page("Text Ch. 1", &["Say \"Hello\" to the text"]),
+    );
+    state.insert_chapter_cache(
+        "lang/japanese/manga/1/chapter/2",
+        "lang/japanese/manga/1/chapter/2/page/0",
+    );
+
+    let hits = state.search_text("無常観", None, 10).expect("lookup");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].context, "Heike Ch. 2");
+    assert_eq!(hits[0].snippet, "<mark>無常観</mark>について");
+    assert_eq!(
+        hits[0].chapter_key.as_deref(),
+        Some("lang/japanese/manga/1/chapter/2")
+    );
+    assert_eq!(
+        state.search_text("無常", None, 10).expect("lookup").len(),
+        2
+    );
+    assert_eq!(state.search_text("無常", None, 1).expect("lookup").len(), 1);
+
+    // Quotes are escaped in the stored JSON, and field names are not text.
+    assert_eq!(
+        state
+            .search_text("\"hello\"", None, 10)
+            .expect("lookup")
+            .len(),
+        1
+    );
+    assert_eq!(
+        state
+            .search_text("tightBoundingBox", None, 10)
+            .expect("lookup")
+            .len(),
+        0
+    );
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn search_narrows_to_a_context_and_highlights_matches() {
+    let (state, dir) = common::temp_state("search-context");
+
+    state.insert_cache_entry(
+        "lang/japanese/manga/1/chapter/1/page/3",
+        &page("約束のネバーランド / 第1話", &["ずっと一緒だよ、約束する"]),
+    );
+    state.insert_cache_entry(
+        "lang/japanese/manga/2/chapter/1/page/0",
+        &page("別の作品 / 第1話", &["約束は守るよ"]),
+    );
+    state.insert_cache_entry(
+        "lang/english/manga/3/chapter/1/page/0",
+        &page(
+            "Tags",
+            &["Use <b> & 50% OFF_now, really, it's a deal for everyone"],
+        ),
+    );
+
+    assert_eq!(
+        state.search_text("約束", None, 10).expect("lookup").len(),
+        2
+    );
+    let hits = state
+        .search_text("約束", Some("約束のネバーランド"), 10)
+        .expect("lookup");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].cache_key, "lang/japanese/manga/1/chapter/1/page/3");
+    assert_eq!(hits[0].snippet, "ずっと一緒だよ、<mark>約束</mark>する");
+
+    // LIKE wildcards in the query are literal, and snippets are HTML-escaped.
+    assert_eq!(state.search_text("0%", None, 10).expect("lookup").len(), 1);
+    assert_eq!(state.search_text("a_d", None, 10).expect("lookup").len(), 0);
+    let hits = state.search_text("50% off", None, 10).expect("lookup");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(
+        hits[0].snippet,
+        "Use &lt;b&gt; &amp; <mark>50% OFF</mark>_now, really, it's a…"
+    );
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn existing_pages_are_indexed_on_startup() {
+    let (state, dir) = common::temp_state("search-backfill");
+    state.insert_cache_entry(
+        "lang/japanese/manga/1/chapter/1/page/0",
+        &page("Heike Ch. 1", &["諸行無常の響きあり"]),
+    );
+    // An index left empty, as on a cache written before search was indexed.
+    if state.text_index {
+        let conn = state.pool.get().expect("connection");
+        conn.execute("DELETE FROM ocr_text", [])
+            .expect("clear index");
+    }
+    drop(state);
+
+    let state = AppState::new(dir.clone(), dir.clone());
+    assert_eq!(
+        state
+            .search_text("諸行無常", None, 10)
+            .expect("lookup")
+            .len(),
+        1
+    );
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use axum::{
+    Json,
+    extract::{Query, State},
+};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use manatan_ocr_server::{
+    backend::OcrBackend,
+    context::ContextIds,
+    handlers::{self, SpreadQuery, SpreadRequest},
+    language::OcrLanguage,
+    logic::{self, BoundingBox, CacheKeyConfig, Granularity, OcrResult},
+    spread::{self, SpreadPage},
+};
+
+mod common;
+
+const CHAPTER: &str = "http://127.0.0.1:4568/api/v1/manga/4/chapter/2";
+
+fn result(text: &str, x: f64, width: f64) -> OcrResult {
+    OcrResult {
+        is_merged: Some(false),
+        forced_orientation: Some("vertical".into()),
+        ..common::line_at(
+            text,
+            BoundingBox {
+                x,
+                y: 0.2,
+                width,
+                height: 0.3,
+                rotation: None,
+            },
+        )
+    }
+}
+
+#[test]
+fn pages_are_stitched_side_by_side() {
+    let left = DynamicImage::ImageRgba8(RgbaImage::from_pixel(300, 400, Rgba([0, 0, 0, 255])));
+    let right = DynamicImage::ImageRgba8(RgbaImage::from_pixel(100, 200, Rgba([255, 0, 0, 255])));
+
+    let (bytes, split) = spread::stitch(&left, &right).expect("stitch");
+    assert!((split - 0.75).abs() < 1e-9);
+    let spread = logic::decode_image(&bytes).expect("png");
+    assert_eq!(spread.dimensions(), (400, 400));
+    assert_eq!(spread.get_pixel(10, 10), Rgba([0, 0, 0, 255]));
+    assert_eq!(spread.get_pixel(310, 10), Rgba([255, 0, 0, 255]));
+    // The shorter page is padded with white below it.
+    assert_eq!(spread.get_pixel(310, 300), Rgba([255, 255, 255, 255]));
+}
+
+#[test]
+fn boxes_belong_to_the_page_holding_most_of_them() {
+    let results = spread::assign_pages(
+        vec![
+            result("左", 0.1, 0.1),
+            result("跨ぐ左寄り", 0.4, 0.15),
+            result("跨ぐ右寄り", 0.45, 0.2),
+            result("右", 0.8, 0.1),
+        ],
+        0.5,
+    );
+    let pages: Vec<SpreadPage> = results.iter().map(|r| r.page).collect();
+    assert_eq!(
+        pages,
+        [
+            SpreadPage::Left,
+            SpreadPage::Left,
+            SpreadPage::Right,
+            SpreadPage::Right
+        ]
+    );
+    let json = serde_json::to_value(&results[3]).expect("json");
+    assert_eq!(json["page"], "right");
+    assert_eq!(json["text"], "右");
+}
+
+#[tokio::test]
+async fn cached_spreads_are_served_with_their_pages() {
+    let (state, dir) = common::temp_state("spread");
+    let (left, right) = (format!("{CHAPTER}/page/3"), format!("{CHAPTER}/page/4"));
+
+    let cache_key = OcrBackend::Lens.cache_key(&spread::cache_key(
+        &logic::get_cache_key(&left, Some(OcrLanguage::default()), &Default::default()),
+        &logic::get_cache_key(&right, Some(OcrLanguage::default()), &Default::default()),
+    ));
+    assert!(spread::split(&state, &cache_key).is_none());
+    state.insert_cache_entry(
+        &cache_key,
+        &common::entry(
+            "Spread",
+            vec![result("ひだり", 0.2, 0.1), result("みぎ", 0.7, 0.1)],
+        ),
+    );
+    spread::record_split(&state, &cache_key, 0.5).expect("record split");
+
+    let request = SpreadRequest {
+        left,
+        right,
+        user: None,
+        pass: None,
+        context: "Spread".to_string(),
+        ids: ContextIds::default(),
+        add_space_on_merge: None,
+        language: None,
+        backend: None,
+        granularity: Granularity::Line,
+        headers: HashMap::new(),
+        cookies: None,
+        token: None,
+    };
+    let Json(body) = handlers::ocr_spread_handler(
+        State(state.clone()),
+        Query(SpreadQuery::default()),
+        Json(request),
+    )
+    .await
+    .expect("cache hit");
+    assert_eq!(body["split"], 0.5);
+    assert_eq!(body["results"][0]["page"], "left");
+    assert_eq!(body["results"][1]["page"], "right");
+    assert_eq!(body["results"][1]["text"], "みぎ");
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn spread_keys_survive_key_migration_and_are_deleted_with_their_gutter() {
+    let (state, dir) = common::temp_state("spread-upkeep");
+    let key_config = CacheKeyConfig::default();
+    let page_key = |page: u32| {
+        logic::get_cache_key(
+            &format!("{CHAPTER}/page/{page}?updatedAt=1700"),
+            Some(OcrLanguage::default()),
+            &key_config,
+        )
+    };
+    let cache_key = spread::cache_key(&page_key(3), &page_key(4));
+    let strip_updated = CacheKeyConfig {
+        strip_params: vec!["updatedAt".to_string()],
+        keep_params: None,
+    };
+    assert_eq!(strip_updated.normalize(&cache_key), cache_key);
+
+    state.insert_cache_entry(&cache_key, &common::entry("Spread", vec![]));
+    spread::record_split(&state, &cache_key, 0.5).expect("record split");
+    let report = state.migrate_cache_keys(&strip_updated).expect("migrate");
+    assert_eq!(report.rewritten, 0);
+    assert_eq!(spread::split(&state, &cache_key), Some(0.5));
+
+    assert_eq!(state.delete_cache_by_context("Spread", false), 1);
+    assert!(spread::split(&state, &cache_key).is_none());
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -0,0 +1,94 @@
+use std::io::{Cursor, Read};
+
+use manatan_ocr_server::{state::CacheEntry, text_export};
+
+mod common;
+
+fn page(context: &str, lines: &[&str]) -> CacheEntry {
+    common::entry(
+        context,
+        lines.iter().map(|text| common::line(text)).collect(),
+    )
+}
+
+#[test]
+fn page_index_reads_the_page_segment() {
+    assert_eq!(
+        text_export::page_index("/api/v1/manga/3/chapter/12/page/10?updatedAt=5"),
+        Some(10)
+    );
+    assert_eq!(
+        text_export::page_index("tesseract//manga/3/chapter/12/page/2"),
+        Some(2)
+    );
+    assert_eq!(
+        text_export::page_index("https://cdn.example/12/007"),
+        Some(7)
+    );
+    assert_eq!(
+        text_export::page_index("https://cdn.example/cover.jpg"),
+        None
+    );
+}
+
+#[test]
+fn chapter_text_is_ordered_by_page_number() {
+    let (state, dir) = common::temp_state("text-export-chapter");
+    let chapter = "約束のネバーランド / 第1話";
+    state.insert_cache_entry("/manga/1/chapter/1/page/10", &page(chapter, &["最後"]));
+    state.insert_cache_entry(
+        "/manga/1/chapter/1/page/2",
+        &page(chapter, &["二枚目", "です"]),
+    );
+    state.insert_cache_entry("/manga/1/chapter/1/page/0", &page(chapter, &["始まり"]));
+    state.insert_cache_entry(
+        "tesseract//manga/1/chapter/1/page/0",
+        &page(chapter, &["重複"]),
+    );
+    state.insert_cache_entry("/manga/1/chapter/2/page/0", &page("次の話", &["別"]));
+
+    let text = text_export::context_text(&state, chapter)
+        .expect("export")
+        .expect("cached pages");
+    assert_eq!(
+        text,
+        "--- Page 1 ---\n始まり\n\n--- Page 2 ---\n二枚目\nです\n\n--- Page 3 ---\n最後\n"
+    );
+    assert!(
+        text_export::context_text(&state, "未読")
+            .expect("export")
+            .is_none()
+    );
+    assert_eq!(
+        text_export::file_name(chapter),
+        "約束のネバーランド _ 第1話.txt"
+    );
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn all_contexts_export_as_a_zip_of_text_files() {
+    let (state, dir) = common::temp_state("text-export-all");
+    state.insert_cache_entry("/manga/1/chapter/1/page/0", &page("Ch. 1", &["一"]));
+    state.insert_cache_entry("/manga/1/chapter/2/page/0", &page("Ch. 2", &["二"]));
+    state.insert_cache_entry("/manga/2/chapter/1/page/0", &page("Ch: 1", &["三"]));
+
+    let mut bytes = Vec::new();
+    text_export::write_all_zip(&state, &mut bytes).expect("zip export");
+    let mut zip = zip::ZipArchive::new(Cursor::new(bytes)).expect("readable zip");
+    let mut names: Vec<String> = zip.file_names().map(str::to_string).collect();
+    names.sort();
+    assert_eq!(names, ["Ch. 1.txt", "Ch. 2.txt", "Ch_ 1.txt"]);
+
+    let mut text = String::new();
+    zip.by_name("Ch. 2.txt")
+        .expect("entry")
+        .read_to_string(&mut text)
+        .expect("text");
+    assert_eq!(text, "--- Page 1 ---\n二\n");
+
+    drop(state);
+    let _ = std::fs::remove_dir_all(dir);
+}
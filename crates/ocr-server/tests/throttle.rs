@@ -0,0 +1,114 @@
+use std::time::Duration;
+
+use manatan_ocr_server::{
+    backend::OcrBackend,
+    throttle::{LensLimiter, LensPacer},
+};
+
+#[test]
+fn latency_spike_after_warmup_starts_pacing() {
+    let pacer = LensPacer::default();
+    for _ in 0..5 {
+        pacer.record_success(Duration::from_millis(100));
+    }
+    assert_eq!(pacer.current_delay(), Duration::ZERO);
+    assert_eq!(pacer.snapshot().baseline_latency_ms, 100);
+
+    pacer.record_success(Duration::from_millis(1000));
+    let snapshot = pacer.snapshot();
+    assert_eq!(pacer.current_delay(), Duration::from_millis(500));
+    assert_eq!(snapshot.throttle_events, 1);
+    assert!(snapshot.throttled_since.is_some());
+    assert_eq!(
+        snapshot.baseline_latency_ms, 100,
+        "spikes do not move the baseline"
+    );
+}
+
+#[test]
+fn slow_first_samples_are_not_treated_as_throttling() {
+    let pacer = LensPacer::default();
+    pacer.record_success(Duration::from_millis(100));
+    pacer.record_success(Duration::from_millis(2000));
+    assert_eq!(pacer.current_delay(), Duration::ZERO);
+    assert_eq!(pacer.snapshot().throttle_events, 0);
+}
+
+#[test]
+fn quota_errors_back_off_and_successes_recover() {
+    let pacer = LensPacer::default();
+    pacer.record_error("connection reset by peer");
+    assert_eq!(pacer.current_delay(), Duration::ZERO);
+
+    pacer.record_error("HTTP 429 Too Many Requests");
+    assert_eq!(pacer.current_delay(), Duration::from_millis(500));
+    pacer.record_error("RESOURCE_EXHAUSTED: quota");
+    assert_eq!(pacer.current_delay(), Duration::from_secs(1));
+    for _ in 0..10 {
+        pacer.record_error("503 Service Unavailable");
+    }
+    assert_eq!(
+        pacer.current_delay(),
+        Duration::from_secs(30),
+        "the delay is capped"
+    );
+
+    for _ in 0..30 {
+        pacer.record_success(Duration::from_millis(100));
+    }
+    let snapshot = pacer.snapshot();
+    assert_eq!(pacer.current_delay(), Duration::ZERO);
+    assert!(snapshot.throttled_since.is_none());
+    assert_eq!(snapshot.throttle_events, 12);
+}
+
+#[tokio::test]
+async fn limiter_caps_lens_calls_and_leaves_local_backends_alone() {
+    let quick = Duration::from_millis(50);
+    let limiter = LensLimiter::new(2);
+    assert!(limiter.acquire(OcrBackend::Tesseract).await.is_none());
+
+    let first = limiter.acquire(OcrBackend::Lens).await;
+    let second = limiter.acquire(OcrBackend::Lens).await;
+    assert!(first.is_some() && second.is_some());
+    assert_eq!(limiter.snapshot().in_use, 2);
+    assert!(
+        tokio::time::timeout(quick, limiter.acquire(OcrBackend::Lens))
+            .await
+            .is_err()
+    );
+
+    drop(first);
+    let third = tokio::time::timeout(quick, limiter.acquire(OcrBackend::Lens))
+        .await
+        .expect("a returned permit is handed on");
+    assert!(third.is_some());
+    assert_eq!(limiter.snapshot().in_use, 2);
+    drop((second, third));
+    assert_eq!(limiter.snapshot().in_use, 0);
+}
+
+#[tokio::test]
+async fn limiter_can_be_resized_while_in_use() {
+    let quick = Duration::from_millis(50);
+    let limiter = LensLimiter::new(1);
+    let held = limiter.acquire(OcrBackend::Lens).await;
+
+    limiter.set_limit(2);
+    assert_eq!(limiter.snapshot().limit, 2);
+    let extra = tokio::time::timeout(quick, limiter.acquire(OcrBackend::Lens))
+        .await
+        .expect("growing adds a permit at once");
+
+    limiter.set_limit(1);
+    drop((held, extra));
+    let only = limiter.acquire(OcrBackend::Lens).await;
+    assert!(
+        tokio::time::timeout(quick, limiter.acquire(OcrBackend::Lens))
+            .await
+            .is_err(),
+        "shrinking takes back the surplus permit"
+    );
+    drop(only);
+    assert_eq!(LensLimiter::new(0).snapshot().limit, 1);
+}
@@ -103,9 +103,15 @@ async fn validate_expected_is_subset_of_raw() {
                 } else {
                     println!("   -> Generating raw data from image...");
                     let image_bytes = fs::read(path).expect("Failed to read image");
-                    logic::get_raw_ocr_data(&image_bytes, None, None, OcrLanguage::default())
-                        .await
-                        .expect("Failed to perform OCR extraction")
+                    logic::get_raw_ocr_data(
+                        &image_bytes,
+                        None,
+                        None,
+                        &Default::default(),
+                        OcrLanguage::default(),
+                    )
+                    .await
+                    .expect("Failed to perform OCR extraction")
                 };
 
                 // 2. Extract Raw Text
@@ -0,0 +1,182 @@
+//! Disk space guard shared by the sub-servers. Large writes call [`ensure_space`] first so
+//! a full disk fails the request up front instead of leaving half-written files behind, and
+//! each data directory is [`register`]ed so the health and metrics endpoints can report it.
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        LazyLock, Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use manatan_events::Event;
+use serde::Serialize;
+
+const MIB: u64 = 1024 * 1024;
+
+static LIMITS: RwLock<DiskLimits> = RwLock::new(DiskLimits::DEFAULT);
+static DIRECTORIES: LazyLock<Mutex<BTreeMap<&'static str, PathBuf>>> =
+    LazyLock::new(Default::default);
+/// Set while free space is below the warning threshold, so each dip is announced once.
+static WARNED: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct DiskLimits {
+    /// Writes that would leave less free space than this are rejected.
+    pub floor_bytes: u64,
+    /// Free space below this publishes [`Event::DiskSpaceLow`].
+    pub warn_bytes: u64,
+}
+
+impl DiskLimits {
+    const DEFAULT: Self = Self {
+        floor_bytes: 512 * MIB,
+        warn_bytes: 2048 * MIB,
+    };
+}
+
+impl Default for DiskLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Replaces the process-wide limits, e.g. with the launcher's command line options.
+pub fn configure(limits: DiskLimits) {
+    if let Ok(mut current) = LIMITS.write() {
+        *current = limits;
+    }
+}
+
+pub fn limits() -> DiskLimits {
+    LIMITS.read().map(|limits| *limits).unwrap_or_default()
+}
+
+/// A write refused because it would take free space below the floor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InsufficientSpace {
+    pub available_bytes: u64,
+    pub needed_bytes: u64,
+    pub floor_bytes: u64,
+}
+
+impl fmt::Display for InsufficientSpace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Not enough free disk space: {} MiB needed but {} MiB available, and {} MiB must stay free",
+            self.needed_bytes.div_ceil(MIB),
+            self.available_bytes / MIB,
+            self.floor_bytes / MIB
+        )
+    }
+}
+
+impl std::error::Error for InsufficientSpace {}
+
+/// Free space on the filesystem holding `path`. The path need not exist yet; its nearest
+/// existing ancestor is measured.
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    let existing = path
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .unwrap_or(Path::new("."));
+    fs4::available_space(existing)
+}
+
+/// Checks that writing `incoming_bytes` under `path` keeps the configured floor free, and
+/// announces low space once the write would cross the warning threshold. Writes are let
+/// through when free space can't be measured.
+pub fn ensure_space(path: &Path, incoming_bytes: u64) -> Result<(), InsufficientSpace> {
+    let Ok(available) = available_space(path) else {
+        return Ok(());
+    };
+    let limits = limits();
+    note_free_space(available.saturating_sub(incoming_bytes), &limits);
+    check_space(available, incoming_bytes, &limits)
+}
+
+/// The decision [`ensure_space`] makes once free space is known.
+pub fn check_space(
+    available_bytes: u64,
+    incoming_bytes: u64,
+    limits: &DiskLimits,
+) -> Result<(), InsufficientSpace> {
+    if available_bytes < incoming_bytes.saturating_add(limits.floor_bytes) {
+        return Err(InsufficientSpace {
+            available_bytes,
+            needed_bytes: incoming_bytes,
+            floor_bytes: limits.floor_bytes,
+        });
+    }
+    Ok(())
+}
+
+fn note_free_space(free_bytes: u64, limits: &DiskLimits) {
+    if free_bytes >= limits.warn_bytes {
+        WARNED.store(false, Ordering::Relaxed);
+    } else if !WARNED.swap(true, Ordering::Relaxed) {
+        manatan_events::publish(Event::DiskSpaceLow {
+            available_bytes: free_bytes,
+            warn_bytes: limits.warn_bytes,
+        });
+    }
+}
+
+/// Lists `path` under `name` in [`usage`]. Registering a name again replaces its path.
+pub fn register(name: &'static str, path: impl Into<PathBuf>) {
+    if let Ok(mut directories) = DIRECTORIES.lock() {
+        directories.insert(name, path.into());
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DirectoryUsage {
+    pub name: &'static str,
+    pub size_bytes: u64,
+    /// Free space on the directory's filesystem, unset when it can't be measured.
+    pub available_bytes: Option<u64>,
+}
+
+/// Size and free space of every registered directory, by name. Walks each directory, so
+/// this is meant for health checks and scrapes rather than hot paths.
+pub fn usage() -> Vec<DirectoryUsage> {
+    let directories = DIRECTORIES
+        .lock()
+        .map(|directories| directories.clone())
+        .unwrap_or_default();
+    let usage: Vec<DirectoryUsage> = directories
+        .into_iter()
+        .map(|(name, path)| DirectoryUsage {
+            name,
+            size_bytes: directory_size(&path),
+            available_bytes: available_space(&path).ok(),
+        })
+        .collect();
+    if let Some(free_bytes) = usage.iter().filter_map(|dir| dir.available_bytes).min() {
+        note_free_space(free_bytes, &limits());
+    }
+    usage
+}
+
+/// Bytes taken by the files under `path`, or by `path` itself when it is a file. Symlinks
+/// are not followed and unreadable entries count as empty.
+pub fn directory_size(path: &Path) -> u64 {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return 0;
+    };
+    if !metadata.is_dir() {
+        return metadata.len();
+    }
+    fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| directory_size(&entry.path()))
+                .sum()
+        })
+        .unwrap_or(0)
+}
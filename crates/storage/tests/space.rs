@@ -0,0 +1,53 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use manatan_storage::{DiskLimits, InsufficientSpace};
+
+const LIMITS: DiskLimits = DiskLimits {
+    floor_bytes: 100,
+    warn_bytes: 1000,
+};
+
+#[test]
+fn writes_that_would_cross_the_floor_are_refused() {
+    assert_eq!(manatan_storage::check_space(1000, 900, &LIMITS), Ok(()));
+    assert_eq!(
+        manatan_storage::check_space(1000, 901, &LIMITS),
+        Err(InsufficientSpace {
+            available_bytes: 1000,
+            needed_bytes: 901,
+            floor_bytes: 100,
+        })
+    );
+    assert!(manatan_storage::check_space(50, 0, &LIMITS).is_err());
+    assert!(manatan_storage::check_space(u64::MAX, u64::MAX, &LIMITS).is_err());
+}
+
+#[test]
+fn registered_directories_report_their_size() {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("time")
+        .as_nanos();
+    let dir = std::env::temp_dir().join(format!("manatan-storage-usage-{nanos}"));
+    std::fs::create_dir_all(dir.join("nested")).expect("temp dir");
+    std::fs::write(dir.join("a.bin"), [0u8; 10]).expect("write");
+    std::fs::write(dir.join("nested").join("b.bin"), [0u8; 5]).expect("write");
+
+    assert_eq!(manatan_storage::directory_size(&dir), 15);
+    assert_eq!(manatan_storage::directory_size(&dir.join("a.bin")), 10);
+    assert_eq!(manatan_storage::directory_size(&dir.join("missing")), 0);
+
+    manatan_storage::register("test-usage", &dir);
+    let usage = manatan_storage::usage();
+    let entry = usage
+        .iter()
+        .find(|entry| entry.name == "test-usage")
+        .expect("registered directory");
+    assert_eq!(entry.size_bytes, 15);
+    assert!(entry.available_bytes.is_some());
+
+    // Paths that don't exist yet are measured on their nearest existing ancestor.
+    assert!(manatan_storage::available_space(&dir.join("not/yet/created")).is_ok());
+
+    let _ = std::fs::remove_dir_all(dir);
+}
@@ -21,7 +21,7 @@ use crate::{
     backend::{AuthFlow, PushResult, SyncBackend},
     error::SyncError,
     state::SyncState,
-    types::SyncPayload,
+    types::{SyncConfig, SyncPayload},
 };
 
 // ============================================================================
@@ -211,15 +211,15 @@ impl GoogleDriveBackend {
         self.hub.as_ref().ok_or(SyncError::NotAuthenticated)
     }
 
-    async fn get_or_create_folder(&self) -> Result<String, SyncError> {
+    /// The id of the folder `config` syncs into, or `None` if it does not exist yet.
+    async fn find_folder(&self, config: &SyncConfig) -> Result<Option<String>, SyncError> {
         let hub = self.get_hub()?;
-        let config = self.state.get_sync_config();
 
         if config.google_drive_folder_type == crate::types::GoogleDriveFolderType::AppData {
-            return Ok("appDataFolder".to_string());
+            return Ok(Some("appDataFolder".to_string()));
         }
 
-        let folder_name = config.google_drive_folder;
+        let folder_name = &config.google_drive_folder;
         let query = format!(
             "name = '{folder_name}' and mimeType = '{FOLDER_MIME_TYPE}' and trashed = false"
         );
@@ -233,15 +233,22 @@ impl GoogleDriveBackend {
             .await
             .map_err(|e| SyncError::DriveError(e.to_string()))?;
 
-        if let Some(files) = file_list.files
-            && let Some(folder) = files.first()
-            && let Some(id) = &folder.id
-        {
-            return Ok(id.clone());
+        Ok(file_list
+            .files
+            .and_then(|files| files.into_iter().next())
+            .and_then(|folder| folder.id))
+    }
+
+    async fn get_or_create_folder(&self) -> Result<String, SyncError> {
+        let hub = self.get_hub()?;
+        let config = self.state.get_sync_config();
+
+        if let Some(id) = self.find_folder(&config).await? {
+            return Ok(id);
         }
 
         let folder = File {
-            name: Some(folder_name),
+            name: Some(config.google_drive_folder),
             mime_type: Some(FOLDER_MIME_TYPE.to_string()),
             ..Default::default()
         };
@@ -570,4 +577,36 @@ impl SyncBackend for GoogleDriveBackend {
     async fn refresh_token(&mut self) -> Result<(), SyncError> {
         self.do_refresh_token().await
     }
+
+    async fn probe_destination(&self, config: &SyncConfig) -> Result<(), SyncError> {
+        // A missing folder is created on the first sync, so it is enough to be able to
+        // write where it would be created.
+        let parent = match self.find_folder(config).await? {
+            Some(id) => id,
+            None => "root".to_string(),
+        };
+        info!("[DRIVE] Probing write access to {}", parent);
+
+        let hub = self.get_hub()?;
+        let probe = File {
+            name: Some(format!(".manatan-probe-{}", uuid::Uuid::new_v4())),
+            parents: Some(vec![parent]),
+            ..Default::default()
+        };
+        let (_, created) = hub
+            .files()
+            .create(probe)
+            .upload(std::io::Cursor::new(b"probe".to_vec()), mime::TEXT_PLAIN)
+            .await
+            .map_err(|e| SyncError::DriveError(e.to_string()))?;
+        let probe_id = created
+            .id
+            .ok_or_else(|| SyncError::DriveError("Failed to get probe file ID".to_string()))?;
+        hub.files()
+            .delete(&probe_id)
+            .doit()
+            .await
+            .map_err(|e| SyncError::DriveError(e.to_string()))?;
+        Ok(())
+    }
 }
@@ -2,7 +2,10 @@ pub mod google_drive;
 
 use async_trait::async_trait;
 
-use crate::{error::SyncError, types::SyncPayload};
+use crate::{
+    error::SyncError,
+    types::{SyncConfig, SyncPayload},
+};
 
 /// Result of a push operation
 #[derive(Debug)]
@@ -57,4 +60,10 @@ pub trait SyncBackend: Send + Sync {
 
     /// Refresh access token
     async fn refresh_token(&mut self) -> Result<(), SyncError>;
+
+    /// Check that the destination `config` points at is reachable and writable, without
+    /// leaving anything behind. Backends without a configurable destination accept any.
+    async fn probe_destination(&self, _config: &SyncConfig) -> Result<(), SyncError> {
+        Ok(())
+    }
 }
@@ -38,6 +38,9 @@ pub enum SyncError {
     #[error("Invalid request: {0}")]
     BadRequest(String),
 
+    #[error("Not found: {0}")]
+    NotFound(String),
+
     #[error("{0}")]
     Other(#[from] anyhow::Error),
 }
@@ -133,6 +136,7 @@ impl IntoResponse for SyncError {
             }
             SyncError::FileNotFound(_) => (StatusCode::NOT_FOUND, "file_not_found"),
             SyncError::BadRequest(_) => (StatusCode::BAD_REQUEST, "bad_request"),
+            SyncError::NotFound(_) => (StatusCode::NOT_FOUND, "not_found"),
             _ => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error"),
         };
 
@@ -0,0 +1,89 @@
+//! Record of completed merges and what each one changed in the local library.
+//!
+//! Entries are keyed by sync time so they list in order; only the newest
+//! [`MAX_ENTRIES`] are kept.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::SyncError, state::SyncState, types::SyncChange};
+
+const HISTORY_PREFIX: &str = "history:";
+/// Entries kept; recording a sync drops the oldest beyond this.
+pub const MAX_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncHistoryEntry {
+    pub id: String,
+    pub synced_at: i64,
+    /// Device that wrote the remote payload, unset when there was nothing to merge with
+    #[serde(default)]
+    pub remote_device: Option<String>,
+    pub progress_entries: usize,
+    pub metadata_entries: usize,
+    pub conflicts: usize,
+    #[serde(default)]
+    pub changes: Vec<SyncChange>,
+}
+
+/// What `GET /history` reports per entry; the change list is fetched separately.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncHistorySummary {
+    pub id: String,
+    pub synced_at: i64,
+    pub remote_device: Option<String>,
+    pub progress_entries: usize,
+    pub metadata_entries: usize,
+    pub conflicts: usize,
+    pub changes: usize,
+}
+
+impl From<&SyncHistoryEntry> for SyncHistorySummary {
+    fn from(entry: &SyncHistoryEntry) -> Self {
+        Self {
+            id: entry.id.clone(),
+            synced_at: entry.synced_at,
+            remote_device: entry.remote_device.clone(),
+            progress_entries: entry.progress_entries,
+            metadata_entries: entry.metadata_entries,
+            conflicts: entry.conflicts,
+            changes: entry.changes.len(),
+        }
+    }
+}
+
+fn entry_key(entry: &SyncHistoryEntry) -> String {
+    format!("{HISTORY_PREFIX}{:020}:{}", entry.synced_at, entry.id)
+}
+
+/// Stores a completed sync and drops entries beyond [`MAX_ENTRIES`].
+pub fn record(state: &SyncState, entry: &SyncHistoryEntry) -> Result<(), SyncError> {
+    state
+        .db
+        .insert(entry_key(entry).as_bytes(), serde_json::to_vec(entry)?)?;
+    let keys: Vec<_> = state
+        .db
+        .scan_prefix(HISTORY_PREFIX)
+        .keys()
+        .collect::<Result<_, _>>()?;
+    for key in keys.iter().take(keys.len().saturating_sub(MAX_ENTRIES)) {
+        state.db.remove(key)?;
+    }
+    state.db.flush()?;
+    Ok(())
+}
+
+/// All recorded syncs, newest first.
+pub fn entries(state: &SyncState) -> Result<Vec<SyncHistoryEntry>, SyncError> {
+    let mut entries = Vec::new();
+    for item in state.db.scan_prefix(HISTORY_PREFIX).rev() {
+        let (_, bytes) = item?;
+        entries.push(serde_json::from_slice(&bytes)?);
+    }
+    Ok(entries)
+}
+
+pub fn get(state: &SyncState, id: &str) -> Result<Option<SyncHistoryEntry>, SyncError> {
+    Ok(entries(state)?.into_iter().find(|entry| entry.id == id))
+}
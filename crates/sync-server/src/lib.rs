@@ -5,9 +5,13 @@ use tower_http::cors::{Any, CorsLayer};
 
 pub mod backend;
 pub mod error;
+pub mod history;
 pub mod merge;
+pub mod outbox;
 pub mod routes;
 pub mod state;
+pub mod tachibk;
+pub mod title_match;
 pub mod types;
 
 pub use error::SyncError;
@@ -15,7 +19,12 @@ pub use state::SyncState;
 pub use types::*;
 
 pub fn create_router(data_dir: PathBuf) -> Router {
-    let state = SyncState::new(data_dir);
+    create_router_with_state(SyncState::new(data_dir))
+}
+
+/// Builds the router around an existing state, so callers can configure it first.
+pub fn create_router_with_state(state: SyncState) -> Router {
+    routes::spawn_scheduler(state.clone());
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
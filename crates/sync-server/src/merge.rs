@@ -2,24 +2,40 @@ use std::collections::HashMap;
 
 use tracing::debug;
 
-use crate::types::{ConflictInfo, LNMetadata, LNProgress, LnCategory, SyncPayload};
+use crate::types::{
+    ConflictInfo, LNMetadata, LNProgress, LnCategory, LnCategoryMetadata, LnLibraryPreferences,
+    LnReaderSettings, SyncChange, SyncChangeKind, SyncPayload,
+};
 
-/// Merge two sync payloads, returning the merged result
+/// Merge two sync payloads, returning the merged result, the conflicts it resolved and
+/// what it changed in the local library
 pub fn merge_payloads(
     local: SyncPayload,
     remote: SyncPayload,
     local_device_id: &str,
-) -> (SyncPayload, Vec<ConflictInfo>) {
+) -> (SyncPayload, Vec<ConflictInfo>, Vec<SyncChange>) {
     let mut conflicts = Vec::new();
+    let mut changes = Vec::new();
+    let remote_device = Some(remote.device_id.clone()).filter(|id| !id.is_empty());
+    let remote_device = remote_device.as_deref();
 
     // Merge progress
-    let (merged_progress, progress_conflicts) =
-        merge_progress_maps(local.ln_progress, remote.ln_progress, local_device_id);
+    let (merged_progress, progress_conflicts) = merge_progress_maps(
+        local.ln_progress,
+        remote.ln_progress,
+        local_device_id,
+        remote_device,
+        &mut changes,
+    );
     conflicts.extend(progress_conflicts);
 
     // Merge metadata
-    let (merged_metadata, metadata_conflicts) =
-        merge_metadata_maps(local.ln_metadata, remote.ln_metadata);
+    let (merged_metadata, metadata_conflicts) = merge_metadata_maps(
+        local.ln_metadata,
+        remote.ln_metadata,
+        remote_device,
+        &mut changes,
+    );
     conflicts.extend(metadata_conflicts);
 
     // Merge content (simple: prefer local if exists, else remote)
@@ -32,11 +48,28 @@ pub fn merge_payloads(
     let merged_manifest = merge_simple_maps(local.file_manifest, remote.file_manifest);
 
     // Merge categories (simple merge - both sides preserved)
-    let merged_categories = merge_categories(local.ln_categories, remote.ln_categories);
+    let merged_categories = merge_categories(
+        local.ln_categories,
+        remote.ln_categories,
+        remote_device,
+        &mut changes,
+    );
 
-    // Merge category metadata (simple: prefer remote)
+    // Merge category metadata (last-modified wins, per category)
     let merged_category_metadata =
-        merge_simple_maps(local.ln_category_metadata, remote.ln_category_metadata);
+        merge_category_metadata(local.ln_category_metadata, remote.ln_category_metadata);
+
+    // Merge library preferences (last-modified wins)
+    let merged_library_preferences =
+        merge_library_preferences(local.ln_library_preferences, remote.ln_library_preferences);
+
+    // Merge reader settings (last-modified wins, per book)
+    let merged_reader_settings =
+        merge_reader_settings_maps(local.ln_reader_settings, remote.ln_reader_settings);
+    let merged_default_reader_settings = newest_reader_settings(
+        local.ln_default_reader_settings,
+        remote.ln_default_reader_settings,
+    );
 
     let merged = SyncPayload {
         schema_version: SyncPayload::CURRENT_SCHEMA_VERSION,
@@ -49,24 +82,142 @@ pub fn merge_payloads(
         file_manifest: merged_manifest,
         ln_categories: merged_categories,
         ln_category_metadata: merged_category_metadata,
+        ln_library_preferences: merged_library_preferences,
+        ln_reader_settings: merged_reader_settings,
+        ln_default_reader_settings: merged_default_reader_settings,
     };
 
-    (merged, conflicts)
+    changes.extend(conflicts.iter().map(|conflict| {
+        change(
+            Some(&conflict.book_id),
+            remote_device,
+            SyncChangeKind::Conflict {
+                field: conflict.field.clone(),
+                local_value: conflict.local_value.clone(),
+                remote_value: conflict.remote_value.clone(),
+                resolution: conflict.resolution.clone(),
+            },
+        )
+    }));
+    describe_changes(&mut changes, &merged);
+
+    (merged, conflicts, changes)
+}
+
+fn change(book_id: Option<&str>, from_device: Option<&str>, kind: SyncChangeKind) -> SyncChange {
+    SyncChange {
+        book_id: book_id.map(str::to_string),
+        title: None,
+        from_device: from_device.map(str::to_string),
+        kind,
+        summary: String::new(),
+    }
+}
+
+/// Fills in book titles and summaries from the merged payload, and orders the changes
+/// by book
+fn describe_changes(changes: &mut [SyncChange], merged: &SyncPayload) {
+    let category_name = |id: &String| {
+        merged
+            .ln_categories
+            .get(id)
+            .map_or_else(|| id.clone(), |category| category.name.clone())
+    };
+    for change in changes.iter_mut() {
+        change.title = change
+            .book_id
+            .as_ref()
+            .and_then(|id| merged.ln_metadata.get(id))
+            .map(|book| book.title.clone());
+        let book = change
+            .title
+            .as_deref()
+            .or(change.book_id.as_deref())
+            .unwrap_or_default();
+        let mut summary = match &change.kind {
+            SyncChangeKind::BookAdded => format!("Added '{book}'"),
+            SyncChangeKind::Progress {
+                from_percent: Some(from),
+                to_percent,
+            } => format!("'{book}': {from:.1}% → {to_percent:.1}%"),
+            SyncChangeKind::Progress {
+                from_percent: None,
+                to_percent,
+            } => format!("'{book}': progress {to_percent:.1}%"),
+            SyncChangeKind::MetadataUpdated { fields } => {
+                format!("'{book}': updated {}", fields.join(", "))
+            }
+            SyncChangeKind::BookCategories { added, removed } => {
+                let mut parts = Vec::new();
+                if !added.is_empty() {
+                    let names: Vec<_> = added.iter().map(category_name).collect();
+                    parts.push(format!("added to {}", names.join(", ")));
+                }
+                if !removed.is_empty() {
+                    let names: Vec<_> = removed.iter().map(category_name).collect();
+                    parts.push(format!("removed from {}", names.join(", ")));
+                }
+                format!("'{book}': {}", parts.join("; "))
+            }
+            SyncChangeKind::CategoryAdded { name, .. } => format!("New category '{name}'"),
+            SyncChangeKind::CategoryRenamed { from, to, .. } => {
+                format!("Category '{from}' renamed to '{to}'")
+            }
+            SyncChangeKind::Conflict {
+                field,
+                local_value,
+                remote_value,
+                resolution,
+            } => format!(
+                "'{book}': {field} differed ({local_value} here, {remote_value} remote), kept {resolution}"
+            ),
+        };
+        if let Some(device) = &change.from_device {
+            summary.push_str(&format!(" (from {device})"));
+        }
+        change.summary = summary;
+    }
+    changes.sort_by(|a, b| a.book_id.cmp(&b.book_id));
 }
 
 /// Merge categories - keep all categories from both sides
 fn merge_categories(
     local: HashMap<String, LnCategory>,
     remote: HashMap<String, LnCategory>,
+    remote_device: Option<&str>,
+    changes: &mut Vec<SyncChange>,
 ) -> HashMap<String, LnCategory> {
     let mut merged = remote;
 
+    for (id, category) in &merged {
+        if !local.contains_key(id) {
+            changes.push(change(
+                None,
+                remote_device,
+                SyncChangeKind::CategoryAdded {
+                    id: id.clone(),
+                    name: category.name.clone(),
+                },
+            ));
+        }
+    }
+
     for (id, category) in local {
         // Keep local categories, or use remote if it exists (last-modified wins)
         match merged.get(&id) {
             Some(existing) => {
                 if category.last_modified > existing.last_modified {
                     merged.insert(id, category);
+                } else if existing.name != category.name {
+                    changes.push(change(
+                        None,
+                        remote_device,
+                        SyncChangeKind::CategoryRenamed {
+                            from: category.name,
+                            to: existing.name.clone(),
+                            id,
+                        },
+                    ));
                 }
             }
             None => {
@@ -78,10 +229,78 @@ fn merge_categories(
     merged
 }
 
+/// Merge category metadata - the most recently modified side wins; remote wins ties,
+/// which also covers entries saved before timestamps were recorded
+fn merge_category_metadata(
+    local: HashMap<String, LnCategoryMetadata>,
+    remote: HashMap<String, LnCategoryMetadata>,
+) -> HashMap<String, LnCategoryMetadata> {
+    let mut merged = remote;
+    for (id, metadata) in local {
+        match merged.get(&id) {
+            Some(existing) if existing.last_modified >= metadata.last_modified => {}
+            _ => {
+                merged.insert(id, metadata);
+            }
+        }
+    }
+    merged
+}
+
+/// Merge library preferences - the most recently modified side wins
+fn merge_library_preferences(
+    local: Option<LnLibraryPreferences>,
+    remote: Option<LnLibraryPreferences>,
+) -> Option<LnLibraryPreferences> {
+    match (local, remote) {
+        (Some(l), Some(r)) => {
+            if r.last_modified > l.last_modified {
+                Some(r)
+            } else {
+                Some(l)
+            }
+        }
+        (l, r) => l.or(r),
+    }
+}
+
+fn newest_reader_settings(
+    local: Option<LnReaderSettings>,
+    remote: Option<LnReaderSettings>,
+) -> Option<LnReaderSettings> {
+    match (local, remote) {
+        (Some(l), Some(r)) => {
+            if r.last_modified > l.last_modified {
+                Some(r)
+            } else {
+                Some(l)
+            }
+        }
+        (l, r) => l.or(r),
+    }
+}
+
+fn merge_reader_settings_maps(
+    local: HashMap<String, LnReaderSettings>,
+    mut remote: HashMap<String, LnReaderSettings>,
+) -> HashMap<String, LnReaderSettings> {
+    let mut merged = HashMap::new();
+    for (id, local_settings) in local {
+        let remote_settings = remote.remove(&id);
+        if let Some(settings) = newest_reader_settings(Some(local_settings), remote_settings) {
+            merged.insert(id, settings);
+        }
+    }
+    merged.extend(remote);
+    merged
+}
+
 fn merge_progress_maps(
     local: HashMap<String, LNProgress>,
     remote: HashMap<String, LNProgress>,
     local_device_id: &str,
+    remote_device: Option<&str>,
+    changes: &mut Vec<SyncChange>,
 ) -> (HashMap<String, LNProgress>, Vec<ConflictInfo>) {
     let mut merged = HashMap::new();
     let mut conflicts = Vec::new();
@@ -164,6 +383,18 @@ fn merge_progress_maps(
             (None, None) => unreachable!(),
         };
 
+        let from = local_progress.map(|l| l.total_progress);
+        if from != Some(chosen.total_progress) {
+            changes.push(change(
+                Some(&book_id),
+                chosen.device_id.as_deref().or(remote_device),
+                SyncChangeKind::Progress {
+                    from_percent: from.map(|progress| progress * 100.0),
+                    to_percent: chosen.total_progress * 100.0,
+                },
+            ));
+        }
+
         merged.insert(book_id, chosen);
     }
 
@@ -173,6 +404,8 @@ fn merge_progress_maps(
 fn merge_metadata_maps(
     local: HashMap<String, LNMetadata>,
     remote: HashMap<String, LNMetadata>,
+    remote_device: Option<&str>,
+    changes: &mut Vec<SyncChange>,
 ) -> (HashMap<String, LNMetadata>, Vec<ConflictInfo>) {
     let mut merged = HashMap::new();
     let conflicts = Vec::new();
@@ -207,12 +440,65 @@ fn merge_metadata_maps(
             (None, None) => unreachable!(),
         };
 
+        match local_meta {
+            None => changes.push(change(
+                Some(&book_id),
+                remote_device,
+                SyncChangeKind::BookAdded,
+            )),
+            Some(l) => {
+                let fields = changed_fields(l, &chosen);
+                if !fields.is_empty() {
+                    changes.push(change(
+                        Some(&book_id),
+                        remote_device,
+                        SyncChangeKind::MetadataUpdated { fields },
+                    ));
+                }
+                let added: Vec<String> = chosen
+                    .category_ids
+                    .iter()
+                    .filter(|id| !l.category_ids.contains(id))
+                    .cloned()
+                    .collect();
+                let removed: Vec<String> = l
+                    .category_ids
+                    .iter()
+                    .filter(|id| !chosen.category_ids.contains(id))
+                    .cloned()
+                    .collect();
+                if !added.is_empty() || !removed.is_empty() {
+                    changes.push(change(
+                        Some(&book_id),
+                        remote_device,
+                        SyncChangeKind::BookCategories { added, removed },
+                    ));
+                }
+            }
+        }
+
         merged.insert(book_id, chosen);
     }
 
     (merged, conflicts)
 }
 
+/// Names of the user-visible metadata fields that differ, as the reader spells them
+fn changed_fields(old: &LNMetadata, new: &LNMetadata) -> Vec<String> {
+    [
+        ("title", old.title != new.title),
+        ("author", old.author != new.author),
+        ("cover", old.cover != new.cover),
+        ("chapterCount", old.chapter_count != new.chapter_count),
+        ("language", old.language != new.language),
+        ("rating", old.rating != new.rating),
+    ]
+    .into_iter()
+    .filter(|(_, changed)| *changed)
+    .map(|(field, _)| field.to_string())
+    .collect()
+}
+
 fn merge_simple_maps<V: Clone>(
     local: HashMap<String, V>,
     remote: HashMap<String, V>,
@@ -223,3 +509,159 @@ fn merge_simple_maps<V: Clone>(
     }
     merged
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn book(id: &str, title: &str, categories: &[&str]) -> LNMetadata {
+        serde_json::from_value(json!({
+            "id": id,
+            "title": title,
+            "author": "Author",
+            "addedAt": 1,
+            "stats": { "chapterLengths": [], "totalLength": 0 },
+            "chapterCount": 1,
+            "toc": [],
+            "syncVersion": 1,
+            "categoryIds": categories,
+        }))
+        .expect("metadata")
+    }
+
+    fn progress(total: f64, device: &str) -> LNProgress {
+        LNProgress {
+            total_progress: total,
+            device_id: Some(device.to_string()),
+            ..LNProgress::default()
+        }
+    }
+
+    fn category(id: &str, name: &str) -> LnCategory {
+        LnCategory {
+            id: id.to_string(),
+            name: name.to_string(),
+            order: 0,
+            created_at: 0,
+            last_modified: 0,
+        }
+    }
+
+    #[test]
+    fn changes_describe_what_the_remote_brought_in() {
+        let mut local = SyncPayload::new("laptop".to_string());
+        local
+            .ln_metadata
+            .insert("a".to_string(), book("a", "Old title", &["fav"]));
+        local
+            .ln_progress
+            .insert("a".to_string(), progress(0.25, "laptop"));
+
+        let mut remote = SyncPayload::new("phone".to_string());
+        let mut renamed = book("a", "New title", &["later"]);
+        renamed.sync_version = Some(2);
+        remote.ln_metadata.insert("a".to_string(), renamed);
+        remote
+            .ln_progress
+            .insert("a".to_string(), progress(0.5, "phone"));
+        remote
+            .ln_metadata
+            .insert("b".to_string(), book("b", "Second book", &[]));
+        remote
+            .ln_categories
+            .insert("later".to_string(), category("later", "Read later"));
+
+        let (_, conflicts, changes) = merge_payloads(local, remote, "laptop");
+        assert_eq!(conflicts.len(), 1);
+        let kinds: Vec<_> = changes.iter().map(|change| &change.kind).collect();
+        assert!(kinds.contains(&&SyncChangeKind::Progress {
+            from_percent: Some(25.0),
+            to_percent: 50.0,
+        }));
+        assert!(kinds.contains(&&SyncChangeKind::MetadataUpdated {
+            fields: vec!["title".to_string()],
+        }));
+        assert!(kinds.contains(&&SyncChangeKind::BookCategories {
+            added: vec!["later".to_string()],
+            removed: vec!["fav".to_string()],
+        }));
+        assert!(kinds.contains(&&SyncChangeKind::BookAdded));
+        assert!(kinds.contains(&&SyncChangeKind::CategoryAdded {
+            id: "later".to_string(),
+            name: "Read later".to_string(),
+        }));
+        assert!(
+            kinds
+                .iter()
+                .any(|kind| matches!(kind, SyncChangeKind::Conflict { .. }))
+        );
+
+        let categories = changes
+            .iter()
+            .find(|change| matches!(change.kind, SyncChangeKind::BookCategories { .. }))
+            .expect("category change");
+        assert_eq!(categories.title.as_deref(), Some("New title"));
+        assert_eq!(categories.from_device.as_deref(), Some("phone"));
+        assert_eq!(
+            categories.summary,
+            "'New title': added to Read later; removed from fav (from phone)"
+        );
+    }
+
+    #[test]
+    fn merging_with_an_identical_library_changes_nothing() {
+        let mut local = SyncPayload::new("laptop".to_string());
+        local
+            .ln_metadata
+            .insert("a".to_string(), book("a", "Title", &[]));
+        local
+            .ln_progress
+            .insert("a".to_string(), progress(0.5, "laptop"));
+        let remote = SyncPayload {
+            device_id: "phone".to_string(),
+            ..local.clone()
+        };
+
+        let (_, conflicts, changes) = merge_payloads(local, remote, "laptop");
+        assert!(conflicts.is_empty());
+        assert!(changes.is_empty());
+    }
+
+    fn category_metadata(sort_by: &str, last_modified: i64) -> LnCategoryMetadata {
+        LnCategoryMetadata {
+            sort_by: sort_by.to_string(),
+            sort_desc: false,
+            cover: None,
+            description: None,
+            hidden: false,
+            last_modified,
+        }
+    }
+
+    #[test]
+    fn newer_category_metadata_wins_and_remote_wins_ties() {
+        let local = HashMap::from([
+            ("newer-here".to_string(), category_metadata("title", 20)),
+            ("newer-there".to_string(), category_metadata("title", 10)),
+            ("tied".to_string(), category_metadata("title", 0)),
+            ("only-here".to_string(), category_metadata("rating", 5)),
+        ]);
+        let remote = HashMap::from([
+            ("newer-here".to_string(), category_metadata("addedAt", 10)),
+            ("newer-there".to_string(), category_metadata("addedAt", 20)),
+            ("tied".to_string(), category_metadata("addedAt", 0)),
+            ("only-there".to_string(), category_metadata("lastRead", 5)),
+        ]);
+
+        let merged = merge_category_metadata(local, remote);
+        let sort_of = |id: &str| merged.get(id).map(|meta| meta.sort_by.as_str());
+        assert_eq!(merged.len(), 5);
+        assert_eq!(sort_of("newer-here"), Some("title"));
+        assert_eq!(sort_of("newer-there"), Some("addedAt"));
+        assert_eq!(sort_of("tied"), Some("addedAt"));
+        assert_eq!(sort_of("only-here"), Some("rating"));
+        assert_eq!(sort_of("only-there"), Some("lastRead"));
+    }
+}
@@ -0,0 +1,110 @@
+//! Persistent queue of payloads whose push to the sync backend failed.
+//!
+//! Entries are keyed by queue time so they replay oldest first. Replaying always goes
+//! through [`crate::merge::merge_payloads`] against the current remote, so an entry can
+//! be replayed any number of times without moving anything backwards.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{error::SyncError, state::SyncState, types::SyncPayload};
+
+const OUTBOX_PREFIX: &str = "outbox:";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxEntry {
+    pub id: String,
+    pub queued_at: i64,
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    pub payload: SyncPayload,
+}
+
+/// What `GET /outbox` reports per entry; the payload itself can be large.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutboxSummary {
+    pub id: String,
+    pub queued_at: i64,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub progress_entries: usize,
+    pub metadata_entries: usize,
+    pub book_ids: Vec<String>,
+}
+
+impl From<&OutboxEntry> for OutboxSummary {
+    fn from(entry: &OutboxEntry) -> Self {
+        let mut book_ids: Vec<String> = entry
+            .payload
+            .ln_progress
+            .keys()
+            .chain(entry.payload.ln_metadata.keys())
+            .cloned()
+            .collect();
+        book_ids.sort();
+        book_ids.dedup();
+        Self {
+            id: entry.id.clone(),
+            queued_at: entry.queued_at,
+            attempts: entry.attempts,
+            last_error: entry.last_error.clone(),
+            progress_entries: entry.payload.ln_progress.len(),
+            metadata_entries: entry.payload.ln_metadata.len(),
+            book_ids,
+        }
+    }
+}
+
+fn entry_key(entry: &OutboxEntry) -> String {
+    format!("{OUTBOX_PREFIX}{:020}:{}", entry.queued_at, entry.id)
+}
+
+/// Queues a payload that could not be pushed.
+pub fn enqueue(
+    state: &SyncState,
+    payload: SyncPayload,
+    error: &SyncError,
+) -> Result<OutboxEntry, SyncError> {
+    let entry = OutboxEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        queued_at: chrono::Utc::now().timestamp_millis(),
+        attempts: 0,
+        last_error: Some(error.to_string()),
+        payload,
+    };
+    store(state, &entry)?;
+    Ok(entry)
+}
+
+/// All queued entries, oldest first.
+pub fn entries(state: &SyncState) -> Result<Vec<OutboxEntry>, SyncError> {
+    let mut entries = Vec::new();
+    for item in state.db.scan_prefix(OUTBOX_PREFIX) {
+        let (_, bytes) = item?;
+        entries.push(serde_json::from_slice(&bytes)?);
+    }
+    Ok(entries)
+}
+
+pub fn is_empty(state: &SyncState) -> bool {
+    state.db.scan_prefix(OUTBOX_PREFIX).next().is_none()
+}
+
+pub fn store(state: &SyncState, entry: &OutboxEntry) -> Result<(), SyncError> {
+    state
+        .db
+        .insert(entry_key(entry).as_bytes(), serde_json::to_vec(entry)?)?;
+    state.db.flush()?;
+    Ok(())
+}
+
+pub fn remove(state: &SyncState, entries: &[OutboxEntry]) -> Result<(), SyncError> {
+    for entry in entries {
+        state.db.remove(entry_key(entry).as_bytes())?;
+    }
+    state.db.flush()?;
+    Ok(())
+}
@@ -6,11 +6,12 @@ use axum::{
     routing::{get, post},
 };
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
 use crate::{
     backend::{AuthFlow, SyncBackend, google_drive::GoogleDriveBackend},
     error::SyncError,
-    state::SyncState,
+    state::{RemoteSyncState, SyncState, remote_id},
 };
 
 pub fn router() -> Router<SyncState> {
@@ -30,6 +31,13 @@ pub struct AuthStatusResponse {
     pub email: Option<String>,
     pub last_sync: Option<i64>,
     pub device_id: String,
+    /// The remote the current config points at, which `lastSync`, `lastEtag` and
+    /// `lastContentHash` belong to.
+    pub remote: String,
+    pub last_etag: Option<String>,
+    pub last_content_hash: Option<String>,
+    /// Bookkeeping for every remote synced with so far.
+    pub remotes: BTreeMap<String, RemoteSyncState>,
 }
 
 async fn auth_status(State(state): State<SyncState>) -> Result<impl IntoResponse, SyncError> {
@@ -46,7 +54,7 @@ async fn auth_status(State(state): State<SyncState>) -> Result<impl IntoResponse
             let mut backend = GoogleDriveBackend::new(state.clone());
             // If initialization fails, we just don't set the backend
             if backend.initialize().await.is_ok() {
-                *gdrive = Some(backend);
+                *gdrive = Some(Box::new(backend));
             }
         }
     }
@@ -86,13 +94,19 @@ async fn auth_status(State(state): State<SyncState>) -> Result<impl IntoResponse
     };
 
     let config = state.get_sync_config();
+    let remote = remote_id(&config);
+    let current = state.get_remote_state(&remote);
 
     let response = Json(AuthStatusResponse {
         connected,
         backend: format!("{:?}", config.backend).to_lowercase(),
         email,
-        last_sync: state.get_last_sync(),
+        last_sync: current.last_sync,
         device_id: state.get_device_id(),
+        remote,
+        last_etag: current.etag,
+        last_content_hash: current.content_hash,
+        remotes: state.remote_states(),
     });
 
     let mut headers = axum::http::HeaderMap::new();
@@ -127,7 +141,7 @@ async fn google_start(
     let auth_flow = backend.start_auth(&req.redirect_uri)?;
 
     // Store backend for later (write lock)
-    *state.google_drive.write().await = Some(backend);
+    *state.google_drive.write().await = Some(Box::new(backend));
 
     Ok(Json(auth_flow))
 }
@@ -207,7 +221,7 @@ async fn google_callback_post(
     }
 
     let mut gdrive = state.google_drive.write().await;
-    let backend = gdrive.get_or_insert_with(|| GoogleDriveBackend::new(state.clone()));
+    let backend = gdrive.get_or_insert_with(|| Box::new(GoogleDriveBackend::new(state.clone())));
 
     backend
         .complete_auth(&body.code, &body.redirect_uri)
@@ -242,7 +256,7 @@ async fn handle_callback(
     };
 
     let mut gdrive = state.google_drive.write().await;
-    let backend = gdrive.get_or_insert_with(|| GoogleDriveBackend::new(state.clone()));
+    let backend = gdrive.get_or_insert_with(|| Box::new(GoogleDriveBackend::new(state.clone())));
 
     backend.complete_auth(&code, &redirect_uri).await?;
 
@@ -1,16 +1,49 @@
 use axum::{
     Json, Router,
-    extract::State,
-    routing::{get, put},
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
 };
-use tracing::info;
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
 
-use crate::{error::SyncError, state::SyncState, types::SyncConfig};
+use super::sync::ensure_backend;
+use crate::{
+    error::SyncError,
+    state::SyncState,
+    types::{GoogleDriveFolderType, SyncBackendType, SyncConfig},
+};
+
+/// Google Drive caps file names at 255 characters.
+const MAX_FOLDER_NAME_CHARS: usize = 255;
 
 pub fn router() -> Router<SyncState> {
     Router::new()
         .route("/", get(get_config))
         .route("/", put(set_config))
+        .route("/validate", post(validate_config))
+}
+
+/// A problem with one field of a proposed config, keyed by its JSON name.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FieldDiagnostic {
+    field: &'static str,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ConfigValidation {
+    valid: bool,
+    diagnostics: Vec<FieldDiagnostic>,
+}
+
+#[derive(Deserialize)]
+struct SetConfigQuery {
+    #[serde(default)]
+    validate: bool,
 }
 
 async fn get_config(State(state): State<SyncState>) -> Json<SyncConfig> {
@@ -20,12 +53,108 @@ async fn get_config(State(state): State<SyncState>) -> Json<SyncConfig> {
 
 async fn set_config(
     State(state): State<SyncState>,
+    Query(query): Query<SetConfigQuery>,
     Json(config): Json<SyncConfig>,
-) -> Result<Json<SyncConfig>, SyncError> {
+) -> Result<Response, SyncError> {
+    if query.validate {
+        let validation = validate(&state, &config).await;
+        if !validation.valid {
+            info!("[CONFIG] Config rejected by validation");
+            return Ok((StatusCode::BAD_REQUEST, Json(validation)).into_response());
+        }
+    }
     info!(
         "[CONFIG] Config updated - sync settings: progress={}, metadata={}, content={}, files={}",
         config.ln_progress, config.ln_metadata, config.ln_content, config.ln_files
     );
     state.set_sync_config(&config)?;
-    Ok(Json(config))
+    Ok(Json(config).into_response())
+}
+
+/// Checks a proposed config against its backend without saving it.
+async fn validate_config(
+    State(state): State<SyncState>,
+    Json(config): Json<SyncConfig>,
+) -> Json<ConfigValidation> {
+    Json(validate(&state, &config).await)
+}
+
+/// Field checks first; the backend is only contacted when they pass, and then has to
+/// accept a probe write where the config would sync to.
+async fn validate(state: &SyncState, config: &SyncConfig) -> ConfigValidation {
+    let mut diagnostics = check_fields(config);
+    if diagnostics.is_empty()
+        && config.backend == SyncBackendType::GoogleDrive
+        && let Err(e) = probe_backend(state, config).await
+    {
+        warn!("[CONFIG] Destination probe failed: {}", e);
+        let field = match &e {
+            SyncError::NotAuthenticated | SyncError::OAuthError(_) => "backend",
+            _ if config.google_drive_folder_type == GoogleDriveFolderType::AppData => {
+                "googleDriveFolderType"
+            }
+            _ => "googleDriveFolder",
+        };
+        diagnostics.push(FieldDiagnostic {
+            field,
+            message: e.user_message(),
+        });
+    }
+    ConfigValidation {
+        valid: diagnostics.is_empty(),
+        diagnostics,
+    }
+}
+
+async fn probe_backend(state: &SyncState, config: &SyncConfig) -> Result<(), SyncError> {
+    ensure_backend(state).await?;
+    let backend = state.google_drive.read().await;
+    match backend.as_ref() {
+        Some(backend) => backend.probe_destination(config).await,
+        None => Err(SyncError::NotAuthenticated),
+    }
+}
+
+fn check_fields(config: &SyncConfig) -> Vec<FieldDiagnostic> {
+    let mut diagnostics = Vec::new();
+    match config.backend {
+        SyncBackendType::None => {}
+        SyncBackendType::WebDav => diagnostics.push(FieldDiagnostic {
+            field: "backend",
+            message: "WebDAV sync is not supported yet".to_string(),
+        }),
+        SyncBackendType::GoogleDrive => {
+            if config.google_drive_folder_type == GoogleDriveFolderType::Public
+                && let Some(message) = folder_name_problem(&config.google_drive_folder)
+            {
+                diagnostics.push(FieldDiagnostic {
+                    field: "googleDriveFolder",
+                    message,
+                });
+            }
+        }
+    }
+    diagnostics
+}
+
+fn folder_name_problem(name: &str) -> Option<String> {
+    if name.trim().is_empty() {
+        return Some("Folder name is required".to_string());
+    }
+    if name.trim() != name {
+        return Some("Folder name must not start or end with whitespace".to_string());
+    }
+    if name.chars().count() > MAX_FOLDER_NAME_CHARS {
+        return Some(format!(
+            "Folder name must be at most {MAX_FOLDER_NAME_CHARS} characters"
+        ));
+    }
+    // Quotes and backslashes would break the Drive search query the folder is found by.
+    if let Some(c) = name
+        .chars()
+        .find(|c| matches!(c, '\'' | '\\' | '/') || c.is_control())
+    {
+        return Some(format!("Folder name must not contain {c:?}"));
+    }
+    None
 }
@@ -0,0 +1,36 @@
+//! Past syncs and the changes each one made.
+
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    routing::get,
+};
+
+use crate::{
+    error::SyncError,
+    history::{self, SyncHistorySummary},
+    state::SyncState,
+    types::SyncChange,
+};
+
+pub fn router() -> Router<SyncState> {
+    Router::new()
+        .route("/", get(list_handler))
+        .route("/{id}/changes", get(changes_handler))
+}
+
+async fn list_handler(
+    State(state): State<SyncState>,
+) -> Result<Json<Vec<SyncHistorySummary>>, SyncError> {
+    let entries = history::entries(&state)?;
+    Ok(Json(entries.iter().map(SyncHistorySummary::from).collect()))
+}
+
+async fn changes_handler(
+    State(state): State<SyncState>,
+    Path(id): Path<String>,
+) -> Result<Json<Vec<SyncChange>>, SyncError> {
+    history::get(&state, &id)?
+        .map(|entry| Json(entry.changes))
+        .ok_or_else(|| SyncError::NotFound(format!("sync history entry {id}")))
+}
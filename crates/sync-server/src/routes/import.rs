@@ -0,0 +1,519 @@
+//! Imports read markers from other manga readers into the Suwayomi library.
+
+use std::collections::HashMap;
+
+use axum::{Json, Router, extract::Multipart, routing::post};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use serde_json::{Value, json};
+use tracing::info;
+
+use crate::{
+    error::SyncError,
+    state::SyncState,
+    tachibk::{self, BackupChapter, BackupManga},
+    title_match::{self, CONFIDENT_MATCH},
+};
+
+const DEFAULT_GRAPHQL_URL: &str = "http://127.0.0.1:4568/api/graphql";
+
+pub fn router() -> Router<SyncState> {
+    Router::new().route("/tachiyomi-backup", post(tachiyomi_backup_handler))
+}
+
+#[derive(Debug, Default)]
+struct ImportForm {
+    backup: Vec<u8>,
+    confirm: bool,
+    /// Backup title to Suwayomi manga id, as approved from the preview. `null` skips
+    /// the title.
+    matches: Option<HashMap<String, Option<i64>>>,
+    graphql_url: Option<String>,
+    user: Option<String>,
+    pass: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SuggestedMatch {
+    manga_id: i64,
+    title: String,
+    score: f64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PreviewEntry {
+    title: String,
+    read_chapters: usize,
+    in_progress_chapters: usize,
+    suggestion: Option<SuggestedMatch>,
+    /// Whether the suggestion is applied on confirm when no explicit matches are sent.
+    confident: bool,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AppliedEntry {
+    title: String,
+    manga_id: i64,
+    marked_read: usize,
+    pages_updated: usize,
+    unmatched_chapters: usize,
+}
+
+/// `POST /import/tachiyomi-backup`: multipart upload of a `.tachibk` file.
+///
+/// Without `confirm=true` nothing is written; the response lists each backed up series
+/// with its best library match so the user can review it. Confirming applies the
+/// approved `matches` (or, if omitted, only confident matches). Progress only ever moves
+/// forward: chapters are never marked unread and page positions never go back.
+async fn tachiyomi_backup_handler(multipart: Multipart) -> Result<Json<Value>, SyncError> {
+    let form = read_form(multipart).await?;
+    let mangas: Vec<BackupManga> = tachibk::parse_backup(&form.backup)
+        .map_err(|err| SyncError::BadRequest(format!("Invalid Tachiyomi backup: {err}")))?
+        .into_iter()
+        .filter(BackupManga::has_progress)
+        .collect();
+
+    let client = GraphqlClient {
+        http: reqwest::Client::new(),
+        url: form
+            .graphql_url
+            .clone()
+            .unwrap_or_else(|| DEFAULT_GRAPHQL_URL.to_string()),
+        user: form.user.clone(),
+        pass: form.pass.clone(),
+    };
+    let library = client.library().await?;
+
+    let preview: Vec<PreviewEntry> = mangas
+        .iter()
+        .map(|manga| {
+            let suggestion = title_match::best_match(
+                &manga.title,
+                library.iter().map(|entry| (entry.title.as_str(), entry)),
+            )
+            .map(|(entry, score)| SuggestedMatch {
+                manga_id: entry.id,
+                title: entry.title.clone(),
+                score,
+            });
+            PreviewEntry {
+                title: manga.title.clone(),
+                read_chapters: manga.chapters.iter().filter(|c| c.read).count(),
+                in_progress_chapters: manga
+                    .chapters
+                    .iter()
+                    .filter(|c| !c.read && c.last_page_read > 0)
+                    .count(),
+                confident: suggestion
+                    .as_ref()
+                    .is_some_and(|suggestion| suggestion.score >= CONFIDENT_MATCH),
+                suggestion,
+            }
+        })
+        .collect();
+
+    if !form.confirm {
+        return Ok(Json(json!({ "applied": false, "entries": preview })));
+    }
+
+    let mut applied = Vec::new();
+    for (manga, entry) in mangas.iter().zip(&preview) {
+        let target = match &form.matches {
+            Some(matches) => matches.get(&manga.title).copied().flatten(),
+            None if entry.confident => entry.suggestion.as_ref().map(|s| s.manga_id),
+            None => None,
+        };
+        let Some(manga_id) = target else {
+            continue;
+        };
+        applied.push(client.apply_progress(manga, manga_id).await?);
+    }
+
+    info!(
+        "[IMPORT] Applied Tachiyomi progress to {} of {} series",
+        applied.len(),
+        mangas.len()
+    );
+    Ok(Json(json!({ "applied": true, "entries": applied })))
+}
+
+async fn read_form(mut multipart: Multipart) -> Result<ImportForm, SyncError> {
+    let bad_request = |err: axum::extract::multipart::MultipartError| {
+        SyncError::BadRequest(format!("Invalid multipart body: {err}"))
+    };
+    let mut form = ImportForm::default();
+    while let Some(field) = multipart.next_field().await.map_err(bad_request)? {
+        let name = field.name().unwrap_or_default().to_string();
+        match name.as_str() {
+            "file" | "backup" => form.backup = field.bytes().await.map_err(bad_request)?.to_vec(),
+            "confirm" => form.confirm = field.text().await.map_err(bad_request)? == "true",
+            "matches" => {
+                let raw = field.text().await.map_err(bad_request)?;
+                form.matches = Some(serde_json::from_str(&raw).map_err(|err| {
+                    SyncError::BadRequest(format!("Invalid matches JSON: {err}"))
+                })?);
+            }
+            "graphqlUrl" => form.graphql_url = Some(field.text().await.map_err(bad_request)?),
+            "user" => form.user = Some(field.text().await.map_err(bad_request)?),
+            "pass" => form.pass = Some(field.text().await.map_err(bad_request)?),
+            _ => {}
+        }
+    }
+    if form.backup.is_empty() {
+        return Err(SyncError::BadRequest("Missing backup file".to_string()));
+    }
+    Ok(form)
+}
+
+#[derive(Debug, Deserialize)]
+struct LibraryManga {
+    id: i64,
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryChapter {
+    id: i64,
+    name: String,
+    chapter_number: f32,
+    is_read: bool,
+    last_page_read: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct Nodes<T> {
+    nodes: Vec<T>,
+}
+
+struct GraphqlClient {
+    http: reqwest::Client,
+    url: String,
+    user: Option<String>,
+    pass: Option<String>,
+}
+
+impl GraphqlClient {
+    async fn request<T: DeserializeOwned>(
+        &self,
+        query: &str,
+        variables: Value,
+    ) -> Result<T, SyncError> {
+        let mut request = self
+            .http
+            .post(&self.url)
+            .json(&json!({ "query": query, "variables": variables }));
+        if let Some(user) = &self.user {
+            request = request.basic_auth(user, self.pass.as_ref());
+        }
+        let response: Value = request
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|err| anyhow::anyhow!("Suwayomi GraphQL request failed: {err}"))?
+            .json()
+            .await
+            .map_err(|err| anyhow::anyhow!("Invalid Suwayomi GraphQL response: {err}"))?;
+        if let Some(errors) = response.get("errors") {
+            return Err(anyhow::anyhow!("Suwayomi GraphQL error: {errors}").into());
+        }
+        Ok(serde_json::from_value(response["data"].clone())?)
+    }
+
+    async fn library(&self) -> Result<Vec<LibraryManga>, SyncError> {
+        #[derive(Deserialize)]
+        struct Data {
+            mangas: Nodes<LibraryManga>,
+        }
+        let data: Data = self
+            .request(
+                "query { mangas(condition: { inLibrary: true }) { nodes { id title } } }",
+                json!({}),
+            )
+            .await?;
+        Ok(data.mangas.nodes)
+    }
+
+    async fn chapters(&self, manga_id: i64) -> Result<Vec<LibraryChapter>, SyncError> {
+        #[derive(Deserialize)]
+        struct Data {
+            chapters: Nodes<LibraryChapter>,
+        }
+        let data: Data = self
+            .request(
+                "query ($mangaId: Int!) { chapters(condition: { mangaId: $mangaId }) { nodes { id name chapterNumber isRead lastPageRead } } }",
+                json!({ "mangaId": manga_id }),
+            )
+            .await?;
+        Ok(data.chapters.nodes)
+    }
+
+    async fn update_chapters(&self, ids: &[i64], patch: Value) -> Result<(), SyncError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+        let _: Value = self
+            .request(
+                "mutation ($ids: [Int!]!, $patch: UpdateChapterPatchInput!) { updateChapters(input: { ids: $ids, patch: $patch }) { chapters { id } } }",
+                json!({ "ids": ids, "patch": patch }),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn apply_progress(
+        &self,
+        manga: &BackupManga,
+        manga_id: i64,
+    ) -> Result<AppliedEntry, SyncError> {
+        let chapters = self.chapters(manga_id).await?;
+        let mut entry = AppliedEntry {
+            title: manga.title.clone(),
+            manga_id,
+            ..Default::default()
+        };
+
+        let mut mark_read = Vec::new();
+        for backup_chapter in &manga.chapters {
+            if !backup_chapter.read && backup_chapter.last_page_read == 0 {
+                continue;
+            }
+            let Some(chapter) = find_chapter(&chapters, backup_chapter) else {
+                entry.unmatched_chapters += 1;
+                continue;
+            };
+            if chapter.is_read {
+                continue;
+            }
+            if backup_chapter.read {
+                mark_read.push(chapter.id);
+            } else if backup_chapter.last_page_read > chapter.last_page_read {
+                self.update_chapters(
+                    &[chapter.id],
+                    json!({ "lastPageRead": backup_chapter.last_page_read }),
+                )
+                .await?;
+                entry.pages_updated += 1;
+            }
+        }
+
+        self.update_chapters(&mark_read, json!({ "isRead": true }))
+            .await?;
+        entry.marked_read = mark_read.len();
+        Ok(entry)
+    }
+}
+
+/// Matches by chapter number when the backup has one, falling back to the chapter name.
+fn find_chapter<'a>(
+    chapters: &'a [LibraryChapter],
+    backup: &BackupChapter,
+) -> Option<&'a LibraryChapter> {
+    if backup.chapter_number >= 0.0 {
+        let by_number: Vec<_> = chapters
+            .iter()
+            .filter(|chapter| (chapter.chapter_number - backup.chapter_number).abs() < 0.001)
+            .collect();
+        if by_number.len() == 1 {
+            return by_number.first().copied();
+        }
+    }
+    let name = title_match::normalize_title(&backup.name);
+    chapters
+        .iter()
+        .find(|chapter| title_match::normalize_title(&chapter.name) == name)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::State;
+    use reqwest::multipart::{Form, Part};
+
+    use super::*;
+
+    fn varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push(((value as u8) & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn field(number: u32, bytes: &[u8], out: &mut Vec<u8>) {
+        varint((u64::from(number) << 3) | 2, out);
+        varint(bytes.len() as u64, out);
+        out.extend_from_slice(bytes);
+    }
+
+    fn chapter(name: &str, read: bool, last_page_read: u8, number: f32) -> Vec<u8> {
+        let mut out = Vec::new();
+        field(2, name.as_bytes(), &mut out);
+        out.extend_from_slice(&[4 << 3, u8::from(read), 6 << 3, last_page_read]);
+        out.push((9 << 3) | 5);
+        out.extend_from_slice(&number.to_le_bytes());
+        out
+    }
+
+    fn manga(title: &str, chapters: &[Vec<u8>]) -> Vec<u8> {
+        let mut out = Vec::new();
+        field(3, title.as_bytes(), &mut out);
+        for chapter in chapters {
+            field(16, chapter, &mut out);
+        }
+        out
+    }
+
+    fn backup() -> Vec<u8> {
+        let mut out = Vec::new();
+        let matched = manga(
+            "ダンジョン飯",
+            &[
+                chapter("Chapter 1", true, 0, 1.0),
+                chapter("Chapter 2", false, 15, 2.0),
+                chapter("Chapter 3", false, 30, 3.0),
+            ],
+        );
+        field(1, &matched, &mut out);
+        field(
+            1,
+            &manga("Not in library", &[chapter("Chapter 1", true, 0, 1.0)]),
+            &mut out,
+        );
+        out
+    }
+
+    /// A stand-in for Suwayomi's GraphQL endpoint that records every mutation.
+    async fn suwayomi(
+        State(mutations): State<Arc<Mutex<Vec<Value>>>>,
+        Json(body): Json<Value>,
+    ) -> Json<Value> {
+        let query = body["query"].as_str().unwrap_or_default();
+        if query.starts_with("mutation") {
+            mutations
+                .lock()
+                .expect("lock")
+                .push(body["variables"].clone());
+            return Json(json!({ "data": { "updateChapters": { "chapters": [] } } }));
+        }
+        if query.contains("mangas") {
+            return Json(json!({ "data": { "mangas": { "nodes": [
+                { "id": 7, "title": "ダンジョン飯 新装版" },
+                { "id": 8, "title": "無職転生" },
+            ] } } }));
+        }
+        Json(json!({ "data": { "chapters": { "nodes": [
+            { "id": 70, "name": "Chapter 1", "chapterNumber": 1.0, "isRead": false, "lastPageRead": 0 },
+            { "id": 71, "name": "Chapter 2", "chapterNumber": 2.0, "isRead": false, "lastPageRead": 20 },
+            { "id": 72, "name": "Chapter 3", "chapterNumber": 3.0, "isRead": false, "lastPageRead": 0 },
+        ] } } }))
+    }
+
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("bind");
+        let addr = listener.local_addr().expect("addr");
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn previews_then_applies_only_forward_progress() {
+        let mutations = Arc::new(Mutex::new(Vec::new()));
+        let suwayomi_url = serve(
+            Router::new()
+                .route("/api/graphql", post(suwayomi))
+                .with_state(mutations.clone()),
+        )
+        .await;
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let dir = std::env::temp_dir().join(format!("manatan-sync-import-{nanos}"));
+        let sync_url = serve(router().with_state(SyncState::new(dir.clone()))).await;
+
+        let upload = |form: Form| {
+            reqwest::Client::new()
+                .post(format!("{sync_url}/tachiyomi-backup"))
+                .multipart(
+                    form.part("file", Part::bytes(backup()).file_name("manatan.tachibk"))
+                        .text("graphqlUrl", format!("{suwayomi_url}/api/graphql")),
+                )
+                .send()
+        };
+
+        let preview: Value = upload(Form::new())
+            .await
+            .expect("preview request")
+            .json()
+            .await
+            .expect("preview json");
+        assert_eq!(preview["applied"], false);
+        assert_eq!(preview["entries"][0]["suggestion"]["mangaId"], 7);
+        assert_eq!(preview["entries"][0]["confident"], false);
+        assert_eq!(preview["entries"][0]["readChapters"], 1);
+        assert_eq!(preview["entries"][0]["inProgressChapters"], 2);
+        assert!(preview["entries"][1]["suggestion"].is_null());
+        assert!(mutations.lock().expect("lock").is_empty());
+
+        // Nothing is confident enough to apply without explicit matches.
+        let unconfirmed: Value = upload(Form::new().text("confirm", "true"))
+            .await
+            .expect("confirm request")
+            .json()
+            .await
+            .expect("confirm json");
+        assert_eq!(unconfirmed["entries"], json!([]));
+        assert!(mutations.lock().expect("lock").is_empty());
+
+        let matches = json!({ "ダンジョン飯": 7, "Not in library": null });
+        let applied: Value = upload(
+            Form::new()
+                .text("confirm", "true")
+                .text("matches", matches.to_string()),
+        )
+        .await
+        .expect("apply request")
+        .json()
+        .await
+        .expect("apply json");
+        assert_eq!(applied["applied"], true);
+        assert_eq!(applied["entries"][0]["markedRead"], 1);
+        assert_eq!(applied["entries"][0]["pagesUpdated"], 1);
+        assert_eq!(
+            *mutations.lock().expect("lock"),
+            vec![
+                json!({ "ids": [72], "patch": { "lastPageRead": 30 } }),
+                json!({ "ids": [70], "patch": { "isRead": true } }),
+            ]
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn rejects_uploads_that_are_not_backups() {
+        let form = Form::new().text("file", "not a backup");
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let dir = std::env::temp_dir().join(format!("manatan-sync-import-bad-{nanos}"));
+        let sync_url = serve(router().with_state(SyncState::new(dir.clone()))).await;
+
+        let response = reqwest::Client::new()
+            .post(format!("{sync_url}/tachiyomi-backup"))
+            .multipart(form)
+            .send()
+            .await
+            .expect("request");
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -4,11 +4,19 @@ use crate::state::SyncState;
 
 mod auth;
 mod config;
+mod history;
+mod import;
+mod outbox;
 mod sync;
 
+pub(crate) use outbox::spawn_scheduler;
+
 pub fn router() -> Router<SyncState> {
     Router::new()
         .nest("/auth", auth::router())
         .nest("/config", config::router())
+        .nest("/history", history::router())
+        .nest("/import", import::router())
+        .nest("/outbox", outbox::router())
         .merge(sync::router())
 }
@@ -0,0 +1,325 @@
+//! Replays pushes that failed while the sync backend was unreachable.
+
+use std::time::Duration;
+
+use axum::{
+    Json, Router,
+    extract::State,
+    routing::{get, post},
+};
+use manatan_events::Event;
+use tracing::{debug, info, warn};
+
+use super::sync::ensure_backend;
+use crate::{
+    backend::{PushResult, SyncBackend},
+    error::SyncError,
+    merge::merge_payloads,
+    outbox::{self, OutboxEntry, OutboxSummary},
+    state::SyncState,
+};
+
+/// How often the scheduler checks whether queued pushes can go out.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn router() -> Router<SyncState> {
+    Router::new()
+        .route("/", get(list_handler))
+        .route("/flush", post(flush_handler))
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlushResponse {
+    pub flushed: usize,
+    pub etag: Option<String>,
+    pub sync_timestamp: Option<i64>,
+}
+
+async fn list_handler(
+    State(state): State<SyncState>,
+) -> Result<Json<Vec<OutboxSummary>>, SyncError> {
+    let entries = outbox::entries(&state)?;
+    Ok(Json(entries.iter().map(OutboxSummary::from).collect()))
+}
+
+async fn flush_handler(State(state): State<SyncState>) -> Result<Json<FlushResponse>, SyncError> {
+    flush(&state).await.map(Json)
+}
+
+/// Periodically replays the outbox once the backend is reachable again.
+pub fn spawn_scheduler(state: SyncState) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+        loop {
+            interval.tick().await;
+            if outbox::is_empty(&state) || state.get_refresh_token().is_none() {
+                continue;
+            }
+            if let Err(e) = flush(&state).await {
+                debug!("[OUTBOX] Replay failed, will retry: {}", e);
+            }
+        }
+    });
+}
+
+/// Whether a failed push should be queued for replay. Requests that can never succeed
+/// as sent, or that have no backend to go to, are not.
+pub(super) fn should_queue(error: &SyncError) -> bool {
+    !matches!(
+        error,
+        SyncError::NotAuthenticated | SyncError::BadRequest(_) | SyncError::OAuthError(_)
+    )
+}
+
+/// Replays every queued entry in one merged push. Entries are only removed once the
+/// push succeeds; on failure each records the attempt and stays queued.
+async fn flush(state: &SyncState) -> Result<FlushResponse, SyncError> {
+    let _guard = state.outbox_lock.lock().await;
+    let queued = outbox::entries(state)?;
+    if queued.is_empty() {
+        return Ok(FlushResponse {
+            flushed: 0,
+            etag: None,
+            sync_timestamp: None,
+        });
+    }
+
+    info!("[OUTBOX] Replaying {} queued push(es)...", queued.len());
+    let result = replay(state, &queued).await;
+    manatan_events::publish(match &result {
+        Ok((progress_entries, metadata_entries, conflicts, _)) => Event::SyncCompleted {
+            progress_entries: *progress_entries,
+            metadata_entries: *metadata_entries,
+            conflicts: *conflicts,
+        },
+        Err(e) => Event::SyncFailed {
+            error: e.to_string(),
+        },
+    });
+
+    match result {
+        Ok((_, _, _, etag)) => {
+            outbox::remove(state, &queued)?;
+            let now = chrono::Utc::now().timestamp_millis();
+            state.set_last_sync(now)?;
+            state.set_last_etag(&etag)?;
+            info!("[OUTBOX] Replayed {} queued push(es)", queued.len());
+            Ok(FlushResponse {
+                flushed: queued.len(),
+                etag: Some(etag),
+                sync_timestamp: Some(now),
+            })
+        }
+        Err(e) => {
+            for mut entry in queued {
+                entry.attempts += 1;
+                entry.last_error = Some(e.to_string());
+                outbox::store(state, &entry)?;
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Folds the queued payloads newest over oldest, merges the result with whatever the
+/// remote holds now, and pushes it. Returns (progress, metadata, conflicts, etag).
+async fn replay(
+    state: &SyncState,
+    queued: &[OutboxEntry],
+) -> Result<(usize, usize, usize, String), SyncError> {
+    ensure_backend(state).await?;
+    let device_id = state.get_device_id();
+
+    let mut entries = queued.iter().rev();
+    let Some(newest) = entries.next() else {
+        return Err(SyncError::BadRequest("Outbox is empty".to_string()));
+    };
+    let mut local = newest.payload.clone();
+    for older in entries {
+        local = merge_payloads(local, older.payload.clone(), &device_id).0;
+    }
+
+    let gdrive = state.google_drive.read().await;
+    let backend = gdrive.as_ref().ok_or(SyncError::NotAuthenticated)?;
+
+    // Always merge, even with our own earlier upload: the remote may have moved on since
+    // the entries were queued, and merging keeps replays from undoing that.
+    let (merged, conflicts, etag) = match backend.pull().await? {
+        Some((remote, remote_etag)) => {
+            let (merged, conflicts, _) = merge_payloads(local, remote, &device_id);
+            (merged, conflicts, Some(remote_etag))
+        }
+        None => (local, vec![], None),
+    };
+
+    if let Some(etag) = &etag
+        && state.is_last_push(&merged, Some(etag))
+    {
+        info!("[OUTBOX] Remote already holds the replayed data, skipping upload");
+        return Ok((
+            merged.ln_progress.len(),
+            merged.ln_metadata.len(),
+            conflicts.len(),
+            etag.clone(),
+        ));
+    }
+
+    match backend.push(&merged, etag.as_deref()).await? {
+        PushResult::Success { etag } => {
+            state.set_last_content(&merged)?;
+            Ok((
+                merged.ln_progress.len(),
+                merged.ln_metadata.len(),
+                conflicts.len(),
+                etag,
+            ))
+        }
+        PushResult::Conflict { remote_etag } => {
+            warn!(
+                "[OUTBOX] Remote changed during replay (etag {})",
+                remote_etag
+            );
+            Err(SyncError::Conflict(format!(
+                "[OUTBOX] Conflict detected! Remote etag: {remote_etag}"
+            )))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    };
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::{
+        backend::AuthFlow,
+        types::{LNProgress, SyncPayload},
+    };
+
+    /// An in-memory remote that can be taken offline.
+    #[derive(Clone, Default)]
+    struct FakeRemote {
+        stored: Arc<Mutex<Option<(SyncPayload, String)>>>,
+        offline: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl SyncBackend for FakeRemote {
+        async fn pull(&self) -> Result<Option<(SyncPayload, String)>, SyncError> {
+            if self.offline.load(Ordering::SeqCst) {
+                return Err(anyhow::anyhow!("remote unreachable").into());
+            }
+            Ok(self.stored.lock().expect("lock").clone())
+        }
+
+        async fn push(
+            &self,
+            data: &SyncPayload,
+            _etag: Option<&str>,
+        ) -> Result<PushResult, SyncError> {
+            let etag = uuid::Uuid::new_v4().to_string();
+            *self.stored.lock().expect("lock") = Some((data.clone(), etag.clone()));
+            Ok(PushResult::Success { etag })
+        }
+
+        async fn is_authenticated(&self) -> bool {
+            true
+        }
+
+        async fn get_user_info(&self) -> Result<Option<String>, SyncError> {
+            Ok(None)
+        }
+
+        fn start_auth(&self, _redirect_uri: &str) -> Result<AuthFlow, SyncError> {
+            Err(SyncError::NotAuthenticated)
+        }
+
+        async fn complete_auth(
+            &mut self,
+            _code: &str,
+            _redirect_uri: &str,
+        ) -> Result<(), SyncError> {
+            Ok(())
+        }
+
+        async fn disconnect(&mut self) -> Result<(), SyncError> {
+            Ok(())
+        }
+
+        async fn refresh_token(&mut self) -> Result<(), SyncError> {
+            Ok(())
+        }
+    }
+
+    fn payload(book_id: &str, total_progress: f64) -> SyncPayload {
+        let mut payload = SyncPayload::new("laptop".to_string());
+        payload.ln_progress.insert(
+            book_id.to_string(),
+            LNProgress {
+                total_progress,
+                last_modified: Some(1),
+                ..LNProgress::default()
+            },
+        );
+        payload
+    }
+
+    #[tokio::test]
+    async fn queued_pushes_replay_once_the_remote_is_back() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let dir = std::env::temp_dir().join(format!("manatan-sync-outbox-{nanos}"));
+        let state = SyncState::new(dir.clone());
+        let remote = FakeRemote::default();
+        *remote.stored.lock().expect("lock") =
+            Some((payload("remote-book", 0.9), "etag-1".to_string()));
+        *state.google_drive.write().await = Some(Box::new(remote.clone()));
+
+        let offline = SyncError::Conflict("offline".to_string());
+        assert!(should_queue(&offline));
+        assert!(!should_queue(&SyncError::NotAuthenticated));
+        outbox::enqueue(&state, payload("a", 0.25), &offline).expect("enqueue");
+        outbox::enqueue(&state, payload("b", 0.5), &offline).expect("enqueue");
+        let queued = outbox::entries(&state).expect("entries");
+        assert_eq!(queued.len(), 2);
+        assert!(queued.iter().all(|entry| entry.attempts == 0));
+
+        // Still unreachable: everything stays queued and records the attempt.
+        remote.offline.store(true, Ordering::SeqCst);
+        assert!(flush(&state).await.is_err());
+        let queued = outbox::entries(&state).expect("entries");
+        assert_eq!(queued.len(), 2);
+        assert!(queued.iter().all(|entry| entry.attempts == 1));
+        assert!(queued.iter().all(|entry| entry.last_error.is_some()));
+
+        remote.offline.store(false, Ordering::SeqCst);
+        let response = flush(&state).await.expect("replay");
+        assert_eq!(response.flushed, 2);
+        assert!(outbox::is_empty(&state));
+        assert_eq!(state.get_last_etag(), response.etag);
+
+        let (pushed, _) = remote
+            .stored
+            .lock()
+            .expect("lock")
+            .clone()
+            .expect("pushed payload");
+        let mut books: Vec<&str> = pushed.ln_progress.keys().map(String::as_str).collect();
+        books.sort();
+        assert_eq!(books, vec!["a", "b", "remote-book"]);
+
+        let again = flush(&state).await.expect("empty replay");
+        assert_eq!(again.flushed, 0);
+
+        drop(state);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -3,12 +3,16 @@ use axum::{
     extract::State,
     routing::{get, post},
 };
-use tracing::{debug, info};
+use manatan_events::Event;
+use tracing::{debug, info, warn};
 
+use super::outbox::should_queue;
 use crate::{
     backend::{PushResult, SyncBackend, google_drive::GoogleDriveBackend},
     error::SyncError,
+    history::{self, SyncHistoryEntry},
     merge::merge_payloads,
+    outbox,
     state::SyncState,
     types::{MergeRequest, MergeResponse, SyncPayload},
 };
@@ -20,7 +24,7 @@ pub fn router() -> Router<SyncState> {
         .route("/push", post(push_handler))
 }
 
-async fn ensure_backend(state: &SyncState) -> Result<(), SyncError> {
+pub(super) async fn ensure_backend(state: &SyncState) -> Result<(), SyncError> {
     let mut gdrive = state.google_drive.write().await;
 
     if gdrive.is_none() {
@@ -30,7 +34,7 @@ async fn ensure_backend(state: &SyncState) -> Result<(), SyncError> {
         if access_token.is_some() && refresh_token.is_some() {
             let mut backend = GoogleDriveBackend::new(state.clone());
             backend.initialize().await?;
-            *gdrive = Some(backend);
+            *gdrive = Some(Box::new(backend));
         } else {
             return Err(SyncError::NotAuthenticated);
         }
@@ -50,6 +54,21 @@ async fn merge_handler(
     State(state): State<SyncState>,
     Json(req): Json<MergeRequest>,
 ) -> Result<Json<MergeResponse>, SyncError> {
+    let result = merge(state, req).await;
+    manatan_events::publish(match &result {
+        Ok(Json(response)) => Event::SyncCompleted {
+            progress_entries: response.payload.ln_progress.len(),
+            metadata_entries: response.payload.ln_metadata.len(),
+            conflicts: response.conflicts.len(),
+        },
+        Err(e) => Event::SyncFailed {
+            error: e.to_string(),
+        },
+    });
+    result
+}
+
+async fn merge(state: SyncState, req: MergeRequest) -> Result<Json<MergeResponse>, SyncError> {
     info!("[MERGE] Starting sync operation...");
     ensure_backend(&state).await?;
 
@@ -86,8 +105,10 @@ async fn merge_handler(
     info!("[MERGE] Downloading remote data from Google Drive...");
     let remote_result = backend.pull().await?;
 
-    let (merged_payload, conflicts, etag) = if let Some((remote_payload, remote_etag)) =
-        remote_result
+    let (merged_payload, conflicts, changes, remote_device, etag) = if let Some((
+        remote_payload,
+        remote_etag,
+    )) = remote_result
     {
         let remote_progress_count = remote_payload.ln_progress.len();
         let remote_metadata_count = remote_payload.ln_metadata.len();
@@ -108,14 +129,21 @@ async fn merge_handler(
                 "[MERGE] Same device detected ({}), will overwrite remote",
                 device_id
             );
-            (local_payload.clone(), vec![], Some(remote_etag))
+            (
+                local_payload.clone(),
+                vec![],
+                vec![],
+                Some(remote_device_id),
+                Some(remote_etag),
+            )
         } else {
             info!(
                 "[MERGE] Different device detected. Local device: {}, Remote device: {}",
                 device_id, remote_device_id
             );
             info!("[MERGE] Merging payloads...");
-            let (merged, conflicts) = merge_payloads(local_payload, remote_payload, &device_id);
+            let (merged, conflicts, changes) =
+                merge_payloads(local_payload, remote_payload, &device_id);
 
             let merged_progress = merged.ln_progress.len();
             let merged_metadata = merged.ln_metadata.len();
@@ -126,31 +154,42 @@ async fn merge_handler(
                 conflicts.len()
             );
 
-            (merged, conflicts, Some(remote_etag))
+            (
+                merged,
+                conflicts,
+                changes,
+                Some(remote_device_id),
+                Some(remote_etag),
+            )
         }
     } else {
         info!("[MERGE] No remote data found, using local data only");
-        (local_payload, vec![], None)
+        (local_payload, vec![], vec![], None, None)
     };
 
     drop(gdrive);
 
     // Push merged data
-    let gdrive = state.google_drive.read().await;
-    let backend = gdrive.as_ref().ok_or(SyncError::NotAuthenticated)?;
-
-    info!("[MERGE] Uploading merged data to Google Drive...");
-    let push_result = backend.push(&merged_payload, etag.as_deref()).await?;
-
-    match push_result {
-        PushResult::Success { etag: new_etag } => {
-            info!("[MERGE] Upload successful! New etag: {}", new_etag);
-            state.set_last_etag(&new_etag)?;
-        }
-        PushResult::Conflict { remote_etag } => {
-            return Err(SyncError::Conflict(format!(
-                "[MERGE] Conflict detected! Expected etag: {etag:?}, got: {remote_etag}"
-            )));
+    if state.is_last_push(&merged_payload, etag.as_deref()) {
+        info!("[MERGE] Remote already holds the merged data, skipping upload");
+    } else {
+        let gdrive = state.google_drive.read().await;
+        let backend = gdrive.as_ref().ok_or(SyncError::NotAuthenticated)?;
+
+        info!("[MERGE] Uploading merged data to Google Drive...");
+        let push_result = backend.push(&merged_payload, etag.as_deref()).await?;
+
+        match push_result {
+            PushResult::Success { etag: new_etag } => {
+                info!("[MERGE] Upload successful! New etag: {}", new_etag);
+                state.set_last_etag(&new_etag)?;
+                state.set_last_content(&merged_payload)?;
+            }
+            PushResult::Conflict { remote_etag } => {
+                return Err(SyncError::Conflict(format!(
+                    "[MERGE] Conflict detected! Expected etag: {etag:?}, got: {remote_etag}"
+                )));
+            }
         }
     }
 
@@ -166,14 +205,34 @@ async fn merge_handler(
         final_progress, final_metadata
     );
     info!("[MERGE] Conflicts resolved: {}", conflicts.len());
+    info!("[MERGE] Local changes: {}", changes.len());
     info!("[MERGE] ==================================");
 
+    let entry = SyncHistoryEntry {
+        id: uuid::Uuid::new_v4().to_string(),
+        synced_at: now,
+        remote_device,
+        progress_entries: final_progress,
+        metadata_entries: final_metadata,
+        conflicts: conflicts.len(),
+        changes,
+    };
+    let history_id = match history::record(&state, &entry) {
+        Ok(()) => Some(entry.id),
+        Err(e) => {
+            warn!("[MERGE] Failed to record sync history: {}", e);
+            None
+        }
+    };
+
     Ok(Json(MergeResponse {
         payload: merged_payload,
         sync_timestamp: now,
         files_to_upload: vec![],
         files_to_download: vec![],
         conflicts,
+        changes: entry.changes,
+        history_id,
     }))
 }
 
@@ -225,6 +284,33 @@ async fn push_handler(
     State(state): State<SyncState>,
     Json(req): Json<PushRequest>,
 ) -> Result<Json<PushResponse>, SyncError> {
+    let progress_entries = req.payload.ln_progress.len();
+    let metadata_entries = req.payload.ln_metadata.len();
+    let result = push(&state, &req).await;
+    manatan_events::publish(match &result {
+        Ok(_) => Event::BackupCompleted {
+            progress_entries,
+            metadata_entries,
+        },
+        Err(e) => Event::SyncFailed {
+            error: e.to_string(),
+        },
+    });
+
+    // Keep the change so it is not lost; the outbox replays it once the backend is
+    // reachable again.
+    if let Err(e) = &result
+        && should_queue(e)
+    {
+        match outbox::enqueue(&state, req.payload, e) {
+            Ok(entry) => info!("[PUSH] Push failed, queued as outbox entry {}", entry.id),
+            Err(queue_err) => warn!("[PUSH] Failed to queue push in outbox: {}", queue_err),
+        }
+    }
+    result
+}
+
+async fn push(state: &SyncState, req: &PushRequest) -> Result<Json<PushResponse>, SyncError> {
     info!("[PUSH] Starting push operation...");
 
     let payload_size = req.payload.ln_progress.len();
@@ -234,7 +320,20 @@ async fn push_handler(
         payload_size, metadata_size
     );
 
-    ensure_backend(&state).await?;
+    ensure_backend(state).await?;
+
+    if let Some(etag) = &req.etag
+        && state.is_last_push(&req.payload, Some(etag))
+    {
+        let now = chrono::Utc::now().timestamp_millis();
+        state.set_last_sync(now)?;
+        info!("[PUSH] Remote already holds this payload, skipping upload");
+        return Ok(Json(PushResponse {
+            success: true,
+            etag: etag.clone(),
+            sync_timestamp: now,
+        }));
+    }
 
     let gdrive = state.google_drive.read().await;
     let backend = gdrive.as_ref().ok_or(SyncError::NotAuthenticated)?;
@@ -247,6 +346,7 @@ async fn push_handler(
             let now = chrono::Utc::now().timestamp_millis();
             state.set_last_sync(now)?;
             state.set_last_etag(&etag)?;
+            state.set_last_content(&req.payload)?;
 
             info!(
                 "[PUSH] Upload successful! Timestamp: {}, etag: {}",
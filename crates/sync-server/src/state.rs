@@ -1,15 +1,21 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::BTreeMap, path::PathBuf, sync::Arc};
 
+use sha2::{Digest, Sha256};
 use sled::Db;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 
-use crate::{backend::google_drive::GoogleDriveBackend, types::SyncConfig};
+use crate::{
+    backend::SyncBackend,
+    types::{GoogleDriveFolderType, SyncBackendType, SyncConfig, SyncPayload},
+};
 
 const DB_KEY_DEVICE_ID: &[u8] = b"device_id";
 const DB_KEY_ACCESS_TOKEN: &[u8] = b"google_access_token";
 const DB_KEY_REFRESH_TOKEN: &[u8] = b"google_refresh_token";
+// Single-slot bookkeeping from before it was kept per remote; moved on startup.
 const DB_KEY_LAST_SYNC: &[u8] = b"last_sync_timestamp";
 const DB_KEY_LAST_ETAG: &[u8] = b"last_sync_etag";
+const DB_KEY_REMOTE_PREFIX: &str = "remote_sync:";
 const DB_KEY_SYNC_CONFIG: &[u8] = b"sync_config";
 const DB_KEY_AUTH_STATE: &[u8] = b"oauth_state";
 const DB_KEY_AUTH_REDIRECT_URI: &[u8] = b"oauth_redirect_uri";
@@ -19,7 +25,11 @@ const DB_KEY_AUTH_CODE_VERIFIER: &[u8] = b"oauth_code_verifier";
 pub struct SyncState {
     pub db: Db,
     pub data_dir: PathBuf,
-    pub google_drive: Arc<RwLock<Option<GoogleDriveBackend>>>,
+    /// The connected remote. Google Drive unless another backend was set with
+    /// [`SyncState::set_backend`].
+    pub google_drive: Arc<RwLock<Option<Box<dyn SyncBackend>>>>,
+    /// Held while the outbox is replayed so two flushes never push the same entries.
+    pub outbox_lock: Arc<Mutex<()>>,
 }
 
 impl SyncState {
@@ -41,7 +51,11 @@ impl SyncState {
             db,
             data_dir: sync_dir,
             google_drive: Arc::new(RwLock::new(None)),
+            outbox_lock: Arc::new(Mutex::new(())),
         };
+        if let Err(e) = state.migrate_legacy_sync_metadata() {
+            tracing::warn!("[SYNC] Failed to migrate sync bookkeeping: {}", e);
+        }
 
         // Try to initialize Google Drive if tokens exist
         let access_token = state.get_access_token();
@@ -53,6 +67,11 @@ impl SyncState {
         state
     }
 
+    /// Replaces the remote backend, e.g. with an in-memory one in tests.
+    pub async fn set_backend(&self, backend: Box<dyn SyncBackend>) {
+        *self.google_drive.write().await = Some(backend);
+    }
+
     // Device ID
     pub fn get_device_id(&self) -> String {
         self.db
@@ -162,30 +181,106 @@ impl SyncState {
         Ok(())
     }
 
-    // Sync Metadata
-    pub fn get_last_sync(&self) -> Option<i64> {
-        self.db.get(DB_KEY_LAST_SYNC).ok().flatten().and_then(|v| {
-            let bytes: [u8; 8] = v.as_ref().try_into().ok()?;
-            Some(i64::from_le_bytes(bytes))
-        })
+    // Sync Metadata, kept per remote so switching backends or folders never reuses
+    // another remote's etag.
+
+    /// The remote the current sync config points at.
+    pub fn current_remote(&self) -> String {
+        remote_id(&self.get_sync_config())
     }
 
-    pub fn set_last_sync(&self, timestamp: i64) -> Result<(), sled::Error> {
-        self.db.insert(DB_KEY_LAST_SYNC, &timestamp.to_le_bytes())?;
+    pub fn get_remote_state(&self, remote: &str) -> RemoteSyncState {
+        self.db
+            .get(format!("{DB_KEY_REMOTE_PREFIX}{remote}"))
+            .ok()
+            .flatten()
+            .and_then(|v| serde_json::from_slice(&v).ok())
+            .unwrap_or_default()
+    }
+
+    /// Bookkeeping for every remote synced with so far, keyed by remote.
+    pub fn remote_states(&self) -> BTreeMap<String, RemoteSyncState> {
+        self.db
+            .scan_prefix(DB_KEY_REMOTE_PREFIX)
+            .filter_map(Result::ok)
+            .filter_map(|(key, value)| {
+                let remote = String::from_utf8_lossy(&key[DB_KEY_REMOTE_PREFIX.len()..]);
+                let state = serde_json::from_slice(&value).ok()?;
+                Some((remote.to_string(), state))
+            })
+            .collect()
+    }
+
+    fn update_remote_state(
+        &self,
+        update: impl FnOnce(&mut RemoteSyncState),
+    ) -> Result<(), sled::Error> {
+        let remote = self.current_remote();
+        let mut remote_state = self.get_remote_state(&remote);
+        update(&mut remote_state);
+        let bytes = serde_json::to_vec(&remote_state).unwrap_or_default();
+        self.db
+            .insert(format!("{DB_KEY_REMOTE_PREFIX}{remote}"), bytes)?;
         self.db.flush()?;
         Ok(())
     }
 
+    pub fn get_last_sync(&self) -> Option<i64> {
+        self.get_remote_state(&self.current_remote()).last_sync
+    }
+
+    pub fn set_last_sync(&self, timestamp: i64) -> Result<(), sled::Error> {
+        self.update_remote_state(|remote| remote.last_sync = Some(timestamp))
+    }
+
     pub fn get_last_etag(&self) -> Option<String> {
-        self.db
-            .get(DB_KEY_LAST_ETAG)
-            .ok()
-            .flatten()
-            .map(|v| String::from_utf8_lossy(&v).to_string())
+        self.get_remote_state(&self.current_remote()).etag
     }
 
     pub fn set_last_etag(&self, etag: &str) -> Result<(), sled::Error> {
-        self.db.insert(DB_KEY_LAST_ETAG, etag.as_bytes())?;
+        self.update_remote_state(|remote| remote.etag = Some(etag.to_string()))
+    }
+
+    pub fn get_last_content_hash(&self) -> Option<String> {
+        self.get_remote_state(&self.current_remote()).content_hash
+    }
+
+    /// Records the payload last pushed to the current remote.
+    pub fn set_last_content(&self, payload: &SyncPayload) -> Result<(), sled::Error> {
+        let hash = content_hash(payload);
+        self.update_remote_state(|remote| remote.content_hash = Some(hash))
+    }
+
+    /// Whether the remote at `etag` already holds `payload`: it is still the file our last
+    /// push left, and that push carried the same content. Uploading again would change
+    /// nothing.
+    pub fn is_last_push(&self, payload: &SyncPayload, etag: Option<&str>) -> bool {
+        let remote = self.get_remote_state(&self.current_remote());
+        etag.is_some()
+            && remote.etag.as_deref() == etag
+            && remote.content_hash == Some(content_hash(payload))
+    }
+
+    /// Moves the single-slot values older versions kept onto the configured remote, which
+    /// is the one they were recorded against.
+    fn migrate_legacy_sync_metadata(&self) -> Result<(), sled::Error> {
+        let last_sync = self.db.get(DB_KEY_LAST_SYNC)?.and_then(|v| {
+            let bytes: [u8; 8] = v.as_ref().try_into().ok()?;
+            Some(i64::from_le_bytes(bytes))
+        });
+        let etag = self
+            .db
+            .get(DB_KEY_LAST_ETAG)?
+            .map(|v| String::from_utf8_lossy(&v).to_string());
+        if last_sync.is_none() && etag.is_none() {
+            return Ok(());
+        }
+        self.update_remote_state(|remote| {
+            remote.last_sync = remote.last_sync.or(last_sync);
+            remote.etag = remote.etag.take().or(etag);
+        })?;
+        self.db.remove(DB_KEY_LAST_SYNC)?;
+        self.db.remove(DB_KEY_LAST_ETAG)?;
         self.db.flush()?;
         Ok(())
     }
@@ -237,6 +332,49 @@ impl SyncState {
     }
 }
 
+/// Names a remote: the backend plus, for Google Drive, the folder the data lives in.
+pub fn remote_id(config: &SyncConfig) -> String {
+    match config.backend {
+        SyncBackendType::GoogleDrive => {
+            let space = match config.google_drive_folder_type {
+                GoogleDriveFolderType::Public => "public",
+                GoogleDriveFolderType::AppData => "appdata",
+            };
+            format!("google_drive:{space}:{}", config.google_drive_folder)
+        }
+        SyncBackendType::WebDav => "webdav".to_string(),
+        SyncBackendType::SyncYomi => "syncyomi".to_string(),
+        SyncBackendType::None => "none".to_string(),
+    }
+}
+
+/// SHA-256 of a payload's JSON, in hex. Going through a `Value` sorts the map keys, so
+/// equal payloads hash alike; the payload's own `lastModified` stamp is left out, as every
+/// merge sets it afresh.
+pub fn content_hash(payload: &SyncPayload) -> String {
+    let bytes = serde_json::to_value(payload)
+        .map(|mut value| {
+            if let Some(fields) = value.as_object_mut() {
+                fields.remove("lastModified");
+            }
+            value
+        })
+        .and_then(|value| serde_json::to_vec(&value))
+        .unwrap_or_default();
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Sync bookkeeping for one remote.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteSyncState {
+    pub last_sync: Option<i64>,
+    /// Etag of the remote file after our last push or pull, for optimistic locking.
+    pub etag: Option<String>,
+    /// [`content_hash`] of the last payload pushed, so pushing it again can be skipped.
+    pub content_hash: Option<String>,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct UploadState {
     pub upload_id: String,
@@ -249,3 +387,122 @@ pub struct UploadState {
     pub started_at: i64,
     pub last_chunk_at: i64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(label: &str) -> PathBuf {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        std::env::temp_dir().join(format!("manatan-sync-state-{label}-{nanos}"))
+    }
+
+    fn use_remote(state: &SyncState, backend: SyncBackendType, folder: &str) {
+        let config = SyncConfig {
+            backend,
+            google_drive_folder: folder.to_string(),
+            ..SyncConfig::default()
+        };
+        state.set_sync_config(&config).expect("save config");
+    }
+
+    #[test]
+    fn only_the_last_pushed_content_at_its_etag_is_unchanged() {
+        let dir = temp_dir("last-push");
+        let state = SyncState::new(dir.clone());
+        use_remote(&state, SyncBackendType::GoogleDrive, "Manatan");
+        let payload = SyncPayload::default();
+        assert!(!state.is_last_push(&payload, None));
+
+        state.set_last_etag("drive-1").expect("etag");
+        state.set_last_content(&payload).expect("hash");
+        assert!(state.is_last_push(&payload, Some("drive-1")));
+        assert!(!state.is_last_push(&payload, Some("drive-2")));
+        assert!(!state.is_last_push(&payload, None));
+
+        let restamped = SyncPayload {
+            last_modified: 42,
+            ..SyncPayload::default()
+        };
+        assert!(state.is_last_push(&restamped, Some("drive-1")));
+        let edited = SyncPayload {
+            device_id: "other".to_string(),
+            ..SyncPayload::default()
+        };
+        assert!(!state.is_last_push(&edited, Some("drive-1")));
+
+        drop(state);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn switching_remotes_never_leaks_etags() {
+        let dir = temp_dir("switch");
+        let state = SyncState::new(dir.clone());
+
+        use_remote(&state, SyncBackendType::GoogleDrive, "Manatan");
+        state.set_last_etag("drive-1").expect("etag");
+        state.set_last_sync(100).expect("sync");
+        state
+            .set_last_content(&SyncPayload::default())
+            .expect("hash");
+
+        use_remote(&state, SyncBackendType::WebDav, "Manatan");
+        assert_eq!(state.get_last_etag(), None);
+        assert_eq!(state.get_last_sync(), None);
+        assert_eq!(state.get_last_content_hash(), None);
+        state.set_last_etag("dav-1").expect("etag");
+
+        use_remote(&state, SyncBackendType::GoogleDrive, "Other folder");
+        assert_eq!(state.get_last_etag(), None);
+
+        use_remote(&state, SyncBackendType::GoogleDrive, "Manatan");
+        assert_eq!(state.get_last_etag().as_deref(), Some("drive-1"));
+        assert_eq!(state.get_last_sync(), Some(100));
+        assert_eq!(
+            state.get_last_content_hash(),
+            Some(content_hash(&SyncPayload::default()))
+        );
+
+        use_remote(&state, SyncBackendType::WebDav, "Manatan");
+        assert_eq!(state.get_last_etag().as_deref(), Some("dav-1"));
+        assert_eq!(
+            state.remote_states().keys().collect::<Vec<_>>(),
+            ["google_drive:public:Manatan", "webdav"]
+        );
+
+        drop(state);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn legacy_values_move_onto_the_configured_remote() {
+        let dir = temp_dir("legacy");
+        let state = SyncState::new(dir.clone());
+        use_remote(&state, SyncBackendType::WebDav, "Manatan");
+        state
+            .db
+            .insert(DB_KEY_LAST_ETAG, b"old-etag".as_slice())
+            .expect("insert");
+        state
+            .db
+            .insert(DB_KEY_LAST_SYNC, &42i64.to_le_bytes())
+            .expect("insert");
+        state.db.flush().expect("flush");
+        drop(state);
+
+        let state = SyncState::new(dir.clone());
+        assert_eq!(state.get_last_etag().as_deref(), Some("old-etag"));
+        assert_eq!(state.get_last_sync(), Some(42));
+        assert!(state.db.get(DB_KEY_LAST_ETAG).expect("get").is_none());
+
+        use_remote(&state, SyncBackendType::GoogleDrive, "Manatan");
+        assert_eq!(state.get_last_etag(), None);
+
+        drop(state);
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,327 @@
+//! Minimal reader for Tachiyomi/Mihon `.tachibk` backups (gzip-compressed protobuf).
+//!
+//! Only the fields needed to carry reading progress over are decoded; everything else is
+//! skipped by wire type, so newer backup versions with extra fields still parse.
+
+use std::io::Read;
+
+use anyhow::{anyhow, bail};
+use flate2::read::GzDecoder;
+use serde::Serialize;
+
+// Field numbers from Mihon's `Backup`, `BackupManga` and `BackupChapter` messages.
+const BACKUP_MANGA: u32 = 1;
+const MANGA_URL: u32 = 2;
+const MANGA_TITLE: u32 = 3;
+const MANGA_CHAPTERS: u32 = 16;
+const CHAPTER_URL: u32 = 1;
+const CHAPTER_NAME: u32 = 2;
+const CHAPTER_READ: u32 = 4;
+const CHAPTER_LAST_PAGE_READ: u32 = 6;
+const CHAPTER_NUMBER: u32 = 9;
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupManga {
+    pub title: String,
+    pub url: String,
+    pub chapters: Vec<BackupChapter>,
+}
+
+impl BackupManga {
+    /// Whether the backup has anything worth importing for this series.
+    pub fn has_progress(&self) -> bool {
+        self.chapters
+            .iter()
+            .any(|chapter| chapter.read || chapter.last_page_read > 0)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupChapter {
+    pub url: String,
+    pub name: String,
+    pub read: bool,
+    pub last_page_read: i64,
+    pub chapter_number: f32,
+}
+
+/// Parses a backup file. Both gzip-compressed and raw protobuf payloads are accepted.
+pub fn parse_backup(bytes: &[u8]) -> anyhow::Result<Vec<BackupManga>> {
+    let decompressed;
+    let payload = if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut buffer = Vec::new();
+        GzDecoder::new(bytes)
+            .read_to_end(&mut buffer)
+            .map_err(|err| anyhow!("Failed to decompress backup: {err}"))?;
+        decompressed = buffer;
+        decompressed.as_slice()
+    } else {
+        bytes
+    };
+
+    let mut mangas = Vec::new();
+    for field in Fields::new(payload) {
+        let (number, value) = field?;
+        if number == BACKUP_MANGA {
+            mangas.push(parse_manga(value.bytes()?)?);
+        }
+    }
+    Ok(mangas)
+}
+
+fn parse_manga(bytes: &[u8]) -> anyhow::Result<BackupManga> {
+    let mut manga = BackupManga::default();
+    for field in Fields::new(bytes) {
+        let (number, value) = field?;
+        match number {
+            MANGA_URL => manga.url = value.string()?,
+            MANGA_TITLE => manga.title = value.string()?,
+            MANGA_CHAPTERS => manga.chapters.push(parse_chapter(value.bytes()?)?),
+            _ => {}
+        }
+    }
+    Ok(manga)
+}
+
+fn parse_chapter(bytes: &[u8]) -> anyhow::Result<BackupChapter> {
+    let mut chapter = BackupChapter::default();
+    for field in Fields::new(bytes) {
+        let (number, value) = field?;
+        match number {
+            CHAPTER_URL => chapter.url = value.string()?,
+            CHAPTER_NAME => chapter.name = value.string()?,
+            CHAPTER_READ => chapter.read = value.varint()? != 0,
+            CHAPTER_LAST_PAGE_READ => chapter.last_page_read = value.varint()? as i64,
+            CHAPTER_NUMBER => chapter.chapter_number = value.fixed32_f32()?,
+            _ => {}
+        }
+    }
+    Ok(chapter)
+}
+
+enum Value<'a> {
+    Varint(u64),
+    Fixed64,
+    Bytes(&'a [u8]),
+    Fixed32([u8; 4]),
+}
+
+impl<'a> Value<'a> {
+    fn varint(&self) -> anyhow::Result<u64> {
+        match self {
+            Value::Varint(value) => Ok(*value),
+            _ => bail!("Expected a varint field in backup"),
+        }
+    }
+
+    fn bytes(&self) -> anyhow::Result<&'a [u8]> {
+        match self {
+            Value::Bytes(bytes) => Ok(bytes),
+            _ => bail!("Expected a length-delimited field in backup"),
+        }
+    }
+
+    fn string(&self) -> anyhow::Result<String> {
+        Ok(String::from_utf8_lossy(self.bytes()?).into_owned())
+    }
+
+    fn fixed32_f32(&self) -> anyhow::Result<f32> {
+        match self {
+            Value::Fixed32(bytes) => Ok(f32::from_le_bytes(*bytes)),
+            _ => bail!("Expected a 32-bit field in backup"),
+        }
+    }
+}
+
+/// Iterates over the top-level fields of one protobuf message.
+struct Fields<'a> {
+    data: &'a [u8],
+    failed: bool,
+}
+
+impl<'a> Fields<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            failed: false,
+        }
+    }
+
+    fn read_varint(&mut self) -> anyhow::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self
+                .data
+                .split_first()
+                .ok_or_else(|| anyhow!("Truncated varint in backup"))?;
+            self.data = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("Malformed varint in backup")
+    }
+
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        if self.data.len() < len {
+            bail!("Truncated field in backup");
+        }
+        let (head, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn next_field(&mut self) -> anyhow::Result<(u32, Value<'a>)> {
+        let key = self.read_varint()?;
+        let number = (key >> 3) as u32;
+        let value = match key & 0x7 {
+            0 => Value::Varint(self.read_varint()?),
+            1 => {
+                self.take(8)?;
+                Value::Fixed64
+            }
+            2 => {
+                let len = self.read_varint()? as usize;
+                Value::Bytes(self.take(len)?)
+            }
+            5 => {
+                let bytes = self.take(4)?;
+                Value::Fixed32([bytes[0], bytes[1], bytes[2], bytes[3]])
+            }
+            wire_type => bail!("Unsupported protobuf wire type {wire_type} in backup"),
+        };
+        Ok((number, value))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = anyhow::Result<(u32, Value<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed || self.data.is_empty() {
+            return None;
+        }
+        let field = self.next_field();
+        self.failed = field.is_err();
+        Some(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{Compression, write::GzEncoder};
+
+    use super::*;
+
+    fn key(number: u32, wire_type: u8, out: &mut Vec<u8>) {
+        varint((u64::from(number) << 3) | u64::from(wire_type), out);
+    }
+
+    fn varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push(((value as u8) & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn bytes_field(number: u32, bytes: &[u8], out: &mut Vec<u8>) {
+        key(number, 2, out);
+        varint(bytes.len() as u64, out);
+        out.extend_from_slice(bytes);
+    }
+
+    fn varint_field(number: u32, value: u64, out: &mut Vec<u8>) {
+        key(number, 0, out);
+        varint(value, out);
+    }
+
+    fn chapter(name: &str, read: bool, last_page_read: u64, number: f32) -> Vec<u8> {
+        let mut out = Vec::new();
+        bytes_field(CHAPTER_URL, format!("/chapter/{name}").as_bytes(), &mut out);
+        bytes_field(CHAPTER_NAME, name.as_bytes(), &mut out);
+        varint_field(CHAPTER_READ, u64::from(read), &mut out);
+        varint_field(CHAPTER_LAST_PAGE_READ, last_page_read, &mut out);
+        key(CHAPTER_NUMBER, 5, &mut out);
+        out.extend_from_slice(&number.to_le_bytes());
+        out
+    }
+
+    fn backup() -> Vec<u8> {
+        let mut manga = Vec::new();
+        bytes_field(MANGA_URL, b"/manga/1", &mut manga);
+        bytes_field(MANGA_TITLE, "ダンジョン飯".as_bytes(), &mut manga);
+        // Fields this reader doesn't know about, of every wire type.
+        varint_field(5, 300, &mut manga);
+        key(7, 1, &mut manga);
+        manga.extend_from_slice(&[0; 8]);
+        bytes_field(
+            MANGA_CHAPTERS,
+            &chapter("Chapter 1", true, 0, 1.0),
+            &mut manga,
+        );
+        bytes_field(
+            MANGA_CHAPTERS,
+            &chapter("Chapter 2", false, 150, 2.0),
+            &mut manga,
+        );
+
+        let mut unread = Vec::new();
+        bytes_field(MANGA_TITLE, "Unread".as_bytes(), &mut unread);
+        bytes_field(
+            MANGA_CHAPTERS,
+            &chapter("Chapter 1", false, 0, 1.0),
+            &mut unread,
+        );
+
+        let mut out = Vec::new();
+        bytes_field(BACKUP_MANGA, &manga, &mut out);
+        bytes_field(2, b"category", &mut out);
+        bytes_field(BACKUP_MANGA, &unread, &mut out);
+        out
+    }
+
+    #[test]
+    fn decodes_progress_and_skips_unknown_fields() {
+        let mangas = parse_backup(&backup()).expect("backup should parse");
+        assert_eq!(mangas.len(), 2);
+
+        let manga = &mangas[0];
+        assert_eq!(manga.title, "ダンジョン飯");
+        assert_eq!(manga.url, "/manga/1");
+        assert_eq!(manga.chapters.len(), 2);
+        assert!(manga.chapters[0].read);
+        assert_eq!(manga.chapters[1].name, "Chapter 2");
+        assert_eq!(manga.chapters[1].last_page_read, 150);
+        assert_eq!(manga.chapters[1].chapter_number, 2.0);
+        assert!(manga.has_progress());
+        assert!(!mangas[1].has_progress());
+    }
+
+    #[test]
+    fn accepts_gzip_compressed_backups() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&backup()).expect("compress");
+        let compressed = encoder.finish().expect("compress");
+
+        let mangas = parse_backup(&compressed).expect("backup should parse");
+        assert_eq!(mangas[0].title, "ダンジョン飯");
+        assert_eq!(mangas[0].chapters.len(), 2);
+    }
+
+    #[test]
+    fn rejects_truncated_and_malformed_backups() {
+        let full = backup();
+        assert!(parse_backup(&full[..full.len() - 3]).is_err());
+        assert!(parse_backup(&[0x1f, 0x8b, 0x00]).is_err());
+        // Wire type 3 (start group) is not supported.
+        assert!(parse_backup(&[(1 << 3) | 3]).is_err());
+        // A varint where the manga message is expected.
+        assert!(parse_backup(&[(BACKUP_MANGA as u8) << 3, 1]).is_err());
+    }
+}
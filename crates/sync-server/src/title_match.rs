@@ -0,0 +1,141 @@
+//! Fuzzy title matching used when importing progress from other readers, where the same
+//! series is often named slightly differently (volume suffixes, brackets, full-width
+//! punctuation).
+
+use std::collections::HashMap;
+
+/// Matches at or above this score are applied without an explicit confirmation.
+pub const CONFIDENT_MATCH: f64 = 0.9;
+/// Matches below this score are not suggested at all.
+pub const MIN_SUGGESTED_MATCH: f64 = 0.5;
+
+/// Lowercases, folds full-width ASCII, and drops bracketed notes, punctuation and
+/// whitespace.
+pub fn normalize_title(title: &str) -> String {
+    let mut normalized = String::with_capacity(title.len());
+    let mut bracket_depth = 0usize;
+    for ch in title.chars() {
+        let ch = fold_full_width(ch);
+        match ch {
+            '(' | '[' | '{' | '【' | '（' | '〔' | '「' | '『' => {
+                // Japanese quotes usually wrap the actual title, so only strip notes.
+                if !matches!(ch, '「' | '『') {
+                    bracket_depth += 1;
+                }
+            }
+            ')' | ']' | '}' | '】' | '）' | '〕' => {
+                bracket_depth = bracket_depth.saturating_sub(1);
+            }
+            _ if bracket_depth > 0 => {}
+            _ if ch.is_alphanumeric() => normalized.extend(ch.to_lowercase()),
+            _ => {}
+        }
+    }
+    // A title made only of a bracketed note would normalize to nothing; keep its text.
+    if normalized.is_empty() {
+        return title
+            .chars()
+            .map(fold_full_width)
+            .filter(|ch| ch.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect();
+    }
+    normalized
+}
+
+fn fold_full_width(ch: char) -> char {
+    match ch {
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(ch as u32 - 0xFEE0).unwrap_or(ch),
+        '\u{3000}' => ' ',
+        _ => ch,
+    }
+}
+
+/// Similarity between two titles in `0.0..=1.0`, using the Dice coefficient over
+/// character bigrams of the normalized titles.
+pub fn title_similarity(left: &str, right: &str) -> f64 {
+    let left = normalize_title(left);
+    let right = normalize_title(right);
+    if left.is_empty() || right.is_empty() {
+        return 0.0;
+    }
+    if left == right {
+        return 1.0;
+    }
+
+    let left_bigrams = bigrams(&left);
+    let right_bigrams = bigrams(&right);
+    let left_total: usize = left_bigrams.values().sum();
+    let right_total: usize = right_bigrams.values().sum();
+    let shared: usize = left_bigrams
+        .iter()
+        .map(|(bigram, count)| (*count).min(right_bigrams.get(bigram).copied().unwrap_or(0)))
+        .sum();
+    (2 * shared) as f64 / (left_total + right_total) as f64
+}
+
+fn bigrams(text: &str) -> HashMap<(char, char), usize> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut counts = HashMap::new();
+    if chars.len() == 1 {
+        counts.insert((chars[0], chars[0]), 1);
+    }
+    for pair in chars.windows(2) {
+        *counts.entry((pair[0], pair[1])).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Picks the best scoring candidate for `title`, ignoring anything below
+/// [`MIN_SUGGESTED_MATCH`].
+pub fn best_match<'a, T>(
+    title: &str,
+    candidates: impl IntoIterator<Item = (&'a str, T)>,
+) -> Option<(T, f64)> {
+    candidates
+        .into_iter()
+        .map(|(candidate, value)| (value, title_similarity(title, candidate)))
+        .filter(|(_, score)| *score >= MIN_SUGGESTED_MATCH)
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn title_matching_ignores_volume_notes_and_width() {
+        let score = title_similarity("本好きの下剋上 (ライトノベル)", "本好きの下剋上");
+        assert!(score >= CONFIDENT_MATCH);
+        assert!(title_similarity("ＳＡＯ", "sao") >= CONFIDENT_MATCH);
+        assert!(title_similarity("本好きの下剋上", "無職転生") < 0.5);
+    }
+
+    #[test]
+    fn normalizing_keeps_quoted_titles_and_bracket_only_names() {
+        assert_eq!(
+            normalize_title("「ダンジョン飯」【完全版】"),
+            "ダンジョン飯"
+        );
+        assert_eq!(
+            normalize_title("Frieren: Beyond Journey's End"),
+            "frierenbeyondjourneysend"
+        );
+        assert_eq!(normalize_title("【特典】"), "特典");
+        assert_eq!(title_similarity("", "title"), 0.0);
+    }
+
+    #[test]
+    fn best_match_picks_the_highest_score_above_the_floor() {
+        let library = [
+            ("無職転生", 1),
+            ("本好きの下剋上", 2),
+            ("本好きの下剋上 短編集", 3),
+        ];
+        let (id, score) = best_match("本好きの下剋上 第一部", library).expect("match");
+        assert_eq!(id, 2);
+        assert!(score < 1.0);
+
+        assert!(best_match("ダンジョン飯", library).is_none());
+    }
+}
@@ -345,6 +345,10 @@ pub struct LNMetadata {
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     #[serde(alias = "languageSettings")]
     pub language_settings: HashMap<String, LNReaderSettings>,
+
+    // User rating (0.0 - 5.0)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<f64>,
 }
 
 // ============================================================================
@@ -362,14 +366,99 @@ pub struct LnCategory {
     pub last_modified: i64,
 }
 
-/// Category metadata (sort settings)
+/// Per-category display settings. Fields added after the first release default so older
+/// sidecars and sync payloads still load.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LnCategoryMetadata {
+    /// Sort used when listing the category without an explicit one; same keys as
+    /// `LnLibraryPreferences::sort_by`
+    #[serde(alias = "sortBy")]
+    pub sort_by: String,
+    #[serde(alias = "sortDesc")]
+    pub sort_desc: bool,
+    /// Artwork for the category: an image URL, or the id of a book whose cover is used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cover: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Hidden categories are left out of the category list unless asked for
+    #[serde(default)]
+    pub hidden: bool,
+    #[serde(default)]
+    #[serde(alias = "lastModified")]
+    pub last_modified: i64,
+}
+
+/// Library display preferences - applied when the client doesn't ask for a specific sort
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LnLibraryPreferences {
+    /// One of "addedAt", "lastRead", "rating" or "title"
+    #[serde(default = "LnLibraryPreferences::default_sort_by")]
     #[serde(alias = "sortBy")]
     pub sort_by: String,
+    #[serde(default = "LnLibraryPreferences::default_sort_desc")]
     #[serde(alias = "sortDesc")]
     pub sort_desc: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub filter: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "gridDensity")]
+    pub grid_density: Option<String>,
+    #[serde(default)]
+    #[serde(alias = "lastModified")]
+    pub last_modified: i64,
+}
+
+impl LnLibraryPreferences {
+    fn default_sort_by() -> String {
+        "addedAt".to_string()
+    }
+
+    fn default_sort_desc() -> bool {
+        true
+    }
+}
+
+impl Default for LnLibraryPreferences {
+    fn default() -> Self {
+        Self {
+            sort_by: Self::default_sort_by(),
+            sort_desc: Self::default_sort_desc(),
+            filter: None,
+            grid_density: None,
+            last_modified: 0,
+        }
+    }
+}
+
+/// Reader typography for a single book or the global default. Only `customCss` is
+/// interpreted by the server; every other key is passed through for the reader.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LnReaderSettings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "customCss")]
+    pub custom_css: Option<String>,
+    #[serde(default)]
+    #[serde(alias = "lastModified")]
+    pub last_modified: i64,
+    #[serde(flatten)]
+    pub settings: serde_json::Map<String, serde_json::Value>,
+}
+
+impl LnReaderSettings {
+    /// Layers these (book) settings over `base` (the global default), key by key.
+    pub fn overlay(&self, base: &LnReaderSettings) -> LnReaderSettings {
+        let mut settings = base.settings.clone();
+        settings.extend(self.settings.clone());
+        LnReaderSettings {
+            custom_css: self.custom_css.clone().or_else(|| base.custom_css.clone()),
+            last_modified: self.last_modified.max(base.last_modified),
+            settings,
+        }
+    }
 }
 
 // ============================================================================
@@ -468,6 +557,21 @@ pub struct SyncPayload {
     #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     #[serde(alias = "lnCategoryMetadata")]
     pub ln_category_metadata: HashMap<String, LnCategoryMetadata>,
+
+    /// LN library display preferences
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "lnLibraryPreferences")]
+    pub ln_library_preferences: Option<LnLibraryPreferences>,
+
+    /// Per-book reader settings (bookId → settings)
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    #[serde(alias = "lnReaderSettings")]
+    pub ln_reader_settings: HashMap<String, LnReaderSettings>,
+
+    /// Reader settings for books without their own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(alias = "lnDefaultReaderSettings")]
+    pub ln_default_reader_settings: Option<LnReaderSettings>,
 }
 
 impl SyncPayload {
@@ -516,6 +620,14 @@ pub struct MergeResponse {
     /// Any conflicts that occurred (informational)
     #[serde(default)]
     pub conflicts: Vec<ConflictInfo>,
+
+    /// What the sync changed in the local library, for a "what changed" view
+    #[serde(default)]
+    pub changes: Vec<SyncChange>,
+
+    /// History entry the changes are kept under, for `GET /history/{id}/changes`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub history_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -528,6 +640,66 @@ pub struct ConflictInfo {
     pub resolution: String,
 }
 
+/// One change a merge made to the local library
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncChange {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub book_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Device the incoming entry was last written by
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_device: Option<String>,
+    #[serde(flatten)]
+    pub kind: SyncChangeKind,
+    /// One-line description for display
+    #[serde(default)]
+    pub summary: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum SyncChangeKind {
+    /// A book that only the remote had
+    BookAdded,
+    /// Overall progress moved, in percent; `from_percent` is unset for books without
+    /// local progress
+    Progress {
+        from_percent: Option<f64>,
+        to_percent: f64,
+    },
+    /// Book metadata fields the remote overwrote
+    MetadataUpdated {
+        fields: Vec<String>,
+    },
+    /// Category ids added to or removed from a book
+    BookCategories {
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    CategoryAdded {
+        id: String,
+        name: String,
+    },
+    CategoryRenamed {
+        id: String,
+        from: String,
+        to: String,
+    },
+    /// Both sides changed the same entry; see [`ConflictInfo`]
+    Conflict {
+        field: String,
+        local_value: String,
+        remote_value: String,
+        resolution: String,
+    },
+}
+
 // ============================================================================
 // Sync Configuration
 // ============================================================================
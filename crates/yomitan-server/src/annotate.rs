@@ -0,0 +1,265 @@
+//! Per-character word status for whole chapters, so the reader can tint text by how well
+//! the user knows each word without a lookup per tap.
+//!
+//! A chapter is segmented greedily: at each position the longest dictionary match wins and
+//! the cursor jumps past it. The result is run-length encoded and cached by content hash,
+//! language, enabled dictionaries and [`status_version`], so re-opening a chapter is free
+//! until the user marks a word or changes dictionaries.
+
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    deinflector::Language as DeinflectLanguage,
+    lookup::{DEFAULT_LOOKUP_WINDOW, LookupService, term_parts},
+    state::AppState,
+};
+
+const STATUS_VERSION_KEY: &str = "word_status_version";
+/// Annotated chapters kept in the cache; the oldest are dropped first.
+pub const MAX_CACHED_ANNOTATIONS: usize = 200;
+
+/// How well the user knows a word. Words never marked are `Unknown`; characters not
+/// covered by any dictionary entry are `NoEntry`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum WordStatus {
+    NoEntry,
+    Unknown,
+    Learning,
+    Known,
+}
+
+impl WordStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WordStatus::NoEntry => "noEntry",
+            WordStatus::Unknown => "unknown",
+            WordStatus::Learning => "learning",
+            WordStatus::Known => "known",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "noEntry" => Some(WordStatus::NoEntry),
+            "unknown" => Some(WordStatus::Unknown),
+            "learning" => Some(WordStatus::Learning),
+            "known" => Some(WordStatus::Known),
+            _ => None,
+        }
+    }
+}
+
+/// A chapter's statuses as `(status, character count)` runs covering the whole text.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Annotation {
+    pub hash: String,
+    pub status_version: i64,
+    pub runs: Vec<(WordStatus, usize)>,
+}
+
+/// Bumped on every status change; part of the annotation cache key.
+pub fn status_version(state: &AppState) -> i64 {
+    state
+        .pool
+        .get()
+        .ok()
+        .and_then(|conn| status_version_from_conn(&conn))
+        .unwrap_or_default()
+}
+
+fn status_version_from_conn(conn: &rusqlite::Connection) -> Option<i64> {
+    conn.query_row(
+        "SELECT value FROM metadata WHERE key = ?",
+        [STATUS_VERSION_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| value.parse().ok())
+}
+
+/// Marks `headword` with `status`. `Unknown` (and `NoEntry`) clear the mark.
+pub fn set_word_status(state: &AppState, headword: &str, status: WordStatus) -> anyhow::Result<()> {
+    let mut conn = state.pool.get()?;
+    let tx = conn.transaction()?;
+    match status {
+        WordStatus::Known | WordStatus::Learning => tx.execute(
+            "INSERT OR REPLACE INTO word_status (headword, status) VALUES (?, ?)",
+            [headword, status.as_str()],
+        )?,
+        WordStatus::Unknown | WordStatus::NoEntry => {
+            tx.execute("DELETE FROM word_status WHERE headword = ?", [headword])?
+        }
+    };
+    let version = status_version_from_conn(&tx).unwrap_or_default() + 1;
+    tx.execute(
+        "INSERT OR REPLACE INTO metadata (key, value) VALUES (?, ?)",
+        [STATUS_VERSION_KEY, &version.to_string()],
+    )?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Stored statuses for the given headwords; unmarked headwords are absent.
+fn statuses_for(
+    conn: &rusqlite::Connection,
+    headwords: &[String],
+) -> anyhow::Result<HashMap<String, WordStatus>> {
+    let mut stmt = conn.prepare("SELECT status FROM word_status WHERE headword = ?")?;
+    let mut statuses = HashMap::new();
+    for headword in headwords {
+        if statuses.contains_key(headword) {
+            continue;
+        }
+        let status: Option<String> = stmt.query_row([headword], |row| row.get(0)).optional()?;
+        if let Some(status) = status.as_deref().and_then(WordStatus::parse) {
+            statuses.insert(headword.clone(), status);
+        }
+    }
+    Ok(statuses)
+}
+
+/// Annotates `text`, serving the cached runs when nothing relevant changed since the last
+/// time this exact text was annotated. Returns the annotation and whether it was cached.
+pub fn annotate_chapter(
+    state: &AppState,
+    lookup: &LookupService,
+    text: &str,
+    language: DeinflectLanguage,
+) -> anyhow::Result<(Annotation, bool)> {
+    let hash = content_hash(text);
+    let conn = state.pool.get()?;
+    let version = status_version_from_conn(&conn).unwrap_or_default();
+    let cache_key = format!(
+        "{hash}:{language:?}:{}:{version}",
+        enabled_dictionaries(state)
+    );
+
+    let cached: Option<String> = conn
+        .query_row(
+            "SELECT runs FROM annotation_cache WHERE cache_key = ?",
+            [&cache_key],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(runs) = cached.and_then(|runs| serde_json::from_str(&runs).ok()) {
+        let annotation = Annotation {
+            hash,
+            status_version: version,
+            runs,
+        };
+        return Ok((annotation, true));
+    }
+
+    let runs = segment(state, lookup, &conn, text, language)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO annotation_cache (cache_key, runs, created_at) VALUES (?, ?, ?)",
+        rusqlite::params![cache_key, serde_json::to_string(&runs)?, now_secs()],
+    )?;
+    conn.execute(
+        "DELETE FROM annotation_cache WHERE cache_key NOT IN
+         (SELECT cache_key FROM annotation_cache ORDER BY created_at DESC LIMIT ?)",
+        [MAX_CACHED_ANNOTATIONS as i64],
+    )?;
+    let annotation = Annotation {
+        hash,
+        status_version: version,
+        runs,
+    };
+    Ok((annotation, false))
+}
+
+fn segment(
+    state: &AppState,
+    lookup: &LookupService,
+    conn: &rusqlite::Connection,
+    text: &str,
+    language: DeinflectLanguage,
+) -> anyhow::Result<Vec<(WordStatus, usize)>> {
+    let mut runs: Vec<(WordStatus, usize)> = Vec::new();
+    let mut push = |status: WordStatus, len: usize| match runs.last_mut() {
+        Some((last, count)) if *last == status => *count += len,
+        _ => runs.push((status, len)),
+    };
+
+    let mut offset = 0;
+    while let Some(c) = text[offset..].chars().next() {
+        if is_separator(c) {
+            push(WordStatus::NoEntry, 1);
+            offset += c.len_utf8();
+            continue;
+        }
+
+        let results = lookup.search_window(state, text, offset, DEFAULT_LOOKUP_WINDOW, language);
+        let longest = results
+            .iter()
+            .map(|(entry, _)| entry.span_chars.end - entry.span_chars.start)
+            .max()
+            .unwrap_or(0);
+        if longest == 0 {
+            push(WordStatus::NoEntry, 1);
+            offset += c.len_utf8();
+            continue;
+        }
+
+        let mut headwords = Vec::new();
+        let mut end = offset;
+        for (entry, _) in &results {
+            if entry.span_chars.end - entry.span_chars.start == longest {
+                headwords.push(term_parts(&entry.term).0);
+                end = entry.span_bytes.end as usize;
+            }
+        }
+        let status = statuses_for(conn, &headwords)?
+            .into_values()
+            .max()
+            .unwrap_or(WordStatus::Unknown);
+        push(status, longest as usize);
+        offset = end.max(offset + c.len_utf8());
+    }
+    Ok(runs)
+}
+
+/// Whitespace and punctuation never start a word, so they skip the lookup.
+fn is_separator(c: char) -> bool {
+    c.is_whitespace()
+        || c.is_ascii_punctuation()
+        || ('\u{3000}'..='\u{303F}').contains(&c)
+        || ('\u{FF01}'..='\u{FF0F}').contains(&c)
+}
+
+fn content_hash(text: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(text.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// Enabled dictionary ids, so enabling or importing a dictionary invalidates the cache.
+fn enabled_dictionaries(state: &AppState) -> String {
+    let dicts = state.dictionaries.read().expect("lock");
+    let mut ids: Vec<i64> = dicts
+        .values()
+        .filter(|d| d.enabled)
+        .map(|d| d.id.0)
+        .collect();
+    ids.sort_unstable();
+    ids.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,396 @@
+//! Permalinks to a single dictionary entry, for sharing a definition outside the reader.
+//!
+//! Links carry the dictionary title next to its numeric id. Re-importing a dictionary gives
+//! it a new id, so when the id no longer exists the title is used to find it again.
+
+use std::fmt::Write as _;
+
+use serde_json::{Value, json};
+use wordbase_api::{DictionaryId, Record};
+
+use crate::{
+    lookup::LookupService,
+    state::{AppState, DictionaryData, StoredRecord},
+};
+
+/// Headwords suggested when an entry is not found.
+pub const MAX_SUGGESTIONS: usize = 5;
+/// Candidate headwords scored for suggestions; enough to cover one leading character.
+const SUGGESTION_CANDIDATES: usize = 500;
+/// Structured content tags rendered as themselves; anything else becomes a `span`.
+const HTML_TAGS: &[&str] = &[
+    "br", "ruby", "rt", "rp", "table", "thead", "tbody", "tfoot", "tr", "td", "th", "span", "div",
+    "ol", "ul", "li", "details", "summary",
+];
+
+/// Finds the dictionary a link points at: the numeric id when it still exists, otherwise
+/// the dictionary called `title`. A non-numeric id is treated as a title.
+pub fn resolve_dictionary(
+    state: &AppState,
+    id: &str,
+    title: Option<&str>,
+) -> Option<DictionaryData> {
+    let dicts = state.dictionaries.read().expect("lock");
+    if let Ok(id) = id.parse::<i64>()
+        && let Some(dict) = dicts.get(&DictionaryId(id))
+    {
+        return Some(dict.clone());
+    }
+    let title = title.unwrap_or(id);
+    dicts.values().find(|d| d.name == title).cloned()
+}
+
+/// The records one dictionary holds for `headword`, narrowed to `reading` when given.
+pub fn entry_records(
+    state: &AppState,
+    dictionary_id: DictionaryId,
+    headword: &str,
+    reading: Option<&str>,
+) -> anyhow::Result<Vec<StoredRecord>> {
+    let conn = state.pool.get()?;
+    let mut stmt = conn.prepare("SELECT json FROM terms WHERE term = ? AND dictionary_id = ?")?;
+    let rows = stmt.query_map(rusqlite::params![headword, dictionary_id.0], |row| {
+        row.get::<_, Vec<u8>>(0)
+    })?;
+
+    let mut decoder = snap::raw::Decoder::new();
+    let mut records = Vec::new();
+    for compressed in rows.flatten() {
+        let Some(mut stored) = decoder
+            .decompress_vec(&compressed)
+            .ok()
+            .and_then(|decompressed| LookupService::decode_stored_record_payload(&decompressed))
+        else {
+            continue;
+        };
+        // Readings equal to the headword are not stored.
+        if let Some(reading) = reading
+            && stored.reading.as_deref().unwrap_or(headword) != reading
+        {
+            continue;
+        }
+        stored.dictionary_id = dictionary_id;
+        stored.headword.get_or_insert_with(|| headword.to_string());
+        records.push(stored);
+    }
+    Ok(records)
+}
+
+/// Headwords in the dictionary closest to `headword` by edit distance, for a not-found
+/// page. Candidates share its first character or are spelled with it as their reading.
+pub fn near_headwords(
+    state: &AppState,
+    dictionary_id: DictionaryId,
+    headword: &str,
+) -> anyhow::Result<Vec<String>> {
+    let Some(first) = headword.chars().next() else {
+        return Ok(Vec::new());
+    };
+    let conn = state.pool.get()?;
+    let mut stmt = conn.prepare(
+        "SELECT DISTINCT term FROM terms
+         WHERE dictionary_id = ?1 AND (substr(term, 1, 1) = ?2 OR reading = ?3)
+         LIMIT ?4",
+    )?;
+    let candidates: Vec<String> = stmt
+        .query_map(
+            rusqlite::params![
+                dictionary_id.0,
+                first.to_string(),
+                headword,
+                SUGGESTION_CANDIDATES as i64
+            ],
+            |row| row.get(0),
+        )?
+        .flatten()
+        .filter(|term: &String| term != headword)
+        .collect();
+
+    let mut scored: Vec<(usize, String)> = candidates
+        .into_iter()
+        .map(|term| (edit_distance(headword, &term), term))
+        .collect();
+    scored.sort_by(|(a, a_term), (b, b_term)| {
+        a.cmp(b)
+            .then_with(|| a_term.chars().count().cmp(&b_term.chars().count()))
+            .then_with(|| a_term.cmp(b_term))
+    });
+    Ok(scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, term)| term)
+        .collect())
+}
+
+/// The stable link to an entry, relative to the dictionary API root. The title rides along
+/// so the link survives a re-import.
+pub fn permalink(dictionary: &DictionaryData, headword: &str, reading: Option<&str>) -> String {
+    let mut url = format!(
+        "/entry/{}/{}?title={}",
+        dictionary.id.0,
+        urlencoding::encode(headword),
+        urlencoding::encode(&dictionary.name)
+    );
+    if let Some(reading) = reading {
+        let _ = write!(url, "&reading={}", urlencoding::encode(reading));
+    }
+    url
+}
+
+/// A standalone page showing the entry with the dictionary's own stylesheet.
+pub fn render_entry_html(
+    dictionary: &DictionaryData,
+    headword: &str,
+    records: &[StoredRecord],
+) -> String {
+    let mut body = String::new();
+    for stored in records {
+        let reading = stored.reading.as_deref().unwrap_or(headword);
+        let _ = write!(
+            body,
+            "<article class=\"entry\"><h1><ruby>{}<rt>{}</rt></ruby></h1>",
+            escape_html(headword),
+            escape_html(reading)
+        );
+        match &stored.record {
+            Record::YomitanGlossary(gloss) => {
+                if !gloss.tags.is_empty() {
+                    body.push_str("<p class=\"tags\">");
+                    for tag in &gloss.tags {
+                        let _ = write!(
+                            body,
+                            "<span class=\"tag\">{}</span>",
+                            escape_html(&tag.name)
+                        );
+                    }
+                    body.push_str("</p>");
+                }
+                body.push_str("<div class=\"glossary\">");
+                render_content(&json!(gloss.content), &mut body);
+                body.push_str("</div>");
+            }
+            other => {
+                let _ = write!(
+                    body,
+                    "<pre>{}</pre>",
+                    escape_html(&serde_json::to_string_pretty(other).unwrap_or_default())
+                );
+            }
+        }
+        body.push_str("</article>");
+    }
+    page(
+        &format!("{headword} - {}", dictionary.name),
+        dictionary,
+        &body,
+    )
+}
+
+/// A not-found page linking to the suggested headwords.
+pub fn render_not_found_html(
+    dictionary: &DictionaryData,
+    headword: &str,
+    suggestions: &[String],
+) -> String {
+    let mut body = format!(
+        "<p>No entry for <b>{}</b> in {}.</p>",
+        escape_html(headword),
+        escape_html(&dictionary.name)
+    );
+    if !suggestions.is_empty() {
+        body.push_str("<p>Did you mean:</p><ul>");
+        for suggestion in suggestions {
+            let _ = write!(
+                body,
+                "<li><a href=\"{}\">{}</a></li>",
+                // Relative to this page, so the link works wherever the API is mounted.
+                escape_html(&format!(
+                    "{}?title={}",
+                    urlencoding::encode(suggestion),
+                    urlencoding::encode(&dictionary.name)
+                )),
+                escape_html(suggestion)
+            );
+        }
+        body.push_str("</ul>");
+    }
+    page(&format!("{headword} - not found"), dictionary, &body)
+}
+
+fn page(title: &str, dictionary: &DictionaryData, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\">\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\
+         <title>{}</title><style>{}</style></head>\
+         <body><div class=\"yomitan-glossary\" data-dictionary=\"{}\">{}</div></body></html>",
+        escape_html(title),
+        // A stylesheet cannot contain markup, so only a closing tag needs defusing.
+        dictionary
+            .styles
+            .as_deref()
+            .unwrap_or_default()
+            .replace("</", "<\\/"),
+        escape_html(&dictionary.name),
+        body
+    )
+}
+
+/// Renders Yomitan structured content. Elements keep their `data` attributes as
+/// `data-sc-*` so dictionary stylesheets match them; images and links are dropped.
+fn render_content(content: &Value, out: &mut String) {
+    match content {
+        Value::String(text) => out.push_str(&escape_html(text)),
+        Value::Array(items) => items.iter().for_each(|item| render_content(item, out)),
+        Value::Object(object) => {
+            let Some(tag) = object.get("tag").and_then(Value::as_str) else {
+                if let Some(inner) = object.get("content") {
+                    render_content(inner, out);
+                } else if let Some(text) = object.get("text").and_then(Value::as_str) {
+                    out.push_str(&escape_html(text));
+                }
+                return;
+            };
+            if tag == "img" {
+                return;
+            }
+            if tag == "br" {
+                out.push_str("<br>");
+                return;
+            }
+            let element = if HTML_TAGS.contains(&tag) {
+                tag
+            } else {
+                "span"
+            };
+            let _ = write!(out, "<{element} class=\"gloss-sc-{}\"", escape_html(tag));
+            if let Some(Value::Object(data)) = object.get("data") {
+                for (key, value) in data {
+                    let value = value
+                        .as_str()
+                        .map_or_else(|| value.to_string(), str::to_string);
+                    let _ = write!(
+                        out,
+                        " data-sc-{}=\"{}\"",
+                        escape_html(key),
+                        escape_html(&value)
+                    );
+                }
+            }
+            out.push('>');
+            if let Some(inner) = object.get("content") {
+                render_content(inner, out);
+            }
+            let _ = write!(out, "</{element}>");
+        }
+        Value::Number(number) => out.push_str(&number.to_string()),
+        Value::Null | Value::Bool(_) => {}
+    }
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Levenshtein distance over characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = Vec::with_capacity(b.len() + 1);
+        current.push(i + 1);
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::Write,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::import::import_zip;
+
+    fn test_data_dir() -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time")
+            .as_nanos();
+        std::env::temp_dir().join(format!(
+            "manatan-yomitan-entry-test-{}-{nanos}",
+            std::process::id()
+        ))
+    }
+
+    fn build_zip(index_json: &str, entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let cursor = std::io::Cursor::new(&mut bytes);
+            let mut zip = ZipWriter::new(cursor);
+            let opts = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+            zip.start_file("index.json", opts).expect("start index");
+            zip.write_all(index_json.as_bytes()).expect("write index");
+            for (name, contents) in entries {
+                zip.start_file(name, opts).expect("start file");
+                zip.write_all(contents.as_bytes()).expect("write file");
+            }
+            zip.finish().expect("finish zip");
+        }
+        bytes
+    }
+
+    #[test]
+    fn entry_permalinks_resolve_by_title_and_suggest_near_misses() {
+        let dir = test_data_dir();
+        let state = AppState::new(dir.clone());
+        let zip = build_zip(
+            r#"{"format":3,"title":"Defs","revision":"1"}"#,
+            &[(
+                "term_bank_1.json",
+                r#"[["生","せい","n",null,0,["life"],0,""],["生","なま","n",null,0,["raw <fish>"],0,""],["生きる","いきる","v1",null,0,["to live"],0,""]]"#,
+            )],
+        );
+        import_zip(&state, &zip).expect("import should succeed");
+
+        let dictionary =
+            resolve_dictionary(&state, "999", Some("Defs")).expect("resolved by title");
+        assert_eq!(dictionary.name, "Defs");
+        assert!(resolve_dictionary(&state, "999", Some("Other")).is_none());
+
+        let all = entry_records(&state, dictionary.id, "生", None).expect("records");
+        assert_eq!(all.len(), 2);
+        let raw = entry_records(&state, dictionary.id, "生", Some("なま")).expect("records");
+        assert_eq!(raw.len(), 1);
+
+        let html = render_entry_html(&dictionary, "生", &raw);
+        assert!(html.contains("raw &lt;fish&gt;"));
+        assert!(html.contains("<rt>なま</rt>"));
+
+        let suggestions = near_headwords(&state, dictionary.id, "生き").expect("suggestions");
+        assert_eq!(suggestions, vec!["生".to_string(), "生きる".to_string()]);
+
+        drop(state);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
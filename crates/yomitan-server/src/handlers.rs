@@ -4,7 +4,7 @@ use axum::{
     Json,
     extract::{Multipart, Path, Query, State},
     http::{HeaderMap, StatusCode},
-    response::IntoResponse,
+    response::{Html, IntoResponse, Response},
 };
 use regex::Regex;
 use reqwest::Client;
@@ -15,7 +15,14 @@ use sha2::{Digest, Sha256};
 use tracing::{error, info, warn};
 use wordbase_api::{DictionaryId, Record, Term, dict::yomitan::GlossaryTag};
 
-use crate::{ServerState, import, lookup::KanjiEntry, state::AppState};
+use crate::{
+    ServerState,
+    annotate::{self, WordStatus},
+    entry, import, kanji,
+    lookup::{self, DEFAULT_LOOKUP_WINDOW, FrequencyStrategy, KanjiEntry},
+    personalization::{self, Personalization, PersonalizationReport},
+    state::AppState,
+};
 
 #[cfg(target_os = "ios")]
 unsafe extern "C" {
@@ -30,6 +37,25 @@ pub struct LookupParams {
     // Optional toggle for grouping results (defaults to true in handler)
     pub group: Option<bool>,
     pub language: Option<DictionaryLanguage>,
+    /// Overrides the stored personalization setting for this lookup.
+    pub personalization: Option<Personalization>,
+}
+
+#[derive(Deserialize)]
+pub struct SearchRequest {
+    pub text: String,
+    /// Byte offset of the cursor within `text`.
+    #[serde(default, alias = "cursorOffset")]
+    pub cursor_offset: usize,
+    #[serde(default, alias = "contextBefore")]
+    pub context_before: String,
+    #[serde(default, alias = "contextAfter")]
+    pub context_after: String,
+    /// Characters after the cursor to consider, capped at `MAX_LOOKUP_WINDOW`.
+    pub window: Option<usize>,
+    pub group: Option<bool>,
+    pub language: Option<DictionaryLanguage>,
+    pub personalization: Option<Personalization>,
 }
 
 #[derive(Deserialize)]
@@ -140,6 +166,8 @@ pub struct ApiGroupedResult {
     pub ipa: Vec<ApiIpa>,
     pub forms: Vec<ApiForm>,
     pub term_tags: Vec<GlossaryTag>,
+    /// Character offset of the match within the looked-up text.
+    pub span_start: usize,
     pub match_len: usize,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub styles: Option<std::collections::HashMap<String, String>>,
@@ -150,6 +178,7 @@ pub struct ApiGroupedResult {
 pub struct ApiLookupResponse {
     pub terms: Vec<ApiGroupedResult>,
     pub kanji: Vec<KanjiEntry>,
+    pub personalization: PersonalizationReport,
 }
 
 #[derive(Deserialize)]
@@ -1195,6 +1224,7 @@ fn clear_dictionary_state(app_state: &AppState) {
             let _ = tx.execute("DELETE FROM terms", []);
             let _ = tx.execute("DELETE FROM dictionaries", []);
             let _ = tx.execute("DELETE FROM metadata", []);
+            let _ = tx.execute("DELETE FROM annotation_cache", []);
             let _ = tx.commit();
         }
         info!("🧹 [Yomitan] Vacuuming after reset...");
@@ -1219,11 +1249,17 @@ pub async fn install_language_internal(
 ) -> Result<String, String> {
     let dict_bytes = download_dictionary_bytes(language).await?;
     let app_state_for_task = app_state.clone();
-    let res =
-        tokio::task::spawn_blocking(move || import::import_zip(&app_state_for_task, &dict_bytes))
-            .await
-            .map_err(|e| e.to_string())?;
-    res.map_err(|e| e.to_string())
+    let res = app_state
+        .imports
+        .run_blocking(
+            "yomitan",
+            format!("Install {language:?} dictionaries"),
+            move || import::import_zip(&app_state_for_task, &dict_bytes),
+        )
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+    res.map(|report| report.message).map_err(|e| e.to_string())
 }
 
 pub async fn manage_dictionaries_handler(
@@ -1463,19 +1499,60 @@ pub async fn reset_db_handler(
     }
 }
 
-#[allow(clippy::useless_let_if_seq)]
 pub async fn lookup_handler(
     State(state): State<ServerState>,
     Query(params): Query<LookupParams>,
 ) -> Result<Json<ApiLookupResponse>, (StatusCode, Json<Value>)> {
-    let cursor_idx = params.index.unwrap_or(0);
     let language = params
         .language
         .or_else(|| load_preferred_language(&state.app))
         .unwrap_or(DictionaryLanguage::Japanese);
-    // determine if we should group results or return raw dictionary entries
-    let should_group = params.group.unwrap_or(true);
+    lookup_response(
+        &state,
+        &params.text,
+        params.index.unwrap_or(0),
+        DEFAULT_LOOKUP_WINDOW,
+        language,
+        // determine if we should group results or return raw dictionary entries
+        params.group.unwrap_or(true),
+        params.personalization,
+    )
+}
+
+/// Lookup for readers that know the paragraph around a selection. The selection is
+/// stitched back into its paragraph so deinflection can see the following characters,
+/// and `spanStart`/`matchLen` in the response are character offsets into that paragraph.
+pub async fn search_handler(
+    State(state): State<ServerState>,
+    Json(req): Json<SearchRequest>,
+) -> Result<Json<ApiLookupResponse>, (StatusCode, Json<Value>)> {
+    let language = req
+        .language
+        .or_else(|| load_preferred_language(&state.app))
+        .unwrap_or(DictionaryLanguage::Japanese);
+    let cursor_idx = req.context_before.len() + req.cursor_offset.min(req.text.len());
+    let paragraph = format!("{}{}{}", req.context_before, req.text, req.context_after);
+    lookup_response(
+        &state,
+        &paragraph,
+        cursor_idx,
+        req.window.unwrap_or(DEFAULT_LOOKUP_WINDOW),
+        language,
+        req.group.unwrap_or(true),
+        req.personalization,
+    )
+}
 
+#[allow(clippy::useless_let_if_seq)]
+fn lookup_response(
+    state: &ServerState,
+    text: &str,
+    cursor_idx: usize,
+    window: usize,
+    language: DictionaryLanguage,
+    should_group: bool,
+    personalization: Option<Personalization>,
+) -> Result<Json<ApiLookupResponse>, (StatusCode, Json<Value>)> {
     if state.app.is_loading() {
         return Err((
             StatusCode::SERVICE_UNAVAILABLE,
@@ -1483,11 +1560,13 @@ pub async fn lookup_handler(
         ));
     }
 
-    let raw_results = state.lookup.search(
+    let (raw_results, personalization) = state.lookup.search_window_personalized(
         &state.app,
-        &params.text,
+        text,
         cursor_idx,
+        window,
         language.deinflect_language(),
+        personalization,
     );
 
     let dict_meta: std::collections::HashMap<DictionaryId, (String, Option<String>)> = {
@@ -1508,6 +1587,7 @@ pub async fn lookup_handler(
         pitch_accents: Vec<ApiPitchAccent>,
         ipa: Vec<ApiIpa>,
         forms_set: Vec<(String, String)>,
+        span_start: usize,
         match_len: usize,
         dict_ids: Vec<DictionaryId>,
     }
@@ -1531,7 +1611,8 @@ pub async fn lookup_handler(
             continue;
         }
 
-        let match_len = entry.0.span_chars.end as usize;
+        let span_start = entry.0.span_chars.start as usize;
+        let match_len = (entry.0.span_chars.end - entry.0.span_chars.start) as usize;
 
         let mut is_freq = false;
         let mut is_pitch = false;
@@ -1739,6 +1820,7 @@ pub async fn lookup_handler(
                         ipa: vec![],
                         term_tags: entry.1.unwrap_or_default(),
                         forms_set: vec![(headword.clone(), reading.clone())],
+                        span_start,
                         match_len,
                         dict_ids: vec![entry.0.source],
                     });
@@ -1758,6 +1840,7 @@ pub async fn lookup_handler(
                         headword: headword.clone(),
                         reading: reading.clone(),
                     }],
+                    span_start,
                     match_len,
                     styles: Some(
                         std::iter::once((
@@ -1776,9 +1859,7 @@ pub async fn lookup_handler(
     }
 
     // Get kanji results separately
-    let kanji_results = state
-        .lookup
-        .search_kanji(&state.app, &params.text, cursor_idx);
+    let kanji_results = state.lookup.search_kanji(&state.app, text, cursor_idx);
 
     if should_group {
         let final_results: Vec<ApiGroupedResult> = map
@@ -1814,6 +1895,7 @@ pub async fn lookup_handler(
                             reading: r,
                         })
                         .collect(),
+                    span_start: agg.span_start,
                     match_len: agg.match_len,
                     styles: Some(
                         agg.dict_ids
@@ -1832,6 +1914,7 @@ pub async fn lookup_handler(
         Ok(Json(ApiLookupResponse {
             terms: final_results,
             kanji: kanji_results,
+            personalization,
         }))
     } else {
         // Iterate through results and attach frequencies to ALL of them.
@@ -1850,6 +1933,7 @@ pub async fn lookup_handler(
         Ok(Json(ApiLookupResponse {
             terms: flat_results,
             kanji: kanji_results,
+            personalization,
         }))
     }
 }
@@ -1897,11 +1981,351 @@ pub async fn list_dictionaries_handler(State(state): State<ServerState>) -> Json
     )
 }
 
+#[derive(Deserialize)]
+pub struct EntryParams {
+    pub reading: Option<String>,
+    /// Dictionary title, used when the id in the path no longer exists.
+    pub title: Option<String>,
+    /// `html` or `json`; defaults to HTML for browsers and JSON otherwise.
+    pub format: Option<String>,
+}
+
+/// One dictionary entry by dictionary and headword, as JSON or as a standalone page for
+/// sharing. Unknown headwords answer 404 with the closest headwords as suggestions.
+pub async fn entry_handler(
+    State(state): State<ServerState>,
+    Path((dictionary_id, headword)): Path<(String, String)>,
+    Query(params): Query<EntryParams>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<Value>)> {
+    let as_html = match params.format.as_deref() {
+        Some(format) => format.eq_ignore_ascii_case("html"),
+        None => headers
+            .get(axum::http::header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .is_some_and(|accept| accept.contains("text/html")),
+    };
+    let internal = |e: anyhow::Error| {
+        error!("❌ Failed to load dictionary entry: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "status": "error", "message": e.to_string() })),
+        )
+    };
+
+    let Some(dictionary) =
+        entry::resolve_dictionary(&state.app, &dictionary_id, params.title.as_deref())
+    else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "status": "error", "message": "Dictionary not found" })),
+        ));
+    };
+    let headword = headword.trim();
+    let reading = params.reading.as_deref().filter(|r| !r.is_empty());
+    let records =
+        entry::entry_records(&state.app, dictionary.id, headword, reading).map_err(internal)?;
+
+    if records.is_empty() {
+        let suggestions =
+            entry::near_headwords(&state.app, dictionary.id, headword).map_err(internal)?;
+        return Ok(if as_html {
+            (
+                StatusCode::NOT_FOUND,
+                Html(entry::render_not_found_html(
+                    &dictionary,
+                    headword,
+                    &suggestions,
+                )),
+            )
+                .into_response()
+        } else {
+            (
+                StatusCode::NOT_FOUND,
+                Json(json!({
+                    "status": "error",
+                    "message": "Entry not found",
+                    "suggestions": suggestions,
+                })),
+            )
+                .into_response()
+        });
+    }
+
+    Ok(if as_html {
+        Html(entry::render_entry_html(&dictionary, headword, &records)).into_response()
+    } else {
+        Json(json!({
+            "status": "ok",
+            "dictionary": { "id": dictionary.id.0, "name": dictionary.name },
+            "headword": headword,
+            "reading": reading,
+            "permalink": entry::permalink(&dictionary, headword, reading),
+            "styles": dictionary.styles,
+            "records": records,
+        }))
+        .into_response()
+    })
+}
+
+#[derive(Deserialize)]
+pub struct FrequencyReportParams {
+    pub term: String,
+}
+
+pub async fn frequency_report_handler(
+    State(state): State<ServerState>,
+    Query(params): Query<FrequencyReportParams>,
+) -> Json<Value> {
+    let term = params.term.trim();
+    let strategy = lookup::load_frequency_strategy(&state.app);
+    let entries = state.lookup.frequency_report(&state.app, term);
+
+    let ranks: Vec<(i64, i64)> = entries
+        .iter()
+        .filter_map(|e| e.rank.map(|rank| (e.priority, rank)))
+        .collect();
+    let min_rank = ranks.iter().map(|(_, rank)| *rank).min();
+    let max_rank = ranks.iter().map(|(_, rank)| *rank).max();
+    // Ratio between the rarest and most common rank; large values flag dictionaries that
+    // disagree about how common the term is.
+    let divergence = match (min_rank, max_rank) {
+        (Some(min), Some(max)) if min > 0 => Some(max as f64 / min as f64),
+        _ => None,
+    };
+
+    Json(json!({
+        "term": term,
+        "strategy": strategy,
+        "aggregatedRank": strategy.aggregate(&ranks),
+        "minRank": min_rank,
+        "maxRank": max_rank,
+        "divergence": divergence,
+        "entries": entries,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct FrequencyStrategyRequest {
+    pub strategy: FrequencyStrategy,
+}
+
+pub async fn get_frequency_strategy_handler(State(state): State<ServerState>) -> Json<Value> {
+    Json(json!({ "strategy": lookup::load_frequency_strategy(&state.app) }))
+}
+
+pub async fn set_frequency_strategy_handler(
+    State(state): State<ServerState>,
+    Json(req): Json<FrequencyStrategyRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    lookup::store_frequency_strategy(&state.app, req.strategy).map_err(|e| {
+        error!("❌ Failed to store frequency strategy: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "status": "error", "message": e.to_string() })),
+        )
+    })?;
+    info!("📊 Frequency strategy set to {}", req.strategy.as_str());
+    Ok(Json(json!({ "status": "ok", "strategy": req.strategy })))
+}
+
+#[derive(Deserialize)]
+pub struct PersonalizationRequest {
+    pub mode: Personalization,
+}
+
+pub async fn get_personalization_handler(State(state): State<ServerState>) -> Json<Value> {
+    Json(json!({ "mode": personalization::load_personalization(&state.app) }))
+}
+
+pub async fn set_personalization_handler(
+    State(state): State<ServerState>,
+    Json(req): Json<PersonalizationRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    personalization::store_personalization(&state.app, req.mode).map_err(|e| {
+        error!("❌ Failed to store personalization: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "status": "error", "message": e.to_string() })),
+        )
+    })?;
+    info!("📊 Lookup personalization set to {}", req.mode.as_str());
+    Ok(Json(json!({ "status": "ok", "mode": req.mode })))
+}
+
+#[derive(Deserialize)]
+pub struct LookupHistoryRequest {
+    pub headword: String,
+    #[serde(default)]
+    pub reading: String,
+}
+
+/// Records that the user opened a term, feeding the personal ordering boost.
+pub async fn record_lookup_handler(
+    State(state): State<ServerState>,
+    Json(req): Json<LookupHistoryRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let headword = req.headword.trim();
+    if headword.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "status": "error", "message": "headword is required" })),
+        ));
+    }
+    personalization::record_lookup(&state.app, headword, req.reading.trim()).map_err(|e| {
+        error!("❌ Failed to record lookup: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "status": "error", "message": e.to_string() })),
+        )
+    })?;
+    Ok(Json(json!({ "status": "ok" })))
+}
+
+#[derive(Deserialize)]
+pub struct WordStatusRequest {
+    pub headword: String,
+    pub status: WordStatus,
+}
+
+/// Marks a word as known or learning; `unknown` clears the mark.
+pub async fn set_word_status_handler(
+    State(state): State<ServerState>,
+    Json(req): Json<WordStatusRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let headword = req.headword.trim();
+    if headword.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "status": "error", "message": "headword is required" })),
+        ));
+    }
+    annotate::set_word_status(&state.app, headword, req.status).map_err(|e| {
+        error!("❌ Failed to store word status: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "status": "error", "message": e.to_string() })),
+        )
+    })?;
+    Ok(Json(json!({
+        "status": "ok",
+        "statusVersion": annotate::status_version(&state.app),
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct AnnotateChapterRequest {
+    pub text: String,
+    pub language: Option<DictionaryLanguage>,
+}
+
+/// Run-length encoded word status for a whole chapter of plain text. Runs are
+/// `[status, characterCount]` pairs in text order, and `cached` tells whether the
+/// chapter was served without segmenting it again.
+pub async fn annotate_chapter_handler(
+    State(state): State<ServerState>,
+    Json(req): Json<AnnotateChapterRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    if state.app.is_loading() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "error": "loading", "message": "Dictionaries are importing..." })),
+        ));
+    }
+    let language = req
+        .language
+        .or_else(|| load_preferred_language(&state.app))
+        .unwrap_or(DictionaryLanguage::Japanese);
+
+    let result = tokio::task::spawn_blocking(move || {
+        annotate::annotate_chapter(
+            &state.app,
+            &state.lookup,
+            &req.text,
+            language.deinflect_language(),
+        )
+    })
+    .await
+    .map_err(anyhow::Error::from)
+    .and_then(|result| result);
+
+    match result {
+        Ok((annotation, cached)) => Ok(Json(json!({
+            "hash": annotation.hash,
+            "statusVersion": annotation.status_version,
+            "cached": cached,
+            "runs": annotation.runs,
+        }))),
+        Err(e) => {
+            error!("❌ Failed to annotate chapter: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "status": "error", "message": e.to_string() })),
+            ))
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct KanjiBreakdownRequest {
+    pub word: String,
+    /// The reading on the card; looked up in the term dictionaries when absent.
+    pub reading: Option<String>,
+}
+
+/// Meanings and readings of each kanji in a word, with the reading each takes in it, as
+/// JSON and as an HTML list for a card field.
+pub async fn kanji_breakdown_handler(
+    State(state): State<ServerState>,
+    Json(req): Json<KanjiBreakdownRequest>,
+) -> Result<Json<kanji::KanjiBreakdown>, (StatusCode, Json<Value>)> {
+    if state.app.is_loading() {
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "error": "loading", "message": "Dictionaries are importing..." })),
+        ));
+    }
+    let word = req.word.trim().to_string();
+    if word.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "status": "error", "message": "word is required" })),
+        ));
+    }
+    let reading = req
+        .reading
+        .map(|reading| reading.trim().to_string())
+        .filter(|reading| !reading.is_empty());
+
+    tokio::task::spawn_blocking(move || {
+        kanji::kanji_breakdown(&state.app, &state.lookup, &word, reading.as_deref())
+    })
+    .await
+    .map(Json)
+    .map_err(|e| {
+        error!("❌ Failed to break down kanji: {}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "status": "error", "message": e.to_string() })),
+        )
+    })
+}
+
+#[derive(Deserialize)]
+pub struct ImportParams {
+    /// Parse the archive and report on it without importing anything.
+    #[serde(default)]
+    pub validate: bool,
+}
+
 pub async fn import_handler(
     State(state): State<ServerState>,
+    Query(params): Query<ImportParams>,
     mut multipart: Multipart,
-) -> Json<Value> {
-    wait_for_startup_guard(&state.app, "import").await;
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    if !params.validate {
+        wait_for_startup_guard(&state.app, "import").await;
+    }
 
     loop {
         match multipart.next_field().await {
@@ -1911,29 +2335,63 @@ pub async fn import_handler(
                         Ok(data) => {
                             info!("📥 [Import API] Received upload ({} bytes)", data.len());
                             let app_state = state.app.clone();
-                            let res = match tokio::task::spawn_blocking(move || {
-                                import::import_zip(&app_state, &data)
-                            })
-                            .await
-                            {
-                                Ok(result) => result,
-                                Err(err) => Err(anyhow::anyhow!(err.to_string())),
+                            if params.validate {
+                                let res = tokio::task::spawn_blocking(move || {
+                                    import::validate_zip(&app_state, &data)
+                                })
+                                .await
+                                .map_err(|err| anyhow::anyhow!(err.to_string()))
+                                .and_then(|result| result);
+                                return Ok(match res {
+                                    Ok(report) => Json(json!({
+                                        "status": if report.valid { "ok" } else { "error" },
+                                        "report": report,
+                                    })),
+                                    Err(e) => {
+                                        error!("❌ [Import API] Validation failed: {}", e);
+                                        Json(json!({ "status": "error", "message": e.to_string() }))
+                                    }
+                                });
+                            }
+                            let imports = app_state.imports.clone();
+                            let queued =
+                                imports.run_blocking("yomitan", "Import dictionary", move || {
+                                    import::import_zip(&app_state, &data)
+                                });
+                            let res = match queued.await {
+                                Ok(Ok(result)) => result,
+                                Ok(Err(err)) => Err(anyhow::anyhow!(err.to_string())),
+                                Err(full) => {
+                                    warn!("⏳ [Import API] {}", full);
+                                    return Err((StatusCode::TOO_MANY_REQUESTS, Json(full.body())));
+                                }
                             };
-                            return match res {
-                                Ok(msg) => {
-                                    info!("✅ {}", msg);
-                                    Json(json!({ "status": "ok", "message": msg }))
+                            manatan_events::publish(manatan_events::Event::DictionaryImported {
+                                success: res.is_ok(),
+                                message: match &res {
+                                    Ok(report) => report.message.clone(),
+                                    Err(e) => e.to_string(),
+                                },
+                            });
+                            return Ok(match res {
+                                Ok(report) => {
+                                    info!("✅ {}", report.message);
+                                    Json(json!({
+                                        "status": "ok",
+                                        "message": report.message,
+                                        "report": report,
+                                    }))
                                 }
                                 Err(e) => {
                                     error!("❌ {}", e);
                                     Json(json!({ "status": "error", "message": e.to_string() }))
                                 }
-                            };
+                            });
                         }
                         Err(e) => {
-                            return Json(
+                            return Ok(Json(
                                 json!({ "status": "error", "message": format!("Upload Failed: {}", e) }),
-                            );
+                            ));
                         }
                     }
                 }
@@ -1941,13 +2399,15 @@ pub async fn import_handler(
             Ok(None) => break,
             Err(e) => {
                 error!("❌ [Import API] Multipart error: {}", e);
-                return Json(
+                return Ok(Json(
                     json!({ "status": "error", "message": format!("Multipart Error: {}", e) }),
-                );
+                ));
             }
         }
     }
-    Json(json!({ "status": "error", "message": "No file field found" }))
+    Ok(Json(
+        json!({ "status": "error", "message": "No file field found" }),
+    ))
 }
 
 pub async fn dict_media_handler(
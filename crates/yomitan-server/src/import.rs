@@ -8,7 +8,7 @@ use std::{
 
 use anyhow::{Result, anyhow};
 use serde::{
-    Deserialize,
+    Deserialize, Serialize,
     de::{DeserializeOwned, DeserializeSeed, IgnoredAny, MapAccess, SeqAccess, Visitor},
 };
 use serde_json::{Value, value::RawValue};
@@ -31,6 +31,10 @@ const MAX_ZIP_ENTRY_COUNT: usize = 65536;
 const MAX_COMPRESSION_RATIO: u64 = 300;
 const MAX_TERMS_INSERTED: usize = 8_000_000;
 const SQLITE_MAX_BIND_PARAMS: usize = 900;
+/// Share of malformed bank entries an import tolerates before it fails.
+const MAX_ROW_ERROR_RATIO: f64 = 0.05;
+/// Malformed entries listed individually in a report; the rest are only counted.
+const MAX_REPORTED_ROW_ERRORS: usize = 50;
 
 fn read_limited_string<R: Read>(reader: R, byte_limit: u64, label: &str) -> Result<String> {
     let mut limited_reader = reader.take(byte_limit.saturating_add(1));
@@ -160,6 +164,63 @@ fn safe_join_path(base: &Path, relative: &str) -> Option<PathBuf> {
     Some(out)
 }
 
+/// Removes files written by an import that did not commit.
+#[derive(Default)]
+struct ImportCleanup {
+    paths: Vec<PathBuf>,
+    committed: bool,
+}
+
+impl Drop for ImportCleanup {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        for path in &self.paths {
+            let _ = if path.is_dir() {
+                fs::remove_dir_all(path)
+            } else {
+                fs::remove_file(path)
+            };
+        }
+    }
+}
+
+fn find_index_file<R: Read + std::io::Seek>(zip: &mut ZipArchive<R>) -> Option<String> {
+    (0..zip.len()).find_map(|i| {
+        zip.by_index(i)
+            .ok()
+            .map(|file| file.name().to_string())
+            .filter(|name| name.ends_with("index.json"))
+    })
+}
+
+fn index_format_version(json: &Value) -> Option<i64> {
+    json.get("format")
+        .or_else(|| json.get("version"))
+        .and_then(|value| {
+            value
+                .as_i64()
+                .or_else(|| value.as_str().and_then(|text| text.parse::<i64>().ok()))
+        })
+}
+
+fn parse_index_meta(json: &Value) -> Result<DictionaryMeta> {
+    let format_version = index_format_version(json);
+    if format_version != Some(3) {
+        return Err(anyhow!(match format_version {
+            Some(found) => format!("Unsupported dictionary format version {found} (expected 3)."),
+            None => "Unsupported dictionary format: missing version (expected 3).".to_string(),
+        }));
+    }
+
+    let name = json["title"].as_str().unwrap_or("Unknown").to_string();
+    let mut dm = DictionaryMeta::new(DictionaryKind::Yomitan, name);
+    dm.version = json["revision"].as_str().map(|s| s.to_string());
+    dm.description = json["description"].as_str().map(|s| s.to_string());
+    Ok(dm)
+}
+
 fn dict_archive_path(state: &AppState, dict_id: DictionaryId) -> PathBuf {
     state
         .data_dir
@@ -1005,7 +1066,15 @@ fn parse_ipa_meta(data_blob: &Value) -> (String, Option<String>) {
     (content, reading_opt)
 }
 
-fn parse_json_array_stream<R, T, F>(reader: R, on_entry: F) -> Result<usize>
+/// Entries read from one bank array. Entries that are valid JSON but not a valid row
+/// are skipped and listed in `errors` by index.
+#[derive(Debug, Default)]
+struct ParsedBank {
+    rows: usize,
+    errors: Vec<(usize, String)>,
+}
+
+fn parse_json_array_stream<R, T, F>(reader: R, on_entry: F) -> Result<ParsedBank>
 where
     R: Read,
     T: DeserializeOwned,
@@ -1017,7 +1086,7 @@ where
     parse_json_array_slice::<T, _>(&bytes, on_entry)
 }
 
-fn parse_json_array_slice<T, F>(bytes: &[u8], mut on_entry: F) -> Result<usize>
+fn parse_json_array_slice<T, F>(bytes: &[u8], mut on_entry: F) -> Result<ParsedBank>
 where
     T: DeserializeOwned,
     F: FnMut(T) -> Result<()>,
@@ -1036,7 +1105,7 @@ where
         T: DeserializeOwned,
         F: FnMut(T) -> Result<()>,
     {
-        type Value = usize;
+        type Value = ParsedBank;
 
         fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
             formatter.write_str("a JSON array")
@@ -1046,12 +1115,20 @@ where
         where
             A: SeqAccess<'de>,
         {
-            let mut count = 0usize;
-            while let Some(entry) = seq.next_element::<T>()? {
-                (self.on_entry)(entry).map_err(serde::de::Error::custom)?;
-                count += 1;
+            // Each entry is split off raw first, so one bad row does not end the bank.
+            let mut bank = ParsedBank::default();
+            let mut index = 0usize;
+            while let Some(raw) = seq.next_element::<&'de RawValue>()? {
+                match serde_json::from_str::<T>(raw.get()) {
+                    Ok(entry) => {
+                        (self.on_entry)(entry).map_err(serde::de::Error::custom)?;
+                        bank.rows += 1;
+                    }
+                    Err(err) => bank.errors.push((index, err.to_string())),
+                }
+                index += 1;
             }
-            Ok(count)
+            Ok(bank)
         }
     }
 
@@ -1069,7 +1146,7 @@ where
         T: DeserializeOwned,
         F: FnMut(T) -> Result<()>,
     {
-        type Value = usize;
+        type Value = ParsedBank;
 
         fn deserialize<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
         where
@@ -1089,13 +1166,95 @@ where
     let parse_bytes = repaired_bytes.as_deref().unwrap_or(bytes);
 
     let mut deserializer = serde_json::Deserializer::from_slice(parse_bytes);
-    let count = ArraySeed::<T, F> {
+    let bank = ArraySeed::<T, F> {
         on_entry: &mut on_entry,
         _marker: PhantomData,
     }
     .deserialize(&mut deserializer)?;
     deserializer.end()?;
-    Ok(count)
+    Ok(bank)
+}
+
+/// A bank entry that could not be read and was skipped.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RowError {
+    pub file: String,
+    /// Position of the entry in the bank's top-level array, from 0.
+    pub index: usize,
+    pub error: String,
+}
+
+/// Malformed entries skipped across an archive's banks.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedRows {
+    /// Entries read from every bank, skipped ones included.
+    pub total_rows: usize,
+    pub skipped_rows: usize,
+    /// The first skipped entries, with the file and index to inspect.
+    pub row_errors: Vec<RowError>,
+}
+
+impl SkippedRows {
+    fn record(&mut self, file: &str, bank: ParsedBank) {
+        self.total_rows += bank.rows + bank.errors.len();
+        self.skipped_rows += bank.errors.len();
+        if let Some((index, error)) = bank.errors.first() {
+            warn!(
+                "      Skipped {} malformed entries in {}; first at index {}: {}",
+                bank.errors.len(),
+                file,
+                index,
+                error
+            );
+        }
+        let room = MAX_REPORTED_ROW_ERRORS.saturating_sub(self.row_errors.len());
+        self.row_errors.extend(
+            bank.errors
+                .into_iter()
+                .take(room)
+                .map(|(index, error)| RowError {
+                    file: file.to_string(),
+                    index,
+                    error,
+                }),
+        );
+    }
+
+    /// Explains why the import should fail, when too many entries were malformed.
+    fn check(&self) -> Result<()> {
+        if self.skipped_rows == 0
+            || (self.skipped_rows as f64) <= self.total_rows as f64 * MAX_ROW_ERROR_RATIO
+        {
+            return Ok(());
+        }
+        let first = self
+            .row_errors
+            .first()
+            .map(|row| {
+                format!(
+                    "; first in {} at entry {}: {}",
+                    row.file, row.index, row.error
+                )
+            })
+            .unwrap_or_default();
+        Err(anyhow!(
+            "{} of {} dictionary entries are malformed, more than the {}% tolerated{first}",
+            self.skipped_rows,
+            self.total_rows,
+            MAX_ROW_ERROR_RATIO * 100.0
+        ))
+    }
+}
+
+/// Outcome of a successful dictionary import.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportReport {
+    pub message: String,
+    #[serde(flatten)]
+    pub skipped: SkippedRows,
 }
 
 fn is_json_hex_digit(byte: u8) -> bool {
@@ -1455,7 +1614,9 @@ fn insert_encoded_term_rows(
     Ok(())
 }
 
-pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
+/// Imports a Yomitan dictionary archive in one transaction. Malformed bank entries are
+/// skipped and reported unless there are too many of them.
+pub fn import_zip(state: &AppState, data: &[u8]) -> Result<ImportReport> {
     if data.len() > MAX_IMPORT_ARCHIVE_BYTES {
         return Err(anyhow!(
             "Archive is too large ({} bytes, max {MAX_IMPORT_ARCHIVE_BYTES}).",
@@ -1504,42 +1665,14 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
     }
 
     // 1. Find index.json
-    let mut index_file_name = None;
-    for i in 0..zip.len() {
-        if let Ok(file) = zip.by_index(i)
-            && file.name().ends_with("index.json")
-        {
-            index_file_name = Some(file.name().to_string());
-            break;
-        }
-    }
-
-    let index_file_name = index_file_name.ok_or_else(|| anyhow!("No index.json found in zip"))?;
+    let index_file_name =
+        find_index_file(&mut zip).ok_or_else(|| anyhow!("No index.json found in zip"))?;
 
     let meta = {
         let file = zip.by_name(&index_file_name)?;
         let s = read_limited_string(file, MAX_INDEX_JSON_BYTES, "index.json")?;
         let json: Value = serde_json::from_str(&s)?;
-
-        let format_value = json.get("format").or_else(|| json.get("version"));
-        let format_version = format_value.and_then(|value| {
-            value
-                .as_i64()
-                .or_else(|| value.as_str().and_then(|text| text.parse::<i64>().ok()))
-        });
-        if format_version != Some(3) {
-            return Err(anyhow!(match format_version {
-                Some(found) =>
-                    format!("Unsupported dictionary format version {found} (expected 3)."),
-                None => "Unsupported dictionary format: missing version (expected 3).".to_string(),
-            }));
-        }
-
-        let name = json["title"].as_str().unwrap_or("Unknown").to_string();
-        let mut dm = DictionaryMeta::new(DictionaryKind::Yomitan, name);
-        dm.version = json["revision"].as_str().map(|s| s.to_string());
-        dm.description = json["description"].as_str().map(|s| s.to_string());
-        dm
+        parse_index_meta(&json)?
     };
 
     let dict_name = meta.name.clone();
@@ -1641,8 +1774,13 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
         }
     }
 
+    // Files written below are removed again if the import fails before committing.
+    let mut cleanup = ImportCleanup::default();
     let dict_media_dir = state.data_dir.join("dict_media").join(&dict_name);
     if !skip_media {
+        if !dict_media_dir.exists() {
+            cleanup.paths.push(dict_media_dir.clone());
+        }
         fs::create_dir_all(&dict_media_dir)?;
 
         let mut media_files_extracted = 0usize;
@@ -1703,16 +1841,17 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
 
     // 4. Scan for term banks and insert
     let mut terms_found = 0usize;
+    let mut skipped = SkippedRows::default();
     let mut encoder = snap::raw::Encoder::new();
 
     for name in &file_names {
         if name.contains("term_bank") && !name.contains("term_meta") && name.ends_with(".json") {
             info!("   -> Processing definitions: {}", name);
 
-            let parse_result = (|| -> Result<usize> {
+            let parse_result = (|| -> Result<ParsedBank> {
                 let mut file = match open_zip_file_safe(&mut zip, name) {
                     Some(f) => f,
-                    None => return Ok(0),
+                    None => return Ok(ParsedBank::default()),
                 };
 
                 let term_batch_size = env_usize("YOMITAN_IMPORTER_TERM_BATCH_SIZE")
@@ -1728,7 +1867,7 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
                 let mut file_bytes = Vec::new();
                 file.read_to_end(&mut file_bytes)?;
 
-                let rows = parse_json_array_slice::<TermBankRow, _>(&file_bytes, |row| {
+                let bank = parse_json_array_slice::<TermBankRow, _>(&file_bytes, |row| {
                     if row.headword.is_empty() {
                         return Ok(());
                     }
@@ -1765,11 +1904,11 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
                     &mut terms_found,
                 )?;
 
-                Ok(rows)
+                Ok(bank)
             })();
 
-            let rows = match parse_result {
-                Ok(count) => count,
+            let bank = match parse_result {
+                Ok(bank) => bank,
                 Err(e) => {
                     let error_str = format!("{e:?}");
                     if error_str.contains("checksum")
@@ -1787,18 +1926,19 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
                 }
             };
 
-            if rows > 0 {
-                info!("      Parsed {} term rows from {}", rows, name);
+            if bank.rows > 0 {
+                info!("      Parsed {} term rows from {}", bank.rows, name);
             }
+            skipped.record(name, bank);
         }
         // Branch 2: Metadata / frequencies / pitch / IPA (term_meta_bank)
         else if name.contains("term_meta_bank") && name.ends_with(".json") {
             info!("   -> Processing metadata: {}", name);
 
-            let parse_result = (|| -> Result<usize> {
+            let parse_result = (|| -> Result<ParsedBank> {
                 let mut file = match open_zip_file_safe(&mut zip, name) {
                     Some(f) => f,
-                    None => return Ok(0),
+                    None => return Ok(ParsedBank::default()),
                 };
 
                 let insert_batch_size = env_usize("YOMITAN_IMPORTER_TERM_INSERT_BATCH_SIZE")
@@ -1810,7 +1950,7 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
                 let mut file_bytes = Vec::new();
                 file.read_to_end(&mut file_bytes)?;
 
-                let rows = parse_json_array_slice::<TermMetaBankRow, _>(&file_bytes, |row| {
+                let bank = parse_json_array_slice::<TermMetaBankRow, _>(&file_bytes, |row| {
                     if row.term.is_empty() || !["freq", "pitch", "ipa"].contains(&row.mode.as_str())
                     {
                         return Ok(());
@@ -1875,11 +2015,11 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
                     insert_encoded_term_rows(&tx, pending_inserts, dict_id, &mut terms_found)?;
                 }
 
-                Ok(rows)
+                Ok(bank)
             })();
 
-            let rows = match parse_result {
-                Ok(count) => count,
+            let bank = match parse_result {
+                Ok(bank) => bank,
                 Err(e) => {
                     let error_str = format!("{e:?}");
                     if error_str.contains("checksum")
@@ -1897,25 +2037,24 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
                 }
             };
 
-            if rows > 0 {
-                info!("      Parsed {} metadata rows from {}", rows, name);
+            if bank.rows > 0 {
+                info!("      Parsed {} metadata rows from {}", bank.rows, name);
             }
+            skipped.record(name, bank);
         }
         // Branch 3: Kanji bank (kanji_bank_*.json) - insert into terms table like pitch/freq
         else if name.contains("kanji_bank") && name.ends_with(".json") {
-            let parse_result = (|| -> Result<usize> {
+            let parse_result = (|| -> Result<ParsedBank> {
                 let mut file = match open_zip_file_safe(&mut zip, name) {
                     Some(f) => f,
-                    None => return Ok(0),
+                    None => return Ok(ParsedBank::default()),
                 };
 
                 let mut stmt = tx.prepare(
                     "INSERT OR REPLACE INTO kanji (character, dictionary_id, onyomi, kunyomi, tags, meanings, stats) VALUES (?, ?, ?, ?, ?, ?, ?)"
                 )?;
 
-                let mut count = 0usize;
-
-                let _rows = parse_json_array_stream::<_, KanjiBankRow, _>(&mut file, |row| {
+                let bank = parse_json_array_stream::<_, KanjiBankRow, _>(&mut file, |row| {
                     if row.character.chars().count() != 1 {
                         return Ok(());
                     }
@@ -1937,15 +2076,14 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
                         stats_json
                     ])?;
 
-                    count += 1;
                     Ok(())
                 })?;
 
-                Ok(count)
+                Ok(bank)
             })();
 
-            let rows = match parse_result {
-                Ok(count) => count,
+            let bank = match parse_result {
+                Ok(bank) => bank,
                 Err(e) => {
                     let error_str = format!("{e:?}");
                     if error_str.contains("checksum")
@@ -1963,18 +2101,19 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
                 }
             };
 
-            if rows > 0 {
-                info!("      Parsed {} kanji from {}", rows, name);
+            if bank.rows > 0 {
+                info!("      Parsed {} kanji from {}", bank.rows, name);
             }
+            skipped.record(name, bank);
         }
         // Branch 4: Kanji metadata (kanji_meta_bank_*.json) - insert as freq like term_meta
         else if name.contains("kanji_meta_bank") && name.ends_with(".json") {
             info!("   -> Processing kanji metadata: {}", name);
 
-            let parse_result = (|| -> Result<usize> {
+            let parse_result = (|| -> Result<ParsedBank> {
                 let mut file = match open_zip_file_safe(&mut zip, name) {
                     Some(f) => f,
-                    None => return Ok(0),
+                    None => return Ok(ParsedBank::default()),
                 };
 
                 let insert_batch_size = env_usize("YOMITAN_IMPORTER_TERM_INSERT_BATCH_SIZE")
@@ -1986,7 +2125,7 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
                 let mut file_bytes = Vec::new();
                 file.read_to_end(&mut file_bytes)?;
 
-                let rows = parse_json_array_slice::<KanjiMetaBankRow, _>(&file_bytes, |row| {
+                let bank = parse_json_array_slice::<KanjiMetaBankRow, _>(&file_bytes, |row| {
                     if row.character.is_empty() || row.meta_type != "freq" {
                         return Ok(());
                     }
@@ -2034,11 +2173,11 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
                     insert_encoded_term_rows(&tx, pending_inserts, dict_id, &mut terms_found)?;
                 }
 
-                Ok(rows)
+                Ok(bank)
             })();
 
-            let rows = match parse_result {
-                Ok(count) => count,
+            let bank = match parse_result {
+                Ok(bank) => bank,
                 Err(e) => {
                     let error_str = format!("{e:?}");
                     if error_str.contains("checksum")
@@ -2056,12 +2195,18 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
                 }
             };
 
-            if rows > 0 {
-                info!("      Parsed {} kanji metadata rows from {}", rows, name);
+            if bank.rows > 0 {
+                info!(
+                    "      Parsed {} kanji metadata rows from {}",
+                    bank.rows, name
+                );
             }
+            skipped.record(name, bank);
         }
     }
 
+    skipped.check()?;
+
     if defer_term_indexes {
         tx.execute_batch(
             "CREATE INDEX IF NOT EXISTS idx_term ON terms(term);
@@ -2077,10 +2222,12 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
         if let Some(parent) = archive_path.parent() {
             fs::create_dir_all(parent)?;
         }
+        cleanup.paths.push(archive_path.clone());
         fs::write(&archive_path, data)?;
     }
 
     tx.commit()?;
+    cleanup.committed = true;
     info!(
         "💾 [Import] Database transaction committed. Total Terms: {}",
         terms_found
@@ -2107,7 +2254,260 @@ pub fn import_zip(state: &AppState, data: &[u8]) -> Result<String> {
         );
     }
 
-    Ok(format!("Imported '{dict_name}'"))
+    let message = if skipped.skipped_rows > 0 {
+        format!(
+            "Imported '{dict_name}', skipping {} malformed entries",
+            skipped.skipped_rows
+        )
+    } else {
+        format!("Imported '{dict_name}'")
+    };
+    Ok(ImportReport { message, skipped })
+}
+
+/// Top-level `index.json` fields defined by the Yomitan dictionary schema.
+const KNOWN_INDEX_FIELDS: &[&str] = &[
+    "title",
+    "revision",
+    "sequenced",
+    "format",
+    "version",
+    "author",
+    "isUpdatable",
+    "indexUrl",
+    "downloadUrl",
+    "url",
+    "description",
+    "attribution",
+    "sourceLanguage",
+    "targetLanguage",
+    "frequencyMode",
+    "tagMeta",
+    "minimumYomitanVersion",
+];
+
+/// What a dry-run import found in a dictionary archive. Nothing is written while
+/// building it; `errors` lists everything that would make the real import fail.
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub title: Option<String>,
+    pub revision: Option<String>,
+    pub format: Option<i64>,
+    pub term_banks: usize,
+    pub term_meta_banks: usize,
+    pub kanji_banks: usize,
+    pub kanji_meta_banks: usize,
+    pub tag_banks: usize,
+    pub terms: usize,
+    pub term_meta: usize,
+    pub kanji: usize,
+    pub kanji_meta: usize,
+    /// Term rows with at least one structured-content definition.
+    pub structured_content_terms: usize,
+    pub media_files: usize,
+    /// Uncompressed size of all archive entries, in bytes.
+    pub total_size: u64,
+    /// Malformed entries the import would skip.
+    #[serde(flatten)]
+    pub skipped: SkippedRows,
+    pub warnings: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+/// Parses every bank in `data` the way [`import_zip`] would, without touching the
+/// database or the filesystem.
+pub fn validate_zip(state: &AppState, data: &[u8]) -> Result<ValidationReport> {
+    if data.len() > MAX_IMPORT_ARCHIVE_BYTES {
+        return Err(anyhow!(
+            "Archive is too large ({} bytes, max {MAX_IMPORT_ARCHIVE_BYTES}).",
+            data.len()
+        ));
+    }
+
+    let mut zip = ZipArchive::new(std::io::Cursor::new(data))?;
+    let mut report = ValidationReport::default();
+    if let Err(err) = validate_zip_archive(&mut zip) {
+        report.errors.push(err.to_string());
+    }
+
+    match find_index_file(&mut zip) {
+        None => report.errors.push("No index.json found in zip".to_string()),
+        Some(index_name) => {
+            let index = zip
+                .by_name(&index_name)
+                .map_err(anyhow::Error::from)
+                .and_then(|file| read_limited_string(file, MAX_INDEX_JSON_BYTES, "index.json"))
+                .and_then(|text| serde_json::from_str::<Value>(&text).map_err(anyhow::Error::from));
+            match index {
+                Ok(json) => validate_index(state, &json, &mut report),
+                Err(err) => report.errors.push(format!("index.json: {err}")),
+            }
+        }
+    }
+
+    let mut file_names = Vec::with_capacity(zip.len());
+    for i in 0..zip.len() {
+        if let Ok(file) = zip.by_index(i)
+            && !file.is_dir()
+        {
+            report.total_size = report.total_size.saturating_add(file.size());
+            file_names.push(file.name().to_string());
+        }
+    }
+
+    let mut unknown_modes = HashSet::new();
+    for name in &file_names {
+        let result =
+            if name.contains("term_bank") && !name.contains("term_meta") && name.ends_with(".json")
+            {
+                report.term_banks += 1;
+                read_bank(&mut zip, name).and_then(|bytes| {
+                    parse_json_array_slice::<TermBankRow, _>(&bytes, |row| {
+                        if !row.headword.is_empty() {
+                            report.terms += 1;
+                            if row
+                                .definitions
+                                .iter()
+                                .any(|definition| definition.get().contains("structured-content"))
+                            {
+                                report.structured_content_terms += 1;
+                            }
+                        }
+                        Ok(())
+                    })
+                })
+            } else if name.contains("term_meta_bank") && name.ends_with(".json") {
+                report.term_meta_banks += 1;
+                read_bank(&mut zip, name).and_then(|bytes| {
+                    parse_json_array_slice::<TermMetaBankRow, _>(&bytes, |row| {
+                        if ["freq", "pitch", "ipa"].contains(&row.mode.as_str()) {
+                            report.term_meta += 1;
+                        } else if !row.term.is_empty() {
+                            unknown_modes.insert(row.mode);
+                        }
+                        Ok(())
+                    })
+                })
+            } else if name.contains("kanji_bank") && name.ends_with(".json") {
+                report.kanji_banks += 1;
+                read_bank(&mut zip, name).and_then(|bytes| {
+                    parse_json_array_slice::<KanjiBankRow, _>(&bytes, |row| {
+                        if row.character.chars().count() == 1 {
+                            report.kanji += 1;
+                        }
+                        Ok(())
+                    })
+                })
+            } else if name.contains("kanji_meta_bank") && name.ends_with(".json") {
+                report.kanji_meta_banks += 1;
+                read_bank(&mut zip, name).and_then(|bytes| {
+                    parse_json_array_slice::<KanjiMetaBankRow, _>(&bytes, |row| {
+                        if row.meta_type == "freq" {
+                            report.kanji_meta += 1;
+                        } else if !row.character.is_empty() {
+                            unknown_modes.insert(row.meta_type);
+                        }
+                        Ok(())
+                    })
+                })
+            } else if name.contains("tag_bank") && name.ends_with(".json") {
+                report.tag_banks += 1;
+                Ok(ParsedBank::default())
+            } else if name.ends_with("index.json") || name.ends_with("styles.css") {
+                Ok(ParsedBank::default())
+            } else if name.ends_with(".json") || name.ends_with(".json.gz") {
+                report
+                    .warnings
+                    .push(format!("{name}: not a recognised bank, it will be ignored"));
+                Ok(ParsedBank::default())
+            } else {
+                report.media_files += 1;
+                Ok(ParsedBank::default())
+            };
+
+        match result {
+            Ok(bank) => report.skipped.record(name, bank),
+            Err(err) => {
+                // The importer tolerates checksum mismatches, so they only warrant a warning.
+                let detail = format!("{err:?}");
+                if detail.contains("checksum")
+                    || detail.contains("CRC")
+                    || detail.contains("InvalidArchive")
+                {
+                    report
+                        .warnings
+                        .push(format!("{name}: checksum error, the bank will be skipped"));
+                } else {
+                    report.errors.push(format!("{name}: {err}"));
+                }
+            }
+        }
+    }
+    if let Err(err) = report.skipped.check() {
+        report.errors.push(err.to_string());
+    }
+
+    let mut unknown_modes: Vec<_> = unknown_modes.into_iter().collect();
+    unknown_modes.sort();
+    for mode in unknown_modes {
+        report.warnings.push(format!(
+            "Unknown metadata mode '{mode}', those rows will be skipped"
+        ));
+    }
+    if report.terms + report.term_meta + report.kanji + report.kanji_meta == 0 {
+        report
+            .warnings
+            .push("Archive contains no importable rows".to_string());
+    }
+
+    report.valid = report.errors.is_empty();
+    Ok(report)
+}
+
+fn validate_index(state: &AppState, json: &Value, report: &mut ValidationReport) {
+    report.format = index_format_version(json);
+    report.title = json["title"].as_str().map(str::to_string);
+    report.revision = json["revision"].as_str().map(str::to_string);
+
+    match parse_index_meta(json) {
+        Ok(meta) => {
+            let normalized_name = meta.name.trim().to_lowercase();
+            let dicts = state.dictionaries.read().expect("lock");
+            if dicts
+                .values()
+                .any(|dict| dict.name.trim().to_lowercase() == normalized_name)
+            {
+                report
+                    .errors
+                    .push(format!("Dictionary '{}' is already imported.", meta.name));
+            }
+        }
+        Err(err) => report.errors.push(err.to_string()),
+    }
+
+    if report.title.is_none() {
+        report
+            .warnings
+            .push("index.json has no title; it will be imported as 'Unknown'".to_string());
+    }
+    if let Some(fields) = json.as_object() {
+        for key in fields.keys() {
+            if !KNOWN_INDEX_FIELDS.contains(&key.as_str()) {
+                report
+                    .warnings
+                    .push(format!("index.json: unknown field '{key}'"));
+            }
+        }
+    }
+}
+
+fn read_bank<R: Read + std::io::Seek>(zip: &mut ZipArchive<R>, name: &str) -> Result<Vec<u8>> {
+    let mut file = zip.by_name(name)?;
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    Ok(bytes)
 }
 
 #[cfg(test)]
@@ -2174,8 +2574,9 @@ mod tests {
                 )],
             );
 
-            let msg = import_zip(state, &zip).expect("import should succeed");
-            assert!(msg.contains("Imported 'Test Dict'"));
+            let report = import_zip(state, &zip).expect("import should succeed");
+            assert!(report.message.contains("Imported 'Test Dict'"));
+            assert_eq!(report.skipped.skipped_rows, 0);
 
             let conn = state.pool.get().expect("db connection");
             let dict_count: i64 = conn
@@ -2254,7 +2655,7 @@ mod tests {
         let mut parsed_rows = 0usize;
         let mut captured = String::new();
 
-        let count = parse_json_array_stream::<_, Vec<Value>, _>(&bytes[..], |arr| {
+        let bank = parse_json_array_stream::<_, Vec<Value>, _>(&bytes[..], |arr| {
             parsed_rows += 1;
             captured = arr
                 .get(5)
@@ -2267,7 +2668,7 @@ mod tests {
         })
         .expect("malformed escapes should be repaired");
 
-        assert_eq!(count, 1);
+        assert_eq!(bank.rows, 1);
         assert_eq!(parsed_rows, 1);
         assert!(captured.contains('�'));
         assert!(captured.contains("\\x"));
@@ -2303,4 +2704,180 @@ mod tests {
             assert_eq!(term_count, 0, "failed import must not leave term rows");
         });
     }
+
+    /// A term bank cut off mid-row, as produced by an interrupted download or repack.
+    const TRUNCATED_TERM_BANK: &str =
+        r#"[["犬","いぬ","n",null,50,["dog"],0,""],["鳥","とり","n",null,"#;
+
+    #[test]
+    fn validate_reports_contents_without_importing() {
+        with_state("validate-report", |state| {
+            let zip = build_zip(
+                r#"{"format":3,"title":"Report Dict","revision":"2024.1","homepage":"x"}"#,
+                &[
+                    (
+                        "term_bank_1.json",
+                        r#"[["猫","ねこ","n",null,100,["cat"],0,""],["犬","いぬ","n",null,50,[{"type":"structured-content","content":"dog"}],0,""]]"#,
+                    ),
+                    (
+                        "term_meta_bank_1.json",
+                        r#"[["猫","freq",10],["猫","mystery",1]]"#,
+                    ),
+                    (
+                        "kanji_bank_1.json",
+                        r#"[["猫","ビョウ","ねこ","",["cat"],{}]]"#,
+                    ),
+                    ("img/cat.png", "png"),
+                ],
+            );
+
+            let report = validate_zip(state, &zip).expect("validation should run");
+            assert!(report.valid, "unexpected errors: {:?}", report.errors);
+            assert_eq!(report.title.as_deref(), Some("Report Dict"));
+            assert_eq!(report.revision.as_deref(), Some("2024.1"));
+            assert_eq!(report.term_banks, 1);
+            assert_eq!(report.terms, 2);
+            assert_eq!(report.structured_content_terms, 1);
+            assert_eq!(report.term_meta, 1);
+            assert_eq!(report.kanji, 1);
+            assert_eq!(report.media_files, 1);
+            assert!(report.total_size > 0);
+            assert!(report.warnings.iter().any(|w| w.contains("homepage")));
+            assert!(report.warnings.iter().any(|w| w.contains("mystery")));
+
+            let conn = state.pool.get().expect("db connection");
+            let dict_count: i64 = conn
+                .query_row("SELECT COUNT(*) FROM dictionaries", [], |row| row.get(0))
+                .expect("dict count query");
+            assert_eq!(dict_count, 0, "validation must not register the dictionary");
+        });
+    }
+
+    #[test]
+    fn validate_flags_truncated_term_bank() {
+        with_state("validate-truncated", |state| {
+            let zip = build_zip(
+                r#"{"format":3,"title":"Truncated Dict","revision":"1"}"#,
+                &[
+                    (
+                        "term_bank_1.json",
+                        r#"[["猫","ねこ","n",null,100,["cat"],0,""]]"#,
+                    ),
+                    ("term_bank_2.json", TRUNCATED_TERM_BANK),
+                ],
+            );
+
+            let report = validate_zip(state, &zip).expect("validation should run");
+            assert!(!report.valid);
+            assert_eq!(report.term_banks, 2);
+            assert!(
+                report
+                    .errors
+                    .iter()
+                    .any(|error| error.starts_with("term_bank_2.json"))
+            );
+        });
+    }
+
+    #[test]
+    fn failure_after_earlier_banks_rolls_back_everything() {
+        with_state("failed-import-later-bank", |state| {
+            let zip = build_zip(
+                r#"{"format":3,"title":"Half Dict","revision":"1"}"#,
+                &[
+                    (
+                        "term_bank_1.json",
+                        r#"[["猫","ねこ","n",null,100,["cat"],0,""]]"#,
+                    ),
+                    ("term_bank_2.json", TRUNCATED_TERM_BANK),
+                    ("img/cat.png", "png"),
+                ],
+            );
+
+            import_zip(state, &zip).expect_err("truncated bank should fail the import");
+
+            let conn = state.pool.get().expect("db connection");
+            let dict_count: i64 = conn
+                .query_row("SELECT COUNT(*) FROM dictionaries", [], |row| row.get(0))
+                .expect("dict count query");
+            let term_count: i64 = conn
+                .query_row("SELECT COUNT(*) FROM terms", [], |row| row.get(0))
+                .expect("term count query");
+            assert_eq!(dict_count, 0);
+            assert_eq!(
+                term_count, 0,
+                "rows from the first bank must be rolled back"
+            );
+            assert!(state.dictionaries.read().expect("lock").is_empty());
+            let archives = fs::read_dir(state.data_dir.join("dict_archives"))
+                .map(|entries| entries.count())
+                .unwrap_or(0);
+            assert_eq!(archives, 0);
+        });
+    }
+
+    fn term_bank_with(malformed: &[(usize, &str)], rows: usize) -> String {
+        let entries: Vec<String> = (0..rows)
+            .map(|index| {
+                malformed
+                    .iter()
+                    .find(|(at, _)| *at == index)
+                    .map(|(_, entry)| entry.to_string())
+                    .unwrap_or_else(|| {
+                        format!(r#"["語{index}","ご","n",null,1,["word {index}"],0,""]"#)
+                    })
+            })
+            .collect();
+        format!("[{}]", entries.join(","))
+    }
+
+    #[test]
+    fn skips_and_reports_malformed_term_rows() {
+        with_state("malformed-rows", |state| {
+            let bank = term_bank_with(&[(7, r#""not a row""#), (12, "42")], 60);
+            let zip = build_zip(
+                r#"{"format":3,"title":"Messy Dict","revision":"1"}"#,
+                &[("term_bank_1.json", bank.as_str())],
+            );
+
+            let report = import_zip(state, &zip).expect("a few bad rows should be skipped");
+            assert!(report.message.contains("skipping 2 malformed entries"));
+            assert_eq!(report.skipped.total_rows, 60);
+            assert_eq!(report.skipped.skipped_rows, 2);
+            let located: Vec<_> = report
+                .skipped
+                .row_errors
+                .iter()
+                .map(|row| (row.file.as_str(), row.index))
+                .collect();
+            assert_eq!(located, [("term_bank_1.json", 7), ("term_bank_1.json", 12)]);
+
+            let conn = state.pool.get().expect("db connection");
+            let term_count: i64 = conn
+                .query_row("SELECT COUNT(*) FROM terms", [], |row| row.get(0))
+                .expect("term count query");
+            assert_eq!(term_count, 58);
+        });
+    }
+
+    #[test]
+    fn too_many_malformed_rows_fail_the_import() {
+        with_state("malformed-rows-over-limit", |state| {
+            let bank = term_bank_with(&[(1, "{}"), (3, "null")], 4);
+            let zip = build_zip(
+                r#"{"format":3,"title":"Broken Rows","revision":"1"}"#,
+                &[("term_bank_1.json", bank.as_str())],
+            );
+
+            let report = validate_zip(state, &zip).expect("validation should run");
+            assert!(!report.valid);
+            assert_eq!(report.skipped.skipped_rows, 2);
+
+            let err = import_zip(state, &zip).expect_err("half the rows are malformed");
+            let message = err.to_string();
+            assert!(message.contains("2 of 4"), "{message}");
+            assert!(message.contains("term_bank_1.json at entry 1"), "{message}");
+            assert!(state.dictionaries.read().expect("lock").is_empty());
+        });
+    }
 }
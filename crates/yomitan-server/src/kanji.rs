@@ -0,0 +1,463 @@
+//! Per-kanji breakdown of a word for mined cards: each kanji's meanings and readings from
+//! the installed kanji dictionaries, and the reading it takes in this word.
+//!
+//! The word's reading is split over its characters by matching the kana in the word
+//! (okurigana and particles) literally and trying each kanji's dictionary readings, with
+//! rendaku and gemination, in between. A kanji standing alone between kana may take any
+//! reading, which covers readings missing from the dictionary. Words that still don't
+//! align, such as 今日 read きょう, list their readings without a reading per kanji.
+
+use rusqlite::OptionalExtension;
+use serde::Serialize;
+
+use crate::{entry::escape_html, lookup::LookupService, state::AppState};
+
+const ITERATION_MARK: char = '々';
+/// Unvoiced kana and their voiced and half-voiced forms, for rendaku in compounds.
+const RENDAKU: &[(char, char)] = &[
+    ('か', 'が'),
+    ('き', 'ぎ'),
+    ('く', 'ぐ'),
+    ('け', 'げ'),
+    ('こ', 'ご'),
+    ('さ', 'ざ'),
+    ('し', 'じ'),
+    ('す', 'ず'),
+    ('せ', 'ぜ'),
+    ('そ', 'ぞ'),
+    ('た', 'だ'),
+    ('ち', 'ぢ'),
+    ('つ', 'づ'),
+    ('て', 'で'),
+    ('と', 'ど'),
+    ('は', 'ば'),
+    ('ひ', 'び'),
+    ('ふ', 'ぶ'),
+    ('へ', 'べ'),
+    ('ほ', 'ぼ'),
+    ('は', 'ぱ'),
+    ('ひ', 'ぴ'),
+    ('ふ', 'ぷ'),
+    ('へ', 'ぺ'),
+    ('ほ', 'ぽ'),
+];
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KanjiPart {
+    pub character: String,
+    pub meanings: Vec<String>,
+    pub onyomi: Vec<String>,
+    pub kunyomi: Vec<String>,
+    /// The part of the word's reading this kanji takes; `None` when the word didn't align.
+    pub reading_in_word: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KanjiBreakdown {
+    pub word: String,
+    pub reading: Option<String>,
+    /// Whether the reading was split over the kanji.
+    pub aligned: bool,
+    pub kanji: Vec<KanjiPart>,
+    /// The breakdown as a list ready to paste into a card field.
+    pub html: String,
+}
+
+/// Breaks `word` down by kanji. Without a `reading`, the one from the highest priority
+/// term dictionary that has the word is used.
+pub fn kanji_breakdown(
+    state: &AppState,
+    lookup: &LookupService,
+    word: &str,
+    reading: Option<&str>,
+) -> KanjiBreakdown {
+    let reading = reading
+        .map(str::to_string)
+        .or_else(|| dictionary_reading(state, word));
+
+    let chars: Vec<char> = word.chars().collect();
+    let mut parts: Vec<Option<KanjiPart>> = Vec::with_capacity(chars.len());
+    for (index, &c) in chars.iter().enumerate() {
+        let part = if c == ITERATION_MARK {
+            parts[..index]
+                .iter()
+                .rev()
+                .flatten()
+                .next()
+                .map(|previous| KanjiPart {
+                    character: c.to_string(),
+                    reading_in_word: None,
+                    ..previous.clone()
+                })
+        } else if is_kanji(c) {
+            Some(kanji_part(state, lookup, c))
+        } else {
+            None
+        };
+        parts.push(part);
+    }
+
+    let segments: Vec<Segment> = chars
+        .iter()
+        .zip(&parts)
+        .enumerate()
+        .map(|(index, (&c, part))| match part {
+            Some(part) => Segment::Kanji {
+                candidates: reading_candidates(part),
+                alone: !chars[..index].last().is_some_and(|&c| is_kanji(c))
+                    && !chars.get(index + 1).is_some_and(|&c| is_kanji(c)),
+            },
+            None => Segment::Kana(to_hiragana(c)),
+        })
+        .collect();
+    let alignment = reading
+        .as_deref()
+        .and_then(|reading| align(&segments, reading));
+
+    let aligned = alignment.is_some();
+    if let Some(chunks) = alignment {
+        for (part, chunk) in parts.iter_mut().zip(chunks) {
+            if let Some(part) = part {
+                part.reading_in_word = Some(chunk);
+            }
+        }
+    }
+    let kanji: Vec<KanjiPart> = parts.into_iter().flatten().collect();
+    let html = render_html(&kanji);
+    KanjiBreakdown {
+        word: word.to_string(),
+        reading,
+        aligned,
+        kanji,
+        html,
+    }
+}
+
+fn dictionary_reading(state: &AppState, word: &str) -> Option<String> {
+    let conn = state.pool.get().ok()?;
+    conn.query_row(
+        "SELECT t.reading FROM terms t JOIN dictionaries d ON d.id = t.dictionary_id
+         WHERE t.term = ? AND d.enabled AND t.reading IS NOT NULL AND t.reading != ''
+         ORDER BY d.priority LIMIT 1",
+        rusqlite::params![word],
+        |row| row.get(0),
+    )
+    .optional()
+    .ok()
+    .flatten()
+}
+
+/// The kanji's entries from every enabled kanji dictionary, merged in priority order.
+fn kanji_part(state: &AppState, lookup: &LookupService, c: char) -> KanjiPart {
+    let character = c.to_string();
+    let mut part = KanjiPart {
+        character: character.clone(),
+        ..KanjiPart::default()
+    };
+    let extend = |into: &mut Vec<String>, values: Vec<String>| {
+        for value in values {
+            if !into.contains(&value) {
+                into.push(value);
+            }
+        }
+    };
+    for entry in lookup.search_kanji(state, &character, 0) {
+        if entry.character != character {
+            continue;
+        }
+        extend(&mut part.meanings, entry.meanings);
+        extend(&mut part.onyomi, entry.onyomi);
+        extend(&mut part.kunyomi, entry.kunyomi);
+    }
+    part
+}
+
+enum Segment {
+    Kana(char),
+    Kanji {
+        candidates: Vec<String>,
+        /// No kanji on either side, so kana anchor it and it may take any reading.
+        alone: bool,
+    },
+}
+
+/// A kanji's readings in hiragana as they can appear inside a word: on'yomi, kun'yomi
+/// stems with and without their first okurigana kana, and the voiced and geminated
+/// forms compounds give them. Longest first.
+fn reading_candidates(part: &KanjiPart) -> Vec<String> {
+    let mut bases = Vec::new();
+    for on in &part.onyomi {
+        bases.push(on.chars().map(to_hiragana).collect::<String>());
+    }
+    for kun in &part.kunyomi {
+        let kun: String = kun.chars().filter(|&c| c != '-').map(to_hiragana).collect();
+        match kun.split_once('.') {
+            Some((stem, okurigana)) => {
+                bases.push(stem.to_string());
+                if let Some(first) = okurigana.chars().next() {
+                    bases.push(format!("{stem}{first}"));
+                }
+            }
+            None => bases.push(kun),
+        }
+    }
+
+    let mut candidates: Vec<String> = Vec::new();
+    for base in bases.into_iter().filter(|base| !base.is_empty()) {
+        let mut chars: Vec<char> = base.chars().collect();
+        let mut variants = vec![base];
+        for &(plain, voiced) in RENDAKU {
+            if chars[0] == plain {
+                variants.push(
+                    std::iter::once(voiced)
+                        .chain(chars[1..].iter().copied())
+                        .collect(),
+                );
+            }
+        }
+        if chars.len() > 1
+            && let Some(last) = chars.last_mut()
+            && matches!(*last, 'つ' | 'く' | 'ち' | 'き')
+        {
+            *last = 'っ';
+            variants.push(chars.iter().collect());
+        }
+        for variant in variants {
+            if !candidates.contains(&variant) {
+                candidates.push(variant);
+            }
+        }
+    }
+    candidates.sort_by_key(|candidate| std::cmp::Reverse(candidate.chars().count()));
+    candidates
+}
+
+/// Splits `reading` into one chunk per segment, or `None` if no split fits.
+fn align(segments: &[Segment], reading: &str) -> Option<Vec<String>> {
+    let reading: Vec<char> = reading.chars().map(to_hiragana).collect();
+    let mut chunks = Vec::with_capacity(segments.len());
+    align_from(segments, &reading, &mut chunks).then_some(chunks)
+}
+
+fn align_from(segments: &[Segment], reading: &[char], chunks: &mut Vec<String>) -> bool {
+    let Some((segment, rest)) = segments.split_first() else {
+        return reading.is_empty();
+    };
+    let try_chunk = |len: usize, chunks: &mut Vec<String>| {
+        chunks.push(reading[..len].iter().collect());
+        if align_from(rest, &reading[len..], chunks) {
+            return true;
+        }
+        chunks.pop();
+        false
+    };
+    match segment {
+        Segment::Kana(c) => reading.first() == Some(c) && try_chunk(1, chunks),
+        Segment::Kanji { candidates, alone } => {
+            let dictionary = candidates.iter().any(|candidate| {
+                let len = candidate.chars().count();
+                reading.len() >= len
+                    && reading[..len].iter().copied().eq(candidate.chars())
+                    && try_chunk(len, chunks)
+            });
+            dictionary || (*alone && (1..=reading.len()).any(|len| try_chunk(len, chunks)))
+        }
+    }
+}
+
+fn render_html(kanji: &[KanjiPart]) -> String {
+    let mut html = String::from("<ul class=\"kanji-breakdown\">");
+    for part in kanji {
+        html.push_str("<li><b>");
+        html.push_str(&escape_html(&part.character));
+        html.push_str("</b>");
+        if let Some(reading) = &part.reading_in_word {
+            html.push_str(&format!(" ({})", escape_html(reading)));
+        }
+        if !part.meanings.is_empty() {
+            html.push_str(&format!(" {}", escape_html(&part.meanings.join(", "))));
+        }
+        let readings: Vec<&str> = part
+            .onyomi
+            .iter()
+            .chain(&part.kunyomi)
+            .map(String::as_str)
+            .collect();
+        if !readings.is_empty() {
+            html.push_str(&format!(
+                " <small>{}</small>",
+                escape_html(&readings.join("、"))
+            ));
+        }
+        html.push_str("</li>");
+    }
+    html.push_str("</ul>");
+    html
+}
+
+fn is_kanji(c: char) -> bool {
+    ('\u{4E00}'..='\u{9FFF}').contains(&c)
+        || ('\u{3400}'..='\u{4DBF}').contains(&c)
+        || c == ITERATION_MARK
+}
+
+fn to_hiragana(c: char) -> char {
+    match c as u32 {
+        code @ 0x30A1..=0x30F6 => char::from_u32(code - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::Write,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::import::import_zip;
+
+    fn part(character: &str, onyomi: &[&str], kunyomi: &[&str]) -> KanjiPart {
+        KanjiPart {
+            character: character.to_string(),
+            onyomi: onyomi.iter().map(|r| r.to_string()).collect(),
+            kunyomi: kunyomi.iter().map(|r| r.to_string()).collect(),
+            ..KanjiPart::default()
+        }
+    }
+
+    fn kanji(candidates: &KanjiPart, alone: bool) -> Segment {
+        Segment::Kanji {
+            candidates: reading_candidates(candidates),
+            alone,
+        }
+    }
+
+    #[test]
+    fn aligns_compounds_with_rendaku_and_gemination() {
+        let segments = [
+            kanji(&part("学", &["ガク"], &["まな.ぶ"]), false),
+            kanji(&part("校", &["コウ", "キョウ"], &[]), false),
+        ];
+        assert_eq!(
+            align(&segments, "がっこう"),
+            Some(vec!["がっ".to_string(), "こう".to_string()])
+        );
+
+        let segments = [
+            kanji(&part("人", &["ジン", "ニン"], &["ひと"]), false),
+            kanji(&part("々", &["ジン", "ニン"], &["ひと"]), false),
+        ];
+        assert_eq!(
+            align(&segments, "ひとびと"),
+            Some(vec!["ひと".to_string(), "びと".to_string()])
+        );
+    }
+
+    #[test]
+    fn okurigana_anchors_the_kanji_reading() {
+        let segments = [
+            kanji(&part("食", &["ショク"], &["く.う", "た.べる"]), true),
+            Segment::Kana('べ'),
+            Segment::Kana('る'),
+        ];
+        assert_eq!(
+            align(&segments, "たべる"),
+            Some(vec!["た".to_string(), "べ".to_string(), "る".to_string()])
+        );
+
+        // A reading the dictionary doesn't list still aligns between kana.
+        let segments = [
+            kanji(&part("私", &["シ"], &["わたくし"]), true),
+            Segment::Kana('た'),
+            Segment::Kana('ち'),
+        ];
+        assert_eq!(
+            align(&segments, "わたしたち"),
+            Some(vec![
+                "わたし".to_string(),
+                "た".to_string(),
+                "ち".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn irregular_readings_do_not_align() {
+        let segments = [
+            kanji(&part("今", &["コン", "キン"], &["いま"]), false),
+            kanji(&part("日", &["ニチ", "ジツ"], &["ひ", "-び", "-か"]), false),
+        ];
+        assert_eq!(align(&segments, "きょう"), None);
+    }
+
+    fn build_zip(index_json: &str, entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let cursor = std::io::Cursor::new(&mut bytes);
+            let mut zip = ZipWriter::new(cursor);
+            let opts = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+            zip.start_file("index.json", opts).expect("start index");
+            zip.write_all(index_json.as_bytes()).expect("write index");
+            for (name, contents) in entries {
+                zip.start_file(name, opts).expect("start file");
+                zip.write_all(contents.as_bytes()).expect("write file");
+            }
+            zip.finish().expect("finish zip");
+        }
+        bytes
+    }
+
+    #[test]
+    fn breakdown_uses_installed_dictionaries() {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time")
+            .as_nanos();
+        let dir = std::env::temp_dir().join(format!(
+            "manatan-yomitan-kanji-test-{}-{nanos}",
+            std::process::id()
+        ));
+        let state = AppState::new(dir.clone());
+        let zip = build_zip(
+            r#"{"format":3,"title":"Kanji","revision":"1"}"#,
+            &[
+                (
+                    "term_bank_1.json",
+                    r#"[["学校","がっこう","n",null,0,["school"],0,""],["今日","きょう","n",null,0,["today"],0,""]]"#,
+                ),
+                (
+                    "kanji_bank_1.json",
+                    r#"[["学","ガク","まな.ぶ","",["study","learning"],{}],["校","コウ キョウ","","",["school"],{}],["今","コン キン","いま","",["now"],{}],["日","ニチ ジツ","ひ -び -か","",["day","sun"],{}]]"#,
+                ),
+            ],
+        );
+        import_zip(&state, &zip).expect("import should succeed");
+        let lookup = LookupService::new();
+
+        let school = kanji_breakdown(&state, &lookup, "学校", None);
+        assert_eq!(school.reading.as_deref(), Some("がっこう"));
+        assert!(school.aligned);
+        assert_eq!(school.kanji.len(), 2);
+        assert_eq!(school.kanji[0].meanings, ["study", "learning"]);
+        assert_eq!(school.kanji[0].reading_in_word.as_deref(), Some("がっ"));
+        assert_eq!(school.kanji[1].onyomi, ["コウ", "キョウ"]);
+        assert_eq!(school.kanji[1].reading_in_word.as_deref(), Some("こう"));
+        assert!(school.html.contains("<b>学</b> (がっ) study, learning"));
+
+        let today = kanji_breakdown(&state, &lookup, "今日", None);
+        assert!(!today.aligned);
+        assert_eq!(today.kanji.len(), 2);
+        assert!(today.kanji.iter().all(|k| k.reading_in_word.is_none()));
+        assert_eq!(today.kanji[1].kunyomi, ["ひ", "-び", "-か"]);
+
+        drop(state);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
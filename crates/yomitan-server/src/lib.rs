@@ -7,16 +7,23 @@ use axum::{
 };
 use tower_http::{cors::CorsLayer, limit::RequestBodyLimitLayer};
 
+pub mod annotate;
 pub mod deinflector;
+pub mod entry;
 pub mod handlers;
 pub mod import;
+pub mod kanji;
 pub mod lookup;
+pub mod personalization;
 pub mod state;
 
 use handlers::{
-    audio_handler, dict_media_handler, import_handler, install_defaults_handler,
-    install_language_handler, list_dictionaries_handler, lookup_handler,
-    manage_dictionaries_handler, reset_db_handler, unload_handler,
+    annotate_chapter_handler, audio_handler, dict_media_handler, entry_handler,
+    frequency_report_handler, get_frequency_strategy_handler, get_personalization_handler,
+    import_handler, install_defaults_handler, install_language_handler, kanji_breakdown_handler,
+    list_dictionaries_handler, lookup_handler, manage_dictionaries_handler, record_lookup_handler,
+    reset_db_handler, search_handler, set_frequency_strategy_handler, set_personalization_handler,
+    set_word_status_handler, unload_handler,
 };
 use lookup::LookupService;
 use state::AppState;
@@ -28,6 +35,7 @@ pub struct ServerState {
 }
 
 pub fn create_router(data_dir: PathBuf) -> Router {
+    manatan_storage::register("yomitan", &data_dir);
     let state = ServerState {
         app: AppState::new(data_dir),
         lookup: Arc::new(LookupService::new()),
@@ -37,8 +45,23 @@ pub fn create_router(data_dir: PathBuf) -> Router {
 
     Router::new()
         .route("/lookup", get(lookup_handler))
+        .route("/search", post(search_handler))
         .route("/audio", get(audio_handler))
         .route("/dictionaries", get(list_dictionaries_handler))
+        .route("/entry/{dictionary_id}/{headword}", get(entry_handler))
+        .route("/frequency-report", get(frequency_report_handler))
+        .route(
+            "/frequency-strategy",
+            get(get_frequency_strategy_handler).post(set_frequency_strategy_handler),
+        )
+        .route(
+            "/personalization",
+            get(get_personalization_handler).post(set_personalization_handler),
+        )
+        .route("/lookup-history", post(record_lookup_handler))
+        .route("/word-status", post(set_word_status_handler))
+        .route("/annotate-chapter", post(annotate_chapter_handler))
+        .route("/kanji-breakdown", post(kanji_breakdown_handler))
         .route("/dict-media/{dict_name}/{*path}", get(dict_media_handler))
         .route("/import", post(import_handler))
         .route("/reset", post(reset_db_handler))
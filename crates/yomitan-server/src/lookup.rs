@@ -10,6 +10,9 @@ use wordbase_api::{
 
 use crate::{
     deinflector::{Deinflector, Language as DeinflectLanguage},
+    personalization::{
+        BoostedTerm, Personalization, PersonalizationReport, personalization_from_conn, weights_for,
+    },
     state::{AppState, StoredRecord},
 };
 
@@ -35,6 +38,147 @@ pub struct KanjiFrequency {
     pub value: String,
 }
 
+/// Per-dictionary frequency entry for a single term, used by the frequency report.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TermFrequency {
+    pub dictionary_id: i64,
+    pub dictionary_name: String,
+    pub priority: i64,
+    pub reading: Option<String>,
+    pub value: String,
+    pub rank: Option<i64>,
+}
+
+/// How ranks from several frequency dictionaries are combined when ordering lookup results.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FrequencyStrategy {
+    /// Order by dictionary priority only, ignoring frequency dictionaries.
+    #[default]
+    Off,
+    /// Use the most common (lowest) rank reported by any dictionary.
+    MinRank,
+    /// Use the mean of all reported ranks.
+    Average,
+    /// Use the rank from the highest-priority frequency dictionary listing the term.
+    Priority,
+}
+
+const FREQUENCY_STRATEGY_KEY: &str = "frequency_strategy";
+
+impl FrequencyStrategy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FrequencyStrategy::Off => "off",
+            FrequencyStrategy::MinRank => "minRank",
+            FrequencyStrategy::Average => "average",
+            FrequencyStrategy::Priority => "priority",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(FrequencyStrategy::Off),
+            "minRank" => Some(FrequencyStrategy::MinRank),
+            "average" => Some(FrequencyStrategy::Average),
+            "priority" => Some(FrequencyStrategy::Priority),
+            _ => None,
+        }
+    }
+
+    /// Combines `(dictionary priority, rank)` pairs into a single sort rank.
+    pub fn aggregate(&self, ranks: &[(i64, i64)]) -> Option<i64> {
+        if ranks.is_empty() {
+            return None;
+        }
+        match self {
+            FrequencyStrategy::Off => None,
+            FrequencyStrategy::MinRank => ranks.iter().map(|(_, rank)| *rank).min(),
+            FrequencyStrategy::Average => {
+                let sum: i64 = ranks.iter().map(|(_, rank)| *rank).sum();
+                Some(sum / ranks.len() as i64)
+            }
+            FrequencyStrategy::Priority => ranks
+                .iter()
+                .min_by_key(|(priority, _)| *priority)
+                .map(|(_, rank)| *rank),
+        }
+    }
+}
+
+pub fn load_frequency_strategy(state: &AppState) -> FrequencyStrategy {
+    state
+        .pool
+        .get()
+        .ok()
+        .map(|conn| frequency_strategy_from_conn(&conn))
+        .unwrap_or_default()
+}
+
+pub fn store_frequency_strategy(
+    state: &AppState,
+    strategy: FrequencyStrategy,
+) -> anyhow::Result<()> {
+    let conn = state.pool.get()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO metadata (key, value) VALUES (?, ?)",
+        [FREQUENCY_STRATEGY_KEY, strategy.as_str()],
+    )?;
+    Ok(())
+}
+
+fn frequency_strategy_from_conn(conn: &rusqlite::Connection) -> FrequencyStrategy {
+    conn.query_row(
+        "SELECT value FROM metadata WHERE key = ?",
+        [FREQUENCY_STRATEGY_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| FrequencyStrategy::parse(&value))
+    .unwrap_or_default()
+}
+
+/// Extracts the display value of a frequency record ("Frequency: 1234 (reading)").
+fn frequency_value(record: &Record) -> Option<&str> {
+    let Record::YomitanGlossary(gloss) = record else {
+        return None;
+    };
+    match gloss.content.first() {
+        Some(structured::Content::String(s)) => s.strip_prefix("Frequency: ").map(str::trim),
+        _ => None,
+    }
+}
+
+/// Parses the leading number of a frequency display value, e.g. "1,234㋕" -> 1234.
+fn parse_frequency_rank(value: &str) -> Option<i64> {
+    let digits: String = value
+        .split_whitespace()
+        .next()?
+        .chars()
+        .filter(|c| *c != ',')
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+pub(crate) fn term_parts(term: &Term) -> (String, String) {
+    match term {
+        Term::Full(h, r) => (h.to_string(), r.to_string()),
+        Term::Headword(h) => (h.to_string(), String::new()),
+        Term::Reading(r) => (r.to_string(), String::new()),
+    }
+}
+
+/// Number of characters after the cursor considered by a lookup.
+pub const DEFAULT_LOOKUP_WINDOW: usize = 24;
+/// Upper bound for caller-supplied windows; every extra character multiplies candidates.
+pub const MAX_LOOKUP_WINDOW: usize = 64;
+
+fn span_len(span: &Span) -> u64 {
+    span.end.saturating_sub(span.start)
+}
+
 pub struct LookupService {
     deinflector: Deinflector,
 }
@@ -85,6 +229,38 @@ impl LookupService {
         cursor_offset: usize,
         language: DeinflectLanguage,
     ) -> Vec<(RecordEntry, Option<Vec<GlossaryTag>>)> {
+        self.search_window(state, text, cursor_offset, DEFAULT_LOOKUP_WINDOW, language)
+    }
+
+    /// Looks up terms starting at the byte `cursor_offset`, considering up to `window`
+    /// characters. Result spans are offsets into `text`, so callers passing a whole
+    /// paragraph can highlight the match directly.
+    pub fn search_window(
+        &self,
+        state: &AppState,
+        text: &str,
+        cursor_offset: usize,
+        window: usize,
+        language: DeinflectLanguage,
+    ) -> Vec<(RecordEntry, Option<Vec<GlossaryTag>>)> {
+        self.search_window_personalized(state, text, cursor_offset, window, language, None)
+            .0
+    }
+
+    /// Same as [`Self::search_window`], additionally reporting how personal lookup
+    /// weights affected the order. `personalization` overrides the stored setting.
+    pub fn search_window_personalized(
+        &self,
+        state: &AppState,
+        text: &str,
+        cursor_offset: usize,
+        window: usize,
+        language: DeinflectLanguage,
+        personalization: Option<Personalization>,
+    ) -> (
+        Vec<(RecordEntry, Option<Vec<GlossaryTag>>)>,
+        PersonalizationReport,
+    ) {
         let mut results = Vec::new();
         let mut processed_candidates = HashSet::new();
 
@@ -92,7 +268,7 @@ impl LookupService {
             Ok(c) => c,
             Err(e) => {
                 error!("❌ Failed to get DB connection: {}", e);
-                return vec![];
+                return (vec![], PersonalizationReport::default());
             }
         };
 
@@ -108,17 +284,25 @@ impl LookupService {
             Ok(s) => s,
             Err(e) => {
                 error!("❌ DB Prepare Error: {}", e);
-                return vec![];
+                return (vec![], PersonalizationReport::default());
             }
         };
 
         let start_index = self.snap_to_char_boundary(text, cursor_offset);
         if start_index >= text.len() {
-            return vec![];
+            return (vec![], PersonalizationReport::default());
         }
 
+        let strategy = frequency_strategy_from_conn(&conn);
+        // headword -> (reading, dictionary priority, rank)
+        let mut frequency_ranks: HashMap<String, Vec<(Option<String>, i64, i64)>> = HashMap::new();
+
         let search_text = &text[start_index..];
-        let chars: Vec<char> = search_text.chars().take(24).collect();
+        let chars: Vec<char> = search_text
+            .chars()
+            .take(window.clamp(1, MAX_LOOKUP_WINDOW))
+            .collect();
+        let char_start = text[..start_index].chars().count() as u64;
         let mut decoder = snap::raw::Decoder::new();
 
         let mut substrings = Vec::new();
@@ -169,6 +353,10 @@ impl LookupService {
                         {
                             stored.dictionary_id = dict_id;
                             let match_len = candidate.source_len;
+                            let match_bytes: usize = chars[..match_len.min(chars.len())]
+                                .iter()
+                                .map(|c| c.len_utf8())
+                                .sum();
 
                             let headword = stored
                                 .headword
@@ -187,15 +375,26 @@ impl LookupService {
                                 0
                             };
 
+                            if let Some(rank) =
+                                frequency_value(&stored.record).and_then(parse_frequency_rank)
+                            {
+                                let priority =
+                                    dict_configs.get(&dict_id).map(|(_, p)| *p).unwrap_or(999);
+                                frequency_ranks
+                                    .entry(headword.to_string())
+                                    .or_default()
+                                    .push((stored.reading.clone(), priority, rank));
+                            }
+
                             results.push((
                                 RecordEntry {
                                     span_bytes: Span {
-                                        start: 0,
-                                        end: candidate.word.len() as u64,
+                                        start: start_index as u64,
+                                        end: (start_index + match_bytes) as u64,
                                     },
                                     span_chars: Span {
-                                        start: 0,
-                                        end: match_len as u64,
+                                        start: char_start,
+                                        end: char_start + match_len as u64,
                                     },
                                     source: stored.dictionary_id,
                                     term: term_obj,
@@ -212,41 +411,188 @@ impl LookupService {
             }
         }
 
-        results.sort_by(|a, b| {
-            let len_cmp = b.0.span_chars.end.cmp(&a.0.span_chars.end);
-            if len_cmp != std::cmp::Ordering::Equal {
-                return len_cmp;
+        let term_rank = |entry: &RecordEntry| -> Option<i64> {
+            if strategy == FrequencyStrategy::Off {
+                return None;
             }
+            let (headword, reading) = term_parts(&entry.term);
+            let ranks: Vec<(i64, i64)> = frequency_ranks
+                .get(&headword)?
+                .iter()
+                .filter(|(freq_reading, _, _)| freq_reading.as_deref().is_none_or(|r| r == reading))
+                .map(|(_, priority, rank)| (*priority, *rank))
+                .collect();
+            strategy.aggregate(&ranks)
+        };
+        let ranks: Vec<Option<i64>> = results.iter().map(|result| term_rank(&result.0)).collect();
 
-            let prio_a = dict_configs
-                .get(&a.0.source)
-                .map(|(_, p)| *p)
-                .unwrap_or(999);
-            let prio_b = dict_configs
-                .get(&b.0.source)
-                .map(|(_, p)| *p)
-                .unwrap_or(999);
+        let mode = personalization.unwrap_or_else(|| personalization_from_conn(&conn));
+        let weights = if mode == Personalization::Off {
+            HashMap::new()
+        } else {
+            let terms: Vec<(String, String)> = results
+                .iter()
+                .map(|result| term_parts(&result.0.term))
+                .collect();
+            weights_for(
+                &conn,
+                terms
+                    .iter()
+                    .map(|(headword, reading)| (headword.as_str(), reading.as_str())),
+            )
+        };
+        let boosts: Vec<f64> = results
+            .iter()
+            .map(|result| {
+                let weight = weights
+                    .get(&term_parts(&result.0.term))
+                    .copied()
+                    .unwrap_or(0.0);
+                mode.boost(weight)
+            })
+            .collect();
+
+        let order = |personalized: bool| -> Vec<usize> {
+            let boost = |index: usize| if personalized { boosts[index] } else { 1.0 };
+            let mut indices: Vec<usize> = (0..results.len()).collect();
+            indices.sort_by(|&i, &j| {
+                let (a, b) = (&results[i], &results[j]);
+                let len_cmp = span_len(&b.0.span_chars).cmp(&span_len(&a.0.span_chars));
+                if len_cmp != std::cmp::Ordering::Equal {
+                    return len_cmp;
+                }
 
-            let prio_cmp = prio_a.cmp(&prio_b);
-            if prio_cmp != std::cmp::Ordering::Equal {
-                return prio_cmp;
-            }
+                // Lower rank means more common; terms without a rank sort last. Personal
+                // lookup weight shrinks a rank, and breaks ties between unranked terms.
+                let rank_cmp = match (ranks[i], ranks[j]) {
+                    (Some(x), Some(y)) => (x as f64 / boost(i)).total_cmp(&(y as f64 / boost(j))),
+                    (Some(_), None) => std::cmp::Ordering::Less,
+                    (None, Some(_)) => std::cmp::Ordering::Greater,
+                    (None, None) => boost(j).total_cmp(&boost(i)),
+                };
+                if rank_cmp != std::cmp::Ordering::Equal {
+                    return rank_cmp;
+                }
 
-            let get_val = |f: Option<&FrequencyValue>| -> i64 {
-                match f {
-                    Some(FrequencyValue::Rank(v)) => *v,
-                    Some(FrequencyValue::Occurrence(v)) => *v,
-                    None => 0,
+                let prio_a = dict_configs
+                    .get(&a.0.source)
+                    .map(|(_, p)| *p)
+                    .unwrap_or(999);
+                let prio_b = dict_configs
+                    .get(&b.0.source)
+                    .map(|(_, p)| *p)
+                    .unwrap_or(999);
+
+                let prio_cmp = prio_a.cmp(&prio_b);
+                if prio_cmp != std::cmp::Ordering::Equal {
+                    return prio_cmp;
                 }
+
+                let get_val = |f: Option<&FrequencyValue>| -> i64 {
+                    match f {
+                        Some(FrequencyValue::Rank(v)) => *v,
+                        Some(FrequencyValue::Occurrence(v)) => *v,
+                        None => 0,
+                    }
+                };
+                get_val(b.0.source_sorting_frequency.as_ref())
+                    .cmp(&get_val(a.0.source_sorting_frequency.as_ref()))
+            });
+            indices
+        };
+
+        let personalized_order = order(true);
+        let mut report = PersonalizationReport {
+            mode,
+            reordered: !weights.is_empty() && personalized_order != order(false),
+            boosted: weights
+                .into_iter()
+                .map(|((headword, reading), weight)| BoostedTerm {
+                    headword,
+                    reading,
+                    weight,
+                })
+                .collect(),
+        };
+        report.boosted.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+
+        let mut slots: Vec<Option<_>> = results.into_iter().map(Some).collect();
+        let results = personalized_order
+            .into_iter()
+            .filter_map(|index| slots[index].take())
+            .collect();
+        (results, report)
+    }
+
+    /// Lists every enabled frequency dictionary's entry for an exact term.
+    pub fn frequency_report(&self, state: &AppState, term: &str) -> Vec<TermFrequency> {
+        let conn = match state.pool.get() {
+            Ok(c) => c,
+            Err(e) => {
+                error!("❌ Failed to get DB connection: {}", e);
+                return vec![];
+            }
+        };
+
+        let dict_configs: HashMap<DictionaryId, (bool, String, i64)> = {
+            let dicts = state.dictionaries.read().expect("lock");
+            dicts
+                .iter()
+                .map(|(id, d)| (*id, (d.enabled, d.name.clone(), d.priority)))
+                .collect()
+        };
+
+        let mut stmt = match conn.prepare("SELECT dictionary_id, json FROM terms WHERE term = ?") {
+            Ok(s) => s,
+            Err(e) => {
+                error!("❌ DB Prepare Error: {}", e);
+                return vec![];
+            }
+        };
+        let Ok(rows) = stmt.query_map(rusqlite::params![term], |row| {
+            let dict_id: i64 = row.get(0)?;
+            let compressed: Vec<u8> = row.get(1)?;
+            Ok((dict_id, compressed))
+        }) else {
+            return vec![];
+        };
+
+        let mut decoder = snap::raw::Decoder::new();
+        let mut report = Vec::new();
+        for (dict_id_raw, compressed_data) in rows.flatten() {
+            let Some((enabled, name, priority)) = dict_configs.get(&DictionaryId(dict_id_raw))
+            else {
+                continue;
+            };
+            if !*enabled {
+                continue;
+            }
+            let Some(stored) = decoder
+                .decompress_vec(&compressed_data)
+                .ok()
+                .and_then(|decompressed| Self::decode_stored_record_payload(&decompressed))
+            else {
+                continue;
+            };
+            let Some(value) = frequency_value(&stored.record) else {
+                continue;
             };
-            get_val(b.0.source_sorting_frequency.as_ref())
-                .cmp(&get_val(a.0.source_sorting_frequency.as_ref()))
-        });
 
-        results
+            report.push(TermFrequency {
+                dictionary_id: dict_id_raw,
+                dictionary_name: name.clone(),
+                priority: *priority,
+                reading: stored.reading.clone(),
+                value: value.to_string(),
+                rank: parse_frequency_rank(value),
+            });
+        }
+
+        report.sort_by_key(|entry| (entry.priority, entry.dictionary_id));
+        report
     }
 
-    fn decode_stored_record_payload(payload: &[u8]) -> Option<StoredRecord> {
+    pub(crate) fn decode_stored_record_payload(payload: &[u8]) -> Option<StoredRecord> {
         if payload.starts_with(COMPACT_GLOSSARY_BIN_V1_PREFIX) {
             return Self::decode_compact_glossary_payload_binary(
                 &payload[COMPACT_GLOSSARY_BIN_V1_PREFIX.len()..],
@@ -825,3 +1171,260 @@ fn should_lowercase(language: DeinflectLanguage) -> bool {
             | DeinflectLanguage::Mongolian
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        io::Write,
+        path::PathBuf,
+        time::{SystemTime, UNIX_EPOCH},
+    };
+
+    use zip::{CompressionMethod, ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+    use crate::annotate::WordStatus;
+    use crate::import::import_zip;
+
+    fn test_data_dir(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("time")
+            .as_nanos();
+        std::env::temp_dir().join(format!(
+            "manatan-yomitan-lookup-test-{name}-{}-{nanos}",
+            std::process::id()
+        ))
+    }
+
+    fn build_zip(index_json: &str, entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        {
+            let cursor = std::io::Cursor::new(&mut bytes);
+            let mut zip = ZipWriter::new(cursor);
+            let opts = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+            zip.start_file("index.json", opts).expect("start index");
+            zip.write_all(index_json.as_bytes()).expect("write index");
+
+            for (name, contents) in entries {
+                zip.start_file(name, opts).expect("start file");
+                zip.write_all(contents.as_bytes()).expect("write file");
+            }
+
+            zip.finish().expect("finish zip");
+        }
+        bytes
+    }
+
+    fn with_state<T>(name: &str, f: impl FnOnce(&AppState) -> T) -> T {
+        let dir = test_data_dir(name);
+        let state = AppState::new(dir.clone());
+        let out = f(&state);
+        drop(state);
+        let _ = fs::remove_dir_all(dir);
+        out
+    }
+
+    /// Imports one definition dictionary and two frequency dictionaries that disagree on
+    /// which reading of 生 is more common. "Freq B" is given the higher priority.
+    fn import_conflicting_frequencies(state: &AppState) {
+        let definitions = build_zip(
+            r#"{"format":3,"title":"Defs","revision":"1"}"#,
+            &[(
+                "term_bank_1.json",
+                r#"[["生","せい","n",null,0,["life"],0,""],["生","なま","n",null,0,["raw"],0,""]]"#,
+            )],
+        );
+        let freq_a = build_zip(
+            r#"{"format":3,"title":"Freq A","revision":"1"}"#,
+            &[(
+                "term_meta_bank_1.json",
+                r#"[["生","freq",{"reading":"せい","frequency":100}],["生","freq",{"reading":"なま","frequency":5000}]]"#,
+            )],
+        );
+        let freq_b = build_zip(
+            r#"{"format":3,"title":"Freq B","revision":"1"}"#,
+            &[(
+                "term_meta_bank_1.json",
+                r#"[["生","freq",{"reading":"せい","frequency":9000}],["生","freq",{"reading":"なま","frequency":200}]]"#,
+            )],
+        );
+        for zip in [definitions, freq_a, freq_b] {
+            import_zip(state, &zip).expect("import should succeed");
+        }
+
+        let mut dicts = state.dictionaries.write().expect("lock");
+        for dict in dicts.values_mut() {
+            dict.priority = match dict.name.as_str() {
+                "Freq B" => 0,
+                "Freq A" => 1,
+                _ => 2,
+            };
+        }
+    }
+
+    fn top_reading(service: &LookupService, state: &AppState) -> String {
+        let results = service.search(state, "生", 0, DeinflectLanguage::Japanese);
+        let (entry, _) = results.first().expect("lookup should return results");
+        term_parts(&entry.term).1
+    }
+
+    #[test]
+    fn frequency_report_lists_each_dictionary() {
+        with_state("frequency-report", |state| {
+            import_conflicting_frequencies(state);
+
+            let report = LookupService::new().frequency_report(state, "生");
+            let mut ranks: Vec<(&str, Option<&str>, Option<i64>)> = report
+                .iter()
+                .map(|e| (e.dictionary_name.as_str(), e.reading.as_deref(), e.rank))
+                .collect();
+            ranks.sort();
+
+            assert_eq!(
+                ranks,
+                vec![
+                    ("Freq A", Some("せい"), Some(100)),
+                    ("Freq A", Some("なま"), Some(5000)),
+                    ("Freq B", Some("せい"), Some(9000)),
+                    ("Freq B", Some("なま"), Some(200)),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn frequency_strategy_changes_ordering_without_reimport() {
+        with_state("frequency-strategy", |state| {
+            import_conflicting_frequencies(state);
+            let service = LookupService::new();
+
+            store_frequency_strategy(state, FrequencyStrategy::MinRank).expect("store strategy");
+            assert_eq!(top_reading(&service, state), "せい");
+
+            store_frequency_strategy(state, FrequencyStrategy::Average).expect("store strategy");
+            assert_eq!(top_reading(&service, state), "なま");
+
+            store_frequency_strategy(state, FrequencyStrategy::Priority).expect("store strategy");
+            assert_eq!(top_reading(&service, state), "なま");
+            assert_eq!(load_frequency_strategy(state), FrequencyStrategy::Priority);
+        });
+    }
+
+    #[test]
+    fn personal_lookups_boost_ordering_by_mode() {
+        with_state("personalization", |state| {
+            import_conflicting_frequencies(state);
+            store_frequency_strategy(state, FrequencyStrategy::MinRank).expect("store strategy");
+            crate::personalization::record_lookup(state, "生", "なま").expect("record lookup");
+            let service = LookupService::new();
+
+            let search = |mode| {
+                let (results, report) = service.search_window_personalized(
+                    state,
+                    "生",
+                    0,
+                    DEFAULT_LOOKUP_WINDOW,
+                    DeinflectLanguage::Japanese,
+                    Some(mode),
+                );
+                let (entry, _) = results.first().expect("lookup should return results");
+                (term_parts(&entry.term).1, report.reordered)
+            };
+
+            assert_eq!(top_reading(&service, state), "せい");
+            assert_eq!(search(Personalization::Mild), ("せい".to_string(), false));
+            assert_eq!(search(Personalization::Strong), ("なま".to_string(), true));
+
+            crate::personalization::store_personalization(state, Personalization::Strong)
+                .expect("store personalization");
+            assert_eq!(top_reading(&service, state), "なま");
+        });
+    }
+
+    #[test]
+    fn lookup_weights_halve_every_half_life() {
+        let elapsed = crate::personalization::HALF_LIFE_SECS as i64;
+        let weight = crate::personalization::decayed(4.0, elapsed);
+        assert!((weight - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn search_spans_are_relative_to_the_paragraph() {
+        with_state("search-spans", |state| {
+            let zip = build_zip(
+                r#"{"format":3,"title":"Defs","revision":"1"}"#,
+                &[(
+                    "term_bank_1.json",
+                    r#"[["猫","ねこ","n",null,0,["cat"],0,""]]"#,
+                )],
+            );
+            import_zip(state, &zip).expect("import should succeed");
+
+            let paragraph = "白い猫がいる";
+            let cursor = "白い".len();
+            let results =
+                LookupService::new().search(state, paragraph, cursor, DeinflectLanguage::Japanese);
+            let (entry, _) = results.first().expect("lookup should return results");
+
+            assert_eq!((entry.span_chars.start, entry.span_chars.end), (2, 3));
+            assert_eq!(
+                &paragraph[entry.span_bytes.start as usize..entry.span_bytes.end as usize],
+                "猫"
+            );
+        });
+    }
+
+    #[test]
+    fn chapter_annotation_follows_word_status() {
+        with_state("annotate", |state| {
+            let zip = build_zip(
+                r#"{"format":3,"title":"Defs","revision":"1"}"#,
+                &[(
+                    "term_bank_1.json",
+                    r#"[["猫","ねこ","n",null,0,["cat"],0,""],["白い","しろい","adj",null,0,["white"],0,""]]"#,
+                )],
+            );
+            import_zip(state, &zip).expect("import should succeed");
+            let service = LookupService::new();
+            let annotate = |text: &str| {
+                crate::annotate::annotate_chapter(
+                    state,
+                    &service,
+                    text,
+                    DeinflectLanguage::Japanese,
+                )
+                .expect("annotate")
+            };
+
+            let (annotation, cached) = annotate("白い猫、ぬ");
+            assert!(!cached);
+            assert_eq!(
+                annotation.runs,
+                vec![(WordStatus::Unknown, 3), (WordStatus::NoEntry, 2)]
+            );
+            assert!(annotate("白い猫、ぬ").1);
+
+            crate::annotate::set_word_status(state, "猫", WordStatus::Known).expect("store status");
+            let (annotation, cached) = annotate("白い猫、ぬ");
+            assert!(!cached);
+            assert_eq!(
+                annotation.runs,
+                vec![
+                    (WordStatus::Unknown, 2),
+                    (WordStatus::Known, 1),
+                    (WordStatus::NoEntry, 2),
+                ]
+            );
+        });
+    }
+
+    #[test]
+    fn parses_frequency_display_values() {
+        assert_eq!(parse_frequency_rank("1234 (せい)"), Some(1234));
+        assert_eq!(parse_frequency_rank("1,234㋕"), Some(1234));
+        assert_eq!(parse_frequency_rank("unknown"), None);
+    }
+}
@@ -0,0 +1,208 @@
+//! Personal ranking boost for terms the user looks up often.
+//!
+//! Every recorded lookup bumps a per-(headword, reading) weight that halves every
+//! [`HALF_LIFE_SECS`], so old habits fade. Weights are updated in place rather than
+//! recomputed from a log, and the table is trimmed to [`MAX_WEIGHTED_TERMS`].
+
+use std::{
+    collections::HashMap,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+use crate::state::AppState;
+
+const PERSONALIZATION_KEY: &str = "personalization";
+/// A lookup counts half as much after this long.
+pub const HALF_LIFE_SECS: f64 = 30.0 * 24.0 * 60.0 * 60.0;
+pub const MAX_WEIGHTED_TERMS: usize = 5000;
+/// Trimming only starts once the table grows this far past the cap, so it runs rarely.
+const TRIM_SLACK: usize = MAX_WEIGHTED_TERMS / 10;
+
+/// How strongly personal lookup weights affect result ordering.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Personalization {
+    #[default]
+    Off,
+    Mild,
+    Strong,
+}
+
+impl Personalization {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Personalization::Off => "off",
+            Personalization::Mild => "mild",
+            Personalization::Strong => "strong",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(Personalization::Off),
+            "mild" => Some(Personalization::Mild),
+            "strong" => Some(Personalization::Strong),
+            _ => None,
+        }
+    }
+
+    /// Divisor applied to a term's frequency rank, and the tie-breaker for unranked
+    /// terms. Always `1.0` when personalization is off or the term has no weight.
+    pub fn boost(&self, weight: f64) -> f64 {
+        let exponent = match self {
+            Personalization::Off => return 1.0,
+            Personalization::Mild => 0.5,
+            Personalization::Strong => 1.5,
+        };
+        (1.0 + weight.max(0.0)).powf(exponent)
+    }
+}
+
+pub fn load_personalization(state: &AppState) -> Personalization {
+    state
+        .pool
+        .get()
+        .ok()
+        .map(|conn| personalization_from_conn(&conn))
+        .unwrap_or_default()
+}
+
+pub fn store_personalization(state: &AppState, mode: Personalization) -> anyhow::Result<()> {
+    let conn = state.pool.get()?;
+    conn.execute(
+        "INSERT OR REPLACE INTO metadata (key, value) VALUES (?, ?)",
+        [PERSONALIZATION_KEY, mode.as_str()],
+    )?;
+    Ok(())
+}
+
+pub(crate) fn personalization_from_conn(conn: &rusqlite::Connection) -> Personalization {
+    conn.query_row(
+        "SELECT value FROM metadata WHERE key = ?",
+        [PERSONALIZATION_KEY],
+        |row| row.get::<_, String>(0),
+    )
+    .ok()
+    .and_then(|value| Personalization::parse(&value))
+    .unwrap_or_default()
+}
+
+/// Weight left after `elapsed_secs` of exponential decay.
+pub fn decayed(weight: f64, elapsed_secs: i64) -> f64 {
+    weight * 0.5f64.powf(elapsed_secs.max(0) as f64 / HALF_LIFE_SECS)
+}
+
+fn now_secs() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+/// Records that the user looked up (headword, reading).
+pub fn record_lookup(state: &AppState, headword: &str, reading: &str) -> anyhow::Result<()> {
+    record_lookup_at(state, headword, reading, now_secs())
+}
+
+pub(crate) fn record_lookup_at(
+    state: &AppState,
+    headword: &str,
+    reading: &str,
+    now: i64,
+) -> anyhow::Result<()> {
+    let mut conn = state.pool.get()?;
+    let tx = conn.transaction()?;
+    let previous: Option<(f64, i64)> = tx
+        .query_row(
+            "SELECT weight, updated_at FROM lookup_weights WHERE headword = ? AND reading = ?",
+            [headword, reading],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let weight = previous.map_or(0.0, |(weight, updated_at)| {
+        decayed(weight, now - updated_at)
+    });
+    tx.execute(
+        "INSERT OR REPLACE INTO lookup_weights (headword, reading, weight, updated_at)
+         VALUES (?, ?, ?, ?)",
+        rusqlite::params![headword, reading, weight + 1.0, now],
+    )?;
+
+    let count: i64 = tx.query_row("SELECT COUNT(*) FROM lookup_weights", [], |row| row.get(0))?;
+    if count as usize > MAX_WEIGHTED_TERMS + TRIM_SLACK {
+        trim_weights(&tx, now)?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Drops the weakest weights until the table is back at [`MAX_WEIGHTED_TERMS`].
+fn trim_weights(conn: &rusqlite::Connection, now: i64) -> anyhow::Result<()> {
+    let mut rows: Vec<(String, String, f64)> = conn
+        .prepare("SELECT headword, reading, weight, updated_at FROM lookup_weights")?
+        .query_map([], |row| {
+            let weight: f64 = row.get(2)?;
+            let updated_at: i64 = row.get(3)?;
+            Ok((row.get(0)?, row.get(1)?, decayed(weight, now - updated_at)))
+        })?
+        .collect::<Result<_, _>>()?;
+    rows.sort_by(|a, b| a.2.total_cmp(&b.2));
+    let excess = rows.len().saturating_sub(MAX_WEIGHTED_TERMS);
+    let mut delete =
+        conn.prepare("DELETE FROM lookup_weights WHERE headword = ? AND reading = ?")?;
+    for (headword, reading, _) in rows.iter().take(excess) {
+        delete.execute([headword, reading])?;
+    }
+    Ok(())
+}
+
+/// Current (decayed) weights for the given terms; terms never looked up are absent.
+pub(crate) fn weights_for<'a>(
+    conn: &rusqlite::Connection,
+    terms: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> HashMap<(String, String), f64> {
+    let now = now_secs();
+    let Ok(mut stmt) = conn.prepare(
+        "SELECT weight, updated_at FROM lookup_weights WHERE headword = ? AND reading = ?",
+    ) else {
+        return HashMap::new();
+    };
+    let mut weights = HashMap::new();
+    for (headword, reading) in terms {
+        let key = (headword.to_string(), reading.to_string());
+        if weights.contains_key(&key) {
+            continue;
+        }
+        if let Ok(Some((weight, updated_at))) = stmt
+            .query_row([headword, reading], |row| {
+                Ok((row.get::<_, f64>(0)?, row.get::<_, i64>(1)?))
+            })
+            .optional()
+        {
+            weights.insert(key, decayed(weight, now - updated_at));
+        }
+    }
+    weights
+}
+
+/// How personalization affected one lookup.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersonalizationReport {
+    pub mode: Personalization,
+    /// Whether the order differs from what the lookup returns without personal weights.
+    pub reordered: bool,
+    /// Terms in the results with a personal weight, heaviest first.
+    pub boosted: Vec<BoostedTerm>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BoostedTerm {
+    pub headword: String,
+    pub reading: String,
+    pub weight: f64,
+}
@@ -8,6 +8,7 @@ use std::{
     time::{Duration, Instant},
 };
 
+use manatan_jobs::WorkerPool;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
 use serde::{Deserialize, Serialize};
@@ -32,9 +33,14 @@ pub struct AppState {
     pub pool: DbPool,
     pub data_dir: PathBuf,
     pub loading: Arc<AtomicBool>,
+    /// Dictionary imports run one at a time; a few more may wait.
+    pub imports: WorkerPool,
     startup_instant: Instant,
 }
 
+const IMPORT_WORKERS: usize = 1;
+const MAX_QUEUED_IMPORTS: usize = 4;
+
 #[cfg(test)]
 const IMPORT_STARTUP_GUARD: Duration = Duration::from_millis(50);
 #[cfg(not(test))]
@@ -131,6 +137,32 @@ impl AppState {
         )
         .ok();
 
+        // Personal lookup weights, decayed and bumped on every recorded lookup.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS lookup_weights (
+                headword TEXT NOT NULL,
+                reading TEXT NOT NULL,
+                weight REAL NOT NULL,
+                updated_at INTEGER NOT NULL,
+                PRIMARY KEY (headword, reading)
+            );",
+        )
+        .ok();
+
+        // Words the user marked as known or learning, and chapter annotations built from them.
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS word_status (
+                headword TEXT PRIMARY KEY,
+                status TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS annotation_cache (
+                cache_key TEXT PRIMARY KEY,
+                runs TEXT NOT NULL,
+                created_at INTEGER NOT NULL
+            );",
+        )
+        .ok();
+
         // 2. Load Dictionaries from DB
         let mut dicts = HashMap::new();
         let mut max_id = 0;
@@ -170,6 +202,7 @@ impl AppState {
             pool,
             data_dir,
             loading: Arc::new(AtomicBool::new(false)),
+            imports: WorkerPool::new("dictionary-import", IMPORT_WORKERS, MAX_QUEUED_IMPORTS),
             startup_instant: Instant::now(),
         }
     }